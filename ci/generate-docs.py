@@ -447,6 +447,7 @@ TOC = [
         "cli/general.md",
         children=[
             Gen("wezterm cli", "cli/cli"),
+            Page("wezterm color-scheme", "cli/color-scheme.md"),
             Page("wezterm connect", "cli/connect.md"),
             Page("wezterm imgcat", "cli/imgcat.md"),
             Page("wezterm ls-fonts", "cli/ls-fonts.md"),
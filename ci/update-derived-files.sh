@@ -18,7 +18,7 @@ done
 
 cargo run --example narrow $PWD/target/debug/wezterm --help | ./target/debug/strip-ansi-escapes > docs/examples/cmd-synopsis-wezterm--help.txt
 
-for cmd in start ssh serial connect ls-fonts show-keys imgcat set-working-directory record replay  ; do
+for cmd in start ssh serial connect ls-fonts show-keys imgcat set-working-directory record replay color-scheme ; do
   fname="docs/examples/cmd-synopsis-wezterm-${cmd}--help.txt"
   cargo run --example narrow $PWD/target/debug/wezterm $cmd --help | ./target/debug/strip-ansi-escapes > $fname
 done
@@ -32,12 +32,15 @@ for cmd in \
     list \
     list-clients \
     move-pane-to-new-tab \
+    move-tab-to-window \
+    pipe-pane \
     rename-workspace \
     send-text \
     set-tab-title \
     set-window-title \
     spawn \
     split-pane \
+    swap-pane \
     ; do
   fname="docs/examples/cmd-synopsis-wezterm-cli-${cmd}--help.txt"
   cargo run --example narrow $PWD/target/debug/wezterm cli $cmd --help | ./target/debug/strip-ansi-escapes > $fname
@@ -16,7 +16,7 @@ use config::keyassignment::{PaneDirection, ScrollbackEraseMode};
 use mux::client::{ClientId, ClientInfo};
 use mux::pane::PaneId;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::{PaneNode, SerdeUrl, SplitRequest, TabId};
+use mux::tab::{FloatingPaneNodes, FloatingPaneRect, PaneNode, SerdeUrl, SplitRequest, TabId};
 use mux::window::WindowId;
 use portable_pty::CommandBuilder;
 use rangeset::*;
@@ -431,7 +431,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 39;
+pub const CODEC_VERSION: usize = 46;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -489,6 +489,15 @@ pdu! {
     WindowTitleChanged: 57,
     RenameWorkspace: 58,
     EraseScrollbackRequest: 59,
+    SpawnFloatingPane: 60,
+    SetFloatingPaneState: 61,
+    SwapPanes: 62,
+    MoveTabToWindow: 63,
+    MoveTabToWindowResponse: 64,
+    PipePane: 65,
+    ExportLines: 66,
+    ExportLinesResponse: 67,
+    PanePipeStopped: 68,
 }
 
 impl Pdu {
@@ -565,6 +574,7 @@ impl Pdu {
             | Pdu::NotifyAlert(NotifyAlert { pane_id, .. })
             | Pdu::SetClipboard(SetClipboard { pane_id, .. })
             | Pdu::PaneFocused(PaneFocused { pane_id })
+            | Pdu::PanePipeStopped(PanePipeStopped { pane_id })
             | Pdu::PaneRemoved(PaneRemoved { pane_id }) => Some(*pane_id),
             _ => None,
         }
@@ -617,6 +627,8 @@ pub struct ListPanesResponse {
     pub tabs: Vec<PaneNode>,
     pub tab_titles: Vec<String>,
     pub window_titles: HashMap<WindowId, String>,
+    /// The floating panes of each of the tabs
+    pub floating_panes: Vec<FloatingPaneNodes>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -631,6 +643,24 @@ pub struct SplitPane {
     pub move_pane_id: Option<PaneId>,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SpawnFloatingPane {
+    /// The pane whose containing tab will hold the floating pane
+    pub pane_id: PaneId,
+    pub rect: Option<FloatingPaneRect>,
+    pub command: Option<CommandBuilder>,
+    pub command_dir: Option<String>,
+    pub domain: config::keyassignment::SpawnTabDomain,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetFloatingPaneState {
+    pub containing_tab_id: TabId,
+    pub pane_id: PaneId,
+    pub rect: FloatingPaneRect,
+    pub visible: bool,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct MovePaneToNewTab {
     pub pane_id: PaneId,
@@ -644,6 +674,38 @@ pub struct MovePaneToNewTabResponse {
     pub window_id: WindowId,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SwapPanes {
+    pub pane_id: PaneId,
+    pub other_pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MoveTabToWindow {
+    pub tab_id: TabId,
+    /// If None, move the tab into a new window
+    pub window_id: Option<WindowId>,
+    pub workspace_for_new_window: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MoveTabToWindowResponse {
+    pub window_id: WindowId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PipePane {
+    pub pane_id: PaneId,
+    /// If None, stop piping the output of the pane
+    pub spec: Option<config::keyassignment::PipePaneSpec>,
+}
+
+/// Sent by the server when the pipe of a pane failed and was stopped
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PanePipeStopped {
+    pub pane_id: PaneId,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SpawnV2 {
     pub domain: config::keyassignment::SpawnTabDomain,
@@ -884,6 +946,20 @@ pub struct GetLines {
     pub lines: Vec<Range<StableRowIndex>>,
 }
 
+/// Renders a range of lines of a pane, along with their colors and
+/// styles, using the palette of the pane
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ExportLines {
+    pub pane_id: PaneId,
+    pub lines: Range<StableRowIndex>,
+    pub format: config::keyassignment::ExportFormat,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ExportLinesResponse {
+    pub text: String,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 struct CellCoordinates {
     line_idx: usize,
@@ -490,6 +490,96 @@ impl SrgbaTuple {
             b / a
         }
     }
+
+    /// Returns the color in the OKLab perceptual color space.
+    /// <https://bottosson.github.io/posts/oklab/>
+    pub fn to_oklaba(self) -> (f64, f64, f64, f64) {
+        let LinearRgba(r, g, b, alpha) = self.to_linear();
+        let (r, g, b) = (r as f64, g as f64, b as f64);
+
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+        (
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+            alpha as f64,
+        )
+    }
+
+    /// Construct a color from OKLab components.  Colors that
+    /// fall outside of the sRGB gamut are clamped to it.
+    pub fn from_oklaba(l: f64, a: f64, b: f64, alpha: f64) -> Self {
+        let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+        fn to_srgb(v: f64) -> f32 {
+            let v = v.max(0.).min(1.);
+            let v = if v <= 0.0031308 {
+                v * 12.92
+            } else {
+                v.powf(1.0 / 2.4) * 1.055 - 0.055
+            };
+            v as f32
+        }
+
+        Self(
+            to_srgb(4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_),
+            to_srgb(-1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_),
+            to_srgb(-0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_),
+            alpha as f32,
+        )
+    }
+
+    /// Returns a version of this color whose contrast_ratio against
+    /// background is at least min_ratio.  The lightness of the color is
+    /// adjusted in the OKLab color space, preserving its hue, by the
+    /// smallest amount that achieves the ratio.  If the ratio cannot be
+    /// achieved then whichever of black or white has the most contrast
+    /// against background is returned.
+    pub fn ensure_contrast_ratio(&self, background: &Self, min_ratio: f64) -> Self {
+        if self.contrast_ratio(background) >= min_ratio {
+            return *self;
+        }
+
+        let (l, a, b, alpha) = self.to_oklaba();
+        let (bg_l, _, _, _) = background.to_oklaba();
+
+        // Search between the current lightness and target for the
+        // lightness closest to the current one that meets min_ratio
+        let search = |target: f64| -> Option<Self> {
+            let extreme = Self::from_oklaba(target, a, b, alpha);
+            if extreme.contrast_ratio(background) < min_ratio {
+                return None;
+            }
+            let mut fail = l;
+            let mut pass = target;
+            for _ in 0..16 {
+                let mid = (fail + pass) / 2.;
+                if Self::from_oklaba(mid, a, b, alpha).contrast_ratio(background) >= min_ratio {
+                    pass = mid;
+                } else {
+                    fail = mid;
+                }
+            }
+            Some(Self::from_oklaba(pass, a, b, alpha))
+        };
+
+        // Prefer moving away from the background lightness
+        let (first, second) = if l >= bg_l { (1., 0.) } else { (0., 1.) };
+        search(first).or_else(|| search(second)).unwrap_or_else(|| {
+            let white = Self(1., 1., 1., self.3);
+            let black = Self(0., 0., 0., self.3);
+            if white.contrast_ratio(background) >= black.contrast_ratio(background) {
+                white
+            } else {
+                black
+            }
+        })
+    }
 }
 
 /// Convert an RGB color space hue angle to an RYB colorspace hue angle
@@ -837,6 +927,43 @@ impl LinearRgba {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn oklab_round_trip() {
+        for color in ["#ff0000", "#336699", "#d6d6d6", "#000000", "#ffffff"] {
+            let c = SrgbaTuple::from_str(color).unwrap();
+            let (l, a, b, alpha) = c.to_oklaba();
+            let round_trip = SrgbaTuple::from_oklaba(l, a, b, alpha);
+            for (x, y) in [
+                (c.0, round_trip.0),
+                (c.1, round_trip.1),
+                (c.2, round_trip.2),
+                (c.3, round_trip.3),
+            ] {
+                assert!((x - y).abs() < 0.001, "{color}: {c:?} vs {round_trip:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ensure_contrast_ratio() {
+        let bg = SrgbaTuple::from_str("#1e1e1e").unwrap();
+
+        // Already has sufficient contrast
+        let fg = SrgbaTuple::from_str("#d6d6d6").unwrap();
+        assert_eq!(fg.ensure_contrast_ratio(&bg, 4.5), fg);
+
+        let fg = SrgbaTuple::from_str("#303040").unwrap();
+        let adjusted = fg.ensure_contrast_ratio(&bg, 4.5);
+        assert!(adjusted.contrast_ratio(&bg) >= 4.5);
+        assert!(adjusted.to_oklaba().0 > fg.to_oklaba().0);
+
+        let bg = SrgbaTuple::from_str("#f0f0f0").unwrap();
+        let fg = SrgbaTuple::from_str("#e0e000").unwrap();
+        let adjusted = fg.ensure_contrast_ratio(&bg, 3.0);
+        assert!(adjusted.contrast_ratio(&bg) >= 3.0);
+        assert!(adjusted.to_oklaba().0 < fg.to_oklaba().0);
+    }
     #[test]
     fn named_rgb() {
         let dark_green = SrgbaTuple::from_named("DarkGreen").unwrap();
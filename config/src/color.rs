@@ -160,6 +160,12 @@ pub struct Palette {
     pub visual_bell: Option<RgbaColor>,
     /// The color to use for the cursor when a dead key or leader state is active
     pub compose_cursor: Option<RgbaColor>,
+    /// The color of the outline drawn around panes that are receiving
+    /// broadcast input. If unspecified, ANSI red is used instead.
+    pub broadcast_indicator: Option<RgbaColor>,
+    /// The color of the marker drawn on panes whose output is being
+    /// piped elsewhere. If unspecified, ANSI red is used instead.
+    pub pipe_indicator: Option<RgbaColor>,
 
     pub copy_mode_active_highlight_fg: Option<ColorSpec>,
     pub copy_mode_active_highlight_bg: Option<ColorSpec>,
@@ -211,6 +217,8 @@ impl Palette {
             split: overlay!(split),
             visual_bell: overlay!(visual_bell),
             compose_cursor: overlay!(compose_cursor),
+            broadcast_indicator: overlay!(broadcast_indicator),
+            pipe_indicator: overlay!(pipe_indicator),
             copy_mode_active_highlight_fg: overlay!(copy_mode_active_highlight_fg),
             copy_mode_active_highlight_bg: overlay!(copy_mode_active_highlight_bg),
             copy_mode_inactive_highlight_fg: overlay!(copy_mode_inactive_highlight_fg),
@@ -742,9 +750,13 @@ impl ColorSchemeFile {
         Ok(dynamic_to_toml(value)?)
     }
 
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+    pub fn to_toml_string(&self) -> anyhow::Result<String> {
         let value = self.to_toml_value()?;
-        let text = toml::to_string_pretty(&value)?;
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let text = self.to_toml_string()?;
         std::fs::write(&path, text)
             .with_context(|| format!("writing toml to {}", path.as_ref().display()))
     }
@@ -17,6 +17,7 @@ use crate::keys::{Key, LeaderKey, Mouse};
 use crate::lua::make_lua_context;
 use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
+use crate::trigger::Trigger;
 use crate::units::Dimension;
 use crate::unix::UnixDomain;
 use crate::wsl::WslDomain;
@@ -153,6 +154,22 @@ pub struct Config {
     #[dynamic(default = "default_pane_select_bg_color")]
     pub pane_select_bg_color: RgbaColor,
 
+    #[dynamic(default = "default_badge_font_size")]
+    pub badge_font_size: f64,
+
+    #[dynamic(default = "default_badge_fg_color")]
+    pub badge_fg_color: RgbaColor,
+
+    /// Show a list of the key bindings that are available while the
+    /// leader key or a key table is active
+    #[dynamic(default)]
+    pub enable_which_key: bool,
+
+    /// How long the leader key or a key table must be active before
+    /// the list of key bindings is shown
+    #[dynamic(default = "default_which_key_delay_ms")]
+    pub which_key_delay_ms: u64,
+
     #[dynamic(default)]
     pub tab_bar_style: TabBarStyle,
 
@@ -171,6 +188,17 @@ pub struct Config {
     #[dynamic(default = "default_scrollback_lines")]
     pub scrollback_lines: usize,
 
+    /// How many of the most recent lines of scrollback are kept
+    /// uncompressed; older lines are compressed and are decompressed
+    /// on demand when they are accessed
+    #[dynamic(default = "default_scrollback_uncompressed_lines")]
+    pub scrollback_uncompressed_lines: usize,
+
+    /// When true, compressed scrollback is written to a temporary
+    /// file rather than being kept in memory
+    #[dynamic(default)]
+    pub scrollback_spill_to_disk: bool,
+
     /// If no `prog` is specified on the command line, use this
     /// instead of running the user's shell.
     /// For example, to have `wezterm` always run `top` by default,
@@ -196,6 +224,9 @@ pub struct Config {
     #[dynamic(default)]
     pub exit_behavior: ExitBehavior,
 
+    #[dynamic(default)]
+    pub exit_behavior_restart: ExitBehaviorRestart,
+
     #[dynamic(default = "default_clean_exits")]
     pub clean_exit_codes: Vec<u32>,
 
@@ -232,6 +263,16 @@ pub struct Config {
     #[dynamic(default = "default_hyperlink_rules")]
     pub hyperlink_rules: Vec<hyperlink::Rule>,
 
+    /// Rules that are evaluated against lines of output as they
+    /// are completed in local panes
+    #[dynamic(default)]
+    pub triggers: Vec<Trigger>,
+
+    /// Limits the number of trigger actions that are performed
+    /// per second, per pane
+    #[dynamic(default = "default_ratelimit_triggers_per_second")]
+    pub ratelimit_triggers_per_second: u32,
+
     /// What to set the TERM variable to
     #[dynamic(default = "default_term")]
     pub term: String,
@@ -558,6 +599,12 @@ pub struct Config {
     #[dynamic(default = "default_one_point_oh")]
     pub text_background_opacity: f32,
 
+    /// When greater than 1.0, the foreground color of text is adjusted
+    /// so that its contrast ratio against the background of the cell
+    /// is at least this value.
+    #[dynamic(default = "default_one_point_oh")]
+    pub minimum_contrast: f32,
+
     /// Specifies how often a blinking cursor transitions between visible
     /// and invisible, expressed in milliseconds.
     /// Setting this to 0 disables blinking.
@@ -713,6 +760,11 @@ pub struct Config {
     #[dynamic(default)]
     pub sort_fallback_fonts_by_coverage: bool,
 
+    /// Persist resolved font fallbacks and rasterized glyphs in
+    /// CACHE_DIR so that subsequent wezterm processes can reuse them
+    #[dynamic(default)]
+    pub enable_font_disk_cache: bool,
+
     #[dynamic(default)]
     pub search_font_dirs_for_fallback: bool,
 
@@ -751,6 +803,13 @@ pub struct Config {
     #[dynamic(default)]
     pub audible_bell: AudibleBell,
 
+    /// When set, a command delimited by OSC 133 semantic prompt
+    /// escapes that runs for at least this many milliseconds will
+    /// show a toast notification when it completes in a pane that
+    /// is not focused.
+    #[dynamic(default)]
+    pub command_finished_notification_threshold_ms: Option<u64>,
+
     #[dynamic(default)]
     pub canonicalize_pasted_newlines: Option<NewlineCanon>,
 
@@ -1172,6 +1231,7 @@ impl Config {
                 (key, mods),
                 KeyTableEntry {
                     action: k.action.clone(),
+                    description: k.description.clone(),
                 },
             );
         }
@@ -1188,6 +1248,7 @@ impl Config {
                     (key, mods),
                     KeyTableEntry {
                         action: k.action.clone(),
+                        description: k.description.clone(),
                     },
                 );
             }
@@ -1505,6 +1566,18 @@ fn default_pane_select_font_size() -> f64 {
     36.0
 }
 
+fn default_badge_fg_color() -> RgbaColor {
+    SrgbaTuple(1.0, 0., 0., 0.5).into()
+}
+
+fn default_badge_font_size() -> f64 {
+    24.0
+}
+
+fn default_which_key_delay_ms() -> u64 {
+    500
+}
+
 fn default_integrated_title_buttons() -> Vec<IntegratedTitleButton> {
     use IntegratedTitleButton::*;
     vec![Hide, Maximize, Close]
@@ -1542,6 +1615,10 @@ fn default_ratelimit_line_prefetches_per_second() -> u32 {
     20
 }
 
+fn default_ratelimit_triggers_per_second() -> u32 {
+    10
+}
+
 fn default_cursor_blink_rate() -> u64 {
     800
 }
@@ -1564,6 +1641,10 @@ fn default_scrollback_lines() -> usize {
     3500
 }
 
+fn default_scrollback_uncompressed_lines() -> usize {
+    10_000
+}
+
 fn default_initial_rows() -> u16 {
     24
 }
@@ -1610,6 +1691,14 @@ pub(crate) fn compute_runtime_dir() -> anyhow::Result<PathBuf> {
     Ok(crate::HOME_DIR.join(".local/share/wezterm"))
 }
 
+pub(crate) fn compute_cache_dir() -> anyhow::Result<PathBuf> {
+    if let Some(cache) = dirs_next::cache_dir() {
+        return Ok(cache.join("wezterm"));
+    }
+
+    Ok(crate::HOME_DIR.join(".cache/wezterm"))
+}
+
 pub fn pki_dir() -> anyhow::Result<PathBuf> {
     compute_runtime_dir().map(|d| d.join("pki"))
 }
@@ -1825,6 +1914,65 @@ pub enum ExitBehavior {
     CloseOnCleanExit,
     /// Hold the pane until it is explicitly closed
     Hold,
+    /// Spawn the program again in the same pane, as configured
+    /// by `exit_behavior_restart`
+    Restart,
+}
+
+/// Controls how `ExitBehavior::Restart` respawns the program
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq)]
+pub struct ExitBehaviorRestart {
+    /// How many consecutive restarts to attempt before holding the
+    /// pane open. None means that there is no limit.
+    #[dynamic(default = "default_restart_max_retries")]
+    pub max_retries: Option<u32>,
+    /// How long to wait before the first restart.
+    /// The delay doubles after each consecutive restart.
+    #[dynamic(default = "default_restart_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+    /// The upper bound for the delay between restarts.
+    /// A program that runs for longer than this is considered to
+    /// have started successfully, and resets the retry count.
+    #[dynamic(default = "default_restart_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Rather than restarting after a delay, wait for Enter
+    /// to be pressed in the pane
+    #[dynamic(default)]
+    pub prompt: bool,
+}
+
+impl Default for ExitBehaviorRestart {
+    fn default() -> Self {
+        Self {
+            max_retries: default_restart_max_retries(),
+            initial_delay_ms: default_restart_initial_delay_ms(),
+            max_delay_ms: default_restart_max_delay_ms(),
+            prompt: false,
+        }
+    }
+}
+
+impl ExitBehaviorRestart {
+    /// Returns the delay before the restart that follows
+    /// `attempt` consecutive prior restarts
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay_ms
+            .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX));
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+fn default_restart_max_retries() -> Option<u32> {
+    Some(5)
+}
+
+fn default_restart_initial_delay_ms() -> u64 {
+    1000
+}
+
+fn default_restart_max_delay_ms() -> u64 {
+    60_000
 }
 
 #[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq)]
@@ -1970,3 +2118,29 @@ pub(crate) fn validate_domain_name(name: &str) -> Result<(), String> {
 fn default_macos_forward_mods() -> Modifiers {
     Modifiers::SHIFT
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn restart_delay() {
+        let restart = ExitBehaviorRestart {
+            max_retries: None,
+            initial_delay_ms: 1000,
+            max_delay_ms: 10_000,
+            prompt: false,
+        };
+        let delays: Vec<u128> = (0..6)
+            .map(|attempt| restart.delay_for_attempt(attempt).as_millis())
+            .collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 8000, 10_000, 10_000]);
+
+        // Shifting past the width of the delay doesn't overflow
+        assert_eq!(restart.delay_for_attempt(64), Duration::from_millis(10_000));
+        assert_eq!(
+            restart.delay_for_attempt(u32::MAX),
+            Duration::from_millis(10_000)
+        );
+    }
+}
@@ -330,6 +330,66 @@ impl TryFrom<String> for FreeTypeLoadFlags {
     }
 }
 
+/// An explicit design coordinate for an axis of a variable font,
+/// written as `"tag=value"`; for example `"wght=450"` or `"CASL=0.5"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromDynamic, ToDynamic)]
+#[dynamic(try_from = "String", into = "String")]
+pub struct FontVariation {
+    /// The OpenType axis tag, such as `wght`, `wdth`, `slnt`
+    /// or a custom axis like `MONO`
+    pub tag: String,
+    pub value: NotNan<f64>,
+}
+
+impl FontVariation {
+    pub fn new(tag: &str, value: f64) -> Option<Self> {
+        Some(Self {
+            tag: tag.to_string(),
+            value: NotNan::new(value).ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for FontVariation {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}={}", self.tag, self.value)
+    }
+}
+
+impl From<FontVariation> for String {
+    fn from(val: FontVariation) -> Self {
+        val.to_string()
+    }
+}
+
+impl From<&FontVariation> for String {
+    fn from(val: &FontVariation) -> Self {
+        val.to_string()
+    }
+}
+
+impl TryFrom<String> for FontVariation {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, String> {
+        let (tag, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid font variation `{}`; expected `tag=value`", s))?;
+        let tag = tag.trim();
+        if tag.is_empty() || tag.len() > 4 || !tag.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(format!(
+                "invalid font variation axis tag `{}` in `{}`; \
+                 tags are 1-4 ASCII characters",
+                tag, s
+            ));
+        }
+        let value: f64 = value
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid font variation value in `{}`: {}", s, err))?;
+        Self::new(tag, value).ok_or_else(|| format!("invalid font variation value in `{}`", s))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, FromDynamic, ToDynamic)]
 pub struct FontAttributes {
     /// The font family name
@@ -357,6 +417,9 @@ pub struct FontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    /// Explicit axis coordinates to apply to a variable font
+    #[dynamic(default)]
+    pub variations: Option<Vec<FontVariation>>,
 }
 impl_lua_conversion_dynamic!(FontAttributes);
 
@@ -385,6 +448,7 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 
@@ -402,6 +466,25 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
+        }
+    }
+}
+
+impl FontAttributes {
+    /// Adjust any explicit `wght` axis coordinate by the same amount
+    /// that the weight changed from `prior`, so that bold and half-bright
+    /// text moves along the weight axis of a variable font.  Fonts without
+    /// an explicit coordinate are handled when the font is matched, and
+    /// only if they have a `wght` axis; italic is never interpolated.
+    fn shift_weight_axis(&mut self, prior: FontWeight) {
+        let delta = self.weight.to_opentype_weight() as f64 - prior.to_opentype_weight() as f64;
+        if let Some(variations) = self.variations.as_mut() {
+            for v in variations.iter_mut().filter(|v| v.tag == "wght") {
+                if let Ok(value) = NotNan::new((*v.value + delta).max(1.)) {
+                    v.value = value;
+                }
+            }
         }
     }
 }
@@ -421,6 +504,7 @@ impl Default for FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 }
@@ -518,7 +602,9 @@ impl TextStyle {
                 .iter()
                 .map(|attr| {
                     let mut attr = attr.clone();
+                    let prior = attr.weight;
                     attr.weight = attr.weight.bolder();
+                    attr.shift_weight_axis(prior);
                     attr.is_synthetic = true;
                     attr
                 })
@@ -534,7 +620,9 @@ impl TextStyle {
                 .iter()
                 .map(|attr| {
                     let mut attr = attr.clone();
+                    let prior = attr.weight;
                     attr.weight = attr.weight.lighter();
+                    attr.shift_weight_axis(prior);
                     attr.is_synthetic = true;
                     attr
                 })
@@ -640,7 +728,7 @@ impl Default for AllowSquareGlyphOverflow {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromDynamic, ToDynamic)]
 pub enum FontLocatorSelection {
     /// Use fontconfig APIs to resolve fonts (!macos, posix systems)
     FontConfig,
@@ -664,10 +752,12 @@ impl Default for FontLocatorSelection {
     }
 }
 
-#[derive(Debug, Clone, Copy, FromDynamic, ToDynamic, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromDynamic, ToDynamic, Default)]
 pub enum FontRasterizerSelection {
     #[default]
     FreeType,
+    /// The pure-Rust rasterizer from the swash crate
+    Swash,
 }
 
 #[derive(Debug, Clone, Copy, FromDynamic, ToDynamic, Default)]
@@ -697,4 +787,46 @@ mod test {
             assert_eq!(style.font[0].family, "Inconsolata");
         }
     }
+
+    #[test]
+    fn test_variation_parse() {
+        let v = FontVariation::try_from("wght=450".to_string()).unwrap();
+        assert_eq!(v, FontVariation::new("wght", 450.).unwrap());
+        assert_eq!(v.to_string(), "wght=450");
+
+        let v = FontVariation::try_from(" slnt = -8.5".to_string()).unwrap();
+        assert_eq!(v, FontVariation::new("slnt", -8.5).unwrap());
+
+        assert!(FontVariation::try_from("wght".to_string()).is_err());
+        assert!(FontVariation::try_from("weight=400".to_string()).is_err());
+        assert!(FontVariation::try_from("wght=heavy".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_bold_shifts_weight_axis() {
+        let mut attr = FontAttributes::new("Recursive");
+        attr.variations = Some(vec![
+            FontVariation::new("wght", 450.).unwrap(),
+            FontVariation::new("CASL", 1.).unwrap(),
+        ]);
+        let style = TextStyle {
+            font: vec![attr],
+            foreground: None,
+        };
+
+        let bold = style.make_bold();
+        assert_eq!(
+            bold.font[0].variations,
+            Some(vec![
+                FontVariation::new("wght", 650.).unwrap(),
+                FontVariation::new("CASL", 1.).unwrap(),
+            ])
+        );
+
+        let dim = style.make_half_bright();
+        assert_eq!(
+            dim.font[0].variations.as_ref().unwrap()[0],
+            FontVariation::new("wght", 250.).unwrap()
+        );
+    }
 }
@@ -12,10 +12,26 @@ use wezterm_input_types::{KeyCode, Modifiers};
 use wezterm_term::input::MouseButton;
 use wezterm_term::SemanticType;
 
-#[derive(Default, Debug, Clone, FromDynamic, ToDynamic, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, FromDynamic, ToDynamic, PartialEq)]
 pub struct LauncherActionArgs {
     pub flags: LauncherFlags,
     pub title: Option<String>,
+    /// Additional entries to show in the launcher
+    #[dynamic(default)]
+    pub entries: Vec<LauncherEntry>,
+}
+
+/// A custom launcher entry
+#[derive(Debug, Clone, FromDynamic, ToDynamic, PartialEq)]
+pub struct LauncherEntry {
+    pub label: String,
+    /// The action to perform when the entry is chosen.
+    /// Use `wezterm.action_callback` to run a lua function.
+    pub action: KeyAssignment,
+    /// Identifies the entry for the purposes of ranking it by how
+    /// frequently and recently it has been chosen.  Defaults to the label.
+    #[dynamic(default)]
+    pub id: Option<String>,
 }
 
 bitflags::bitflags! {
@@ -317,10 +333,56 @@ impl Default for ClipboardCopyDestination {
     }
 }
 
+/// The formats in which the text of a pane can be exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub enum ExportFormat {
+    /// Plain text, without colors or styles
+    Text,
+    /// Text with the escape sequences that color and style it
+    Ansi,
+    /// An HTML fragment with inline styles
+    Html,
+    /// A standalone SVG image
+    Svg,
+}
+impl_lua_conversion_dynamic!(ExportFormat);
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<ExportFormat> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "ansi" => Ok(Self::Ansi),
+            "html" => Ok(Self::Html),
+            "svg" => Ok(Self::Svg),
+            _ => anyhow::bail!(
+                "unknown export format {}; expected text, ansi, html or svg",
+                s
+            ),
+        }
+    }
+}
+
+/// Copies the selection, preserving its colors and styles
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct CopyAs {
+    pub format: ExportFormat,
+    #[dynamic(default)]
+    pub destination: ClipboardCopyDestination,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum ClipboardPasteSource {
     Clipboard,
     PrimarySelection,
+    /// A named register that was populated by yanking in copy mode
+    Register(char),
 }
 
 impl Default for ClipboardPasteSource {
@@ -333,6 +395,9 @@ impl Default for ClipboardPasteSource {
 pub enum PaneSelectMode {
     Activate,
     SwapWithActive,
+    /// Add the selected pane to the broadcast input set,
+    /// or remove it if it is already a member
+    ToggleBroadcast,
 }
 
 impl Default for PaneSelectMode {
@@ -341,6 +406,16 @@ impl Default for PaneSelectMode {
     }
 }
 
+/// Which panes receive input that is typed or pasted into
+/// one of them while broadcast input is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum BroadcastScope {
+    CurrentTab,
+    CurrentWorkspace,
+    /// The panes chosen with `PaneSelect{mode="ToggleBroadcast"}`
+    SelectedPanes,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub struct PaneSelectArguments {
     /// Overrides the main quick_select_alphabet config
@@ -427,6 +502,30 @@ impl Default for CharSelectArguments {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct GlobalSearchArguments {
+    /// The pattern to search for.  If it is empty, the overlay
+    /// prompts for the pattern.
+    #[dynamic(default)]
+    pub pattern: Pattern,
+    /// How many lines to show before and after each match
+    #[dynamic(default = "default_global_search_context_lines")]
+    pub context_lines: usize,
+}
+
+impl Default for GlobalSearchArguments {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::default(),
+            context_lines: default_global_search_context_lines(),
+        }
+    }
+}
+
+fn default_global_search_context_lines() -> usize {
+    1
+}
+
 #[derive(Default, Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct QuickSelectArguments {
     /// Overrides the main quick_select_alphabet config
@@ -451,6 +550,7 @@ pub enum KeyAssignment {
     SpawnWindow,
     ToggleFullScreen,
     CopyTo(ClipboardCopyDestination),
+    CopyAs(CopyAs),
     PasteFrom(ClipboardPasteSource),
     ActivateTabRelative(isize),
     ActivateTabRelativeNoWrap(isize),
@@ -476,6 +576,10 @@ pub enum KeyAssignment {
     ScrollByLine(isize),
     ScrollByCurrentEventWheelDelta,
     ScrollToPrompt(isize),
+    ScrollToMark(isize),
+    ScrollToFailedCommand(isize),
+    CopyLastCommandOutput(ClipboardCopyDestination),
+    RerunLastCommand,
     ScrollToTop,
     ScrollToBottom,
     ShowTabNavigator,
@@ -490,6 +594,7 @@ pub enum KeyAssignment {
     ShowLauncherArgs(LauncherActionArgs),
     ClearScrollback(ScrollbackEraseMode),
     Search(Pattern),
+    GlobalSearch(GlobalSearchArguments),
     ActivateCopyMode,
 
     SelectTextAtMouseCursor(SelectionMode),
@@ -550,9 +655,141 @@ pub enum KeyAssignment {
     ActivateWindow(usize),
     ActivateWindowRelative(isize),
     ActivateWindowRelativeNoWrap(isize),
+    PromptInputLine(PromptInputLine),
+    InputSelector(InputSelector),
+    SetTabTitle(String),
+    ToggleBroadcastInput(BroadcastScope),
+    SpawnFloatingPane(SpawnCommand),
+    ToggleFloatingPanes,
+    MovePaneToNewTab,
+    MovePaneToTab(MovePaneToTab),
+    SwapPaneWithTab(isize),
+    MoveTabToNewWindow,
+    MoveTabToWindow(usize),
+    SelectLayout(PaneLayout),
+    CycleLayouts,
+    PipePane(PipePaneSpec),
+    StopPipePane,
 }
 impl_lua_conversion_dynamic!(KeyAssignment);
 
+/// Prompts for a line of text, then performs `action` with it
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct PromptInputLine {
+    /// Explains what is being prompted for
+    #[dynamic(default)]
+    pub description: String,
+    /// If this is an `EmitEvent`, typically produced by
+    /// `wezterm.action_callback`, the handler is called with the
+    /// line, or nil if the prompt was cancelled.
+    /// Otherwise the line is used to fill in the action;
+    /// see `KeyAssignment::with_prompt_input`.
+    pub action: Box<KeyAssignment>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct InputSelectorEntry {
+    pub label: String,
+    #[dynamic(default)]
+    pub id: Option<String>,
+}
+
+/// Prompts to choose one of a list of items, then performs `action`
+/// with the choice
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct InputSelector {
+    #[dynamic(default)]
+    pub title: String,
+    pub choices: Vec<InputSelectorEntry>,
+    /// If this is an `EmitEvent`, the handler is called with the id and
+    /// label of the choice, or nils if the selector was cancelled.
+    /// Otherwise the id, or the label if there is no id, is used to
+    /// fill in the action in the same way as `PromptInputLine`.
+    pub action: Box<KeyAssignment>,
+}
+
+impl KeyAssignment {
+    /// Returns a copy of this action with the parameter that is
+    /// typically prompted for replaced by `input`.
+    /// Returns an error if the input isn't suitable for the action,
+    /// or if the action doesn't accept input.
+    pub fn with_prompt_input(&self, input: &str) -> anyhow::Result<KeyAssignment> {
+        /// The input is parsed into arguments which are appended
+        /// to any that are already present, so that eg: `ssh` can
+        /// be completed with the name of a host
+        fn with_args(cmd: &SpawnCommand, input: &str) -> anyhow::Result<SpawnCommand> {
+            let mut args = shlex::split(input)
+                .ok_or_else(|| anyhow::anyhow!("unable to parse {input:?} into arguments"))?;
+            if args.is_empty() {
+                anyhow::bail!("no command was entered");
+            }
+            if let Some(prefix) = &cmd.args {
+                args.splice(0..0, prefix.iter().cloned());
+            }
+            Ok(SpawnCommand {
+                args: Some(args),
+                ..cmd.clone()
+            })
+        }
+
+        Ok(match self {
+            Self::SetTabTitle(_) => Self::SetTabTitle(input.to_string()),
+            Self::SendString(_) => Self::SendString(input.to_string()),
+            Self::SwitchToWorkspace { name: None, spawn } => {
+                if input.is_empty() {
+                    anyhow::bail!("no workspace name was entered");
+                }
+                Self::SwitchToWorkspace {
+                    name: Some(input.to_string()),
+                    spawn: spawn.clone(),
+                }
+            }
+            Self::SpawnCommandInNewTab(cmd) => Self::SpawnCommandInNewTab(with_args(cmd, input)?),
+            Self::SpawnCommandInNewWindow(cmd) => {
+                Self::SpawnCommandInNewWindow(with_args(cmd, input)?)
+            }
+            Self::SpawnFloatingPane(cmd) => Self::SpawnFloatingPane(with_args(cmd, input)?),
+            Self::SplitPane(split) => Self::SplitPane(SplitPane {
+                command: with_args(&split.command, input)?,
+                ..split.clone()
+            }),
+            action => anyhow::bail!("{action:?} doesn't accept input from a prompt"),
+        })
+    }
+}
+
+/// Moves the active pane into a new split of the active pane
+/// of another tab in the same window
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct MovePaneToTab {
+    /// The index of the target tab; negative values
+    /// count back from the last tab
+    pub tab: isize,
+    pub direction: PaneDirection,
+    #[dynamic(default)]
+    pub size: SplitSize,
+    #[dynamic(default)]
+    pub top_level: bool,
+}
+
+/// Copies the output of a pane to a file or to the input
+/// of a command, in the manner of tmux's `pipe-pane`
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct PipePaneSpec {
+    /// Append the output to this file
+    #[dynamic(default)]
+    pub file: Option<String>,
+    /// Spawn this command and write the output to its stdin
+    #[dynamic(default)]
+    pub command: Option<Vec<String>>,
+    /// Remove escape sequences from the output, keeping only the text
+    #[dynamic(default)]
+    pub strip_escapes: bool,
+    /// Prefix each line of the output with the current time
+    #[dynamic(default)]
+    pub timestamps: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct SplitPane {
     pub direction: PaneDirection,
@@ -576,6 +813,48 @@ impl Default for SplitSize {
     }
 }
 
+/// A predefined arrangement of the panes in a tab,
+/// in the manner of tmux's `select-layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum PaneLayout {
+    /// Keeps the current arrangement, but gives each of the panes
+    /// in a row or column an equal share of its space
+    Rebalance,
+    /// All panes side by side, with equal widths
+    EvenHorizontal,
+    /// All panes stacked top to bottom, with equal heights
+    EvenVertical,
+    /// The first pane across the top, with the others
+    /// side by side beneath it
+    MainHorizontal,
+    /// The first pane on the left, with the others
+    /// stacked on the right
+    MainVertical,
+    /// The panes arranged in a grid of rows and columns
+    Tiled,
+}
+
+impl PaneLayout {
+    /// The layouts visited by `CycleLayouts`, in order
+    pub const CYCLE: &'static [PaneLayout] = &[
+        PaneLayout::EvenHorizontal,
+        PaneLayout::EvenVertical,
+        PaneLayout::MainHorizontal,
+        PaneLayout::MainVertical,
+        PaneLayout::Tiled,
+    ];
+
+    /// Returns the layout that follows this one in the cycle
+    pub fn next(self) -> Self {
+        let idx = Self::CYCLE
+            .iter()
+            .position(|&l| l == self)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        Self::CYCLE[idx % Self::CYCLE.len()]
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum RotationDirection {
     Clockwise,
@@ -620,10 +899,34 @@ pub enum CopyModeAssignment {
     MoveForwardSemanticZone,
     MoveBackwardZoneOfType(SemanticType),
     MoveForwardZoneOfType(SemanticType),
-    JumpForward { prev_char: bool },
-    JumpBackward { prev_char: bool },
+    MoveBackwardMark,
+    MoveForwardMark,
+    JumpForward {
+        prev_char: bool,
+    },
+    JumpBackward {
+        prev_char: bool,
+    },
     JumpAgain,
     JumpReverse,
+    /// Wait for the next key and select the corresponding text object
+    /// around the cursor, eg: `w` for a word or `"` for a quoted string.
+    /// `inner` excludes the surrounding whitespace or delimiters.
+    SelectTextObject {
+        inner: bool,
+    },
+    /// Wait for the next key and record the cursor position under
+    /// that name
+    SetNamedMark,
+    /// Wait for the next key and move to the position recorded under
+    /// that name.  If `exact_position` is false, move to the start of
+    /// the content on the marked line instead.
+    MoveToNamedMark {
+        exact_position: bool,
+    },
+    /// Wait for the next key and use the register with that name for
+    /// the next copy
+    SelectRegister,
 }
 
 pub type KeyTable = HashMap<(KeyCode, Modifiers), KeyTableEntry>;
@@ -637,4 +940,98 @@ pub struct KeyTables {
 #[derive(Debug, Clone, PartialEq)]
 pub struct KeyTableEntry {
     pub action: KeyAssignment,
+    /// Overrides the description that is derived from the action
+    pub description: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spawn(args: Option<&[&str]>) -> SpawnCommand {
+        SpawnCommand {
+            args: args.map(|args| args.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prompt_input_text() {
+        assert_eq!(
+            KeyAssignment::SetTabTitle(String::new())
+                .with_prompt_input("my tab")
+                .unwrap(),
+            KeyAssignment::SetTabTitle("my tab".to_string())
+        );
+        assert_eq!(
+            KeyAssignment::SendString(String::new())
+                .with_prompt_input("echo hello")
+                .unwrap(),
+            KeyAssignment::SendString("echo hello".to_string())
+        );
+    }
+
+    #[test]
+    fn prompt_input_workspace() {
+        let action = KeyAssignment::SwitchToWorkspace {
+            name: None,
+            spawn: None,
+        };
+        assert_eq!(
+            action.with_prompt_input("work").unwrap(),
+            KeyAssignment::SwitchToWorkspace {
+                name: Some("work".to_string()),
+                spawn: None,
+            }
+        );
+        assert!(action.with_prompt_input("").is_err());
+
+        // A workspace that is already named doesn't take input
+        assert!(KeyAssignment::SwitchToWorkspace {
+            name: Some("work".to_string()),
+            spawn: None,
+        }
+        .with_prompt_input("other")
+        .is_err());
+    }
+
+    #[test]
+    fn prompt_input_args() {
+        let action = KeyAssignment::SpawnCommandInNewTab(spawn(None));
+        assert_eq!(
+            action.with_prompt_input("vim 'my file'").unwrap(),
+            KeyAssignment::SpawnCommandInNewTab(spawn(Some(&["vim", "my file"])))
+        );
+        assert!(action.with_prompt_input("").is_err());
+        assert!(action.with_prompt_input("'unterminated").is_err());
+
+        // The input is appended to the existing args
+        let action = KeyAssignment::SplitPane(SplitPane {
+            direction: PaneDirection::Right,
+            size: SplitSize::default(),
+            command: spawn(Some(&["ssh"])),
+            top_level: false,
+        });
+        assert_eq!(
+            action.with_prompt_input("-p 2222 host").unwrap(),
+            KeyAssignment::SplitPane(SplitPane {
+                direction: PaneDirection::Right,
+                size: SplitSize::default(),
+                command: spawn(Some(&["ssh", "-p", "2222", "host"])),
+                top_level: false,
+            })
+        );
+        assert!(action.with_prompt_input(" ").is_err());
+    }
+
+    #[test]
+    fn prompt_input_unsupported() {
+        let err = KeyAssignment::ReloadConfiguration
+            .with_prompt_input("whatever")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ReloadConfiguration doesn't accept input from a prompt"
+        );
+    }
 }
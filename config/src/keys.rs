@@ -123,6 +123,9 @@ pub struct Key {
     #[dynamic(flatten)]
     pub key: KeyNoAction,
     pub action: KeyAssignment,
+    /// Describes the binding when listing the available keys
+    #[dynamic(default)]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Clone, FromDynamic, ToDynamic)]
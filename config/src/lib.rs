@@ -36,6 +36,7 @@ mod serial;
 mod ssh;
 mod terminal;
 mod tls;
+mod trigger;
 mod units;
 mod unix;
 mod version;
@@ -54,6 +55,7 @@ pub use serial::*;
 pub use ssh::*;
 pub use terminal::*;
 pub use tls::*;
+pub use trigger::*;
 pub use units::*;
 pub use unix::*;
 pub use version::*;
@@ -65,6 +67,7 @@ lazy_static! {
     pub static ref HOME_DIR: PathBuf = dirs_next::home_dir().expect("can't find HOME dir");
     pub static ref CONFIG_DIRS: Vec<PathBuf> = config_dirs();
     pub static ref RUNTIME_DIR: PathBuf = compute_runtime_dir().unwrap();
+    pub static ref CACHE_DIR: PathBuf = compute_cache_dir().unwrap();
     static ref CONFIG: Configuration = Configuration::new();
     static ref CONFIG_FILE_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
     static ref CONFIG_SKIP: AtomicBool = AtomicBool::new(false);
@@ -1,8 +1,8 @@
 use crate::exec_domain::{ExecDomain, ValueOrFunc};
 use crate::keyassignment::KeyAssignment;
 use crate::{
-    Config, FontAttributes, FontStretch, FontStyle, FontWeight, FreeTypeLoadTarget, RgbaColor,
-    TextStyle,
+    Config, FontAttributes, FontStretch, FontStyle, FontVariation, FontWeight, FreeTypeLoadTarget,
+    RgbaColor, TextStyle,
 };
 use anyhow::{anyhow, Context};
 use luahelper::{dynamic_to_lua_value, from_lua_value_dynamic, lua_value_to_dynamic, to_lua};
@@ -495,6 +495,8 @@ struct LuaFontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    #[dynamic(default)]
+    pub variations: Option<Vec<FontVariation>>,
 }
 impl<'lua> FromLua<'lua> for LuaFontAttributes {
     fn from_lua(value: Value<'lua>, _lua: &'lua Lua) -> Result<Self, mlua::Error> {
@@ -582,6 +584,7 @@ fn font<'lua>(
             },
             scale: attrs.scale,
             assume_emoji_presentation: attrs.assume_emoji_presentation,
+            variations: attrs.variations,
         }));
 
     Ok(text_style)
@@ -631,6 +634,7 @@ fn font_with_fallback<'lua>(
                 },
                 scale: attrs.scale,
                 assume_emoji_presentation: attrs.assume_emoji_presentation,
+                variations: attrs.variations,
             }));
     }
 
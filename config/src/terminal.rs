@@ -52,6 +52,14 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().scrollback_lines
     }
 
+    fn scrollback_uncompressed_lines(&self) -> usize {
+        self.configuration().scrollback_uncompressed_lines
+    }
+
+    fn scrollback_spill_to_disk(&self) -> bool {
+        self.configuration().scrollback_spill_to_disk
+    }
+
     fn enable_csi_u_key_encoding(&self) -> bool {
         self.configuration().enable_csi_u_key_encoding
     }
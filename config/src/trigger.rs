@@ -0,0 +1,93 @@
+use crate::color::RgbaColor;
+use termwiz::cell::CellAttributes;
+use termwiz::color::ColorSpec as TWColorSpec;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+/// A rule that is evaluated against each line of output as it
+/// is completed in a local pane
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct Trigger {
+    /// The regex to match against the text of the logical line
+    pub regex: String,
+    /// What to do for each match
+    pub action: TriggerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub enum TriggerAction {
+    /// Apply some styling to the matched text
+    Highlight(TriggerHighlight),
+    /// Emit the named lua event, passing the captures
+    EmitEvent(String),
+    /// Send the text to the pane as though it had been typed.
+    /// Captures can be referenced using `$1` or `${1}`.
+    SendString(String),
+    /// Show a toast notification.
+    /// Captures can be referenced using `$1` or `${1}`.
+    Notify(TriggerNotification),
+    /// Set a user var on the pane.
+    /// Captures can be referenced using `$1` or `${1}`.
+    SetUserVar(TriggerUserVar),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, FromDynamic, ToDynamic)]
+pub struct TriggerHighlight {
+    /// Which capture to highlight; 0 is the whole match
+    #[dynamic(default)]
+    pub capture: usize,
+    #[dynamic(default)]
+    pub foreground: Option<RgbaColor>,
+    #[dynamic(default)]
+    pub background: Option<RgbaColor>,
+    #[dynamic(default)]
+    pub intensity: Option<wezterm_term::Intensity>,
+    #[dynamic(default)]
+    pub underline: Option<wezterm_term::Underline>,
+    #[dynamic(default)]
+    pub italic: Option<bool>,
+    #[dynamic(default)]
+    pub strikethrough: Option<bool>,
+    #[dynamic(default)]
+    pub reverse: Option<bool>,
+}
+
+impl TriggerHighlight {
+    /// Apply the specified styling to attr, leaving the
+    /// unspecified attributes unchanged
+    pub fn apply(&self, attr: &mut CellAttributes) {
+        if let Some(color) = self.foreground {
+            attr.set_foreground(TWColorSpec::TrueColor(*color));
+        }
+        if let Some(color) = self.background {
+            attr.set_background(TWColorSpec::TrueColor(*color));
+        }
+        if let Some(intensity) = self.intensity {
+            attr.set_intensity(intensity);
+        }
+        if let Some(underline) = self.underline {
+            attr.set_underline(underline);
+        }
+        if let Some(italic) = self.italic {
+            attr.set_italic(italic);
+        }
+        if let Some(strikethrough) = self.strikethrough {
+            attr.set_strikethrough(strikethrough);
+        }
+        if let Some(reverse) = self.reverse {
+            attr.set_reverse(reverse);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct TriggerNotification {
+    #[dynamic(default)]
+    pub title: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct TriggerUserVar {
+    pub name: String,
+    pub value: String,
+}
@@ -0,0 +1,203 @@
+use super::{color_to_hex, parse_color};
+use config::{ColorSchemeFile, Palette, RgbaColor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The `colors` section of an alacritty configuration file,
+/// which may be either TOML or YAML.
+/// <https://github.com/alacritty/alacritty/blob/master/extra/man/alacritty.5.scd>
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Alacritty {
+    colors: Colors,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Colors {
+    primary: Primary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<CursorColors>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selection: Option<SelectionColors>,
+    normal: AnsiColors,
+    bright: AnsiColors,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    indexed_colors: Vec<IndexedColor>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Primary {
+    foreground: String,
+    background: String,
+}
+
+/// The colors in the cursor and selection sections may be
+/// `CellForeground` or `CellBackground` rather than an actual color;
+/// we ignore those values.
+#[derive(Deserialize, Serialize, Debug)]
+struct CursorColors {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct SelectionColors {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct AnsiColors {
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    magenta: String,
+    cyan: String,
+    white: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct IndexedColor {
+    index: u8,
+    color: String,
+}
+
+fn cell_color(c: Option<&String>) -> Option<RgbaColor> {
+    parse_color(c?).ok()
+}
+
+impl AnsiColors {
+    fn parse(&self) -> anyhow::Result<[RgbaColor; 8]> {
+        Ok([
+            parse_color(&self.black)?,
+            parse_color(&self.red)?,
+            parse_color(&self.green)?,
+            parse_color(&self.yellow)?,
+            parse_color(&self.blue)?,
+            parse_color(&self.magenta)?,
+            parse_color(&self.cyan)?,
+            parse_color(&self.white)?,
+        ])
+    }
+
+    fn from_colors(colors: &[RgbaColor]) -> Self {
+        Self {
+            black: color_to_hex(&colors[0]),
+            red: color_to_hex(&colors[1]),
+            green: color_to_hex(&colors[2]),
+            yellow: color_to_hex(&colors[3]),
+            blue: color_to_hex(&colors[4]),
+            magenta: color_to_hex(&colors[5]),
+            cyan: color_to_hex(&colors[6]),
+            white: color_to_hex(&colors[7]),
+        }
+    }
+}
+
+impl Alacritty {
+    pub fn parse_toml_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let scheme: Self = toml::from_str(data)?;
+        scheme.into_scheme()
+    }
+
+    pub fn parse_yaml_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let scheme: Self = serde_yaml::from_str(data)?;
+        scheme.into_scheme()
+    }
+
+    pub fn export_toml_str(scheme: &ColorSchemeFile) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(&Self::from_scheme(scheme))?)
+    }
+
+    pub fn export_yaml_str(scheme: &ColorSchemeFile) -> anyhow::Result<String> {
+        Ok(serde_yaml::to_string(&Self::from_scheme(scheme))?)
+    }
+
+    fn into_scheme(self) -> anyhow::Result<ColorSchemeFile> {
+        let colors = self.colors;
+
+        let mut indexed = HashMap::new();
+        for entry in &colors.indexed_colors {
+            indexed.insert(entry.index, parse_color(&entry.color)?);
+        }
+
+        let cursor_bg = cell_color(colors.cursor.as_ref().and_then(|c| c.cursor.as_ref()));
+
+        Ok(ColorSchemeFile {
+            colors: Palette {
+                foreground: Some(parse_color(&colors.primary.foreground)?),
+                background: Some(parse_color(&colors.primary.background)?),
+                cursor_fg: cell_color(colors.cursor.as_ref().and_then(|c| c.text.as_ref())),
+                cursor_bg,
+                cursor_border: cursor_bg,
+                selection_fg: cell_color(colors.selection.as_ref().and_then(|c| c.text.as_ref())),
+                selection_bg: cell_color(
+                    colors
+                        .selection
+                        .as_ref()
+                        .and_then(|c| c.background.as_ref()),
+                ),
+                ansi: Some(colors.normal.parse()?),
+                brights: Some(colors.bright.parse()?),
+                indexed,
+                ..Default::default()
+            },
+            metadata: Default::default(),
+        })
+    }
+
+    fn from_scheme(scheme: &ColorSchemeFile) -> Self {
+        let palette = &scheme.colors;
+        let numbered = super::numbered_from_palette(palette);
+        let numbered: Vec<RgbaColor> = numbered.values().copied().collect();
+        let hex = |c: &Option<RgbaColor>| c.as_ref().map(color_to_hex);
+
+        let cursor = if palette.cursor_fg.is_some() || palette.cursor_bg.is_some() {
+            Some(CursorColors {
+                text: hex(&palette.cursor_fg),
+                cursor: hex(&palette.cursor_bg),
+            })
+        } else {
+            None
+        };
+        let selection = if palette.selection_fg.is_some() || palette.selection_bg.is_some() {
+            Some(SelectionColors {
+                text: hex(&palette.selection_fg),
+                background: hex(&palette.selection_bg),
+            })
+        } else {
+            None
+        };
+
+        let mut indexed_colors: Vec<IndexedColor> = palette
+            .indexed
+            .iter()
+            .map(|(index, color)| IndexedColor {
+                index: *index,
+                color: color_to_hex(color),
+            })
+            .collect();
+        indexed_colors.sort_by_key(|entry| entry.index);
+
+        Self {
+            colors: Colors {
+                primary: Primary {
+                    foreground: hex(&palette.foreground)
+                        .unwrap_or_else(|| color_to_hex(&numbered[7])),
+                    background: hex(&palette.background)
+                        .unwrap_or_else(|| color_to_hex(&numbered[0])),
+                },
+                cursor,
+                selection,
+                normal: AnsiColors::from_colors(&numbered[0..8]),
+                bright: AnsiColors::from_colors(&numbered[8..16]),
+                indexed_colors,
+            },
+        }
+    }
+}
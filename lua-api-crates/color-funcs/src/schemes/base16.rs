@@ -33,8 +33,11 @@ impl Base16Scheme {
         P: std::fmt::Debug,
     {
         let data = std::fs::read_to_string(&path).context(format!("read file {path:?}"))?;
+        Self::parse_str(&data)
+    }
 
-        let scheme: Self = serde_yaml::from_str(&data)?;
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let scheme: Self = serde_yaml::from_str(data)?;
 
         let base_0 = RgbaColor::try_from(scheme.base00)?;
         let base_1 = RgbaColor::try_from(scheme.base01)?;
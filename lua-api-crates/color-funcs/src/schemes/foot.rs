@@ -0,0 +1,122 @@
+use super::{color_to_hex, parse_color};
+use config::{ColorSchemeFile, Palette, RgbaColor};
+use std::collections::HashMap;
+
+/// The `[colors]` and `[cursor]` sections of a foot.ini file.
+/// Colors are specified as `RRGGBB` without a leading `#`.
+/// <https://codeberg.org/dnkl/foot/src/branch/master/doc/foot.ini.5.scd>
+pub struct Foot;
+
+fn foot_color(color: &RgbaColor) -> String {
+    color_to_hex(color).trim_start_matches('#').to_string()
+}
+
+impl Foot {
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let mut numbered = HashMap::new();
+        let mut named = HashMap::new();
+        let mut cursor = None;
+        let mut section = String::new();
+
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            match (section.as_str(), key) {
+                // Both `[cursor] color` and the newer `[colors] cursor`
+                // have the form `TEXT CURSOR`
+                ("cursor", "color") | ("colors", "cursor") => {
+                    let mut fields = value.split_whitespace();
+                    if let (Some(text), Some(bg)) = (fields.next(), fields.next()) {
+                        cursor.replace((parse_color(text)?, parse_color(bg)?));
+                    }
+                }
+                ("colors", "foreground")
+                | ("colors", "background")
+                | ("colors", "selection-foreground")
+                | ("colors", "selection-background") => {
+                    named.insert(key.to_string(), parse_color(value)?);
+                }
+                ("colors", key) => {
+                    let idx = if let Some(idx) = key.strip_prefix("regular") {
+                        idx.parse::<u8>().ok().filter(|idx| *idx < 8)
+                    } else if let Some(idx) = key.strip_prefix("bright") {
+                        idx.parse::<u8>()
+                            .ok()
+                            .filter(|idx| *idx < 8)
+                            .map(|idx| idx + 8)
+                    } else {
+                        key.parse::<u8>().ok().filter(|idx| *idx >= 16)
+                    };
+                    if let Some(idx) = idx {
+                        numbered.insert(idx, parse_color(value)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ColorSchemeFile {
+            colors: Palette {
+                foreground: named.get("foreground").copied(),
+                background: named.get("background").copied(),
+                cursor_fg: cursor.map(|(text, _)| text),
+                cursor_bg: cursor.map(|(_, bg)| bg),
+                cursor_border: cursor.map(|(_, bg)| bg),
+                selection_fg: named.get("selection-foreground").copied(),
+                selection_bg: named.get("selection-background").copied(),
+                ..super::palette_from_numbered(numbered)
+            },
+            metadata: super::metadata_from_comments(data, &['#']),
+        })
+    }
+
+    pub fn export_str(scheme: &ColorSchemeFile) -> String {
+        let palette = &scheme.colors;
+        let mut result = super::metadata_to_comments(&scheme.metadata, "#");
+        if !result.is_empty() {
+            result.push('\n');
+        }
+
+        if let (Some(text), Some(bg)) = (&palette.cursor_fg, &palette.cursor_bg) {
+            result.push_str(&format!(
+                "[cursor]\ncolor={} {}\n\n",
+                foot_color(text),
+                foot_color(bg)
+            ));
+        }
+
+        result.push_str("[colors]\n");
+        for (key, color) in [
+            ("foreground", &palette.foreground),
+            ("background", &palette.background),
+            ("selection-foreground", &palette.selection_fg),
+            ("selection-background", &palette.selection_bg),
+        ] {
+            if let Some(color) = color {
+                result.push_str(&format!("{key}={}\n", foot_color(color)));
+            }
+        }
+
+        for (idx, color) in super::numbered_from_palette(palette) {
+            let color = foot_color(&color);
+            match idx {
+                0..=7 => result.push_str(&format!("regular{idx}={color}\n")),
+                8..=15 => result.push_str(&format!("bright{}={color}\n", idx - 8)),
+                _ => result.push_str(&format!("{idx}={color}\n")),
+            }
+        }
+
+        result
+    }
+}
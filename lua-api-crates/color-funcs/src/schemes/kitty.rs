@@ -0,0 +1,94 @@
+use super::{color_to_hex, parse_color};
+use config::{ColorSchemeFile, Palette};
+use std::collections::HashMap;
+
+/// A kitty theme; a `.conf` file containing `name value` lines.
+/// Metadata is conventionally recorded in `## name: value` comments.
+/// <https://sw.kovidgoyal.net/kitty/kittens/themes/>
+pub struct Kitty;
+
+impl Kitty {
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let mut numbered = HashMap::new();
+        let mut named = HashMap::new();
+
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(char::is_whitespace) {
+                Some((key, value)) => (key, value.trim()),
+                None => continue,
+            };
+
+            // Special values such as `none` or `background` are used
+            // to indicate that the color is derived from the cell
+            if value == "none" || value == "background" {
+                continue;
+            }
+
+            if let Some(idx) = key.strip_prefix("color") {
+                if let Ok(idx) = idx.parse::<u8>() {
+                    numbered.insert(idx, parse_color(value)?);
+                }
+                continue;
+            }
+
+            match key {
+                "foreground"
+                | "background"
+                | "cursor"
+                | "cursor_text_color"
+                | "selection_foreground"
+                | "selection_background" => {
+                    named.insert(key, parse_color(value)?);
+                }
+                _ => {}
+            }
+        }
+
+        let cursor = named.get("cursor").copied();
+
+        Ok(ColorSchemeFile {
+            colors: Palette {
+                foreground: named.get("foreground").copied(),
+                background: named.get("background").copied(),
+                cursor_fg: named.get("cursor_text_color").copied(),
+                cursor_bg: cursor,
+                cursor_border: cursor,
+                selection_fg: named.get("selection_foreground").copied(),
+                selection_bg: named.get("selection_background").copied(),
+                ..super::palette_from_numbered(numbered)
+            },
+            metadata: super::metadata_from_comments(data, &['#']),
+        })
+    }
+
+    pub fn export_str(scheme: &ColorSchemeFile) -> String {
+        let palette = &scheme.colors;
+        let mut result = super::metadata_to_comments(&scheme.metadata, "##");
+        if !result.is_empty() {
+            result.push('\n');
+        }
+
+        for (key, color) in [
+            ("foreground", &palette.foreground),
+            ("background", &palette.background),
+            ("cursor", &palette.cursor_bg),
+            ("cursor_text_color", &palette.cursor_fg),
+            ("selection_foreground", &palette.selection_fg),
+            ("selection_background", &palette.selection_bg),
+        ] {
+            if let Some(color) = color {
+                result.push_str(&format!("{key} {}\n", color_to_hex(color)));
+            }
+        }
+
+        for (idx, color) in super::numbered_from_palette(palette) {
+            result.push_str(&format!("color{idx} {}\n", color_to_hex(&color)));
+        }
+
+        result
+    }
+}
@@ -1,4 +1,483 @@
+use anyhow::Context;
+use config::{ColorSchemeFile, ColorSchemeMetaData, Palette, RgbaColor};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod alacritty;
 pub mod base16;
+pub mod foot;
 pub mod gogh;
 pub mod iterm2;
+pub mod kitty;
 pub mod sexy;
+pub mod windows_terminal;
+pub mod xresources;
+
+/// The color scheme file formats that we know how to read and/or write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeFormat {
+    /// wezterm's own TOML format
+    Wezterm,
+    Base16,
+    ITerm2,
+    TerminalSexy,
+    WindowsTerminal,
+    AlacrittyToml,
+    AlacrittyYaml,
+    Kitty,
+    Foot,
+    Xresources,
+    /// The JSON collection of themes published by the Gogh project,
+    /// which may hold many schemes in a single file
+    Gogh,
+}
+
+impl SchemeFormat {
+    pub const ALL: &'static [Self] = &[
+        Self::Wezterm,
+        Self::Base16,
+        Self::ITerm2,
+        Self::TerminalSexy,
+        Self::WindowsTerminal,
+        Self::AlacrittyToml,
+        Self::AlacrittyYaml,
+        Self::Kitty,
+        Self::Foot,
+        Self::Xresources,
+        Self::Gogh,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wezterm => "wezterm",
+            Self::Base16 => "base16",
+            Self::ITerm2 => "iterm2",
+            Self::TerminalSexy => "terminal-sexy",
+            Self::WindowsTerminal => "windows-terminal",
+            Self::AlacrittyToml => "alacritty",
+            Self::AlacrittyYaml => "alacritty-yaml",
+            Self::Kitty => "kitty",
+            Self::Foot => "foot",
+            Self::Xresources => "xresources",
+            Self::Gogh => "gogh",
+        }
+    }
+
+    /// Returns true if we can produce a file in this format
+    pub fn can_write(self) -> bool {
+        !matches!(
+            self,
+            Self::Base16 | Self::ITerm2 | Self::TerminalSexy | Self::Gogh
+        )
+    }
+
+    /// Guess the format of a file from its name and, where the
+    /// extension is shared between formats, its content.
+    pub fn detect(path: &Path, data: Option<&str>) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        if file_name.starts_with(".xresources")
+            || file_name.starts_with(".xdefaults")
+            || file_name == "xresources"
+        {
+            return Some(Self::Xresources);
+        }
+
+        match ext.as_deref()? {
+            "toml" => match data {
+                Some(data) if data.contains("[colors.primary]") => Some(Self::AlacrittyToml),
+                _ => Some(Self::Wezterm),
+            },
+            "yml" | "yaml" => match data {
+                Some(data) if data.contains("base00") => Some(Self::Base16),
+                _ => Some(Self::AlacrittyYaml),
+            },
+            "json" => match data {
+                Some(data) if data.contains("\"themes\"") => Some(Self::Gogh),
+                Some(data) if data.contains("\"color\"") => Some(Self::TerminalSexy),
+                _ => Some(Self::WindowsTerminal),
+            },
+            "itermcolors" => Some(Self::ITerm2),
+            "conf" => Some(Self::Kitty),
+            "ini" => Some(Self::Foot),
+            "xresources" | "xdefaults" | "ad" => Some(Self::Xresources),
+            _ => None,
+        }
+    }
+
+    /// Parse a file that holds a single color scheme.  For formats
+    /// that hold a collection of schemes, the collection must consist
+    /// of exactly one scheme; use parse_all_str to obtain all of them.
+    pub fn parse_str(self, data: &str) -> anyhow::Result<ColorSchemeFile> {
+        match self {
+            Self::Wezterm => ColorSchemeFile::from_toml_str(data),
+            Self::Base16 => base16::Base16Scheme::parse_str(data),
+            Self::ITerm2 => iterm2::ITerm2::parse_str(data),
+            Self::TerminalSexy => sexy::Sexy::parse_str(data),
+            Self::WindowsTerminal => windows_terminal::WindowsTerminal::parse_str(data),
+            Self::AlacrittyToml => alacritty::Alacritty::parse_toml_str(data),
+            Self::AlacrittyYaml => alacritty::Alacritty::parse_yaml_str(data),
+            Self::Kitty => kitty::Kitty::parse_str(data),
+            Self::Foot => foot::Foot::parse_str(data),
+            Self::Xresources => xresources::Xresources::parse_str(data),
+            Self::Gogh => {
+                let mut schemes = gogh::GoghTheme::load_all(data.as_bytes())?;
+                match schemes.len() {
+                    1 => Ok(schemes.remove(0)),
+                    n => anyhow::bail!("expected a single color scheme, but found {n}"),
+                }
+            }
+        }
+    }
+
+    /// Parse all of the color schemes held by a file
+    pub fn parse_all_str(self, data: &str) -> anyhow::Result<Vec<ColorSchemeFile>> {
+        match self {
+            Self::Gogh => gogh::GoghTheme::load_all(data.as_bytes()),
+            _ => Ok(vec![self.parse_str(data)?]),
+        }
+    }
+
+    pub fn export_str(self, scheme: &ColorSchemeFile) -> anyhow::Result<String> {
+        match self {
+            Self::Wezterm => scheme.to_toml_string(),
+            Self::WindowsTerminal => windows_terminal::WindowsTerminal::export_str(scheme),
+            Self::AlacrittyToml => alacritty::Alacritty::export_toml_str(scheme),
+            Self::AlacrittyYaml => alacritty::Alacritty::export_yaml_str(scheme),
+            Self::Kitty => Ok(kitty::Kitty::export_str(scheme)),
+            Self::Foot => Ok(foot::Foot::export_str(scheme)),
+            Self::Xresources => Ok(xresources::Xresources::export_str(scheme)),
+            Self::Base16 | Self::ITerm2 | Self::TerminalSexy | Self::Gogh => {
+                anyhow::bail!("writing {} color schemes is not supported", self.name())
+            }
+        }
+    }
+
+    /// Load a color scheme from path.  If format is None then it is
+    /// detected from the file.  If the file doesn't specify a name
+    /// for the scheme, the name of the file is used instead.
+    pub fn load_file<P: AsRef<Path>>(
+        path: P,
+        format: Option<Self>,
+    ) -> anyhow::Result<ColorSchemeFile> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("read file {}", path.display()))?;
+
+        let format = match format {
+            Some(format) => format,
+            None => Self::detect(path, Some(&data)).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unable to determine the color scheme format of {}",
+                    path.display()
+                )
+            })?,
+        };
+
+        let mut scheme = format
+            .parse_str(&data)
+            .with_context(|| format!("parsing {} as {}", path.display(), format.name()))?;
+        if scheme.metadata.name.is_none() {
+            scheme.metadata.name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.to_string());
+        }
+        Ok(scheme)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(
+        self,
+        scheme: &ColorSchemeFile,
+        path: P,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let data = self.export_str(scheme)?;
+        std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+impl FromStr for SchemeFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        for format in Self::ALL {
+            if format.name().eq_ignore_ascii_case(s) {
+                return Ok(*format);
+            }
+        }
+        let names: Vec<&str> = Self::ALL.iter().map(|f| f.name()).collect();
+        anyhow::bail!(
+            "unknown color scheme format {s}; possible values are: {}",
+            names.join(", ")
+        )
+    }
+}
+
+impl std::fmt::Display for SchemeFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parse a color as it appears in the various scheme formats.
+/// In addition to the usual forms, accepts the `0xRRGGBB` form
+/// used by alacritty and the bare `RRGGBB` form used by foot.
+pub(crate) fn parse_color(s: &str) -> anyhow::Result<RgbaColor> {
+    let s = s.trim().trim_matches(|c: char| c == '\'' || c == '"');
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return RgbaColor::try_from(format!("#{hex}"));
+    }
+    if s.len() == 6 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return RgbaColor::try_from(format!("#{s}"));
+    }
+    RgbaColor::try_from(s.to_string())
+}
+
+/// Returns the color in `#RRGGBB` form
+pub(crate) fn color_to_hex(color: &RgbaColor) -> String {
+    color.to_rgb_string()
+}
+
+/// Build a palette from a set of numbered colors.  Entries 0-15 form
+/// the ansi and brights colors; entries that are missing from that range
+/// are filled in from the default palette.  The remaining entries are
+/// placed in the indexed colors.
+pub(crate) fn palette_from_numbered(numbered: HashMap<u8, RgbaColor>) -> Palette {
+    let defaults: Palette = wezterm_term::color::ColorPalette::default().into();
+    let mut ansi = defaults.ansi.unwrap_or_default();
+    let mut brights = defaults.brights.unwrap_or_default();
+    let mut indexed = HashMap::new();
+
+    for (idx, color) in numbered {
+        match idx {
+            0..=7 => ansi[idx as usize] = color,
+            8..=15 => brights[idx as usize - 8] = color,
+            _ => {
+                indexed.insert(idx, color);
+            }
+        }
+    }
+
+    Palette {
+        ansi: Some(ansi),
+        brights: Some(brights),
+        indexed,
+        ..Default::default()
+    }
+}
+
+/// The inverse of palette_from_numbered: returns the ansi, brights and
+/// indexed colors of the palette, ordered by their color number.
+/// The defaults are used for any of the first 16 colors that are not
+/// set in the palette.
+pub(crate) fn numbered_from_palette(palette: &Palette) -> BTreeMap<u8, RgbaColor> {
+    let defaults: Palette = wezterm_term::color::ColorPalette::default().into();
+    let ansi = palette.ansi.or(defaults.ansi).unwrap_or_default();
+    let brights = palette.brights.or(defaults.brights).unwrap_or_default();
+
+    let mut numbered = BTreeMap::new();
+    for (idx, color) in ansi.iter().chain(brights.iter()).enumerate() {
+        numbered.insert(idx as u8, *color);
+    }
+    for (idx, color) in &palette.indexed {
+        numbered.insert(*idx, *color);
+    }
+    numbered
+}
+
+/// Extract the name, author and url of a scheme from `key: value`
+/// pairs that appear in comment lines starting with one of the
+/// characters in comment_chars.
+pub(crate) fn metadata_from_comments(s: &str, comment_chars: &[char]) -> ColorSchemeMetaData {
+    let mut metadata = ColorSchemeMetaData::default();
+    for line in s.lines() {
+        let line = line.trim();
+        if !line.starts_with(comment_chars) {
+            continue;
+        }
+        let line = line.trim_start_matches(comment_chars);
+        if let Some((k, v)) = line.split_once(':') {
+            let k = k.trim().to_ascii_lowercase();
+            let v = v.trim().to_string();
+            if v.is_empty() {
+                continue;
+            }
+            match k.as_str() {
+                "name" | "scheme" => {
+                    metadata.name.get_or_insert(v);
+                }
+                "author" => {
+                    metadata.author.get_or_insert(v);
+                }
+                "url" | "upstream" => {
+                    metadata.origin_url.get_or_insert(v);
+                }
+                _ => {}
+            }
+        }
+    }
+    metadata
+}
+
+/// Produces comment lines that metadata_from_comments can parse
+pub(crate) fn metadata_to_comments(metadata: &ColorSchemeMetaData, comment: &str) -> String {
+    let mut result = String::new();
+    for (k, v) in [
+        ("name", &metadata.name),
+        ("author", &metadata.author),
+        ("url", &metadata.origin_url),
+    ] {
+        if let Some(v) = v {
+            result.push_str(&format!("{comment} {k}: {v}\n"));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_scheme() -> ColorSchemeFile {
+        let mut numbered = HashMap::new();
+        for idx in 0..16u8 {
+            numbered.insert(idx, RgbaColor::from((idx * 16, 0x20, 0xff - idx * 8)));
+        }
+        numbered.insert(52, RgbaColor::from((0xfb, 0xda, 0xda)));
+        let palette = palette_from_numbered(numbered);
+
+        ColorSchemeFile {
+            colors: Palette {
+                foreground: Some(RgbaColor::from((0xd0, 0xd0, 0xd0))),
+                background: Some(RgbaColor::from((0x10, 0x10, 0x18))),
+                cursor_fg: Some(RgbaColor::from((0x10, 0x10, 0x18))),
+                cursor_bg: Some(RgbaColor::from((0xff, 0xcc, 0x00))),
+                cursor_border: Some(RgbaColor::from((0xff, 0xcc, 0x00))),
+                selection_fg: Some(RgbaColor::from((0x00, 0x00, 0x00))),
+                selection_bg: Some(RgbaColor::from((0xaa, 0xbb, 0xcc))),
+                ..palette
+            },
+            metadata: ColorSchemeMetaData {
+                name: Some("Sample".to_string()),
+                author: Some("Someone".to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let scheme = sample_scheme();
+        for format in SchemeFormat::ALL {
+            if !format.can_write() {
+                continue;
+            }
+            let data = format.export_str(&scheme).unwrap();
+            let parsed = format
+                .parse_str(&data)
+                .map_err(|err| anyhow::anyhow!("{format}: {err:#}\n{data}"))
+                .unwrap();
+
+            let colors = &parsed.colors;
+            assert_eq!(colors.foreground, scheme.colors.foreground, "{format}");
+            assert_eq!(colors.background, scheme.colors.background, "{format}");
+            assert_eq!(colors.cursor_bg, scheme.colors.cursor_bg, "{format}");
+            assert_eq!(colors.ansi, scheme.colors.ansi, "{format}");
+            assert_eq!(colors.brights, scheme.colors.brights, "{format}");
+            // Windows Terminal has no way to represent the indexed colors
+            if *format != SchemeFormat::WindowsTerminal {
+                assert_eq!(colors.indexed, scheme.colors.indexed, "{format}");
+            }
+            // alacritty has no way to represent the name of the scheme
+            if !matches!(
+                format,
+                SchemeFormat::AlacrittyToml | SchemeFormat::AlacrittyYaml
+            ) {
+                assert_eq!(parsed.metadata.name, scheme.metadata.name, "{format}");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_colors() {
+        let expected = RgbaColor::from((0x1d, 0x1f, 0x21));
+        assert_eq!(parse_color("#1d1f21").unwrap(), expected);
+        assert_eq!(parse_color("0x1d1f21").unwrap(), expected);
+        assert_eq!(parse_color("1d1f21").unwrap(), expected);
+        assert_eq!(parse_color("'0x1D1F21'").unwrap(), expected);
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            SchemeFormat::detect(Path::new("foo.toml"), Some("[colors]")),
+            Some(SchemeFormat::Wezterm)
+        );
+        assert_eq!(
+            SchemeFormat::detect(Path::new("foo.toml"), Some("[colors.primary]")),
+            Some(SchemeFormat::AlacrittyToml)
+        );
+        assert_eq!(
+            SchemeFormat::detect(Path::new("foo.yaml"), Some("base00: \"000000\"")),
+            Some(SchemeFormat::Base16)
+        );
+        assert_eq!(
+            SchemeFormat::detect(Path::new("/home/me/.Xresources"), None),
+            Some(SchemeFormat::Xresources)
+        );
+        assert_eq!(
+            SchemeFormat::detect(Path::new("theme.conf"), None),
+            Some(SchemeFormat::Kitty)
+        );
+        assert_eq!(SchemeFormat::detect(Path::new("README"), None), None);
+    }
+
+    fn gogh_theme(name: &str) -> String {
+        let mut fields = vec![format!("\"name\": \"{name}\"")];
+        for idx in 1..=16 {
+            fields.push(format!("\"color_{idx:02}\": \"#{:02x}0000\"", idx * 8));
+        }
+        for key in ["foreground", "background", "cursor"] {
+            fields.push(format!("\"{key}\": \"#ffffff\""));
+        }
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    #[test]
+    fn gogh() {
+        let one = format!("{{\"themes\": [{}]}}", gogh_theme("One"));
+        let two = format!(
+            "{{\"themes\": [{}, {}]}}",
+            gogh_theme("One"),
+            gogh_theme("Two")
+        );
+
+        assert_eq!(
+            SchemeFormat::detect(Path::new("themes.json"), Some(&two)),
+            Some(SchemeFormat::Gogh)
+        );
+
+        let scheme = SchemeFormat::Gogh.parse_str(&one).unwrap();
+        assert_eq!(scheme.metadata.name.as_deref(), Some("One"));
+        assert_eq!(
+            scheme.colors.brights.unwrap()[7],
+            RgbaColor::from((0x80, 0, 0))
+        );
+
+        assert!(SchemeFormat::Gogh.parse_str(&two).is_err());
+        let names: Vec<_> = SchemeFormat::Gogh
+            .parse_all_str(&two)
+            .unwrap()
+            .into_iter()
+            .map(|scheme| scheme.metadata.name.unwrap())
+            .collect();
+        assert_eq!(names, vec!["One", "Two"]);
+
+        assert!(!SchemeFormat::Gogh.can_write());
+    }
+}
@@ -17,8 +17,12 @@ impl Sexy {
     where
         P: std::fmt::Debug,
     {
-        let data = std::fs::read(&path).context(format!("read file {path:?}"))?;
-        let sexy: Self = serde_json::from_slice(&data)?;
+        let data = std::fs::read_to_string(&path).context(format!("read file {path:?}"))?;
+        Self::parse_str(&data)
+    }
+
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let sexy: Self = serde_json::from_str(data)?;
 
         Ok(ColorSchemeFile {
             colors: Palette {
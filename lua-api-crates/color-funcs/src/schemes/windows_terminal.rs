@@ -0,0 +1,119 @@
+use super::{color_to_hex, parse_color};
+use config::{ColorSchemeFile, ColorSchemeMetaData, Palette, RgbaColor};
+use serde::{Deserialize, Serialize};
+
+/// A scheme from the `schemes` section of the Windows Terminal
+/// settings.json file.
+/// <https://learn.microsoft.com/en-us/windows/terminal/customize-settings/color-schemes>
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowsTerminal {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    foreground: String,
+    background: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selection_background: Option<String>,
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    purple: String,
+    cyan: String,
+    white: String,
+    bright_black: String,
+    bright_red: String,
+    bright_green: String,
+    bright_yellow: String,
+    bright_blue: String,
+    bright_purple: String,
+    bright_cyan: String,
+    bright_white: String,
+}
+
+impl WindowsTerminal {
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let scheme: Self = serde_json::from_str(data)?;
+
+        let cursor = scheme
+            .cursor_color
+            .as_deref()
+            .map(parse_color)
+            .transpose()?;
+        let selection_bg = scheme
+            .selection_background
+            .as_deref()
+            .map(parse_color)
+            .transpose()?;
+
+        Ok(ColorSchemeFile {
+            colors: Palette {
+                foreground: Some(parse_color(&scheme.foreground)?),
+                background: Some(parse_color(&scheme.background)?),
+                cursor_bg: cursor,
+                cursor_border: cursor,
+                selection_bg,
+                ansi: Some([
+                    parse_color(&scheme.black)?,
+                    parse_color(&scheme.red)?,
+                    parse_color(&scheme.green)?,
+                    parse_color(&scheme.yellow)?,
+                    parse_color(&scheme.blue)?,
+                    parse_color(&scheme.purple)?,
+                    parse_color(&scheme.cyan)?,
+                    parse_color(&scheme.white)?,
+                ]),
+                brights: Some([
+                    parse_color(&scheme.bright_black)?,
+                    parse_color(&scheme.bright_red)?,
+                    parse_color(&scheme.bright_green)?,
+                    parse_color(&scheme.bright_yellow)?,
+                    parse_color(&scheme.bright_blue)?,
+                    parse_color(&scheme.bright_purple)?,
+                    parse_color(&scheme.bright_cyan)?,
+                    parse_color(&scheme.bright_white)?,
+                ]),
+                ..Default::default()
+            },
+            metadata: ColorSchemeMetaData {
+                name: scheme.name,
+                ..Default::default()
+            },
+        })
+    }
+
+    pub fn export_str(scheme: &ColorSchemeFile) -> anyhow::Result<String> {
+        let numbered = super::numbered_from_palette(&scheme.colors);
+        let color = |idx: u8| color_to_hex(&numbered[&idx]);
+        let hex = |c: &Option<RgbaColor>| c.as_ref().map(color_to_hex);
+
+        let wt = Self {
+            name: scheme.metadata.name.clone(),
+            foreground: hex(&scheme.colors.foreground).unwrap_or_else(|| color(7)),
+            background: hex(&scheme.colors.background).unwrap_or_else(|| color(0)),
+            cursor_color: hex(&scheme.colors.cursor_bg),
+            selection_background: hex(&scheme.colors.selection_bg),
+            black: color(0),
+            red: color(1),
+            green: color(2),
+            yellow: color(3),
+            blue: color(4),
+            purple: color(5),
+            cyan: color(6),
+            white: color(7),
+            bright_black: color(8),
+            bright_red: color(9),
+            bright_green: color(10),
+            bright_yellow: color(11),
+            bright_blue: color(12),
+            bright_purple: color(13),
+            bright_cyan: color(14),
+            bright_white: color(15),
+        };
+
+        Ok(serde_json::to_string_pretty(&wt)?)
+    }
+}
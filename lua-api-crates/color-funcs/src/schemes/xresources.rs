@@ -0,0 +1,106 @@
+use super::{color_to_hex, parse_color};
+use config::{ColorSchemeFile, Palette};
+use std::collections::HashMap;
+
+/// X resources of the form `*.color0: #000000`, as used by xterm,
+/// urxvt and many other X11 terminals.  Simple `#define NAME VALUE`
+/// macros are expanded, as those are commonly used by schemes that
+/// are meant to be processed by `xrdb`.
+pub struct Xresources;
+
+impl Xresources {
+    pub fn parse_str(data: &str) -> anyhow::Result<ColorSchemeFile> {
+        let mut defines = HashMap::new();
+        let mut numbered = HashMap::new();
+        let mut named = HashMap::new();
+
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('!') {
+                continue;
+            }
+            if let Some(define) = line.strip_prefix("#define") {
+                let mut fields = define.split_whitespace();
+                if let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+                    defines.insert(name.to_string(), value.to_string());
+                }
+                continue;
+            }
+            if line.starts_with('#') {
+                // Some other preprocessor directive
+                continue;
+            }
+
+            let (resource, value) = match line.split_once(':') {
+                Some((resource, value)) => (resource.trim(), value.trim()),
+                None => continue,
+            };
+            let value = defines.get(value).map(String::as_str).unwrap_or(value);
+
+            // We only care about the last component of the resource,
+            // regardless of the program or class that it is bound to
+            let key = resource
+                .rsplit(|c: char| c == '*' || c == '.')
+                .next()
+                .unwrap_or(resource);
+
+            if let Some(idx) = key.strip_prefix("color") {
+                if let Ok(idx) = idx.parse::<u8>() {
+                    numbered.insert(idx, parse_color(value)?);
+                }
+                continue;
+            }
+
+            match key {
+                "foreground" | "background" | "cursorColor" | "cursorColor2" | "highlightColor"
+                | "highlightTextColor" => {
+                    named.insert(key.to_string(), parse_color(value)?);
+                }
+                _ => {}
+            }
+        }
+
+        let cursor = named.get("cursorColor").copied();
+
+        Ok(ColorSchemeFile {
+            colors: Palette {
+                foreground: named.get("foreground").copied(),
+                background: named.get("background").copied(),
+                cursor_fg: named.get("cursorColor2").copied(),
+                cursor_bg: cursor,
+                cursor_border: cursor,
+                selection_fg: named.get("highlightTextColor").copied(),
+                selection_bg: named.get("highlightColor").copied(),
+                ..super::palette_from_numbered(numbered)
+            },
+            metadata: super::metadata_from_comments(data, &['!']),
+        })
+    }
+
+    pub fn export_str(scheme: &ColorSchemeFile) -> String {
+        let palette = &scheme.colors;
+        let mut result = super::metadata_to_comments(&scheme.metadata, "!");
+        if !result.is_empty() {
+            result.push('\n');
+        }
+
+        for (key, color) in [
+            ("foreground", &palette.foreground),
+            ("background", &palette.background),
+            ("cursorColor", &palette.cursor_bg),
+            ("cursorColor2", &palette.cursor_fg),
+            ("highlightColor", &palette.selection_bg),
+            ("highlightTextColor", &palette.selection_fg),
+        ] {
+            if let Some(color) = color {
+                result.push_str(&format!("*.{key}: {}\n", color_to_hex(color)));
+            }
+        }
+
+        for (idx, color) in super::numbered_from_palette(palette) {
+            result.push_str(&format!("*.color{idx}: {}\n", color_to_hex(&color)));
+        }
+
+        result
+    }
+}
@@ -3,8 +3,9 @@ use luahelper::{dynamic_to_lua_value, from_lua, to_lua};
 use mlua::Value;
 use std::cmp::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use termwiz::cell::SemanticType;
-use wezterm_term::{SemanticZone, StableRowIndex};
+use wezterm_term::{CommandBlock, SemanticZone, StableRowIndex};
 
 #[derive(Clone, Copy, Debug)]
 pub struct MuxPane(pub PaneId);
@@ -225,6 +226,21 @@ impl UserData for MuxPane {
             Ok(text)
         });
 
+        methods.add_method(
+            "export_lines",
+            |_, this, (format, nlines): (Value, Option<usize>)| {
+                let format: config::keyassignment::ExportFormat = from_lua(format)?;
+                let mux = get_mux()?;
+                let pane = this.resolve(&mux)?;
+                let dims = pane.get_dimensions();
+                let nlines = nlines.unwrap_or(dims.viewport_rows);
+                let bottom_row = dims.physical_top + dims.viewport_rows as isize;
+                let top_row = bottom_row.saturating_sub(nlines as isize);
+                mux::export::export_pane_lines(&*pane, top_row..bottom_row, format)
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+            },
+        );
+
         methods.add_method(
             "get_logical_lines_as_text",
             |_, this, nlines: Option<usize>| {
@@ -296,6 +312,17 @@ impl UserData for MuxPane {
             Ok(zones)
         });
 
+        methods.add_method("get_command_blocks", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            let blocks: Vec<LuaCommandBlock> = pane
+                .get_command_blocks()
+                .into_iter()
+                .map(LuaCommandBlock::from)
+                .collect();
+            to_lua(lua, blocks)
+        });
+
         methods.add_method(
             "get_semantic_zone_at",
             |lua, this, (x, y): (usize, StableRowIndex)| {
@@ -380,6 +407,41 @@ impl UserData for MuxPane {
             },
         );
 
+        methods.add_async_method(
+            "move_to_split",
+            |_lua, this, (target, args): (MuxPane, Option<MoveToSplit>)| async move {
+                let args = args.unwrap_or_default();
+                args.run(this, target).await
+            },
+        );
+
+        methods.add_async_method("swap_with", |_lua, this, other: MuxPane| async move {
+            let mux = Mux::get();
+            mux.swap_panes(this.0, other.0)
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+            Ok(())
+        });
+
+        methods.add_async_method("start_pipe", |_lua, this, spec: Value| async move {
+            let spec: config::keyassignment::PipePaneSpec = from_lua(spec)?;
+            let mux = Mux::get();
+            mux.pipe_pane(this.0, Some(spec))
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            Ok(())
+        });
+
+        methods.add_async_method("stop_pipe", |_lua, this, ()| async move {
+            let mux = Mux::get();
+            mux.pipe_pane(this.0, None)
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            Ok(())
+        });
+
+        methods.add_method("is_piped", |_lua, this, ()| Ok(mux::pipe::is_piped(this.0)));
+
         methods.add_method("activate", move |_lua, this, ()| {
             let mux = Mux::get();
             let pane = this.resolve(&mux)?;
@@ -406,6 +468,40 @@ impl UserData for MuxPane {
     }
 }
 
+/// The lua representation of a `CommandBlock`; times are expressed
+/// as seconds since the unix epoch
+#[derive(Debug, ToDynamic)]
+struct LuaCommandBlock {
+    prompt: Option<SemanticZone>,
+    input: Option<SemanticZone>,
+    output: Option<SemanticZone>,
+    exit_status: Option<i32>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+    duration: Option<Duration>,
+    is_running: bool,
+    cwd: Option<String>,
+}
+
+impl From<CommandBlock> for LuaCommandBlock {
+    fn from(block: CommandBlock) -> Self {
+        fn epoch_seconds(t: SystemTime) -> Option<f64> {
+            t.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs_f64())
+        }
+        Self {
+            prompt: block.prompt,
+            input: block.input,
+            output: block.output,
+            exit_status: block.exit_status,
+            start_time: block.start_time.and_then(epoch_seconds),
+            end_time: block.end_time.and_then(epoch_seconds),
+            duration: block.duration(),
+            is_running: block.is_running(),
+            cwd: block.cwd,
+        }
+    }
+}
+
 #[derive(Debug, Default, FromDynamic, ToDynamic)]
 struct SplitPane {
     #[dynamic(flatten)]
@@ -425,6 +521,29 @@ fn default_split_size() -> f32 {
     0.5
 }
 
+fn split_request(direction: HandySplitDirection, top_level: bool, size: f32) -> SplitRequest {
+    let size = if size == 0.0 {
+        SplitSize::Percent(50)
+    } else if size < 1.0 {
+        SplitSize::Percent((size * 100.).floor() as u8)
+    } else {
+        SplitSize::Cells(size as usize)
+    };
+
+    SplitRequest {
+        direction: match direction {
+            HandySplitDirection::Right | HandySplitDirection::Left => SplitDirection::Horizontal,
+            HandySplitDirection::Top | HandySplitDirection::Bottom => SplitDirection::Vertical,
+        },
+        target_is_second: match direction {
+            HandySplitDirection::Top | HandySplitDirection::Left => false,
+            HandySplitDirection::Bottom | HandySplitDirection::Right => true,
+        },
+        top_level,
+        size,
+    }
+}
+
 impl SplitPane {
     async fn run(self, pane: MuxPane) -> mlua::Result<MuxPane> {
         let (command, command_dir) = self.cmd_builder.to_command_builder();
@@ -433,28 +552,7 @@ impl SplitPane {
             command_dir,
         };
 
-        let size = if self.size == 0.0 {
-            SplitSize::Percent(50)
-        } else if self.size < 1.0 {
-            SplitSize::Percent((self.size * 100.).floor() as u8)
-        } else {
-            SplitSize::Cells(self.size as usize)
-        };
-
-        let direction = match self.direction {
-            HandySplitDirection::Right | HandySplitDirection::Left => SplitDirection::Horizontal,
-            HandySplitDirection::Top | HandySplitDirection::Bottom => SplitDirection::Vertical,
-        };
-
-        let request = SplitRequest {
-            direction,
-            target_is_second: match self.direction {
-                HandySplitDirection::Top | HandySplitDirection::Left => false,
-                HandySplitDirection::Bottom | HandySplitDirection::Right => true,
-            },
-            top_level: self.top_level,
-            size,
-        };
+        let request = split_request(self.direction, self.top_level, self.size);
 
         let mux = get_mux()?;
         let (pane, _size) = mux
@@ -465,3 +563,27 @@ impl SplitPane {
         Ok(MuxPane(pane.pane_id()))
     }
 }
+
+#[derive(Debug, Default, FromDynamic, ToDynamic)]
+struct MoveToSplit {
+    #[dynamic(default)]
+    direction: HandySplitDirection,
+    #[dynamic(default)]
+    top_level: bool,
+    #[dynamic(default = "default_split_size")]
+    size: f32,
+}
+impl_lua_conversion_dynamic!(MoveToSplit);
+
+impl MoveToSplit {
+    async fn run(self, pane: MuxPane, target: MuxPane) -> mlua::Result<()> {
+        let request = split_request(self.direction, self.top_level, self.size);
+
+        let mux = get_mux()?;
+        mux.move_pane_to_tab(pane.0, target.0, request)
+            .await
+            .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use config::keyassignment::PaneDirection;
+use config::keyassignment::{PaneDirection, PaneLayout};
+use mux::layout::LayoutNode;
 
 use super::*;
 use luahelper::mlua::Value;
@@ -73,6 +74,27 @@ impl UserData for MuxTab {
             Ok(pane)
         });
 
+        methods.add_async_method("move_to_window", |_, this, window: MuxWindow| async move {
+            let mux = get_mux()?;
+            let window_id = mux
+                .move_tab_to_window(this.0, Some(window.0), None)
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+            Ok(MuxWindow(window_id))
+        });
+
+        methods.add_async_method(
+            "move_to_new_window",
+            |_, this, workspace: Option<String>| async move {
+                let mux = get_mux()?;
+                let window_id = mux
+                    .move_tab_to_window(this.0, None, workspace)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+                Ok(MuxWindow(window_id))
+            },
+        );
+
         methods.add_method("set_zoomed", |_, this, zoomed: bool| {
             let mux = get_mux()?;
             let tab = this.resolve(&mux)?;
@@ -124,6 +146,28 @@ impl UserData for MuxTab {
             Ok(())
         });
 
+        methods.add_method("select_layout", |_, this, layout: Value| {
+            let mux = get_mux()?;
+            let tab = this.resolve(&mux)?;
+            let layout: PaneLayout = from_lua(layout)?;
+            tab.select_layout(layout);
+            Ok(())
+        });
+
+        methods.add_method("get_layout", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let tab = this.resolve(&mux)?;
+            to_lua(lua, tab.get_layout())
+        });
+
+        methods.add_method("set_layout", |_, this, layout: Value| {
+            let mux = get_mux()?;
+            let tab = this.resolve(&mux)?;
+            let layout: LayoutNode = from_lua(layout)?;
+            tab.apply_layout(&layout)
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
         methods.add_method("get_size", |lua, this, _: ()| {
             let mux = get_mux()?;
             let tab = this.resolve(&mux)?;
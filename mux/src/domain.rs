@@ -7,16 +7,16 @@
 
 use crate::localpane::LocalPane;
 use crate::pane::{alloc_pane_id, Pane, PaneId};
-use crate::tab::{SplitRequest, Tab, TabId};
+use crate::tab::{FloatingPaneRect, SplitRequest, Tab, TabId};
 use crate::window::WindowId;
 use crate::Mux;
 use anyhow::{bail, Context, Error};
 use async_trait::async_trait;
-use config::keyassignment::{SpawnCommand, SpawnTabDomain};
+use config::keyassignment::{PipePaneSpec, SpawnCommand, SpawnTabDomain};
 use config::{configuration, ExecDomain, SerialDomain, ValueOrFunc, WslDomain};
 use downcast_rs::{impl_downcast, Downcast};
 use parking_lot::Mutex;
-use portable_pty::{native_pty_system, CommandBuilder, PtySystem};
+use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySystem};
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::Write;
@@ -81,29 +81,16 @@ pub trait Domain: Downcast + Send + Sync {
             None => anyhow::bail!("Invalid tab id {}", tab),
         };
 
-        let pane_index = match tab
-            .iter_panes_ignoring_zoom()
-            .iter()
-            .find(|p| p.pane.pane_id() == pane_id)
-        {
-            Some(p) => p.index,
-            None => anyhow::bail!("invalid pane id {}", pane_id),
-        };
-
-        let split_size = match tab.compute_split_size(pane_index, split_request) {
-            Some(s) => s,
-            None => anyhow::bail!("invalid pane index {}", pane_index),
-        };
-
-        let pane = match source {
-            SplitSource::Spawn {
-                command,
-                command_dir,
-            } => {
-                self.spawn_pane(split_size.second, command, command_dir)
-                    .await?
-            }
+        // Detach a pane that is being moved before figuring out the
+        // index of the pane to split, as the source pane may be in the
+        // same tab, in which case removing it changes the indices
+        let moved_pane = match &source {
+            SplitSource::Spawn { .. } => None,
             SplitSource::MovePane(src_pane_id) => {
+                let src_pane_id = *src_pane_id;
+                if src_pane_id == pane_id {
+                    anyhow::bail!("cannot move pane {} into a split of itself", pane_id);
+                }
                 let (_domain, _window, src_tab) = mux
                     .resolve_pane_id(src_pane_id)
                     .ok_or_else(|| anyhow::anyhow!("pane {} not found", src_pane_id))?;
@@ -120,14 +107,65 @@ pub trait Domain: Downcast + Send + Sync {
                     mux.remove_tab(src_tab.tab_id());
                 }
 
-                pane
+                Some(pane)
+            }
+        };
+
+        let pane_index = match tab
+            .iter_panes_ignoring_zoom()
+            .iter()
+            .find(|p| p.pane.pane_id() == pane_id)
+        {
+            Some(p) => p.index,
+            None => anyhow::bail!("invalid pane id {}", pane_id),
+        };
+
+        let split_size = match tab.compute_split_size(pane_index, split_request) {
+            Some(s) => s,
+            None => anyhow::bail!("invalid pane index {}", pane_index),
+        };
+
+        let pane = match (moved_pane, source) {
+            (Some(pane), _) => pane,
+            (
+                None,
+                SplitSource::Spawn {
+                    command,
+                    command_dir,
+                },
+            ) => {
+                self.spawn_pane(split_size.second, command, command_dir)
+                    .await?
             }
+            (None, SplitSource::MovePane(_)) => unreachable!(),
         };
 
         tab.split_and_insert(pane_index, split_request, Arc::clone(&pane))?;
         Ok(pane)
     }
 
+    /// Spawn a new command in a pane that floats above the
+    /// tiled panes of the specified tab
+    async fn spawn_floating_pane(
+        &self,
+        tab: TabId,
+        rect: FloatingPaneRect,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let mux = Mux::get();
+        let tab = match mux.get_tab(tab) {
+            Some(t) => t,
+            None => anyhow::bail!("Invalid tab id {}", tab),
+        };
+
+        let tab_size = tab.get_size();
+        let size = rect.clamp_to(&tab_size).terminal_size(&tab_size);
+        let pane = self.spawn_pane(size, command, command_dir).await?;
+        tab.insert_floating_pane(Arc::clone(&pane), rect);
+        Ok(pane)
+    }
+
     async fn spawn_pane(
         &self,
         size: TerminalSize,
@@ -135,6 +173,41 @@ pub trait Domain: Downcast + Send + Sync {
         command_dir: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>>;
 
+    /// The mux will call this method on the domain of the panes that
+    /// are being swapped to give the domain a chance to handle the swap.
+    /// If this method returns Ok(false), then the mux will handle the
+    /// swap itself by mutating its local Tabs.
+    async fn swap_panes(&self, _pane_id: PaneId, _other_pane_id: PaneId) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// The mux will call this method on the domain of a pane whose
+    /// output is to be piped, or no longer piped, to give the domain
+    /// a chance to set up the pipe where the pty is being read.
+    /// If this method returns Ok(false), then the mux will manage
+    /// the pipe itself.
+    async fn pipe_pane(
+        &self,
+        _pane_id: PaneId,
+        _spec: Option<PipePaneSpec>,
+    ) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// The mux will call this method on the domain of the active pane
+    /// of the tab that is being moved to give the domain a chance to
+    /// handle the movement.
+    /// If this method returns Ok(None), then the mux will handle the
+    /// movement itself by mutating its local Windows.
+    async fn move_tab_to_window(
+        &self,
+        _tab_id: TabId,
+        _window_id: Option<WindowId>,
+        _workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<WindowId>> {
+        Ok(None)
+    }
+
     /// The mux will call this method on the domain of the pane that
     /// is being moved to give the domain a chance to handle the movement.
     /// If this method returns Ok(None), then the mux will handle the
@@ -433,6 +506,14 @@ impl LocalDomain {
         Ok(())
     }
 
+    /// Opens a new pty of the specified size using the pty system
+    /// of this domain.  This is used to restart the program in a pane.
+    pub(crate) fn openpty(&self, size: TerminalSize) -> anyhow::Result<PtyPair> {
+        self.pty_system
+            .lock()
+            .openpty(crate::terminal_size_to_pty_size(size)?)
+    }
+
     async fn build_command(
         &self,
         command: Option<CommandBuilder>,
@@ -485,6 +566,13 @@ impl WriterWrapper {
     }
 }
 
+impl WriterWrapper {
+    /// Redirects this and all clones of this writer to `writer`
+    pub fn replace(&self, writer: Box<dyn Write + Send>) {
+        *self.writer.lock() = writer;
+    }
+}
+
 impl std::io::Write for WriterWrapper {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.writer.lock().write(buf)
@@ -522,6 +610,7 @@ impl Domain for LocalDomain {
             },
             self.name
         );
+        let restart_command = cmd.clone();
         let child = pair.slave.spawn_command(cmd)?;
         log::trace!("spawned: {:?}", child);
 
@@ -538,15 +627,17 @@ impl Domain for LocalDomain {
             terminal.enable_conpty_quirks();
         }
 
-        let pane: Arc<dyn Pane> = Arc::new(LocalPane::new(
+        let pane = LocalPane::new(
             pane_id,
             terminal,
             child,
             pair.master,
-            Box::new(writer),
+            Box::new(writer.clone()),
             self.id,
             command_description,
-        ));
+        );
+        pane.enable_restart(restart_command, writer);
+        let pane: Arc<dyn Pane> = Arc::new(pane);
 
         let mux = Mux::get();
         mux.add_pane(&pane)?;
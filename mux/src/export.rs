@@ -0,0 +1,547 @@
+//! Renders the lines of a pane, along with their colors and styles,
+//! as HTML, SVG or text that contains escape sequences, so that they
+//! can be shared outside of the terminal.
+use crate::pane::Pane;
+use config::configuration;
+use config::keyassignment::ExportFormat;
+use std::fmt::Write;
+use std::ops::Range;
+use termwiz::cell::{CellAttributes, Intensity, Underline};
+use termwiz::color::ColorAttribute;
+use termwiz::surface::Line;
+use wezterm_term::color::ColorPalette;
+use wezterm_term::StableRowIndex;
+
+/// The metrics used to lay out SVG output, in pixels.
+/// The cell width is typical of monospace fonts.
+const SVG_FONT_SIZE: f32 = 14.;
+const SVG_CELL_WIDTH: f32 = SVG_FONT_SIZE * 0.6;
+const SVG_LINE_HEIGHT: f32 = SVG_FONT_SIZE * 1.2;
+const SVG_PADDING: f32 = SVG_FONT_SIZE;
+
+/// The schemes of the hyperlinks that are exported as links.
+/// Anything else, such as `javascript:`, is exported as plain text
+/// so that the output is safe to embed in a web page.
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto", "file"];
+
+/// The resolved appearance of a cell
+#[derive(Debug, Clone, PartialEq)]
+struct Style {
+    /// None if the foreground is the default color
+    fg: Option<String>,
+    /// None if the background is the default color
+    bg: Option<String>,
+    bold: bool,
+    half_bright: bool,
+    italic: bool,
+    underline: Underline,
+    underline_color: Option<String>,
+    strikethrough: bool,
+    overline: bool,
+    href: Option<String>,
+}
+
+impl Style {
+    fn new(attrs: &CellAttributes, palette: &ColorPalette) -> Self {
+        let mut fg = match attrs.foreground() {
+            ColorAttribute::Default => None,
+            color => Some(palette.resolve_fg(color)),
+        };
+        let mut bg = match attrs.background() {
+            ColorAttribute::Default => None,
+            color => Some(palette.resolve_bg(color)),
+        };
+        if attrs.reverse() {
+            let new_bg = fg.unwrap_or(palette.foreground);
+            fg = Some(bg.unwrap_or(palette.background));
+            bg = Some(new_bg);
+        }
+        if attrs.invisible() {
+            fg = Some(bg.unwrap_or(palette.background));
+        }
+
+        Self {
+            fg: fg.map(|c| c.to_rgb_string()),
+            bg: bg.map(|c| c.to_rgb_string()),
+            bold: attrs.intensity() == Intensity::Bold,
+            half_bright: attrs.intensity() == Intensity::Half,
+            italic: attrs.italic(),
+            underline: attrs.underline(),
+            underline_color: match attrs.underline_color() {
+                ColorAttribute::Default => None,
+                color => Some(palette.resolve_fg(color).to_rgb_string()),
+            },
+            strikethrough: attrs.strikethrough(),
+            overline: attrs.overline(),
+            href: attrs
+                .hyperlink()
+                .map(|link| link.uri())
+                .filter(|uri| is_safe_link(uri))
+                .map(|uri| uri.to_string()),
+        }
+    }
+
+    /// Returns true if a space with this style would be invisible
+    fn is_blank(&self) -> bool {
+        self.bg.is_none()
+            && self.underline == Underline::None
+            && !self.strikethrough
+            && !self.overline
+    }
+
+    fn text_decoration_lines(&self) -> Vec<&'static str> {
+        let mut lines = vec![];
+        if self.underline != Underline::None {
+            lines.push("underline");
+        }
+        if self.strikethrough {
+            lines.push("line-through");
+        }
+        if self.overline {
+            lines.push("overline");
+        }
+        lines
+    }
+
+    /// Returns the CSS declarations for this style
+    fn css(&self) -> String {
+        let mut css = vec![];
+        if let Some(fg) = &self.fg {
+            css.push(format!("color:{}", fg));
+        }
+        if let Some(bg) = &self.bg {
+            css.push(format!("background-color:{}", bg));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.half_bright {
+            css.push("opacity:0.6".to_string());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        let lines = self.text_decoration_lines();
+        if !lines.is_empty() {
+            css.push(format!("text-decoration-line:{}", lines.join(" ")));
+            match self.underline {
+                Underline::Double => css.push("text-decoration-style:double".to_string()),
+                Underline::Curly => css.push("text-decoration-style:wavy".to_string()),
+                Underline::Dotted => css.push("text-decoration-style:dotted".to_string()),
+                Underline::Dashed => css.push("text-decoration-style:dashed".to_string()),
+                Underline::None | Underline::Single => {}
+            }
+            if let Some(color) = &self.underline_color {
+                css.push(format!("text-decoration-color:{}", color));
+            }
+        }
+        css.join(";")
+    }
+}
+
+/// A sequence of cells with the same style
+struct Run {
+    style: Style,
+    text: String,
+    /// The cell index of the first cell in the run
+    col: usize,
+    /// The number of cells occupied by the run
+    width: usize,
+}
+
+/// Groups the cells of line into runs of the same style,
+/// omitting any trailing blank cells
+fn is_safe_link(uri: &str) -> bool {
+    match url::Url::parse(uri) {
+        Ok(url) => LINK_SCHEMES.contains(&url.scheme()),
+        Err(_) => false,
+    }
+}
+
+fn styled_runs(line: &Line, palette: &ColorPalette) -> Vec<Run> {
+    let cells: Vec<_> = line
+        .visible_cells()
+        .map(|cell| {
+            let style = Style::new(cell.attrs(), palette);
+            (cell, style)
+        })
+        .collect();
+    let end = cells
+        .iter()
+        .rposition(|(cell, style)| cell.str() != " " || !style.is_blank())
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+
+    let mut runs: Vec<Run> = vec![];
+    for (cell, style) in cells.into_iter().take(end) {
+        match runs.last_mut() {
+            Some(run) if run.style == style => {
+                run.text.push_str(cell.str());
+                run.width += cell.width();
+            }
+            _ => runs.push(Run {
+                style,
+                text: cell.str().to_string(),
+                col: cell.cell_index(),
+                width: cell.width(),
+            }),
+        }
+    }
+    runs
+}
+
+/// Escapes text for inclusion in HTML or XML
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Returns a CSS font-family list based on the configured font
+fn font_family() -> String {
+    let config = configuration();
+    let mut families: Vec<String> = config
+        .font
+        .font
+        .iter()
+        .map(|attr| format!("'{}'", attr.family.replace('\'', "")))
+        .collect();
+    families.push("monospace".to_string());
+    families.join(",")
+}
+
+/// Renders lines as an HTML `<pre>` element with inline styles,
+/// suitable for pasting into documents and bug reports
+pub fn lines_to_html(lines: &[Line], palette: &ColorPalette) -> String {
+    let mut html = String::new();
+    write!(
+        html,
+        "<pre style=\"font-family:{};color:{};background-color:{};padding:0.5em\">",
+        escape(&font_family()),
+        palette.foreground.to_rgb_string(),
+        palette.background.to_rgb_string()
+    )
+    .ok();
+
+    for (idx, line) in lines.iter().enumerate() {
+        if idx > 0 {
+            html.push('\n');
+        }
+        for run in styled_runs(line, palette) {
+            if let Some(href) = &run.style.href {
+                write!(
+                    html,
+                    "<a href=\"{}\" style=\"color:inherit\">",
+                    escape(href)
+                )
+                .ok();
+            }
+            let css = run.style.css();
+            if css.is_empty() {
+                html.push_str(&escape(&run.text));
+            } else {
+                write!(html, "<span style=\"{}\">{}</span>", css, escape(&run.text)).ok();
+            }
+            if run.style.href.is_some() {
+                html.push_str("</a>");
+            }
+        }
+    }
+
+    html.push_str("</pre>\n");
+    html
+}
+
+/// Renders lines as a standalone SVG image, laid out on a grid of
+/// cells so that the alignment of the text is preserved
+pub fn lines_to_svg(lines: &[Line], palette: &ColorPalette) -> String {
+    let runs: Vec<Vec<Run>> = lines
+        .iter()
+        .map(|line| styled_runs(line, palette))
+        .collect();
+    let cols = runs
+        .iter()
+        .filter_map(|line| line.last().map(|run| run.col + run.width))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = (cols as f32 * SVG_CELL_WIDTH + 2. * SVG_PADDING).ceil();
+    let height = (lines.len().max(1) as f32 * SVG_LINE_HEIGHT + 2. * SVG_PADDING).ceil();
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+         width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n\
+         <g font-family=\"{}\" font-size=\"{}\" fill=\"{}\">\n",
+        palette.background.to_rgb_string(),
+        escape(&font_family()),
+        SVG_FONT_SIZE,
+        palette.foreground.to_rgb_string(),
+    )
+    .ok();
+
+    for (row, line) in runs.iter().enumerate() {
+        let top = SVG_PADDING + row as f32 * SVG_LINE_HEIGHT;
+
+        for run in line {
+            if let Some(bg) = &run.style.bg {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    SVG_PADDING + run.col as f32 * SVG_CELL_WIDTH,
+                    top,
+                    run.width as f32 * SVG_CELL_WIDTH,
+                    SVG_LINE_HEIGHT,
+                    bg
+                )
+                .ok();
+            }
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // Place the baseline so that the descenders fit in the line
+        write!(
+            svg,
+            "<text y=\"{}\" xml:space=\"preserve\">",
+            top + SVG_LINE_HEIGHT * 0.8
+        )
+        .ok();
+        for run in line {
+            if let Some(href) = &run.style.href {
+                write!(svg, "<a href=\"{}\">", escape(href)).ok();
+            }
+            write!(
+                svg,
+                "<tspan x=\"{}\" textLength=\"{}\"",
+                SVG_PADDING + run.col as f32 * SVG_CELL_WIDTH,
+                run.width as f32 * SVG_CELL_WIDTH
+            )
+            .ok();
+            if let Some(fg) = &run.style.fg {
+                write!(svg, " fill=\"{}\"", fg).ok();
+            }
+            if run.style.bold {
+                svg.push_str(" font-weight=\"bold\"");
+            }
+            if run.style.half_bright {
+                svg.push_str(" fill-opacity=\"0.6\"");
+            }
+            if run.style.italic {
+                svg.push_str(" font-style=\"italic\"");
+            }
+            let decorations = run.style.text_decoration_lines();
+            if !decorations.is_empty() {
+                write!(svg, " text-decoration=\"{}\"", decorations.join(" ")).ok();
+            }
+            write!(svg, ">{}</tspan>", escape(&run.text)).ok();
+            if run.style.href.is_some() {
+                svg.push_str("</a>");
+            }
+        }
+        svg.push_str("</text>\n");
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Renders lines in the requested format.
+/// Trailing whitespace is removed from each line.
+pub fn export_lines(
+    lines: &[Line],
+    palette: &ColorPalette,
+    format: ExportFormat,
+) -> anyhow::Result<String> {
+    let pruned = || {
+        lines
+            .iter()
+            .map(|line| {
+                let mut line = line.clone();
+                line.prune_trailing_blanks(line.current_seqno());
+                line
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match format {
+        ExportFormat::Text => {
+            let mut text = String::new();
+            for line in lines {
+                text.push_str(line.as_str().trim_end());
+                text.push('\n');
+            }
+            Ok(text)
+        }
+        ExportFormat::Ansi => termwiz_funcs::lines_to_escapes(pruned()),
+        ExportFormat::Html => Ok(lines_to_html(lines, palette)),
+        ExportFormat::Svg => Ok(lines_to_svg(lines, palette)),
+    }
+}
+
+/// Renders the rows of pane in the requested format, resolving
+/// colors through the palette of the pane.
+/// Wrapped lines are joined together, except for SVG output which
+/// reproduces the rows as they appear in the terminal.
+pub fn export_pane_lines(
+    pane: &dyn Pane,
+    rows: Range<StableRowIndex>,
+    format: ExportFormat,
+) -> anyhow::Result<String> {
+    let mut lines = vec![];
+    for line in pane.get_logical_lines(rows.clone()) {
+        if format == ExportFormat::Svg {
+            for (idx, phys) in line.physical_lines.into_iter().enumerate() {
+                if rows.contains(&(line.first_row + idx as StableRowIndex)) {
+                    lines.push(phys);
+                }
+            }
+        } else {
+            lines.push(line.logical);
+        }
+    }
+    export_lines(&lines, &pane.palette(), format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use termwiz::cell::Cell;
+    use termwiz::color::AnsiColor;
+    use termwiz::hyperlink::Hyperlink;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn line(cells: &[(&str, CellAttributes)]) -> Line {
+        let mut result = vec![];
+        for (text, attrs) in cells {
+            for c in text.chars() {
+                result.push(Cell::new(c, attrs.clone()));
+            }
+        }
+        Line::from_cells(result, SEQ_ZERO)
+    }
+
+    #[test]
+    fn html() {
+        let palette = ColorPalette::default();
+        let mut red = CellAttributes::default();
+        red.set_foreground(AnsiColor::Maroon);
+        let mut bold = CellAttributes::default();
+        bold.set_intensity(Intensity::Bold)
+            .set_underline(Underline::Curly);
+
+        let lines = vec![
+            line(&[
+                ("<a>", CellAttributes::default()),
+                ("red", red.clone()),
+                ("   ", CellAttributes::default()),
+            ]),
+            line(&[("bold", bold)]),
+        ];
+
+        let html = lines_to_html(&lines, &palette);
+        let body = html.split_once('>').unwrap().1;
+        assert_eq!(
+            body,
+            format!(
+                "&lt;a&gt;<span style=\"color:{}\">red</span>\n\
+                 <span style=\"font-weight:bold;text-decoration-line:underline;\
+                 text-decoration-style:wavy\">bold</span></pre>\n",
+                palette.colors.0[1].to_rgb_string()
+            )
+        );
+    }
+
+    #[test]
+    fn svg() {
+        let palette = ColorPalette::default();
+        let mut inverse = CellAttributes::default();
+        inverse.set_reverse(true);
+
+        let lines = vec![line(&[("ab", CellAttributes::default()), ("cd", inverse)])];
+        let svg = lines_to_svg(&lines, &palette);
+        assert!(svg.starts_with("<svg "), "{}", svg);
+        assert!(svg.ends_with("</svg>\n"), "{}", svg);
+        // The reversed cells get a background in the foreground color
+        assert!(
+            svg.contains(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                SVG_PADDING + 2. * SVG_CELL_WIDTH,
+                SVG_PADDING,
+                2. * SVG_CELL_WIDTH,
+                SVG_LINE_HEIGHT,
+                palette.foreground.to_rgb_string()
+            )),
+            "{}",
+            svg
+        );
+        assert!(svg.contains(">ab</tspan>"), "{}", svg);
+        assert!(svg.contains(&format!(
+            " fill=\"{}\">cd</tspan>",
+            palette.background.to_rgb_string()
+        )));
+    }
+
+    #[test]
+    fn unsafe_links_are_plain_text() {
+        let palette = ColorPalette::default();
+        let link = |uri: &str| {
+            let mut attrs = CellAttributes::default();
+            attrs.set_hyperlink(Some(Arc::new(Hyperlink::new(uri))));
+            attrs
+        };
+
+        let lines = vec![line(&[
+            ("web", link("https://wezfurlong.org/")),
+            ("mail", link("mailto:someone@example.com")),
+            ("js", link("javascript:alert(1)")),
+            ("JS", link("JavaScript:alert(1)")),
+            ("data", link("data:text/html,<b>hi</b>")),
+        ])];
+
+        let html = lines_to_html(&lines, &palette);
+        assert!(
+            html.contains("<a href=\"https://wezfurlong.org/\" style=\"color:inherit\">web</a>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(
+                "<a href=\"mailto:someone@example.com\" style=\"color:inherit\">mail</a>"
+            ),
+            "{}",
+            html
+        );
+        assert!(html.contains("</a>jsJSdata</pre>"), "{}", html);
+        assert_eq!(html.matches("<a ").count(), 2, "{}", html);
+
+        let svg = lines_to_svg(&lines, &palette);
+        assert!(
+            svg.contains("<a href=\"https://wezfurlong.org/\">"),
+            "{}",
+            svg
+        );
+        assert!(
+            svg.contains("<a href=\"mailto:someone@example.com\">"),
+            "{}",
+            svg
+        );
+        assert!(!svg.contains("javascript"), "{}", svg);
+        assert!(!svg.contains("data:"), "{}", svg);
+        assert_eq!(svg.matches("<a ").count(), 2, "{}", svg);
+    }
+}
@@ -0,0 +1,238 @@
+//! Automatic arrangements of the panes in a tab, in the manner of
+//! tmux's `select-layout`.
+//!
+//! A layout is described by a tree of `LayoutNode`s, each of which is
+//! either a slot for a single pane, or a split that divides its space
+//! between any number of children.  That n-ary description is then
+//! rewritten into the binary split tree used by `Tab`.
+use crate::pane::Pane;
+use crate::tab::{SplitDirection, SplitDirectionAndSize, Tree};
+use config::keyassignment::PaneLayout;
+use std::sync::Arc;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+use wezterm_term::TerminalSize;
+
+/// The share of the space given to the main pane by the
+/// `MainHorizontal` and `MainVertical` layouts
+const MAIN_PANE_PERCENT: usize = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct LayoutNode {
+    /// The share of its parent's space that this node receives,
+    /// relative to the weights of its siblings
+    #[dynamic(default = "default_weight")]
+    pub weight: usize,
+    /// How the children are arranged; Horizontal places them side by
+    /// side, Vertical stacks them top to bottom.  Defaults to Horizontal.
+    #[dynamic(default)]
+    pub direction: Option<SplitDirection>,
+    /// If empty, this node is a slot for a single pane
+    #[dynamic(default)]
+    pub children: Vec<LayoutNode>,
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+impl LayoutNode {
+    pub fn pane(weight: usize) -> Self {
+        Self {
+            weight,
+            direction: None,
+            children: vec![],
+        }
+    }
+
+    pub fn split(weight: usize, direction: SplitDirection, children: Vec<LayoutNode>) -> Self {
+        Self {
+            weight,
+            direction: Some(direction),
+            children,
+        }
+    }
+
+    fn is_pane(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    fn direction(&self) -> SplitDirection {
+        self.direction.unwrap_or(SplitDirection::Horizontal)
+    }
+
+    /// Returns the number of panes needed to fill this layout
+    pub fn num_panes(&self) -> usize {
+        if self.is_pane() {
+            1
+        } else {
+            self.children.iter().map(|c| c.num_panes()).sum()
+        }
+    }
+
+    /// Computes one of the predefined layouts for num_panes panes.
+    /// `PaneLayout::Rebalance` depends on the existing arrangement,
+    /// so it is computed via `from_tree` instead.
+    pub fn for_layout(layout: PaneLayout, num_panes: usize) -> Self {
+        use SplitDirection::{Horizontal, Vertical};
+
+        let even = |direction, count| {
+            LayoutNode::split(
+                1,
+                direction,
+                (0..count).map(|_| LayoutNode::pane(1)).collect(),
+            )
+        };
+
+        if num_panes <= 1 {
+            return Self::pane(1);
+        }
+
+        match layout {
+            PaneLayout::EvenHorizontal | PaneLayout::Rebalance => even(Horizontal, num_panes),
+            PaneLayout::EvenVertical => even(Vertical, num_panes),
+            PaneLayout::MainHorizontal | PaneLayout::MainVertical => {
+                let (outer, inner) = if layout == PaneLayout::MainVertical {
+                    (Horizontal, Vertical)
+                } else {
+                    (Vertical, Horizontal)
+                };
+                let mut others = even(inner, num_panes - 1);
+                others.weight = 100 - MAIN_PANE_PERCENT;
+                Self::split(1, outer, vec![Self::pane(MAIN_PANE_PERCENT), others])
+            }
+            PaneLayout::Tiled => {
+                let cols = (num_panes as f64).sqrt().ceil() as usize;
+                let rows = (num_panes + cols - 1) / cols;
+                let mut remaining = num_panes;
+                let mut row_nodes = vec![];
+                for row in 0..rows {
+                    // The last row takes whatever is left over
+                    let count = if row == rows - 1 { remaining } else { cols };
+                    remaining -= count;
+                    row_nodes.push(if count == 1 {
+                        Self::pane(1)
+                    } else {
+                        even(Horizontal, count)
+                    });
+                }
+                Self::split(1, Vertical, row_nodes)
+            }
+        }
+    }
+
+    /// Describes the arrangement of an existing tree of panes, using
+    /// the current sizes of the panes as their weights.
+    /// Consecutive splits in the same direction are merged into
+    /// a single node.
+    pub fn from_tree(tree: &Tree, size: &TerminalSize) -> Self {
+        fn extent(direction: Option<SplitDirection>, size: &TerminalSize) -> usize {
+            match direction {
+                Some(SplitDirection::Vertical) => size.rows,
+                _ => size.cols,
+            }
+        }
+
+        fn walk(tree: &Tree, size: &TerminalSize, parent: Option<SplitDirection>) -> LayoutNode {
+            let weight = extent(parent, size).max(1);
+            match tree {
+                Tree::Empty | Tree::Leaf(_) | Tree::Node { data: None, .. } => {
+                    LayoutNode::pane(weight)
+                }
+                Tree::Node {
+                    left,
+                    right,
+                    data: Some(data),
+                } => {
+                    let direction = data.direction;
+                    let mut children = vec![];
+                    for (subtree, subsize) in [(left, &data.first), (right, &data.second)] {
+                        let child = walk(subtree, subsize, Some(direction));
+                        if !child.is_pane() && child.direction() == direction {
+                            children.extend(child.children);
+                        } else {
+                            children.push(child);
+                        }
+                    }
+                    LayoutNode::split(weight, direction, children)
+                }
+            }
+        }
+
+        walk(tree, size, None)
+    }
+
+    /// Gives all of the children of each split an equal weight
+    pub fn rebalance(&mut self) {
+        for child in self.children.iter_mut() {
+            child.weight = 1;
+            child.rebalance();
+        }
+    }
+
+    /// Builds the split tree for this layout, filling its slots with
+    /// panes taken in order from `panes`, which must yield at least
+    /// `num_panes()` panes.
+    /// `size` is the overall size of the tab, and `cell_dims` the
+    /// pixel dimensions of a single cell.
+    pub(crate) fn build_tree(
+        &self,
+        panes: &mut dyn Iterator<Item = Arc<dyn Pane>>,
+        size: &TerminalSize,
+        cell_dims: &TerminalSize,
+    ) -> Tree {
+        if self.is_pane() {
+            return match panes.next() {
+                Some(pane) => Tree::Leaf(pane),
+                None => Tree::Empty,
+            };
+        }
+        if self.children.len() == 1 {
+            return self.children[0].build_tree(panes, size, cell_dims);
+        }
+
+        let direction = self.direction();
+        let dim = match direction {
+            SplitDirection::Horizontal => size.cols,
+            SplitDirection::Vertical => size.rows,
+        };
+
+        // The first child gets its share of the space that remains after
+        // accounting for the dividers, and the second half of the binary
+        // split recursively divides the rest between the other children
+        let total_weight: usize = self.children.iter().map(|c| c.weight.max(1)).sum();
+        let dividers = self.children.len() - 1;
+        let available = dim.saturating_sub(dividers);
+        let first_dim = ((available * self.children[0].weight.max(1)) / total_weight.max(1))
+            .max(1)
+            .min(dim.saturating_sub(2).max(1));
+        let second_dim = dim.saturating_sub(first_dim + 1);
+
+        let sized = |extent: usize| -> TerminalSize {
+            let (cols, rows) = match direction {
+                SplitDirection::Horizontal => (extent, size.rows),
+                SplitDirection::Vertical => (size.cols, extent),
+            };
+            TerminalSize {
+                rows,
+                cols,
+                pixel_width: cell_dims.pixel_width * cols,
+                pixel_height: cell_dims.pixel_height * rows,
+                dpi: cell_dims.dpi,
+            }
+        };
+        let first = sized(first_dim);
+        let second = sized(second_dim);
+
+        let rest = LayoutNode::split(1, direction, self.children[1..].to_vec());
+
+        Tree::Node {
+            left: Box::new(self.children[0].build_tree(panes, &first, cell_dims)),
+            right: Box::new(rest.build_tree(panes, &second, cell_dims)),
+            data: Some(SplitDirectionAndSize {
+                direction,
+                first,
+                second,
+            }),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use crate::client::{ClientId, ClientInfo};
 use crate::pane::{Pane, PaneId};
-use crate::tab::{SplitRequest, Tab, TabId};
+use crate::tab::{FloatingPaneRect, SplitRequest, Tab, TabId};
 use crate::window::{Window, WindowId};
 use anyhow::{anyhow, Context, Error};
-use config::keyassignment::SpawnTabDomain;
+use config::keyassignment::{PipePaneSpec, SpawnTabDomain};
 use config::{configuration, ExitBehavior, GuiPosition};
 use domain::{Domain, DomainId, DomainState, SplitSource};
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
@@ -34,8 +34,11 @@ pub mod activity;
 pub mod client;
 pub mod connui;
 pub mod domain;
+pub mod export;
+pub mod layout;
 pub mod localpane;
 pub mod pane;
+pub mod pipe;
 pub mod renderable;
 pub mod ssh;
 pub mod tab;
@@ -43,6 +46,7 @@ pub mod termwiztermtab;
 pub mod tmux;
 pub mod tmux_commands;
 mod tmux_pty;
+mod trigger;
 pub mod window;
 
 use crate::activity::Activity;
@@ -91,6 +95,15 @@ pub enum MuxNotification {
         old_workspace: String,
         new_workspace: String,
     },
+    /// A trigger with an EmitEvent action matched the output of a pane
+    TriggerEvent {
+        pane_id: PaneId,
+        name: String,
+        captures: Vec<Option<String>>,
+    },
+    /// The pipe that was copying the output of a pane failed,
+    /// or couldn't keep up with the output, and was stopped
+    PanePipeStopped(PaneId),
 }
 
 static SUB_ID: AtomicUsize = AtomicUsize::new(0);
@@ -256,6 +269,27 @@ fn allocate_socketpair() -> anyhow::Result<(FileDescriptor, FileDescriptor)> {
     Ok((tx, rx))
 }
 
+/// Spawns a thread that reads the output from the pane's pty and
+/// feeds it to its terminal parser.
+/// `previous` is the reader thread of a program that previously ran
+/// in the pane; the new thread waits for it to finish relaying its
+/// output before reading, so that the output of the two programs
+/// cannot interleave.
+pub(crate) fn start_pane_reader(
+    pane: &Arc<dyn Pane>,
+    banner: Option<String>,
+    reader: Box<dyn std::io::Read + Send>,
+    previous: Option<thread::JoinHandle<()>>,
+) -> thread::JoinHandle<()> {
+    let pane = Arc::downgrade(pane);
+    thread::spawn(move || {
+        if let Some(previous) = previous {
+            previous.join().ok();
+        }
+        read_from_pane_pty(pane, banner, reader)
+    })
+}
+
 /// This function is run in a separate thread; its purpose is to perform
 /// blocking reads from the pty (non-blocking reads are not portable to
 /// all platforms and pty/tty types), parse the escape sequences and
@@ -291,7 +325,7 @@ fn read_from_pane_pty(
         }
     };
 
-    std::thread::spawn({
+    let parser = std::thread::spawn({
         let dead = Arc::clone(&dead);
         move || parse_buffered_data(pane, &dead, rx)
     });
@@ -313,6 +347,7 @@ fn read_from_pane_pty(
             Ok(size) => {
                 histogram!("read_from_pane_pty.bytes.rate", size as f64);
                 log::trace!("read_pty pane {pane_id} read {size} bytes");
+                pipe::write_output(pane_id, &buf[..size]);
                 if let Err(err) = tx.write_all(&buf[..size]) {
                     error!(
                         "read_pty failed to write to parser: pane {} {:?}",
@@ -324,8 +359,13 @@ fn read_from_pane_pty(
         }
     }
 
+    // Let the parser drain what we've written to it, so that all
+    // of the output has been sent to the pane once we return
+    drop(tx);
+    parser.join().ok();
+
     match configuration().exit_behavior {
-        ExitBehavior::Hold | ExitBehavior::CloseOnCleanExit => {
+        ExitBehavior::Hold | ExitBehavior::CloseOnCleanExit | ExitBehavior::Restart => {
             // We don't know if we can unilaterally close
             // this pane right now, so don't!
             promise::spawn::spawn_into_main_thread(async move {
@@ -753,8 +793,10 @@ impl Mux {
         let pane_id = pane.pane_id();
         if let Some(reader) = pane.reader()? {
             let banner = self.banner.read().clone();
-            let pane = Arc::downgrade(pane);
-            thread::spawn(move || read_from_pane_pty(pane, banner, reader));
+            let reader_thread = start_pane_reader(pane, banner, reader, None);
+            if let Some(local_pane) = pane.downcast_ref::<localpane::LocalPane>() {
+                local_pane.set_reader_thread(reader_thread);
+            }
         }
         self.recompute_pane_count();
         self.notify(MuxNotification::PaneAdded(pane_id));
@@ -778,6 +820,8 @@ impl Mux {
         log::debug!("removing pane {}", pane_id);
         if let Some(pane) = self.panes.write().remove(&pane_id).clone() {
             log::debug!("killing pane {}", pane_id);
+            pipe::stop(pane_id);
+            trigger::pane_removed(pane_id);
             pane.kill();
             self.recompute_pane_count();
             self.notify(MuxNotification::PaneRemoved(pane_id));
@@ -799,6 +843,9 @@ impl Mux {
         for pos in tab.iter_panes_ignoring_zoom() {
             pane_ids.push(pos.pane.pane_id());
         }
+        for pos in tab.iter_floating_panes() {
+            pane_ids.push(pos.pane.pane_id());
+        }
         log::debug!("panes to remove: {pane_ids:?}");
         for pane_id in pane_ids {
             self.remove_pane_internal(pane_id);
@@ -1028,7 +1075,9 @@ impl Mux {
     pub fn resolve_pane_id(&self, pane_id: PaneId) -> Option<(DomainId, WindowId, TabId)> {
         let mut ids = None;
         for tab in self.tabs.read().values() {
-            for p in tab.iter_panes_ignoring_zoom() {
+            let mut panes = tab.iter_panes_ignoring_zoom();
+            panes.append(&mut tab.iter_floating_panes());
+            for p in panes {
                 if p.pane.pane_id() == pane_id {
                     ids = Some((tab.tab_id(), p.pane.domain_id()));
                     break;
@@ -1189,6 +1238,53 @@ impl Mux {
         Ok((pane, size))
     }
 
+    /// Spawns a new pane that floats above the tiled panes of the tab
+    /// that contains pane_id.  When rect is None, the floating pane is
+    /// centered in the tab and covers most of it.
+    pub async fn spawn_floating_pane(
+        &self,
+        pane_id: PaneId,
+        rect: Option<FloatingPaneRect>,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        domain: config::keyassignment::SpawnTabDomain,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let (_pane_domain_id, window_id, tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} invalid", pane_id))?;
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} invalid", tab_id))?;
+
+        let domain = self
+            .resolve_spawn_tab_domain(Some(pane_id), &domain)
+            .context("resolve_spawn_tab_domain")?;
+
+        if domain.state() == DomainState::Detached {
+            domain.attach(Some(window_id)).await?;
+        }
+
+        let current_pane = self
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+        let term_config = current_pane.get_config();
+        let command_dir = self.resolve_cwd(
+            command_dir,
+            Some(Arc::clone(&current_pane)),
+            domain.domain_id(),
+        );
+
+        let rect = rect.unwrap_or_else(|| FloatingPaneRect::centered(&tab.get_size(), 80));
+        let pane = domain
+            .spawn_floating_pane(tab_id, rect, command, command_dir)
+            .await?;
+        if let Some(config) = term_config {
+            pane.set_config(config);
+        }
+
+        Ok(pane)
+    }
+
     pub async fn move_pane_to_new_tab(
         &self,
         pane_id: PaneId,
@@ -1248,6 +1344,207 @@ impl Mux {
         Ok((tab, window_id))
     }
 
+    /// Moves pane_id out of its tab and into a new split of
+    /// target_pane_id, which may be in a different tab or window.
+    /// This is the equivalent of tmux's `join-pane`.
+    pub async fn move_pane_to_tab(
+        &self,
+        pane_id: PaneId,
+        target_pane_id: PaneId,
+        request: SplitRequest,
+    ) -> anyhow::Result<(Arc<dyn Pane>, TerminalSize)> {
+        if pane_id == target_pane_id {
+            anyhow::bail!("cannot move pane {} into a split of itself", pane_id);
+        }
+        let (domain_id, _, _) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let (target_domain_id, _, _) = self
+            .resolve_pane_id(target_pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", target_pane_id))?;
+        if domain_id != target_domain_id {
+            anyhow::bail!(
+                "cannot move pane {} into pane {} because they belong to different domains",
+                pane_id,
+                target_pane_id
+            );
+        }
+
+        self.split_pane(
+            target_pane_id,
+            request,
+            SplitSource::MovePane(pane_id),
+            SpawnTabDomain::CurrentPaneDomain,
+        )
+        .await
+    }
+
+    /// Exchanges the positions of two panes, which may be in different
+    /// tabs or windows.  This is the equivalent of tmux's `swap-pane`.
+    pub async fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> anyhow::Result<()> {
+        if pane_id == other_pane_id {
+            return Ok(());
+        }
+        let (domain_id, _, tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let (other_domain_id, _, other_tab_id) = self
+            .resolve_pane_id(other_pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", other_pane_id))?;
+        if domain_id != other_domain_id {
+            anyhow::bail!(
+                "cannot swap pane {} with pane {} because they belong to different domains",
+                pane_id,
+                other_pane_id
+            );
+        }
+
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {domain_id} of pane {pane_id} not found"))?;
+        if domain.swap_panes(pane_id, other_pane_id).await? {
+            return Ok(());
+        }
+
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+
+        if tab_id == other_tab_id {
+            if !tab.swap_panes(pane_id, other_pane_id) {
+                anyhow::bail!("failed to swap pane {} with {}", pane_id, other_pane_id);
+            }
+            return Ok(());
+        }
+
+        let other_tab = self
+            .get_tab(other_tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", other_tab_id))?;
+        let pane = self
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let other_pane = self
+            .get_pane(other_pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", other_pane_id))?;
+
+        tab.replace_pane(pane_id, &other_pane)
+            .ok_or_else(|| anyhow!("pane {} wasn't in its containing tab!?", pane_id))?;
+        other_tab
+            .replace_pane(other_pane_id, &pane)
+            .ok_or_else(|| anyhow!("pane {} wasn't in its containing tab!?", other_pane_id))?;
+
+        Ok(())
+    }
+
+    /// Starts copying the output of a pane as described by spec,
+    /// replacing any existing pipe for that pane, or stops copying it
+    /// if spec is None.  This is the equivalent of tmux's `pipe-pane`.
+    pub async fn pipe_pane(
+        &self,
+        pane_id: PaneId,
+        spec: Option<PipePaneSpec>,
+    ) -> anyhow::Result<()> {
+        let (domain_id, _, _) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {domain_id} of pane {pane_id} not found"))?;
+
+        if domain.pipe_pane(pane_id, spec.clone()).await? {
+            if spec.is_some() {
+                pipe::set_remote(pane_id);
+            } else {
+                pipe::stop(pane_id);
+            }
+        } else {
+            match &spec {
+                Some(spec) => pipe::start(pane_id, spec)?,
+                None => {
+                    pipe::stop(pane_id);
+                }
+            }
+        }
+
+        // Let the gui know that it needs to update the indicator
+        self.notify(MuxNotification::PaneOutput(pane_id));
+        Ok(())
+    }
+
+    /// Moves a tab to the end of the tab list of window_id, or to
+    /// a new window if window_id is None.  Returns the id of the window
+    /// that now contains the tab.
+    /// This is the equivalent of tmux's `move-window`.
+    pub async fn move_tab_to_window(
+        &self,
+        tab_id: TabId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<WindowId> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+        let src_window_id = self
+            .window_containing_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is not in a window", tab_id))?;
+        if Some(src_window_id) == window_id {
+            return Ok(src_window_id);
+        }
+
+        let pane = tab
+            .get_active_pane()
+            .ok_or_else(|| anyhow!("tab {} has no panes", tab_id))?;
+        let domain_id = pane.domain_id();
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {domain_id} of tab {tab_id} not found"))?;
+
+        if let Some(window_id) = domain
+            .move_tab_to_window(tab_id, window_id, workspace_for_new_window.clone())
+            .await?
+        {
+            return Ok(window_id);
+        }
+
+        let window_builder;
+        let window_id = if let Some(window_id) = window_id {
+            let size = {
+                let window = self
+                    .get_window(window_id)
+                    .ok_or_else(|| anyhow!("window_id {} not found on this server", window_id))?;
+                window.get_active().map(|tab| tab.get_size())
+            };
+            if let Some(size) = size {
+                tab.resize(size);
+            }
+            window_id
+        } else {
+            let workspace = workspace_for_new_window.or_else(|| {
+                self.get_window(src_window_id)
+                    .map(|w| w.get_workspace().to_string())
+            });
+            window_builder = self.new_empty_window(workspace, None);
+            *window_builder
+        };
+
+        if let Some(mut src_window) = self.get_window_mut(src_window_id) {
+            if let Some(idx) = src_window.idx_by_id(tab_id) {
+                src_window.remove_by_idx(idx);
+            }
+        }
+
+        self.add_tab_to_window(&tab, window_id)?;
+        if let Some(mut window) = self.get_window_mut(window_id) {
+            if let Some(idx) = window.idx_by_id(tab_id) {
+                window.save_and_then_set_active(idx);
+            }
+        }
+
+        self.prune_dead_windows();
+
+        Ok(window_id)
+    }
+
     pub async fn spawn_tab_or_window(
         &self,
         window_id: Option<WindowId>,
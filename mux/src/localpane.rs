@@ -1,17 +1,18 @@
-use crate::domain::DomainId;
+use crate::domain::{DomainId, LocalDomain, WriterWrapper};
 use crate::pane::{
     CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern, SearchResult,
     WithPaneLines,
 };
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
+use crate::trigger::{HighlightSpan, TriggerScanner};
 use crate::{Domain, Mux, MuxNotification};
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 use async_trait::async_trait;
 use config::keyassignment::ScrollbackEraseMode;
-use config::{configuration, ExitBehavior};
+use config::{configuration, ExitBehavior, ExitBehaviorRestart, TriggerHighlight};
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
-use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty, PtySize};
+use portable_pty::{Child, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize};
 use procinfo::LocalProcessInfo;
 use rangeset::RangeSet;
 use smol::channel::{bounded, Receiver, TryRecvError};
@@ -21,6 +22,7 @@ use std::convert::TryInto;
 use std::io::{Result as IoResult, Write};
 use std::ops::Range;
 use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use termwiz::escape::csi::{Sgr, CSI};
 use termwiz::escape::{Action, DeviceControlMode};
@@ -30,8 +32,8 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, AlertHandler, Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent,
-    SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
+    Alert, AlertHandler, Clipboard, CommandBlock, DownloadHandler, KeyCode, KeyModifiers,
+    MouseEvent, SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
 };
 
 const PROC_INFO_CACHE_TTL: Duration = Duration::from_millis(300);
@@ -48,9 +50,63 @@ enum ProcessState {
     DeadPendingClose {
         killed: bool,
     },
+    /// The program exited and will be spawned again,
+    /// either after a delay or when Enter is pressed
+    PendingRestart {
+        killed: bool,
+        awaiting_enter: bool,
+    },
     Dead,
 }
 
+/// The information needed to respawn the program in a pane
+/// when `exit_behavior = "Restart"`
+struct RestartState {
+    command: CommandBuilder,
+    /// Shared with the terminal, so that its responses
+    /// are sent to the new pty after a restart
+    writer: WriterWrapper,
+    /// The number of consecutive restarts
+    attempts: u32,
+    started: Instant,
+    /// The thread that reads the output of the current program
+    reader_thread: Option<JoinHandle<()>>,
+}
+
+/// What to do once the program in a pane with
+/// `exit_behavior = "Restart"` has exited
+#[derive(Debug, PartialEq, Eq)]
+enum RestartPlan {
+    /// The retry limit has been reached
+    GiveUp,
+    /// Wait for Enter to be pressed in the pane
+    Prompt,
+    /// Restart after the specified delay
+    After(Duration),
+}
+
+impl RestartPlan {
+    /// Decides how to respond to a program that exited after running
+    /// for `ran_for`, having been restarted `attempts` consecutive
+    /// times.  `attempts` is reset if the program ran for long enough
+    /// that it isn't considered to be in a crash loop.
+    fn new(config: &ExitBehaviorRestart, attempts: &mut u32, ran_for: Duration) -> Self {
+        if ran_for >= Duration::from_millis(config.max_delay_ms) {
+            *attempts = 0;
+        }
+        match config.max_retries {
+            Some(max_retries) if *attempts >= max_retries => Self::GiveUp,
+            _ if config.prompt => Self::Prompt,
+            _ => Self::After(config.delay_for_attempt(*attempts)),
+        }
+    }
+}
+
+const EXIT_BEHAVIOR: &str = "This message is shown because \
+    \x1b]8;;https://wezfurlong.org/wezterm/\
+    config/lua/config/exit_behavior.html\
+    \x1b\\exit_behavior\x1b]8;;\x1b\\";
+
 struct CachedProcInfo {
     root: LocalProcessInfo,
     updated: Instant,
@@ -126,6 +182,8 @@ pub struct LocalPane {
     #[cfg(unix)]
     leader: Arc<Mutex<Option<CachedLeaderInfo>>>,
     command_description: String,
+    triggers: Mutex<TriggerScanner>,
+    restart: Mutex<Option<RestartState>>,
 }
 
 #[async_trait(?Send)]
@@ -226,7 +284,8 @@ impl Pane for LocalPane {
                 let _ = signaller.kill();
                 *killed = true;
             }
-            ProcessState::DeadPendingClose { killed } => {
+            ProcessState::DeadPendingClose { killed }
+            | ProcessState::PendingRestart { killed, .. } => {
                 *killed = true;
             }
             _ => {}
@@ -237,11 +296,6 @@ impl Pane for LocalPane {
         let mut proc = self.process.lock();
         let mut notify = None;
 
-        const EXIT_BEHAVIOR: &str = "This message is shown because \
-            \x1b]8;;https://wezfurlong.org/wezterm/\
-            config/lua/config/exit_behavior.html\
-            \x1b\\exit_behavior\x1b]8;;\x1b\\";
-
         match &mut *proc {
             ProcessState::Running {
                 child_waiter,
@@ -291,11 +345,18 @@ impl Pane for LocalPane {
                             *proc = ProcessState::DeadPendingClose { killed: false }
                         }
                         (ExitBehavior::Hold, _, true) => *proc = ProcessState::Dead,
+                        (ExitBehavior::Restart, _, false) => {
+                            let (message, state) = self.plan_restart(&status);
+                            notify = Some(message);
+                            *proc = state;
+                        }
+                        (ExitBehavior::Restart, _, true) => *proc = ProcessState::Dead,
                     }
                     log::debug!("child terminated, new state is {:?}", proc);
                 }
             }
-            ProcessState::DeadPendingClose { killed } => {
+            ProcessState::DeadPendingClose { killed }
+            | ProcessState::PendingRestart { killed, .. } => {
                 if *killed {
                     *proc = ProcessState::Dead;
                     log::debug!("child state -> {:?}", proc);
@@ -311,6 +372,7 @@ impl Pane for LocalPane {
         match &*proc {
             ProcessState::Running { .. } => false,
             ProcessState::DeadPendingClose { .. } => false,
+            ProcessState::PendingRestart { .. } => false,
             ProcessState::Dead => true,
         }
     }
@@ -332,7 +394,14 @@ impl Pane for LocalPane {
     }
 
     fn perform_actions(&self, actions: Vec<termwiz::escape::Action>) {
-        self.terminal.lock().perform_actions(actions)
+        let batch = {
+            let mut terminal = self.terminal.lock();
+            terminal.perform_actions(actions);
+            self.triggers.lock().scan(self.pane_id, &mut terminal)
+        };
+        if let Some(batch) = batch {
+            crate::trigger::queue(batch);
+        }
     }
 
     fn mouse_event(&self, event: MouseEvent) -> Result<(), Error> {
@@ -342,6 +411,14 @@ impl Pane for LocalPane {
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> Result<(), Error> {
         Mux::get().record_input_for_current_identity();
+        if key == KeyCode::Enter && self.is_awaiting_restart_prompt() {
+            let pane_id = self.pane_id;
+            promise::spawn::spawn_into_main_thread(async move {
+                restart_pane(pane_id);
+            })
+            .detach();
+            return Ok(());
+        }
         if self.tmux_domain.lock().is_some() {
             log::error!("key: {:?}", key);
             if key == KeyCode::Char('q') {
@@ -570,6 +647,18 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        self.terminal.lock().get_command_blocks()
+    }
+
+    fn get_marks(&self) -> Vec<StableRowIndex> {
+        self.terminal.lock().get_marks()
+    }
+
+    fn get_badge(&self) -> Option<String> {
+        self.terminal.lock().get_badge()
+    }
+
     async fn search(
         &self,
         pattern: Pattern,
@@ -755,6 +844,38 @@ struct LocalPaneDCSHandler {
     tmux_domain: Option<Arc<TmuxDomainState>>,
 }
 
+/// Respawns the program in the pane, if it is waiting to be restarted
+fn restart_pane(pane_id: PaneId) {
+    let mux = Mux::get();
+    let pane = match mux.get_pane(pane_id) {
+        Some(pane) => pane,
+        None => return,
+    };
+    let local_pane = match pane.downcast_ref::<LocalPane>() {
+        Some(local_pane) => local_pane,
+        None => return,
+    };
+    match local_pane.respawn() {
+        Ok(Some((reader, previous_reader))) => {
+            let reader_thread = crate::start_pane_reader(&pane, None, reader, previous_reader);
+            local_pane.set_reader_thread(reader_thread);
+            mux.notify(MuxNotification::PaneOutput(pane_id));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            log::error!("failed to restart pane {pane_id}: {err:#}");
+            *local_pane.process.lock() = ProcessState::DeadPendingClose { killed: false };
+            emit_output_for_pane(
+                pane_id,
+                &format!(
+                    "\r\n⚠️  Failed to restart {}: {:#}\r\n",
+                    local_pane.command_description, err
+                ),
+            );
+        }
+    }
+}
+
 pub(crate) fn emit_output_for_pane(pane_id: PaneId, message: &str) {
     let mut parser = termwiz::escape::parser::Parser::new();
     let mut actions = vec![Action::CSI(CSI::Sgr(Sgr::Reset))];
@@ -944,7 +1065,213 @@ impl LocalPane {
             #[cfg(unix)]
             leader: Arc::new(Mutex::new(None)),
             command_description,
+            triggers: Mutex::new(TriggerScanner::default()),
+            restart: Mutex::new(None),
+        }
+    }
+
+    /// Allows the program to be respawned in this pane when
+    /// `exit_behavior = "Restart"`.  `writer` must be the writer
+    /// that is shared by the pane and its terminal.
+    pub(crate) fn enable_restart(&self, command: CommandBuilder, writer: WriterWrapper) {
+        self.restart.lock().replace(RestartState {
+            command,
+            writer,
+            attempts: 0,
+            started: Instant::now(),
+            reader_thread: None,
+        });
+    }
+
+    /// Records the thread that reads the output of the program, so
+    /// that a restarted program can wait for it to finish
+    pub(crate) fn set_reader_thread(&self, reader_thread: JoinHandle<()>) {
+        if let Some(restart) = self.restart.lock().as_mut() {
+            restart.reader_thread.replace(reader_thread);
+        }
+    }
+
+    fn is_awaiting_restart_prompt(&self) -> bool {
+        matches!(
+            &*self.process.lock(),
+            ProcessState::PendingRestart {
+                killed: false,
+                awaiting_enter: true
+            }
+        )
+    }
+
+    /// Decides what to do about the program having exited with `status`.
+    /// Returns the message to show in the pane, and the new process state.
+    fn plan_restart(&self, status: &ExitStatus) -> (String, ProcessState) {
+        let config = configuration().exit_behavior_restart;
+        let hold = ProcessState::DeadPendingClose { killed: false };
+
+        let mut restart = self.restart.lock();
+        let restart = match restart.as_mut() {
+            Some(restart) => restart,
+            None => {
+                return (
+                    format!(
+                        "\r\n⚠️  Process {} exited: {}\r\n\
+                         It cannot be restarted in this pane.\r\n{}=\"Restart\"\r\n",
+                        self.command_description, status, EXIT_BEHAVIOR
+                    ),
+                    hold,
+                )
+            }
+        };
+
+        let plan = RestartPlan::new(&config, &mut restart.attempts, restart.started.elapsed());
+
+        let separator = format!(
+            "\r\n\x1b[2m──── Process {} exited: {} ────\x1b[0m\r\n",
+            self.command_description, status
+        );
+
+        let delay = match plan {
+            RestartPlan::GiveUp => {
+                return (
+                    format!(
+                        "{separator}⚠️  Giving up after {} consecutive restarts.\r\n{}=\"Restart\"\r\n",
+                        restart.attempts, EXIT_BEHAVIOR
+                    ),
+                    hold,
+                );
+            }
+            RestartPlan::Prompt => {
+                return (
+                    format!("{separator}Press Enter to restart it.\r\n"),
+                    ProcessState::PendingRestart {
+                        killed: false,
+                        awaiting_enter: true,
+                    },
+                );
+            }
+            RestartPlan::After(delay) => delay,
+        };
+
+        let pane_id = self.pane_id;
+        promise::spawn::spawn_into_main_thread(async move {
+            smol::Timer::after(delay).await;
+            restart_pane(pane_id);
+        })
+        .detach();
+
+        (
+            format!(
+                "{separator}Restarting in {:.1}s (restart {}{}).\r\n",
+                delay.as_secs_f32(),
+                restart.attempts + 1,
+                match config.max_retries {
+                    Some(max) => format!(" of {max}"),
+                    None => String::new(),
+                }
+            ),
+            ProcessState::PendingRestart {
+                killed: false,
+                awaiting_enter: false,
+            },
+        )
+    }
+
+    /// Spawns the program again, replacing the pty.
+    /// Returns the reader for the new pty along with the reader thread
+    /// of the program that it replaces, or None if the pane is no
+    /// longer waiting to be restarted.
+    fn respawn(
+        &self,
+    ) -> anyhow::Result<Option<(Box<dyn std::io::Read + Send>, Option<JoinHandle<()>>)>> {
+        let mut proc = self.process.lock();
+        if !matches!(&*proc, ProcessState::PendingRestart { killed: false, .. }) {
+            return Ok(None);
+        }
+
+        let mut restart = self.restart.lock();
+        let restart = restart
+            .as_mut()
+            .ok_or_else(|| anyhow!("pane {} cannot be restarted", self.pane_id))?;
+
+        let domain = Mux::get()
+            .get_domain(self.domain_id)
+            .ok_or_else(|| anyhow!("domain {} not found", self.domain_id))?;
+        let domain = domain
+            .downcast_ref::<LocalDomain>()
+            .ok_or_else(|| anyhow!("domain {} is not a local domain", self.domain_id))?;
+
+        let size = self.terminal.lock().get_size();
+        let pair = domain.openpty(size)?;
+        let child = pair.slave.spawn_command(restart.command.clone())?;
+        log::trace!("restarted: {:?}", child);
+
+        restart.writer.replace(pair.master.take_writer()?);
+        let reader = pair.master.try_clone_reader()?;
+        *self.pty.lock() = pair.master;
+
+        let (child_waiter, signaller, pid) = split_child(child);
+        *proc = ProcessState::Running {
+            child_waiter,
+            pid,
+            signaller,
+            killed: false,
+        };
+
+        restart.attempts += 1;
+        restart.started = Instant::now();
+        let previous_reader = restart.reader_thread.take();
+
+        // The cached process information refers to the prior pty
+        self.proc_list.lock().take();
+        #[cfg(unix)]
+        self.leader.lock().take();
+
+        Ok(Some((reader, previous_reader)))
+    }
+
+    /// Apply a trigger highlight to the specified cells.
+    /// Spans whose line has changed since it was captured by the
+    /// trigger scanner are skipped, as the matched text may no
+    /// longer be present.
+    /// Returns true if any cells were changed.
+    pub(crate) fn apply_trigger_highlight(
+        &self,
+        spans: &[HighlightSpan],
+        highlight: &TriggerHighlight,
+    ) -> bool {
+        let mut terminal = self.terminal.lock();
+        if terminal.is_alt_screen_active() {
+            return false;
+        }
+        terminal.increment_seqno();
+        let seqno = terminal.current_seqno();
+        let screen = terminal.screen_mut();
+        let mut changed = false;
+        for span in spans {
+            let phys = match screen.stable_row_to_phys(span.row) {
+                Some(phys) => phys,
+                None => continue,
+            };
+            let line = screen.line_mut(phys);
+            if line.current_seqno() != span.seqno {
+                continue;
+            }
+            for cell in line
+                .cells_mut_for_attr_changes_only()
+                .iter_mut()
+                .skip(span.cols.start)
+                .take(span.cols.end - span.cols.start)
+            {
+                highlight.apply(cell.attrs_mut());
+            }
+            line.update_last_change_seqno(seqno);
+            changed = true;
         }
+        changed
+    }
+
+    /// Set a user var in response to a trigger
+    pub(crate) fn set_user_var(&self, name: String, value: String) {
+        self.terminal.lock().set_user_var(name, value);
     }
 
     #[cfg(unix)]
@@ -1066,3 +1393,82 @@ impl Drop for LocalPane {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(max_retries: Option<u32>, prompt: bool) -> ExitBehaviorRestart {
+        ExitBehaviorRestart {
+            max_retries,
+            initial_delay_ms: 100,
+            max_delay_ms: 1000,
+            prompt,
+        }
+    }
+
+    #[test]
+    fn restart_backoff() {
+        let config = config(Some(5), false);
+        let quick = Duration::from_millis(10);
+        let mut attempts = 0;
+        let mut delays = vec![];
+        loop {
+            match RestartPlan::new(&config, &mut attempts, quick) {
+                RestartPlan::After(delay) => delays.push(delay.as_millis()),
+                plan => {
+                    assert_eq!(plan, RestartPlan::GiveUp);
+                    break;
+                }
+            }
+            attempts += 1;
+        }
+        // The delay doubles up to the cap, and we give up
+        // after max_retries consecutive restarts
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000]);
+        assert_eq!(attempts, 5);
+    }
+
+    #[test]
+    fn restart_resets_after_long_run() {
+        let config = config(Some(2), false);
+        let mut attempts = 2;
+        assert_eq!(
+            RestartPlan::new(&config, &mut attempts, Duration::from_millis(999)),
+            RestartPlan::GiveUp
+        );
+        assert_eq!(attempts, 2);
+
+        assert_eq!(
+            RestartPlan::new(&config, &mut attempts, Duration::from_millis(1000)),
+            RestartPlan::After(Duration::from_millis(100))
+        );
+        assert_eq!(attempts, 0);
+    }
+
+    #[test]
+    fn restart_without_limit() {
+        let config = config(None, false);
+        let mut attempts = 1000;
+        assert_eq!(
+            RestartPlan::new(&config, &mut attempts, Duration::ZERO),
+            RestartPlan::After(Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn restart_prompt() {
+        let config = config(Some(1), true);
+        let mut attempts = 0;
+        assert_eq!(
+            RestartPlan::new(&config, &mut attempts, Duration::ZERO),
+            RestartPlan::Prompt
+        );
+        // The limit applies to prompted restarts too
+        attempts = 1;
+        assert_eq!(
+            RestartPlan::new(&config, &mut attempts, Duration::ZERO),
+            RestartPlan::GiveUp
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::domain::DomainId;
 use crate::renderable::*;
+use crate::tab::FloatingPaneRect;
 use crate::Mux;
 use async_trait::async_trait;
 use config::keyassignment::{KeyAssignment, ScrollbackEraseMode};
@@ -17,8 +18,8 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, SemanticZone, StableRowIndex,
-    TerminalConfiguration, TerminalSize,
+    Clipboard, CommandBlock, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, SemanticZone,
+    StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 
 static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
@@ -261,6 +262,9 @@ pub trait Pane: Downcast + Send + Sync {
     /// Called as a hint that the pane is being resized as part of
     /// a zoom-to-fill-all-the-tab-space operation.
     fn set_zoomed(&self, _zoomed: bool) {}
+    /// Called as a hint that this floating pane has been moved,
+    /// resized, shown or hidden.
+    fn set_floating_state(&self, _rect: FloatingPaneRect, _visible: bool) {}
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()>;
     fn key_up(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()>;
     fn perform_assignment(&self, _assignment: &KeyAssignment) -> PerformAssignmentResult {
@@ -318,11 +322,41 @@ pub trait Pane: Downcast + Send + Sync {
         Ok(vec![])
     }
 
+    /// Returns the lines in the specified range, in the same way as
+    /// get_lines.  Panes whose line data is cached locally, such as
+    /// those from a remote mux, may not yet have the requested lines,
+    /// so this fetches them from their source of truth.
+    async fn fetch_lines(
+        &self,
+        lines: Range<StableRowIndex>,
+    ) -> anyhow::Result<(StableRowIndex, Vec<Line>)> {
+        Ok(self.get_lines(lines))
+    }
+
     /// Retrieve the set of semantic zones
     fn get_semantic_zones(&self) -> anyhow::Result<Vec<SemanticZone>> {
         Ok(vec![])
     }
 
+    /// Retrieve the shell commands delimited by OSC 133 semantic
+    /// prompt escapes, in the order that they were run.
+    /// Panes that don't track the exit status of the commands
+    /// return an empty list.
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        vec![]
+    }
+
+    /// Retrieve the rows that were marked by the iTerm2 `SetMark`
+    /// escape sequence, in ascending order
+    fn get_marks(&self) -> Vec<StableRowIndex> {
+        vec![]
+    }
+
+    /// Returns the evaluated iTerm2 badge text for the pane, if any
+    fn get_badge(&self) -> Option<String> {
+        None
+    }
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -0,0 +1,393 @@
+//! Copies the output of panes to a file or to the input of another
+//! program, in the manner of tmux's `pipe-pane`.
+//!
+//! The data is captured by the thread that reads from the pty of the
+//! pane, before it is parsed by the terminal model, so the copy is
+//! an exact record of what the program in the pane produced.
+//! It is handed to a separate thread to be written, so that a slow
+//! file or command cannot stall the pane.
+use crate::pane::PaneId;
+use crate::{Mux, MuxNotification};
+use anyhow::{anyhow, Context};
+use config::keyassignment::PipePaneSpec;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, ControlCode};
+
+/// How many chunks of output may be waiting to be written to a pipe.
+/// If the pipe falls further behind than this, it is stopped.
+const MAX_QUEUED_CHUNKS: usize = 256;
+
+lazy_static::lazy_static! {
+    static ref PIPES: Mutex<HashMap<PaneId, PipeState>> = Mutex::new(HashMap::new());
+}
+
+static PIPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+enum PipeState {
+    /// The output is being copied by this process
+    Local(LocalPipe),
+    /// The output is being copied by the mux server that hosts the pane
+    Remote,
+}
+
+/// Sends output to the thread that writes it to the pipe.
+/// The thread exits once this is dropped.
+struct LocalPipe {
+    /// Distinguishes this pipe from any that replace it
+    id: usize,
+    tx: SyncSender<Vec<u8>>,
+}
+
+struct PanePipe {
+    writer: Box<dyn Write + Send>,
+    child: Option<Child>,
+    /// Present when escape sequences are being stripped from the output
+    parser: Option<Parser>,
+    timestamps: bool,
+    at_line_start: bool,
+}
+
+impl PanePipe {
+    fn new(spec: &PipePaneSpec) -> anyhow::Result<Self> {
+        let (writer, child): (Box<dyn Write + Send>, Option<Child>) =
+            match (&spec.file, &spec.command) {
+                (Some(path), None) => {
+                    let file = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .with_context(|| format!("opening {} for writing", path))?;
+                    (Box::new(file), None)
+                }
+                (None, Some(argv)) => {
+                    let (prog, args) = argv
+                        .split_first()
+                        .ok_or_else(|| anyhow!("the command to pipe to must not be empty"))?;
+                    let mut child = Command::new(prog)
+                        .args(args)
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn()
+                        .with_context(|| format!("spawning {:?}", argv))?;
+                    let stdin = child
+                        .stdin
+                        .take()
+                        .ok_or_else(|| anyhow!("failed to capture stdin of {:?}", argv))?;
+                    (Box::new(stdin), Some(child))
+                }
+                _ => anyhow::bail!("exactly one of `file` or `command` must be specified"),
+            };
+
+        Ok(Self {
+            writer,
+            child,
+            parser: if spec.strip_escapes {
+                Some(Parser::new())
+            } else {
+                None
+            },
+            timestamps: spec.timestamps,
+            at_line_start: true,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let stripped;
+        let data = match self.parser.as_mut() {
+            Some(parser) => {
+                // Keep only the printable text and the whitespace
+                // controls, the same as the strip-ansi-escapes utility
+                let mut text = String::new();
+                parser.parse(data, |action| match action {
+                    Action::Print(c) => text.push(c),
+                    Action::PrintString(s) => text.push_str(&s),
+                    Action::Control(
+                        c @ (ControlCode::HorizontalTab
+                        | ControlCode::LineFeed
+                        | ControlCode::CarriageReturn),
+                    ) => text.push(c as u8 as char),
+                    _ => {}
+                });
+                stripped = text;
+                stripped.as_bytes()
+            }
+            None => data,
+        };
+
+        if !self.timestamps {
+            return self.writer.write_all(data);
+        }
+
+        let mut output = Vec::with_capacity(data.len());
+        for line in data.split_inclusive(|&b| b == b'\n') {
+            if self.at_line_start {
+                let stamp = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S%.3f] ");
+                output.extend_from_slice(stamp.to_string().as_bytes());
+            }
+            output.extend_from_slice(line);
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        self.writer.write_all(&output)
+    }
+}
+
+impl Drop for PanePipe {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            // Replace the writer so that the child sees EOF on its stdin,
+            // then reap it without blocking the caller
+            self.writer = Box::new(std::io::sink());
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+/// Writes the output that is received from rx to the pipe until
+/// either the pipe fails or it is stopped
+fn run_pipe(pane_id: PaneId, id: usize, mut pipe: PanePipe, rx: Receiver<Vec<u8>>) {
+    while let Ok(data) = rx.recv() {
+        if let Err(err) = pipe.write(&data) {
+            log::error!("pipe for pane {} failed, stopping it: {:#}", pane_id, err);
+            stop_failed(pane_id, id);
+            return;
+        }
+    }
+}
+
+/// Removes the pipe identified by id and lets the gui and any mux
+/// clients know that it has stopped.  Does nothing if the pipe has
+/// already been stopped or replaced by another pipe.
+fn stop_failed(pane_id: PaneId, id: usize) {
+    let mut pipes = PIPES.lock();
+    if !matches!(pipes.get(&pane_id), Some(PipeState::Local(p)) if p.id == id) {
+        return;
+    }
+    pipes.remove(&pane_id);
+    drop(pipes);
+    if Mux::try_get().is_some() {
+        Mux::notify_from_any_thread(MuxNotification::PanePipeStopped(pane_id));
+    }
+}
+
+/// Starts copying the output of pane_id as described by spec,
+/// replacing any pipe that was already active for that pane.
+/// This only has an effect for panes whose pty is read by this process.
+pub(crate) fn start(pane_id: PaneId, spec: &PipePaneSpec) -> anyhow::Result<()> {
+    start_pipe(pane_id, PanePipe::new(spec)?)
+}
+
+fn start_pipe(pane_id: PaneId, pipe: PanePipe) -> anyhow::Result<()> {
+    let id = PIPE_ID.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = sync_channel(MAX_QUEUED_CHUNKS);
+    std::thread::Builder::new()
+        .name(format!("pipe-pane-{pane_id}"))
+        .spawn(move || run_pipe(pane_id, id, pipe, rx))
+        .context("spawning pipe thread")?;
+    PIPES
+        .lock()
+        .insert(pane_id, PipeState::Local(LocalPipe { id, tx }));
+    Ok(())
+}
+
+/// Records that the output of pane_id is being copied by the
+/// mux server that hosts it
+pub(crate) fn set_remote(pane_id: PaneId) {
+    PIPES.lock().insert(pane_id, PipeState::Remote);
+}
+
+/// Called when the mux server that hosts pane_id advises that
+/// its pipe has stopped
+pub fn remote_stopped(pane_id: PaneId) {
+    let mut pipes = PIPES.lock();
+    if matches!(pipes.get(&pane_id), Some(PipeState::Remote)) {
+        pipes.remove(&pane_id);
+    }
+}
+
+/// Stops copying the output of pane_id.
+/// Returns true if a pipe was active.
+pub(crate) fn stop(pane_id: PaneId) -> bool {
+    PIPES.lock().remove(&pane_id).is_some()
+}
+
+/// Returns true if the output of pane_id is being copied
+pub fn is_piped(pane_id: PaneId) -> bool {
+    PIPES.lock().contains_key(&pane_id)
+}
+
+/// Called by the pty reader thread with each chunk of output from
+/// pane_id.  This never blocks: if the pipe can't keep up with the
+/// output it is stopped, as is a pipe that has failed, for example
+/// because the program that it was feeding has exited.
+pub(crate) fn write_output(pane_id: PaneId, data: &[u8]) {
+    let (id, result) = match PIPES.lock().get(&pane_id) {
+        Some(PipeState::Local(pipe)) => (pipe.id, pipe.tx.try_send(data.to_vec())),
+        _ => return,
+    };
+
+    match result {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            log::error!(
+                "pipe for pane {} can't keep up with the output, stopping it",
+                pane_id
+            );
+            stop_failed(pane_id, id);
+        }
+        Err(TrySendError::Disconnected(_)) => {
+            // The pipe failed, and the writer thread has already
+            // removed it or is about to
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn pipe(buf: &SharedBuf, strip_escapes: bool, timestamps: bool) -> PanePipe {
+        PanePipe {
+            writer: Box::new(buf.clone()),
+            child: None,
+            parser: if strip_escapes {
+                Some(Parser::new())
+            } else {
+                None
+            },
+            timestamps,
+            at_line_start: true,
+        }
+    }
+
+    #[test]
+    fn strip_escapes() {
+        let buf = SharedBuf::default();
+        let mut p = pipe(&buf, false, false);
+        p.write(b"\x1b[31mred\x1b[0m\r\n").unwrap();
+        assert_eq!(&*buf.0.lock(), b"\x1b[31mred\x1b[0m\r\n");
+
+        let buf = SharedBuf::default();
+        let mut p = pipe(&buf, true, false);
+        p.write(b"\x1b[31mred\x1b[0m\tt").unwrap();
+        p.write(b"ext\x07\r\n").unwrap();
+        assert_eq!(&*buf.0.lock(), b"red\ttext\r\n");
+    }
+
+    #[test]
+    fn timestamps() {
+        let buf = SharedBuf::default();
+        let mut p = pipe(&buf, false, true);
+        p.write(b"one\ntw").unwrap();
+        p.write(b"o\nthree").unwrap();
+        let text = String::from_utf8(buf.0.lock().clone()).unwrap();
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert_eq!(lines.len(), 3);
+        for (line, expected) in lines.iter().zip(["one", "two", "three"]) {
+            // "[YYYY-mm-dd HH:MM:SS.sss] "
+            assert_eq!(line.len(), 26 + expected.len(), "{:?}", line);
+            assert!(line.starts_with('['), "{:?}", line);
+            assert!(line.ends_with(&format!("] {}", expected)), "{:?}", line);
+        }
+    }
+
+    fn wait_for(buf: &SharedBuf, expected: &[u8]) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while &*buf.0.lock() != expected {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "timed out waiting for {:?}, have {:?}",
+                expected,
+                buf.0.lock()
+            );
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn output_is_written_by_pipe_thread() {
+        let pane_id = 1000;
+        let buf = SharedBuf::default();
+        start_pipe(pane_id, pipe(&buf, false, false)).unwrap();
+        assert!(is_piped(pane_id));
+
+        write_output(pane_id, b"hello ");
+        write_output(pane_id, b"world");
+        assert!(stop(pane_id));
+        assert!(!is_piped(pane_id));
+
+        // What was queued before the pipe was stopped is still written
+        wait_for(&buf, b"hello world");
+    }
+
+    #[test]
+    fn overflow_stops_pipe() {
+        let pane_id = 1001;
+        // Nothing drains this channel, so it fills up
+        let (tx, _rx) = sync_channel(MAX_QUEUED_CHUNKS);
+        PIPES
+            .lock()
+            .insert(pane_id, PipeState::Local(LocalPipe { id: 42, tx }));
+
+        for _ in 0..MAX_QUEUED_CHUNKS {
+            write_output(pane_id, b"data");
+        }
+        assert!(is_piped(pane_id));
+
+        write_output(pane_id, b"one too many");
+        assert!(!is_piped(pane_id));
+    }
+
+    #[test]
+    fn remote_pipe_state() {
+        let pane_id = 1002;
+        set_remote(pane_id);
+        assert!(is_piped(pane_id));
+        remote_stopped(pane_id);
+        assert!(!is_piped(pane_id));
+
+        // A notification about a remote pipe doesn't affect a local one
+        let buf = SharedBuf::default();
+        start_pipe(pane_id, pipe(&buf, false, false)).unwrap();
+        remote_stopped(pane_id);
+        assert!(is_piped(pane_id));
+        stop(pane_id);
+    }
+
+    #[test]
+    fn stale_failure_keeps_replacement() {
+        let pane_id = 1003;
+        let (tx, _rx) = sync_channel(MAX_QUEUED_CHUNKS);
+        PIPES
+            .lock()
+            .insert(pane_id, PipeState::Local(LocalPipe { id: 7, tx }));
+
+        // A failure reported by the pipe that this one replaced
+        stop_failed(pane_id, 6);
+        assert!(is_piped(pane_id));
+
+        stop_failed(pane_id, 7);
+        assert!(!is_piped(pane_id));
+    }
+}
@@ -1,10 +1,11 @@
 use crate::domain::DomainId;
+use crate::layout::LayoutNode;
 use crate::pane::*;
 use crate::renderable::StableCursorPosition;
 use crate::{Mux, MuxNotification, WindowId};
 use bintree::PathBranch;
 use config::configuration;
-use config::keyassignment::PaneDirection;
+use config::keyassignment::{PaneDirection, PaneLayout};
 use parking_lot::Mutex;
 use rangeset::intersects_range;
 use serde::{Deserialize, Serialize};
@@ -12,6 +13,7 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 use std::sync::Arc;
 use url::Url;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_term::{StableRowIndex, TerminalSize};
 
 pub type Tree = bintree::Tree<Arc<dyn Pane>, SplitDirectionAndSize>;
@@ -37,6 +39,11 @@ impl Recency {
     }
 }
 
+struct FloatingPane {
+    pane: Arc<dyn Pane>,
+    rect: FloatingPaneRect,
+}
+
 struct TabInner {
     id: TabId,
     pane: Option<Tree>,
@@ -46,6 +53,15 @@ struct TabInner {
     zoomed: Option<Arc<dyn Pane>>,
     title: String,
     recency: Recency,
+    /// Panes that are layered above the tiled panes,
+    /// ordered from bottom to top
+    floating: Vec<FloatingPane>,
+    floating_visible: bool,
+    /// Whether the topmost floating pane has the focus
+    /// rather than the active tiled pane
+    floating_focused: bool,
+    /// The most recently selected predefined layout
+    layout: Option<PaneLayout>,
 }
 
 /// A Tab is a container of Panes
@@ -62,6 +78,8 @@ pub struct PositionedPane {
     pub is_active: bool,
     /// true if this pane is zoomed
     pub is_zoomed: bool,
+    /// true if this pane is floating above the tiled panes
+    pub is_floating: bool,
     /// The offset from the top left corner of the containing tab to the top
     /// left corner of this pane, in cells.
     pub left: usize,
@@ -83,6 +101,7 @@ impl std::fmt::Debug for PositionedPane {
         fmt.debug_struct("PositionedPane")
             .field("index", &self.index)
             .field("is_active", &self.is_active)
+            .field("is_floating", &self.is_floating)
             .field("left", &self.left)
             .field("top", &self.top)
             .field("width", &self.width)
@@ -92,7 +111,62 @@ impl std::fmt::Debug for PositionedPane {
     }
 }
 
+/// The position and size of a floating pane, in cells, relative to
+/// the top left corner of the containing tab.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FloatingPaneRect {
+    pub left: usize,
+    pub top: usize,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl FloatingPaneRect {
+    /// Returns a rect that is centered in a tab of the specified size,
+    /// and that covers `percent` of its width and height.
+    /// The rect is at least one cell in size, but no larger than the tab.
+    pub fn centered(size: &TerminalSize, percent: u8) -> Self {
+        let percent = percent.clamp(10, 100) as usize;
+        let cols = (size.cols * percent / 100).clamp(1, size.cols.max(1));
+        let rows = (size.rows * percent / 100).clamp(1, size.rows.max(1));
+        Self {
+            left: size.cols.saturating_sub(cols) / 2,
+            top: size.rows.saturating_sub(rows) / 2,
+            cols,
+            rows,
+        }
+    }
+
+    /// Returns a copy of this rect that has been moved and, if
+    /// necessary, shrunk so that it fits in a tab of the specified size
+    pub fn clamp_to(&self, size: &TerminalSize) -> Self {
+        let cols = self.cols.clamp(1, size.cols.max(1));
+        let rows = self.rows.clamp(1, size.rows.max(1));
+        Self {
+            left: self.left.min(size.cols.saturating_sub(cols)),
+            top: self.top.min(size.rows.saturating_sub(rows)),
+            cols,
+            rows,
+        }
+    }
+
+    /// Returns the dimensions of a pane occupying this rect
+    /// in a tab of the specified size
+    pub fn terminal_size(&self, tab_size: &TerminalSize) -> TerminalSize {
+        let cell_dims = cell_dimensions(tab_size);
+        TerminalSize {
+            rows: self.rows,
+            cols: self.cols,
+            pixel_width: cell_dims.pixel_width * self.cols,
+            pixel_height: cell_dims.pixel_height * self.rows,
+            dpi: cell_dims.dpi,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
 pub enum SplitDirection {
     Horizontal,
     Vertical,
@@ -252,33 +326,53 @@ fn pane_tree(
                 node: data,
             }
         }
-        Tree::Leaf(pane) => {
-            let dims = pane.get_dimensions();
-            let working_dir = pane.get_current_working_dir();
-            let cursor_pos = pane.get_cursor_position();
+        Tree::Leaf(pane) => PaneNode::Leaf(pane_entry(
+            pane,
+            tab_id,
+            window_id,
+            is_pane(pane, &active),
+            is_pane(pane, &zoomed),
+            workspace,
+            left_col,
+            top_row,
+        )),
+    }
+}
 
-            PaneNode::Leaf(PaneEntry {
-                window_id,
-                tab_id,
-                pane_id: pane.pane_id(),
-                title: pane.get_title(),
-                is_active_pane: is_pane(pane, &active),
-                is_zoomed_pane: is_pane(pane, &zoomed),
-                size: TerminalSize {
-                    cols: dims.cols,
-                    rows: dims.viewport_rows,
-                    pixel_height: dims.pixel_height,
-                    pixel_width: dims.pixel_width,
-                    dpi: dims.dpi,
-                },
-                working_dir: working_dir.map(Into::into),
-                workspace: workspace.to_string(),
-                cursor_pos,
-                physical_top: dims.physical_top,
-                left_col,
-                top_row,
-            })
-        }
+fn pane_entry(
+    pane: &Arc<dyn Pane>,
+    tab_id: TabId,
+    window_id: WindowId,
+    is_active_pane: bool,
+    is_zoomed_pane: bool,
+    workspace: &str,
+    left_col: usize,
+    top_row: usize,
+) -> PaneEntry {
+    let dims = pane.get_dimensions();
+    let working_dir = pane.get_current_working_dir();
+    let cursor_pos = pane.get_cursor_position();
+
+    PaneEntry {
+        window_id,
+        tab_id,
+        pane_id: pane.pane_id(),
+        title: pane.get_title(),
+        is_active_pane,
+        is_zoomed_pane,
+        size: TerminalSize {
+            cols: dims.cols,
+            rows: dims.viewport_rows,
+            pixel_height: dims.pixel_height,
+            pixel_width: dims.pixel_width,
+            dpi: dims.dpi,
+        },
+        working_dir: working_dir.map(Into::into),
+        workspace: workspace.to_string(),
+        cursor_pos,
+        physical_top: dims.physical_top,
+        left_col,
+        top_row,
     }
 }
 
@@ -554,6 +648,61 @@ impl Tab {
         self.inner.lock().codec_pane_tree()
     }
 
+    /// Like sync_with_pane_tree, but for the floating panes
+    /// of the remote tab
+    pub fn sync_with_floating_panes<F>(&self, floating: FloatingPaneNodes, make_pane: F)
+    where
+        F: FnMut(PaneEntry) -> Arc<dyn Pane>,
+    {
+        self.inner
+            .lock()
+            .sync_with_floating_panes(floating, make_pane)
+    }
+
+    pub fn codec_floating_panes(&self) -> FloatingPaneNodes {
+        self.inner.lock().codec_floating_panes()
+    }
+
+    /// Adds a pane that floats above the tiled panes of this tab,
+    /// and gives it the focus.  The floating panes are made visible
+    /// if they were hidden.
+    pub fn insert_floating_pane(&self, pane: Arc<dyn Pane>, rect: FloatingPaneRect) {
+        self.inner.lock().insert_floating_pane(pane, rect)
+    }
+
+    /// Returns the floating panes, ordered from bottom to top,
+    /// along with their positioning information.
+    /// This includes the floating panes even when they are hidden.
+    pub fn iter_floating_panes(&self) -> Vec<PositionedPane> {
+        self.inner.lock().iter_floating_panes()
+    }
+
+    pub fn floating_panes_visible(&self) -> bool {
+        self.inner.lock().floating_visible
+    }
+
+    /// Shows or hides all of the floating panes of this tab.
+    /// Showing them gives the focus to the topmost floating pane,
+    /// while hiding them returns it to the active tiled pane.
+    pub fn set_floating_panes_visible(&self, visible: bool) {
+        self.inner.lock().set_floating_panes_visible(visible)
+    }
+
+    pub fn get_floating_pane_rect(&self, pane_id: PaneId) -> Option<FloatingPaneRect> {
+        self.inner
+            .lock()
+            .floating
+            .iter()
+            .find(|f| f.pane.pane_id() == pane_id)
+            .map(|f| f.rect)
+    }
+
+    /// Moves and/or resizes a floating pane.
+    /// Returns false if pane_id isn't a floating pane in this tab.
+    pub fn set_floating_pane_rect(&self, pane_id: PaneId, rect: FloatingPaneRect) -> bool {
+        self.inner.lock().set_floating_pane_rect(pane_id, rect)
+    }
+
     /// Returns a count of how many panes are in this tab
     pub fn count_panes(&self) -> Option<usize> {
         self.inner.try_lock().map(|mut inner| inner.count_panes())
@@ -588,6 +737,36 @@ impl Tab {
         self.inner.lock().rotate_clockwise()
     }
 
+    /// Rearranges the tiled panes according to one of
+    /// the predefined layouts
+    pub fn select_layout(&self, layout: PaneLayout) {
+        self.inner.lock().select_layout(layout)
+    }
+
+    /// Selects the layout that follows the most recently
+    /// selected layout, returning the newly selected layout
+    pub fn cycle_layouts(&self) -> PaneLayout {
+        let mut inner = self.inner.lock();
+        let layout = match inner.layout {
+            Some(layout) => layout.next(),
+            None => PaneLayout::CYCLE[0],
+        };
+        inner.select_layout(layout);
+        layout
+    }
+
+    /// Rearranges the tiled panes according to layout, filling its
+    /// slots with the panes in their current order.
+    /// The layout must have exactly as many slots as there are panes.
+    pub fn apply_layout(&self, layout: &LayoutNode) -> anyhow::Result<()> {
+        self.inner.lock().apply_layout(layout)
+    }
+
+    /// Describes the current arrangement of the tiled panes
+    pub fn get_layout(&self) -> LayoutNode {
+        self.inner.lock().get_layout()
+    }
+
     pub fn iter_splits(&self) -> Vec<PositionedSplit> {
         self.inner.lock().iter_splits()
     }
@@ -717,6 +896,20 @@ impl Tab {
         self.inner.lock().swap_active_with_index(pane_index)
     }
 
+    /// Exchange the positions of two panes that are both in this tab.
+    /// Returns false if either pane is not part of this tab.
+    pub fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> bool {
+        self.inner.lock().swap_panes(pane_id, other_pane_id)
+    }
+
+    /// Puts `pane` in the place occupied by the pane with the specified
+    /// pane_id, returning the pane that was replaced.
+    /// The replaced pane is still live in the mux; the intent is for
+    /// it to be placed into a different tab.
+    pub fn replace_pane(&self, pane_id: PaneId, pane: &Arc<dyn Pane>) -> Option<Arc<dyn Pane>> {
+        self.inner.lock().replace_pane(pane_id, pane)
+    }
+
     /// Computes the size of the pane that would result if the specified
     /// pane was split in a particular direction.
     /// The intent is to call this prior to spawning the new pane so that
@@ -757,6 +950,10 @@ impl TabInner {
             zoomed: None,
             title: String::new(),
             recency: Recency::default(),
+            floating: vec![],
+            floating_visible: true,
+            floating_focused: false,
+            layout: None,
         }
     }
 
@@ -833,7 +1030,7 @@ impl TabInner {
             }
         };
 
-        let active = self.get_active_pane();
+        let active = self.get_active_tiled_pane();
         let zoomed = self.zoomed.as_ref();
         if let Some(root) = self.pane.as_ref() {
             pane_tree(
@@ -886,7 +1083,7 @@ impl TabInner {
         if self.zoomed.take().is_some() {
             // We were zoomed, but now we are not.
             // Re-apply the size to the panes
-            if let Some(pane) = self.get_active_pane() {
+            if let Some(pane) = self.get_active_tiled_pane() {
                 pane.set_zoomed(false);
             }
             self.size = self.size_before_zoom;
@@ -895,7 +1092,7 @@ impl TabInner {
             // We weren't zoomed, but now we want to zoom.
             // Locate the active pane
             self.size_before_zoom = size;
-            if let Some(pane) = self.get_active_pane() {
+            if let Some(pane) = self.get_active_tiled_pane() {
                 pane.set_zoomed(true);
                 pane.resize(size).ok();
                 self.zoomed.replace(pane);
@@ -912,6 +1109,9 @@ impl TabInner {
                 Tree::Leaf(p) => p.pane_id() == pane,
             }
         }
+        if self.floating.iter().any(|f| f.pane.pane_id() == pane) {
+            return true;
+        }
         match &self.pane {
             Some(root) => contains(root, pane),
             None => false,
@@ -993,6 +1193,73 @@ impl TabInner {
         Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
     }
 
+    fn get_layout(&mut self) -> LayoutNode {
+        let size = self.size;
+        match self.pane.as_ref() {
+            Some(tree) => LayoutNode::from_tree(tree, &size),
+            None => LayoutNode::pane(1),
+        }
+    }
+
+    fn select_layout(&mut self, layout: PaneLayout) {
+        self.set_zoomed(false);
+        let spec = match layout {
+            PaneLayout::Rebalance => {
+                let mut spec = self.get_layout();
+                spec.rebalance();
+                spec
+            }
+            _ => LayoutNode::for_layout(layout, self.iter_panes_ignoring_zoom().len()),
+        };
+        match self.apply_layout(&spec) {
+            Ok(()) => {
+                self.layout.replace(layout);
+            }
+            Err(err) => log::error!("failed to apply layout {:?}: {:#}", layout, err),
+        }
+    }
+
+    fn apply_layout(&mut self, layout: &LayoutNode) -> anyhow::Result<()> {
+        self.set_zoomed(false);
+        let panes: Vec<Arc<dyn Pane>> = self
+            .iter_panes_ignoring_zoom()
+            .into_iter()
+            .map(|p| p.pane)
+            .collect();
+        if panes.is_empty() {
+            return Ok(());
+        }
+        if layout.num_panes() != panes.len() {
+            anyhow::bail!(
+                "the layout has {} panes, but the tab has {}",
+                layout.num_panes(),
+                panes.len()
+            );
+        }
+
+        let active_id = panes.get(self.active).map(|p| p.pane_id());
+        let size = self.size;
+        let cell_dims = self.cell_dimensions();
+        let tree = layout.build_tree(&mut panes.into_iter(), &size, &cell_dims);
+        apply_sizes_from_splits(&tree, &size);
+        self.pane.replace(tree);
+
+        // The panes may now be in a different order, so
+        // find the active pane again
+        if let Some(idx) = self
+            .iter_panes_ignoring_zoom()
+            .iter()
+            .position(|p| Some(p.pane.pane_id()) == active_id)
+        {
+            self.active = idx;
+        }
+        self.recency = Recency::default();
+        self.recency.tag(self.active);
+
+        Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+        Ok(())
+    }
+
     fn iter_panes_impl(&mut self, respect_zoom_state: bool) -> Vec<PositionedPane> {
         let mut panes = vec![];
 
@@ -1001,8 +1268,9 @@ impl TabInner {
                 let size = self.size;
                 panes.push(PositionedPane {
                     index: 0,
-                    is_active: true,
+                    is_active: !self.floating_has_focus(),
                     is_zoomed: true,
+                    is_floating: false,
                     left: 0,
                     top: 0,
                     width: size.cols.into(),
@@ -1015,7 +1283,12 @@ impl TabInner {
             }
         }
 
-        let active_idx = self.active;
+        let active_idx = if self.floating_has_focus() {
+            // None of the tiled panes are active
+            usize::MAX
+        } else {
+            self.active
+        };
         let zoomed_id = self.zoomed.as_ref().map(|p| p.pane_id());
         let root_size = self.size;
         let mut cursor = self.pane.take().unwrap().cursor();
@@ -1049,6 +1322,7 @@ impl TabInner {
                     index,
                     is_active: index == active_idx,
                     is_zoomed: zoomed_id == Some(pane.pane_id()),
+                    is_floating: false,
                     left,
                     top,
                     width: dims.cols as _,
@@ -1172,6 +1446,8 @@ impl TabInner {
             apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
         }
 
+        self.clamp_floating_panes();
+
         Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
     }
 
@@ -1557,6 +1833,115 @@ impl TabInner {
             .is_empty()
     }
 
+    fn swap_panes(&mut self, pane_id: PaneId, other_pane_id: PaneId) -> bool {
+        let pane = self.get_pane_by_id(pane_id);
+        let other = self.get_pane_by_id(other_pane_id);
+        let (pane, other) = match (pane, other) {
+            (Some(pane), Some(other)) => (pane, other),
+            _ => return false,
+        };
+        let prior = self.get_active_pane();
+        let replaced = self.map_panes(|p| {
+            if p.pane_id() == pane_id {
+                Some(Arc::clone(&other))
+            } else if p.pane_id() == other_pane_id {
+                Some(Arc::clone(&pane))
+            } else {
+                None
+            }
+        });
+        self.advise_focus_change(prior);
+        !replaced.is_empty()
+    }
+
+    fn replace_pane(&mut self, pane_id: PaneId, pane: &Arc<dyn Pane>) -> Option<Arc<dyn Pane>> {
+        let prior = self.get_active_pane();
+        let replaced = self
+            .map_panes(|p| {
+                if p.pane_id() == pane_id {
+                    Some(Arc::clone(pane))
+                } else {
+                    None
+                }
+            })
+            .pop();
+        if let Some(prior) = prior {
+            if prior.pane_id() == pane_id {
+                // The focus stays in the same place in the layout,
+                // but it is now a different pane
+                prior.focus_changed(false);
+                self.advise_focus_change(None);
+            } else {
+                self.advise_focus_change(Some(prior));
+            }
+        }
+        replaced
+    }
+
+    fn get_pane_by_id(&mut self, pane_id: PaneId) -> Option<Arc<dyn Pane>> {
+        if let Some(f) = self.floating.iter().find(|f| f.pane.pane_id() == pane_id) {
+            return Some(Arc::clone(&f.pane));
+        }
+        self.iter_panes_ignoring_zoom()
+            .into_iter()
+            .find(|p| p.pane.pane_id() == pane_id)
+            .map(|p| p.pane)
+    }
+
+    /// Replaces each pane for which `f` returns a substitute, keeping
+    /// the layout intact, and resizes the substitutes to fit the slot
+    /// that they now occupy.  Returns the panes that were replaced.
+    fn map_panes<F>(&mut self, f: F) -> Vec<Arc<dyn Pane>>
+    where
+        F: Fn(&Arc<dyn Pane>) -> Option<Arc<dyn Pane>>,
+    {
+        let mut replaced = vec![];
+
+        if let Some(zoomed) = self.zoomed.as_mut() {
+            if let Some(new_pane) = f(zoomed) {
+                *zoomed = new_pane;
+            }
+        }
+
+        let mut cursor = self.pane.take().unwrap().cursor();
+        loop {
+            if cursor.is_leaf() {
+                let leaf = cursor.leaf_mut().unwrap();
+                if let Some(mut new_pane) = f(leaf) {
+                    std::mem::swap(leaf, &mut new_pane);
+                    replaced.push(new_pane);
+                }
+            }
+            match cursor.preorder_next() {
+                Ok(c) => cursor = c,
+                Err(c) => {
+                    self.pane.replace(c.tree());
+                    break;
+                }
+            }
+        }
+
+        let size = self.size;
+        for floating in self.floating.iter_mut() {
+            if let Some(new_pane) = f(&floating.pane) {
+                new_pane.resize(floating.rect.terminal_size(&size)).ok();
+                replaced.push(std::mem::replace(&mut floating.pane, new_pane));
+            }
+        }
+
+        if !replaced.is_empty() {
+            match self.zoomed.as_ref() {
+                Some(zoomed) => {
+                    zoomed.resize(size).ok();
+                }
+                None => apply_sizes_from_splits(self.pane.as_ref().unwrap(), &size),
+            }
+            Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+        }
+
+        replaced
+    }
+
     fn kill_panes_in_domain(&mut self, domain: DomainId) -> bool {
         !self
             .remove_pane_if(|_, pane| pane.domain_id() == domain, true)
@@ -1666,6 +2051,25 @@ impl TabInner {
             self.active = active_idx.saturating_sub(removed_indices.len());
         }
 
+        // Floating panes are indexed after the tiled panes
+        let tiled_count = self.iter_panes_ignoring_zoom().len();
+        let mut floating_index = tiled_count;
+        let mut removed_floating = false;
+        self.floating.retain(|floating| {
+            let index = floating_index;
+            floating_index += 1;
+            if f(index, &floating.pane) {
+                dead_panes.push(Arc::clone(&floating.pane));
+                removed_floating = true;
+                false
+            } else {
+                true
+            }
+        });
+        if removed_floating && self.floating.is_empty() {
+            self.floating_focused = false;
+        }
+
         if !dead_panes.is_empty() && kill {
             let to_kill: Vec<_> = dead_panes.iter().map(|p| p.pane_id()).collect();
             promise::spawn::spawn_into_main_thread(async move {
@@ -1680,7 +2084,8 @@ impl TabInner {
     }
 
     fn can_close_without_prompting(&mut self, reason: CloseReason) -> bool {
-        let panes = self.iter_panes_ignoring_zoom();
+        let mut panes = self.iter_panes_ignoring_zoom();
+        panes.append(&mut self.iter_floating_panes());
         for pos in &panes {
             if !pos.pane.can_close_without_prompting(reason) {
                 return false;
@@ -1703,6 +2108,15 @@ impl TabInner {
     }
 
     fn get_active_pane(&mut self) -> Option<Arc<dyn Pane>> {
+        if self.floating_has_focus() {
+            return self.floating.last().map(|f| Arc::clone(&f.pane));
+        }
+        self.get_active_tiled_pane()
+    }
+
+    /// Returns the active pane from the tiled layout, ignoring
+    /// any floating pane that may have the focus
+    fn get_active_tiled_pane(&mut self) -> Option<Arc<dyn Pane>> {
         if let Some(zoomed) = self.zoomed.as_ref() {
             return Some(Arc::clone(zoomed));
         }
@@ -1717,31 +2131,53 @@ impl TabInner {
         self.active
     }
 
+    fn floating_has_focus(&self) -> bool {
+        self.floating_focused && self.floating_visible && !self.floating.is_empty()
+    }
+
     fn set_active_pane(&mut self, pane: &Arc<dyn Pane>) {
-        if let Some(item) = self
+        if let Some(idx) = self
+            .floating
+            .iter()
+            .position(|f| f.pane.pane_id() == pane.pane_id())
+        {
+            let prior = self.get_active_pane();
+            self.raise_floating_pane(idx);
+            self.advise_focus_change(prior);
+        } else if let Some(item) = self
             .iter_panes_ignoring_zoom()
             .iter()
             .find(|p| p.pane.pane_id() == pane.pane_id())
         {
             let prior = self.get_active_pane();
+            self.floating_focused = false;
             self.active = item.index;
             self.recency.tag(item.index);
             self.advise_focus_change(prior);
         }
     }
 
+    /// Moves the floating pane at idx to the top of the stack
+    /// and gives it the focus
+    fn raise_floating_pane(&mut self, idx: usize) {
+        let floating = self.floating.remove(idx);
+        self.floating.push(floating);
+        self.floating_visible = true;
+        self.floating_focused = true;
+    }
+
     fn advise_focus_change(&mut self, prior: Option<Arc<dyn Pane>>) {
-        let mux = Mux::get();
+        let mux = Mux::try_get();
         let current = self.get_active_pane();
         match (prior, current) {
             (Some(prior), Some(current)) if prior.pane_id() != current.pane_id() => {
                 prior.focus_changed(false);
                 current.focus_changed(true);
-                mux.notify(MuxNotification::PaneFocused(current.pane_id()));
+                mux.map(|mux| mux.notify(MuxNotification::PaneFocused(current.pane_id())));
             }
             (None, Some(current)) => {
                 current.focus_changed(true);
-                mux.notify(MuxNotification::PaneFocused(current.pane_id()));
+                mux.map(|mux| mux.notify(MuxNotification::PaneFocused(current.pane_id())));
             }
             (Some(prior), None) => {
                 prior.focus_changed(false);
@@ -1754,9 +2190,164 @@ impl TabInner {
 
     fn set_active_idx(&mut self, pane_index: usize) {
         let prior = self.get_active_pane();
-        self.active = pane_index;
-        self.recency.tag(pane_index);
+        let tiled_count = self.count_panes();
+        if pane_index >= tiled_count {
+            // Floating panes are indexed after the tiled panes
+            if pane_index - tiled_count < self.floating.len() {
+                self.raise_floating_pane(pane_index - tiled_count);
+            }
+        } else {
+            self.floating_focused = false;
+            self.active = pane_index;
+            self.recency.tag(pane_index);
+        }
+        self.advise_focus_change(prior);
+    }
+
+    fn insert_floating_pane(&mut self, pane: Arc<dyn Pane>, rect: FloatingPaneRect) {
+        let prior = self.get_active_pane();
+        let rect = rect.clamp_to(&self.size);
+        pane.resize(rect.terminal_size(&self.size)).ok();
+        self.floating.push(FloatingPane { pane, rect });
+        self.floating_visible = true;
+        self.floating_focused = true;
+        self.advise_focus_change(prior);
+    }
+
+    fn iter_floating_panes(&mut self) -> Vec<PositionedPane> {
+        let tiled_count = self.count_panes();
+        let cell_dims = self.cell_dimensions();
+        let has_focus = self.floating_has_focus();
+        let num_floating = self.floating.len();
+        self.floating
+            .iter()
+            .enumerate()
+            .map(|(i, floating)| {
+                let rect = floating.rect;
+                PositionedPane {
+                    index: tiled_count + i,
+                    is_active: has_focus && i + 1 == num_floating,
+                    is_zoomed: false,
+                    is_floating: true,
+                    left: rect.left,
+                    top: rect.top,
+                    width: rect.cols,
+                    pixel_width: rect.cols * cell_dims.pixel_width,
+                    height: rect.rows,
+                    pixel_height: rect.rows * cell_dims.pixel_height,
+                    pane: Arc::clone(&floating.pane),
+                }
+            })
+            .collect()
+    }
+
+    fn set_floating_panes_visible(&mut self, visible: bool) {
+        if self.floating_visible == visible && self.floating_focused == visible {
+            return;
+        }
+        let prior = self.get_active_pane();
+        self.floating_visible = visible;
+        self.floating_focused = visible;
+        for floating in &self.floating {
+            floating.pane.set_floating_state(floating.rect, visible);
+        }
         self.advise_focus_change(prior);
+        Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+    }
+
+    fn set_floating_pane_rect(&mut self, pane_id: PaneId, rect: FloatingPaneRect) -> bool {
+        let size = self.size;
+        let rect = rect.clamp_to(&size);
+        let visible = self.floating_visible;
+        match self
+            .floating
+            .iter_mut()
+            .find(|f| f.pane.pane_id() == pane_id)
+        {
+            Some(floating) => {
+                if floating.rect != rect {
+                    if (floating.rect.cols, floating.rect.rows) != (rect.cols, rect.rows) {
+                        floating.pane.resize(rect.terminal_size(&size)).ok();
+                    }
+                    floating.rect = rect;
+                    floating.pane.set_floating_state(rect, visible);
+                    Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keeps the floating panes within the bounds of the tab
+    /// after it has been resized
+    fn clamp_floating_panes(&mut self) {
+        let size = self.size;
+        for floating in &mut self.floating {
+            let rect = floating.rect.clamp_to(&size);
+            if rect != floating.rect {
+                floating.rect = rect;
+                floating.pane.resize(rect.terminal_size(&size)).ok();
+            }
+        }
+    }
+
+    fn codec_floating_panes(&mut self) -> FloatingPaneNodes {
+        let mux = Mux::get();
+        let tab_id = self.id;
+        let window_id = match mux.window_containing_tab(tab_id) {
+            Some(w) => w,
+            None => return FloatingPaneNodes::default(),
+        };
+        let workspace = match mux.get_window(window_id) {
+            Some(w) => w.get_workspace().to_string(),
+            None => return FloatingPaneNodes::default(),
+        };
+        let has_focus = self.floating_has_focus();
+        let num_floating = self.floating.len();
+        FloatingPaneNodes {
+            visible: self.floating_visible,
+            panes: self
+                .floating
+                .iter()
+                .enumerate()
+                .map(|(i, floating)| {
+                    (
+                        pane_entry(
+                            &floating.pane,
+                            tab_id,
+                            window_id,
+                            has_focus && i + 1 == num_floating,
+                            false,
+                            &workspace,
+                            floating.rect.left,
+                            floating.rect.top,
+                        ),
+                        floating.rect,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn sync_with_floating_panes<F>(&mut self, floating: FloatingPaneNodes, mut make_pane: F)
+    where
+        F: FnMut(PaneEntry) -> Arc<dyn Pane>,
+    {
+        let mut focused = false;
+        self.floating = floating
+            .panes
+            .into_iter()
+            .map(|(entry, rect)| {
+                focused = entry.is_active_pane;
+                FloatingPane {
+                    pane: make_pane(entry),
+                    rect,
+                }
+            })
+            .collect();
+        self.floating_visible = floating.visible;
+        self.floating_focused = focused;
     }
 
     fn assign_pane(&mut self, pane: &Arc<dyn Pane>) {
@@ -1772,7 +2363,7 @@ impl TabInner {
 
     fn swap_active_with_index(&mut self, pane_index: usize) -> Option<()> {
         let active_idx = self.get_active_idx();
-        let mut pane = self.get_active_pane()?;
+        let mut pane = self.get_active_tiled_pane()?;
         log::trace!(
             "swap_active_with_index: pane_index {} active {}",
             pane_index,
@@ -1965,6 +2556,9 @@ impl TabInner {
                 }
             }
 
+            // The newly split pane takes the focus from any floating pane
+            self.floating_focused = false;
+
             let mut cursor = self.pane.take().unwrap().cursor();
 
             if request.top_level && !cursor.is_leaf() {
@@ -2112,6 +2706,15 @@ pub struct PaneEntry {
     pub left_col: usize,
 }
 
+/// The floating panes of a tab, ordered from bottom to top.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
+#[derive(Deserialize, Clone, Serialize, PartialEq, Debug, Default)]
+pub struct FloatingPaneNodes {
+    pub visible: bool,
+    pub panes: Vec<(PaneEntry, FloatingPaneRect)>,
+}
+
 #[derive(Deserialize, Clone, Serialize, PartialEq, Debug)]
 #[serde(try_from = "String", into = "String")]
 pub struct SerdeUrl {
@@ -2466,6 +3069,289 @@ mod test {
         assert_eq!(600, panes[2].pixel_height);
     }
 
+    #[test]
+    fn floating_panes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+
+        let rect = FloatingPaneRect::centered(&size, 50);
+        assert_eq!(
+            rect,
+            FloatingPaneRect {
+                left: 20,
+                top: 6,
+                cols: 40,
+                rows: 12
+            }
+        );
+
+        let floating = FakePane::new(2, size);
+        tab.insert_floating_pane(Arc::clone(&floating), rect);
+        let floating_size = || *floating.downcast_ref::<FakePane>().unwrap().size.lock();
+        assert_eq!(floating_size().cols, 40);
+        assert_eq!(floating_size().rows, 12);
+        assert!(tab.contains_pane(2));
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 2);
+
+        // The tiled panes are unaffected by the floating pane
+        let panes = tab.iter_panes();
+        assert_eq!(1, panes.len());
+        assert_eq!(false, panes[0].is_active);
+
+        let panes = tab.iter_floating_panes();
+        assert_eq!(1, panes.len());
+        assert_eq!(1, panes[0].index);
+        assert_eq!(true, panes[0].is_active);
+        assert_eq!(true, panes[0].is_floating);
+        assert_eq!(20, panes[0].left);
+        assert_eq!(6, panes[0].top);
+        assert_eq!(400, panes[0].pixel_width);
+        assert_eq!(300, panes[0].pixel_height);
+
+        // Hiding the floating panes returns focus to the tiled pane
+        tab.set_floating_panes_visible(false);
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 1);
+        tab.set_active_idx(1);
+        assert!(tab.floating_panes_visible());
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 2);
+
+        // Moving out of bounds is clamped to the tab
+        assert!(tab.set_floating_pane_rect(
+            2,
+            FloatingPaneRect {
+                left: 70,
+                top: 20,
+                cols: 40,
+                rows: 12
+            }
+        ));
+        assert_eq!(
+            tab.get_floating_pane_rect(2).unwrap(),
+            FloatingPaneRect {
+                left: 40,
+                top: 12,
+                cols: 40,
+                rows: 12
+            }
+        );
+
+        // Shrinking the tab keeps the floating pane within it
+        tab.resize(TerminalSize {
+            rows: 10,
+            cols: 30,
+            pixel_width: 300,
+            pixel_height: 250,
+            dpi: 96,
+        });
+        assert_eq!(
+            tab.get_floating_pane_rect(2).unwrap(),
+            FloatingPaneRect {
+                left: 0,
+                top: 0,
+                cols: 30,
+                rows: 10
+            }
+        );
+        assert_eq!(floating_size().cols, 30);
+
+        assert!(tab.remove_pane(2).is_some());
+        assert!(!tab.contains_pane(2));
+        assert!(tab.iter_floating_panes().is_empty());
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 1);
+    }
+
+    #[test]
+    fn centered_floating_rect_fits_tab() {
+        let size = |cols, rows| TerminalSize {
+            rows,
+            cols,
+            pixel_width: cols * 10,
+            pixel_height: rows * 20,
+            dpi: 96,
+        };
+
+        assert_eq!(
+            FloatingPaneRect::centered(&size(80, 24), 100),
+            FloatingPaneRect {
+                left: 0,
+                top: 0,
+                cols: 80,
+                rows: 24
+            }
+        );
+
+        // A tiny tab still gets a one cell rect, rather than underflowing
+        assert_eq!(
+            FloatingPaneRect::centered(&size(3, 2), 10),
+            FloatingPaneRect {
+                left: 1,
+                top: 0,
+                cols: 1,
+                rows: 1
+            }
+        );
+
+        // A tab with no size yet
+        assert_eq!(
+            FloatingPaneRect::centered(&size(0, 0), 50),
+            FloatingPaneRect {
+                left: 0,
+                top: 0,
+                cols: 1,
+                rows: 1
+            }
+        );
+    }
+
+    #[test]
+    fn swap_and_replace_panes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+        let request = SplitRequest {
+            direction: SplitDirection::Horizontal,
+            ..Default::default()
+        };
+        let split = tab.compute_split_size(0, request).unwrap();
+        tab.split_and_insert(0, request, FakePane::new(2, split.second))
+            .unwrap();
+
+        assert!(tab.swap_panes(1, 2));
+        let panes = tab.iter_panes();
+        assert_eq!(2, panes[0].pane.pane_id());
+        assert_eq!(39, panes[0].width);
+        assert_eq!(1, panes[1].pane.pane_id());
+        assert_eq!(40, panes[1].width);
+        assert!(!tab.swap_panes(1, 3));
+
+        let replacement = FakePane::new(3, size);
+        let replaced = tab.replace_pane(1, &replacement).unwrap();
+        assert_eq!(1, replaced.pane_id());
+        assert!(!tab.contains_pane(1));
+        let panes = tab.iter_panes();
+        assert_eq!(3, panes[1].pane.pane_id());
+        assert_eq!(
+            40,
+            replacement
+                .downcast_ref::<FakePane>()
+                .unwrap()
+                .size
+                .lock()
+                .cols
+        );
+        assert!(tab.replace_pane(1, &replacement).is_none());
+    }
+
+    #[test]
+    fn layouts() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+        for id in 2..=4 {
+            let request = SplitRequest {
+                direction: SplitDirection::Vertical,
+                ..Default::default()
+            };
+            let split = tab.compute_split_size(0, request).unwrap();
+            tab.split_and_insert(0, request, FakePane::new(id, split.second))
+                .unwrap();
+        }
+        tab.set_active_idx(2);
+        let active = tab.get_active_pane().unwrap().pane_id();
+
+        let geometry = |tab: &Tab| -> Vec<(usize, usize, usize, usize)> {
+            tab.iter_panes()
+                .iter()
+                .map(|p| (p.left, p.top, p.width, p.height))
+                .collect()
+        };
+
+        tab.select_layout(PaneLayout::EvenHorizontal);
+        assert_eq!(
+            geometry(&tab),
+            vec![
+                (0, 0, 19, 24),
+                (20, 0, 19, 24),
+                (40, 0, 19, 24),
+                (60, 0, 20, 24)
+            ]
+        );
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), active);
+
+        tab.select_layout(PaneLayout::Tiled);
+        assert_eq!(
+            geometry(&tab),
+            vec![
+                (0, 0, 39, 11),
+                (40, 0, 40, 11),
+                (0, 12, 39, 12),
+                (40, 12, 40, 12)
+            ]
+        );
+
+        tab.select_layout(PaneLayout::MainVertical);
+        assert_eq!(
+            geometry(&tab),
+            vec![
+                (0, 0, 47, 24),
+                (48, 0, 32, 7),
+                (48, 8, 32, 7),
+                (48, 16, 32, 8)
+            ]
+        );
+
+        // The current arrangement can be recovered and re-applied
+        let layout = tab.get_layout();
+        assert_eq!(layout.num_panes(), 4);
+        tab.apply_layout(&layout).unwrap();
+        assert_eq!(
+            geometry(&tab),
+            vec![
+                (0, 0, 47, 24),
+                (48, 0, 32, 7),
+                (48, 8, 32, 7),
+                (48, 16, 32, 8)
+            ]
+        );
+
+        tab.select_layout(PaneLayout::Rebalance);
+        assert_eq!(
+            geometry(&tab),
+            vec![
+                (0, 0, 39, 24),
+                (40, 0, 40, 7),
+                (40, 8, 40, 7),
+                (40, 16, 40, 8)
+            ]
+        );
+
+        assert!(tab.apply_layout(&LayoutNode::pane(1)).is_err());
+        assert_eq!(tab.cycle_layouts(), PaneLayout::EvenHorizontal);
+        assert_eq!(tab.cycle_layouts(), PaneLayout::EvenVertical);
+    }
+
     fn is_send_and_sync<T: Send + Sync>() -> bool {
         true
     }
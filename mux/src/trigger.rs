@@ -0,0 +1,473 @@
+//! Evaluates the `triggers` configuration against the output of local
+//! panes.  Lines are copied as they are completed while the output
+//! is applied to the terminal model, but the regexes are evaluated and
+//! the actions are performed in a separate thread, without holding the
+//! terminal lock, so that triggers don't slow down the processing of
+//! the output.
+use crate::localpane::LocalPane;
+use crate::pane::{Pane, PaneId};
+use crate::{Mux, MuxNotification};
+use config::{configuration, ConfigHandle, TriggerAction};
+use parking_lot::Mutex;
+use ratelim::RateLimiter;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use termwiz::surface::{Line, SequenceNo};
+use wezterm_term::{Alert, StableRowIndex, Terminal};
+
+/// How many batches of lines can be queued up for evaluation before
+/// we start to drop them.  This bounds the memory used when a program
+/// produces output faster than we can evaluate the triggers.
+const MAX_QUEUED_BATCHES: usize = 128;
+
+lazy_static::lazy_static! {
+    static ref WORKER: SyncSender<TriggerBatch> = spawn_worker();
+    static ref LIMITERS: Mutex<HashMap<PaneId, PaneLimiter>> = Mutex::new(HashMap::new());
+}
+
+/// A physical line that is part of a completed logical line
+struct TriggerRow {
+    row: StableRowIndex,
+    /// Used to detect whether the line was changed between being
+    /// captured and the trigger action being performed
+    seqno: SequenceNo,
+    line: Line,
+}
+
+/// A completed logical line
+struct TriggerLine {
+    rows: Vec<TriggerRow>,
+}
+
+impl TriggerLine {
+    /// Returns the text of the logical line, along with a mapping from
+    /// the byte offset of each cell in that text to its row index and
+    /// cell index
+    fn text_and_cell_map(&self) -> (String, Vec<(usize, usize, usize)>) {
+        let mut text = String::new();
+        let mut map = vec![];
+        for (idx, row) in self.rows.iter().enumerate() {
+            for cell in row.line.visible_cells() {
+                map.push((text.len(), idx, cell.cell_index()));
+                text.push_str(cell.str());
+            }
+        }
+        let trimmed = text.trim_end().len();
+        text.truncate(trimmed);
+        map.retain(|&(offset, _, _)| offset < trimmed);
+        (text, map)
+    }
+}
+
+pub(crate) struct TriggerBatch {
+    pane_id: PaneId,
+    lines: Vec<TriggerLine>,
+}
+
+/// A range of cells that should be highlighted
+pub(crate) struct HighlightSpan {
+    pub row: StableRowIndex,
+    pub seqno: SequenceNo,
+    pub cols: Range<usize>,
+}
+
+/// Tracks which lines of a pane have been passed to the trigger
+/// worker.  This is updated with the terminal lock held.
+#[derive(Default)]
+pub(crate) struct TriggerScanner {
+    /// The first row that has yet to be evaluated
+    next_row: Option<StableRowIndex>,
+}
+
+impl TriggerScanner {
+    /// Called after actions have been applied to the terminal.
+    /// Copies any logical lines that have been completed since the
+    /// last call; the caller should pass the result to `queue`
+    /// after releasing the terminal lock.
+    /// A line is considered to be complete once the cursor has
+    /// moved below it.
+    pub fn scan(&mut self, pane_id: PaneId, term: &mut Terminal) -> Option<TriggerBatch> {
+        if configuration().triggers.is_empty() || term.is_alt_screen_active() {
+            // Full screen applications don't produce lines of output
+            // in a meaningful sense, so we don't evaluate them
+            self.next_row = None;
+            return None;
+        }
+
+        let cursor_row = term.screen().visible_row_to_stable_row(term.cursor_pos().y);
+        let first_row = match self.next_row {
+            // If the cursor moved up, eg: the screen was cleared,
+            // we don't want to re-evaluate the lines that follow it
+            Some(row) if row <= cursor_row => row,
+            _ => cursor_row,
+        };
+        if first_row == cursor_row {
+            self.next_row = Some(cursor_row);
+            return None;
+        }
+
+        let (first, lines) = crate::renderable::terminal_get_lines(term, first_row..cursor_row);
+
+        let mut completed = vec![];
+        let mut current: Option<TriggerLine> = None;
+        for (idx, line) in lines.into_iter().enumerate() {
+            let wrapped = line.last_cell_was_wrapped();
+            let row = TriggerRow {
+                row: first + idx as StableRowIndex,
+                seqno: line.current_seqno(),
+                line,
+            };
+            match current.as_mut() {
+                Some(logical) => logical.rows.push(row),
+                None => current = Some(TriggerLine { rows: vec![row] }),
+            }
+            if !wrapped {
+                completed.extend(current.take());
+            }
+        }
+
+        // A logical line that wraps onto the cursor row isn't
+        // complete yet; we'll pick it up again next time around
+        self.next_row = Some(
+            current
+                .and_then(|logical| logical.rows.first().map(|r| r.row))
+                .unwrap_or(cursor_row),
+        );
+
+        if completed.is_empty() {
+            return None;
+        }
+        Some(TriggerBatch {
+            pane_id,
+            lines: completed,
+        })
+    }
+}
+
+/// Queue up a batch of lines for evaluation by the worker thread
+pub(crate) fn queue(batch: TriggerBatch) {
+    let pane_id = batch.pane_id;
+    match WORKER.try_send(batch) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            log::debug!("trigger queue is full, skipping output of pane {pane_id}");
+        }
+        Err(TrySendError::Disconnected(_)) => {
+            log::error!("trigger worker thread is not running");
+        }
+    }
+}
+
+/// Called when a pane is removed from the mux
+pub(crate) fn pane_removed(pane_id: PaneId) {
+    LIMITERS.lock().remove(&pane_id);
+}
+
+fn spawn_worker() -> SyncSender<TriggerBatch> {
+    let (tx, rx) = sync_channel(MAX_QUEUED_BATCHES);
+    std::thread::Builder::new()
+        .name("triggers".to_string())
+        .spawn(move || TriggerWorker::default().run(rx))
+        .expect("failed to spawn trigger worker thread");
+    tx
+}
+
+/// Applies ratelimit_triggers_per_second to the actions of a pane
+struct PaneLimiter {
+    limiter: RateLimiter,
+    /// How many actions have been dropped since we started
+    /// exceeding the limit
+    dropped: usize,
+}
+
+impl PaneLimiter {
+    fn new() -> Self {
+        Self {
+            limiter: RateLimiter::new(|config| config.ratelimit_triggers_per_second),
+            dropped: 0,
+        }
+    }
+
+    /// Returns true if the action may be performed.
+    /// We log when we start dropping actions, and how many were
+    /// dropped once they are admitted again, rather than logging
+    /// each one.
+    fn admit(&mut self, pane_id: PaneId, action: &TriggerAction) -> bool {
+        if self.limiter.non_blocking_admittance_check(1) {
+            if self.dropped > 0 {
+                log::warn!(
+                    "{} trigger actions for pane {} were dropped \
+                     due to ratelimit_triggers_per_second",
+                    self.dropped,
+                    pane_id
+                );
+                self.dropped = 0;
+            }
+            return true;
+        }
+        if self.dropped == 0 {
+            log::warn!(
+                "trigger rate limit exceeded for pane {}; \
+                 dropping {:?} and further actions",
+                pane_id,
+                action
+            );
+        }
+        self.dropped += 1;
+        false
+    }
+}
+
+#[derive(Default)]
+struct TriggerWorker {
+    generation: Option<usize>,
+    triggers: Vec<(Regex, TriggerAction)>,
+}
+
+impl TriggerWorker {
+    fn run(mut self, rx: Receiver<TriggerBatch>) {
+        while let Ok(batch) = rx.recv() {
+            let config = configuration();
+            self.compile_triggers(&config);
+            if self.triggers.is_empty() {
+                continue;
+            }
+            let mux = match Mux::try_get() {
+                Some(mux) => mux,
+                None => continue,
+            };
+            let pane = match mux.get_pane(batch.pane_id) {
+                Some(pane) => pane,
+                None => continue,
+            };
+            let local_pane = match pane.downcast_ref::<LocalPane>() {
+                Some(local_pane) => local_pane,
+                None => continue,
+            };
+
+            for line in &batch.lines {
+                let (text, map) = line.text_and_cell_map();
+                for (regex, action) in &self.triggers {
+                    for captures in regex.captures_iter(&text) {
+                        let admitted = LIMITERS
+                            .lock()
+                            .entry(batch.pane_id)
+                            .or_insert_with(PaneLimiter::new)
+                            .admit(batch.pane_id, action);
+                        if admitted {
+                            perform_action(
+                                batch.pane_id,
+                                local_pane,
+                                line,
+                                &map,
+                                &captures,
+                                action,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn compile_triggers(&mut self, config: &ConfigHandle) {
+        if self.generation == Some(config.generation()) {
+            return;
+        }
+        self.generation.replace(config.generation());
+        self.triggers = config
+            .triggers
+            .iter()
+            .filter_map(|trigger| match Regex::new(&trigger.regex) {
+                Ok(regex) => Some((regex, trigger.action.clone())),
+                Err(err) => {
+                    log::error!("invalid trigger regex {}: {:#}", trigger.regex, err);
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+/// Expand `$N` and `${N}` references to captures in template
+fn expand(captures: &Captures, template: &str) -> String {
+    let mut result = String::new();
+    captures.expand(template, &mut result);
+    result
+}
+
+/// Compute the cells covered by the specified byte range of the
+/// text produced by `TriggerLine::text_and_cell_map`
+fn highlight_spans(
+    line: &TriggerLine,
+    map: &[(usize, usize, usize)],
+    range: Range<usize>,
+) -> Vec<HighlightSpan> {
+    let mut spans: Vec<HighlightSpan> = vec![];
+    for &(offset, row_idx, cell_idx) in map {
+        if !range.contains(&offset) {
+            continue;
+        }
+        let row = &line.rows[row_idx];
+        match spans.last_mut() {
+            Some(span) if span.row == row.row => {
+                span.cols.end = cell_idx + 1;
+            }
+            _ => spans.push(HighlightSpan {
+                row: row.row,
+                seqno: row.seqno,
+                cols: cell_idx..cell_idx + 1,
+            }),
+        }
+    }
+    // Account for the width of the final cell in each span
+    for span in &mut spans {
+        let row = line.rows.iter().find(|r| r.row == span.row);
+        if let Some(cell) = row.and_then(|r| r.line.get_cell(span.cols.end - 1)) {
+            span.cols.end += cell.width().saturating_sub(1);
+        }
+    }
+    spans
+}
+
+fn perform_action(
+    pane_id: PaneId,
+    pane: &LocalPane,
+    line: &TriggerLine,
+    map: &[(usize, usize, usize)],
+    captures: &Captures,
+    action: &TriggerAction,
+) {
+    match action {
+        TriggerAction::Highlight(highlight) => {
+            let range = match captures.get(highlight.capture) {
+                Some(m) => m.range(),
+                None => return,
+            };
+            let spans = highlight_spans(line, map, range);
+            if pane.apply_trigger_highlight(&spans, highlight) {
+                Mux::notify_from_any_thread(MuxNotification::PaneOutput(pane_id));
+            }
+        }
+        TriggerAction::EmitEvent(name) => {
+            let captures = captures
+                .iter()
+                .map(|m| m.map(|m| m.as_str().to_string()))
+                .collect();
+            Mux::notify_from_any_thread(MuxNotification::TriggerEvent {
+                pane_id,
+                name: name.to_string(),
+                captures,
+            });
+        }
+        TriggerAction::SendString(text) => {
+            let text = expand(captures, text);
+            if let Err(err) = pane.writer().write_all(text.as_bytes()) {
+                log::error!("trigger failed to send text to pane {pane_id}: {err:#}");
+            }
+        }
+        TriggerAction::Notify(notif) => {
+            Mux::notify_from_any_thread(MuxNotification::Alert {
+                pane_id,
+                alert: Alert::ToastNotification {
+                    title: notif.title.as_ref().map(|title| expand(captures, title)),
+                    body: expand(captures, &notif.body),
+                    focus: true,
+                },
+            });
+        }
+        TriggerAction::SetUserVar(var) => {
+            pane.set_user_var(expand(captures, &var.name), expand(captures, &var.value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::cell::CellAttributes;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn trigger_line(rows: &[&str]) -> TriggerLine {
+        let last = rows.len() - 1;
+        TriggerLine {
+            rows: rows
+                .iter()
+                .enumerate()
+                .map(|(idx, text)| {
+                    let attrs = CellAttributes::default();
+                    let line = if idx == last {
+                        Line::from_text(text, &attrs, SEQ_ZERO, None)
+                    } else {
+                        Line::from_text_with_wrapped_last_col(text, &attrs, SEQ_ZERO)
+                    };
+                    TriggerRow {
+                        row: 10 + idx as StableRowIndex,
+                        seqno: SEQ_ZERO,
+                        line,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn spans_for(line: &TriggerLine, pattern: &str) -> Vec<(StableRowIndex, Range<usize>)> {
+        let (text, map) = line.text_and_cell_map();
+        let range = Regex::new(pattern).unwrap().find(&text).unwrap().range();
+        highlight_spans(line, &map, range)
+            .into_iter()
+            .map(|span| (span.row, span.cols))
+            .collect()
+    }
+
+    #[test]
+    fn wrapped_line_text() {
+        let line = trigger_line(&["hello wo", "rld   "]);
+        let (text, map) = line.text_and_cell_map();
+        assert_eq!(text, "hello world");
+        assert_eq!(map.len(), text.len());
+        assert_eq!(map[8], (8, 1, 0));
+    }
+
+    #[test]
+    fn highlight_across_rows() {
+        let line = trigger_line(&["hello wo", "rld"]);
+        assert_eq!(spans_for(&line, "world"), vec![(10, 6..8), (11, 0..3)]);
+        assert_eq!(spans_for(&line, "ell"), vec![(10, 1..4)]);
+    }
+
+    #[test]
+    fn highlight_wide_cells() {
+        let line = trigger_line(&["日本語 ok"]);
+        assert_eq!(spans_for(&line, "本"), vec![(10, 2..4)]);
+        assert_eq!(spans_for(&line, "ok"), vec![(10, 7..9)]);
+    }
+
+    #[test]
+    fn expand_captures() {
+        let regex = Regex::new(r"(\w+)@(?P<host>\w+)").unwrap();
+        let captures = regex.captures("login wez@example").unwrap();
+        assert_eq!(expand(&captures, "$1 on ${host}!"), "wez on example!");
+        assert_eq!(expand(&captures, "$0"), "wez@example");
+    }
+
+    #[test]
+    fn rate_limit() {
+        let action = TriggerAction::SendString("x".to_string());
+        let limit = configuration().ratelimit_triggers_per_second as usize;
+        let mut limiter = PaneLimiter::new();
+        let admitted = (0..limit * 2).filter(|_| limiter.admit(1, &action)).count();
+        assert_eq!(admitted, limit);
+        assert_eq!(limiter.dropped, limit);
+    }
+
+    #[test]
+    fn removed_panes_forget_their_limiter() {
+        LIMITERS.lock().insert(4242, PaneLimiter::new());
+        pane_removed(4242);
+        assert!(!LIMITERS.lock().contains_key(&4242));
+    }
+}
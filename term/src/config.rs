@@ -147,6 +147,19 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
         3500
     }
 
+    /// Returns the number of the most recent rows of scrollback that
+    /// are kept uncompressed.  Older rows are compressed in segments
+    /// and decompressed on demand when they are accessed.
+    fn scrollback_uncompressed_lines(&self) -> usize {
+        10_000
+    }
+
+    /// Returns true if compressed scrollback should be written to
+    /// a temporary file rather than being kept in memory.
+    fn scrollback_spill_to_disk(&self) -> bool {
+        false
+    }
+
     /// Return true if the embedding application wants to use CSI-u encoding
     /// for keys that would otherwise be ambiguous.
     /// <http://www.leonerd.org.uk/hacks/fixterms/>
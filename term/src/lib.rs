@@ -36,6 +36,8 @@ pub use termwiz::surface::line::*;
 pub mod screen;
 pub use crate::screen::*;
 
+mod scrollback;
+
 use termwiz::hyperlink::Hyperlink;
 
 pub mod terminal;
@@ -125,6 +127,93 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// Accumulates the semantic zones of a sequence of lines, merging
+/// runs of the same semantic type that span multiple lines
+#[derive(Debug, Default)]
+pub struct SemanticZoneBuilder {
+    current: Option<SemanticZone>,
+    zones: Vec<SemanticZone>,
+}
+
+impl SemanticZoneBuilder {
+    /// Add the next line, which is located at stable_row
+    pub fn add_line(&mut self, stable_row: StableRowIndex, line: &Line) {
+        for zone_range in line.semantic_zone_ranges_uncached().iter() {
+            let new_zone = match self.current.as_ref() {
+                None => true,
+                Some(zone) => zone.semantic_type != zone_range.semantic_type,
+            };
+
+            if new_zone {
+                if let Some(zone) = self.current.take() {
+                    self.zones.push(zone);
+                }
+
+                self.current.replace(SemanticZone {
+                    start_x: zone_range.range.start as usize,
+                    start_y: stable_row,
+                    end_x: zone_range.range.end as usize,
+                    end_y: stable_row,
+                    semantic_type: zone_range.semantic_type,
+                });
+            }
+
+            if let Some(zone) = self.current.as_mut() {
+                zone.end_x = zone_range.range.end as usize;
+                zone.end_y = stable_row;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<SemanticZone> {
+        if let Some(zone) = self.current.take() {
+            self.zones.push(zone);
+        }
+        self.zones
+    }
+}
+
+/// A shell command, as delimited by the OSC 133 semantic prompt
+/// escapes emitted by shell integration.  Each part of the command
+/// is represented as a zone; a zone is None if the shell didn't
+/// mark up that part, or if it was empty.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CommandBlock {
+    pub prompt: Option<SemanticZone>,
+    pub input: Option<SemanticZone>,
+    pub output: Option<SemanticZone>,
+    /// The exit status reported via OSC 133;D
+    pub exit_status: Option<i32>,
+    /// When the command was started (OSC 133;C)
+    pub start_time: Option<std::time::SystemTime>,
+    /// When the command finished (OSC 133;D)
+    pub end_time: Option<std::time::SystemTime>,
+    /// The working directory at the time the command was started
+    pub cwd: Option<String>,
+}
+
+impl CommandBlock {
+    /// Returns true if the command has been started, but the shell
+    /// has not yet reported its completion
+    pub fn is_running(&self) -> bool {
+        self.start_time.is_some() && self.end_time.is_none()
+    }
+
+    /// Returns true if the command completed with a non-zero exit status
+    pub fn failed(&self) -> bool {
+        matches!(self.exit_status, Some(status) if status != 0)
+    }
+
+    /// How long the command ran for; if it is still running,
+    /// how long it has been running so far
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        let start = self.start_time?;
+        let end = self.end_time.unwrap_or_else(std::time::SystemTime::now);
+        end.duration_since(start).ok()
+    }
+}
+
 pub mod color;
 
 #[cfg(test)]
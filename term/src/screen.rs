@@ -1,8 +1,8 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::range_plus_one))]
 use super::*;
 use crate::config::BidiMode;
+use crate::scrollback::{LineRef, LineStore};
 use log::debug;
-use std::collections::VecDeque;
 use std::sync::Arc;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::SequenceNo;
@@ -14,14 +14,15 @@ use termwiz::surface::SequenceNo;
 #[derive(Debug, Clone)]
 pub struct Screen {
     /// Holds the line data that comprises the screen contents.
-    /// This is allocated with capacity for the entire scrollback.
     /// The last N lines are the visible lines, with those prior being
     /// the lines that have scrolled off the top of the screen.
     /// Index 0 is the topmost line of the screen/scrollback (depending
     /// on the current window size) and will be the first line to be
     /// popped off the front of the screen when a new line is added that
-    /// would otherwise have exceeded the line capacity
-    lines: VecDeque<Line>,
+    /// would otherwise have exceeded the line capacity.
+    /// Lines that are older than the configured number of uncompressed
+    /// lines are held compressed.
+    lines: LineStore,
 
     /// Whenever we scroll a line off the top of the scrollback, we
     /// increment this.  We use this offset to translate between
@@ -52,6 +53,11 @@ fn scrollback_size(config: &Arc<dyn TerminalConfiguration>, allow_scrollback: bo
     }
 }
 
+/// Returns the number of lines of scrollback that are held uncompressed
+fn uncompressed_size(config: &Arc<dyn TerminalConfiguration>, allow_scrollback: bool) -> usize {
+    scrollback_size(config, allow_scrollback).min(config.scrollback_uncompressed_lines())
+}
+
 impl Screen {
     /// Create a new Screen with the specified dimensions.
     /// The Cells in the viewable portion of the screen are set to the
@@ -67,7 +73,7 @@ impl Screen {
         let physical_cols = size.cols.max(1);
 
         let mut lines =
-            VecDeque::with_capacity(physical_rows + scrollback_size(config, allow_scrollback));
+            LineStore::with_capacity(physical_rows + uncompressed_size(config, allow_scrollback));
         for _ in 0..physical_rows {
             let mut line = Line::new(seqno);
             bidi_mode.apply_to_line(&mut line, seqno);
@@ -94,6 +100,14 @@ impl Screen {
         scrollback_size(&self.config, self.allow_scrollback)
     }
 
+    /// Compresses the scrollback beyond the configured number of
+    /// uncompressed lines
+    fn compress_scrollback(&mut self) {
+        let keep = self.physical_rows + uncompressed_size(&self.config, self.allow_scrollback);
+        self.lines
+            .maintain(keep, self.config.scrollback_spill_to_disk());
+    }
+
     fn rewrap_lines(
         &mut self,
         physical_cols: usize,
@@ -102,12 +116,17 @@ impl Screen {
         cursor_y: PhysRowIndex,
         seqno: SequenceNo,
     ) -> (usize, PhysRowIndex) {
-        let mut rewrapped = VecDeque::new();
+        let mut rewrapped = LineStore::default();
         let mut logical_line: Option<Line> = None;
         let mut logical_cursor_x: Option<usize> = None;
         let mut adjusted_cursor = (cursor_x, cursor_y);
 
-        for (phys_idx, mut line) in self.lines.drain(..).enumerate() {
+        // The rewrapped lines are compressed as we go, so that the
+        // whole of a large scrollback is never decompressed at once
+        let keep = physical_rows + uncompressed_size(&self.config, self.allow_scrollback);
+        let spill = self.config.scrollback_spill_to_disk();
+
+        for (phys_idx, mut line) in std::mem::take(&mut self.lines).into_iter().enumerate() {
             line.update_last_change_seqno(seqno);
             let was_wrapped = line.last_cell_was_wrapped();
 
@@ -168,6 +187,7 @@ impl Screen {
                     rewrapped.push_back(line);
                 }
             }
+            rewrapped.maintain(keep, spill);
         }
         self.lines = rewrapped;
 
@@ -178,7 +198,11 @@ impl Screen {
         // real information off the top of the scrollback
         let capacity = physical_rows + self.scrollback_size();
         while self.lines.len() > capacity
-            && self.lines.back().map(Line::is_whitespace).unwrap_or(false)
+            && self
+                .lines
+                .back()
+                .map(|l| l.is_whitespace())
+                .unwrap_or(false)
         {
             self.lines.pop_back();
         }
@@ -214,7 +238,12 @@ impl Screen {
         // maximized states.
         let cursor_phys = self.phys_row(cursor.y);
         for _ in cursor_phys + 1..self.lines.len() {
-            if self.lines.back().map(Line::is_whitespace).unwrap_or(false) {
+            if self
+                .lines
+                .back()
+                .map(|l| l.is_whitespace())
+                .unwrap_or(false)
+            {
                 self.lines.pop_back();
             }
         }
@@ -230,22 +259,23 @@ impl Screen {
             if self.allow_scrollback {
                 self.rewrap_lines(physical_cols, physical_rows, cursor.x, cursor_phys, seqno)
             } else {
-                for line in &mut self.lines {
-                    if physical_cols < self.physical_cols {
+                let narrower = physical_cols < self.physical_cols;
+                self.lines.for_each_mut(|_, line| {
+                    if narrower {
                         // Do a simple prune of the lines instead
                         line.resize(physical_cols, seqno);
                     } else {
                         // otherwise: invalidate them
                         line.update_last_change_seqno(seqno);
                     }
-                }
+                });
                 (cursor.x, cursor_phys)
             }
         } else {
             (cursor.x, cursor_phys)
         };
 
-        let capacity = physical_rows + self.scrollback_size();
+        let capacity = physical_rows + uncompressed_size(&self.config, self.allow_scrollback);
         let current_capacity = self.lines.capacity();
         if capacity > current_capacity {
             self.lines.reserve(capacity - current_capacity);
@@ -313,6 +343,7 @@ impl Screen {
 
         self.physical_rows = physical_rows;
         self.physical_cols = physical_cols;
+        self.compress_scrollback();
         CursorPosition {
             x: cursor_x,
             y: new_cursor_y,
@@ -325,7 +356,10 @@ impl Screen {
     /// Get mutable reference to a line, relative to start of scrollback.
     #[inline]
     pub fn line_mut(&mut self, idx: PhysRowIndex) -> &mut Line {
-        &mut self.lines[idx]
+        let len = self.lines.len();
+        self.lines
+            .get_mut(idx)
+            .unwrap_or_else(|| panic!("line index {} is out of range 0..{}", idx, len))
     }
 
     /// Returns the number of occupied rows of scrollback
@@ -337,8 +371,8 @@ impl Screen {
     #[inline]
     pub fn dirty_line(&mut self, idx: VisibleRowIndex, seqno: SequenceNo) {
         let line_idx = self.phys_row(idx);
-        if line_idx < self.lines.len() {
-            self.lines[line_idx].update_last_change_seqno(seqno);
+        if let Some(line) = self.lines.get_mut(line_idx) {
+            line.update_last_change_seqno(seqno);
         }
     }
 
@@ -346,20 +380,26 @@ impl Screen {
     #[cfg(test)]
     pub fn visible_lines(&self) -> Vec<Line> {
         let line_idx = self.lines.len() - self.physical_rows;
-        let mut lines = Vec::new();
-        for line in self.lines.iter().skip(line_idx) {
-            if lines.len() >= self.physical_rows {
-                break;
-            }
-            lines.push(line.clone());
-        }
-        lines
+        self.lines_in_phys_range(line_idx..line_idx + self.physical_rows)
     }
 
     /// Returns a copy of the lines in the screen (including scrollback)
     #[cfg(test)]
     pub fn all_lines(&self) -> Vec<Line> {
-        self.lines.iter().map(|l| l.clone()).collect()
+        self.lines_in_phys_range(0..self.lines.len())
+    }
+
+    /// Returns the number of compressed segments of scrollback
+    #[cfg(test)]
+    pub fn scrollback_segments(&self) -> usize {
+        self.lines.num_segments()
+    }
+
+    /// Returns the number of segments of scrollback that are
+    /// currently held in their compressed form
+    #[cfg(test)]
+    pub fn frozen_scrollback_segments(&self) -> usize {
+        self.lines.num_frozen_segments()
     }
 
     pub fn insert_cell(
@@ -576,7 +616,7 @@ impl Screen {
 
                 // Copy the source cells first
                 let cells = {
-                    self.lines[src_row]
+                    self.line_mut(src_row)
                         .cells_mut()
                         .iter()
                         .skip(left_and_right_margins.start)
@@ -747,15 +787,17 @@ impl Screen {
                 self.lines.insert(phys_scroll.end, line);
             }
         }
+
+        if scroll_region.start == 0 {
+            self.compress_scrollback();
+        }
     }
 
     pub fn erase_scrollback(&mut self) {
         let len = self.lines.len();
         let to_clear = len - self.physical_rows;
-        for _ in 0..to_clear {
-            self.lines.pop_front();
-            self.stable_row_index_offset += 1;
-        }
+        self.lines.truncate_front(to_clear);
+        self.stable_row_index_offset += to_clear;
     }
 
     /// ```text
@@ -837,7 +879,7 @@ impl Screen {
 
                 // Copy the source cells first
                 let cells = {
-                    self.lines[src_row]
+                    self.line_mut(src_row)
                         .cells_mut()
                         .iter()
                         .skip(left_and_right_margins.start)
@@ -886,12 +928,9 @@ impl Screen {
     }
 
     pub fn lines_in_phys_range(&self, phys_range: Range<PhysRowIndex>) -> Vec<Line> {
-        self.lines
-            .iter()
-            .skip(phys_range.start)
-            .take(phys_range.end - phys_range.start)
-            .cloned()
-            .collect()
+        self.lines.with_range(phys_range, |lines| {
+            lines.iter().map(|&line| line.clone()).collect()
+        })
     }
 
     pub fn get_changed_stable_rows(
@@ -901,17 +940,13 @@ impl Screen {
     ) -> Vec<StableRowIndex> {
         let phys = self.stable_range(&stable_lines);
         let mut set = vec![];
-        for (idx, line) in self
-            .lines
-            .iter()
-            .enumerate()
-            .skip(phys.start)
-            .take(phys.end - phys.start)
-        {
-            if line.changed_since(seqno) {
-                set.push(self.phys_to_stable_row_index(idx))
+        self.lines.with_range(phys.clone(), |lines| {
+            for (idx, line) in lines.iter().enumerate() {
+                if line.changed_since(seqno) {
+                    set.push(self.phys_to_stable_row_index(phys.start + idx))
+                }
             }
-        }
+        });
         set
     }
 
@@ -919,61 +954,33 @@ impl Screen {
     where
         F: FnMut(&[&Line]),
     {
-        let (first, second) = self.lines.as_slices();
-        let first_range = 0..first.len();
-        let second_range = first.len()..first.len() + second.len();
-        let first_range = phys_intersection(&first_range, &phys_range);
-        let second_range = phys_intersection(&second_range, &phys_range);
-
-        let mut lines: Vec<&Line> = Vec::with_capacity(phys_range.end - phys_range.start);
-        for line in &first[first_range] {
-            lines.push(line);
-        }
-        for line in &second[second_range] {
-            lines.push(line);
-        }
-        func(&lines)
+        self.lines.with_range(phys_range, |lines| func(lines))
     }
 
     pub fn with_phys_lines_mut<F>(&mut self, phys_range: Range<PhysRowIndex>, mut func: F)
     where
         F: FnMut(&mut [&mut Line]),
     {
-        let (first, second) = self.lines.as_mut_slices();
-        let first_len = first.len();
-        let first_range = 0..first.len();
-        let second_range = first.len()..first.len() + second.len();
-        let first_range = phys_intersection(&first_range, &phys_range);
-        let second_range = phys_intersection(&second_range, &phys_range);
-
-        let mut lines: Vec<&mut Line> = Vec::with_capacity(phys_range.end - phys_range.start);
-        for line in &mut first[first_range] {
-            lines.push(line);
-        }
-        for line in &mut second[second_range.start.saturating_sub(first_len)
-            ..second_range.end.saturating_sub(first_len)]
-        {
-            lines.push(line);
-        }
-        func(&mut lines)
+        self.lines.with_range_mut(phys_range, |lines| func(lines))
     }
 
-    pub fn for_each_phys_line<F>(&self, mut f: F)
+    pub fn for_each_phys_line<F>(&self, f: F)
     where
         F: FnMut(usize, &Line),
     {
-        for (idx, line) in self.lines.iter().enumerate() {
-            f(idx, line);
-        }
+        self.lines.for_each(f);
     }
 
-    pub fn for_each_phys_line_mut<F>(&mut self, mut f: F)
+    pub fn for_each_phys_line_mut<F>(&mut self, f: F)
     where
         F: FnMut(usize, &mut Line),
     {
-        for (idx, line) in self.lines.iter_mut().enumerate() {
-            f(idx, line);
-        }
+        self.lines.for_each_mut(f);
+    }
+
+    /// Marks every line, including those in the scrollback, as changed
+    pub fn dirty_all_lines(&mut self, seqno: SequenceNo) {
+        self.lines.update_last_change_seqno(seqno);
     }
 
     pub fn for_each_logical_line_in_stable_range_mut<F>(
@@ -994,7 +1001,10 @@ impl Screen {
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
         while phys_range.start > 0 {
-            let prior = &mut self.lines[phys_range.start - 1];
+            let prior = match self.lines.get(phys_range.start - 1) {
+                Some(prior) => prior,
+                None => break,
+            };
             if !prior.last_cell_was_wrapped() {
                 break;
             }
@@ -1073,7 +1083,10 @@ impl Screen {
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
         while phys_range.start > 0 {
-            let prior = &self.lines[phys_range.start - 1];
+            let prior = match self.lines.get(phys_range.start - 1) {
+                Some(prior) => prior,
+                None => break,
+            };
             if !prior.last_cell_was_wrapped() {
                 break;
             }
@@ -1085,7 +1098,7 @@ impl Screen {
         }
 
         let mut phys_row = phys_range.start;
-        let mut line_vec: Vec<&Line> = vec![];
+        let mut line_vec: Vec<LineRef> = vec![];
         while phys_row < phys_range.end {
             // Look forwards until we find the end of this logical line
             let mut total_len = 0;
@@ -1099,8 +1112,9 @@ impl Screen {
                     }
                     end_inclusive = idx;
                     total_len += line.len();
+                    let wrapped = line.last_cell_was_wrapped();
                     line_vec.push(line);
-                    if !line.last_cell_was_wrapped() {
+                    if !wrapped {
                         break;
                     }
                 } else if idx == phys_row {
@@ -1123,7 +1137,8 @@ impl Screen {
                 break;
             }
 
-            let continue_iteration = f(logical_stable_range, &line_vec);
+            let lines: Vec<&Line> = line_vec.iter().map(|line| &**line).collect();
+            let continue_iteration = f(logical_stable_range, &lines);
 
             if !continue_iteration {
                 break;
@@ -1131,13 +1146,3 @@ impl Screen {
         }
     }
 }
-
-fn phys_intersection(r1: &Range<PhysRowIndex>, r2: &Range<PhysRowIndex>) -> Range<PhysRowIndex> {
-    let start = r1.start.max(r2.start);
-    let end = r1.end.min(r2.end);
-    if end > start {
-        start..end
-    } else {
-        0..0
-    }
-}
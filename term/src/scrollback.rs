@@ -0,0 +1,959 @@
+//! Storage for the lines of a `Screen`.
+//!
+//! The most recent lines, which include the visible portion of the
+//! screen, are held as regular `Line`s.  Older scrollback is grouped
+//! into segments of `SEGMENT_ROWS` lines that are serialized and
+//! compressed, and that may optionally be written to a temporary file
+//! rather than being kept in memory.  Segments are decoded on demand
+//! when their rows are accessed, so that a very large scrollback
+//! doesn't require a proportionally large amount of memory.
+use crate::Line;
+use anyhow::Context;
+use std::collections::VecDeque;
+use std::ops::{Deref, Range};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use termwiz::surface::{SequenceNo, SEQ_ZERO};
+
+/// The number of lines in a segment.  Only the first segment in
+/// the store may hold fewer lines than this.
+const SEGMENT_ROWS: usize = 1024;
+
+/// The number of segments that may be held decoded for modification
+/// before the least recently decoded of them is compressed again
+const MAX_THAWED_SEGMENTS: usize = 4;
+
+/// The number of segments decoded for reading that are cached
+const DECODE_CACHE_SIZE: usize = 4;
+
+/// The lines of a segment that were decoded for reading
+#[derive(Clone)]
+struct Decoded {
+    lines: Arc<Vec<Line>>,
+    /// False if the segment could not be decoded, in which case
+    /// lines are blank placeholders for its content
+    ok: bool,
+}
+
+/// A reference to a line in the store.  Lines in compressed segments
+/// are decoded on demand, so they cannot be referenced directly.
+pub(crate) enum LineRef<'a> {
+    Borrowed(&'a Line),
+    Decoded(Arc<Vec<Line>>, usize),
+}
+
+impl Deref for LineRef<'_> {
+    type Target = Line;
+    fn deref(&self) -> &Line {
+        match self {
+            Self::Borrowed(line) => line,
+            Self::Decoded(lines, idx) => &lines[*idx],
+        }
+    }
+}
+
+pub(crate) struct LineStore {
+    /// The oldest lines, in order
+    segments: VecDeque<Segment>,
+    /// The total number of lines held in segments
+    frozen_rows: usize,
+    /// The most recent lines, in order, following those in segments
+    hot: VecDeque<Line>,
+    next_segment_id: u64,
+    /// The ids of the segments that were decoded for modification,
+    /// least recently decoded first
+    thawed: VecDeque<u64>,
+    cache: Mutex<lru::LruCache<u64, Decoded>>,
+    spill: bool,
+    spill_dir: Option<Arc<SpillDir>>,
+}
+
+#[derive(Clone)]
+struct Segment {
+    /// Identifies the segment in the decode cache.  Ids increase
+    /// from the front of the store to the back.
+    id: u64,
+    len: usize,
+    /// Applied to the lines when they are decoded, so that all of
+    /// the lines can be marked as changed without decoding them
+    seqno: SequenceNo,
+    data: SegmentData,
+}
+
+#[derive(Clone)]
+enum SegmentData {
+    Thawed(ThawedLines),
+    Frozen(Frozen),
+}
+
+#[derive(Clone)]
+struct ThawedLines {
+    lines: VecDeque<Line>,
+    /// Pinned lines are never compressed again; this is the case for
+    /// lines that cannot be encoded and for the first segment once
+    /// lines start being removed from the front of the store.
+    pinned: bool,
+    /// The encoded data and the content of the lines at the time that
+    /// they were decoded, so that unmodified lines need not be encoded
+    /// again.
+    original: Option<(Frozen, Decoded)>,
+}
+
+#[derive(Clone)]
+enum Frozen {
+    Compressed(Arc<Vec<u8>>),
+    Spilled(Arc<SpillFile>),
+}
+
+impl Frozen {
+    fn try_decode(&self, len: usize, seqno: SequenceNo) -> anyhow::Result<Vec<Line>> {
+        let mut lines = match self {
+            Self::Compressed(data) => decode_lines(data)?,
+            Self::Spilled(file) => decode_lines(&file.read()?)?,
+        };
+        anyhow::ensure!(
+            lines.len() == len,
+            "scrollback segment decoded to {} lines instead of {}",
+            lines.len(),
+            len
+        );
+        for line in &mut lines {
+            line.update_last_change_seqno(seqno);
+        }
+        Ok(lines)
+    }
+
+    /// Decodes the lines, substituting blank lines if that fails.
+    /// The encoded data is left untouched, so a segment that couldn't
+    /// be read, for example because of a transient error reading its
+    /// spill file, may be decoded successfully later.
+    fn decode(&self, len: usize, seqno: SequenceNo) -> Decoded {
+        match self.try_decode(len, seqno) {
+            Ok(lines) => Decoded {
+                lines: Arc::new(lines),
+                ok: true,
+            },
+            Err(err) => {
+                log::error!("failed to load scrollback segment: {:#}", err);
+                Decoded {
+                    lines: Arc::new((0..len).map(|_| Line::new(seqno)).collect()),
+                    ok: false,
+                }
+            }
+        }
+    }
+}
+
+/// A per-store directory holding the segments that were spilled to disk
+struct SpillDir {
+    dir: tempfile::TempDir,
+    next_file: AtomicUsize,
+}
+
+impl SpillDir {
+    fn new() -> anyhow::Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("wezterm-scrollback-")
+            .tempdir()
+            .context("creating scrollback spill directory")?;
+        Ok(Self {
+            dir,
+            next_file: AtomicUsize::new(0),
+        })
+    }
+
+    fn write(self: &Arc<Self>, data: &[u8]) -> anyhow::Result<SpillFile> {
+        let n = self.next_file.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.path().join(format!("{}.seg", n));
+        std::fs::write(&path, data).with_context(|| format!("writing {}", path.display()))?;
+        Ok(SpillFile {
+            path,
+            _dir: Arc::clone(self),
+        })
+    }
+}
+
+struct SpillFile {
+    path: PathBuf,
+    /// Keeps the directory alive until all of its files are removed
+    _dir: Arc<SpillDir>,
+}
+
+impl SpillFile {
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        std::fs::read(&self.path).with_context(|| format!("reading {}", self.path.display()))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn encode_lines(lines: &VecDeque<Line>) -> anyhow::Result<Vec<u8>> {
+    use serde::Serialize;
+    let mut compressed = Vec::new();
+    let mut compress = zstd::Encoder::new(&mut compressed, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    let mut encode = varbincode::Serializer::new(&mut compress);
+    lines.serialize(&mut encode)?;
+    drop(encode);
+    compress.finish()?;
+    Ok(compressed)
+}
+
+#[cfg(feature = "use_serde")]
+fn decode_lines(data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    let mut decompress = zstd::Decoder::new(data)?;
+    let mut decode = varbincode::Deserializer::new(&mut decompress);
+    serde::Deserialize::deserialize(&mut decode).map_err(Into::into)
+}
+
+#[cfg(not(feature = "use_serde"))]
+fn encode_lines(_lines: &VecDeque<Line>) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("scrollback compression requires the use_serde feature")
+}
+
+#[cfg(not(feature = "use_serde"))]
+fn decode_lines(_data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    anyhow::bail!("scrollback compression requires the use_serde feature")
+}
+
+/// Encodes lines so that they can be held compactly, writing them to
+/// spill_dir if it is set.  Returns None if the lines cannot be encoded,
+/// in which case they must be kept as they are.
+fn freeze(lines: &VecDeque<Line>, spill_dir: Option<&Arc<SpillDir>>) -> Option<Frozen> {
+    // Image data would be duplicated into every cell that references it
+    if lines.iter().any(|line| {
+        line.visible_cells()
+            .any(|cell| cell.attrs().images().is_some())
+    }) {
+        return None;
+    }
+
+    let data = match encode_lines(lines) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("failed to compress scrollback: {:#}", err);
+            return None;
+        }
+    };
+
+    if let Some(dir) = spill_dir {
+        match dir.write(&data) {
+            Ok(file) => return Some(Frozen::Spilled(Arc::new(file))),
+            Err(err) => {
+                log::error!(
+                    "failed to spill scrollback to disk, keeping it in memory: {:#}",
+                    err
+                );
+            }
+        }
+    }
+
+    Some(Frozen::Compressed(Arc::new(data)))
+}
+
+/// Decides how lines that were decoded from original should now be
+/// held: unmodified lines retain their original encoding, as do the
+/// placeholders for lines that could not be decoded, because changes
+/// to those aren't worth losing the real content over.
+fn refreeze(
+    lines: VecDeque<Line>,
+    original: Option<(Frozen, Decoded)>,
+    spill_dir: Option<&Arc<SpillDir>>,
+) -> SegmentData {
+    if let Some((frozen, decoded)) = original {
+        if !decoded.ok || lines.iter().eq(decoded.lines.iter()) {
+            return SegmentData::Frozen(frozen);
+        }
+    }
+    match freeze(&lines, spill_dir) {
+        Some(frozen) => SegmentData::Frozen(frozen),
+        None => SegmentData::Thawed(ThawedLines {
+            lines,
+            pinned: true,
+            original: None,
+        }),
+    }
+}
+
+impl Default for LineStore {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl Clone for LineStore {
+    fn clone(&self) -> Self {
+        Self {
+            segments: self.segments.clone(),
+            frozen_rows: self.frozen_rows,
+            hot: self.hot.clone(),
+            next_segment_id: self.next_segment_id,
+            thawed: self.thawed.clone(),
+            cache: Mutex::new(lru::LruCache::new(DECODE_CACHE_SIZE)),
+            spill: self.spill,
+            spill_dir: self.spill_dir.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for LineStore {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("LineStore")
+            .field("len", &self.len())
+            .field("segments", &self.segments.len())
+            .field("frozen_rows", &self.frozen_rows)
+            .field("thawed", &self.thawed.len())
+            .field("spill", &self.spill)
+            .finish()
+    }
+}
+
+impl LineStore {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            segments: VecDeque::new(),
+            frozen_rows: 0,
+            hot: VecDeque::with_capacity(capacity),
+            next_segment_id: 0,
+            thawed: VecDeque::new(),
+            cache: Mutex::new(lru::LruCache::new(DECODE_CACHE_SIZE)),
+            spill: false,
+            spill_dir: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frozen_rows + self.hot.len()
+    }
+
+    #[cfg(test)]
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[cfg(test)]
+    pub fn num_frozen_segments(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|seg| matches!(seg.data, SegmentData::Frozen(_)))
+            .count()
+    }
+
+    /// Returns the capacity of the uncompressed portion of the store
+    pub fn capacity(&self) -> usize {
+        self.hot.capacity()
+    }
+
+    /// Reserves space in the uncompressed portion of the store
+    pub fn reserve(&mut self, additional: usize) {
+        self.hot.reserve(additional);
+    }
+
+    /// Returns the segment index and the offset within that segment
+    /// of idx, which must be less than frozen_rows
+    fn locate(&self, idx: usize) -> (usize, usize) {
+        let front_len = self.segments.front().map(|seg| seg.len).unwrap_or(0);
+        if idx < front_len {
+            (0, idx)
+        } else {
+            let idx = idx - front_len;
+            (1 + idx / SEGMENT_ROWS, idx % SEGMENT_ROWS)
+        }
+    }
+
+    /// Returns the index of the first line held by the segment seg_idx
+    fn segment_start(&self, seg_idx: usize) -> usize {
+        if seg_idx == 0 {
+            0
+        } else {
+            self.segments[0].len + (seg_idx - 1) * SEGMENT_ROWS
+        }
+    }
+
+    fn spill_dir(&mut self) -> Option<Arc<SpillDir>> {
+        if !self.spill {
+            return None;
+        }
+        if self.spill_dir.is_none() {
+            match SpillDir::new() {
+                Ok(dir) => self.spill_dir = Some(Arc::new(dir)),
+                Err(err) => {
+                    log::error!("{:#}, keeping scrollback in memory", err);
+                    self.spill = false;
+                    return None;
+                }
+            }
+        }
+        self.spill_dir.clone()
+    }
+
+    fn decoded(&self, seg: &Segment, frozen: &Frozen) -> Arc<Vec<Line>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(decoded) = cache.get(&seg.id) {
+            return Arc::clone(&decoded.lines);
+        }
+        let decoded = frozen.decode(seg.len, seg.seqno);
+        let lines = Arc::clone(&decoded.lines);
+        cache.put(seg.id, decoded);
+        lines
+    }
+
+    /// Decodes the segment seg_idx so that its lines can be modified
+    fn thaw(&mut self, seg_idx: usize, pin: bool) {
+        let seg = &mut self.segments[seg_idx];
+        match &mut seg.data {
+            SegmentData::Thawed(thawed) => {
+                if pin && !thawed.pinned {
+                    thawed.pinned = true;
+                    thawed.original = None;
+                    let id = seg.id;
+                    self.thawed.retain(|&thawed_id| thawed_id != id);
+                }
+            }
+            SegmentData::Frozen(frozen) => {
+                let decoded = match self.cache.get_mut().unwrap().pop(&seg.id) {
+                    Some(decoded) => decoded,
+                    None => frozen.decode(seg.len, seg.seqno),
+                };
+                let lines = Arc::clone(&decoded.lines);
+                // Once pinned, the lines are never encoded again, so
+                // this is the point at which the content of a segment
+                // that couldn't be decoded is lost
+                let original = if pin {
+                    None
+                } else {
+                    self.thawed.push_back(seg.id);
+                    Some((frozen.clone(), decoded))
+                };
+                let lines = match Arc::try_unwrap(lines) {
+                    Ok(lines) => lines.into(),
+                    Err(lines) => lines.iter().cloned().collect(),
+                };
+                seg.data = SegmentData::Thawed(ThawedLines {
+                    lines,
+                    pinned: pin,
+                    original,
+                });
+            }
+        }
+    }
+
+    /// Compresses the least recently thawed segments until no more
+    /// than MAX_THAWED_SEGMENTS remain decoded
+    fn limit_thawed(&mut self) {
+        if self.thawed.len() <= MAX_THAWED_SEGMENTS {
+            return;
+        }
+        let spill_dir = self.spill_dir();
+        while self.thawed.len() > MAX_THAWED_SEGMENTS {
+            let id = self.thawed.pop_front().unwrap();
+            let seg_idx = match self.segments.binary_search_by_key(&id, |seg| seg.id) {
+                Ok(idx) => idx,
+                Err(_) => continue,
+            };
+            let seg = &mut self.segments[seg_idx];
+            if let SegmentData::Thawed(thawed) = &mut seg.data {
+                if thawed.pinned {
+                    continue;
+                }
+                let lines = std::mem::take(&mut thawed.lines);
+                let original = thawed.original.take();
+                seg.data = refreeze(lines, original, spill_dir.as_ref());
+            }
+        }
+    }
+
+    /// Moves the segments from seg_idx onwards back into the
+    /// uncompressed portion of the store
+    fn unfreeze_from(&mut self, seg_idx: usize) {
+        while self.segments.len() > seg_idx {
+            let last = self.segments.len() - 1;
+            self.thaw(last, true);
+            let seg = self.segments.pop_back().unwrap();
+            self.frozen_rows -= seg.len;
+            if let SegmentData::Thawed(thawed) = seg.data {
+                for line in thawed.lines.into_iter().rev() {
+                    self.hot.push_front(line);
+                }
+            }
+        }
+    }
+
+    /// Compresses the oldest lines so that fewer than `keep` +
+    /// SEGMENT_ROWS lines are held uncompressed.  When `spill` is
+    /// true, the compressed lines are written to a temporary file
+    /// rather than being kept in memory.
+    pub fn maintain(&mut self, keep: usize, spill: bool) {
+        if !cfg!(feature = "use_serde") {
+            return;
+        }
+        self.spill = spill;
+        self.limit_thawed();
+        while self.hot.len() >= keep + SEGMENT_ROWS {
+            let spill_dir = self.spill_dir();
+            let lines: VecDeque<Line> = self.hot.drain(..SEGMENT_ROWS).collect();
+            let data = refreeze(lines, None, spill_dir.as_ref());
+            self.segments.push_back(Segment {
+                id: self.next_segment_id,
+                len: SEGMENT_ROWS,
+                seqno: SEQ_ZERO,
+                data,
+            });
+            self.next_segment_id += 1;
+            self.frozen_rows += SEGMENT_ROWS;
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> Option<LineRef> {
+        if idx >= self.frozen_rows {
+            return self.hot.get(idx - self.frozen_rows).map(LineRef::Borrowed);
+        }
+        let (seg_idx, offset) = self.locate(idx);
+        let seg = &self.segments[seg_idx];
+        match &seg.data {
+            SegmentData::Thawed(thawed) => thawed.lines.get(offset).map(LineRef::Borrowed),
+            SegmentData::Frozen(frozen) => {
+                Some(LineRef::Decoded(self.decoded(seg, frozen), offset))
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut Line> {
+        if idx >= self.frozen_rows {
+            return self.hot.get_mut(idx - self.frozen_rows);
+        }
+        let (seg_idx, offset) = self.locate(idx);
+        self.limit_thawed();
+        self.thaw(seg_idx, false);
+        match &mut self.segments[seg_idx].data {
+            SegmentData::Thawed(thawed) => thawed.lines.get_mut(offset),
+            SegmentData::Frozen(_) => None,
+        }
+    }
+
+    pub fn back(&self) -> Option<LineRef> {
+        self.len().checked_sub(1).and_then(|idx| self.get(idx))
+    }
+
+    pub fn push_back(&mut self, line: Line) {
+        self.hot.push_back(line);
+    }
+
+    pub fn pop_back(&mut self) -> Option<Line> {
+        if self.hot.is_empty() && !self.segments.is_empty() {
+            self.unfreeze_from(self.segments.len() - 1);
+        }
+        self.hot.pop_back()
+    }
+
+    pub fn pop_front(&mut self) -> Option<Line> {
+        if self.segments.is_empty() {
+            return self.hot.pop_front();
+        }
+        self.thaw(0, true);
+        let seg = &mut self.segments[0];
+        let line = match &mut seg.data {
+            SegmentData::Thawed(thawed) => thawed.lines.pop_front(),
+            SegmentData::Frozen(_) => None,
+        };
+        seg.len -= 1;
+        self.frozen_rows -= 1;
+        if seg.len == 0 {
+            self.segments.pop_front();
+        }
+        line
+    }
+
+    /// Removes the first n lines, discarding whole segments
+    /// without decoding them
+    pub fn truncate_front(&mut self, mut n: usize) {
+        while let Some(seg) = self.segments.front() {
+            if n < seg.len {
+                break;
+            }
+            n -= seg.len;
+            self.frozen_rows -= seg.len;
+            self.segments.pop_front();
+        }
+        for _ in 0..n {
+            self.pop_front();
+        }
+    }
+
+    pub fn insert(&mut self, idx: usize, line: Line) {
+        if idx < self.frozen_rows {
+            self.unfreeze_from(self.locate(idx).0);
+        }
+        self.hot.insert(idx - self.frozen_rows, line);
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Option<Line> {
+        if idx == 0 {
+            return self.pop_front();
+        }
+        if idx < self.frozen_rows {
+            self.unfreeze_from(self.locate(idx).0);
+        }
+        self.hot.remove(idx - self.frozen_rows)
+    }
+
+    /// Marks all of the lines as changed as of seqno
+    pub fn update_last_change_seqno(&mut self, seqno: SequenceNo) {
+        for seg in self.segments.iter_mut() {
+            match &mut seg.data {
+                SegmentData::Thawed(thawed) => {
+                    for line in thawed.lines.iter_mut() {
+                        line.update_last_change_seqno(seqno);
+                    }
+                }
+                SegmentData::Frozen(_) => {
+                    seg.seqno = seg.seqno.max(seqno);
+                }
+            }
+        }
+        for line in self.hot.iter_mut() {
+            line.update_last_change_seqno(seqno);
+        }
+        // Bring the cached copies of the frozen segments up to date
+        // rather than discarding them, as they are likely to be read
+        // again soon
+        for (_, decoded) in self.cache.get_mut().unwrap().iter_mut() {
+            for line in Arc::make_mut(&mut decoded.lines).iter_mut() {
+                line.update_last_change_seqno(seqno);
+            }
+        }
+    }
+
+    /// Calls f with the lines in range, which is clamped to the
+    /// bounds of the store
+    pub fn with_range<F, R>(&self, range: Range<usize>, f: F) -> R
+    where
+        F: FnOnce(&[&Line]) -> R,
+    {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+        let refs: Vec<LineRef> = (start..end).filter_map(|idx| self.get(idx)).collect();
+        let lines: Vec<&Line> = refs.iter().map(|line| &**line).collect();
+        f(&lines)
+    }
+
+    /// Calls f with mutable references to the lines in range, which
+    /// is clamped to the bounds of the store
+    pub fn with_range_mut<F, R>(&mut self, range: Range<usize>, f: F) -> R
+    where
+        F: FnOnce(&mut [&mut Line]) -> R,
+    {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        let mut first_seg = self.segments.len();
+        if start < end && start < self.frozen_rows {
+            self.limit_thawed();
+            first_seg = self.locate(start).0;
+            let last_seg = self.locate(end.min(self.frozen_rows) - 1).0;
+            for seg_idx in first_seg..=last_seg {
+                self.thaw(seg_idx, false);
+            }
+        }
+
+        let mut lines: Vec<&mut Line> = Vec::with_capacity(end - start);
+        let mut seg_start = self.segment_start(first_seg.min(self.segments.len()));
+        for seg in self.segments.iter_mut().skip(first_seg) {
+            if seg_start >= end {
+                break;
+            }
+            if let SegmentData::Thawed(thawed) = &mut seg.data {
+                lines.extend(
+                    thawed
+                        .lines
+                        .iter_mut()
+                        .skip(start.saturating_sub(seg_start))
+                        .take(end - start.max(seg_start)),
+                );
+            }
+            seg_start += seg.len;
+        }
+        let hot_start = self.frozen_rows;
+        if end > hot_start {
+            lines.extend(
+                self.hot
+                    .iter_mut()
+                    .skip(start.saturating_sub(hot_start))
+                    .take(end - start.max(hot_start)),
+            );
+        }
+        f(&mut lines)
+    }
+
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &Line),
+    {
+        let mut idx = 0;
+        for seg in &self.segments {
+            match &seg.data {
+                SegmentData::Thawed(thawed) => {
+                    for line in &thawed.lines {
+                        f(idx, line);
+                        idx += 1;
+                    }
+                }
+                SegmentData::Frozen(frozen) => {
+                    for line in self.decoded(seg, frozen).iter() {
+                        f(idx, line);
+                        idx += 1;
+                    }
+                }
+            }
+        }
+        for line in &self.hot {
+            f(idx, line);
+            idx += 1;
+        }
+    }
+
+    /// Calls f with each line in turn.  Compressed segments are
+    /// decoded one at a time and are only encoded again if f
+    /// modified them.
+    pub fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut Line),
+    {
+        let spill_dir = self.spill_dir();
+        let cache = self.cache.get_mut().unwrap();
+        let mut idx = 0;
+        for seg in self.segments.iter_mut() {
+            match &mut seg.data {
+                SegmentData::Thawed(thawed) => {
+                    for line in thawed.lines.iter_mut() {
+                        f(idx, line);
+                        idx += 1;
+                    }
+                }
+                SegmentData::Frozen(frozen) => {
+                    let original = match cache.pop(&seg.id) {
+                        Some(decoded) => decoded,
+                        None => frozen.decode(seg.len, seg.seqno),
+                    };
+                    let mut lines: VecDeque<Line> = original.lines.iter().cloned().collect();
+                    for line in lines.iter_mut() {
+                        f(idx, line);
+                        idx += 1;
+                    }
+                    let original = Some((frozen.clone(), original));
+                    seg.data = refreeze(lines, original, spill_dir.as_ref());
+                }
+            }
+        }
+        for line in self.hot.iter_mut() {
+            f(idx, line);
+            idx += 1;
+        }
+    }
+}
+
+impl IntoIterator for LineStore {
+    type Item = Line;
+    type IntoIter = IntoIter;
+
+    /// Returns the lines in order, decoding the segments one at a time
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            segments: self.segments,
+            current: VecDeque::new(),
+            hot: self.hot,
+        }
+    }
+}
+
+pub(crate) struct IntoIter {
+    segments: VecDeque<Segment>,
+    current: VecDeque<Line>,
+    hot: VecDeque<Line>,
+}
+
+impl Iterator for IntoIter {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        loop {
+            if let Some(line) = self.current.pop_front() {
+                return Some(line);
+            }
+            let seg = match self.segments.pop_front() {
+                Some(seg) => seg,
+                None => return self.hot.pop_front(),
+            };
+            self.current = match seg.data {
+                SegmentData::Thawed(thawed) => thawed.lines,
+                SegmentData::Frozen(frozen) => {
+                    let decoded = frozen.decode(seg.len, seg.seqno);
+                    match Arc::try_unwrap(decoded.lines) {
+                        Ok(lines) => lines.into(),
+                        Err(lines) => lines.iter().cloned().collect(),
+                    }
+                }
+            };
+        }
+    }
+}
+
+#[cfg(all(test, feature = "use_serde"))]
+mod test {
+    use super::*;
+
+    fn line(n: usize) -> Line {
+        let mut line = Line::from_text(&format!("line {}", n), &Default::default(), 1, None);
+        line.compress_for_scrollback();
+        line
+    }
+
+    fn text(line: &Line) -> String {
+        line.as_str().to_string()
+    }
+
+    fn store(num_lines: usize, keep: usize, spill: bool) -> LineStore {
+        let mut store = LineStore::default();
+        for n in 0..num_lines {
+            store.push_back(line(n));
+            store.maintain(keep, spill);
+        }
+        store
+    }
+
+    fn assert_contents(store: &LineStore, first: usize) {
+        let mut expect = first;
+        store.for_each(|idx, line| {
+            assert_eq!(text(line), format!("line {}", expect), "at idx {}", idx);
+            expect += 1;
+        });
+        assert_eq!(expect - first, store.len());
+    }
+
+    #[test]
+    fn compress_and_access() {
+        let mut store = store(SEGMENT_ROWS * 8 + 10, 100, false);
+        assert_eq!(store.len(), SEGMENT_ROWS * 8 + 10);
+        assert_eq!(store.segments.len(), 7);
+        assert!(store.hot.len() < 100 + SEGMENT_ROWS);
+        assert_contents(&store, 0);
+
+        assert_eq!(text(&store.get(1500).unwrap()), "line 1500");
+        store.with_range(SEGMENT_ROWS - 2..SEGMENT_ROWS + 2, |lines| {
+            let lines: Vec<String> = lines.iter().map(|l| text(l)).collect();
+            assert_eq!(
+                lines,
+                (SEGMENT_ROWS - 2..SEGMENT_ROWS + 2)
+                    .map(|n| format!("line {}", n))
+                    .collect::<Vec<_>>()
+            );
+        });
+
+        // Modifications to compressed lines are retained
+        store.get_mut(10).unwrap().set_marked(true, 2);
+        store.with_range_mut(SEGMENT_ROWS * 3..SEGMENT_ROWS * 5, |lines| {
+            assert_eq!(lines.len(), SEGMENT_ROWS * 2);
+            lines[0].set_marked(true, 2);
+        });
+        for n in 0..store.segments.len() {
+            assert!(store.get_mut(n * SEGMENT_ROWS).is_some());
+        }
+        store.maintain(100, false);
+        assert_eq!(store.thawed.len(), MAX_THAWED_SEGMENTS);
+        assert!(matches!(store.segments[0].data, SegmentData::Frozen(_)));
+        assert!(matches!(store.segments[3].data, SegmentData::Frozen(_)));
+        assert!(store.get(10).unwrap().is_marked());
+        assert!(store.get(SEGMENT_ROWS * 3).unwrap().is_marked());
+        assert!(!store.get(11).unwrap().is_marked());
+        assert_contents(&store, 0);
+    }
+
+    #[test]
+    fn pop_and_truncate() {
+        let mut store = store(SEGMENT_ROWS * 4, 10, false);
+        assert_eq!(text(&store.pop_front().unwrap()), "line 0");
+        assert_eq!(text(&store.remove(0).unwrap()), "line 1");
+        assert_contents(&store, 2);
+
+        store.truncate_front(SEGMENT_ROWS + 10);
+        assert_contents(&store, SEGMENT_ROWS + 12);
+
+        // Popping beyond the uncompressed lines decodes the last segment
+        let first = SEGMENT_ROWS + 12;
+        let mut last = first + store.len() - 1;
+        while !store.hot.is_empty() {
+            assert_eq!(text(&store.pop_back().unwrap()), format!("line {}", last));
+            last -= 1;
+        }
+        assert_eq!(text(&store.pop_back().unwrap()), format!("line {}", last));
+        assert_contents(&store, first);
+
+        let lines: Vec<String> = store.clone().into_iter().map(|l| text(&l)).collect();
+        assert_eq!(lines.len(), store.len());
+        assert_eq!(lines[0], format!("line {}", SEGMENT_ROWS + 12));
+    }
+
+    #[test]
+    fn spill_to_disk() {
+        let mut store = store(SEGMENT_ROWS * 3, 10, true);
+        let dir = store.spill_dir.as_ref().unwrap().dir.path().to_path_buf();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        assert_contents(&store, 0);
+
+        store.truncate_front(SEGMENT_ROWS);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert_contents(&store, SEGMENT_ROWS);
+
+        drop(store);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn seqno_updates_keep_cache() {
+        let mut store = store(SEGMENT_ROWS * 2, 10, false);
+        assert_eq!(text(&store.get(0).unwrap()), "line 0");
+        assert_eq!(store.cache.get_mut().unwrap().len(), 1);
+
+        store.update_last_change_seqno(42);
+        assert_eq!(store.cache.get_mut().unwrap().len(), 1);
+        assert_eq!(store.get(0).unwrap().current_seqno(), 42);
+        assert_eq!(store.get(SEGMENT_ROWS - 1).unwrap().current_seqno(), 42);
+        assert_contents(&store, 0);
+    }
+
+    #[test]
+    fn undecodable_segment_is_kept() {
+        let num_lines = SEGMENT_ROWS * (MAX_THAWED_SEGMENTS + 2) + 10;
+        let mut store = store(num_lines, 10, false);
+        let garbage = Arc::new(b"not a segment".to_vec());
+        store.segments[0].data = SegmentData::Frozen(Frozen::Compressed(Arc::clone(&garbage)));
+        let is_garbage = |store: &LineStore| match &store.segments[0].data {
+            SegmentData::Frozen(Frozen::Compressed(data)) => Arc::ptr_eq(data, &garbage),
+            _ => false,
+        };
+
+        // Reads see blank lines in place of the segment
+        assert_eq!(text(&store.get(1).unwrap()), "");
+        assert_eq!(
+            text(&store.get(SEGMENT_ROWS).unwrap()),
+            format!("line {}", SEGMENT_ROWS)
+        );
+        store.for_each_mut(|_, line| line.set_marked(true, 2));
+        assert!(is_garbage(&store));
+
+        // Modifying the placeholders doesn't replace the data when
+        // the segment is compressed again
+        store.get_mut(1).unwrap().set_marked(true, 2);
+        for n in 1..=MAX_THAWED_SEGMENTS {
+            store.get_mut(n * SEGMENT_ROWS).unwrap();
+        }
+        store.maintain(10, false);
+        assert!(is_garbage(&store));
+        assert_eq!(store.len(), num_lines);
+    }
+}
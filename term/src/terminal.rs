@@ -59,6 +59,19 @@ pub enum Alert {
     /// When something bumps the seqno in the terminal model and
     /// the terminal is not focused
     OutputSinceFocusLost,
+    /// The iTerm2 `SetProfile` escape sequence was used to request
+    /// a change to the named profile
+    SetProfile(String),
+    /// The iTerm2 badge format has been changed (or cleared)
+    BadgeFormatChanged(Option<String>),
+    /// A command delimited by OSC 133 semantic prompt escapes
+    /// has finished running
+    CommandFinished {
+        /// How long the command ran for
+        duration: std::time::Duration,
+        /// The exit status reported by the shell, if any
+        exit_status: Option<i32>,
+    },
 }
 
 pub trait AlertHandler: Send + Sync {
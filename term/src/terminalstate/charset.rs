@@ -0,0 +1,282 @@
+//! Character set remapping for the DEC Special Graphics, DEC
+//! Supplemental, DEC Technical and National Replacement Character Sets.
+//! wezterm is a UTF-8 terminal, so these only ever apply to the
+//! graphic left (GL) half of the code table.
+use termwiz::escape::esc::CharacterSet;
+
+/// Map `c`, a character from the GL half of the code table, through
+/// `charset`.  Returns None if the character is unchanged.
+pub(crate) fn map_char(charset: CharacterSet, c: char) -> Option<char> {
+    use CharacterSet::*;
+    if !(' '..='\x7f').contains(&c) {
+        return None;
+    }
+    let mapped = match charset {
+        Ascii => return None,
+        Uk => match c {
+            '#' => '£',
+            _ => return None,
+        },
+        DecSpecialGraphics => match c {
+            '`' => '◆',
+            'a' => '▒',
+            'b' => '␉',
+            'c' => '␌',
+            'd' => '␍',
+            'e' => '␊',
+            'f' => '°',
+            'g' => '±',
+            'h' => '␤',
+            'i' => '␋',
+            'j' => '┘',
+            'k' => '┐',
+            'l' => '┌',
+            'm' => '└',
+            'n' => '┼',
+            'o' => '⎺',
+            'p' => '⎻',
+            'q' => '─',
+            'r' => '⎼',
+            's' => '⎽',
+            't' => '├',
+            'u' => '┤',
+            'v' => '┴',
+            'w' => '┬',
+            'x' => '│',
+            'y' => '≤',
+            'z' => '≥',
+            '{' => 'π',
+            '|' => '≠',
+            '}' => '£',
+            '~' => '·',
+            _ => return None,
+        },
+        DecSupplemental | DecSupplementalGraphics => match c {
+            ' ' | '\x7f' => return None,
+            // These differ from ISO Latin-1
+            '(' => '¤',
+            'W' => 'Œ',
+            ']' => 'Ÿ',
+            'w' => 'œ',
+            '}' => 'ÿ',
+            _ => char::from_u32(c as u32 + 0x80)?,
+        },
+        IsoLatin1Supplemental => char::from_u32(c as u32 + 0x80)?,
+        DecTechnical => match c {
+            '!' => '⎷',
+            '"' => '┌',
+            '#' => '─',
+            '$' => '⌠',
+            '%' => '⌡',
+            '&' => '│',
+            '\'' => '⎡',
+            '(' => '⎣',
+            ')' => '⎤',
+            '*' => '⎦',
+            '+' => '⎛',
+            ',' => '⎝',
+            '-' => '⎞',
+            '.' => '⎠',
+            '/' => '⎨',
+            '0' => '⎬',
+            '<' => '≤',
+            '=' => '≠',
+            '>' => '≥',
+            '?' => '∫',
+            '@' => '∴',
+            'A' => '∝',
+            'B' => '∞',
+            'C' => '÷',
+            'D' => 'Δ',
+            'E' => '∇',
+            'F' => 'Φ',
+            'G' => 'Γ',
+            'H' => '∼',
+            'I' => '≃',
+            'J' => 'Θ',
+            'K' => '×',
+            'L' => 'Λ',
+            'M' => '⇔',
+            'N' => '⇒',
+            'O' => '≡',
+            'P' => 'Π',
+            'Q' => 'Ψ',
+            'S' => 'Σ',
+            'V' => '√',
+            'W' => 'Ω',
+            'X' => 'Ξ',
+            'Y' => 'Υ',
+            'Z' => '⊂',
+            '[' => '⊃',
+            '\\' => '∩',
+            ']' => '∪',
+            '^' => '∧',
+            '_' => '∨',
+            '`' => '¬',
+            'a' => 'α',
+            'b' => 'β',
+            'c' => 'χ',
+            'd' => 'δ',
+            'e' => 'ε',
+            'f' => 'φ',
+            'g' => 'γ',
+            'h' => 'η',
+            'i' => 'ι',
+            'j' => 'θ',
+            'k' => 'κ',
+            'l' => 'λ',
+            'n' => 'ν',
+            'o' => '∂',
+            'p' => 'π',
+            'q' => 'ψ',
+            'r' => 'ρ',
+            's' => 'σ',
+            't' => 'τ',
+            'v' => 'ƒ',
+            'w' => 'ω',
+            'x' => 'ξ',
+            'y' => 'υ',
+            'z' => 'ζ',
+            '{' => '←',
+            '|' => '↑',
+            '}' => '→',
+            '~' => '↓',
+            _ => return None,
+        },
+        Dutch => match c {
+            '#' => '£',
+            '@' => '¾',
+            '[' => 'ĳ',
+            '\\' => '½',
+            ']' => '|',
+            '{' => '¨',
+            '|' => 'ƒ',
+            '}' => '¼',
+            '~' => '´',
+            _ => return None,
+        },
+        Finnish => match c {
+            '[' => 'Ä',
+            '\\' => 'Ö',
+            ']' => 'Å',
+            '^' => 'Ü',
+            '`' => 'é',
+            '{' => 'ä',
+            '|' => 'ö',
+            '}' => 'å',
+            '~' => 'ü',
+            _ => return None,
+        },
+        French => match c {
+            '#' => '£',
+            '@' => 'à',
+            '[' => '°',
+            '\\' => 'ç',
+            ']' => '§',
+            '{' => 'é',
+            '|' => 'ù',
+            '}' => 'è',
+            '~' => '¨',
+            _ => return None,
+        },
+        FrenchCanadian => match c {
+            '@' => 'à',
+            '[' => 'â',
+            '\\' => 'ç',
+            ']' => 'ê',
+            '^' => 'î',
+            '`' => 'ô',
+            '{' => 'é',
+            '|' => 'ù',
+            '}' => 'è',
+            '~' => 'û',
+            _ => return None,
+        },
+        German => match c {
+            '@' => '§',
+            '[' => 'Ä',
+            '\\' => 'Ö',
+            ']' => 'Ü',
+            '{' => 'ä',
+            '|' => 'ö',
+            '}' => 'ü',
+            '~' => 'ß',
+            _ => return None,
+        },
+        Italian => match c {
+            '#' => '£',
+            '@' => '§',
+            '[' => '°',
+            '\\' => 'ç',
+            ']' => 'é',
+            '`' => 'ù',
+            '{' => 'à',
+            '|' => 'ò',
+            '}' => 'è',
+            '~' => 'ì',
+            _ => return None,
+        },
+        NorwegianDanish => match c {
+            '@' => 'Ä',
+            '[' => 'Æ',
+            '\\' => 'Ø',
+            ']' => 'Å',
+            '^' => 'Ü',
+            '`' => 'ä',
+            '{' => 'æ',
+            '|' => 'ø',
+            '}' => 'å',
+            '~' => 'ü',
+            _ => return None,
+        },
+        Portuguese => match c {
+            '[' => 'Ã',
+            '\\' => 'Ç',
+            ']' => 'Õ',
+            '{' => 'ã',
+            '|' => 'ç',
+            '}' => 'õ',
+            _ => return None,
+        },
+        Spanish => match c {
+            '#' => '£',
+            '@' => '§',
+            '[' => '¡',
+            '\\' => 'Ñ',
+            ']' => '¿',
+            '{' => '°',
+            '|' => 'ñ',
+            '}' => 'ç',
+            _ => return None,
+        },
+        Swedish => match c {
+            '@' => 'É',
+            '[' => 'Ä',
+            '\\' => 'Ö',
+            ']' => 'Å',
+            '^' => 'Ü',
+            '`' => 'é',
+            '{' => 'ä',
+            '|' => 'ö',
+            '}' => 'å',
+            '~' => 'ü',
+            _ => return None,
+        },
+        Swiss => match c {
+            '#' => 'ù',
+            '@' => 'à',
+            '[' => 'é',
+            '\\' => 'ç',
+            ']' => 'ê',
+            '^' => 'î',
+            '_' => 'è',
+            '`' => 'ô',
+            '{' => 'ä',
+            '|' => 'ö',
+            '}' => 'ü',
+            '~' => 'û',
+            _ => return None,
+        },
+    };
+    Some(mapped)
+}
@@ -0,0 +1,231 @@
+//! Tracks the shell commands delimited by the OSC 133 semantic
+//! prompt escapes so that their exit status and timing can be
+//! queried after the fact.
+use crate::{Alert, CommandBlock, SemanticType, SemanticZone, StableRowIndex, TerminalState};
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+/// Bound the number of commands that we remember.  In practice
+/// commands are discarded once they scroll out of the scrollback,
+/// but this guards against a shell that emits a prompt per line.
+const MAX_COMMANDS: usize = 10_000;
+
+/// A cursor position; column and stable row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Marker {
+    y: StableRowIndex,
+    x: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CommandRecord {
+    prompt: Option<Marker>,
+    input: Option<Marker>,
+    output: Option<Marker>,
+    end: Option<Marker>,
+    exit_status: Option<i32>,
+    start_time: Option<SystemTime>,
+    end_time: Option<SystemTime>,
+    cwd: Option<String>,
+}
+
+impl CommandRecord {
+    fn first_row(&self) -> Option<StableRowIndex> {
+        self.prompt.or(self.input).or(self.output).map(|m| m.y)
+    }
+}
+
+pub(crate) type CommandRecords = VecDeque<CommandRecord>;
+
+impl TerminalState {
+    fn command_marker(&self) -> Marker {
+        Marker {
+            y: self.screen().visible_row_to_stable_row(self.cursor.y),
+            x: self.cursor.x,
+        }
+    }
+
+    /// We only track commands in the primary screen; the alternate
+    /// screen has no scrollback and its stable row indices overlap
+    /// with those of the primary.
+    fn tracking_commands(&self) -> bool {
+        !self.screen.is_alt_screen_active()
+    }
+
+    fn push_command(&mut self, record: CommandRecord) {
+        let first_row = self.screen().phys_to_stable_row_index(0);
+        while let Some(front) = self.commands.front() {
+            let expired = match front.end.map(|m| m.y).or(front.first_row()) {
+                Some(row) => row < first_row,
+                None => true,
+            };
+            if !expired && self.commands.len() < MAX_COMMANDS {
+                break;
+            }
+            self.commands.pop_front();
+        }
+        self.commands.push_back(record);
+    }
+
+    /// OSC 133;D, or the implied end of the prior command when the
+    /// next prompt is started.  The status is only recorded if the
+    /// command was actually started; shells report a status even
+    /// when an empty command line was submitted.
+    pub(crate) fn command_finished(&mut self, status: Option<i32>) {
+        if !self.tracking_commands() {
+            return;
+        }
+        let marker = self.command_marker();
+        let mut finished = None;
+        if let Some(record) = self.commands.back_mut() {
+            if record.end.is_none() {
+                record.end.replace(marker);
+                if record.output.is_some() {
+                    let now = SystemTime::now();
+                    record.end_time.replace(now);
+                    record.exit_status = status;
+                    finished = record
+                        .start_time
+                        .and_then(|start| now.duration_since(start).ok());
+                }
+            }
+        }
+        if let (Some(duration), Some(handler)) = (finished, self.alert_handler.as_mut()) {
+            handler.alert(Alert::CommandFinished {
+                duration,
+                exit_status: status,
+            });
+        }
+    }
+
+    /// OSC 133;A or OSC 133;N
+    pub(crate) fn command_prompt_started(&mut self) {
+        if !self.tracking_commands() {
+            return;
+        }
+        let marker = self.command_marker();
+        if let Some(record) = self.commands.back_mut() {
+            if record.input.is_none() && record.output.is_none() {
+                // The shell is re-drawing the prompt, perhaps because
+                // the window was resized; don't treat it as a new command
+                record.prompt.replace(marker);
+                record.end = None;
+                return;
+            }
+        }
+        self.push_command(CommandRecord {
+            prompt: Some(marker),
+            ..CommandRecord::default()
+        });
+    }
+
+    /// OSC 133;B or OSC 133;I
+    pub(crate) fn command_input_started(&mut self) {
+        if !self.tracking_commands() {
+            return;
+        }
+        let marker = self.command_marker();
+        if let Some(record) = self.commands.back_mut() {
+            if record.output.is_none() && record.end.is_none() {
+                record.input.replace(marker);
+                return;
+            }
+        }
+        self.push_command(CommandRecord {
+            input: Some(marker),
+            ..CommandRecord::default()
+        });
+    }
+
+    /// OSC 133;C
+    pub(crate) fn command_output_started(&mut self) {
+        if !self.tracking_commands() {
+            return;
+        }
+        let marker = self.command_marker();
+        let cwd = self.current_dir.as_ref().map(|url| url.to_string());
+        match self.commands.back_mut() {
+            Some(record) if record.output.is_none() && record.end.is_none() => {
+                record.output.replace(marker);
+                record.start_time.replace(SystemTime::now());
+                record.cwd = cwd;
+            }
+            _ => {
+                self.push_command(CommandRecord {
+                    output: Some(marker),
+                    start_time: Some(SystemTime::now()),
+                    cwd,
+                    ..CommandRecord::default()
+                });
+            }
+        }
+    }
+
+    /// Returns the commands that are still (at least partially)
+    /// present in the scrollback, in the order that they were run.
+    pub fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        // Always consult the primary screen, even if the alternate
+        // screen is active
+        let screen = &self.screen.screen;
+        let first_row = screen.phys_to_stable_row_index(0);
+        let last_col = screen.physical_cols.saturating_sub(1);
+        // Where to end any zone that is still open
+        let cursor = if self.tracking_commands() {
+            self.command_marker()
+        } else {
+            Marker {
+                y: screen.visible_row_to_stable_row(screen.physical_rows as i64),
+                x: 0,
+            }
+        };
+
+        // Produce a zone covering start up to, but not including, end
+        let zone = |start: Marker, end: Marker, semantic_type: SemanticType| {
+            if end <= start {
+                return None;
+            }
+            let (end_x, end_y) = if end.x > 0 {
+                (end.x - 1, end.y)
+            } else {
+                (last_col, end.y - 1)
+            };
+            Some(SemanticZone {
+                start_x: start.x,
+                start_y: start.y,
+                end_x,
+                end_y,
+                semantic_type,
+            })
+        };
+
+        self.commands
+            .iter()
+            .filter(
+                |record| match record.end.map(|m| m.y).or(record.first_row()) {
+                    Some(row) => row >= first_row,
+                    None => false,
+                },
+            )
+            .map(|record| {
+                let end = record.end.unwrap_or(cursor);
+                CommandBlock {
+                    prompt: record.prompt.and_then(|start| {
+                        let end = record.input.or(record.output).unwrap_or(end);
+                        zone(start, end, SemanticType::Prompt)
+                    }),
+                    input: record.input.and_then(|start| {
+                        let end = record.output.unwrap_or(end);
+                        zone(start, end, SemanticType::Input)
+                    }),
+                    output: record
+                        .output
+                        .and_then(|start| zone(start, end, SemanticType::Output)),
+                    exit_status: record.exit_status,
+                    start_time: record.start_time,
+                    end_time: record.end_time,
+                    cwd: record.cwd.clone(),
+                }
+            })
+            .collect()
+    }
+}
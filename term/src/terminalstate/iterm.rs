@@ -3,6 +3,7 @@ use crate::TerminalState;
 use ::image::imageops::FilterType;
 use ::image::ImageFormat;
 use log::error;
+use std::collections::HashMap;
 use termwiz::escape::osc::ITermFileData;
 use termwiz::image::ImageDataType;
 
@@ -151,3 +152,34 @@ impl TerminalState {
         }
     }
 }
+
+/// Evaluate an iTerm2 badge format string.
+/// iTerm2 badges can interpolate variables using `\(name)`; we support
+/// the `user.NAME` variables that are set via the `SetUserVar` escape
+/// sequence.  Unknown variables expand to the empty string.
+pub fn evaluate_badge_format(format: &str, user_vars: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut remain = format;
+
+    while let Some(start) = remain.find("\\(") {
+        result.push_str(&remain[..start]);
+        let after = &remain[start + 2..];
+        match after.find(')') {
+            Some(end) => {
+                let name = after[..end].trim();
+                let name = name.strip_prefix("user.").unwrap_or(name);
+                if let Some(value) = user_vars.get(name) {
+                    result.push_str(value);
+                }
+                remain = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&remain[start..]);
+                remain = "";
+            }
+        }
+    }
+    result.push_str(remain);
+
+    result
+}
@@ -30,16 +30,19 @@ impl TerminalState {
     ) -> anyhow::Result<()> {
         let encoding = self.effective_keyboard_encoding();
 
-        let to_send = key.encode(
-            mods,
-            KeyCodeEncodeModes {
-                encoding,
-                newline_mode: self.newline_mode,
-                application_cursor_keys: self.application_cursor_keys,
-                modify_other_keys: self.modify_other_keys,
-            },
-            is_down,
-        )?;
+        let to_send = match self.vt52_key(key, mods, is_down) {
+            Some(to_send) => to_send.to_string(),
+            None => key.encode(
+                mods,
+                KeyCodeEncodeModes {
+                    encoding,
+                    newline_mode: self.newline_mode,
+                    application_cursor_keys: self.application_cursor_keys,
+                    modify_other_keys: self.modify_other_keys,
+                },
+                is_down,
+            )?,
+        };
 
         if to_send.is_empty() {
             return Ok(());
@@ -57,6 +60,24 @@ impl TerminalState {
         Ok(())
     }
 
+    /// In VT52 mode, the cursor and PF keys send different sequences
+    fn vt52_key(&self, key: KeyCode, mods: KeyModifiers, is_down: bool) -> Option<&'static str> {
+        if self.dec_ansi_mode || !is_down || !mods.is_empty() {
+            return None;
+        }
+        match key {
+            KeyCode::UpArrow | KeyCode::ApplicationUpArrow => Some("\x1bA"),
+            KeyCode::DownArrow | KeyCode::ApplicationDownArrow => Some("\x1bB"),
+            KeyCode::RightArrow | KeyCode::ApplicationRightArrow => Some("\x1bC"),
+            KeyCode::LeftArrow | KeyCode::ApplicationLeftArrow => Some("\x1bD"),
+            KeyCode::Function(1) => Some("\x1bP"),
+            KeyCode::Function(2) => Some("\x1bQ"),
+            KeyCode::Function(3) => Some("\x1bR"),
+            KeyCode::Function(4) => Some("\x1bS"),
+            _ => None,
+        }
+    }
+
     pub fn key_up(&mut self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         self.key_up_down(key, mods, false)
     }
@@ -17,6 +17,7 @@ use termwiz::escape::csi::{
     EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
     XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus, XtermKeyModifierResource,
 };
+use termwiz::escape::esc::{CharacterSet, CharsetIndex};
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::image::ImageData;
 use termwiz::input::KeyboardEncoding;
@@ -24,8 +25,11 @@ use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
 
+mod charset;
+mod commands;
 mod image;
 mod iterm;
+pub use iterm::evaluate_badge_format;
 mod keyboard;
 mod kitty;
 mod mouse;
@@ -46,13 +50,6 @@ pub(crate) struct TabStop {
     tab_width: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum CharSet {
-    Ascii,
-    Uk,
-    DecLineDrawing,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum MouseEncoding {
     X10,
@@ -135,8 +132,10 @@ struct SavedCursor {
     wrap_next: bool,
     pen: CellAttributes,
     dec_origin_mode: bool,
-    g0_charset: CharSet,
-    g1_charset: CharSet,
+    g0_charset: CharacterSet,
+    g1_charset: CharacterSet,
+    g2_charset: CharacterSet,
+    g3_charset: CharacterSet,
     // TODO: selective_erase when supported
 }
 
@@ -293,6 +292,7 @@ pub struct TerminalState {
     application_cursor_keys: bool,
     modify_other_keys: Option<i64>,
 
+    /// DECANM; when reset, the terminal is in VT52 compatibility mode
     dec_ansi_mode: bool,
 
     /// https://vt100.net/dec/ek-vt38t-ug-001.pdf#page=132 has a
@@ -326,9 +326,15 @@ pub struct TerminalState {
 
     keyboard_encoding: KeyboardEncoding,
     /// Support for US, UK, and DEC Special Graphics
-    g0_charset: CharSet,
-    g1_charset: CharSet,
-    shift_out: bool,
+    g0_charset: CharacterSet,
+    g1_charset: CharacterSet,
+    g2_charset: CharacterSet,
+    g3_charset: CharacterSet,
+    /// The character set that is invoked into GL by SI, SO, LS2 or LS3
+    gl_charset: CharsetIndex,
+    /// Set by SS2 or SS3 to select the character set for the next
+    /// printed character only
+    single_shift: Option<CharsetIndex>,
 
     newline_mode: bool,
 
@@ -377,6 +383,12 @@ pub struct TerminalState {
     suppress_initial_title_change: bool,
 
     accumulating_title: Option<String>,
+    /// Text captured between iTerm2 CopyToClipboard and EndCopy
+    accumulating_clipboard: Option<String>,
+    /// The iTerm2 badge format string, if any
+    badge_format: Option<String>,
+    /// Shell commands delimited by OSC 133 semantic prompts
+    commands: commands::CommandRecords,
 
     lost_focus_seqno: SequenceNo,
     focused: bool,
@@ -530,7 +542,7 @@ impl TerminalState {
             insert: false,
             application_cursor_keys: false,
             modify_other_keys: None,
-            dec_ansi_mode: false,
+            dec_ansi_mode: true,
             sixel_display_mode: false,
             use_private_color_registers_for_each_graphic: false,
             color_map,
@@ -545,9 +557,12 @@ impl TerminalState {
             mouse_tracking: false,
             last_mouse_move: None,
             cursor_visible: true,
-            g0_charset: CharSet::Ascii,
-            g1_charset: CharSet::DecLineDrawing,
-            shift_out: false,
+            g0_charset: CharacterSet::Ascii,
+            g1_charset: CharacterSet::DecSpecialGraphics,
+            g2_charset: CharacterSet::DecSupplemental,
+            g3_charset: CharacterSet::DecSupplemental,
+            gl_charset: CharsetIndex::G0,
+            single_shift: None,
             newline_mode: false,
             current_mouse_buttons: vec![],
             tabs: TabStop::new(size.cols, 8),
@@ -574,6 +589,9 @@ impl TerminalState {
             suppress_initial_title_change: false,
             enable_conpty_quirks: false,
             accumulating_title: None,
+            accumulating_clipboard: None,
+            badge_format: None,
+            commands: Default::default(),
             lost_focus_seqno: seqno,
             focused: true,
             bidi_enabled: None,
@@ -892,10 +910,7 @@ impl TerminalState {
     /// When dealing with selection, mark a range of lines as dirty
     pub fn make_all_lines_dirty(&mut self) {
         let seqno = self.seqno;
-        let screen = self.screen_mut();
-        screen.for_each_phys_line_mut(|_, line| {
-            line.update_last_change_seqno(seqno);
-        });
+        self.screen_mut().dirty_all_lines(seqno);
     }
 
     /// Returns the 0-based cursor position relative to the top left of
@@ -918,6 +933,15 @@ impl TerminalState {
         &self.user_vars
     }
 
+    /// Set a user var, as though the iTerm2 SetUserVar escape
+    /// sequence had been processed
+    pub fn set_user_var(&mut self, name: String, value: String) {
+        self.user_vars.insert(name.clone(), value.clone());
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(Alert::SetUserVar { name, value });
+        }
+    }
+
     fn clear_semantic_attribute_due_to_movement(&mut self) {
         if self.clear_semantic_attribute_on_newline {
             self.clear_semantic_attribute_on_newline = false;
@@ -2503,6 +2527,8 @@ impl TerminalState {
             dec_origin_mode: self.dec_origin_mode,
             g0_charset: self.g0_charset,
             g1_charset: self.g1_charset,
+            g2_charset: self.g2_charset,
+            g3_charset: self.g3_charset,
         };
         debug!(
             "saving cursor {:?} is_alt={}",
@@ -2523,8 +2549,10 @@ impl TerminalState {
                 wrap_next: false,
                 pen: Default::default(),
                 dec_origin_mode: false,
-                g0_charset: CharSet::Ascii,
-                g1_charset: CharSet::DecLineDrawing,
+                g0_charset: CharacterSet::Ascii,
+                g1_charset: CharacterSet::DecSpecialGraphics,
+                g2_charset: CharacterSet::DecSupplemental,
+                g3_charset: CharacterSet::DecSupplemental,
             });
         debug!(
             "restore cursor {:?} is_alt={}",
@@ -2542,7 +2570,10 @@ impl TerminalState {
         self.dec_origin_mode = saved.dec_origin_mode;
         self.g0_charset = saved.g0_charset;
         self.g1_charset = saved.g1_charset;
-        self.shift_out = false;
+        self.g2_charset = saved.g2_charset;
+        self.g3_charset = saved.g3_charset;
+        self.gl_charset = CharsetIndex::G0;
+        self.single_shift = None;
         self.newline_mode = false;
     }
 
@@ -2605,47 +2636,47 @@ impl TerminalState {
     ///
     /// By default, all screen data is of type Output.  The shell needs to
     /// employ OSC 133 escapes to markup its output.
-    pub fn get_semantic_zones(&mut self) -> anyhow::Result<Vec<SemanticZone>> {
-        let screen = self.screen_mut();
+    pub fn get_semantic_zones(&self) -> anyhow::Result<Vec<SemanticZone>> {
+        let screen = self.screen();
 
-        let mut current_zone: Option<SemanticZone> = None;
-        let mut zones = vec![];
+        let mut zones = SemanticZoneBuilder::default();
 
         let first_stable_row = screen.phys_to_stable_row_index(0);
-        screen.for_each_phys_line_mut(|idx, line| {
-            let stable_row = first_stable_row + idx as StableRowIndex;
-
-            for zone_range in line.semantic_zone_ranges() {
-                let new_zone = match current_zone.as_ref() {
-                    None => true,
-                    Some(zone) => zone.semantic_type != zone_range.semantic_type,
-                };
-
-                if new_zone {
-                    if let Some(zone) = current_zone.take() {
-                        zones.push(zone);
-                    }
+        screen.for_each_phys_line(|idx, line| {
+            zones.add_line(first_stable_row + idx as StableRowIndex, line);
+        });
 
-                    current_zone.replace(SemanticZone {
-                        start_x: zone_range.range.start as usize,
-                        start_y: stable_row,
-                        end_x: zone_range.range.end as usize,
-                        end_y: stable_row,
-                        semantic_type: zone_range.semantic_type,
-                    });
-                }
+        Ok(zones.finish())
+    }
 
-                if let Some(zone) = current_zone.as_mut() {
-                    zone.end_x = zone_range.range.end as usize;
-                    zone.end_y = stable_row;
-                }
+    /// Returns the rows that have been marked by the iTerm2
+    /// `SetMark` escape sequence, in ascending order
+    pub fn get_marks(&self) -> Vec<StableRowIndex> {
+        let screen = self.screen();
+        let first_stable_row = screen.phys_to_stable_row_index(0);
+        let mut marks = vec![];
+        screen.for_each_phys_line(|idx, line| {
+            if line.is_marked() {
+                marks.push(first_stable_row + idx as StableRowIndex);
             }
         });
-        if let Some(zone) = current_zone.take() {
-            zones.push(zone);
-        }
+        marks
+    }
+
+    /// Returns the badge text for the pane, computed by evaluating
+    /// the badge format set by the iTerm2 `SetBadgeFormat` escape
+    /// sequence against the current user vars
+    pub fn get_badge(&self) -> Option<String> {
+        let format = self.badge_format.as_ref()?;
+        Some(evaluate_badge_format(format, &self.user_vars))
+    }
 
-        Ok(zones)
+    /// Resolve an iTerm2 variable name, as used by `ReportVariable`.
+    /// Only user vars, either with or without the `user.` prefix,
+    /// are known to us.
+    fn iterm_variable(&self, name: &str) -> Option<String> {
+        let name = name.strip_prefix("user.").unwrap_or(name);
+        self.user_vars.get(name).cloned()
     }
 
     #[inline]
@@ -1,7 +1,6 @@
 use crate::terminal::Alert;
-use crate::terminalstate::{
-    default_color_map, CharSet, MouseEncoding, TabStop, UnicodeVersionStackEntry,
-};
+use crate::terminalstate::charset::map_char;
+use crate::terminalstate::{default_color_map, MouseEncoding, TabStop, UnicodeVersionStackEntry};
 use crate::{ClipboardSelection, Position, TerminalState, VisibleRowIndex, DCS, ST};
 use finl_unicode::grapheme_clusters::Graphemes;
 use log::{debug, error};
@@ -14,18 +13,25 @@ use termwiz::cell::{grapheme_column_width, Cell, CellAttributes, SemanticType};
 use termwiz::escape::csi::{
     CharacterPath, EraseInDisplay, Keyboard, KittyKeyboardFlags, KittyKeyboardMode,
 };
+use termwiz::escape::csi::{Cursor, Edit, EraseInLine};
+use termwiz::escape::esc::{CharacterSet, CharsetIndex};
 use termwiz::escape::osc::{
     ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
     ITermUnicodeVersionOp, Selection,
 };
 use termwiz::escape::{
-    Action, ControlCode, DeviceControlMode, Esc, EscCode, OperatingSystemCommand, CSI,
+    Action, ControlCode, DeviceControlMode, Esc, EscCode, OneBased, OperatingSystemCommand, Vt52,
+    CSI,
 };
 use termwiz::input::KeyboardEncoding;
 use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
 
+/// The most text that we'll capture between iTerm2 CopyToClipboard
+/// and EndCopy.  If the output exceeds this, the copy is abandoned.
+const MAX_CLIPBOARD_ACCUMULATION: usize = 1024 * 1024;
+
 /// A helper struct for implementing `vtparse::VTActor` while compartmentalizing
 /// the terminal state and the embedding/host terminal interface
 pub(crate) struct Performer<'a> {
@@ -61,54 +67,43 @@ impl<'a> Performer<'a> {
         }
     }
 
-    /// Apply character set related remapping to the input glyph if required
-    fn remap_grapheme<'b>(&self, g: &'b str) -> &'b str {
-        if (self.shift_out && self.g1_charset == CharSet::DecLineDrawing)
-            || (!self.shift_out && self.g0_charset == CharSet::DecLineDrawing)
-        {
-            match g {
-                "`" => "◆",
-                "a" => "▒",
-                "b" => "␉",
-                "c" => "␌",
-                "d" => "␍",
-                "e" => "␊",
-                "f" => "°",
-                "g" => "±",
-                "h" => "␤",
-                "i" => "␋",
-                "j" => "┘",
-                "k" => "┐",
-                "l" => "┌",
-                "m" => "└",
-                "n" => "┼",
-                "o" => "⎺",
-                "p" => "⎻",
-                "q" => "─",
-                "r" => "⎼",
-                "s" => "⎽",
-                "t" => "├",
-                "u" => "┤",
-                "v" => "┴",
-                "w" => "┬",
-                "x" => "│",
-                "y" => "≤",
-                "z" => "≥",
-                "{" => "π",
-                "|" => "≠",
-                "}" => "£",
-                "~" => "·",
-                _ => g,
-            }
-        } else if (self.shift_out && self.g1_charset == CharSet::Uk)
-            || (!self.shift_out && self.g0_charset == CharSet::Uk)
-        {
-            match g {
-                "#" => "£",
-                _ => g,
-            }
-        } else {
-            g
+    /// Appends c to the text being captured for iTerm2 CopyToClipboard.
+    /// Returns false if no capture is in progress, or if it was
+    /// abandoned because it grew too large, in which case c should
+    /// be displayed instead.
+    fn accumulate_clipboard(&mut self, c: char) -> bool {
+        let text = match self.accumulating_clipboard.as_mut() {
+            Some(text) => text,
+            None => return false,
+        };
+        if text.len() + c.len_utf8() > MAX_CLIPBOARD_ACCUMULATION {
+            log::warn!(
+                "iTerm2 CopyToClipboard exceeded {} bytes; abandoning the copy",
+                MAX_CLIPBOARD_ACCUMULATION
+            );
+            self.accumulating_clipboard.take();
+            return false;
+        }
+        text.push(c);
+        true
+    }
+
+    /// Apply character set related remapping to the input glyph if required.
+    /// `buf` provides storage for the remapped glyph.
+    fn remap_grapheme<'b>(&mut self, g: &'b str, buf: &'b mut [u8; 4]) -> &'b str {
+        let index = self.single_shift.take().unwrap_or(self.gl_charset);
+        let charset = match index {
+            CharsetIndex::G0 => self.g0_charset,
+            CharsetIndex::G1 => self.g1_charset,
+            CharsetIndex::G2 => self.g2_charset,
+            CharsetIndex::G3 => self.g3_charset,
+        };
+        if charset == CharacterSet::Ascii || g.len() != 1 {
+            return g;
+        }
+        match g.chars().next().and_then(|c| map_char(charset, c)) {
+            Some(c) => c.encode_utf8(buf),
+            None => g,
         }
     }
 
@@ -130,7 +125,8 @@ impl<'a> Performer<'a> {
         };
 
         for g in Graphemes::new(text) {
-            let g = self.remap_grapheme(g);
+            let mut buf = [0u8; 4];
+            let g = self.remap_grapheme(g, &mut buf);
 
             let print_width = grapheme_column_width(g, Some(self.unicode_version));
             if print_width == 0 {
@@ -267,6 +263,7 @@ impl<'a> Performer<'a> {
                     log::error!("kitty_img: {:#}", err);
                 }
             }
+            Action::Vt52(vt52) => self.vt52_dispatch(vt52),
         }
     }
 
@@ -350,7 +347,7 @@ impl<'a> Performer<'a> {
         // We buffer up the chars to increase the chances of correctly grouping graphemes into cells
         if let Some(title) = self.accumulating_title.as_mut() {
             title.push(c);
-        } else {
+        } else if !self.accumulate_clipboard(c) {
             self.print.push(c);
         }
     }
@@ -358,6 +355,13 @@ impl<'a> Performer<'a> {
     fn control(&mut self, control: ControlCode) {
         let seqno = self.seqno;
         self.pop_tmux_title_state();
+        if self.accumulating_clipboard.is_some() {
+            // Output between iTerm2 CopyToClipboard and EndCopy
+            // goes to the clipboard rather than the display
+            if control != ControlCode::LineFeed || self.accumulate_clipboard('\n') {
+                return;
+            }
+        }
         self.flush_print();
         match control {
             ControlCode::LineFeed | ControlCode::VerticalTab | ControlCode::FormFeed => {
@@ -440,17 +444,22 @@ impl<'a> Performer<'a> {
             }
             ControlCode::RI => self.c1_reverse_index(),
 
-            // wezterm only supports UTF-8, so does not support the
-            // DEC National Replacement Character Sets.  However, it does
-            // support the DEC Special Graphics character set used by
-            // numerous ncurses applications.  DEC Special Graphics can be
-            // selected by ASCII Shift Out (0x0E, ^N) or by setting G0
-            // via ESC ( 0 .
+            // wezterm only supports UTF-8, so the character sets
+            // designated to G0-G3 only ever apply to GL.
+            // DEC Special Graphics, used by numerous ncurses
+            // applications, can be selected by ASCII Shift Out
+            // (0x0E, ^N) or by setting G0 via ESC ( 0 .
             ControlCode::ShiftIn => {
-                self.shift_out = false;
+                self.gl_charset = CharsetIndex::G0;
             }
             ControlCode::ShiftOut => {
-                self.shift_out = true;
+                self.gl_charset = CharsetIndex::G1;
+            }
+            ControlCode::SS2 => {
+                self.single_shift = Some(CharsetIndex::G2);
+            }
+            ControlCode::SS3 => {
+                self.single_shift = Some(CharsetIndex::G3);
             }
 
             ControlCode::Enquiry => {
@@ -565,6 +574,61 @@ impl<'a> Performer<'a> {
         };
     }
 
+    /// Process an escape sequence received in VT52 mode.
+    /// <https://vt100.net/docs/vt100-ug/chapter3.html#S3.3.5>
+    fn vt52_dispatch(&mut self, vt52: Vt52) {
+        self.flush_print();
+        match vt52 {
+            Vt52::CursorUp => self.csi_dispatch(CSI::Cursor(Cursor::Up(1))),
+            Vt52::CursorDown => self.csi_dispatch(CSI::Cursor(Cursor::Down(1))),
+            Vt52::CursorRight => self.csi_dispatch(CSI::Cursor(Cursor::Right(1))),
+            Vt52::CursorLeft => self.csi_dispatch(CSI::Cursor(Cursor::Left(1))),
+            Vt52::CursorHome => self.csi_dispatch(CSI::Cursor(Cursor::Position {
+                line: OneBased::new(1),
+                col: OneBased::new(1),
+            })),
+            Vt52::DirectCursorAddress { line, column } => {
+                self.set_cursor_pos(
+                    &Position::Absolute(column as i64),
+                    &Position::Absolute(line as i64),
+                );
+            }
+            Vt52::ReverseLineFeed => self.c1_reverse_index(),
+            Vt52::EraseToEndOfScreen => self.csi_dispatch(CSI::Edit(Edit::EraseInDisplay(
+                EraseInDisplay::EraseToEndOfDisplay,
+            ))),
+            Vt52::EraseToEndOfLine => {
+                self.csi_dispatch(CSI::Edit(Edit::EraseInLine(EraseInLine::EraseToEndOfLine)))
+            }
+            // The VT100 uses its special graphics set in VT52 mode,
+            // rather than the set used by the original VT52
+            Vt52::EnterGraphicsMode => {
+                self.g0_charset = CharacterSet::DecSpecialGraphics;
+            }
+            Vt52::ExitGraphicsMode => {
+                self.g0_charset = CharacterSet::Ascii;
+            }
+            Vt52::Identify => {
+                write!(self.writer, "\x1b/Z").ok();
+                self.writer.flush().ok();
+            }
+            Vt52::EnterAlternateKeypad => {
+                self.application_keypad = true;
+            }
+            Vt52::ExitAlternateKeypad => {
+                self.application_keypad = false;
+            }
+            Vt52::EnterAnsiMode => {
+                self.dec_ansi_mode = true;
+            }
+            Vt52::Unspecified(_) => {
+                if self.config.log_unknown_escape_sequences() {
+                    log::warn!("unknown VT52 escape: {:?}", vt52);
+                }
+            }
+        }
+    }
+
     fn esc_dispatch(&mut self, esc: Esc) {
         let seqno = self.seqno;
         self.flush_print();
@@ -595,22 +659,47 @@ impl<'a> Performer<'a> {
             Esc::Code(EscCode::NextLine) => self.c1_nel(),
             Esc::Code(EscCode::HorizontalTabSet) => self.c1_hts(),
             Esc::Code(EscCode::DecLineDrawingG0) => {
-                self.g0_charset = CharSet::DecLineDrawing;
+                self.g0_charset = CharacterSet::DecSpecialGraphics;
             }
             Esc::Code(EscCode::AsciiCharacterSetG0) => {
-                self.g0_charset = CharSet::Ascii;
+                self.g0_charset = CharacterSet::Ascii;
             }
             Esc::Code(EscCode::UkCharacterSetG0) => {
-                self.g0_charset = CharSet::Uk;
+                self.g0_charset = CharacterSet::Uk;
             }
             Esc::Code(EscCode::DecLineDrawingG1) => {
-                self.g1_charset = CharSet::DecLineDrawing;
+                self.g1_charset = CharacterSet::DecSpecialGraphics;
             }
             Esc::Code(EscCode::AsciiCharacterSetG1) => {
-                self.g1_charset = CharSet::Ascii;
+                self.g1_charset = CharacterSet::Ascii;
             }
             Esc::Code(EscCode::UkCharacterSetG1) => {
-                self.g1_charset = CharSet::Uk;
+                self.g1_charset = CharacterSet::Uk;
+            }
+            Esc::DesignateCharacterSet { index, charset } => match index {
+                CharsetIndex::G0 => self.g0_charset = charset,
+                CharsetIndex::G1 => self.g1_charset = charset,
+                CharsetIndex::G2 => self.g2_charset = charset,
+                CharsetIndex::G3 => self.g3_charset = charset,
+            },
+            Esc::Code(EscCode::LockingShiftG2) => {
+                self.gl_charset = CharsetIndex::G2;
+            }
+            Esc::Code(EscCode::LockingShiftG3) => {
+                self.gl_charset = CharsetIndex::G3;
+            }
+            Esc::Code(EscCode::SingleShiftG2) => {
+                self.single_shift = Some(CharsetIndex::G2);
+            }
+            Esc::Code(EscCode::SingleShiftG3) => {
+                self.single_shift = Some(CharsetIndex::G3);
+            }
+            Esc::Code(
+                EscCode::LockingShiftG1Right
+                | EscCode::LockingShiftG2Right
+                | EscCode::LockingShiftG3Right,
+            ) => {
+                // GR has no meaning for a UTF-8 terminal
             }
             Esc::Code(EscCode::DecSaveCursorPosition) => self.dec_save_cursor(),
             Esc::Code(EscCode::DecRestoreCursorPosition) => self.dec_restore_cursor(),
@@ -674,7 +763,7 @@ impl<'a> Performer<'a> {
                 self.color_map = default_color_map();
                 self.application_cursor_keys = false;
                 self.sixel_display_mode = false;
-                self.dec_ansi_mode = false;
+                self.dec_ansi_mode = true;
                 self.application_keypad = false;
                 self.bracketed_paste = false;
                 self.focus_tracking = false;
@@ -686,9 +775,12 @@ impl<'a> Performer<'a> {
                 self.button_event_mouse = false;
                 self.current_mouse_buttons.clear();
                 self.cursor_visible = true;
-                self.g0_charset = CharSet::Ascii;
-                self.g1_charset = CharSet::DecLineDrawing;
-                self.shift_out = false;
+                self.g0_charset = CharacterSet::Ascii;
+                self.g1_charset = CharacterSet::DecSpecialGraphics;
+                self.g2_charset = CharacterSet::DecSupplemental;
+                self.g3_charset = CharacterSet::DecSupplemental;
+                self.gl_charset = CharsetIndex::G0;
+                self.single_shift = None;
                 self.newline_mode = false;
                 self.tabs = TabStop::new(self.screen().physical_cols, 8);
                 self.palette.take();
@@ -698,6 +790,8 @@ impl<'a> Performer<'a> {
                 self.unicode_version_stack.clear();
                 self.suppress_initial_title_change = false;
                 self.accumulating_title.take();
+                self.accumulating_clipboard.take();
+                self.commands.clear();
 
                 self.screen.full_reset();
                 self.screen.activate_primary_screen(seqno);
@@ -809,10 +903,7 @@ impl<'a> Performer<'a> {
                 }
                 ITermProprietary::File(image) => self.set_image(*image),
                 ITermProprietary::SetUserVar { name, value } => {
-                    self.user_vars.insert(name.clone(), value.clone());
-                    if let Some(handler) = self.alert_handler.as_mut() {
-                        handler.alert(Alert::SetUserVar { name, value });
-                    }
+                    self.set_user_var(name, value);
                 }
                 ITermProprietary::UnicodeVersion(ITermUnicodeVersionOp::Set(n)) => {
                     self.unicode_version.version = n;
@@ -835,7 +926,75 @@ impl<'a> Performer<'a> {
                         }
                     }
                 }
-                _ => {
+                ITermProprietary::SetMark => {
+                    let seqno = self.seqno;
+                    let y = self.cursor.y;
+                    let screen = self.screen_mut();
+                    let y = screen.phys_row(y);
+                    screen.line_mut(y).set_marked(true, seqno);
+                }
+                ITermProprietary::ClearScrollback => {
+                    self.erase_in_display(EraseInDisplay::EraseScrollback);
+                }
+                ITermProprietary::CurrentDir(dir) => {
+                    // Unlike OSC 7, this is a plain path on the local host
+                    self.current_dir = Url::from_file_path(&dir).ok();
+                    if let Some(handler) = self.alert_handler.as_mut() {
+                        handler.alert(Alert::CurrentWorkingDirectoryChanged);
+                    }
+                }
+                ITermProprietary::SetProfile(name) => {
+                    if let Some(handler) = self.alert_handler.as_mut() {
+                        handler.alert(Alert::SetProfile(name));
+                    }
+                }
+                ITermProprietary::CopyToClipboard(_) => {
+                    // The parameter names a pasteboard, which only makes
+                    // sense on macOS; we always use the clipboard.
+                    self.flush_print();
+                    self.accumulating_clipboard.replace(String::new());
+                }
+                ITermProprietary::EndCopy => {
+                    if let Some(text) = self.accumulating_clipboard.take() {
+                        if let Err(err) =
+                            self.set_clipboard_contents(ClipboardSelection::Clipboard, Some(text))
+                        {
+                            error!("failed to set clipboard in response to EndCopy: {:#}", err);
+                        }
+                    }
+                }
+                ITermProprietary::Copy(text) => {
+                    if let Err(err) =
+                        self.set_clipboard_contents(ClipboardSelection::Clipboard, Some(text))
+                    {
+                        error!("failed to set clipboard in response to Copy: {:#}", err);
+                    }
+                }
+                ITermProprietary::ReportVariable(name) => {
+                    let value = self.iterm_variable(&name).unwrap_or_default();
+                    let response = OperatingSystemCommand::ITermProprietary(
+                        ITermProprietary::ReportVariable(value),
+                    );
+                    write!(self.writer, "{}", response).ok();
+                    self.writer.flush().ok();
+                }
+                ITermProprietary::SetBadgeFormat(format) => {
+                    let format = if format.is_empty() {
+                        None
+                    } else {
+                        Some(format)
+                    };
+                    self.badge_format = format.clone();
+                    if let Some(handler) = self.alert_handler.as_mut() {
+                        handler.alert(Alert::BadgeFormatChanged(format));
+                    }
+                }
+                ITermProprietary::StealFocus | ITermProprietary::HighlightCursorLine(_) => {
+                    // We don't allow programs to pull the window into the
+                    // foreground, and we have no cursor line highlight
+                    log::debug!("ignoring unsupported iTerm2 sequence {:?}", iterm);
+                }
+                ITermProprietary::ReportCellSize { .. } => {
                     if self.config.log_unknown_escape_sequences() {
                         log::warn!("unhandled iterm2: {:?}", iterm);
                     }
@@ -848,8 +1007,10 @@ impl<'a> Performer<'a> {
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::FreshLineAndStartPrompt { .. },
             ) => {
+                self.command_finished(None);
                 self.fresh_line();
                 self.pen.set_semantic_type(SemanticType::Prompt);
+                self.command_prompt_started();
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::StartPrompt(_),
@@ -859,29 +1020,36 @@ impl<'a> Performer<'a> {
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfCommandWithFreshLine { .. },
             ) => {
+                self.command_finished(None);
                 self.fresh_line();
                 self.pen.set_semantic_type(SemanticType::Prompt);
+                self.command_prompt_started();
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Input);
+                self.command_input_started();
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilEndOfLine { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Input);
                 self.clear_semantic_attribute_on_newline = true;
+                self.command_input_started();
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Output);
+                self.command_output_started();
             }
 
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::CommandStatus { .. },
-            ) => {}
+                FinalTermSemanticPrompt::CommandStatus { status, .. },
+            ) => {
+                self.command_finished(Some(status));
+            }
 
             OperatingSystemCommand::SystemNotification(message) => {
                 if let Some(handler) = self.alert_handler.as_mut() {
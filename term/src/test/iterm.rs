@@ -0,0 +1,68 @@
+//! Testing iTerm2 proprietary escape sequences
+
+use super::*;
+use k9::assert_equal as assert_eq;
+use std::collections::HashMap;
+
+#[test]
+fn test_set_mark() {
+    let mut term = TestTerm::new(3, 4, 10);
+    term.print("a\r\n");
+    term.print("\x1b]1337;SetMark\x07b\r\nc\r\nd\r\ne");
+    assert_visible_contents(&term, file!(), line!(), &["c", "d", "e"]);
+    assert_eq!(term.get_marks(), vec![1]);
+
+    term.print("\x1b]1337;ClearScrollback\x07");
+    assert_eq!(term.get_marks(), vec![]);
+}
+
+#[test]
+fn test_copy_to_clipboard() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print("\x1b]1337;CopyToClipboard=\x07hello\r\nworld\x1b]1337;EndCopy\x07");
+    assert_eq!(term.get_clipboard(), Some("hello\nworld".to_string()));
+    assert_visible_contents(&term, file!(), line!(), &["", "", ""]);
+
+    // A reset abandons an in-progress copy
+    term.print("\x1b]1337;CopyToClipboard=\x07lost\x1bcshown");
+    term.print("\x1b]1337;EndCopy\x07");
+    assert_eq!(term.get_clipboard(), Some("hello\nworld".to_string()));
+    assert_visible_contents(&term, file!(), line!(), &["shown", "", ""]);
+}
+
+#[test]
+fn test_copy_to_clipboard_overflow() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print("\x1b]1337;CopyToClipboard=\x07");
+    term.print("x".repeat(1024 * 1024));
+    term.print("after\x1b]1337;EndCopy\x07");
+    assert_eq!(term.get_clipboard(), None);
+    assert_visible_contents(&term, file!(), line!(), &["after", "", ""]);
+}
+
+#[test]
+fn test_badge_format() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(term.get_badge(), None);
+
+    term.print("\x1b]1337;SetBadgeFormat=XCh1c2VyLmhvc3QpIQ==\x07");
+    assert_eq!(term.get_badge(), Some("!".to_string()));
+
+    term.print("\x1b]1337;SetUserVar=host=ZXhhbXBsZQ==\x07");
+    assert_eq!(term.get_badge(), Some("example!".to_string()));
+
+    term.print("\x1b]1337;SetBadgeFormat=\x07");
+    assert_eq!(term.get_badge(), None);
+}
+
+#[test]
+fn test_evaluate_badge_format() {
+    let mut vars = HashMap::new();
+    vars.insert("name".to_string(), "wez".to_string());
+    assert_eq!(
+        evaluate_badge_format("hello \\(user.name) \\(name)", &vars),
+        "hello wez wez"
+    );
+    assert_eq!(evaluate_badge_format("\\(missing)", &vars), "");
+    assert_eq!(evaluate_badge_format("\\(open", &vars), "\\(open");
+}
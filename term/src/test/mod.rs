@@ -6,6 +6,9 @@ mod c0;
 use bitflags::bitflags;
 mod c1;
 mod csi;
+mod iterm;
+mod scrollback;
+mod vt52;
 // mod selection; FIXME: port to render layer
 use crate::color::ColorPalette;
 use k9::assert_equal as assert_eq;
@@ -40,17 +43,23 @@ impl Clipboard for LocalClip {
 
 struct TestTerm {
     term: Terminal,
+    clip: Arc<LocalClip>,
 }
 
 #[derive(Debug)]
 struct TestTermConfig {
     scrollback: usize,
+    uncompressed_scrollback: usize,
 }
 impl TerminalConfiguration for TestTermConfig {
     fn scrollback_size(&self) -> usize {
         self.scrollback
     }
 
+    fn scrollback_uncompressed_lines(&self) -> usize {
+        self.uncompressed_scrollback
+    }
+
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
@@ -58,6 +67,35 @@ impl TerminalConfiguration for TestTermConfig {
 
 impl TestTerm {
     fn new(height: usize, width: usize, scrollback: usize) -> Self {
+        Self::with_config(
+            height,
+            width,
+            TestTermConfig {
+                scrollback,
+                uncompressed_scrollback: usize::MAX,
+            },
+        )
+    }
+
+    /// Creates a terminal that compresses all but the most recent
+    /// uncompressed_scrollback lines of its scrollback
+    fn with_compressed_scrollback(
+        height: usize,
+        width: usize,
+        scrollback: usize,
+        uncompressed_scrollback: usize,
+    ) -> Self {
+        Self::with_config(
+            height,
+            width,
+            TestTermConfig {
+                scrollback,
+                uncompressed_scrollback,
+            },
+        )
+    }
+
+    fn with_config(height: usize, width: usize, config: TestTermConfig) -> Self {
         let _ = env_logger::Builder::new()
             .is_test(true)
             .filter_level(log::LevelFilter::Trace)
@@ -71,21 +109,26 @@ impl TestTerm {
                 pixel_height: height * 16,
                 dpi: 0,
             },
-            Arc::new(TestTermConfig { scrollback }),
+            Arc::new(config),
             "WezTerm",
             "O_o",
             Box::new(Vec::new()),
         );
-        let clip: Arc<dyn Clipboard> = Arc::new(LocalClip::new());
-        term.set_clipboard(&clip);
+        let clip = Arc::new(LocalClip::new());
+        let dyn_clip: Arc<dyn Clipboard> = Arc::clone(&clip) as _;
+        term.set_clipboard(&dyn_clip);
 
-        let mut term = Self { term };
+        let mut term = Self { term, clip };
 
         term.set_auto_wrap(true);
 
         term
     }
 
+    fn get_clipboard(&self) -> Option<String> {
+        self.clip.clip.lock().unwrap().clone()
+    }
+
     fn print<B: AsRef<[u8]>>(&mut self, bytes: B) {
         self.term.advance_bytes(bytes);
     }
@@ -479,6 +522,61 @@ fn test_semantic() {
     );
 }
 
+#[test]
+fn test_command_blocks() {
+    let mut term = TestTerm::new(6, 10, 0);
+    term.print("\x1b]133;A\x1b\\> \x1b]133;B\x1b\\ls\r\n");
+    term.print("\x1b]133;C\x1b\\a\r\nb\r\n\x1b]133;D;1\x1b\\");
+    term.print("\x1b]133;A\x1b\\> \x1b]133;B\x1b\\true\r\n");
+    term.print("\x1b]133;C\x1b\\\x1b]133;D;0\x1b\\");
+    term.print("\x1b]133;A\x1b\\> ");
+
+    fn zone(
+        start_x: usize,
+        start_y: StableRowIndex,
+        end_x: usize,
+        end_y: StableRowIndex,
+        semantic_type: SemanticType,
+    ) -> Option<SemanticZone> {
+        Some(SemanticZone {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            semantic_type,
+        })
+    }
+
+    let blocks = term.get_command_blocks();
+    assert_eq!(blocks.len(), 3);
+
+    assert_eq!(blocks[0].prompt, zone(0, 0, 1, 0, SemanticType::Prompt));
+    assert_eq!(blocks[0].input, zone(2, 0, 9, 0, SemanticType::Input));
+    assert_eq!(blocks[0].output, zone(0, 1, 9, 2, SemanticType::Output));
+    assert_eq!(blocks[0].exit_status, Some(1));
+    assert!(blocks[0].failed());
+    assert!(!blocks[0].is_running());
+    assert!(blocks[0].duration().is_some());
+
+    // The command produced no output
+    assert_eq!(blocks[1].input, zone(2, 3, 9, 3, SemanticType::Input));
+    assert_eq!(blocks[1].output, None);
+    assert_eq!(blocks[1].exit_status, Some(0));
+    assert!(!blocks[1].failed());
+
+    // The prompt is still being displayed
+    assert_eq!(blocks[2].prompt, zone(0, 4, 1, 4, SemanticType::Prompt));
+    assert_eq!(blocks[2].input, None);
+    assert_eq!(blocks[2].exit_status, None);
+    assert_eq!(blocks[2].start_time, None);
+
+    // An empty command line doesn't record a status
+    term.print("\x1b]133;B\x1b\\\r\n\x1b]133;D;0\x1b\\");
+    let blocks = term.get_command_blocks();
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[2].exit_status, None);
+}
+
 #[test]
 fn issue_1161() {
     let mut term = TestTerm::new(1, 5, 0);
@@ -0,0 +1,151 @@
+//! Tests of a screen whose scrollback is compressed in segments,
+//! with lines that are wrapped across the boundaries of segments.
+
+use super::*;
+
+const ROWS: usize = 5;
+const NUM_LINES: usize = 1500;
+
+/// Each logical line is 29 columns wide
+fn logical_line(n: usize) -> String {
+    format!("{:05} abcdefghijklmnopqrstuvw", n)
+}
+
+/// Returns the physical lines that the logical lines are wrapped into
+fn wrapped_lines(cols: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for n in 0..NUM_LINES {
+        let line = logical_line(n);
+        let mut text = line.as_str();
+        while !text.is_empty() {
+            let (row, rest) = text.split_at(cols.min(text.len()));
+            lines.push(row.to_string());
+            text = rest;
+        }
+    }
+    lines
+}
+
+/// Returns a terminal that holds all but the most recent 10 lines
+/// of its scrollback compressed, with NUM_LINES logical lines
+/// wrapped into three rows each.  A segment holds 1024 rows, so
+/// some of the logical lines straddle two segments.
+fn compressed_term() -> TestTerm {
+    let mut term = TestTerm::with_compressed_scrollback(ROWS, 10, 10_000, 10);
+    for n in 0..NUM_LINES {
+        term.print(logical_line(n));
+        term.print("\r\n");
+    }
+    assert_segments(&term);
+    term
+}
+
+fn assert_segments(term: &TestTerm) {
+    if cfg!(feature = "use_serde") {
+        assert!(term.screen().scrollback_segments() > 1);
+    }
+}
+
+fn assert_wrapped(term: &TestTerm, cols: usize) {
+    let expect = wrapped_lines(cols);
+    let lines: Vec<String> = term
+        .screen()
+        .all_lines()
+        .iter()
+        .take(expect.len())
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    assert_eq!(lines, expect);
+}
+
+fn resize(term: &mut TestTerm, cols: usize) {
+    term.resize(TerminalSize {
+        rows: ROWS,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    });
+}
+
+/// Returns the text and the stable range of each logical line
+/// that begins or ends within range, in the same way as pane search
+fn logical_lines(
+    term: &TestTerm,
+    range: Range<StableRowIndex>,
+) -> Vec<(String, Range<StableRowIndex>)> {
+    let mut result = vec![];
+    term.screen()
+        .for_each_logical_line_in_stable_range(range, |sr, lines| {
+            let text: String = lines
+                .iter()
+                .map(|line| line.as_str().into_owned())
+                .collect();
+            result.push((text.trim_end().to_string(), sr));
+            true
+        });
+    result
+}
+
+#[test]
+fn rewrap_compressed_scrollback() {
+    let mut term = compressed_term();
+    assert_wrapped(&term, 10);
+
+    resize(&mut term, 20);
+    assert_segments(&term);
+    assert_wrapped(&term, 20);
+
+    resize(&mut term, 7);
+    assert_segments(&term);
+    assert_wrapped(&term, 7);
+}
+
+#[test]
+fn logical_lines_span_segments() {
+    let term = compressed_term();
+    let lines = logical_lines(&term, 0..(NUM_LINES * 3) as StableRowIndex);
+    assert_eq!(lines.len(), NUM_LINES);
+    for (n, (text, range)) in lines.into_iter().enumerate() {
+        assert_eq!(text, logical_line(n));
+        let start = (n * 3) as StableRowIndex;
+        assert_eq!(range, start..start + 3);
+    }
+}
+
+#[test]
+fn search_across_segments() {
+    let term = compressed_term();
+
+    // The first row of the second segment is the last row of
+    // line 341, so the search has to look back into the first segment
+    let lines = logical_lines(&term, 1024..1025);
+    assert_eq!(&lines[0], &(logical_line(341), 1023..1026));
+
+    let needle = "00682 abcdefghijklmnopqrstuvw";
+    let found: Vec<Range<StableRowIndex>> =
+        logical_lines(&term, 0..(NUM_LINES * 3) as StableRowIndex)
+            .into_iter()
+            .filter(|(text, _)| text.contains(needle))
+            .map(|(_, range)| range)
+            .collect();
+    // Line 682 spans rows 2046..2049, straddling the second and
+    // third segments
+    assert_eq!(found, vec![2046..2049]);
+}
+
+#[test]
+fn semantic_zones_leave_segments_compressed() {
+    let term = compressed_term();
+    let frozen = term.screen().frozen_scrollback_segments();
+
+    let zones = term.get_semantic_zones().unwrap();
+    assert_eq!(zones.len(), 1);
+    assert_eq!(zones[0].semantic_type, SemanticType::Output);
+    assert_eq!(zones[0].start_y, 0);
+
+    assert_eq!(term.screen().frozen_scrollback_segments(), frozen);
+    if cfg!(feature = "use_serde") {
+        assert!(frozen > 0);
+    }
+}
@@ -0,0 +1,164 @@
+//! Testing VT52 compatibility mode and character set designation.
+//! These are loosely based on the vt52 and character set tests in vttest.
+
+use super::*;
+
+/// Produce the `ESC Y` direct cursor address sequence for the
+/// 0-based line and column
+fn cup(line: u8, column: u8) -> String {
+    format!("\x1bY{}{}", (line + 0x20) as char, (column + 0x20) as char)
+}
+
+#[test]
+fn test_vt52_enter_exit() {
+    let mut term = TestTerm::new(3, 10, 0);
+    // Reset DECANM to enter VT52 mode; CSI sequences are no longer
+    // recognized, so `ESC [` is ignored and the rest printed as text
+    term.print("\x1b[?2l\x1b[2Jhi");
+    assert_visible_contents(&term, file!(), line!(), &["2Jhi", "", ""]);
+
+    // ESC < returns to ANSI mode
+    term.print("\x1b<\x1b[2J\x1b[Hok");
+    assert_visible_contents(&term, file!(), line!(), &["ok        ", "", ""]);
+}
+
+#[test]
+fn test_vt52_cursor_movement() {
+    let mut term = TestTerm::new(5, 10, 0);
+    term.print("\x1b[?2l");
+
+    term.print(&cup(2, 3));
+    term.assert_cursor_pos(3, 2, None, None);
+    term.print("X");
+
+    term.print("\x1bA\x1bA\x1bDY");
+    term.assert_cursor_pos(4, 0, None, None);
+
+    term.print("\x1bB\x1bB\x1bB\x1bCZ");
+    term.assert_cursor_pos(6, 3, None, None);
+
+    // Cursor up stops at the top of the screen
+    term.print("\x1bH\x1bA");
+    term.assert_cursor_pos(0, 0, None, None);
+    term.print("*");
+
+    assert_visible_contents(&term, file!(), line!(), &["*  Y", "", "   X", "     Z", ""]);
+}
+
+#[test]
+fn test_vt52_direct_cursor_address_interrupted() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print("\x1b[?2l");
+    // Control characters are executed in the middle of `ESC Y`
+    term.print("abc\x1bY\x08!\"d");
+    assert_visible_contents(&term, file!(), line!(), &["abc", "  d", ""]);
+    term.assert_cursor_pos(3, 1, None, None);
+
+    // ESC abandons a partial `ESC Y` and starts a new sequence
+    term.print("\x1bY\x1bHe");
+    assert_visible_contents(&term, file!(), line!(), &["ebc", "  d", ""]);
+}
+
+#[test]
+fn test_vt52_erase() {
+    let mut term = TestTerm::new(3, 8, 0);
+    term.print("\x1b[?2l");
+    term.print("FooBar\r\nFooBar\r\nFooBar");
+
+    term.print(&cup(0, 3));
+    term.print("\x1bK");
+    term.print(&cup(1, 4));
+    term.print("\x1bJ");
+    assert_visible_contents(&term, file!(), line!(), &["Foo", "FooB", "        "]);
+
+    term.print("\x1bH\x1bJ");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["        ", "        ", "        "],
+    );
+}
+
+#[test]
+fn test_vt52_reverse_line_feed() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print("\x1b[?2l");
+    term.print("one\r\ntwo\r\nthree");
+    term.print(&cup(1, 0));
+    term.print("\x1bI");
+    term.assert_cursor_pos(0, 0, None, None);
+    // Scrolls down when at the top of the screen
+    term.print("\x1bIzero");
+    assert_visible_contents(&term, file!(), line!(), &["zero", "one", "two"]);
+}
+
+#[test]
+fn test_vt52_graphics_mode() {
+    let mut term = TestTerm::new(1, 10, 0);
+    term.print("\x1b[?2l");
+    term.print("\x1bFlqk\x1bGlqk");
+    assert_visible_contents(&term, file!(), line!(), &["┌─┐lqk"]);
+}
+
+#[test]
+fn test_vt52_reset() {
+    let mut term = TestTerm::new(1, 10, 0);
+    term.print("\x1b[?2lab");
+    // RIS leaves VT52 mode, so CSI sequences work again
+    term.print("\x1bc\x1b[2Cc");
+    assert_visible_contents(&term, file!(), line!(), &["  c"]);
+
+    term.print("\x1b[?2l\x1b<\x1bc\x1b[Hd");
+    assert_visible_contents(&term, file!(), line!(), &["d         "]);
+}
+
+#[test]
+fn test_national_replacement_charsets() {
+    let mut term = TestTerm::new(6, 12, 0);
+    let chars = "#@[\\]^_`{|}~";
+
+    // German in G0
+    term.print(format!("\x1b(K{}\r\n", chars));
+    // Swedish via its alternate final character
+    term.print(format!("\x1b(7{}\r\n", chars));
+    // Portuguese uses a second intermediate
+    term.print(format!("\x1b(%6{}\r\n", chars));
+    // UK, and back to ASCII
+    term.print(format!("\x1b(A{}\r\n", chars));
+    term.print(format!("\x1b(B{}", chars));
+
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &[
+            "#§ÄÖÜ^_`äöüß",
+            "#ÉÄÖÅÜ_éäöåü",
+            "#@ÃÇÕ^_`ãçõ~",
+            "£@[\\]^_`{|}~",
+            "#@[\\]^_`{|}~",
+            "",
+        ],
+    );
+}
+
+#[test]
+fn test_charset_shifts() {
+    let mut term = TestTerm::new(4, 10, 0);
+
+    // Designate DEC Technical into G2, DEC special graphics into G1
+    // and ISO Latin-1 into G3
+    term.print("\x1b*>\x1b)0\x1b/A");
+
+    // Single shift only applies to the next character
+    term.print("\x1bNa\x1bOAa\r\n");
+    // Locking shifts persist until shifted back with SI
+    term.print("\x0eqq\x0fqq\r\n");
+    term.print("\x1bnabc\x1boAB\x0fAB\r\n");
+
+    // Restoring the cursor also restores GL to G0
+    term.print("\x1b7\x0eq\x1b8q");
+
+    assert_visible_contents(&term, file!(), line!(), &["αÁa", "──qq", "αβχÁÂAB", "q"]);
+}
@@ -11,6 +11,113 @@ pub enum Esc {
         control: u8,
     },
     Code(EscCode),
+    /// SCS - Designate a character set to one of the G0-G3 slots.
+    /// The designations for ASCII, UK and DEC Line Drawing into G0
+    /// and G1 are parsed as the corresponding `EscCode` instead.
+    DesignateCharacterSet {
+        index: CharsetIndex,
+        charset: CharacterSet,
+    },
+}
+
+/// Identifies one of the four character set slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharsetIndex {
+    G0,
+    G1,
+    G2,
+    G3,
+}
+
+/// The character sets that can be designated via SCS.
+/// See <https://vt100.net/docs/vt510-rm/SCS.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterSet {
+    Ascii,
+    Uk,
+    DecSpecialGraphics,
+    /// The user-preferred supplemental set; we always treat
+    /// this as DEC Supplemental Graphics
+    DecSupplemental,
+    DecSupplementalGraphics,
+    DecTechnical,
+    Dutch,
+    Finnish,
+    French,
+    FrenchCanadian,
+    German,
+    Italian,
+    NorwegianDanish,
+    Portuguese,
+    Spanish,
+    Swedish,
+    Swiss,
+    /// The 96 character ISO Latin-1 Supplemental set
+    IsoLatin1Supplemental,
+}
+
+impl CharacterSet {
+    /// Parse the final byte(s) of a 94 character set designation
+    fn parse_94(second_intermediate: Option<u8>, control: u8) -> Option<Self> {
+        Some(match (second_intermediate, control) {
+            (None, b'B') => Self::Ascii,
+            (None, b'A') => Self::Uk,
+            (None, b'0') => Self::DecSpecialGraphics,
+            (None, b'<') => Self::DecSupplemental,
+            (None, b'>') => Self::DecTechnical,
+            (None, b'4') => Self::Dutch,
+            (None, b'C') | (None, b'5') => Self::Finnish,
+            (None, b'R') | (None, b'f') => Self::French,
+            (None, b'Q') | (None, b'9') => Self::FrenchCanadian,
+            (None, b'K') => Self::German,
+            (None, b'Y') => Self::Italian,
+            (None, b'E') | (None, b'6') | (None, b'`') => Self::NorwegianDanish,
+            (None, b'Z') => Self::Spanish,
+            (None, b'H') | (None, b'7') => Self::Swedish,
+            (None, b'=') => Self::Swiss,
+            (Some(b'%'), b'5') => Self::DecSupplementalGraphics,
+            (Some(b'%'), b'6') => Self::Portuguese,
+            _ => return None,
+        })
+    }
+
+    /// Parse the final byte of a 96 character set designation
+    fn parse_96(second_intermediate: Option<u8>, control: u8) -> Option<Self> {
+        match (second_intermediate, control) {
+            (None, b'A') => Some(Self::IsoLatin1Supplemental),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is a 96 character set, which are
+    /// designated using a different set of intermediates
+    pub fn is_96(self) -> bool {
+        matches!(self, Self::IsoLatin1Supplemental)
+    }
+
+    /// The final byte(s) used to designate this set
+    fn final_str(self) -> &'static str {
+        match self {
+            Self::Ascii => "B",
+            Self::Uk => "A",
+            Self::DecSpecialGraphics => "0",
+            Self::DecSupplemental => "<",
+            Self::DecSupplementalGraphics => "%5",
+            Self::DecTechnical => ">",
+            Self::Dutch => "4",
+            Self::Finnish => "C",
+            Self::French => "R",
+            Self::FrenchCanadian => "Q",
+            Self::German => "K",
+            Self::Italian => "Y",
+            Self::NorwegianDanish => "E",
+            Self::Portuguese => "%6",
+            Self::Spanish => "Z",
+            Self::Swedish => "H",
+            Self::Swiss => "=",
+            Self::IsoLatin1Supplemental => "A",
+        }
+    }
 }
 
 macro_rules! esc {
@@ -70,6 +177,17 @@ pub enum EscCode {
     /// DECPNM - Normal Keypad
     DecNormalKeyPad = esc!('>'),
 
+    /// LS2 - Invoke the G2 Character Set into GL
+    LockingShiftG2 = esc!('n'),
+    /// LS3 - Invoke the G3 Character Set into GL
+    LockingShiftG3 = esc!('o'),
+    /// LS1R - Invoke the G1 Character Set into GR
+    LockingShiftG1Right = esc!('~'),
+    /// LS2R - Invoke the G2 Character Set into GR
+    LockingShiftG2Right = esc!('}'),
+    /// LS3R - Invoke the G3 Character Set into GR
+    LockingShiftG3Right = esc!('|'),
+
     /// Designate G0 Character Set – DEC Line Drawing
     DecLineDrawingG0 = esc!('(', '0'),
     /// Designate G0 Character Set - UK
@@ -111,12 +229,72 @@ pub enum EscCode {
 
 impl Esc {
     pub fn parse(intermediate: Option<u8>, control: u8) -> Self {
-        Self::internal_parse(intermediate, control).unwrap_or_else(|_| Esc::Unspecified {
-            intermediate,
+        match intermediate {
+            Some(i) => Self::parse_with_intermediates(&[i], control),
+            None => Self::parse_with_intermediates(&[], control),
+        }
+    }
+
+    /// Parse an escape sequence that may have more than one
+    /// intermediate byte, such as `ESC ( % 6`
+    pub fn parse_with_intermediates(intermediates: &[u8], control: u8) -> Self {
+        let parsed = match intermediates {
+            [] => Self::internal_parse(None, control),
+            [i] => Self::internal_parse(Some(*i), control)
+                .or_else(|_| Self::parse_designation(*i, None, control)),
+            [i, j] => Self::parse_designation(*i, Some(*j), control),
+            _ => Err(()),
+        };
+        parsed.unwrap_or_else(|_| Esc::Unspecified {
+            intermediate: if intermediates.len() == 1 {
+                Some(intermediates[0])
+            } else {
+                None
+            },
             control,
         })
     }
 
+    fn parse_designation(
+        intermediate: u8,
+        second_intermediate: Option<u8>,
+        control: u8,
+    ) -> Result<Self, ()> {
+        let (index, charset) = match intermediate {
+            b'(' => (
+                CharsetIndex::G0,
+                CharacterSet::parse_94(second_intermediate, control),
+            ),
+            b')' => (
+                CharsetIndex::G1,
+                CharacterSet::parse_94(second_intermediate, control),
+            ),
+            b'*' => (
+                CharsetIndex::G2,
+                CharacterSet::parse_94(second_intermediate, control),
+            ),
+            b'+' => (
+                CharsetIndex::G3,
+                CharacterSet::parse_94(second_intermediate, control),
+            ),
+            b'-' => (
+                CharsetIndex::G1,
+                CharacterSet::parse_96(second_intermediate, control),
+            ),
+            b'.' => (
+                CharsetIndex::G2,
+                CharacterSet::parse_96(second_intermediate, control),
+            ),
+            b'/' => (
+                CharsetIndex::G3,
+                CharacterSet::parse_96(second_intermediate, control),
+            ),
+            _ => return Err(()),
+        };
+        let charset = charset.ok_or(())?;
+        Ok(Esc::DesignateCharacterSet { index, charset })
+    }
+
     fn internal_parse(intermediate: Option<u8>, control: u8) -> Result<Self, ()> {
         let packed = match intermediate {
             Some(high) => ((u16::from(high)) << 8) | u16::from(control),
@@ -153,6 +331,18 @@ impl Display for Esc {
                     f.write_char((packed & 0xff) as u8 as char)?;
                 }
             }
+            DesignateCharacterSet { index, charset } => {
+                let intermediate = match (index, charset.is_96()) {
+                    (CharsetIndex::G0, _) => '(',
+                    (CharsetIndex::G1, false) => ')',
+                    (CharsetIndex::G2, false) => '*',
+                    (CharsetIndex::G3, false) => '+',
+                    (CharsetIndex::G1, true) => '-',
+                    (CharsetIndex::G2, true) => '.',
+                    (CharsetIndex::G3, true) => '/',
+                };
+                write!(f, "{}{}", intermediate, charset.final_str())?;
+            }
             Unspecified {
                 intermediate,
                 control,
@@ -202,4 +392,50 @@ mod test {
         assert_eq!(parse("#5"), Esc::Code(EscCode::DecSingleWidthLine));
         assert_eq!(parse("#6"), Esc::Code(EscCode::DecDoubleWidthLine));
     }
+
+    #[test]
+    fn designate_character_set() {
+        assert_eq!(parse("(A"), Esc::Code(EscCode::UkCharacterSetG0));
+        assert_eq!(
+            parse("(K"),
+            Esc::DesignateCharacterSet {
+                index: CharsetIndex::G0,
+                charset: CharacterSet::German,
+            }
+        );
+        assert_eq!(
+            parse("+>"),
+            Esc::DesignateCharacterSet {
+                index: CharsetIndex::G3,
+                charset: CharacterSet::DecTechnical,
+            }
+        );
+        assert_eq!(
+            parse("-A"),
+            Esc::DesignateCharacterSet {
+                index: CharsetIndex::G1,
+                charset: CharacterSet::IsoLatin1Supplemental,
+            }
+        );
+        assert_eq!(
+            parse("*0"),
+            Esc::DesignateCharacterSet {
+                index: CharsetIndex::G2,
+                charset: CharacterSet::DecSpecialGraphics,
+            }
+        );
+
+        let portuguese = Esc::parse_with_intermediates(b"(%", b'6');
+        assert_eq!(
+            portuguese,
+            Esc::DesignateCharacterSet {
+                index: CharsetIndex::G0,
+                charset: CharacterSet::Portuguese,
+            }
+        );
+        assert_eq!(encode(&portuguese), "\x1b(%6");
+
+        // Aliases are encoded using the canonical final byte
+        assert_eq!(encode(&Esc::parse(Some(b'('), b'7')), "\x1b(H");
+    }
 }
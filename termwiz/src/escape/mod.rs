@@ -16,11 +16,13 @@ pub mod csi;
 pub mod esc;
 pub mod osc;
 pub mod parser;
+pub mod vt52;
 
 pub use self::apc::KittyImage;
 pub use self::csi::CSI;
 pub use self::esc::{Esc, EscCode};
 pub use self::osc::OperatingSystemCommand;
+pub use self::vt52::Vt52;
 
 use vtparse::CsiParam;
 
@@ -45,6 +47,8 @@ pub enum Action {
     /// wants information
     XtGetTcap(Vec<String>),
     KittyImage(Box<KittyImage>),
+    /// An escape sequence recognized while in VT52 mode
+    Vt52(Vt52),
 }
 
 impl Action {
@@ -110,6 +114,7 @@ impl Display for Action {
                 Ok(())
             }
             Action::KittyImage(img) => img.fmt(f),
+            Action::Vt52(vt52) => vt52.fmt(f),
         }
     }
 }
@@ -1156,6 +1156,20 @@ impl ITermProprietary {
                 base64_decode(osc[2])?,
             )?));
         }
+        if osc.len() == 2 && keyword == "SetBadgeFormat" {
+            if let Some(p1) = p1 {
+                return Ok(ITermProprietary::SetBadgeFormat(String::from_utf8(
+                    base64_decode(p1)?,
+                )?));
+            }
+        }
+        if osc.len() == 2 && keyword == "ReportVariable" {
+            if let Some(p1) = p1 {
+                return Ok(ITermProprietary::ReportVariable(String::from_utf8(
+                    base64_decode(p1)?,
+                )?));
+            }
+        }
 
         if osc.len() == 3 && keyword == "ReportCellSize" && p1.is_some() {
             if let Some(p1) = p1 {
@@ -1663,6 +1677,26 @@ mod test {
             ))
         );
 
+        assert_eq!(
+            parse(
+                &["1337", "SetBadgeFormat=aGVsbG8="],
+                "\x1b]1337;SetBadgeFormat=aGVsbG8=\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::SetBadgeFormat(
+                "hello".into()
+            ))
+        );
+
+        assert_eq!(
+            parse(
+                &["1337", "ReportVariable=aGVsbG8="],
+                "\x1b]1337;ReportVariable=aGVsbG8=\x1b\\"
+            ),
+            OperatingSystemCommand::ITermProprietary(ITermProprietary::ReportVariable(
+                "hello".into()
+            ))
+        );
+
         assert_eq!(
             parse(
                 &["1337", "ReportCellSize=12.0", "15.5"],
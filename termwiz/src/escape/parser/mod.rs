@@ -1,7 +1,8 @@
 #![allow(clippy::many_single_char_names)]
+use crate::escape::csi::{DecPrivateMode, DecPrivateModeCode, Mode};
 use crate::escape::{
-    Action, DeviceControlMode, EnterDeviceControlMode, Esc, OperatingSystemCommand,
-    ShortDeviceControl, CSI,
+    Action, DeviceControlMode, EnterDeviceControlMode, Esc, EscCode, OperatingSystemCommand,
+    ShortDeviceControl, Vt52, CSI,
 };
 use crate::tmux_cc::Event;
 use log::error;
@@ -11,7 +12,9 @@ use std::cell::RefCell;
 use vtparse::{CsiParam, VTActor, VTParser};
 
 mod sixel;
+mod vt52;
 use sixel::SixelBuilder;
+use vt52::Vt52Parser;
 
 #[derive(Default)]
 struct GetTcapBuilder {
@@ -48,6 +51,8 @@ struct ParseState {
     dcs: Option<ShortDeviceControl>,
     get_tcap: Option<GetTcapBuilder>,
     tmux_state: Option<RefCell<crate::tmux_cc::Parser>>,
+    /// Set while the terminal is in VT52 compatibility mode (DECANM reset)
+    vt52: Option<Vt52Parser>,
 }
 
 /// The `Parser` struct holds the state machine that is used to decode
@@ -106,11 +111,34 @@ impl Parser {
                 }
             }
         } else {
-            let mut perform = Performer {
-                callback: &mut callback,
-                state: &mut self.state.borrow_mut(),
-            };
-            self.state_machine.parse(bytes, &mut perform);
+            let mut parser_state = self.state.borrow_mut();
+            for &b in bytes {
+                // DECANM can switch us in and out of VT52 mode part way
+                // through the buffer, so we need to check it for each byte
+                let mut vt52_mode = None;
+                let mut track_mode = |action: Action| {
+                    if let Some(enable) = vt52_mode_change(&action) {
+                        vt52_mode = Some(enable);
+                    }
+                    callback(action);
+                };
+                if let Some(vt52) = parser_state.vt52.as_mut() {
+                    vt52.parse_byte(b, &mut track_mode);
+                } else {
+                    let mut perform = Performer {
+                        callback: &mut track_mode,
+                        state: &mut parser_state,
+                    };
+                    self.state_machine.parse_byte(b, &mut perform);
+                }
+                match vt52_mode {
+                    Some(true) => {
+                        parser_state.vt52.get_or_insert_with(Vt52Parser::new);
+                    }
+                    Some(false) => parser_state.vt52 = None,
+                    None => {}
+                }
+            }
         }
     }
 
@@ -184,6 +212,25 @@ impl Parser {
     }
 }
 
+/// Returns Some(true) if the action puts the terminal into VT52
+/// compatibility mode, Some(false) if it returns the terminal to
+/// ANSI mode, or None if the mode is unchanged.
+/// This mirrors the DECANM handling in the terminal state, so that
+/// the two always agree on which parser should be used.
+fn vt52_mode_change(action: &Action) -> Option<bool> {
+    match action {
+        Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+            DecPrivateModeCode::DecAnsiMode,
+        )))) => Some(true),
+        Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
+            DecPrivateModeCode::DecAnsiMode,
+        ))))
+        | Action::Vt52(Vt52::EnterAnsiMode)
+        | Action::Esc(Esc::Code(EscCode::FullReset)) => Some(false),
+        _ => None,
+    }
+}
+
 struct Performer<'a, F: FnMut(Action) + 'a> {
     callback: &'a mut F,
     state: &'a mut ParseState,
@@ -324,12 +371,8 @@ impl<'a, F: FnMut(Action)> VTActor for Performer<'a, F> {
         // It doesn't appear to be possible for params.len() > 1 due to the way
         // that the state machine in vte functions.  As such, it also seems to
         // be impossible for ignored_extra_intermediates to be true too.
-        (self.callback)(Action::Esc(Esc::parse(
-            if intermediates.len() == 1 {
-                Some(intermediates[0])
-            } else {
-                None
-            },
+        (self.callback)(Action::Esc(Esc::parse_with_intermediates(
+            intermediates,
             control,
         )));
     }
@@ -341,8 +384,8 @@ mod test {
     use crate::cell::{Intensity, Underline};
     use crate::color::ColorSpec;
     use crate::escape::csi::{
-        CharacterPath, DecPrivateMode, DecPrivateModeCode, Device, Mode, Sgr, Window, XtSmGraphics,
-        XtSmGraphicsItem, XtermKeyModifierResource,
+        CharacterPath, Cursor, DecPrivateMode, DecPrivateModeCode, Device, Mode, Sgr, Window,
+        XtSmGraphics, XtSmGraphicsItem, XtermKeyModifierResource,
     };
     use crate::escape::{EscCode, OneBased};
     use k9::assert_equal as assert_eq;
@@ -981,6 +1024,61 @@ mod test {
     }
     */
 
+    #[test]
+    fn vt52() {
+        use crate::escape::{ControlCode, Vt52};
+        assert_eq!(
+            round_trip_parse("\x1b[?2lhi\x1bA\x1bY%*\x1bFa\r\x1b<\x1bD"),
+            vec![
+                Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                    DecPrivateModeCode::DecAnsiMode
+                )))),
+                Action::Print('h'),
+                Action::Print('i'),
+                Action::Vt52(Vt52::CursorUp),
+                Action::Vt52(Vt52::DirectCursorAddress {
+                    line: 5,
+                    column: 10
+                }),
+                Action::Vt52(Vt52::EnterGraphicsMode),
+                Action::Print('a'),
+                Action::Control(ControlCode::CarriageReturn),
+                Action::Vt52(Vt52::EnterAnsiMode),
+                // Back in ANSI mode, so this is IND rather than cursor left
+                Action::Esc(Esc::Code(EscCode::Index)),
+            ]
+        );
+
+        // Control codes are processed in the middle of ESC Y,
+        // while ESC cancels it
+        let mut p = Parser::new();
+        p.parse_as_vec(b"\x1b[?2l");
+        assert_eq!(
+            p.parse_as_vec(b"\x1bY\x08  \x1bY!\x1b[caf\xc3\xa9"),
+            vec![
+                Action::Control(ControlCode::Backspace),
+                Action::Vt52(Vt52::DirectCursorAddress { line: 0, column: 0 }),
+                // There is no CSI in VT52 mode
+                Action::Vt52(Vt52::Unspecified(b'[')),
+                Action::Print('c'),
+                Action::Print('a'),
+                Action::Print('f'),
+                Action::Print('é'),
+            ]
+        );
+
+        // RIS returns to ANSI mode
+        let mut p = Parser::new();
+        p.parse_as_vec(b"\x1b[?2l");
+        assert_eq!(
+            p.parse_as_vec(b"\x1bc\x1b[A"),
+            vec![
+                Action::Esc(Esc::Code(EscCode::FullReset)),
+                Action::CSI(CSI::Cursor(Cursor::Up(1))),
+            ]
+        );
+    }
+
     #[test]
     fn decset() {
         assert_eq!(
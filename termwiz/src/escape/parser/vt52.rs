@@ -0,0 +1,116 @@
+use crate::escape::{Action, ControlCode, Esc, EscCode, Vt52};
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    CursorLine,
+    CursorColumn(u8),
+}
+
+/// The VT52 escape sequence grammar is much simpler than that of
+/// ANSI terminals, and doesn't fit the vtparse state tables: `ESC Y`
+/// is followed by two printable bytes and most of the ANSI string
+/// introducers mean something else, so it gets a little state
+/// machine of its own.
+pub struct Vt52Parser {
+    state: State,
+    utf8: Vec<u8>,
+}
+
+impl Vt52Parser {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            utf8: vec![],
+        }
+    }
+
+    /// Process a byte, passing any recognized action to the callback.
+    /// Whether the action leaves VT52 mode is decided by the caller;
+    /// see `vt52_mode_change`.
+    pub fn parse_byte<F: FnMut(Action)>(&mut self, byte: u8, callback: &mut F) {
+        if byte >= 0x80 || !self.utf8.is_empty() {
+            if self.parse_utf8(byte, callback) {
+                return;
+            }
+        }
+
+        if byte < 0x20 && byte != 0x1b {
+            // Control characters are executed even in the middle
+            // of an escape sequence
+            if let Some(code) = ControlCode::from_u8(byte) {
+                callback(Action::Control(code));
+            }
+            return;
+        }
+
+        if byte == 0x1b {
+            // ESC cancels any sequence that is in progress
+            self.state = State::Escape;
+            return;
+        }
+
+        match self.state {
+            State::Ground => match byte {
+                0x7f => {}
+                _ => callback(Action::Print(byte as char)),
+            },
+            State::Escape => match Vt52::parse(byte) {
+                Some(Vt52::Unspecified(b'c')) => {
+                    // RIS isn't a VT52 sequence, but we honor it so that
+                    // a terminal stuck in VT52 mode can always be reset
+                    self.state = State::Ground;
+                    callback(Action::Esc(Esc::Code(EscCode::FullReset)));
+                }
+                Some(vt52) => {
+                    self.state = State::Ground;
+                    callback(Action::Vt52(vt52));
+                }
+                None => self.state = State::CursorLine,
+            },
+            State::CursorLine => {
+                self.state = State::CursorColumn(byte.saturating_sub(0x20));
+            }
+            State::CursorColumn(line) => {
+                self.state = State::Ground;
+                callback(Action::Vt52(Vt52::DirectCursorAddress {
+                    line,
+                    column: byte.saturating_sub(0x20),
+                }));
+            }
+        }
+    }
+
+    /// Accumulate UTF-8 sequences; the VT52 was a 7-bit terminal,
+    /// but we still want to display non-ASCII text sensibly.
+    /// Returns true if the byte was consumed.
+    fn parse_utf8<F: FnMut(Action)>(&mut self, byte: u8, callback: &mut F) -> bool {
+        if byte < 0x80 {
+            // Incomplete sequence followed by ASCII
+            self.utf8.clear();
+            callback(Action::Print(char::REPLACEMENT_CHARACTER));
+            return false;
+        }
+
+        self.utf8.push(byte);
+        match std::str::from_utf8(&self.utf8) {
+            Ok(s) => {
+                if let Some(c) = s.chars().next() {
+                    self.state = State::Ground;
+                    callback(Action::Print(c));
+                }
+                self.utf8.clear();
+            }
+            Err(err) if err.error_len().is_some() => {
+                self.utf8.clear();
+                callback(Action::Print(char::REPLACEMENT_CHARACTER));
+            }
+            Err(_) => {
+                // Need more bytes
+            }
+        }
+        true
+    }
+}
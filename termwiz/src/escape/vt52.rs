@@ -0,0 +1,97 @@
+//! Escape sequences recognized while the terminal is in VT52
+//! compatibility mode; see <https://vt100.net/docs/vt100-ug/chapter3.html#S3.3.5>
+use std::fmt::{Display, Error as FmtError, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vt52 {
+    /// ESC A
+    CursorUp,
+    /// ESC B
+    CursorDown,
+    /// ESC C
+    CursorRight,
+    /// ESC D
+    CursorLeft,
+    /// ESC F - Select the VT52 special graphics character set
+    EnterGraphicsMode,
+    /// ESC G - Select the ASCII character set
+    ExitGraphicsMode,
+    /// ESC H
+    CursorHome,
+    /// ESC I - Move the cursor up, scrolling down if at the top margin
+    ReverseLineFeed,
+    /// ESC J
+    EraseToEndOfScreen,
+    /// ESC K
+    EraseToEndOfLine,
+    /// ESC Y <line> <column> - Move the cursor to the specified
+    /// position.  The coordinates are 0-based.
+    DirectCursorAddress { line: u8, column: u8 },
+    /// ESC Z - Request that the terminal identify itself
+    Identify,
+    /// ESC = - Enter alternate keypad mode
+    EnterAlternateKeypad,
+    /// ESC > - Exit alternate keypad mode
+    ExitAlternateKeypad,
+    /// ESC < - Leave VT52 mode and return to ANSI mode
+    EnterAnsiMode,
+    /// An escape sequence that isn't defined by the VT52
+    Unspecified(u8),
+}
+
+impl Vt52 {
+    /// Parse the byte that follows ESC.
+    /// Returns None for `ESC Y`, as it needs two more bytes before
+    /// it can be returned as a `DirectCursorAddress`.
+    pub fn parse(control: u8) -> Option<Self> {
+        Some(match control {
+            b'A' => Self::CursorUp,
+            b'B' => Self::CursorDown,
+            b'C' => Self::CursorRight,
+            b'D' => Self::CursorLeft,
+            b'F' => Self::EnterGraphicsMode,
+            b'G' => Self::ExitGraphicsMode,
+            b'H' => Self::CursorHome,
+            b'I' => Self::ReverseLineFeed,
+            b'J' => Self::EraseToEndOfScreen,
+            b'K' => Self::EraseToEndOfLine,
+            b'Y' => return None,
+            b'Z' => Self::Identify,
+            b'=' => Self::EnterAlternateKeypad,
+            b'>' => Self::ExitAlternateKeypad,
+            b'<' => Self::EnterAnsiMode,
+            c => Self::Unspecified(c),
+        })
+    }
+}
+
+impl Display for Vt52 {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        let c = match self {
+            Self::CursorUp => 'A',
+            Self::CursorDown => 'B',
+            Self::CursorRight => 'C',
+            Self::CursorLeft => 'D',
+            Self::EnterGraphicsMode => 'F',
+            Self::ExitGraphicsMode => 'G',
+            Self::CursorHome => 'H',
+            Self::ReverseLineFeed => 'I',
+            Self::EraseToEndOfScreen => 'J',
+            Self::EraseToEndOfLine => 'K',
+            Self::DirectCursorAddress { line, column } => {
+                return write!(
+                    f,
+                    "\x1bY{}{}",
+                    line.saturating_add(0x20) as char,
+                    column.saturating_add(0x20) as char
+                );
+            }
+            Self::Identify => 'Z',
+            Self::EnterAlternateKeypad => '=',
+            Self::ExitAlternateKeypad => '>',
+            Self::EnterAnsiMode => '<',
+            Self::Unspecified(c) => *c as char,
+        };
+        write!(f, "\x1b{}", c)
+    }
+}
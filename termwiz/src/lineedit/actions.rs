@@ -1,3 +1,5 @@
+use crate::lineedit::vi::ViCommand;
+
 pub type RepeatCount = usize;
 
 #[derive(Debug, Clone, Copy)]
@@ -12,6 +14,7 @@ pub enum Movement {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Action {
     AcceptLine,
     Cancel,
@@ -28,4 +31,7 @@ pub enum Action {
     NoAction,
     HistoryIncSearchBackwards,
     HistoryIncSearchForwards,
+    Undo(RepeatCount),
+    Redo(RepeatCount),
+    Vi(ViCommand),
 }
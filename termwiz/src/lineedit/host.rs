@@ -1,8 +1,8 @@
 use crate::cell::{AttributeChange, CellAttributes};
 use crate::input::InputEvent;
 use crate::lineedit::actions::Action;
-use crate::lineedit::{BasicHistory, History, LineEditor};
-use crate::surface::Change;
+use crate::lineedit::{BasicHistory, EditMode, History, LineEditor, ViMode};
+use crate::surface::{Change, CursorShape};
 
 /// The `OutputElement` type allows returning graphic attribute changes
 /// as well as textual output.
@@ -96,6 +96,23 @@ pub trait LineEditorHost {
     fn resolve_action(&mut self, _event: &InputEvent, _editor: &mut LineEditor) -> Option<Action> {
         None
     }
+
+    /// Selects between emacs and vi style key bindings.
+    /// This is consulted at the start of each `LineEditor::read_line`.
+    /// The default implementation returns `EditMode::Emacs`.
+    fn edit_mode(&self) -> EditMode {
+        EditMode::Emacs
+    }
+
+    /// Returns the cursor shape to use while in the specified vi mode.
+    /// The default implementation uses a blinking bar in insert mode
+    /// and a steady block in normal and visual mode.
+    fn vi_cursor_shape(&self, mode: ViMode) -> CursorShape {
+        match mode {
+            ViMode::Insert => CursorShape::BlinkingBar,
+            ViMode::Normal | ViMode::Visual => CursorShape::SteadyBlock,
+        }
+    }
 }
 
 /// A candidate for tab completion.
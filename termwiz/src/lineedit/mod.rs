@@ -37,10 +37,35 @@
 //! Ctrl-W        | Delete word leading up to cursor
 //! Alt-b, Alt-Left | Move the cursor backwards one word
 //! Alt-f, Alt-Right | Move the cursor forwards one word
+//!
+//! ## Vi Mode
+//!
+//! When `LineEditorHost::edit_mode` returns `EditMode::Vi` the editor
+//! starts each line in insert mode, where the bindings above apply.
+//! Escape switches to normal mode, which supports:
+//!
+//! Keystroke     | Action
+//! ---------     | ------
+//! h, l, w, W, b, B, e, E, 0, ^, $ | Cursor motions
+//! f, F, t, T, ;, , | Find a character on the line, and repeat the find
+//! d, c, y       | Delete, change and yank operators followed by a motion or a text object; doubled (`dd`) to apply to the whole line
+//! iw, aw, i", a", i(, a( ... | Text objects for words, quotes and brackets
+//! x, X, s, S, D, C, Y | Shorthands for `dl`, `dh`, `cl`, `cc`, `d$`, `c$` and `yy`
+//! i, a, I, A    | Enter insert mode
+//! v             | Enter visual mode; operators then apply to the selection
+//! r, ~          | Replace the character under the cursor, or toggle its case
+//! p, P          | Put the yanked or deleted text after or before the cursor
+//! u, Ctrl-R     | Undo and redo
+//! j, k          | Next and previous history entries
+//!
+//! Motions and commands accept a count prefix, such as `3w` or `2d3w`.
+//! The cursor shape reflects the current mode; see
+//! `LineEditorHost::vi_cursor_shape`.
 use crate::caps::{Capabilities, ProbeHints};
+use crate::cell::{unicode_column_width, AttributeChange};
 use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
 use crate::surface::change::ChangeSequence;
-use crate::surface::{Change, Position};
+use crate::surface::{Change, CursorShape, Position};
 use crate::terminal::{new_terminal, Terminal};
 use crate::{bail, ensure, Result};
 use unicode_segmentation::GraphemeCursor;
@@ -48,9 +73,15 @@ use unicode_segmentation::GraphemeCursor;
 mod actions;
 mod history;
 mod host;
+mod vi;
 pub use actions::{Action, Movement, RepeatCount};
 pub use history::*;
 pub use host::*;
+use vi::ViState;
+pub use vi::{
+    EditMode, FindKind, InsertPosition, ViCommand, ViMode, ViMotion, ViOperator, ViTarget,
+    ViTextObject,
+};
 
 /// The `LineEditor` struct provides line editing facilities similar
 /// to those in the unix shell.
@@ -85,6 +116,15 @@ pub struct LineEditor<'term> {
     move_to_editor_end: Option<Change>,
 
     state: EditorState,
+
+    /// Present when editing in vi mode
+    vi: Option<ViState>,
+
+    undo_stack: Vec<(String, usize)>,
+    redo_stack: Vec<(String, usize)>,
+    /// Set while consecutive InsertChar actions are being
+    /// grouped into a single undo step
+    grouping_inserts: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -163,6 +203,10 @@ impl<'term> LineEditor<'term> {
             move_to_editor_start: None,
             move_to_editor_end: None,
             state: EditorState::Inactive,
+            vi: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            grouping_inserts: false,
         }
     }
 
@@ -173,6 +217,9 @@ impl<'term> LineEditor<'term> {
 
         changes.add(Change::ClearToEndOfScreen(Default::default()));
         changes.add(Change::AllAttributes(Default::default()));
+        if let Some(vi) = &self.vi {
+            changes.add(Change::CursorShape(host.vi_cursor_shape(vi.mode())));
+        }
         for ele in host.render_prompt(&self.prompt) {
             changes.add(ele);
         }
@@ -191,7 +238,25 @@ impl<'term> LineEditor<'term> {
 
         let cursor_position_after_printing_prompt = changes.current_cursor_position();
 
-        let (elements, cursor_x_pos) = host.highlight_line(line_to_display, cursor);
+        // The visual mode selection is shown in reverse video in
+        // place of the host supplied highlighting
+        let selection = match (&self.state, &self.vi) {
+            (EditorState::Editing, Some(vi)) => vi.selection(&self.line, self.cursor),
+            _ => None,
+        };
+        let (elements, cursor_x_pos) = match selection {
+            Some(range) => (
+                vec![
+                    OutputElement::Text(line_to_display[..range.start].to_string()),
+                    OutputElement::Attribute(AttributeChange::Reverse(true)),
+                    OutputElement::Text(line_to_display[range.clone()].to_string()),
+                    OutputElement::Attribute(AttributeChange::Reverse(false)),
+                    OutputElement::Text(line_to_display[range.end..].to_string()),
+                ],
+                unicode_column_width(&line_to_display[0..cursor], None),
+            ),
+            None => host.highlight_line(line_to_display, cursor),
+        };
 
         // Calculate what the cursor position would be after printing X columns
         // of text from the specified location.
@@ -297,6 +362,12 @@ impl<'term> LineEditor<'term> {
         self.prompt = prompt.to_owned();
     }
 
+    /// Returns the current vi mode state, or `None` if the editor
+    /// is not using vi mode.
+    pub fn vi_mode(&self) -> Option<ViMode> {
+        self.vi.as_ref().map(|vi| vi.mode())
+    }
+
     /// Enter line editing mode.
     /// Control is not returned to the caller until a line has been
     /// accepted, or until an error is detected.
@@ -321,6 +392,10 @@ impl<'term> LineEditor<'term> {
             self.terminal
                 .render(&[move_end, Change::ClearToEndOfScreen(Default::default())])?;
         }
+        if self.vi.is_some() {
+            self.terminal
+                .render(&[Change::CursorShape(CursorShape::Default)])?;
+        }
 
         self.terminal.flush()?;
         self.terminal.set_cooked_mode()?;
@@ -336,6 +411,12 @@ impl<'term> LineEditor<'term> {
             return Some(action);
         }
 
+        if let Some(vi) = self.vi.as_mut() {
+            if let Some(action) = vi.resolve_key(event) {
+                return Some(action);
+            }
+        }
+
         match event {
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('C'),
@@ -635,6 +716,31 @@ impl<'term> LineEditor<'term> {
         self.completion = None;
     }
 
+    /// Save the current line and cursor as an undo step
+    fn record_undo(&mut self) {
+        let state = (self.line.clone(), self.cursor);
+        if self.undo_stack.last() != Some(&state) {
+            self.undo_stack.push(state);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn undo(&mut self) {
+        if let Some((line, cursor)) = self.undo_stack.pop() {
+            let line = std::mem::replace(&mut self.line, line);
+            self.redo_stack.push((line, self.cursor));
+            self.cursor = cursor;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some((line, cursor)) = self.redo_stack.pop() {
+            let line = std::mem::replace(&mut self.line, line);
+            self.undo_stack.push((line, self.cursor));
+            self.cursor = cursor;
+        }
+    }
+
     fn cancel_search_state(&mut self) {
         if let EditorState::Searching {
             matching_line,
@@ -808,6 +914,29 @@ impl<'term> LineEditor<'term> {
             (action, _) => action,
         };
 
+        let searching = matches!(self.state, EditorState::Searching { .. });
+        match &action {
+            Action::InsertChar(..)
+            | Action::InsertText(..)
+            | Action::Kill(_)
+            | Action::KillAndMove(..)
+            | Action::Complete
+                if !searching =>
+            {
+                // Typing in vi insert mode belongs to the undo step that was
+                // recorded when insert mode was entered, while runs of
+                // inserted characters are grouped together in emacs mode
+                let grouped = self.vi_mode() == Some(ViMode::Insert)
+                    || (matches!(action, Action::InsertChar(..)) && self.grouping_inserts);
+                if !grouped {
+                    self.record_undo();
+                }
+            }
+            Action::Vi(command) if command.is_edit() => self.record_undo(),
+            _ => {}
+        }
+        self.grouping_inserts = matches!(action, Action::InsertChar(..));
+
         match action {
             Action::Cancel => self.state = EditorState::Cancelled,
             Action::NoAction => {}
@@ -942,6 +1071,36 @@ impl<'term> LineEditor<'term> {
                     self.line = line;
                 }
             }
+
+            Action::Undo(rep) => {
+                self.clear_completion();
+                self.cancel_search_state();
+                for _ in 0..rep {
+                    self.undo();
+                }
+            }
+            Action::Redo(rep) => {
+                self.clear_completion();
+                self.cancel_search_state();
+                for _ in 0..rep {
+                    self.redo();
+                }
+            }
+            Action::Vi(command) => {
+                self.clear_completion();
+                self.cancel_search_state();
+                if let Some(vi) = self.vi.as_mut() {
+                    vi.apply(command, &mut self.line, &mut self.cursor);
+                }
+            }
+        }
+
+        if self
+            .vi_mode()
+            .map(|mode| mode != ViMode::Insert)
+            .unwrap_or(false)
+        {
+            self.cursor = vi::clamp_normal_cursor(&self.line, self.cursor);
         }
 
         Ok(())
@@ -953,6 +1112,18 @@ impl<'term> LineEditor<'term> {
         self.history_pos = None;
         self.bottom_line = None;
         self.clear_completion();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.grouping_inserts = false;
+
+        self.vi = match (host.edit_mode(), self.vi.take()) {
+            (EditMode::Vi, Some(mut vi)) => {
+                vi.reset();
+                Some(vi)
+            }
+            (EditMode::Vi, None) => Some(ViState::default()),
+            (EditMode::Emacs, _) => None,
+        };
 
         self.render(host)?;
         while let Some(event) = self.terminal.poll_input(None)? {
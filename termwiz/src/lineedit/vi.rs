@@ -0,0 +1,1169 @@
+//! Vi style modal editing for the `LineEditor`.
+//!
+//! Keys pressed while in normal or visual mode are accumulated until
+//! they form a complete command such as `3dw`, `ci"` or `fx`, which is
+//! then returned as an `Action::Vi` for `LineEditor::apply_action` to
+//! apply to the line buffer.
+//!
+//! Motions and text objects are computed in terms of `char` indices
+//! rather than grapheme clusters.
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use crate::lineedit::actions::{Action, RepeatCount};
+use std::ops::Range;
+
+/// The maximum number of bytes that a single counted `p` or `P` may
+/// insert; a count such as `99999999p` is clamped so that it cannot
+/// exhaust memory.
+const MAX_PUT_BYTES: usize = 1024 * 1024;
+
+/// Selects the key bindings used by the `LineEditor`.
+/// The mode is queried from `LineEditorHost::edit_mode` each time
+/// `LineEditor::read_line` is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditMode {
+    /// readline style bindings; this is the default
+    #[default]
+    Emacs,
+    /// Modal vi style bindings.  Each line starts out in insert mode.
+    Vi,
+}
+
+/// The state of the vi mode editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViMode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViOperator {
+    /// `d`
+    Delete,
+    /// `c`
+    Change,
+    /// `y`
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindKind {
+    /// `f`
+    Forward,
+    /// `F`
+    Backward,
+    /// `t`
+    TillForward,
+    /// `T`
+    TillBackward,
+}
+
+impl FindKind {
+    fn reverse(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+            Self::TillForward => Self::TillBackward,
+            Self::TillBackward => Self::TillForward,
+        }
+    }
+
+    fn is_forward(self) -> bool {
+        matches!(self, Self::Forward | Self::TillForward)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViMotion {
+    /// `h`, Backspace
+    Left,
+    /// `l`, Space
+    Right,
+    /// `w` and `W`
+    WordForward { big: bool },
+    /// `b` and `B`
+    WordBackward { big: bool },
+    /// `e` and `E`
+    WordEnd { big: bool },
+    /// `0`
+    StartOfLine,
+    /// `^`
+    FirstNonBlank,
+    /// `$`
+    EndOfLine,
+    /// `f`, `F`, `t` and `T` followed by the character to find
+    Find(FindKind, char),
+    /// `;`
+    RepeatFind,
+    /// `,`
+    RepeatFindReverse,
+    /// The entire line; produced by doubling an operator, as in `dd`
+    WholeLine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViTextObject {
+    /// `w` and `W`
+    Word { big: bool },
+    /// `"`, `'` and `` ` ``
+    Quoted(char),
+    /// `(`, `[`, `{` and `<` along with their closing counterparts
+    Bracketed(char, char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViTarget {
+    Motion(ViMotion),
+    /// A text object; `around` is true for the `a` form
+    /// and false for the `i` form.
+    TextObject {
+        object: ViTextObject,
+        around: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// `i`
+    BeforeCursor,
+    /// `a`
+    AfterCursor,
+    /// `I`
+    FirstNonBlank,
+    /// `A`
+    EndOfLine,
+}
+
+/// A command produced by the vi mode key parser.
+/// These are ignored unless the editor is in vi mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViCommand {
+    Move(RepeatCount, ViMotion),
+    Operate(ViOperator, RepeatCount, ViTarget),
+    /// Apply an operator to the visual selection
+    OperateSelection(ViOperator),
+    /// Select a text object while in visual mode
+    SelectTextObject {
+        object: ViTextObject,
+        around: bool,
+    },
+    /// Swap the cursor and the anchor of the visual selection (`o`)
+    SwapSelectionEnds,
+    /// Toggle the case of the visual selection (`~`)
+    ToggleCaseSelection,
+    Insert(InsertPosition),
+    /// Return to normal mode from insert or visual mode
+    NormalMode,
+    /// `v`
+    VisualMode,
+    /// `r` followed by the replacement character
+    ReplaceChar(RepeatCount, char),
+    /// `~`
+    ToggleCase(RepeatCount),
+    /// `p` and `P`
+    Put {
+        before: bool,
+        count: RepeatCount,
+    },
+}
+
+impl ViCommand {
+    /// Returns true if the command modifies the line, or begins an
+    /// insert, and should therefore be recorded as an undo step.
+    pub fn is_edit(&self) -> bool {
+        match self {
+            Self::Operate(op, ..) | Self::OperateSelection(op) => *op != ViOperator::Yank,
+            Self::ToggleCaseSelection
+            | Self::Insert(_)
+            | Self::ReplaceChar(..)
+            | Self::ToggleCase(_)
+            | Self::Put { .. } => true,
+            Self::Move(..)
+            | Self::SelectTextObject { .. }
+            | Self::SwapSelectionEnds
+            | Self::NormalMode
+            | Self::VisualMode => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Parsed<T> {
+    Incomplete,
+    Invalid,
+    Complete(T),
+}
+
+impl<T> Parsed<T> {
+    fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Parsed<U> {
+        match self {
+            Self::Incomplete => Parsed::Incomplete,
+            Self::Invalid => Parsed::Invalid,
+            Self::Complete(t) => Parsed::Complete(f(t)),
+        }
+    }
+}
+
+/// Tracks the modal state, pending keys and the unnamed register
+pub(crate) struct ViState {
+    mode: ViMode,
+    pending: Vec<char>,
+    /// byte index of the fixed end of the visual selection
+    visual_anchor: usize,
+    register: String,
+    last_find: Option<(FindKind, char)>,
+}
+
+impl Default for ViState {
+    fn default() -> Self {
+        Self {
+            mode: ViMode::Insert,
+            pending: vec![],
+            visual_anchor: 0,
+            register: String::new(),
+            last_find: None,
+        }
+    }
+}
+
+impl ViState {
+    pub fn mode(&self) -> ViMode {
+        self.mode
+    }
+
+    /// Prepare for editing a new line.  The register and the last
+    /// character search are retained.
+    pub fn reset(&mut self) {
+        self.mode = ViMode::Insert;
+        self.pending.clear();
+        self.visual_anchor = 0;
+    }
+
+    /// Returns the byte range of the visual selection, if any
+    pub fn selection(&self, line: &str, cursor: usize) -> Option<Range<usize>> {
+        if self.mode != ViMode::Visual || line.is_empty() {
+            return None;
+        }
+        let anchor = char_index(line, self.visual_anchor);
+        let cursor = char_index(line, cursor);
+        let start = anchor.min(cursor);
+        let end = (anchor.max(cursor) + 1).min(line.chars().count());
+        Some(byte_index(line, start)..byte_index(line, end))
+    }
+
+    /// Map a key press to an action.
+    /// Returns `None` to fall back to the regular key bindings, which
+    /// is the case for most keys in insert mode.
+    pub fn resolve_key(&mut self, event: &InputEvent) -> Option<Action> {
+        let key = match event {
+            InputEvent::Key(key) => key,
+            _ => return None,
+        };
+
+        if self.mode == ViMode::Insert {
+            return match key {
+                KeyEvent {
+                    key: KeyCode::Escape,
+                    modifiers: Modifiers::NONE,
+                } => Some(Action::Vi(ViCommand::NormalMode)),
+                _ => None,
+            };
+        }
+
+        match key {
+            KeyEvent {
+                key: KeyCode::Escape,
+                modifiers: Modifiers::NONE,
+            } => {
+                self.pending.clear();
+                Some(match self.mode {
+                    ViMode::Visual => Action::Vi(ViCommand::NormalMode),
+                    _ => Action::NoAction,
+                })
+            }
+            KeyEvent {
+                key: KeyCode::Char('R'),
+                modifiers: Modifiers::CTRL,
+            } => {
+                let (count, _) = parse_count(&self.pending);
+                self.pending.clear();
+                Some(Action::Redo(count.unwrap_or(1)))
+            }
+            KeyEvent {
+                key: KeyCode::Backspace,
+                modifiers: Modifiers::NONE,
+            } => {
+                self.pending.clear();
+                Some(Action::Vi(ViCommand::Move(1, ViMotion::Left)))
+            }
+            KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::NONE,
+            }
+            | KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::SHIFT,
+            } => {
+                self.pending.push(*c);
+                match parse(&self.pending, self.mode == ViMode::Visual) {
+                    Parsed::Incomplete => Some(Action::NoAction),
+                    Parsed::Invalid => {
+                        self.pending.clear();
+                        Some(Action::NoAction)
+                    }
+                    Parsed::Complete(action) => {
+                        self.pending.clear();
+                        Some(action)
+                    }
+                }
+            }
+            _ => {
+                self.pending.clear();
+                None
+            }
+        }
+    }
+
+    /// Apply `command` to the line buffer.
+    /// `cursor` is the byte index of the cursor within `line`.
+    pub fn apply(&mut self, command: ViCommand, line: &mut String, cursor: &mut usize) {
+        let chars: Vec<char> = line.chars().collect();
+        let pos = char_index(line, *cursor);
+
+        match command {
+            ViCommand::Move(count, motion) => {
+                if let Some(target) = eval_motion(&chars, pos, motion, count, &mut self.last_find) {
+                    *cursor = byte_index(line, target);
+                }
+            }
+            ViCommand::Operate(op, count, target) => {
+                let range = match target {
+                    ViTarget::Motion(motion) => {
+                        motion_range(&chars, pos, op, motion, count, &mut self.last_find)
+                    }
+                    ViTarget::TextObject { object, around } => {
+                        text_object_range(&chars, pos, object, around)
+                    }
+                };
+                if let Some(range) = range {
+                    self.operate(op, range, line, cursor);
+                }
+            }
+            ViCommand::OperateSelection(op) => {
+                if let Some(range) = self.selection(line, *cursor) {
+                    let range = char_index(line, range.start)..char_index(line, range.end);
+                    self.operate(op, range, line, cursor);
+                }
+            }
+            ViCommand::SelectTextObject { object, around } => {
+                if let Some(range) = text_object_range(&chars, pos, object, around) {
+                    if !range.is_empty() {
+                        self.visual_anchor = byte_index(line, range.start);
+                        *cursor = byte_index(line, range.end - 1);
+                    }
+                }
+            }
+            ViCommand::SwapSelectionEnds => {
+                std::mem::swap(&mut self.visual_anchor, cursor);
+            }
+            ViCommand::ToggleCaseSelection => {
+                if let Some(range) = self.selection(line, *cursor) {
+                    let toggled = toggle_case(&line[range.clone()]);
+                    line.replace_range(range.clone(), &toggled);
+                    *cursor = range.start;
+                }
+                self.mode = ViMode::Normal;
+            }
+            ViCommand::Insert(position) => {
+                *cursor = match position {
+                    InsertPosition::BeforeCursor => *cursor,
+                    InsertPosition::AfterCursor => byte_index(line, (pos + 1).min(chars.len())),
+                    InsertPosition::FirstNonBlank => byte_index(line, first_non_blank(&chars)),
+                    InsertPosition::EndOfLine => line.len(),
+                };
+                self.mode = ViMode::Insert;
+            }
+            ViCommand::NormalMode => {
+                if self.mode == ViMode::Insert && pos > 0 {
+                    // Like vi, the cursor steps back onto the last
+                    // inserted character
+                    *cursor = byte_index(line, pos - 1);
+                }
+                self.mode = ViMode::Normal;
+            }
+            ViCommand::VisualMode => {
+                self.visual_anchor = *cursor;
+                self.mode = ViMode::Visual;
+            }
+            ViCommand::ReplaceChar(count, c) => {
+                if pos + count <= chars.len() {
+                    let start = byte_index(line, pos);
+                    let end = byte_index(line, pos + count);
+                    let replacement: String = std::iter::repeat(c).take(count).collect();
+                    line.replace_range(start..end, &replacement);
+                    *cursor = byte_index(line, pos + count - 1);
+                }
+            }
+            ViCommand::ToggleCase(count) => {
+                let end = (pos + count).min(chars.len());
+                let range = byte_index(line, pos)..byte_index(line, end);
+                let toggled = toggle_case(&line[range.clone()]);
+                line.replace_range(range.clone(), &toggled);
+                *cursor = range.start + toggled.len();
+            }
+            ViCommand::Put { before, count } => {
+                if let Some(last) = self.register.chars().last() {
+                    let count = count.min(MAX_PUT_BYTES / self.register.len()).max(1);
+                    let text = self.register.repeat(count);
+                    let at = if before || line.is_empty() {
+                        *cursor
+                    } else {
+                        byte_index(line, pos + 1)
+                    };
+                    line.insert_str(at, &text);
+                    *cursor = at + text.len() - last.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Apply an operator to the `char` index `range`
+    fn operate(
+        &mut self,
+        op: ViOperator,
+        range: Range<usize>,
+        line: &mut String,
+        cursor: &mut usize,
+    ) {
+        let start = byte_index(line, range.start);
+        let end = byte_index(line, range.end);
+        if start != end {
+            self.register = line[start..end].to_string();
+        }
+        *cursor = start;
+        match op {
+            ViOperator::Delete => {
+                line.replace_range(start..end, "");
+                self.mode = ViMode::Normal;
+            }
+            ViOperator::Change => {
+                line.replace_range(start..end, "");
+                self.mode = ViMode::Insert;
+            }
+            ViOperator::Yank => {
+                self.mode = ViMode::Normal;
+            }
+        }
+    }
+}
+
+/// Returns the `char` index corresponding to byte index `byte`
+pub(crate) fn char_index(line: &str, byte: usize) -> usize {
+    line[..byte.min(line.len())].chars().count()
+}
+
+/// Returns the byte index corresponding to `char` index `idx`
+pub(crate) fn byte_index(line: &str, idx: usize) -> usize {
+    line.char_indices()
+        .nth(idx)
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| line.len())
+}
+
+/// In normal and visual mode the cursor rests on a character rather
+/// than after the end of the line
+pub(crate) fn clamp_normal_cursor(line: &str, cursor: usize) -> usize {
+    if cursor >= line.len() {
+        line.char_indices().last().map(|(i, _)| i).unwrap_or(0)
+    } else {
+        cursor
+    }
+}
+
+fn toggle_case(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().collect::<Vec<_>>()
+            } else {
+                c.to_uppercase().collect::<Vec<_>>()
+            }
+        })
+        .collect()
+}
+
+/// Parse a (possibly absent) count from the start of `keys`.
+/// A leading `0` is the start-of-line motion rather than a count.
+fn parse_count(keys: &[char]) -> (Option<RepeatCount>, &[char]) {
+    let mut count: RepeatCount = 0;
+    let mut idx = 0;
+    while let Some(digit) = keys.get(idx).and_then(|c| c.to_digit(10)) {
+        if idx == 0 && digit == 0 {
+            break;
+        }
+        count = count
+            .saturating_mul(10)
+            .saturating_add(digit as RepeatCount);
+        idx += 1;
+    }
+    (if idx == 0 { None } else { Some(count) }, &keys[idx..])
+}
+
+fn parse_motion(keys: &[char]) -> Parsed<ViMotion> {
+    let find = |kind| match keys.get(1) {
+        Some(c) => Parsed::Complete(ViMotion::Find(kind, *c)),
+        None => Parsed::Incomplete,
+    };
+    let motion = match keys.first() {
+        None => return Parsed::Incomplete,
+        Some('h') => ViMotion::Left,
+        Some('l') | Some(' ') => ViMotion::Right,
+        Some('w') => ViMotion::WordForward { big: false },
+        Some('W') => ViMotion::WordForward { big: true },
+        Some('b') => ViMotion::WordBackward { big: false },
+        Some('B') => ViMotion::WordBackward { big: true },
+        Some('e') => ViMotion::WordEnd { big: false },
+        Some('E') => ViMotion::WordEnd { big: true },
+        Some('0') => ViMotion::StartOfLine,
+        Some('^') => ViMotion::FirstNonBlank,
+        Some('$') => ViMotion::EndOfLine,
+        Some(';') => ViMotion::RepeatFind,
+        Some(',') => ViMotion::RepeatFindReverse,
+        Some('f') => return find(FindKind::Forward),
+        Some('F') => return find(FindKind::Backward),
+        Some('t') => return find(FindKind::TillForward),
+        Some('T') => return find(FindKind::TillBackward),
+        Some(_) => return Parsed::Invalid,
+    };
+    Parsed::Complete(motion)
+}
+
+/// Parse `i` or `a` followed by a text object
+fn parse_text_object(keys: &[char]) -> Parsed<ViTarget> {
+    let around = match keys.first() {
+        None => return Parsed::Incomplete,
+        Some('i') => false,
+        Some('a') => true,
+        Some(_) => return Parsed::Invalid,
+    };
+    let object = match keys.get(1) {
+        None => return Parsed::Incomplete,
+        Some('w') => ViTextObject::Word { big: false },
+        Some('W') => ViTextObject::Word { big: true },
+        Some(q @ '"') | Some(q @ '\'') | Some(q @ '`') => ViTextObject::Quoted(*q),
+        Some('(') | Some(')') | Some('b') => ViTextObject::Bracketed('(', ')'),
+        Some('[') | Some(']') => ViTextObject::Bracketed('[', ']'),
+        Some('{') | Some('}') | Some('B') => ViTextObject::Bracketed('{', '}'),
+        Some('<') | Some('>') => ViTextObject::Bracketed('<', '>'),
+        Some(_) => return Parsed::Invalid,
+    };
+    Parsed::Complete(ViTarget::TextObject { object, around })
+}
+
+/// Parse the keys accumulated in normal or visual mode
+fn parse(keys: &[char], visual: bool) -> Parsed<Action> {
+    let (count, rest) = parse_count(keys);
+    let c = match rest.first() {
+        Some(c) => *c,
+        None => return Parsed::Incomplete,
+    };
+    let count = count.unwrap_or(1);
+    let vi = |command| Parsed::Complete(Action::Vi(command));
+
+    if visual {
+        let op = match c {
+            'd' | 'x' => ViOperator::Delete,
+            'c' | 's' => ViOperator::Change,
+            'y' => ViOperator::Yank,
+            '~' => return vi(ViCommand::ToggleCaseSelection),
+            'o' => return vi(ViCommand::SwapSelectionEnds),
+            'v' => return vi(ViCommand::NormalMode),
+            'i' | 'a' => {
+                return parse_text_object(rest).map(|target| match target {
+                    ViTarget::TextObject { object, around } => {
+                        Action::Vi(ViCommand::SelectTextObject { object, around })
+                    }
+                    ViTarget::Motion(motion) => Action::Vi(ViCommand::Move(count, motion)),
+                })
+            }
+            _ => return parse_motion(rest).map(|m| Action::Vi(ViCommand::Move(count, m))),
+        };
+        return vi(ViCommand::OperateSelection(op));
+    }
+
+    let op = match c {
+        'd' => Some(ViOperator::Delete),
+        'c' => Some(ViOperator::Change),
+        'y' => Some(ViOperator::Yank),
+        _ => None,
+    };
+    if let Some(op) = op {
+        // The operator may itself be followed by a count, as in `d3w`;
+        // the effective count is the product of both
+        let (op_count, rest) = parse_count(&rest[1..]);
+        let count = count.saturating_mul(op_count.unwrap_or(1));
+        return match rest.first() {
+            None => Parsed::Incomplete,
+            Some(c2) if *c2 == c => vi(ViCommand::Operate(
+                op,
+                count,
+                ViTarget::Motion(ViMotion::WholeLine),
+            )),
+            Some('i') | Some('a') => {
+                parse_text_object(rest).map(|t| Action::Vi(ViCommand::Operate(op, count, t)))
+            }
+            Some(_) => parse_motion(rest)
+                .map(|m| Action::Vi(ViCommand::Operate(op, count, ViTarget::Motion(m)))),
+        };
+    }
+
+    let operate = |op, count, motion| vi(ViCommand::Operate(op, count, ViTarget::Motion(motion)));
+
+    match c {
+        'x' => operate(ViOperator::Delete, count, ViMotion::Right),
+        'X' => operate(ViOperator::Delete, count, ViMotion::Left),
+        's' => operate(ViOperator::Change, count, ViMotion::Right),
+        'S' => operate(ViOperator::Change, 1, ViMotion::WholeLine),
+        'D' => operate(ViOperator::Delete, 1, ViMotion::EndOfLine),
+        'C' => operate(ViOperator::Change, 1, ViMotion::EndOfLine),
+        'Y' => operate(ViOperator::Yank, 1, ViMotion::WholeLine),
+        'i' => vi(ViCommand::Insert(InsertPosition::BeforeCursor)),
+        'a' => vi(ViCommand::Insert(InsertPosition::AfterCursor)),
+        'I' => vi(ViCommand::Insert(InsertPosition::FirstNonBlank)),
+        'A' => vi(ViCommand::Insert(InsertPosition::EndOfLine)),
+        'v' => vi(ViCommand::VisualMode),
+        '~' => vi(ViCommand::ToggleCase(count)),
+        'p' => vi(ViCommand::Put {
+            before: false,
+            count,
+        }),
+        'P' => vi(ViCommand::Put {
+            before: true,
+            count,
+        }),
+        'r' => match rest.get(1) {
+            Some(replacement) => vi(ViCommand::ReplaceChar(count, *replacement)),
+            None => Parsed::Incomplete,
+        },
+        'u' => Parsed::Complete(Action::Undo(count)),
+        'j' | '+' => Parsed::Complete(Action::HistoryNext),
+        'k' | '-' => Parsed::Complete(Action::HistoryPrevious),
+        _ => parse_motion(rest).map(|m| Action::Vi(ViCommand::Move(count, m))),
+    }
+}
+
+/// Classify a character for the purposes of word motions:
+/// 0 for whitespace, 1 for keyword characters and 2 for punctuation.
+/// For "big" words all non-whitespace characters are in the same class.
+fn char_class(c: char, big: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn first_non_blank(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .position(|c| !c.is_whitespace())
+        .unwrap_or(chars.len())
+}
+
+fn next_word_start(chars: &[char], mut idx: usize, big: bool) -> usize {
+    let len = chars.len();
+    if idx >= len {
+        return len;
+    }
+    let class = char_class(chars[idx], big);
+    if class != 0 {
+        while idx < len && char_class(chars[idx], big) == class {
+            idx += 1;
+        }
+    }
+    while idx < len && chars[idx].is_whitespace() {
+        idx += 1;
+    }
+    idx
+}
+
+fn prev_word_start(chars: &[char], mut idx: usize, big: bool) -> usize {
+    if idx == 0 || chars.is_empty() {
+        return 0;
+    }
+    idx = idx.min(chars.len()) - 1;
+    while idx > 0 && chars[idx].is_whitespace() {
+        idx -= 1;
+    }
+    let class = char_class(chars[idx], big);
+    while idx > 0 && char_class(chars[idx - 1], big) == class {
+        idx -= 1;
+    }
+    idx
+}
+
+fn is_word_end(chars: &[char], idx: usize, big: bool) -> bool {
+    match (chars.get(idx), chars.get(idx + 1)) {
+        (Some(c), Some(next)) => {
+            !c.is_whitespace() && char_class(*c, big) != char_class(*next, big)
+        }
+        (Some(c), None) => !c.is_whitespace(),
+        _ => false,
+    }
+}
+
+fn word_end(chars: &[char], mut idx: usize, big: bool) -> usize {
+    let len = chars.len();
+    if len == 0 {
+        return 0;
+    }
+    idx += 1;
+    while idx < len && chars[idx].is_whitespace() {
+        idx += 1;
+    }
+    if idx >= len {
+        return len - 1;
+    }
+    let class = char_class(chars[idx], big);
+    while idx + 1 < len && char_class(chars[idx + 1], big) == class {
+        idx += 1;
+    }
+    idx
+}
+
+fn find_char(
+    chars: &[char],
+    cursor: usize,
+    kind: FindKind,
+    target: char,
+    count: RepeatCount,
+) -> Option<usize> {
+    let mut idx = cursor;
+    for _ in 0..count {
+        idx = if kind.is_forward() {
+            (idx + 1..chars.len()).find(|&i| chars[i] == target)?
+        } else {
+            (0..idx).rev().find(|&i| chars[i] == target)?
+        };
+    }
+    Some(match kind {
+        FindKind::Forward | FindKind::Backward => idx,
+        FindKind::TillForward => idx - 1,
+        FindKind::TillBackward => idx + 1,
+    })
+}
+
+/// Resolve the find kind used by a motion, taking `;` and `,`
+/// into account
+fn find_kind(motion: ViMotion, last_find: &Option<(FindKind, char)>) -> Option<(FindKind, char)> {
+    match motion {
+        ViMotion::Find(kind, c) => Some((kind, c)),
+        ViMotion::RepeatFind => *last_find,
+        ViMotion::RepeatFindReverse => last_find.map(|(kind, c)| (kind.reverse(), c)),
+        _ => None,
+    }
+}
+
+/// Compute the `char` index that results from applying a motion.
+/// Returns `None` if the motion fails, such as when `f` doesn't find
+/// the requested character.
+pub(crate) fn eval_motion(
+    chars: &[char],
+    cursor: usize,
+    motion: ViMotion,
+    count: RepeatCount,
+    last_find: &mut Option<(FindKind, char)>,
+) -> Option<usize> {
+    let len = chars.len();
+    Some(match motion {
+        ViMotion::Left => cursor.saturating_sub(count),
+        ViMotion::Right => cursor.saturating_add(count).min(len),
+        ViMotion::WordForward { big } => {
+            (0..count).fold(cursor, |idx, _| next_word_start(chars, idx, big))
+        }
+        ViMotion::WordBackward { big } => {
+            (0..count).fold(cursor, |idx, _| prev_word_start(chars, idx, big))
+        }
+        ViMotion::WordEnd { big } => (0..count).fold(cursor, |idx, _| word_end(chars, idx, big)),
+        ViMotion::StartOfLine => 0,
+        ViMotion::FirstNonBlank => first_non_blank(chars),
+        ViMotion::EndOfLine => len.saturating_sub(1),
+        ViMotion::Find(..) | ViMotion::RepeatFind | ViMotion::RepeatFindReverse => {
+            let (kind, target) = find_kind(motion, last_find)?;
+            if let ViMotion::Find(..) = motion {
+                last_find.replace((kind, target));
+            }
+            find_char(chars, cursor, kind, target, count)?
+        }
+        ViMotion::WholeLine => cursor,
+    })
+}
+
+/// Compute the `char` index range affected by applying operator `op`
+/// with `motion`
+pub(crate) fn motion_range(
+    chars: &[char],
+    cursor: usize,
+    op: ViOperator,
+    motion: ViMotion,
+    count: RepeatCount,
+    last_find: &mut Option<(FindKind, char)>,
+) -> Option<Range<usize>> {
+    let len = chars.len();
+    let (target, inclusive) = match motion {
+        ViMotion::WholeLine => return Some(0..len),
+        // `cw` on a non-blank behaves like `ce`, except that a word
+        // that ends under the cursor is changed in its entirety
+        ViMotion::WordForward { big }
+            if op == ViOperator::Change
+                && chars
+                    .get(cursor)
+                    .map(|c| !c.is_whitespace())
+                    .unwrap_or(false) =>
+        {
+            let mut target = cursor;
+            for n in 0..count {
+                if n == 0 && is_word_end(chars, target, big) {
+                    continue;
+                }
+                target = word_end(chars, target, big);
+            }
+            (target, true)
+        }
+        _ => {
+            let target = eval_motion(chars, cursor, motion, count, last_find)?;
+            let inclusive = match motion {
+                ViMotion::WordEnd { .. } | ViMotion::EndOfLine => true,
+                _ => find_kind(motion, last_find)
+                    .map(|(kind, _)| kind.is_forward())
+                    .unwrap_or(false),
+            };
+            (target, inclusive)
+        }
+    };
+
+    let (start, end) = if target < cursor {
+        (target, cursor)
+    } else {
+        (cursor, target)
+    };
+    let end = if inclusive { (end + 1).min(len) } else { end };
+    Some(start..end)
+}
+
+/// Compute the `char` index range of a text object surrounding
+/// the cursor
+pub(crate) fn text_object_range(
+    chars: &[char],
+    cursor: usize,
+    object: ViTextObject,
+    around: bool,
+) -> Option<Range<usize>> {
+    let len = chars.len();
+    if len == 0 {
+        return None;
+    }
+    let cursor = cursor.min(len - 1);
+
+    match object {
+        ViTextObject::Word { big } => {
+            let class = char_class(chars[cursor], big);
+            let mut start = cursor;
+            while start > 0 && char_class(chars[start - 1], big) == class {
+                start -= 1;
+            }
+            let mut end = cursor + 1;
+            while end < len && char_class(chars[end], big) == class {
+                end += 1;
+            }
+            if around {
+                if class == 0 {
+                    // Whitespace followed by the next word
+                    if let Some(next) = chars.get(end) {
+                        let next_class = char_class(*next, big);
+                        while end < len && char_class(chars[end], big) == next_class {
+                            end += 1;
+                        }
+                    }
+                } else {
+                    // Trailing whitespace, or leading whitespace if
+                    // there is no trailing whitespace
+                    let word_end = end;
+                    while end < len && chars[end].is_whitespace() {
+                        end += 1;
+                    }
+                    if end == word_end {
+                        while start > 0 && chars[start - 1].is_whitespace() {
+                            start -= 1;
+                        }
+                    }
+                }
+            }
+            Some(start..end)
+        }
+        ViTextObject::Quoted(quote) => {
+            let quotes: Vec<usize> = (0..len)
+                .filter(|&i| chars[i] == quote && (i == 0 || chars[i - 1] != '\\'))
+                .collect();
+            // Quotes pair up from the start of the line; use the pair
+            // that contains the cursor, or else the first that follows it
+            let pair = quotes.chunks_exact(2).find(|pair| cursor <= pair[1])?;
+            let (open, close) = (pair[0], pair[1]);
+            if around {
+                let mut end = close + 1;
+                while end < len && chars[end].is_whitespace() {
+                    end += 1;
+                }
+                Some(open..end)
+            } else {
+                Some(open + 1..close)
+            }
+        }
+        ViTextObject::Bracketed(open, close) => {
+            let start = if chars[cursor] == open {
+                cursor
+            } else {
+                let mut depth = 0;
+                let mut found = None;
+                for idx in (0..cursor).rev() {
+                    if chars[idx] == close {
+                        depth += 1;
+                    } else if chars[idx] == open {
+                        if depth == 0 {
+                            found = Some(idx);
+                            break;
+                        }
+                        depth -= 1;
+                    }
+                }
+                found?
+            };
+            let mut depth = 0;
+            let mut end = None;
+            for idx in start + 1..len {
+                if chars[idx] == open {
+                    depth += 1;
+                } else if chars[idx] == close {
+                    if depth == 0 {
+                        end = Some(idx);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let end = end?;
+            if around {
+                Some(start..end + 1)
+            } else {
+                Some(start + 1..end)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_str(keys: &str) -> Parsed<ViCommand> {
+        let keys: Vec<char> = keys.chars().collect();
+        match parse(&keys, false) {
+            Parsed::Incomplete => Parsed::Incomplete,
+            Parsed::Invalid => Parsed::Invalid,
+            Parsed::Complete(Action::Vi(command)) => Parsed::Complete(command),
+            Parsed::Complete(action) => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    /// Run a sequence of normal mode keys against `line` with the
+    /// cursor at byte `cursor`, returning the resulting line and cursor
+    fn run(line: &str, cursor: usize, keys: &str) -> (String, usize) {
+        let mut state = ViState::default();
+        state.mode = ViMode::Normal;
+        let mut line = line.to_string();
+        let mut cursor = cursor;
+        for c in keys.chars() {
+            let event = InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::NONE,
+            });
+            if let Some(Action::Vi(command)) = state.resolve_key(&event) {
+                state.apply(command, &mut line, &mut cursor);
+            }
+        }
+        (line, cursor)
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(
+            parse_str("3w"),
+            Parsed::Complete(ViCommand::Move(3, ViMotion::WordForward { big: false }))
+        );
+        assert_eq!(
+            parse_str("2d3w"),
+            Parsed::Complete(ViCommand::Operate(
+                ViOperator::Delete,
+                6,
+                ViTarget::Motion(ViMotion::WordForward { big: false })
+            ))
+        );
+        assert_eq!(
+            parse_str("0"),
+            Parsed::Complete(ViCommand::Move(1, ViMotion::StartOfLine))
+        );
+        assert_eq!(
+            parse_str("10l"),
+            Parsed::Complete(ViCommand::Move(10, ViMotion::Right))
+        );
+    }
+
+    #[test]
+    fn pending() {
+        assert_eq!(parse_str("d"), Parsed::Incomplete);
+        assert_eq!(parse_str("ci"), Parsed::Incomplete);
+        assert_eq!(parse_str("f"), Parsed::Incomplete);
+        assert_eq!(parse_str("dz"), Parsed::Invalid);
+        assert_eq!(
+            parse_str("ci\""),
+            Parsed::Complete(ViCommand::Operate(
+                ViOperator::Change,
+                1,
+                ViTarget::TextObject {
+                    object: ViTextObject::Quoted('"'),
+                    around: false
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn word_motions() {
+        let chars: Vec<char> = "foo.bar  baz".chars().collect();
+        let mut last_find = None;
+        let w = ViMotion::WordForward { big: false };
+        let big_w = ViMotion::WordForward { big: true };
+        assert_eq!(eval_motion(&chars, 0, w, 1, &mut last_find), Some(3));
+        assert_eq!(eval_motion(&chars, 0, w, 3, &mut last_find), Some(9));
+        assert_eq!(eval_motion(&chars, 0, big_w, 1, &mut last_find), Some(9));
+        assert_eq!(
+            eval_motion(
+                &chars,
+                9,
+                ViMotion::WordBackward { big: false },
+                1,
+                &mut last_find
+            ),
+            Some(4)
+        );
+        assert_eq!(
+            eval_motion(
+                &chars,
+                0,
+                ViMotion::WordEnd { big: false },
+                1,
+                &mut last_find
+            ),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn delete_word() {
+        assert_eq!(run("hello world", 0, "dw"), ("world".to_string(), 0));
+        assert_eq!(run("one two three", 0, "d2w"), ("three".to_string(), 0));
+        assert_eq!(run("one two three", 4, "dd"), ("".to_string(), 0));
+        assert_eq!(run("one two three", 4, "D"), ("one ".to_string(), 4));
+    }
+
+    #[test]
+    fn change_word() {
+        let mut state = ViState::default();
+        state.mode = ViMode::Normal;
+        let mut line = "hello world".to_string();
+        let mut cursor = 0;
+        state.apply(
+            ViCommand::Operate(
+                ViOperator::Change,
+                1,
+                ViTarget::Motion(ViMotion::WordForward { big: false }),
+            ),
+            &mut line,
+            &mut cursor,
+        );
+        // `cw` leaves the trailing space intact
+        assert_eq!(line, " world");
+        assert_eq!(cursor, 0);
+        assert_eq!(state.mode(), ViMode::Insert);
+        assert_eq!(state.register, "hello");
+    }
+
+    #[test]
+    fn find_char_motions() {
+        assert_eq!(run("a,b,c,d", 0, "f,"), ("a,b,c,d".to_string(), 1));
+        assert_eq!(run("a,b,c,d", 0, "2f,"), ("a,b,c,d".to_string(), 3));
+        assert_eq!(run("a,b,c,d", 0, "f,;"), ("a,b,c,d".to_string(), 3));
+        assert_eq!(run("a,b,c,d", 0, "f,;,"), ("a,b,c,d".to_string(), 1));
+        assert_eq!(run("a,b,c,d", 0, "dt,"), (",b,c,d".to_string(), 0));
+        assert_eq!(run("a,b,c,d", 0, "df,"), ("b,c,d".to_string(), 0));
+        assert_eq!(run("a,b,c,d", 6, "dF,"), ("a,b,cd".to_string(), 5));
+    }
+
+    #[test]
+    fn text_objects() {
+        assert_eq!(
+            run("echo \"hello world\" done", 8, "ci\""),
+            ("echo \"\" done".to_string(), 6)
+        );
+        assert_eq!(
+            run("echo \"hello world\" done", 8, "da\""),
+            ("echo done".to_string(), 5)
+        );
+        assert_eq!(run("f(a, (b), c)", 2, "di("), ("f()".to_string(), 2));
+        assert_eq!(run("f(a, (b), c)", 6, "da("), ("f(a, , c)".to_string(), 5));
+        assert_eq!(
+            run("one two three", 5, "diw"),
+            ("one  three".to_string(), 4)
+        );
+        assert_eq!(run("one two three", 5, "daw"), ("one three".to_string(), 4));
+        assert_eq!(run("one two", 5, "daw"), ("one".to_string(), 3));
+    }
+
+    #[test]
+    fn put_and_replace() {
+        assert_eq!(run("abc", 0, "xp"), ("bac".to_string(), 1));
+        assert_eq!(run("abc", 0, "yl2P"), ("aaabc".to_string(), 1));
+        assert_eq!(run("abc", 0, "2rx"), ("xxc".to_string(), 1));
+        assert_eq!(run("abc", 0, "3~"), ("ABC".to_string(), 3));
+    }
+
+    #[test]
+    fn put_count_is_clamped() {
+        let (line, _) = run("abc", 0, "yl99999999999p");
+        assert_eq!(line.len(), 3 + MAX_PUT_BYTES);
+    }
+
+    #[test]
+    fn visual() {
+        let mut state = ViState::default();
+        state.mode = ViMode::Normal;
+        let mut line = "one two three".to_string();
+        let mut cursor = 4;
+        state.apply(ViCommand::VisualMode, &mut line, &mut cursor);
+        state.apply(
+            ViCommand::Move(1, ViMotion::WordEnd { big: false }),
+            &mut line,
+            &mut cursor,
+        );
+        assert_eq!(state.selection(&line, cursor), Some(4..7));
+        state.apply(
+            ViCommand::OperateSelection(ViOperator::Delete),
+            &mut line,
+            &mut cursor,
+        );
+        assert_eq!(line, "one  three");
+        assert_eq!(state.mode(), ViMode::Normal);
+    }
+}
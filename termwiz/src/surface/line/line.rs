@@ -195,6 +195,7 @@ impl Line {
     /// Wrap the line so that it fits within the provided width.
     /// Returns the list of resultant line(s)
     pub fn wrap(self, width: usize, seqno: SequenceNo) -> Vec<Self> {
+        let marked = self.is_marked();
         let mut cells: Vec<CellRef> = self.visible_cells().collect();
         if let Some(end_idx) = cells.iter().rposition(|c| c.str() != " ") {
             cells.truncate(end_idx + 1);
@@ -223,6 +224,12 @@ impl Line {
                 );
             }
 
+            if marked {
+                if let Some(line) = lines.first_mut() {
+                    line.set_marked(true, seqno);
+                }
+            }
+
             lines
         } else {
             vec![self]
@@ -381,6 +388,18 @@ impl Line {
         self.update_last_change_seqno(seqno);
     }
 
+    /// Returns true if the line has been marked via set_marked
+    pub fn is_marked(&self) -> bool {
+        self.bits.contains(LineBits::MARKED)
+    }
+
+    /// Set a flag to indicate that the line has been marked as a
+    /// location that the user may want to navigate back to
+    pub fn set_marked(&mut self, marked: bool, seqno: SequenceNo) {
+        self.bits.set(LineBits::MARKED, marked);
+        self.update_last_change_seqno(seqno);
+    }
+
     /// Returns a tuple of (BIDI_ENABLED, Direction), indicating whether
     /// the line should have the bidi algorithm applied and its base
     /// direction, respectively.
@@ -403,7 +422,7 @@ impl Line {
         self.zones.clear();
     }
 
-    fn compute_zones(&mut self) {
+    fn compute_zones(&self) -> Vec<ZoneRange> {
         let blank_cell = Cell::blank();
         let mut last_cell: Option<CellRef> = None;
         let mut current_zone: Option<ZoneRange> = None;
@@ -452,16 +471,27 @@ impl Line {
         if let Some(zone) = current_zone.take() {
             zones.push(zone);
         }
-        self.zones = zones;
+        zones
     }
 
     pub fn semantic_zone_ranges(&mut self) -> &[ZoneRange] {
         if self.zones.is_empty() {
-            self.compute_zones();
+            self.zones = self.compute_zones();
         }
         &self.zones
     }
 
+    /// Like `semantic_zone_ranges`, but doesn't require mutable access.
+    /// The cached ranges are returned if present, otherwise they are
+    /// computed without being cached.
+    pub fn semantic_zone_ranges_uncached(&self) -> Cow<[ZoneRange]> {
+        if self.zones.is_empty() {
+            Cow::Owned(self.compute_zones())
+        } else {
+            Cow::Borrowed(&self.zones)
+        }
+    }
+
     /// If we have any cells with an implicit hyperlink, remove the hyperlink
     /// from the cell attributes but leave the remainder of the attributes alone.
     #[inline]
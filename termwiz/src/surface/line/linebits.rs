@@ -49,5 +49,10 @@ bitflags! {
         /// Otherwise, the auto-detect direction is used, falling back
         /// to the direction specified by the RTL bit.
         const AUTO_DETECT_DIRECTION = 1<<8;
+
+        /// true if the line has been marked, for example by the
+        /// iTerm2 `SetMark` escape sequence, so that it can be
+        /// navigated to later.
+        const MARKED = 1<<9;
     }
 }
@@ -0,0 +1,308 @@
+#!/usr/bin/env python3
+
+# Generates the tiny color fonts used by the wezterm-font rasterizer tests.
+#
+# Both fonts have 1000 units per em and the same four glyphs:
+#   0: .notdef (empty)
+#   1: `A`, a square covering the whole em
+#   2: `B`, a color glyph that is solid red (COLRv1) or solid green (SVG)
+#   3: `C`, a color glyph painted in the text foreground color
+# Glyphs 2 and 3 use the square as their monochrome fallback outline.
+#
+# Run this from the test-data directory to regenerate
+# colrv1.ttf and svg-glyphs.ttf.
+
+import struct
+
+UPEM = 1000
+NUM_GLYPHS = 4
+
+
+def u16(v):
+    return struct.pack(">H", v & 0xFFFF)
+
+
+def i16(v):
+    return struct.pack(">h", v)
+
+
+def u24(v):
+    return struct.pack(">I", v)[1:]
+
+
+def u32(v):
+    return struct.pack(">I", v & 0xFFFFFFFF)
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def head():
+    return (
+        u32(0x00010000)  # version
+        + u32(0x00010000)  # fontRevision
+        + u32(0)  # checkSumAdjustment; patched later
+        + u32(0x5F0F3CF5)  # magicNumber
+        + u16(0x000B)  # flags
+        + u16(UPEM)
+        + b"\0" * 16  # created, modified
+        + i16(0)
+        + i16(0)
+        + i16(UPEM)
+        + i16(UPEM)  # bounding box
+        + u16(0)  # macStyle
+        + u16(8)  # lowestRecPPEM
+        + i16(2)  # fontDirectionHint
+        + i16(0)  # indexToLocFormat: short offsets
+        + i16(0)  # glyphDataFormat
+    )
+
+
+def hhea():
+    return (
+        u32(0x00010000)
+        + i16(UPEM)  # ascender
+        + i16(0)  # descender
+        + i16(0)  # lineGap
+        + u16(UPEM)  # advanceWidthMax
+        + i16(0)  # minLeftSideBearing
+        + i16(0)  # minRightSideBearing
+        + i16(UPEM)  # xMaxExtent
+        + i16(1)  # caretSlopeRise
+        + i16(0)  # caretSlopeRun
+        + i16(0)  # caretOffset
+        + b"\0" * 8  # reserved
+        + i16(0)  # metricDataFormat
+        + u16(NUM_GLYPHS)  # numberOfHMetrics
+    )
+
+
+def maxp():
+    return (
+        u32(0x00010000)
+        + u16(NUM_GLYPHS)
+        + u16(4)  # maxPoints
+        + u16(1)  # maxContours
+        + u16(0)  # maxCompositePoints
+        + u16(0)  # maxCompositeContours
+        + u16(2)  # maxZones
+        + u16(0) * 8
+    )
+
+
+def os2():
+    return (
+        u16(4)  # version
+        + i16(UPEM)  # xAvgCharWidth
+        + u16(400)  # usWeightClass
+        + u16(5)  # usWidthClass
+        + u16(0)  # fsType
+        + i16(0) * 10  # sub/superscript and strikeout metrics
+        + i16(0)  # sFamilyClass
+        + b"\0" * 10  # panose
+        + u32(1) + u32(0) * 3  # ulUnicodeRange: Basic Latin
+        + b"WEZ "  # achVendID
+        + u16(0x40)  # fsSelection: REGULAR
+        + u16(0x41)  # usFirstCharIndex
+        + u16(0x43)  # usLastCharIndex
+        + i16(UPEM)  # sTypoAscender
+        + i16(0)  # sTypoDescender
+        + i16(0)  # sTypoLineGap
+        + u16(UPEM)  # usWinAscent
+        + u16(0)  # usWinDescent
+        + u32(1) + u32(0)  # ulCodePageRange: Latin 1
+        + i16(UPEM)  # sxHeight
+        + i16(UPEM)  # sCapHeight
+        + u16(0)  # usDefaultChar
+        + u16(0x20)  # usBreakChar
+        + u16(1)  # usMaxContext
+    )
+
+
+def hmtx():
+    return (u16(UPEM) + i16(0)) * NUM_GLYPHS
+
+
+def cmap():
+    # Format 4 mapping A..C to glyphs 1..3
+    seg_count = 2
+    sub = (
+        u16(4)
+        + u16(16 + 8 * seg_count)  # length
+        + u16(0)  # language
+        + u16(seg_count * 2)
+        + u16(4)  # searchRange
+        + u16(1)  # entrySelector
+        + u16(0)  # rangeShift
+        + u16(0x43)
+        + u16(0xFFFF)  # endCode
+        + u16(0)  # reservedPad
+        + u16(0x41)
+        + u16(0xFFFF)  # startCode
+        + i16(1 - 0x41)
+        + i16(1)  # idDelta
+        + u16(0)
+        + u16(0)  # idRangeOffset
+    )
+    return u16(0) + u16(1) + u16(3) + u16(1) + u32(12) + sub
+
+
+def square():
+    # A single clockwise contour covering the em square
+    return (
+        i16(1)  # numberOfContours
+        + i16(0)
+        + i16(0)
+        + i16(UPEM)
+        + i16(UPEM)
+        + u16(3)  # endPtsOfContours
+        + u16(0)  # instructionLength
+        + bytes([0x01] * 4)  # on-curve, long coordinates
+        + i16(0) + i16(0) + i16(UPEM) + i16(0)  # x deltas
+        + i16(0) + i16(UPEM) + i16(0) + i16(-UPEM)  # y deltas
+    )
+
+
+def glyf_and_loca():
+    glyphs = [b"", square(), square(), square()]
+    glyf = b""
+    loca = b""
+    for g in glyphs:
+        loca += u16(len(glyf) // 2)
+        glyf += g
+    loca += u16(len(glyf) // 2)
+    return glyf, loca
+
+
+def post():
+    return u32(0x00030000) + u32(0) + i16(-100) + i16(50) + u32(1) + u32(0) * 4
+
+
+def name(family):
+    names = [
+        (1, family),
+        (2, "Regular"),
+        (4, family + " Regular"),
+        (6, family.replace(" ", "") + "-Regular"),
+    ]
+    header_len = 6 + 12 * len(names)
+    records = b""
+    strings = b""
+    for name_id, text in names:
+        data = text.encode("utf-16-be")
+        records += u16(3) + u16(1) + u16(0x409) + u16(name_id)
+        records += u16(len(data)) + u16(len(strings))
+        strings += data
+    return u16(0) + u16(len(names)) + u16(header_len) + records + strings
+
+
+def cpal():
+    # A single palette whose only entry is opaque red
+    return (
+        u16(0)  # version
+        + u16(1)  # numPaletteEntries
+        + u16(1)  # numPalettes
+        + u16(1)  # numColorRecords
+        + u32(14)  # colorRecordsArrayOffset
+        + u16(0)  # colorRecordIndices[0]
+        + bytes([0, 0, 0xFF, 0xFF])  # red, stored as BGRA
+    )
+
+
+def colr():
+    def paint_solid_square(palette_index):
+        # PaintGlyph(gid 1) filled with PaintSolid
+        return (
+            bytes([10])
+            + u24(6)
+            + u16(1)
+            + bytes([2])
+            + u16(palette_index)
+            + u16(0x4000)  # alpha 1.0
+        )
+
+    paints = [(2, paint_solid_square(0)), (3, paint_solid_square(0xFFFF))]
+    list_len = 4 + 6 * len(paints)
+    base_glyph_list = u32(len(paints))
+    paint_data = b""
+    for gid, paint in paints:
+        base_glyph_list += u16(gid) + u32(list_len + len(paint_data))
+        paint_data += paint
+
+    header = (
+        u16(1)  # version
+        + u16(0)  # numBaseGlyphRecords
+        + u32(0)  # baseGlyphRecordsOffset
+        + u32(0)  # layerRecordsOffset
+        + u16(0)  # numLayerRecords
+        + u32(34)  # baseGlyphListOffset
+        + u32(0)  # layerListOffset
+        + u32(0)  # clipListOffset
+        + u32(0)  # varIndexMapOffset
+        + u32(0)  # itemVariationStoreOffset
+    )
+    assert len(header) == 34
+    return header + base_glyph_list + paint_data
+
+
+def svg():
+    doc = (
+        '<svg xmlns="http://www.w3.org/2000/svg">'
+        '<rect id="glyph2" y="-1000" width="1000" height="1000" fill="#00ff00"/>'
+        '<rect id="glyph3" y="-1000" width="1000" height="1000" fill="currentColor"/>'
+        "</svg>"
+    ).encode("utf-8")
+    doc_list = u16(1) + u16(2) + u16(3) + u32(14) + u32(len(doc)) + doc
+    return u16(0) + u32(10) + u32(0) + doc_list
+
+
+def build(path, family, extra):
+    glyf, loca = glyf_and_loca()
+    tables = {
+        b"head": head(),
+        b"hhea": hhea(),
+        b"maxp": maxp(),
+        b"OS/2": os2(),
+        b"hmtx": hmtx(),
+        b"cmap": cmap(),
+        b"glyf": glyf,
+        b"loca": loca,
+        b"post": post(),
+        b"name": name(family),
+    }
+    tables.update(extra)
+
+    tags = sorted(tables)
+    num_tables = len(tags)
+    entry_selector = num_tables.bit_length() - 1
+    search_range = 16 << entry_selector
+    font = (
+        u32(0x00010000)
+        + u16(num_tables)
+        + u16(search_range)
+        + u16(entry_selector)
+        + u16(num_tables * 16 - search_range)
+    )
+    offset = 12 + 16 * num_tables
+    directory = b""
+    data = b""
+    head_offset = None
+    for tag in tags:
+        table = tables[tag]
+        if tag == b"head":
+            head_offset = offset + len(data)
+        directory += tag + u32(checksum(table)) + u32(offset + len(data)) + u32(len(table))
+        data += table + b"\0" * (-len(table) % 4)
+
+    font = bytearray(font + directory + data)
+    adjustment = (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF
+    font[head_offset + 8 : head_offset + 12] = u32(adjustment)
+
+    with open(path, "wb") as f:
+        f.write(font)
+
+
+build("colrv1.ttf", "WezTerm COLR Test", {b"COLR": colr(), b"CPAL": cpal()})
+build("svg-glyphs.ttf", "WezTerm SVG Test", {b"SVG ": svg()})
@@ -1281,17 +1281,22 @@ impl Client {
     rpc!(list_panes, ListPanes = (), ListPanesResponse);
     rpc!(spawn_v2, SpawnV2, SpawnResponse);
     rpc!(split_pane, SplitPane, SpawnResponse);
+    rpc!(spawn_floating_pane, SpawnFloatingPane, SpawnResponse);
     rpc!(
         move_pane_to_new_tab,
         MovePaneToNewTab,
         MovePaneToNewTabResponse
     );
+    rpc!(swap_panes, SwapPanes, UnitResponse);
+    rpc!(pipe_pane, PipePane, UnitResponse);
+    rpc!(move_tab_to_window, MoveTabToWindow, MoveTabToWindowResponse);
     rpc!(write_to_pane, WriteToPane, UnitResponse);
     rpc!(send_paste, SendPaste, UnitResponse);
     rpc!(key_down, SendKeyDown, UnitResponse);
     rpc!(mouse_event, SendMouseEvent, UnitResponse);
     rpc!(resize, Resize, UnitResponse);
     rpc!(set_zoomed, SetPaneZoomed, UnitResponse);
+    rpc!(set_floating_state, SetFloatingPaneState, UnitResponse);
     rpc!(activate_pane_direction, ActivatePaneDirection, UnitResponse);
     rpc!(
         get_pane_render_changes,
@@ -1299,6 +1304,7 @@ impl Client {
         LivenessResponse
     );
     rpc!(get_lines, GetLines, GetLinesResponse);
+    rpc!(export_lines, ExportLines, ExportLinesResponse);
     rpc!(
         get_dimensions,
         GetPaneRenderableDimensions,
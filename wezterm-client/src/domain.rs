@@ -2,13 +2,13 @@ use crate::client::Client;
 use crate::pane::ClientPane;
 use anyhow::{anyhow, bail};
 use async_trait::async_trait;
-use codec::{ListPanesResponse, SpawnV2, SplitPane};
-use config::keyassignment::SpawnTabDomain;
+use codec::{ListPanesResponse, SpawnFloatingPane, SpawnV2, SplitPane};
+use config::keyassignment::{PipePaneSpec, SpawnTabDomain};
 use config::{SshDomain, TlsDomainClient, UnixDomain};
 use mux::connui::{ConnectionUI, ConnectionUIParams};
 use mux::domain::{alloc_domain_id, Domain, DomainId, DomainState, SplitSource};
 use mux::pane::{Pane, PaneId};
-use mux::tab::{SplitRequest, Tab, TabId};
+use mux::tab::{FloatingPaneRect, PaneEntry, SplitRequest, Tab, TabId};
 use mux::window::WindowId;
 use mux::{Mux, MuxNotification};
 use portable_pty::CommandBuilder;
@@ -544,7 +544,12 @@ impl ClientDomain {
             .copied()
             .collect();
 
-        for (tabroot, tab_title) in panes.tabs.into_iter().zip(panes.tab_titles.iter()) {
+        for ((tabroot, tab_title), floating) in panes
+            .tabs
+            .into_iter()
+            .zip(panes.tab_titles.iter())
+            .zip(panes.floating_panes.into_iter())
+        {
             let root_size = match tabroot.root_size() {
                 Some(size) => size,
                 None => continue,
@@ -584,7 +589,7 @@ impl ClientDomain {
 
                 log::debug!("domain: {} tree: {:#?}", inner.local_domain_id, tabroot);
                 let mut workspace = None;
-                tab.sync_with_pane_tree(root_size, tabroot, |entry| {
+                let mut make_pane = |entry: PaneEntry| -> Arc<dyn Pane> {
                     workspace.replace(entry.workspace.clone());
                     remote_panes_to_forget.remove(&entry.pane_id);
                     if let Some(pane_id) = inner.remote_to_local_pane_id(entry.pane_id) {
@@ -623,7 +628,9 @@ impl ClientDomain {
                         mux.add_pane(&pane).expect("failed to add pane to mux");
                         pane
                     }
-                });
+                };
+                tab.sync_with_pane_tree(root_size, tabroot, &mut make_pane);
+                tab.sync_with_floating_panes(floating, &mut make_pane);
 
                 if let Some(local_window_id) = inner.remote_to_local_window(remote_window_id) {
                     let mut window = mux
@@ -821,6 +828,106 @@ impl Domain for ClientDomain {
         Ok(Some((tab, local_win_id)))
     }
 
+    async fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let mux = Mux::get();
+        let remote_pane_id = |pane_id: PaneId| -> anyhow::Result<PaneId> {
+            let local_pane = mux
+                .get_pane(pane_id)
+                .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+            let pane = local_pane
+                .downcast_ref::<ClientPane>()
+                .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", pane_id))?;
+            Ok(pane.remote_pane_id)
+        };
+
+        inner
+            .client
+            .swap_panes(codec::SwapPanes {
+                pane_id: remote_pane_id(pane_id)?,
+                other_pane_id: remote_pane_id(other_pane_id)?,
+            })
+            .await?;
+
+        self.resync().await?;
+
+        Ok(true)
+    }
+
+    async fn pipe_pane(&self, pane_id: PaneId, spec: Option<PipePaneSpec>) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+        let local_pane = Mux::get()
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+        let pane = local_pane
+            .downcast_ref::<ClientPane>()
+            .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", pane_id))?;
+
+        inner
+            .client
+            .pipe_pane(codec::PipePane {
+                pane_id: pane.remote_pane_id,
+                spec,
+            })
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn move_tab_to_window(
+        &self,
+        tab_id: TabId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<WindowId>> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let remote_tab_id = self
+            .local_to_remote_tab_id(tab_id)
+            .ok_or_else(|| anyhow!("tab {} has no remote counterpart", tab_id))?;
+        let remote_window_id = match window_id {
+            Some(local_window) => Some(self.local_to_remote_window_id(local_window).ok_or_else(
+                || {
+                    anyhow!(
+                        "window {} is not associated with domain {}",
+                        local_window,
+                        self.local_domain_id
+                    )
+                },
+            )?),
+            None => None,
+        };
+
+        let result = inner
+            .client
+            .move_tab_to_window(codec::MoveTabToWindow {
+                tab_id: remote_tab_id,
+                window_id: remote_window_id,
+                workspace_for_new_window,
+            })
+            .await?;
+
+        self.resync().await?;
+
+        let local_win_id = self
+            .remote_to_local_window_id(result.window_id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "remote window {} didn't resolve after resync",
+                    result.window_id
+                )
+            })?;
+
+        Ok(Some(local_win_id))
+    }
+
     async fn spawn(
         &self,
         size: TerminalSize,
@@ -890,12 +997,26 @@ impl Domain for ClientDomain {
             .downcast_ref::<ClientPane>()
             .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", pane_id))?;
 
-        let (command, command_dir, move_pane_id) = match source {
+        let (command, command_dir, move_pane) = match source {
             SplitSource::Spawn {
                 command,
                 command_dir,
             } => (command, command_dir, None),
-            SplitSource::MovePane(move_pane_id) => (None, None, Some(move_pane_id)),
+            SplitSource::MovePane(move_pane_id) => {
+                let move_pane = mux
+                    .get_pane(move_pane_id)
+                    .ok_or_else(|| anyhow!("pane_id {} is invalid", move_pane_id))?;
+                (None, None, Some(move_pane))
+            }
+        };
+        let move_pane_id = match &move_pane {
+            Some(move_pane) => Some(
+                move_pane
+                    .downcast_ref::<ClientPane>()
+                    .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", move_pane.pane_id()))?
+                    .remote_pane_id,
+            ),
+            None => None,
         };
 
         let result = inner
@@ -910,6 +1031,13 @@ impl Domain for ClientDomain {
             })
             .await?;
 
+        if let Some(move_pane) = move_pane {
+            // The pane already exists locally; let the resync
+            // reflect its new position in the layout
+            self.resync().await?;
+            return Ok(move_pane);
+        }
+
         let pane: Arc<dyn Pane> = Arc::new(ClientPane::new(
             &inner,
             result.tab_id,
@@ -935,6 +1063,59 @@ impl Domain for ClientDomain {
         Ok(pane)
     }
 
+    async fn spawn_floating_pane(
+        &self,
+        tab_id: TabId,
+        rect: FloatingPaneRect,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let mux = Mux::get();
+
+        let tab = mux
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} is invalid", tab_id))?;
+        // The remote end identifies the tab by one of its panes
+        let remote_pane_id = tab
+            .iter_panes_ignoring_zoom()
+            .iter()
+            .find_map(|p| {
+                p.pane
+                    .downcast_ref::<ClientPane>()
+                    .map(|pane| pane.remote_pane_id)
+            })
+            .ok_or_else(|| anyhow!("tab_id {} has no remote panes", tab_id))?;
+
+        let result = inner
+            .client
+            .spawn_floating_pane(SpawnFloatingPane {
+                domain: SpawnTabDomain::CurrentPaneDomain,
+                pane_id: remote_pane_id,
+                rect: Some(rect),
+                command,
+                command_dir,
+            })
+            .await?;
+
+        let pane: Arc<dyn Pane> = Arc::new(ClientPane::new(
+            &inner,
+            result.tab_id,
+            result.pane_id,
+            result.size,
+            "wezterm",
+        ));
+
+        tab.insert_floating_pane(Arc::clone(&pane), rect);
+
+        mux.add_pane(&pane)?;
+
+        Ok(pane)
+    }
+
     async fn attach(&self, window_id: Option<WindowId>) -> anyhow::Result<()> {
         if self.state() == DomainState::Attached {
             // Already attached
@@ -12,7 +12,7 @@ use mux::pane::{
     SearchResult, WithPaneLines,
 };
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::TabId;
+use mux::tab::{FloatingPaneRect, TabId};
 use mux::{Mux, MuxNotification};
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 use rangeset::RangeSet;
@@ -46,6 +46,7 @@ pub struct ClientPane {
     mouse_grabbed: Mutex<bool>,
     ignore_next_kill: Mutex<bool>,
     user_vars: Mutex<HashMap<String, String>>,
+    badge_format: Mutex<Option<String>>,
     config: Mutex<Option<Arc<dyn TerminalConfiguration>>>,
     unseen_output: Mutex<bool>,
 }
@@ -128,6 +129,7 @@ impl ClientPane {
             ignore_next_kill: Mutex::new(false),
             unseen_output: Mutex::new(false),
             user_vars: Mutex::new(HashMap::new()),
+            badge_format: Mutex::new(None),
             config: Mutex::new(None),
         }
     }
@@ -183,6 +185,9 @@ impl ClientPane {
                     Alert::SetUserVar { name, value } => {
                         self.user_vars.lock().insert(name.clone(), value.clone());
                     }
+                    Alert::BadgeFormatChanged(format) => {
+                        *self.badge_format.lock() = format.clone();
+                    }
                     Alert::OutputSinceFocusLost => {
                         *self.unseen_output.lock() = true;
                         mux.notify(MuxNotification::Alert {
@@ -222,6 +227,12 @@ impl ClientPane {
                     log::error!("Error reconciling remote PaneFocused notification: {err:#}");
                 }
             }
+            Pdu::PanePipeStopped(PanePipeStopped { pane_id }) => {
+                log::trace!("pipe of remote pane {pane_id} has stopped");
+                mux::pipe::remote_stopped(self.local_pane_id);
+                let mux = Mux::get();
+                mux.notify(MuxNotification::PanePipeStopped(self.local_pane_id));
+            }
             _ => bail!("unhandled unilateral pdu: {:?}", pdu),
         };
         Ok(())
@@ -373,6 +384,24 @@ impl Pane for ClientPane {
         inner.update_last_send();
     }
 
+    fn set_floating_state(&self, rect: FloatingPaneRect, visible: bool) {
+        let client = Arc::clone(&self.client);
+        let remote_pane_id = self.remote_pane_id;
+        let remote_tab_id = self.remote_tab_id;
+        promise::spawn::spawn(async move {
+            client
+                .client
+                .set_floating_state(SetFloatingPaneState {
+                    containing_tab_id: remote_tab_id,
+                    pane_id: remote_pane_id,
+                    rect,
+                    visible,
+                })
+                .await
+        })
+        .detach();
+    }
+
     fn resize(&self, size: TerminalSize) -> anyhow::Result<()> {
         let render = self.renderable.lock();
         let mut inner = render.inner.borrow_mut();
@@ -434,6 +463,31 @@ impl Pane for ClientPane {
         }
     }
 
+    async fn fetch_lines(
+        &self,
+        lines: Range<StableRowIndex>,
+    ) -> anyhow::Result<(StableRowIndex, Vec<Line>)> {
+        let first_row = lines.start;
+        let response = self
+            .client
+            .client
+            .get_lines(GetLines {
+                pane_id: self.remote_pane_id,
+                lines: vec![lines],
+            })
+            .await?;
+        let lines = hydrate_lines(
+            Arc::clone(&self.client),
+            self.remote_pane_id,
+            response.lines,
+        )
+        .await;
+        // hydrate_lines doesn't preserve the order of the lines
+        let lines: BTreeMap<StableRowIndex, Line> = lines.into_iter().collect();
+        let first_row = lines.keys().next().copied().unwrap_or(first_row);
+        Ok((first_row, lines.into_values().collect()))
+    }
+
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         let input_serial;
         {
@@ -597,6 +651,14 @@ impl Pane for ClientPane {
         self.user_vars.lock().clone()
     }
 
+    fn get_badge(&self) -> Option<String> {
+        let format = self.badge_format.lock().clone()?;
+        Some(wezterm_term::evaluate_badge_format(
+            &format,
+            &self.user_vars.lock(),
+        ))
+    }
+
     fn set_config(&self, config: Arc<dyn TerminalConfiguration>) {
         let palette = config.color_palette();
         // If the application running in the pane hasn't changed the
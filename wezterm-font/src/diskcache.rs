@@ -0,0 +1,1113 @@
+//! A persistent cache of resolved font fallbacks and rasterized glyphs.
+//!
+//! Resolving fallback fonts via the system font locator and rasterizing
+//! glyphs is relatively expensive, and the results are the same from one
+//! wezterm process to the next, so when `enable_font_disk_cache` is set
+//! we keep them on disk in `CACHE_DIR`.
+//!
+//! Entries are keyed by a fingerprint of the font files involved (their
+//! path, size and modification time) so that they are invalidated when
+//! the fonts change, and each file carries a header with the format
+//! version and the wezterm version so that a new build of wezterm
+//! won't try to use data produced by an older one.  Keys are a SHA-256
+//! of an explicit encoding of their inputs, so they are stable from one
+//! build to the next.
+//!
+//! Cache files are append-only sequences of length-prefixed records,
+//! each with a CRC32 of its content.  A truncated trailing record (eg:
+//! from a crash) is ignored and records that fail their checksum are
+//! discarded.  Files are locked while they are read or written, so that
+//! concurrent wezterm processes don't interleave their writes.
+//!
+//! Only the location of each glyph is read when a shard is opened;
+//! glyphs are read from the file as they are needed.  New records are
+//! handed off to a background thread which appends them in batches.
+use crate::db::FontDatabase;
+use crate::locator::{FontDataHandle, FontDataSource, FontOrigin};
+use crate::parser::ParsedFont;
+use crate::rasterizer::RasterizedGlyph;
+use crate::units::PixelLength;
+use config::{
+    ConfigHandle, DisplayPixelGeometry, FontLocatorSelection, FontRasterizerSelection,
+    FreeTypeLoadFlags, FreeTypeLoadTarget,
+};
+use rangeset::RangeSet;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use wezterm_color_types::SrgbaTuple;
+
+const MAGIC: &[u8; 4] = b"WZFC";
+/// Bump this when changing the encoding of the records
+const FORMAT_VERSION: u32 = 3;
+/// When the cache grows beyond this size, the least recently
+/// modified files are removed
+const MAX_CACHE_SIZE: u64 = 64 * 1024 * 1024;
+/// Each record is preceded by its length and checksum
+const RECORD_HEADER_LEN: u64 = 8;
+/// How long the writer waits for the rest of a burst of
+/// records before appending them
+const WRITE_BATCH_DELAY: Duration = Duration::from_millis(500);
+
+lazy_static::lazy_static! {
+    static ref FALLBACK: Mutex<Option<FallbackCache>> = Mutex::new(None);
+    static ref WRITER: Mutex<Option<Sender<PendingWrite>>> = Mutex::new(None);
+    /// The content hashes of font files, keyed by their path, size
+    /// and modification time, so that each file is read only once
+    static ref CONTENT_HASHES: Mutex<HashMap<(PathBuf, u64, Duration), u64>> =
+        Mutex::new(HashMap::new());
+    static ref BUILTIN_HASHES: Mutex<HashMap<&'static str, u64>> = Mutex::new(HashMap::new());
+}
+
+fn cache_dir() -> PathBuf {
+    config::CACHE_DIR.join("fonts")
+}
+
+/// Returns a hash of `data` that doesn't vary between builds
+fn stable_hash(data: &[u8]) -> u64 {
+    let digest = Sha256::digest(data);
+    u64::from_le_bytes(digest[0..8].try_into().unwrap())
+}
+
+fn header() -> Vec<u8> {
+    let mut enc = Encoder::default();
+    enc.bytes_raw(MAGIC);
+    enc.u32(FORMAT_VERSION);
+    enc.u64(stable_hash(config::wezterm_version().as_bytes()));
+    enc.0
+}
+
+/// Computes a fingerprint for the font data by hashing its content.
+/// On-disk fonts are read the first time that they are seen by this
+/// process, and again only if their size or modification time changes.
+/// Returns None for in-memory fonts, which are not cacheable.
+fn source_fingerprint(source: &FontDataSource) -> Option<u64> {
+    match source {
+        FontDataSource::OnDisk(path) => {
+            let meta = std::fs::metadata(path).ok()?;
+            let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            let key = (path.clone(), meta.len(), modified);
+            if let Some(hash) = CONTENT_HASHES.lock().unwrap().get(&key) {
+                return Some(*hash);
+            }
+            let hash = stable_hash(&std::fs::read(path).ok()?);
+            CONTENT_HASHES.lock().unwrap().insert(key, hash);
+            Some(hash)
+        }
+        FontDataSource::BuiltIn { name, data } => Some(
+            *BUILTIN_HASHES
+                .lock()
+                .unwrap()
+                .entry(*name)
+                .or_insert_with(|| stable_hash(data)),
+        ),
+        FontDataSource::Memory { .. } => None,
+    }
+}
+
+// The settings below are encoded as explicit values, rather than
+// through their Debug representation, so that the cache keys don't
+// change unless the settings do.  The values must not be reused.
+
+fn load_target_to_u8(target: Option<FreeTypeLoadTarget>) -> u8 {
+    match target {
+        None => 0,
+        Some(FreeTypeLoadTarget::Normal) => 1,
+        Some(FreeTypeLoadTarget::Light) => 2,
+        Some(FreeTypeLoadTarget::Mono) => 3,
+        Some(FreeTypeLoadTarget::HorizontalLcd) => 4,
+        Some(FreeTypeLoadTarget::VerticalLcd) => 5,
+    }
+}
+
+fn rasterizer_to_u8(rasterizer: FontRasterizerSelection) -> u8 {
+    match rasterizer {
+        FontRasterizerSelection::FreeType => 0,
+        FontRasterizerSelection::Swash => 1,
+    }
+}
+
+fn pixel_geometry_to_u8(geometry: DisplayPixelGeometry) -> u8 {
+    match geometry {
+        DisplayPixelGeometry::RGB => 0,
+        DisplayPixelGeometry::BGR => 1,
+    }
+}
+
+fn locator_to_u8(locator: FontLocatorSelection) -> u8 {
+    match locator {
+        FontLocatorSelection::FontConfig => 0,
+        FontLocatorSelection::Gdi => 1,
+        FontLocatorSelection::CoreText => 2,
+        FontLocatorSelection::ConfigDirsOnly => 3,
+    }
+}
+
+fn encode_load_flags(enc: &mut Encoder, flags: Option<FreeTypeLoadFlags>) {
+    match flags {
+        None => enc.u8(0),
+        Some(flags) => {
+            enc.u8(1);
+            enc.u32(flags.bits());
+        }
+    }
+}
+
+/// Takes an advisory lock on the file, waiting until it is available.
+/// The lock is released by `unlock_file` or when the file is closed.
+#[cfg(unix)]
+fn lock_file(file: &File, exclusive: bool) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let op = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    if unsafe { libc::flock(file.as_raw_fd(), op) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unlock_file(file: &File) {
+    use std::os::unix::io::AsRawFd;
+    unsafe {
+        libc::flock(file.as_raw_fd(), libc::LOCK_UN);
+    }
+}
+
+#[cfg(windows)]
+fn lock_file(file: &File, exclusive: bool) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::LockFileEx;
+    use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, OVERLAPPED};
+    let flags = if exclusive {
+        LOCKFILE_EXCLUSIVE_LOCK
+    } else {
+        0
+    };
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    let res = unsafe { LockFileEx(file.as_raw_handle() as _, flags, 0, !0, !0, &mut overlapped) };
+    if res == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn unlock_file(file: &File) {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::UnlockFile;
+    unsafe {
+        UnlockFile(file.as_raw_handle() as _, 0, 0, !0, !0);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_file(_file: &File, _exclusive: bool) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_file(_file: &File) {}
+
+/// Returns true if `file` starts with the header for this version
+fn has_current_header<R: Read + Seek>(file: &mut R) -> std::io::Result<bool> {
+    let header = header();
+    let mut actual = vec![0u8; header.len()];
+    file.seek(SeekFrom::Start(0))?;
+    match file.read_exact(&mut actual) {
+        Ok(()) => Ok(actual == header),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the records from the cache file at `path`.
+/// Returns an empty body if the file doesn't exist, or was
+/// produced by a different version.
+fn read_cache_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    lock_file(&file, false)?;
+    let mut body = vec![];
+    if has_current_header(&mut file)? {
+        file.read_to_end(&mut body)?;
+    }
+    unlock_file(&file);
+    Ok(body)
+}
+
+/// Appends `payload` to `buf` as a record
+fn encode_record(buf: &mut Vec<u8>, payload: &[u8]) {
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+}
+
+/// Splits the body of a cache file into its records, discarding
+/// those that fail their checksum
+fn split_records(mut body: &[u8]) -> Vec<&[u8]> {
+    let header_len = RECORD_HEADER_LEN as usize;
+    let mut records = vec![];
+    while body.len() >= header_len {
+        let len = u32::from_le_bytes(body[0..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(body[4..8].try_into().unwrap());
+        let record = match body.get(header_len..header_len + len) {
+            Some(record) => record,
+            None => break,
+        };
+        if crc32fast::hash(record) == crc {
+            records.push(record);
+        } else {
+            log::debug!("discarding font cache record with a bad checksum");
+        }
+        body = &body[header_len + len..];
+    }
+    records
+}
+
+/// Appends `records` to the cache file at `path`, first resetting
+/// the file if it was produced by a different version
+fn append_records(path: &Path, records: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    lock_file(&file, true)?;
+    if !has_current_header(&mut file)? {
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header())?;
+    }
+    file.seek(SeekFrom::End(0))?;
+    let result = file.write_all(records);
+    unlock_file(&file);
+    Ok(result?)
+}
+
+struct PendingWrite {
+    path: PathBuf,
+    records: Vec<u8>,
+}
+
+/// Queues encoded records to be appended to the cache file at `path`
+/// by the writer thread, so that the caller doesn't block on I/O
+fn queue_write(path: &Path, records: Vec<u8>) {
+    let mut writer = WRITER.lock().unwrap();
+    if writer.is_none() {
+        let (tx, rx) = channel();
+        let spawned = std::thread::Builder::new()
+            .name("font-cache-writer".to_string())
+            .spawn(move || write_thread(rx));
+        if let Err(err) = spawned {
+            log::warn!("Unable to start font cache writer: {:#}", err);
+            return;
+        }
+        writer.replace(tx);
+    }
+    if let Some(tx) = writer.as_ref() {
+        tx.send(PendingWrite {
+            path: path.to_path_buf(),
+            records,
+        })
+        .ok();
+    }
+}
+
+fn write_thread(rx: Receiver<PendingWrite>) {
+    while let Ok(first) = rx.recv() {
+        // Glyphs tend to be rasterized in bursts; wait for the rest
+        // of the burst so that each file is locked and written once
+        std::thread::sleep(WRITE_BATCH_DELAY);
+
+        let mut batches: HashMap<PathBuf, Vec<u8>> = HashMap::new();
+        for pending in std::iter::once(first).chain(rx.try_iter()) {
+            batches
+                .entry(pending.path)
+                .or_default()
+                .extend_from_slice(&pending.records);
+        }
+        for (path, records) in batches {
+            if let Err(err) = append_records(&path, &records) {
+                log::warn!(
+                    "Unable to write to font cache {}: {:#}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Removes the least recently modified cache files until the
+/// total size is within MAX_CACHE_SIZE
+fn prune(dir: &Path) {
+    let mut files = vec![];
+    let mut total = 0;
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if let Ok(meta) = entry.metadata() {
+                if meta.is_file() {
+                    total += meta.len();
+                    files.push((meta.modified().ok(), meta.len(), entry.path()));
+                }
+            }
+        }
+    }
+    files.sort();
+    for (_, len, path) in files {
+        if total <= MAX_CACHE_SIZE {
+            break;
+        }
+        log::trace!("pruning font cache file {}", path.display());
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn f64(&mut self, v: f64) {
+        self.u64(v.to_bits());
+    }
+    fn bytes_raw(&mut self, v: &[u8]) {
+        self.0.extend_from_slice(v);
+    }
+    fn bytes(&mut self, v: &[u8]) {
+        self.u32(v.len() as u32);
+        self.bytes_raw(v);
+    }
+    fn str(&mut self, v: &str) {
+        self.bytes(v.as_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (result, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(result)
+    }
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_bits(self.u64()?))
+    }
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+fn encode_glyph(glyph_pos: u32, glyph: &RasterizedGlyph) -> Vec<u8> {
+    let mut enc = Encoder::default();
+    enc.u32(glyph_pos);
+    enc.u32(glyph.width as u32);
+    enc.u32(glyph.height as u32);
+    enc.f64(glyph.bearing_x.get());
+    enc.f64(glyph.bearing_y.get());
+    enc.u8(glyph.has_color as u8);
+    enc.bytes(&glyph.data);
+    enc.0
+}
+
+fn decode_glyph(record: &[u8]) -> Option<(u32, RasterizedGlyph)> {
+    let mut dec = Decoder { data: record };
+    let glyph_pos = dec.u32()?;
+    let width = dec.u32()? as usize;
+    let height = dec.u32()? as usize;
+    let bearing_x = PixelLength::new(dec.f64()?);
+    let bearing_y = PixelLength::new(dec.f64()?);
+    let has_color = dec.u8()? != 0;
+    let data = dec.bytes()?.to_vec();
+    if data.len() != width * height * 4 {
+        return None;
+    }
+    Some((
+        glyph_pos,
+        RasterizedGlyph {
+            data,
+            height,
+            width,
+            bearing_x,
+            bearing_y,
+            has_color,
+        },
+    ))
+}
+
+/// The configuration that influences how glyphs are rasterized.
+/// It is read once when a font is loaded, rather than for each glyph.
+#[derive(Debug, Clone)]
+pub struct GlyphCacheConfig {
+    font_rasterizer: FontRasterizerSelection,
+    freetype_load_flags: FreeTypeLoadFlags,
+    freetype_load_target: FreeTypeLoadTarget,
+    freetype_render_target: Option<FreeTypeLoadTarget>,
+    freetype_interpreter_version: Option<u32>,
+}
+
+impl GlyphCacheConfig {
+    /// Returns None if the disk cache is disabled
+    pub fn new(config: &ConfigHandle) -> Option<Self> {
+        if !config.enable_font_disk_cache {
+            return None;
+        }
+        Some(Self {
+            font_rasterizer: config.font_rasterizer,
+            freetype_load_flags: config.freetype_load_flags,
+            freetype_load_target: config.freetype_load_target,
+            freetype_render_target: config.freetype_render_target,
+            freetype_interpreter_version: config.freetype_interpreter_version,
+        })
+    }
+
+    fn encode(&self, enc: &mut Encoder) {
+        enc.u8(rasterizer_to_u8(self.font_rasterizer));
+        encode_load_flags(enc, Some(self.freetype_load_flags));
+        enc.u8(load_target_to_u8(Some(self.freetype_load_target)));
+        enc.u8(load_target_to_u8(self.freetype_render_target));
+        match self.freetype_interpreter_version {
+            None => enc.u8(0),
+            Some(version) => {
+                enc.u8(1);
+                enc.u32(version);
+            }
+        }
+    }
+}
+
+/// The location of a glyph record within a shard file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RecordLocation {
+    offset: u64,
+    len: u32,
+    crc: u32,
+}
+
+/// Scans the records of a glyph shard and returns the location of
+/// the record for each glyph.  Only the start of each record is read.
+fn index_glyph_records<R: Read + Seek>(
+    file: &mut R,
+) -> std::io::Result<HashMap<u32, RecordLocation>> {
+    let mut index = HashMap::new();
+    if !has_current_header(file)? {
+        return Ok(index);
+    }
+    let mut offset = file.stream_position()?;
+    let end = file.seek(SeekFrom::End(0))?;
+    // The length and checksum, followed by the glyph index
+    // which starts each glyph record
+    let mut start = [0u8; 12];
+    while offset + start.len() as u64 <= end {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut start)?;
+        let len = u32::from_le_bytes(start[0..4].try_into().unwrap());
+        let crc = u32::from_le_bytes(start[4..8].try_into().unwrap());
+        let glyph_pos = u32::from_le_bytes(start[8..12].try_into().unwrap());
+        let payload = offset + RECORD_HEADER_LEN;
+        if payload + len as u64 > end {
+            // Truncated
+            break;
+        }
+        index.insert(
+            glyph_pos,
+            RecordLocation {
+                offset: payload,
+                len,
+                crc,
+            },
+        );
+        offset = payload + len as u64;
+    }
+    Ok(index)
+}
+
+/// Reads the glyph at `location`, returning None if the record
+/// fails its checksum or is otherwise invalid
+fn read_glyph_record<R: Read + Seek>(
+    file: &mut R,
+    glyph_pos: u32,
+    location: RecordLocation,
+) -> Option<RasterizedGlyph> {
+    let mut record = vec![0u8; location.len as usize];
+    file.seek(SeekFrom::Start(location.offset)).ok()?;
+    file.read_exact(&mut record).ok()?;
+    if crc32fast::hash(&record) != location.crc {
+        return None;
+    }
+    match decode_glyph(&record)? {
+        (pos, glyph) if pos == glyph_pos => Some(glyph),
+        _ => None,
+    }
+}
+
+/// The rasterized glyphs for a particular font at a particular
+/// size and dpi, along with the rasterization options
+pub struct GlyphShard {
+    path: PathBuf,
+    file: Option<File>,
+    glyphs: HashMap<u32, RecordLocation>,
+}
+
+impl GlyphShard {
+    /// Open the shard for the specified font, size and dpi.
+    /// `foreground` is the text color for glyphs that are painted
+    /// using it, and None for all of the other glyphs.
+    /// Returns None if the font is not cacheable.
+    pub fn open(
+        parsed: &ParsedFont,
+        config: &GlyphCacheConfig,
+        pixel_geometry: DisplayPixelGeometry,
+        font_size: f64,
+        dpi: u32,
+        foreground: Option<SrgbaTuple>,
+    ) -> Option<Self> {
+        let mut enc = Encoder::default();
+        enc.u64(source_fingerprint(&parsed.handle.source)?);
+        enc.u32(parsed.handle.index);
+        enc.u32(parsed.handle.variation);
+        enc.u32(parsed.variations.len() as u32);
+        for variation in &parsed.variations {
+            enc.str(&variation.tag);
+            enc.f64(variation.value.into_inner());
+        }
+        enc.u8(parsed.synthesize_bold as u8);
+        enc.u8(parsed.synthesize_italic as u8);
+        enc.f64(parsed.scale.unwrap_or(1.));
+        encode_load_flags(&mut enc, parsed.freetype_load_flags);
+        enc.u8(load_target_to_u8(parsed.freetype_load_target));
+        enc.u8(load_target_to_u8(parsed.freetype_render_target));
+        config.encode(&mut enc);
+        enc.u8(pixel_geometry_to_u8(pixel_geometry));
+        enc.f64(font_size);
+        enc.u32(dpi);
+        match foreground {
+            None => enc.u8(0),
+            Some(SrgbaTuple(r, g, b, a)) => {
+                enc.u8(1);
+                for component in [r, g, b, a] {
+                    enc.u32(component.to_bits());
+                }
+            }
+        }
+
+        let path = cache_dir().join(format!("glyphs-{:016x}", stable_hash(&enc.0)));
+        let (file, glyphs) = match Self::load_index(&path) {
+            Ok((file, glyphs)) => (file, glyphs),
+            Err(err) => {
+                log::warn!("Unable to read font cache {}: {:#}", path.display(), err);
+                (None, HashMap::new())
+            }
+        };
+        log::trace!(
+            "found {} cached glyphs for {} in {}",
+            glyphs.len(),
+            parsed.handle.diagnostic_string(),
+            path.display()
+        );
+        Some(Self { path, file, glyphs })
+    }
+
+    fn load_index(path: &Path) -> anyhow::Result<(Option<File>, HashMap<u32, RecordLocation>)> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((None, HashMap::new()))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        lock_file(&file, false)?;
+        let glyphs = index_glyph_records(&mut file);
+        unlock_file(&file);
+        Ok((Some(file), glyphs?))
+    }
+
+    pub fn get(&mut self, glyph_pos: u32) -> Option<RasterizedGlyph> {
+        let location = *self.glyphs.get(&glyph_pos)?;
+        let file = self.file.as_mut()?;
+        let glyph = read_glyph_record(file, glyph_pos, location);
+        if glyph.is_none() {
+            log::debug!(
+                "discarding invalid record for glyph {} in font cache {}",
+                glyph_pos,
+                self.path.display()
+            );
+            self.glyphs.remove(&glyph_pos);
+        }
+        glyph
+    }
+
+    pub fn insert(&mut self, glyph_pos: u32, glyph: &RasterizedGlyph) {
+        let mut record = vec![];
+        encode_record(&mut record, &encode_glyph(glyph_pos, glyph));
+        queue_write(&self.path, record);
+    }
+}
+
+struct CachedFont {
+    path: Option<PathBuf>,
+    built_in_name: Option<String>,
+    index: u32,
+    variation: u32,
+    origin: u8,
+    fingerprint: u64,
+    coverage: Vec<(u32, u32)>,
+}
+
+fn origin_to_u8(origin: &FontOrigin) -> Option<u8> {
+    match origin {
+        FontOrigin::FontConfig => Some(0),
+        FontOrigin::CoreText => Some(1),
+        FontOrigin::DirectWrite => Some(2),
+        FontOrigin::Gdi => Some(3),
+        FontOrigin::FontDirs => Some(4),
+        FontOrigin::BuiltIn => Some(5),
+        FontOrigin::FontConfigMatch(_) => None,
+    }
+}
+
+fn origin_from_u8(origin: u8) -> Option<FontOrigin> {
+    match origin {
+        0 => Some(FontOrigin::FontConfig),
+        1 => Some(FontOrigin::CoreText),
+        2 => Some(FontOrigin::DirectWrite),
+        3 => Some(FontOrigin::Gdi),
+        4 => Some(FontOrigin::FontDirs),
+        5 => Some(FontOrigin::BuiltIn),
+        _ => None,
+    }
+}
+
+impl CachedFont {
+    fn from_parsed(parsed: &ParsedFont) -> Option<Self> {
+        let handle = &parsed.handle;
+        let (path, built_in_name) = match &handle.source {
+            FontDataSource::OnDisk(path) => (Some(path.clone()), None),
+            FontDataSource::BuiltIn { name, .. } => (None, Some(name.to_string())),
+            FontDataSource::Memory { .. } => return None,
+        };
+        Some(Self {
+            path,
+            built_in_name,
+            index: handle.index,
+            variation: handle.variation,
+            origin: origin_to_u8(&handle.origin)?,
+            fingerprint: source_fingerprint(&handle.source)?,
+            coverage: handle
+                .coverage
+                .as_ref()
+                .map(|cov| cov.iter().map(|r| (r.start, r.end)).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn encode(&self, enc: &mut Encoder) {
+        match (&self.path, &self.built_in_name) {
+            (Some(path), _) => {
+                enc.u8(0);
+                enc.bytes(path.to_string_lossy().as_bytes());
+            }
+            (None, Some(name)) => {
+                enc.u8(1);
+                enc.bytes(name.as_bytes());
+            }
+            (None, None) => unreachable!(),
+        }
+        enc.u32(self.index);
+        enc.u32(self.variation);
+        enc.u8(self.origin);
+        enc.u64(self.fingerprint);
+        enc.u32(self.coverage.len() as u32);
+        for &(start, end) in &self.coverage {
+            enc.u32(start);
+            enc.u32(end);
+        }
+    }
+
+    fn decode(dec: &mut Decoder) -> Option<Self> {
+        let kind = dec.u8()?;
+        let name = String::from_utf8(dec.bytes()?.to_vec()).ok()?;
+        let (path, built_in_name) = match kind {
+            0 => (Some(PathBuf::from(name)), None),
+            1 => (None, Some(name)),
+            _ => return None,
+        };
+        let index = dec.u32()?;
+        let variation = dec.u32()?;
+        let origin = dec.u8()?;
+        let fingerprint = dec.u64()?;
+        let num_ranges = dec.u32()?;
+        let mut coverage = vec![];
+        for _ in 0..num_ranges {
+            coverage.push((dec.u32()?, dec.u32()?));
+        }
+        Some(Self {
+            path,
+            built_in_name,
+            index,
+            variation,
+            origin,
+            fingerprint,
+            coverage,
+        })
+    }
+
+    /// Produce the ParsedFont for this entry, provided that
+    /// the underlying font hasn't changed
+    fn to_parsed(&self, built_in: &FontDatabase) -> Option<ParsedFont> {
+        if let Some(name) = &self.built_in_name {
+            return built_in.list_available().into_iter().find(|p| {
+                matches!(&p.handle.source, FontDataSource::BuiltIn { name: n, .. } if *n == name.as_str())
+                    && p.handle.index == self.index
+                    && source_fingerprint(&p.handle.source) == Some(self.fingerprint)
+            });
+        }
+
+        let source = FontDataSource::OnDisk(self.path.clone()?);
+        if source_fingerprint(&source) != Some(self.fingerprint) {
+            return None;
+        }
+        let coverage = if self.coverage.is_empty() {
+            None
+        } else {
+            let mut cov = RangeSet::new();
+            for &(start, end) in &self.coverage {
+                cov.add_range(start..end);
+            }
+            Some(cov)
+        };
+        let handle = FontDataHandle {
+            source,
+            index: self.index,
+            variation: self.variation,
+            origin: origin_from_u8(self.origin)?,
+            coverage,
+        };
+        ParsedFont::from_locator(&handle).ok()
+    }
+}
+
+struct FallbackCache {
+    entries: HashMap<u64, Vec<CachedFont>>,
+}
+
+fn fallback_path() -> PathBuf {
+    cache_dir().join("fallback")
+}
+
+impl FallbackCache {
+    fn load() -> anyhow::Result<Self> {
+        prune(&cache_dir());
+        let body = read_cache_file(&fallback_path())?;
+        let mut entries = HashMap::new();
+        for record in split_records(&body) {
+            let mut dec = Decoder { data: record };
+            if let Some((key, fonts)) = Self::decode(&mut dec) {
+                entries.insert(key, fonts);
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    fn decode(dec: &mut Decoder) -> Option<(u64, Vec<CachedFont>)> {
+        let key = dec.u64()?;
+        let num_fonts = dec.u32()?;
+        let mut fonts = vec![];
+        for _ in 0..num_fonts {
+            fonts.push(CachedFont::decode(dec)?);
+        }
+        Some((key, fonts))
+    }
+}
+
+/// Computes the key for a fallback resolution.  The key includes the
+/// configuration that influences the result, as well as the modification
+/// time of the font_dirs so that newly added fonts are picked up.
+fn fallback_key(codepoints: &[char], config: &ConfigHandle) -> u64 {
+    let mut codepoints = codepoints.to_vec();
+    codepoints.sort_unstable();
+    codepoints.dedup();
+
+    let mut enc = Encoder::default();
+    enc.u32(codepoints.len() as u32);
+    for c in codepoints {
+        enc.u32(c as u32);
+    }
+    enc.u8(locator_to_u8(config.font_locator));
+    enc.u8(config.search_font_dirs_for_fallback as u8);
+    enc.u8(config.sort_fallback_fonts_by_coverage as u8);
+    for dir in &config.font_dirs {
+        enc.str(&dir.to_string_lossy());
+        let modified = std::fs::metadata(dir)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        enc.u64(modified.as_secs());
+        enc.u32(modified.subsec_nanos());
+    }
+    stable_hash(&enc.0)
+}
+
+fn with_fallback_cache<R>(func: impl FnOnce(&mut FallbackCache) -> Option<R>) -> Option<R> {
+    let mut cache = FALLBACK.lock().unwrap();
+    if cache.is_none() {
+        match FallbackCache::load() {
+            Ok(loaded) => cache.replace(loaded),
+            Err(err) => {
+                log::warn!("Unable to load font fallback cache: {:#}", err);
+                return None;
+            }
+        };
+    }
+    func(cache.as_mut()?)
+}
+
+/// Returns the previously resolved fallback fonts for the set of
+/// codepoints, if any, and if none of those fonts have since changed.
+pub fn lookup_fallback(
+    codepoints: &[char],
+    config: &ConfigHandle,
+    built_in: &FontDatabase,
+) -> Option<Vec<ParsedFont>> {
+    let key = fallback_key(codepoints, config);
+    let cached = with_fallback_cache(|cache| {
+        let fonts = cache.entries.get(&key)?;
+        let mut parsed = vec![];
+        for font in fonts {
+            parsed.push(font.to_parsed(built_in)?);
+        }
+        Some(parsed)
+    });
+    if cached.is_some() {
+        log::trace!("using cached fallback for {:?}", codepoints);
+    }
+    cached
+}
+
+/// Record the fallback fonts that were resolved for the set of codepoints.
+/// This should only be called when the fonts cover all of the codepoints,
+/// so that a cache hit never suppresses the missing glyph warning.
+pub fn store_fallback(codepoints: &[char], config: &ConfigHandle, fonts: &[ParsedFont]) {
+    let cached: Option<Vec<CachedFont>> = fonts.iter().map(CachedFont::from_parsed).collect();
+    let cached = match cached {
+        Some(cached) if !cached.is_empty() => cached,
+        _ => return,
+    };
+    let key = fallback_key(codepoints, config);
+
+    let mut enc = Encoder::default();
+    enc.u64(key);
+    enc.u32(cached.len() as u32);
+    for font in &cached {
+        font.encode(&mut enc);
+    }
+
+    let mut record = vec![];
+    encode_record(&mut record, &enc.0);
+    queue_write(&fallback_path(), record);
+
+    with_fallback_cache(|cache| {
+        cache.entries.insert(key, cached);
+        Some(())
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glyph_round_trip() {
+        let glyph = RasterizedGlyph {
+            data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            height: 1,
+            width: 2,
+            bearing_x: PixelLength::new(-1.5),
+            bearing_y: PixelLength::new(10.),
+            has_color: true,
+        };
+        let (glyph_pos, decoded) = decode_glyph(&encode_glyph(42, &glyph)).unwrap();
+        assert_eq!(glyph_pos, 42);
+        assert_eq!(decoded.data, glyph.data);
+        assert_eq!(decoded.width, 2);
+        assert_eq!(decoded.height, 1);
+        assert_eq!(decoded.bearing_x.get(), -1.5);
+        assert_eq!(decoded.bearing_y.get(), 10.);
+        assert!(decoded.has_color);
+    }
+
+    fn test_glyph(value: u8) -> RasterizedGlyph {
+        RasterizedGlyph {
+            data: vec![value; 4],
+            height: 1,
+            width: 1,
+            bearing_x: PixelLength::new(0.),
+            bearing_y: PixelLength::new(0.),
+            has_color: false,
+        }
+    }
+
+    #[test]
+    fn config_encoding() {
+        let config = GlyphCacheConfig {
+            font_rasterizer: FontRasterizerSelection::FreeType,
+            freetype_load_flags: FreeTypeLoadFlags::NO_HINTING,
+            freetype_load_target: FreeTypeLoadTarget::Light,
+            freetype_render_target: None,
+            freetype_interpreter_version: Some(40),
+        };
+        let encode = |config: &GlyphCacheConfig| {
+            let mut enc = Encoder::default();
+            config.encode(&mut enc);
+            enc.0
+        };
+
+        // The encoding must not change between releases
+        assert_eq!(
+            encode(&config),
+            vec![0, 1, 2, 0, 0, 0, 2, 0, 1, 40, 0, 0, 0]
+        );
+
+        let mut render = config.clone();
+        render.freetype_render_target = Some(FreeTypeLoadTarget::Normal);
+        assert_ne!(encode(&render), encode(&config));
+
+        let mut swash = config.clone();
+        swash.font_rasterizer = FontRasterizerSelection::Swash;
+        assert_ne!(encode(&swash), encode(&config));
+    }
+
+    #[test]
+    fn truncated_records() {
+        let mut body = vec![];
+        for record in [&b"hello"[..], &b"world"[..]] {
+            encode_record(&mut body, record);
+        }
+        assert_eq!(split_records(&body), vec![&b"hello"[..], &b"world"[..]]);
+        body.truncate(body.len() - 1);
+        assert_eq!(split_records(&body), vec![&b"hello"[..]]);
+
+        let record = encode_glyph(1, &test_glyph(0));
+        assert!(decode_glyph(&record[..record.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn corrupt_records() {
+        let mut body = vec![];
+        for record in [&b"hello"[..], &b"world"[..]] {
+            encode_record(&mut body, record);
+        }
+        // Flip a bit in the content of the first record
+        body[RECORD_HEADER_LEN as usize] ^= 1;
+        assert_eq!(split_records(&body), vec![&b"world"[..]]);
+    }
+
+    #[test]
+    fn glyph_index() {
+        let mut file = header();
+        for (glyph_pos, value) in [(1, 10), (2, 20), (1, 30)] {
+            encode_record(&mut file, &encode_glyph(glyph_pos, &test_glyph(value)));
+        }
+        // Add a truncated record
+        let mut partial = vec![];
+        encode_record(&mut partial, &encode_glyph(3, &test_glyph(40)));
+        file.extend_from_slice(&partial[..partial.len() - 1]);
+        let mut file = std::io::Cursor::new(file);
+
+        let index = index_glyph_records(&mut file).unwrap();
+        assert_eq!(index.len(), 2);
+        // The most recent record for a glyph wins
+        let glyph = read_glyph_record(&mut file, 1, index[&1]).unwrap();
+        assert_eq!(glyph.data, vec![30; 4]);
+        let glyph = read_glyph_record(&mut file, 2, index[&2]).unwrap();
+        assert_eq!(glyph.data, vec![20; 4]);
+        // A mismatched glyph index is rejected
+        assert!(read_glyph_record(&mut file, 2, index[&1]).is_none());
+
+        // A corrupted record fails its checksum
+        let location = index[&2];
+        file.get_mut()[location.offset as usize + 20] ^= 1;
+        assert!(read_glyph_record(&mut file, 2, location).is_none());
+
+        // Data from another version is ignored
+        let mut other = vec![0u8; 64];
+        other[0..4].copy_from_slice(b"XXXX");
+        let index = index_glyph_records(&mut std::io::Cursor::new(other)).unwrap();
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn stable_keys() {
+        // These must not change from one build to the next
+        assert_eq!(stable_hash(b""), 0x141cfc9842c4b0e3);
+        let mut enc = Encoder::default();
+        enc.u32(1);
+        enc.str("a");
+        assert_eq!(enc.0, vec![1, 0, 0, 0, 1, 0, 0, 0, b'a']);
+    }
+
+    #[test]
+    fn cached_font_round_trip() {
+        let font = CachedFont {
+            path: Some(PathBuf::from("/usr/share/fonts/foo.ttf")),
+            built_in_name: None,
+            index: 1,
+            variation: 2,
+            origin: 0,
+            fingerprint: 0x1234,
+            coverage: vec![(0x20, 0x7f), (0x4e00, 0x9fff)],
+        };
+        let mut enc = Encoder::default();
+        font.encode(&mut enc);
+        let decoded = CachedFont::decode(&mut Decoder { data: &enc.0 }).unwrap();
+        assert_eq!(decoded.path, font.path);
+        assert_eq!(decoded.index, 1);
+        assert_eq!(decoded.variation, 2);
+        assert_eq!(decoded.fingerprint, 0x1234);
+        assert_eq!(decoded.coverage, font.coverage);
+    }
+}
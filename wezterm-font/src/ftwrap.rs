@@ -3,7 +3,7 @@
 use crate::locator::{FontDataHandle, FontDataSource};
 use crate::parser::ParsedFont;
 use anyhow::{anyhow, Context};
-use config::{configuration, FreeTypeLoadFlags, FreeTypeLoadTarget};
+use config::{configuration, FontVariation, FreeTypeLoadFlags, FreeTypeLoadTarget};
 pub use freetype::*;
 use memmap2::{Mmap, MmapOptions};
 use rangeset::RangeSet;
@@ -119,6 +119,34 @@ struct ComputedCellMetrics {
     pub height: f64,
 }
 
+/// Describes a design axis of a variable font
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariationAxis {
+    pub tag: String,
+    pub minimum: f64,
+    pub default: f64,
+    pub maximum: f64,
+}
+
+fn fixed_to_f64(value: FT_Fixed) -> f64 {
+    value as f64 / (1 << 16) as f64
+}
+
+fn f64_to_fixed(value: f64) -> FT_Fixed {
+    (value * (1 << 16) as f64).round() as FT_Fixed
+}
+
+/// Convert an OpenType tag into its textual form, eg: `wght`
+fn tag_to_string(tag: FT_ULong) -> String {
+    let bytes = [
+        (tag >> 24) as u8,
+        (tag >> 16) as u8,
+        (tag >> 8) as u8,
+        tag as u8,
+    ];
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
+
 #[derive(Debug)]
 pub struct SelectedFontSize {
     pub width: f64,
@@ -192,6 +220,103 @@ impl Face {
         }
     }
 
+    /// Returns the design axes of a variable font, or an empty
+    /// vec for a font that has no variations
+    pub fn variation_axes(&self) -> Vec<VariationAxis> {
+        unsafe {
+            if ((*self.face).face_flags as u32 & FT_FACE_FLAG_MULTIPLE_MASTERS) == 0 {
+                return vec![];
+            }
+
+            let mut mm = std::ptr::null_mut();
+            if !succeeded(FT_Get_MM_Var(self.face, &mut mm)) {
+                return vec![];
+            }
+
+            let axes = std::slice::from_raw_parts((*mm).axis, (*mm).num_axis as usize)
+                .iter()
+                .map(|axis| VariationAxis {
+                    tag: tag_to_string(axis.tag),
+                    minimum: fixed_to_f64(axis.minimum),
+                    default: fixed_to_f64(axis.def),
+                    maximum: fixed_to_f64(axis.maximum),
+                })
+                .collect();
+
+            FT_Done_MM_Var(self.lib, mm);
+            axes
+        }
+    }
+
+    /// Apply explicit design coordinates to the axes of a variable font.
+    /// Axes that are not mentioned keep their current values, which are
+    /// those of the named instance that was loaded, if any.
+    /// Values are clamped to the range supported by the font.
+    pub fn set_variation_coordinates(
+        &mut self,
+        variations: &[FontVariation],
+    ) -> anyhow::Result<()> {
+        if variations.is_empty() {
+            return Ok(());
+        }
+
+        let axes = self.variation_axes();
+        if axes.is_empty() {
+            log::warn!(
+                "Ignoring variations {:?} for {} because it is not a variable font",
+                variations,
+                self.family_name()
+            );
+            return Ok(());
+        }
+
+        let mut coords: Vec<FT_Fixed> = vec![0; axes.len()];
+        unsafe {
+            ft_result(
+                FT_Get_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")?;
+        }
+
+        for variation in variations {
+            match axes.iter().position(|axis| axis.tag == variation.tag) {
+                Some(idx) => {
+                    let axis = &axes[idx];
+                    let value = variation
+                        .value
+                        .into_inner()
+                        .clamp(axis.minimum, axis.maximum);
+                    coords[idx] = f64_to_fixed(value);
+                }
+                None => log::warn!(
+                    "{} has no `{}` variation axis; available axes are {:?}",
+                    self.family_name(),
+                    variation.tag,
+                    axes.iter()
+                        .map(|axis| axis.tag.as_str())
+                        .collect::<Vec<_>>()
+                ),
+            }
+        }
+
+        unsafe {
+            ft_result(
+                FT_Set_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Set_Var_Design_Coordinates")
+        }
+    }
+
     pub fn get_glyph_name(&self, glyph_index: u32) -> Option<String> {
         let mut buf = [0u8; 128];
         let res = unsafe {
@@ -302,6 +427,69 @@ impl Face {
         }
     }
 
+    /// Returns a copy of the raw data for the sfnt table with the
+    /// specified tag, eg: `b"COLR"`, or None if the font doesn't have it.
+    pub fn load_sfnt_table(&self, tag: &[u8; 4]) -> Option<Vec<u8>> {
+        let tag = u32::from_be_bytes(*tag) as FT_ULong;
+        unsafe {
+            let mut len: FT_ULong = 0;
+            if !succeeded(FT_Load_Sfnt_Table(
+                self.face,
+                tag,
+                0,
+                ptr::null_mut(),
+                &mut len,
+            )) || len == 0
+            {
+                return None;
+            }
+            let mut data = vec![0u8; len as usize];
+            if !succeeded(FT_Load_Sfnt_Table(
+                self.face,
+                tag,
+                0,
+                data.as_mut_ptr(),
+                &mut len,
+            )) {
+                return None;
+            }
+            Some(data)
+        }
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        unsafe { (*self.face).units_per_EM }
+    }
+
+    /// Returns the number of pixels per font unit for the current size
+    pub fn pixels_per_font_unit(&self) -> Option<f64> {
+        unsafe {
+            let size = (*self.face).size;
+            if size.is_null() {
+                return None;
+            }
+            // x_scale is 16.16 and converts font units to 26.6 pixels
+            Some(fixed_to_f64((*size).metrics.x_scale) / 64.)
+        }
+    }
+
+    /// Load the outline of the specified glyph in font units,
+    /// without hinting or applying the transform.
+    /// Returns None for glyphs that have no outline.
+    pub fn load_unscaled_outline(&mut self, glyph_index: FT_UInt) -> Option<&FT_Outline> {
+        unsafe {
+            let flags = FT_LOAD_NO_SCALE | FT_LOAD_NO_HINTING | FT_LOAD_NO_BITMAP;
+            if !succeeded(FT_Load_Glyph(self.face, glyph_index, flags as FT_Int32)) {
+                return None;
+            }
+            let slot = &*(*self.face).glyph;
+            if slot.format != FT_Glyph_Format::FT_GLYPH_FORMAT_OUTLINE {
+                return None;
+            }
+            Some(&slot.outline)
+        }
+    }
+
     /// Returns the cap_height/units_per_EM ratio if known
     pub fn cap_height(&self) -> Option<f64> {
         unsafe {
@@ -19,9 +19,11 @@ use std::time::{Duration, Instant};
 use termwiz::cell::Presentation;
 use thiserror::Error;
 use wezterm_bidi::Direction;
+use wezterm_color_types::SrgbaTuple;
 use wezterm_term::{CellAttributes, Intensity};
 use wezterm_toast_notification::ToastNotification;
 
+mod diskcache;
 mod hbwrap;
 
 pub mod db;
@@ -54,10 +56,17 @@ lazy_static::lazy_static! {
 
 pub struct LoadedFont {
     rasterizers: RefCell<HashMap<FallbackIdx, Box<dyn FontRasterizer>>>,
+    /// Previously rasterized glyphs loaded from the disk cache.
+    /// Glyphs that are painted using the foreground color are
+    /// kept apart from the others, in a shard for each color.
+    disk_glyphs: RefCell<HashMap<(FallbackIdx, Option<SrgbaTuple>), Option<diskcache::GlyphShard>>>,
     handles: RefCell<Vec<ParsedFont>>,
     shaper: RefCell<Box<dyn FontShaper>>,
     metrics: FontMetrics,
     pixel_geometry: DisplayPixelGeometry,
+    font_rasterizer: FontRasterizerSelection,
+    /// None if the disk cache is disabled
+    disk_cache: Option<diskcache::GlyphCacheConfig>,
     font_size: f64,
     dpi: u32,
     font_config: Weak<FontConfigInner>,
@@ -262,30 +271,75 @@ impl LoadedFont {
         }
     }
 
-    pub fn rasterize_glyph(
+    /// Calls func with the rasterizer for the specified fallback font,
+    /// creating it if needed
+    fn with_rasterizer<R>(
         &self,
-        glyph_pos: u32,
         fallback: FallbackIdx,
-    ) -> anyhow::Result<RasterizedGlyph> {
+        func: impl FnOnce(&dyn FontRasterizer) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
         let mut rasterizers = self.rasterizers.borrow_mut();
-        if let Some(raster) = rasterizers.get(&fallback) {
-            raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi)
-        } else {
-            let raster_selection = self
-                .font_config
-                .upgrade()
-                .map_or(FontRasterizerSelection::default(), |c| {
-                    c.config.borrow().font_rasterizer
-                });
+        if !rasterizers.contains_key(&fallback) {
             let raster = new_rasterizer(
-                raster_selection,
+                self.font_rasterizer,
                 &(self.handles.borrow())[fallback],
                 self.pixel_geometry,
             )?;
-            let result = raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi);
             rasterizers.insert(fallback, raster);
-            result
         }
+        func(&**rasterizers.get(&fallback).expect("just inserted"))
+    }
+
+    /// Returns true if the rendering of the glyph may depend upon
+    /// the foreground color passed to `rasterize_glyph`; this is the
+    /// case for COLRv1 and OpenType-SVG glyphs that use the text color
+    pub fn uses_foreground(&self, glyph_pos: u32, fallback: FallbackIdx) -> anyhow::Result<bool> {
+        self.with_rasterizer(fallback, |raster| Ok(raster.uses_foreground(glyph_pos)))
+    }
+
+    /// `foreground` is the resolved text color of the cell in which
+    /// the glyph is displayed
+    pub fn rasterize_glyph(
+        &self,
+        glyph_pos: u32,
+        fallback: FallbackIdx,
+        foreground: SrgbaTuple,
+    ) -> anyhow::Result<RasterizedGlyph> {
+        let shard_foreground = if self.uses_foreground(glyph_pos, fallback)? {
+            Some(foreground)
+        } else {
+            None
+        };
+
+        let mut disk_glyphs = self.disk_glyphs.borrow_mut();
+        let mut shard = match &self.disk_cache {
+            Some(disk_cache) => disk_glyphs
+                .entry((fallback, shard_foreground))
+                .or_insert_with(|| {
+                    diskcache::GlyphShard::open(
+                        &(self.handles.borrow())[fallback],
+                        disk_cache,
+                        self.pixel_geometry,
+                        self.font_size,
+                        self.dpi,
+                        shard_foreground,
+                    )
+                })
+                .as_mut(),
+            None => None,
+        };
+        if let Some(glyph) = shard.as_mut().and_then(|shard| shard.get(glyph_pos)) {
+            return Ok(glyph);
+        }
+
+        let glyph = self.with_rasterizer(fallback, |raster| {
+            raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi, foreground)
+        })?;
+
+        if let Some(shard) = shard {
+            shard.insert(glyph_pos, &glyph);
+        }
+        Ok(glyph)
     }
 
     pub fn clone_handles(&self) -> Vec<ParsedFont> {
@@ -313,6 +367,17 @@ impl FallbackResolveInfo {
             fallback_str.escape_unicode()
         );
 
+        if self.config.enable_font_disk_cache {
+            if let Some(mut handles) =
+                diskcache::lookup_fallback(&self.no_glyphs, &self.config, &self.built_in)
+            {
+                let mut pending = self.pending.lock().unwrap();
+                pending.append(&mut handles);
+                (self.completion)();
+                return;
+            }
+        }
+
         match self.locator.locate_fallback_for_codepoints(&self.no_glyphs) {
             Ok(ref mut handles) => extra_handles.append(handles),
             Err(err) => log::error!(
@@ -349,7 +414,7 @@ impl FallbackResolveInfo {
         }
 
         let mut wanted = RangeSet::new();
-        for c in self.no_glyphs {
+        for &c in &self.no_glyphs {
             wanted.add(c as u32);
         }
         log::trace!(
@@ -386,6 +451,10 @@ impl FallbackResolveInfo {
             Err(_) => false,
         });
 
+        if wanted.is_empty() && self.config.enable_font_disk_cache {
+            diskcache::store_fallback(&self.no_glyphs, &self.config, &extra_handles);
+        }
+
         if !extra_handles.is_empty() {
             let mut pending = self.pending.lock().unwrap();
             pending.append(&mut extra_handles);
@@ -440,6 +509,7 @@ impl FallbackResolveInfo {
                     ),
                     url: Some(url.to_string()),
                     timeout: Some(Duration::from_secs(15)),
+                    on_click: None,
                 }
                 .show();
             } else {
@@ -463,6 +533,7 @@ struct FontConfigInner {
     built_in: RefCell<Arc<FontDatabase>>,
     title_font: RefCell<Option<Rc<LoadedFont>>>,
     pane_select_font: RefCell<Option<Rc<LoadedFont>>>,
+    badge_font: RefCell<Option<Rc<LoadedFont>>>,
     char_select_font: RefCell<Option<Rc<LoadedFont>>>,
     command_palette_font: RefCell<Option<Rc<LoadedFont>>>,
     fallback_channel: RefCell<Option<Sender<FallbackResolveInfo>>>,
@@ -484,6 +555,7 @@ impl FontConfigInner {
             metrics: RefCell::new(None),
             title_font: RefCell::new(None),
             pane_select_font: RefCell::new(None),
+            badge_font: RefCell::new(None),
             char_select_font: RefCell::new(None),
             command_palette_font: RefCell::new(None),
             font_scale: RefCell::new(1.0),
@@ -502,6 +574,7 @@ impl FontConfigInner {
         fonts.clear();
         self.title_font.borrow_mut().take();
         self.pane_select_font.borrow_mut().take();
+        self.badge_font.borrow_mut().take();
         self.char_select_font.borrow_mut().take();
         self.command_palette_font.borrow_mut().take();
         self.metrics.borrow_mut().take();
@@ -614,6 +687,7 @@ impl FontConfigInner {
 
         let loaded = Rc::new(LoadedFont {
             rasterizers: RefCell::new(HashMap::new()),
+            disk_glyphs: RefCell::new(HashMap::new()),
             handles: RefCell::new(handles),
             shaper: RefCell::new(shaper),
             metrics,
@@ -625,6 +699,8 @@ impl FontConfigInner {
             id: alloc_font_id(),
             tried_glyphs: RefCell::new(HashSet::new()),
             pixel_geometry: config.display_pixel_geometry,
+            font_rasterizer: config.font_rasterizer,
+            disk_cache: diskcache::GlyphCacheConfig::new(&config),
         });
 
         Ok(loaded)
@@ -695,6 +771,22 @@ impl FontConfigInner {
         Ok(loaded)
     }
 
+    fn badge_font(&self, myself: &Rc<Self>) -> anyhow::Result<Rc<LoadedFont>> {
+        let config = self.config.borrow();
+
+        let mut badge_font = self.badge_font.borrow_mut();
+
+        if let Some(entry) = badge_font.as_ref() {
+            return Ok(Rc::clone(entry));
+        }
+
+        let loaded = self.make_title_font_impl(myself, Some(config.badge_font_size), true)?;
+
+        badge_font.replace(Rc::clone(&loaded));
+
+        Ok(loaded)
+    }
+
     fn resolve_font_helper_impl(
         &self,
         attributes: &[FontAttributes],
@@ -914,6 +1006,7 @@ impl FontConfigInner {
 
         let loaded = Rc::new(LoadedFont {
             rasterizers: RefCell::new(HashMap::new()),
+            disk_glyphs: RefCell::new(HashMap::new()),
             handles: RefCell::new(handles),
             shaper: RefCell::new(shaper),
             metrics,
@@ -925,6 +1018,8 @@ impl FontConfigInner {
             id: alloc_font_id(),
             tried_glyphs: RefCell::new(HashSet::new()),
             pixel_geometry: config.display_pixel_geometry,
+            font_rasterizer: config.font_rasterizer,
+            disk_cache: diskcache::GlyphCacheConfig::new(&config),
         });
 
         fonts.insert(style.clone(), Rc::clone(&loaded));
@@ -1061,6 +1156,10 @@ impl FontConfiguration {
         self.inner.pane_select_font(&self.inner)
     }
 
+    pub fn badge_font(&self) -> anyhow::Result<Rc<LoadedFont>> {
+        self.inner.badge_font(&self.inner)
+    }
+
     pub fn char_select_font(&self) -> anyhow::Result<Rc<LoadedFont>> {
         self.inner.char_select_font(&self.inner)
     }
@@ -1,6 +1,7 @@
+use crate::ftwrap::VariationAxis;
 use crate::locator::{FontDataHandle, FontDataSource, FontOrigin};
 use crate::shaper::GlyphInfo;
-use config::{FontAttributes, FontStyle, FreeTypeLoadFlags, FreeTypeLoadTarget};
+use config::{FontAttributes, FontStyle, FontVariation, FreeTypeLoadFlags, FreeTypeLoadTarget};
 pub use config::{FontStretch, FontWeight};
 use rangeset::RangeSet;
 use std::cmp::Ordering;
@@ -33,6 +34,10 @@ pub struct ParsedFont {
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
     pub scale: Option<f64>,
+    /// The design axes, if this is a variable font
+    pub variation_axes: Vec<VariationAxis>,
+    /// Design coordinates to apply when loading this font
+    pub variations: Vec<FontVariation>,
 }
 
 impl std::fmt::Debug for ParsedFont {
@@ -54,6 +59,8 @@ impl std::fmt::Debug for ParsedFont {
             .field("freetype_render_target", &self.freetype_render_target)
             .field("freetype_load_flags", &self.freetype_load_flags)
             .field("scale", &self.scale)
+            .field("variation_axes", &self.variation_axes)
+            .field("variations", &self.variations)
             .finish()
     }
 }
@@ -79,6 +86,8 @@ impl Clone for ParsedFont {
             freetype_load_flags: self.freetype_load_flags,
             is_built_in_fallback: self.is_built_in_fallback,
             scale: self.scale,
+            variation_axes: self.variation_axes.clone(),
+            variations: self.variations.clone(),
         }
     }
 }
@@ -301,6 +310,13 @@ impl ParsedFont {
             for aka in &p.names.aliases {
                 code.push_str(&format!("  -- AKA: \"{}\"\n", aka));
             }
+            if !p.variation_axes.is_empty() {
+                code.push_str("  -- Variation axes:");
+                for axis in &p.variation_axes {
+                    code.push_str(&format!(" {}={}..{}", axis.tag, axis.minimum, axis.maximum));
+                }
+                code.push_str("\n");
+            }
 
             if p.weight == FontWeight::REGULAR
                 && p.stretch == FontStretch::Normal
@@ -310,6 +326,7 @@ impl ParsedFont {
                 && p.freetype_load_flags.is_none()
                 && p.harfbuzz_features.is_none()
                 && p.scale.is_none()
+                && p.variations.is_empty()
             {
                 code.push_str(&format!("  \"{}\",\n", p.names.family));
             } else {
@@ -347,6 +364,16 @@ impl ParsedFont {
                     }
                     code.push('}');
                 }
+                if !p.variations.is_empty() {
+                    code.push_str(", variations={");
+                    for (idx, v) in p.variations.iter().enumerate() {
+                        if idx > 0 {
+                            code.push_str(", ");
+                        }
+                        code.push_str(&format!("\"{}\"", v));
+                    }
+                    code.push('}');
+                }
                 code.push_str("},\n")
             }
             code.push_str("\n");
@@ -366,6 +393,7 @@ impl ParsedFont {
         let stretch = FontStretch::from_opentype_stretch(width);
         let cap_height = face.cap_height();
         let pixel_sizes = face.pixel_sizes();
+        let variation_axes = face.variation_axes();
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (crate::ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
@@ -416,6 +444,8 @@ impl ParsedFont {
             freetype_load_target: None,
             freetype_load_flags: None,
             scale: None,
+            variation_axes,
+            variations: vec![],
         })
     }
 
@@ -665,6 +695,22 @@ impl ParsedFont {
             && attr.weight < self.weight
             && self.weight >= FontWeight::REGULAR;
 
+        self.variations = attr.variations.clone().unwrap_or_default();
+        // A variable font can render the requested weight by interpolating
+        // along its weight axis, which looks better than synthesizing it
+        if attr.weight != self.weight && !self.variations.iter().any(|v| v.tag == "wght") {
+            if let Some(axis) = self.variation_axes.iter().find(|axis| axis.tag == "wght") {
+                let wanted = attr.weight.to_opentype_weight() as f64;
+                if wanted >= axis.minimum && wanted <= axis.maximum {
+                    if let Some(variation) = FontVariation::new("wght", wanted) {
+                        self.variations.push(variation);
+                        self.synthesize_bold = false;
+                        self.synthesize_dim = false;
+                    }
+                }
+            }
+        }
+
         match attr.assume_emoji_presentation {
             Some(assume) => {
                 self.assume_emoji_presentation = assume;
@@ -0,0 +1,815 @@
+//! A small software compositor used to paint vector color glyphs
+//! (COLRv1 and OpenType-SVG) into a `RasterizedGlyph`.
+//!
+//! Path coverage is computed by tiny-skia, but shading and compositing
+//! are performed here so that we have full control over the gradient
+//! geometry (two-point conical and sweep gradients) and the complete
+//! set of blend modes required by COLRv1.
+use tiny_skia::{FillRule, Paint, Path, Pixmap, Transform};
+
+/// Premultiplied RGBA color with components in the range 0.0-1.0
+pub type Rgba = [f32; 4];
+
+/// A 2x3 affine transformation matrix.
+/// Maps `(x, y)` to `(xx * x + xy * y + dx, yx * x + yy * y + dy)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub xx: f32,
+    pub yx: f32,
+    pub xy: f32,
+    pub yy: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Affine {
+    pub fn identity() -> Self {
+        Self::new(1., 0., 0., 1., 0., 0.)
+    }
+
+    pub fn new(xx: f32, yx: f32, xy: f32, yy: f32, dx: f32, dy: f32) -> Self {
+        Self {
+            xx,
+            yx,
+            xy,
+            yy,
+            dx,
+            dy,
+        }
+    }
+
+    pub fn translate(dx: f32, dy: f32) -> Self {
+        Self::new(1., 0., 0., 1., dx, dy)
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self::new(sx, 0., 0., sy, 0., 0.)
+    }
+
+    /// Counter-clockwise rotation (in a y-up coordinate system)
+    pub fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::new(cos, sin, -sin, cos, 0., 0.)
+    }
+
+    pub fn skew(x_degrees: f32, y_degrees: f32) -> Self {
+        Self::new(
+            1.,
+            y_degrees.to_radians().tan(),
+            x_degrees.to_radians().tan(),
+            1.,
+            0.,
+            0.,
+        )
+    }
+
+    /// Returns the transform that applies `other` first and then `self`
+    pub fn then(&self, other: &Affine) -> Affine {
+        Affine {
+            xx: self.xx * other.xx + self.xy * other.yx,
+            yx: self.yx * other.xx + self.yy * other.yx,
+            xy: self.xx * other.xy + self.xy * other.yy,
+            yy: self.yx * other.xy + self.yy * other.yy,
+            dx: self.xx * other.dx + self.xy * other.dy + self.dx,
+            dy: self.yx * other.dx + self.yy * other.dy + self.dy,
+        }
+    }
+
+    /// Wraps `self` around the point `(cx, cy)`
+    pub fn around(&self, cx: f32, cy: f32) -> Affine {
+        Affine::translate(cx, cy)
+            .then(self)
+            .then(&Affine::translate(-cx, -cy))
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.xx * x + self.xy * y + self.dx,
+            self.yx * x + self.yy * y + self.dy,
+        )
+    }
+
+    pub fn invert(&self) -> Option<Affine> {
+        let det = self.xx * self.yy - self.xy * self.yx;
+        if det.abs() <= f32::EPSILON * 1e-3 || !det.is_finite() {
+            return None;
+        }
+        let xx = self.yy / det;
+        let yx = -self.yx / det;
+        let xy = -self.xy / det;
+        let yy = self.xx / det;
+        Some(Affine {
+            xx,
+            yx,
+            xy,
+            yy,
+            dx: -(xx * self.dx + xy * self.dy),
+            dy: -(yx * self.dx + yy * self.dy),
+        })
+    }
+
+    /// Returns the bounding box of `rect` after transformation
+    pub fn map_rect(&self, rect: &BBox) -> BBox {
+        let mut result = BBox::empty();
+        for (x, y) in [
+            (rect.min_x, rect.min_y),
+            (rect.max_x, rect.min_y),
+            (rect.min_x, rect.max_y),
+            (rect.max_x, rect.max_y),
+        ] {
+            let (x, y) = self.apply(x, y);
+            result.add_point(x, y);
+        }
+        result
+    }
+
+    pub fn to_skia(&self) -> Transform {
+        Transform::from_row(self.xx, self.yx, self.xy, self.yy, self.dx, self.dy)
+    }
+}
+
+/// An axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl BBox {
+    pub fn empty() -> Self {
+        Self {
+            min_x: f32::INFINITY,
+            min_y: f32::INFINITY,
+            max_x: f32::NEG_INFINITY,
+            max_y: f32::NEG_INFINITY,
+        }
+    }
+
+    pub fn new(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Self {
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    pub fn of_path(path: &Path) -> Self {
+        let rect = path.bounds();
+        Self::new(rect.left(), rect.top(), rect.right(), rect.bottom())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.min_x < self.max_x && self.min_y < self.max_y)
+    }
+
+    pub fn add_point(&mut self, x: f32, y: f32) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    pub fn union(&mut self, other: &BBox) {
+        if !other.is_empty() {
+            self.add_point(other.min_x, other.min_y);
+            self.add_point(other.max_x, other.max_y);
+        }
+    }
+
+    pub fn inflate(&self, amount: f32) -> BBox {
+        BBox::new(
+            self.min_x - amount,
+            self.min_y - amount,
+            self.max_x + amount,
+            self.max_y + amount,
+        )
+    }
+}
+
+/// Controls how a gradient behaves outside of its 0.0-1.0 range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extend {
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+impl Extend {
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Extend::Pad => t.clamp(0., 1.),
+            Extend::Repeat => t - t.floor(),
+            Extend::Reflect => {
+                let t = (t * 0.5 - (t * 0.5).floor()) * 2.;
+                if t > 1. {
+                    2. - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: Rgba,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientGeometry {
+    /// Linear gradient from p0 (t=0) to p1 (t=1)
+    Linear { p0: (f32, f32), p1: (f32, f32) },
+    /// Two point conical gradient between two circles
+    Radial {
+        c0: (f32, f32),
+        r0: f32,
+        c1: (f32, f32),
+        r1: f32,
+    },
+    /// Sweep gradient around `center` between two angles
+    /// expressed in counter-clockwise degrees
+    Sweep {
+        center: (f32, f32),
+        start: f32,
+        end: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub geometry: GradientGeometry,
+    /// Sorted by offset
+    pub stops: Vec<ColorStop>,
+    pub extend: Extend,
+    /// Maps canvas pixel coordinates into gradient space
+    pub inverse: Affine,
+}
+
+impl Gradient {
+    /// Construct a gradient whose geometry is expressed in a space that
+    /// `transform` maps onto the canvas.
+    /// Returns None if the transform is degenerate or if there are no stops.
+    pub fn new(
+        geometry: GradientGeometry,
+        mut stops: Vec<ColorStop>,
+        extend: Extend,
+        transform: &Affine,
+    ) -> Option<Self> {
+        if stops.is_empty() {
+            return None;
+        }
+        stops.sort_by(|a, b| {
+            a.offset
+                .partial_cmp(&b.offset)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Some(Self {
+            geometry,
+            stops,
+            extend,
+            inverse: transform.invert()?,
+        })
+    }
+
+    fn parameter(&self, x: f32, y: f32) -> Option<f32> {
+        match &self.geometry {
+            GradientGeometry::Linear { p0, p1 } => {
+                let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+                let len = dx * dx + dy * dy;
+                if len == 0. {
+                    return None;
+                }
+                Some(((x - p0.0) * dx + (y - p0.1) * dy) / len)
+            }
+            GradientGeometry::Radial { c0, r0, c1, r1 } => {
+                two_point_conical(x, y, *c0, *r0, *c1, *r1)
+            }
+            GradientGeometry::Sweep { center, start, end } => {
+                if start == end {
+                    return None;
+                }
+                let mut angle = (y - center.1).atan2(x - center.0).to_degrees();
+                if angle < 0. {
+                    angle += 360.;
+                }
+                Some((angle - start) / (end - start))
+            }
+        }
+    }
+
+    /// Evaluate the color at the center of the pixel at `(x, y)`
+    pub fn color_at(&self, x: f32, y: f32) -> Rgba {
+        let (gx, gy) = self.inverse.apply(x + 0.5, y + 0.5);
+        let t = match self.parameter(gx, gy) {
+            Some(t) if t.is_finite() => self.extend.apply(t),
+            _ => return [0.; 4],
+        };
+        let first = &self.stops[0];
+        if t <= first.offset {
+            return first.color;
+        }
+        for pair in self.stops.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if t <= b.offset {
+                let span = b.offset - a.offset;
+                if span <= 0. {
+                    return b.color;
+                }
+                let f = (t - a.offset) / span;
+                return lerp(&a.color, &b.color, f);
+            }
+        }
+        self.stops[self.stops.len() - 1].color
+    }
+}
+
+/// Solve for the largest `t` for which the point lies on the circle
+/// interpolated between (c0, r0) and (c1, r1) with a non-negative radius.
+fn two_point_conical(
+    x: f32,
+    y: f32,
+    c0: (f32, f32),
+    r0: f32,
+    c1: (f32, f32),
+    r1: f32,
+) -> Option<f32> {
+    let (cdx, cdy) = (c1.0 - c0.0, c1.1 - c0.1);
+    let dr = r1 - r0;
+    let (pdx, pdy) = (x - c0.0, y - c0.1);
+    let a = cdx * cdx + cdy * cdy - dr * dr;
+    let b = pdx * cdx + pdy * cdy + r0 * dr;
+    let c = pdx * pdx + pdy * pdy - r0 * r0;
+
+    if a.abs() < 1e-6 {
+        if b == 0. {
+            return None;
+        }
+        let t = c / (2. * b);
+        return if r0 + t * dr >= 0. { Some(t) } else { None };
+    }
+
+    let disc = b * b - a * c;
+    if disc < 0. {
+        return None;
+    }
+    let sq = disc.sqrt();
+    let t1 = (b + sq) / a;
+    let t2 = (b - sq) / a;
+    let (hi, lo) = if t1 > t2 { (t1, t2) } else { (t2, t1) };
+    if r0 + hi * dr >= 0. {
+        Some(hi)
+    } else if r0 + lo * dr >= 0. {
+        Some(lo)
+    } else {
+        None
+    }
+}
+
+fn lerp(a: &Rgba, b: &Rgba, f: f32) -> Rgba {
+    [
+        a[0] + (b[0] - a[0]) * f,
+        a[1] + (b[1] - a[1]) * f,
+        a[2] + (b[2] - a[2]) * f,
+        a[3] + (b[3] - a[3]) * f,
+    ]
+}
+
+/// Premultiply a straight alpha color
+pub fn premultiply(r: f32, g: f32, b: f32, a: f32) -> Rgba {
+    [r * a, g * a, b * a, a]
+}
+
+/// How a shape is filled
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fill {
+    Solid(Rgba),
+    Gradient(Gradient),
+}
+
+impl Fill {
+    fn color_at(&self, x: f32, y: f32) -> Rgba {
+        match self {
+            Fill::Solid(color) => *color,
+            Fill::Gradient(g) => g.color_at(x, y),
+        }
+    }
+}
+
+/// Per-pixel coverage for a path, in the range 0.0-1.0
+pub struct Mask {
+    coverage: Vec<f32>,
+}
+
+impl Mask {
+    /// Computes the anti-aliased coverage of `path` after applying
+    /// `transform`, which maps the path into canvas pixel coordinates.
+    pub fn from_path(
+        path: &Path,
+        fill_rule: FillRule,
+        transform: &Affine,
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        Self::from_paths(&[(path, fill_rule)], transform, width, height)
+    }
+
+    /// Computes the coverage of the union of a set of paths
+    pub fn from_paths(
+        paths: &[(&Path, FillRule)],
+        transform: &Affine,
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        let mut pixmap = Pixmap::new(width as u32, height as u32)?;
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(0xff, 0xff, 0xff, 0xff);
+        paint.anti_alias = true;
+        for (path, fill_rule) in paths {
+            pixmap.fill_path(path, &paint, *fill_rule, transform.to_skia(), None);
+        }
+        let coverage = pixmap
+            .data()
+            .chunks_exact(4)
+            .map(|px| px[3] as f32 / 255.)
+            .collect();
+        Some(Self { coverage })
+    }
+}
+
+/// Operators used to combine a source with a backdrop.
+/// The variants and their order match the COLRv1 CompositeMode enumeration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositeMode {
+    Clear,
+    Src,
+    Dest,
+    SrcOver,
+    DestOver,
+    SrcIn,
+    DestIn,
+    SrcOut,
+    DestOut,
+    SrcAtop,
+    DestAtop,
+    Xor,
+    Plus,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Multiply,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl CompositeMode {
+    pub fn from_u8(mode: u8) -> Option<Self> {
+        use CompositeMode::*;
+        const MODES: [CompositeMode; 28] = [
+            Clear, Src, Dest, SrcOver, DestOver, SrcIn, DestIn, SrcOut, DestOut, SrcAtop, DestAtop,
+            Xor, Plus, Screen, Overlay, Darken, Lighten, ColorDodge, ColorBurn, HardLight,
+            SoftLight, Difference, Exclusion, Multiply, Hue, Saturation, Color, Luminosity,
+        ];
+        MODES.get(mode as usize).copied()
+    }
+
+    /// Combine premultiplied `src` with premultiplied `dst`
+    pub fn apply(self, src: Rgba, dst: Rgba) -> Rgba {
+        use CompositeMode::*;
+        let (sa, da) = (src[3], dst[3]);
+        let porter_duff = |fa: f32, fb: f32| -> Rgba {
+            [
+                src[0] * fa + dst[0] * fb,
+                src[1] * fa + dst[1] * fb,
+                src[2] * fa + dst[2] * fb,
+                sa * fa + da * fb,
+            ]
+        };
+        match self {
+            Clear => [0.; 4],
+            Src => src,
+            Dest => dst,
+            SrcOver => porter_duff(1., 1. - sa),
+            DestOver => porter_duff(1. - da, 1.),
+            SrcIn => porter_duff(da, 0.),
+            DestIn => porter_duff(0., sa),
+            SrcOut => porter_duff(1. - da, 0.),
+            DestOut => porter_duff(0., 1. - sa),
+            SrcAtop => porter_duff(da, 1. - sa),
+            DestAtop => porter_duff(1. - da, sa),
+            Xor => porter_duff(1. - da, 1. - sa),
+            Plus => [
+                (src[0] + dst[0]).min(1.),
+                (src[1] + dst[1]).min(1.),
+                (src[2] + dst[2]).min(1.),
+                (sa + da).min(1.),
+            ],
+            Hue | Saturation | Color | Luminosity => {
+                let cs = unpremultiply(&src);
+                let cb = unpremultiply(&dst);
+                let mixed = match self {
+                    Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+                    Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+                    Color => set_lum(cs, lum(cb)),
+                    _ => set_lum(cb, lum(cs)),
+                };
+                blend_result(&src, &dst, mixed)
+            }
+            _ => {
+                let cs = unpremultiply(&src);
+                let cb = unpremultiply(&dst);
+                let mut mixed = [0.; 3];
+                for i in 0..3 {
+                    mixed[i] = separable_blend(self, cs[i], cb[i]);
+                }
+                blend_result(&src, &dst, mixed)
+            }
+        }
+    }
+}
+
+fn unpremultiply(c: &Rgba) -> [f32; 3] {
+    if c[3] <= 0. {
+        [0.; 3]
+    } else {
+        [c[0] / c[3], c[1] / c[3], c[2] / c[3]]
+    }
+}
+
+/// Apply the general blending formula from the W3C compositing spec
+fn blend_result(src: &Rgba, dst: &Rgba, mixed: [f32; 3]) -> Rgba {
+    let (sa, da) = (src[3], dst[3]);
+    let both = sa * da;
+    let mut result = [0.; 4];
+    for i in 0..3 {
+        result[i] = (1. - da) * src[i] + (1. - sa) * dst[i] + both * mixed[i].clamp(0., 1.);
+    }
+    result[3] = sa + da - both;
+    result
+}
+
+fn separable_blend(mode: CompositeMode, s: f32, b: f32) -> f32 {
+    use CompositeMode::*;
+    match mode {
+        Screen => s + b - s * b,
+        Overlay => separable_blend(HardLight, b, s),
+        Darken => s.min(b),
+        Lighten => s.max(b),
+        ColorDodge => {
+            if b == 0. {
+                0.
+            } else if s >= 1. {
+                1.
+            } else {
+                (b / (1. - s)).min(1.)
+            }
+        }
+        ColorBurn => {
+            if b >= 1. {
+                1.
+            } else if s <= 0. {
+                0.
+            } else {
+                1. - ((1. - b) / s).min(1.)
+            }
+        }
+        HardLight => {
+            if s <= 0.5 {
+                b * 2. * s
+            } else {
+                separable_blend(Screen, 2. * s - 1., b)
+            }
+        }
+        SoftLight => {
+            if s <= 0.5 {
+                b - (1. - 2. * s) * b * (1. - b)
+            } else {
+                let d = if b <= 0.25 {
+                    ((16. * b - 12.) * b + 4.) * b
+                } else {
+                    b.sqrt()
+                };
+                b + (2. * s - 1.) * (d - b)
+            }
+        }
+        Difference => (s - b).abs(),
+        Exclusion => s + b - 2. * s * b,
+        Multiply => s * b,
+        _ => s,
+    }
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    let mut c = c;
+    if n < 0. {
+        for v in c.iter_mut() {
+            *v = l + (*v - l) * l / (l - n);
+        }
+    }
+    if x > 1. {
+        for v in c.iter_mut() {
+            *v = l + (*v - l) * (1. - l) / (x - l);
+        }
+    }
+    c
+}
+
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    clip_color([c[0] + d, c[1] + d, c[2] + d])
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let max = c[0].max(c[1]).max(c[2]);
+    let min = c[0].min(c[1]).min(c[2]);
+    if max <= min {
+        return [0.; 3];
+    }
+    let mut result = [0.; 3];
+    for i in 0..3 {
+        result[i] = (c[i] - min) * s / (max - min);
+    }
+    result
+}
+
+/// A premultiplied RGBA floating point surface
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.; 4]; width * height],
+        }
+    }
+
+    pub fn new_layer(&self) -> Self {
+        Self::new(self.width, self.height)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgba {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Paint `fill` over the canvas using source-over, restricted
+    /// to the optional coverage mask
+    pub fn fill(&mut self, mask: Option<&Mask>, fill: &Fill) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                let coverage = mask.map(|m| m.coverage[idx]).unwrap_or(1.);
+                if coverage <= 0. {
+                    continue;
+                }
+                let mut color = fill.color_at(x as f32, y as f32);
+                if coverage < 1. {
+                    for c in color.iter_mut() {
+                        *c *= coverage;
+                    }
+                }
+                self.pixels[idx] = CompositeMode::SrcOver.apply(color, self.pixels[idx]);
+            }
+        }
+    }
+
+    /// Composite `src` onto this canvas, optionally scaling
+    /// the source by a coverage mask and an opacity
+    pub fn composite(
+        &mut self,
+        src: &Canvas,
+        mode: CompositeMode,
+        mask: Option<&Mask>,
+        opacity: f32,
+    ) {
+        for (idx, dst) in self.pixels.iter_mut().enumerate() {
+            let scale = mask.map(|m| m.coverage[idx]).unwrap_or(1.) * opacity;
+            let mut color = src.pixels[idx];
+            if scale < 1. {
+                for c in color.iter_mut() {
+                    *c *= scale;
+                }
+            }
+            *dst = mode.apply(color, *dst);
+        }
+    }
+
+    /// Convert to premultiplied RGBA 32bpp data
+    pub fn into_rgba8(self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for px in self.pixels {
+            for c in px.iter() {
+                data.push((c.clamp(0., 1.) * 255.).round() as u8);
+            }
+        }
+        data
+    }
+}
+
+/// Holds the canvas dimensions and the mapping from glyph space to
+/// canvas pixels that is derived from the bounds of the glyph
+pub struct Placement {
+    pub width: usize,
+    pub height: usize,
+    /// Pixel offset of the canvas left edge relative to the glyph origin
+    pub left: i32,
+    /// Pixel offset of the canvas top edge relative to the baseline;
+    /// positive values are above the baseline
+    pub top: i32,
+    pub transform: Affine,
+}
+
+impl Placement {
+    /// Given the bounds of the glyph in y-down pixel space relative to the
+    /// glyph origin, and the transform that maps glyph space into that pixel
+    /// space, compute the canvas placement.
+    pub fn new(bounds: &BBox, to_pixels: &Affine) -> Option<Self> {
+        if bounds.is_empty() || !bounds.min_x.is_finite() || !bounds.max_y.is_finite() {
+            return None;
+        }
+        let left = bounds.min_x.floor();
+        let top = bounds.min_y.floor();
+        let width = (bounds.max_x.ceil() - left).max(1.) as usize;
+        let height = (bounds.max_y.ceil() - top).max(1.) as usize;
+        Some(Self {
+            width,
+            height,
+            left: left as i32,
+            top: -top as i32,
+            transform: Affine::translate(-left, -top).then(to_pixels),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn affine_invert() {
+        let a = Affine::new(2., 0.5, -1., 3., 10., -4.);
+        let inv = a.invert().unwrap();
+        let (x, y) = a.apply(3., 7.);
+        let (x, y) = inv.apply(x, y);
+        assert!((x - 3.).abs() < 1e-4 && (y - 7.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn extend_modes() {
+        assert_eq!(Extend::Pad.apply(1.5), 1.);
+        assert_eq!(Extend::Repeat.apply(1.25), 0.25);
+        assert_eq!(Extend::Reflect.apply(1.25), 0.75);
+        assert_eq!(Extend::Reflect.apply(-0.25), 0.25);
+    }
+
+    #[test]
+    fn radial_concentric() {
+        let t = two_point_conical(5., 0., (0., 0.), 0., (0., 0.), 10.).unwrap();
+        assert!((t - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn blend_multiply() {
+        let src = premultiply(0.5, 1., 1., 1.);
+        let dst = premultiply(0.5, 0.5, 0., 1.);
+        let result = CompositeMode::Multiply.apply(src, dst);
+        assert!((result[0] - 0.25).abs() < 1e-5);
+        assert!((result[1] - 0.5).abs() < 1e-5);
+        assert_eq!(result[2], 0.);
+        assert_eq!(result[3], 1.);
+    }
+}
@@ -0,0 +1,815 @@
+//! Renders glyphs from the COLR version 1 table.
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/colr>
+//!
+//! FreeType can parse the COLRv1 paint graph, but leaves the actual
+//! painting to the application; without this module those glyphs are
+//! rendered using their monochrome fallback outline.
+//!
+//! The variable paint formats are accepted, but their deltas are not
+//! applied: the default instance of the paint graph is rendered.
+use crate::rasterizer::canvas::*;
+use crate::rasterizer::RasterizedGlyph;
+use crate::units::PixelLength;
+use std::collections::HashMap;
+use tiny_skia::{FillRule, Path};
+
+/// Guards against cycles in the paint graph
+const MAX_DEPTH: usize = 64;
+
+/// Provides glyph outlines to the renderer
+pub trait GlyphOutlines {
+    /// Returns the outline of the glyph in font units, with y pointing up
+    fn glyph_outline(&mut self, glyph: u16) -> Option<Path>;
+}
+
+/// Big-endian accessors for the binary table data.
+/// All of these return None rather than panic when out of bounds.
+#[derive(Clone, Copy)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn u8(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    pub fn u16(&self, offset: usize) -> Option<u16> {
+        let b = self.data.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn i16(&self, offset: usize) -> Option<i16> {
+        self.u16(offset).map(|v| v as i16)
+    }
+
+    pub fn u24(&self, offset: usize) -> Option<u32> {
+        let b = self.data.get(offset..offset + 3)?;
+        Some(u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+
+    pub fn u32(&self, offset: usize) -> Option<u32> {
+        let b = self.data.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn f2dot14(&self, offset: usize) -> Option<f32> {
+        self.i16(offset).map(|v| v as f32 / 16384.)
+    }
+
+    pub fn fixed(&self, offset: usize) -> Option<f32> {
+        self.u32(offset).map(|v| v as i32 as f32 / 65536.)
+    }
+
+    /// Resolves an offset relative to `base`, treating zero as absent
+    fn offset24(&self, base: usize, at: usize) -> Option<usize> {
+        match self.u24(at)? {
+            0 => None,
+            off => Some(base + off as usize),
+        }
+    }
+
+    fn offset32(&self, base: usize, at: usize) -> Option<usize> {
+        match self.u32(at)? {
+            0 => None,
+            off => Some(base + off as usize),
+        }
+    }
+}
+
+/// A decoded paint table
+#[derive(Debug, Clone, PartialEq)]
+enum PaintNode {
+    Layers {
+        first: u32,
+        count: u8,
+    },
+    Solid {
+        palette_index: u16,
+        alpha: f32,
+    },
+    LinearGradient {
+        color_line: usize,
+        var: bool,
+        p0: (f32, f32),
+        p1: (f32, f32),
+        p2: (f32, f32),
+    },
+    RadialGradient {
+        color_line: usize,
+        var: bool,
+        c0: (f32, f32),
+        r0: f32,
+        c1: (f32, f32),
+        r1: f32,
+    },
+    SweepGradient {
+        color_line: usize,
+        var: bool,
+        center: (f32, f32),
+        start: f32,
+        end: f32,
+    },
+    Glyph {
+        paint: usize,
+        glyph: u16,
+    },
+    ColrGlyph {
+        glyph: u16,
+    },
+    Transform {
+        paint: usize,
+        transform: Affine,
+    },
+    Composite {
+        source: usize,
+        mode: CompositeMode,
+        backdrop: usize,
+    },
+}
+
+pub struct ColrV1 {
+    colr: Vec<u8>,
+    palette: Vec<Rgba>,
+    base_glyph_list: usize,
+    layer_list: Option<usize>,
+    clip_list: Option<usize>,
+}
+
+impl ColrV1 {
+    /// Parse the COLR table and optional CPAL table.
+    /// Returns None if the COLR table is not version 1 or is malformed.
+    pub fn new(colr: Vec<u8>, cpal: Option<&[u8]>) -> Option<Self> {
+        let r = Reader::new(&colr);
+        if r.u16(0)? != 1 {
+            return None;
+        }
+        let base_glyph_list = r.offset32(0, 14)?;
+        let layer_list = r.offset32(0, 18);
+        let clip_list = r.offset32(0, 22);
+        let palette = cpal.and_then(parse_cpal).unwrap_or_default();
+        Some(Self {
+            colr,
+            palette,
+            base_glyph_list,
+            layer_list,
+            clip_list,
+        })
+    }
+
+    fn reader(&self) -> Reader<'_> {
+        Reader::new(&self.colr)
+    }
+
+    /// Returns the offset of the root paint for `glyph`, if it has one
+    fn base_paint(&self, glyph: u16) -> Option<usize> {
+        let r = self.reader();
+        let list = self.base_glyph_list;
+        let count = r.u32(list)? as usize;
+        // BaseGlyphPaintRecords are sorted by glyph id
+        let (mut lo, mut hi) = (0, count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let rec = list + 4 + mid * 6;
+            let gid = r.u16(rec)?;
+            if gid == glyph {
+                return r.offset32(list, rec + 2);
+            } else if gid < glyph {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        None
+    }
+
+    pub fn has_glyph(&self, glyph: u16) -> bool {
+        self.base_paint(glyph).is_some()
+    }
+
+    /// Returns the clip box for `glyph`, in font units
+    fn clip_box(&self, glyph: u16) -> Option<BBox> {
+        let r = self.reader();
+        let list = self.clip_list?;
+        let count = r.u32(list + 1)? as usize;
+        for i in 0..count {
+            let rec = list + 5 + i * 7;
+            let start = r.u16(rec)?;
+            let end = r.u16(rec + 2)?;
+            if glyph >= start && glyph <= end {
+                let clip = r.offset24(list, rec + 4)?;
+                return Some(BBox::new(
+                    r.i16(clip + 1)? as f32,
+                    r.i16(clip + 3)? as f32,
+                    r.i16(clip + 5)? as f32,
+                    r.i16(clip + 7)? as f32,
+                ));
+            }
+        }
+        None
+    }
+
+    fn layer_paint(&self, index: u32) -> Option<usize> {
+        let r = self.reader();
+        let list = self.layer_list?;
+        let count = r.u32(list)?;
+        if index >= count {
+            return None;
+        }
+        r.offset32(list, list + 4 + index as usize * 4)
+    }
+
+    fn parse_paint(&self, p: usize) -> Option<PaintNode> {
+        let r = self.reader();
+        let format = r.u8(p)?;
+        let fword = |at: usize| r.i16(p + at).map(|v| v as f32);
+        let point = |at: usize| Some((fword(at)?, fword(at + 2)?));
+        // Most paint formats come in pairs where the odd numbered variant
+        // appends a VarIndexBase to the otherwise identical layout
+        let var = format % 2 == 1;
+        let child = || r.offset24(p, p + 1);
+        let transform =
+            |paint: usize, transform: Affine| Some(PaintNode::Transform { paint, transform });
+        match format {
+            1 => Some(PaintNode::Layers {
+                count: r.u8(p + 1)?,
+                first: r.u32(p + 2)?,
+            }),
+            2 | 3 => Some(PaintNode::Solid {
+                palette_index: r.u16(p + 1)?,
+                alpha: r.f2dot14(p + 3)?,
+            }),
+            4 | 5 => Some(PaintNode::LinearGradient {
+                color_line: r.offset24(p, p + 1)?,
+                var,
+                p0: point(4)?,
+                p1: point(8)?,
+                p2: point(12)?,
+            }),
+            6 | 7 => Some(PaintNode::RadialGradient {
+                color_line: r.offset24(p, p + 1)?,
+                var,
+                c0: point(4)?,
+                r0: r.u16(p + 8)? as f32,
+                c1: point(10)?,
+                r1: r.u16(p + 14)? as f32,
+            }),
+            8 | 9 => Some(PaintNode::SweepGradient {
+                color_line: r.offset24(p, p + 1)?,
+                var,
+                center: point(4)?,
+                start: r.f2dot14(p + 8)? * 180.,
+                end: r.f2dot14(p + 10)? * 180.,
+            }),
+            10 => Some(PaintNode::Glyph {
+                paint: child()?,
+                glyph: r.u16(p + 4)?,
+            }),
+            11 => Some(PaintNode::ColrGlyph {
+                glyph: r.u16(p + 1)?,
+            }),
+            12 | 13 => {
+                let t = r.offset24(p, p + 4)?;
+                transform(
+                    child()?,
+                    Affine::new(
+                        r.fixed(t)?,
+                        r.fixed(t + 4)?,
+                        r.fixed(t + 8)?,
+                        r.fixed(t + 12)?,
+                        r.fixed(t + 16)?,
+                        r.fixed(t + 20)?,
+                    ),
+                )
+            }
+            14 | 15 => transform(child()?, Affine::translate(fword(4)?, fword(6)?)),
+            16 | 17 => transform(
+                child()?,
+                Affine::scale(r.f2dot14(p + 4)?, r.f2dot14(p + 6)?),
+            ),
+            18 | 19 => transform(
+                child()?,
+                Affine::scale(r.f2dot14(p + 4)?, r.f2dot14(p + 6)?).around(fword(8)?, fword(10)?),
+            ),
+            20 | 21 => {
+                let s = r.f2dot14(p + 4)?;
+                transform(child()?, Affine::scale(s, s))
+            }
+            22 | 23 => {
+                let s = r.f2dot14(p + 4)?;
+                transform(child()?, Affine::scale(s, s).around(fword(6)?, fword(8)?))
+            }
+            24 | 25 => transform(child()?, Affine::rotate(r.f2dot14(p + 4)? * 180.)),
+            26 | 27 => transform(
+                child()?,
+                Affine::rotate(r.f2dot14(p + 4)? * 180.).around(fword(6)?, fword(8)?),
+            ),
+            28 | 29 => transform(
+                child()?,
+                Affine::skew(-r.f2dot14(p + 4)? * 180., r.f2dot14(p + 6)? * 180.),
+            ),
+            30 | 31 => transform(
+                child()?,
+                Affine::skew(-r.f2dot14(p + 4)? * 180., r.f2dot14(p + 6)? * 180.)
+                    .around(fword(8)?, fword(10)?),
+            ),
+            32 => Some(PaintNode::Composite {
+                source: child()?,
+                mode: CompositeMode::from_u8(r.u8(p + 4)?)?,
+                backdrop: r.offset24(p, p + 5)?,
+            }),
+            _ => {
+                log::debug!("COLRv1: unsupported paint format {}", format);
+                None
+            }
+        }
+    }
+
+    fn color(&self, palette_index: u16, alpha: f32, foreground: Rgba) -> Rgba {
+        let color = if palette_index == 0xffff {
+            foreground
+        } else {
+            self.palette
+                .get(palette_index as usize)
+                .copied()
+                .unwrap_or([0., 0., 0., 1.])
+        };
+        [
+            color[0] * alpha,
+            color[1] * alpha,
+            color[2] * alpha,
+            color[3] * alpha,
+        ]
+    }
+
+    fn color_line(
+        &self,
+        offset: usize,
+        var: bool,
+        foreground: Rgba,
+    ) -> Option<(Extend, Vec<ColorStop>)> {
+        let r = self.reader();
+        let extend = match r.u8(offset)? {
+            1 => Extend::Repeat,
+            2 => Extend::Reflect,
+            _ => Extend::Pad,
+        };
+        let count = r.u16(offset + 1)? as usize;
+        let stride = if var { 10 } else { 6 };
+        let mut stops = Vec::with_capacity(count);
+        for i in 0..count {
+            let s = offset + 3 + i * stride;
+            stops.push(ColorStop {
+                offset: r.f2dot14(s)?,
+                color: self.color(r.u16(s + 2)?, r.f2dot14(s + 4)?, foreground),
+            });
+        }
+        Some((extend, stops))
+    }
+
+    /// Render `glyph` into a bitmap.
+    /// `to_pixels` maps font units (y-up) to pixels (y-down), relative to
+    /// the glyph origin.
+    /// `foreground` is used for palette entries that reference the text color.
+    pub fn render(
+        &self,
+        glyph: u16,
+        to_pixels: &Affine,
+        foreground: Rgba,
+        outlines: &mut dyn GlyphOutlines,
+    ) -> Option<RasterizedGlyph> {
+        let root = self.base_paint(glyph)?;
+        let mut painter = Painter {
+            colr: self,
+            outlines,
+            cache: HashMap::new(),
+            foreground,
+        };
+
+        let bounds = match self.clip_box(glyph) {
+            Some(clip) => to_pixels.map_rect(&clip),
+            None => {
+                let mut bounds = BBox::empty();
+                painter.bounds(root, to_pixels, 0, &mut bounds);
+                bounds
+            }
+        };
+        let placement = Placement::new(&bounds, to_pixels)?;
+        let mut canvas = Canvas::new(placement.width, placement.height);
+        painter.paint(root, &placement.transform, 0, &mut canvas);
+
+        Some(RasterizedGlyph {
+            data: canvas.into_rgba8(),
+            height: placement.height,
+            width: placement.width,
+            bearing_x: PixelLength::new(placement.left as f64),
+            bearing_y: PixelLength::new(placement.top as f64),
+            has_color: true,
+        })
+    }
+}
+
+/// Extract the colors from the first palette in the CPAL table
+fn parse_cpal(cpal: &[u8]) -> Option<Vec<Rgba>> {
+    let r = Reader::new(cpal);
+    let num_entries = r.u16(2)? as usize;
+    let records = r.u32(8)? as usize;
+    let first = r.u16(12)? as usize;
+    let mut palette = Vec::with_capacity(num_entries);
+    for i in 0..num_entries {
+        let c = records + (first + i) * 4;
+        // Colors are stored as BGRA
+        let b = r.u8(c)? as f32 / 255.;
+        let g = r.u8(c + 1)? as f32 / 255.;
+        let red = r.u8(c + 2)? as f32 / 255.;
+        let a = r.u8(c + 3)? as f32 / 255.;
+        palette.push(premultiply(red, g, b, a));
+    }
+    Some(palette)
+}
+
+struct Painter<'a> {
+    colr: &'a ColrV1,
+    outlines: &'a mut dyn GlyphOutlines,
+    cache: HashMap<u16, Option<Path>>,
+    foreground: Rgba,
+}
+
+impl<'a> Painter<'a> {
+    fn outline(&mut self, glyph: u16) -> Option<&Path> {
+        let outlines = &mut self.outlines;
+        self.cache
+            .entry(glyph)
+            .or_insert_with(|| outlines.glyph_outline(glyph))
+            .as_ref()
+    }
+
+    /// Accumulate the area covered by the glyph outlines referenced
+    /// by the paint graph
+    fn bounds(&mut self, paint: usize, ctm: &Affine, depth: usize, bounds: &mut BBox) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        match self.colr.parse_paint(paint) {
+            Some(PaintNode::Layers { first, count }) => {
+                for i in 0..count as u32 {
+                    if let Some(layer) = self.colr.layer_paint(first + i) {
+                        self.bounds(layer, ctm, depth + 1, bounds);
+                    }
+                }
+            }
+            Some(PaintNode::Glyph { glyph, .. }) => {
+                if let Some(path) = self.outline(glyph) {
+                    bounds.union(&ctm.map_rect(&BBox::of_path(path)));
+                }
+            }
+            Some(PaintNode::ColrGlyph { glyph }) => {
+                if let Some(root) = self.colr.base_paint(glyph) {
+                    self.bounds(root, ctm, depth + 1, bounds);
+                }
+            }
+            Some(PaintNode::Transform { paint, transform }) => {
+                self.bounds(paint, &ctm.then(&transform), depth + 1, bounds);
+            }
+            Some(PaintNode::Composite {
+                source, backdrop, ..
+            }) => {
+                self.bounds(source, ctm, depth + 1, bounds);
+                self.bounds(backdrop, ctm, depth + 1, bounds);
+            }
+            _ => {}
+        }
+    }
+
+    /// If `node` is a plain fill, return it in canvas space
+    fn fill(&self, node: &PaintNode, ctm: &Affine) -> Option<Fill> {
+        let fg = self.foreground;
+        match node {
+            PaintNode::Solid {
+                palette_index,
+                alpha,
+            } => Some(Fill::Solid(self.colr.color(*palette_index, *alpha, fg))),
+            PaintNode::LinearGradient {
+                color_line,
+                var,
+                p0,
+                p1,
+                p2,
+            } => {
+                let (extend, stops) = self.colr.color_line(*color_line, *var, fg)?;
+                // The gradient runs from p0 towards p1, but with the
+                // color lines parallel to p0->p2, so project p1 onto
+                // the line perpendicular to p0->p2
+                let (vx, vy) = (p2.0 - p0.0, p2.1 - p0.1);
+                let (nx, ny) = (vy, -vx);
+                let n_len = nx * nx + ny * ny;
+                let end = if n_len == 0. {
+                    *p1
+                } else {
+                    let d = ((p1.0 - p0.0) * nx + (p1.1 - p0.1) * ny) / n_len;
+                    (p0.0 + d * nx, p0.1 + d * ny)
+                };
+                Gradient::new(
+                    GradientGeometry::Linear { p0: *p0, p1: end },
+                    stops,
+                    extend,
+                    ctm,
+                )
+                .map(Fill::Gradient)
+            }
+            PaintNode::RadialGradient {
+                color_line,
+                var,
+                c0,
+                r0,
+                c1,
+                r1,
+            } => {
+                let (extend, stops) = self.colr.color_line(*color_line, *var, fg)?;
+                Gradient::new(
+                    GradientGeometry::Radial {
+                        c0: *c0,
+                        r0: *r0,
+                        c1: *c1,
+                        r1: *r1,
+                    },
+                    stops,
+                    extend,
+                    ctm,
+                )
+                .map(Fill::Gradient)
+            }
+            PaintNode::SweepGradient {
+                color_line,
+                var,
+                center,
+                start,
+                end,
+            } => {
+                let (extend, stops) = self.colr.color_line(*color_line, *var, fg)?;
+                Gradient::new(
+                    GradientGeometry::Sweep {
+                        center: *center,
+                        start: *start,
+                        end: *end,
+                    },
+                    stops,
+                    extend,
+                    ctm,
+                )
+                .map(Fill::Gradient)
+            }
+            _ => None,
+        }
+    }
+
+    fn paint(&mut self, paint: usize, ctm: &Affine, depth: usize, canvas: &mut Canvas) {
+        if depth > MAX_DEPTH {
+            log::warn!("COLRv1: paint graph is too deep; ignoring remainder");
+            return;
+        }
+        let node = match self.colr.parse_paint(paint) {
+            Some(node) => node,
+            None => return,
+        };
+        match node {
+            PaintNode::Layers { first, count } => {
+                for i in 0..count as u32 {
+                    if let Some(layer) = self.colr.layer_paint(first + i) {
+                        self.paint(layer, ctm, depth + 1, canvas);
+                    }
+                }
+            }
+            PaintNode::Glyph { paint, glyph } => {
+                let (width, height) = (canvas.width, canvas.height);
+                let mask = match self
+                    .outline(glyph)
+                    .and_then(|path| Mask::from_path(path, FillRule::Winding, ctm, width, height))
+                {
+                    Some(mask) => mask,
+                    None => return,
+                };
+                // The child is usually a simple fill; avoid allocating
+                // an intermediate layer for that case
+                let simple = self
+                    .colr
+                    .parse_paint(paint)
+                    .and_then(|child| self.fill(&child, ctm));
+                match simple {
+                    Some(fill) => canvas.fill(Some(&mask), &fill),
+                    None => {
+                        let mut layer = canvas.new_layer();
+                        self.paint(paint, ctm, depth + 1, &mut layer);
+                        canvas.composite(&layer, CompositeMode::SrcOver, Some(&mask), 1.);
+                    }
+                }
+            }
+            PaintNode::ColrGlyph { glyph } => {
+                if let Some(root) = self.colr.base_paint(glyph) {
+                    self.paint(root, ctm, depth + 1, canvas);
+                }
+            }
+            PaintNode::Transform { paint, transform } => {
+                self.paint(paint, &ctm.then(&transform), depth + 1, canvas);
+            }
+            PaintNode::Composite {
+                source,
+                mode,
+                backdrop,
+            } => {
+                let mut src = canvas.new_layer();
+                self.paint(source, ctm, depth + 1, &mut src);
+                let mut dest = canvas.new_layer();
+                self.paint(backdrop, ctm, depth + 1, &mut dest);
+                dest.composite(&src, mode, None, 1.);
+                canvas.composite(&dest, CompositeMode::SrcOver, None, 1.);
+            }
+            fill => {
+                if let Some(fill) = self.fill(&fill, ctm) {
+                    canvas.fill(None, &fill);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tiny_skia::PathBuilder;
+
+    /// Every glyph is a 100x100 font unit square sitting on the baseline
+    struct Squares;
+
+    impl GlyphOutlines for Squares {
+        fn glyph_outline(&mut self, _glyph: u16) -> Option<Path> {
+            let mut pb = PathBuilder::new();
+            pb.move_to(0., 0.);
+            pb.line_to(100., 0.);
+            pb.line_to(100., 100.);
+            pb.line_to(0., 100.);
+            pb.close();
+            pb.finish()
+        }
+    }
+
+    /// Palette: 0 = opaque red, 1 = opaque blue
+    fn cpal() -> Vec<u8> {
+        let mut t = vec![];
+        t.extend_from_slice(&0u16.to_be_bytes()); // version
+        t.extend_from_slice(&2u16.to_be_bytes()); // numPaletteEntries
+        t.extend_from_slice(&1u16.to_be_bytes()); // numPalettes
+        t.extend_from_slice(&2u16.to_be_bytes()); // numColorRecords
+        t.extend_from_slice(&14u32.to_be_bytes()); // colorRecordsArrayOffset
+        t.extend_from_slice(&0u16.to_be_bytes()); // colorRecordIndices[0]
+        t.extend_from_slice(&[0, 0, 0xff, 0xff]); // red (BGRA)
+        t.extend_from_slice(&[0xff, 0, 0, 0xff]); // blue (BGRA)
+        t
+    }
+
+    fn u24(v: usize) -> [u8; 3] {
+        let b = (v as u32).to_be_bytes();
+        [b[1], b[2], b[3]]
+    }
+
+    /// Build a COLRv1 table with a single base glyph (gid 1) whose
+    /// paint is supplied by the caller. `paint` is placed immediately
+    /// after the BaseGlyphList, and its internal offsets are relative
+    /// to its own start.
+    fn colr(paint: &[u8]) -> Vec<u8> {
+        let header_len = 34;
+        let base_glyph_list = header_len;
+        let mut t = vec![];
+        t.extend_from_slice(&1u16.to_be_bytes()); // version
+        t.extend_from_slice(&0u16.to_be_bytes()); // numBaseGlyphRecords
+        t.extend_from_slice(&0u32.to_be_bytes()); // baseGlyphRecordsOffset
+        t.extend_from_slice(&0u32.to_be_bytes()); // layerRecordsOffset
+        t.extend_from_slice(&0u16.to_be_bytes()); // numLayerRecords
+        t.extend_from_slice(&(base_glyph_list as u32).to_be_bytes());
+        t.extend_from_slice(&0u32.to_be_bytes()); // layerListOffset
+        t.extend_from_slice(&0u32.to_be_bytes()); // clipListOffset
+        t.extend_from_slice(&0u32.to_be_bytes()); // varIndexMapOffset
+        t.extend_from_slice(&0u32.to_be_bytes()); // itemVariationStoreOffset
+        assert_eq!(t.len(), header_len);
+        // BaseGlyphList with one record
+        t.extend_from_slice(&1u32.to_be_bytes());
+        t.extend_from_slice(&1u16.to_be_bytes()); // glyph id
+        t.extend_from_slice(&10u32.to_be_bytes()); // paint offset
+        t.extend_from_slice(paint);
+        t
+    }
+
+    /// PaintGlyph(gid 2) wrapping `child` which follows immediately
+    fn paint_glyph(child: &[u8]) -> Vec<u8> {
+        let mut t = vec![10];
+        t.extend_from_slice(&u24(6));
+        t.extend_from_slice(&2u16.to_be_bytes());
+        t.extend_from_slice(child);
+        t
+    }
+
+    fn solid(palette_index: u16) -> Vec<u8> {
+        let mut t = vec![2];
+        t.extend_from_slice(&palette_index.to_be_bytes());
+        t.extend_from_slice(&0x4000u16.to_be_bytes()); // alpha 1.0
+        t
+    }
+
+    fn render(colr_table: Vec<u8>) -> RasterizedGlyph {
+        let colr = ColrV1::new(colr_table, Some(&cpal())).unwrap();
+        assert!(colr.has_glyph(1));
+        assert!(!colr.has_glyph(2));
+        // 100 font units -> 10 pixels
+        let to_pixels = Affine::scale(0.1, -0.1);
+        colr.render(1, &to_pixels, [1., 1., 1., 1.], &mut Squares)
+            .unwrap()
+    }
+
+    fn pixel(glyph: &RasterizedGlyph, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * glyph.width + x) * 4;
+        [
+            glyph.data[i],
+            glyph.data[i + 1],
+            glyph.data[i + 2],
+            glyph.data[i + 3],
+        ]
+    }
+
+    #[test]
+    fn rejects_v0() {
+        let mut table = colr(&solid(0));
+        table[1] = 0;
+        assert!(ColrV1::new(table, None).is_none());
+    }
+
+    #[test]
+    fn solid_glyph() {
+        let glyph = render(colr(&paint_glyph(&solid(0))));
+        assert_eq!(glyph.width, 10);
+        assert_eq!(glyph.height, 10);
+        assert_eq!(glyph.bearing_x.get(), 0.);
+        assert_eq!(glyph.bearing_y.get(), 10.);
+        assert!(glyph.has_color);
+        assert_eq!(pixel(&glyph, 5, 5), [0xff, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn foreground_color() {
+        let glyph = render(colr(&paint_glyph(&solid(0xffff))));
+        assert_eq!(pixel(&glyph, 5, 5), [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        // PaintLinearGradient from x=0 (red) to x=100 (blue)
+        let mut gradient = vec![4];
+        gradient.extend_from_slice(&u24(16)); // colorLine offset
+        for v in [0i16, 0, 100, 0, 0, 100] {
+            gradient.extend_from_slice(&v.to_be_bytes());
+        }
+        assert_eq!(gradient.len(), 16);
+        gradient.push(0); // extend pad
+        gradient.extend_from_slice(&2u16.to_be_bytes());
+        for (offset, index) in [(0u16, 0u16), (0x4000, 1)] {
+            gradient.extend_from_slice(&offset.to_be_bytes());
+            gradient.extend_from_slice(&index.to_be_bytes());
+            gradient.extend_from_slice(&0x4000u16.to_be_bytes());
+        }
+        let glyph = render(colr(&paint_glyph(&gradient)));
+        let left = pixel(&glyph, 0, 5);
+        let right = pixel(&glyph, 9, 5);
+        assert!(left[0] > 0xe0 && left[2] < 0x20, "{:?}", left);
+        assert!(right[2] > 0xe0 && right[0] < 0x20, "{:?}", right);
+    }
+
+    #[test]
+    fn translated_composite() {
+        // PaintComposite(DEST_OUT) with a red square backdrop and a
+        // translated copy of the square as the source punches out the
+        // right half
+        let backdrop = paint_glyph(&solid(0));
+        let mut translate = vec![14];
+        translate.extend_from_slice(&u24(8));
+        translate.extend_from_slice(&50i16.to_be_bytes());
+        translate.extend_from_slice(&0i16.to_be_bytes());
+        translate.extend_from_slice(&paint_glyph(&solid(1)));
+
+        let mut composite = vec![32];
+        composite.extend_from_slice(&u24(8));
+        composite.push(8); // DEST_OUT
+        composite.extend_from_slice(&u24(8 + translate.len()));
+        composite.extend_from_slice(&translate);
+        composite.extend_from_slice(&backdrop);
+
+        let glyph = render(colr(&composite));
+        // Bounds include the translated source
+        assert_eq!(glyph.width, 15);
+        assert_eq!(pixel(&glyph, 2, 5), [0xff, 0, 0, 0xff]);
+        assert_eq!(pixel(&glyph, 7, 5), [0, 0, 0, 0]);
+        assert_eq!(pixel(&glyph, 12, 5), [0, 0, 0, 0]);
+    }
+}
@@ -1,13 +1,19 @@
 use crate::parser::ParsedFont;
-use crate::rasterizer::FontRasterizer;
+use crate::rasterizer::colr::GlyphOutlines;
+use crate::rasterizer::{FontRasterizer, VectorColorTables};
 use crate::units::*;
 use crate::{ftwrap, RasterizedGlyph};
-use ::freetype::{FT_GlyphSlotRec_, FT_Glyph_Format, FT_Matrix};
+use ::freetype::{
+    FT_GlyphSlotRec_, FT_Glyph_Format, FT_Matrix, FT_Outline, FT_Outline_Decompose,
+    FT_Outline_Funcs, FT_Vector,
+};
 use anyhow::bail;
 use config::{DisplayPixelGeometry, FreeTypeLoadFlags, FreeTypeLoadTarget};
 use std::cell::RefCell;
+use std::os::raw::{c_int, c_void};
 use std::{mem, slice};
-use wezterm_color_types::linear_u8_to_srgb8;
+use tiny_skia::{Path, PathBuilder};
+use wezterm_color_types::{linear_u8_to_srgb8, SrgbaTuple};
 
 pub struct FreeTypeRasterizer {
     has_color: bool,
@@ -20,6 +26,7 @@ pub struct FreeTypeRasterizer {
     freetype_load_flags: Option<FreeTypeLoadFlags>,
     display_pixel_geometry: DisplayPixelGeometry,
     scale: f64,
+    vector_color: VectorColorTables,
 }
 
 impl FontRasterizer for FreeTypeRasterizer {
@@ -28,11 +35,16 @@ impl FontRasterizer for FreeTypeRasterizer {
         glyph_pos: u32,
         size: f64,
         dpi: u32,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<RasterizedGlyph> {
         self.face
             .borrow_mut()
             .set_font_size(size * self.scale, dpi)?;
 
+        if let Some(glyph) = self.rasterize_vector_color(glyph_pos, foreground) {
+            return Ok(glyph);
+        }
+
         let (load_flags, render_mode) = ftwrap::compute_load_flags_from_config(
             self.freetype_load_flags,
             self.freetype_load_target,
@@ -71,9 +83,36 @@ impl FontRasterizer for FreeTypeRasterizer {
             Ok(glyph)
         }
     }
+
+    fn uses_foreground(&self, glyph_pos: u32) -> bool {
+        self.vector_color.has_glyph(glyph_pos)
+    }
 }
 
 impl FreeTypeRasterizer {
+    /// Paint COLRv1 and OpenType-SVG glyphs, which FreeType itself
+    /// can only render using their monochrome fallback outlines
+    fn rasterize_vector_color(
+        &self,
+        glyph_pos: u32,
+        foreground: SrgbaTuple,
+    ) -> Option<RasterizedGlyph> {
+        if !self.vector_color.has_glyph(glyph_pos) {
+            return None;
+        }
+        let mut face = self.face.borrow_mut();
+        let pixels_per_unit = face.pixels_per_font_unit()? as f32;
+        let units_per_em = face.units_per_em() as f32;
+        self.vector_color.rasterize(
+            glyph_pos,
+            pixels_per_unit,
+            units_per_em,
+            self.synthesize_italic,
+            foreground,
+            &mut FaceOutlines { face: &mut face },
+        )
+    }
+
     fn rasterize_mono(
         &self,
         pitch: usize,
@@ -308,6 +347,7 @@ impl FreeTypeRasterizer {
         log::trace!("Rasterizier wants {:?}", parsed);
         let lib = ftwrap::Library::new()?;
         let mut face = lib.face_from_locator(&parsed.handle)?;
+        face.set_variation_coordinates(&parsed.variations)?;
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
@@ -321,6 +361,8 @@ impl FreeTypeRasterizer {
             }));
         }
 
+        let vector_color = VectorColorTables::new(parsed, |tag| face.load_sfnt_table(tag));
+
         Ok(Self {
             _lib: lib,
             face: RefCell::new(face),
@@ -332,6 +374,84 @@ impl FreeTypeRasterizer {
             freetype_render_target: parsed.freetype_render_target,
             display_pixel_geometry,
             scale: parsed.scale.unwrap_or(1.),
+            vector_color,
         })
     }
 }
+
+struct FaceOutlines<'a> {
+    face: &'a mut ftwrap::Face,
+}
+
+impl<'a> GlyphOutlines for FaceOutlines<'a> {
+    fn glyph_outline(&mut self, glyph: u16) -> Option<Path> {
+        let outline = self.face.load_unscaled_outline(glyph as u32)?;
+        outline_to_path(outline)
+    }
+}
+
+/// Convert a FreeType outline into a tiny-skia path
+fn outline_to_path(outline: &FT_Outline) -> Option<Path> {
+    unsafe extern "C" fn move_to(to: *const FT_Vector, user: *mut c_void) -> c_int {
+        let pb = &mut *(user as *mut PathBuilder);
+        pb.move_to((*to).x as f32, (*to).y as f32);
+        0
+    }
+    unsafe extern "C" fn line_to(to: *const FT_Vector, user: *mut c_void) -> c_int {
+        let pb = &mut *(user as *mut PathBuilder);
+        pb.line_to((*to).x as f32, (*to).y as f32);
+        0
+    }
+    unsafe extern "C" fn conic_to(
+        control: *const FT_Vector,
+        to: *const FT_Vector,
+        user: *mut c_void,
+    ) -> c_int {
+        let pb = &mut *(user as *mut PathBuilder);
+        pb.quad_to(
+            (*control).x as f32,
+            (*control).y as f32,
+            (*to).x as f32,
+            (*to).y as f32,
+        );
+        0
+    }
+    unsafe extern "C" fn cubic_to(
+        control1: *const FT_Vector,
+        control2: *const FT_Vector,
+        to: *const FT_Vector,
+        user: *mut c_void,
+    ) -> c_int {
+        let pb = &mut *(user as *mut PathBuilder);
+        pb.cubic_to(
+            (*control1).x as f32,
+            (*control1).y as f32,
+            (*control2).x as f32,
+            (*control2).y as f32,
+            (*to).x as f32,
+            (*to).y as f32,
+        );
+        0
+    }
+
+    let funcs = FT_Outline_Funcs {
+        move_to: Some(move_to),
+        line_to: Some(line_to),
+        conic_to: Some(conic_to),
+        cubic_to: Some(cubic_to),
+        shift: 0,
+        delta: 0,
+    };
+    let mut pb = PathBuilder::new();
+    let res = unsafe {
+        FT_Outline_Decompose(
+            outline as *const FT_Outline as *mut FT_Outline,
+            &funcs,
+            &mut pb as *mut PathBuilder as *mut c_void,
+        )
+    };
+    if !ftwrap::succeeded(res) {
+        return None;
+    }
+    pb.finish()
+}
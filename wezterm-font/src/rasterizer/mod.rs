@@ -1,8 +1,19 @@
 use crate::parser::ParsedFont;
+use crate::rasterizer::canvas::{premultiply, Affine};
+use crate::rasterizer::colr::{ColrV1, GlyphOutlines};
+use crate::rasterizer::svg::SvgTable;
 use crate::units::*;
 use config::FontRasterizerSelection;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex, Weak};
+use wezterm_color_types::SrgbaTuple;
 
+pub(crate) mod canvas;
+pub(crate) mod colr;
 pub mod freetype;
+pub(crate) mod svg;
+pub mod swash;
 
 /// A bitmap representation of a glyph.
 /// The data is stored as pre-multiplied RGBA 32bpp.
@@ -78,12 +89,120 @@ impl RasterizedGlyph {
 /// Rasterizes the specified glyph index in the associated font
 /// and returns the generated bitmap
 pub trait FontRasterizer {
+    /// `foreground` is the color used for the parts of COLRv1 and
+    /// OpenType-SVG glyphs that are painted in the text color
     fn rasterize_glyph(
         &self,
         glyph_pos: u32,
         size: f64,
         dpi: u32,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<RasterizedGlyph>;
+
+    /// Returns true if the rendering of the glyph may depend upon
+    /// the foreground color passed to `rasterize_glyph`
+    fn uses_foreground(&self, glyph_pos: u32) -> bool;
+}
+
+/// The parsed COLRv1 and OpenType-SVG tables of a font face
+struct ColorTables {
+    colr: Option<ColrV1>,
+    svg: Option<SvgTable>,
+}
+
+lazy_static::lazy_static! {
+    /// The tables are shared by all of the rasterizers for a given
+    /// face, rather than being copied for each size and style in use
+    static ref COLOR_TABLES: Mutex<HashMap<(String, u32), Weak<ColorTables>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The COLRv1 and OpenType-SVG tables of a font.
+/// Glyphs from these tables are painted by wezterm rather than by
+/// the rasterizer backend, which can only produce their monochrome
+/// fallback outlines.
+pub(crate) struct VectorColorTables {
+    tables: Arc<ColorTables>,
+}
+
+impl VectorColorTables {
+    /// `load_table` returns the data for the sfnt table with the specified tag.
+    /// It is only called if the tables for `parsed` aren't already loaded.
+    pub fn new(parsed: &ParsedFont, load_table: impl Fn(&[u8; 4]) -> Option<Vec<u8>>) -> Self {
+        let key = (
+            parsed.handle.source.name_or_path_str().into_owned(),
+            parsed.handle.index,
+        );
+        let mut cache = COLOR_TABLES.lock().unwrap();
+        let tables = match cache.get(&key).and_then(Weak::upgrade) {
+            Some(tables) => tables,
+            None => {
+                let colr = load_table(b"COLR").and_then(|colr| {
+                    let cpal = load_table(b"CPAL");
+                    ColrV1::new(colr, cpal.as_deref())
+                });
+                let svg = load_table(b"SVG ").and_then(SvgTable::new);
+                let tables = Arc::new(ColorTables { colr, svg });
+                cache.retain(|_, tables| tables.strong_count() > 0);
+                cache.insert(key, Arc::downgrade(&tables));
+                tables
+            }
+        };
+        Self { tables }
+    }
+
+    pub fn has_glyph(&self, glyph_pos: u32) -> bool {
+        match u16::try_from(glyph_pos) {
+            Ok(glyph) => {
+                self.tables
+                    .colr
+                    .as_ref()
+                    .map_or(false, |c| c.has_glyph(glyph))
+                    || self
+                        .tables
+                        .svg
+                        .as_ref()
+                        .map_or(false, |s| s.has_glyph(glyph))
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Paint the glyph, if it is present in either table.
+    /// `pixels_per_unit` is the scale from font units to pixels.
+    /// `foreground` is used for paint that references the text color.
+    pub fn rasterize(
+        &self,
+        glyph_pos: u32,
+        pixels_per_unit: f32,
+        units_per_em: f32,
+        synthesize_italic: bool,
+        foreground: SrgbaTuple,
+        outlines: &mut dyn GlyphOutlines,
+    ) -> Option<RasterizedGlyph> {
+        let glyph = u16::try_from(glyph_pos).ok()?;
+        let SrgbaTuple(r, g, b, a) = foreground;
+        let foreground = premultiply(r, g, b, a);
+        let scale = pixels_per_unit;
+        // Synthesized italics are produced by skewing the font units
+        let skew = if synthesize_italic { 0.2 } else { 0. };
+
+        if let Some(colr) = self.tables.colr.as_ref().filter(|c| c.has_glyph(glyph)) {
+            // Font units are y-up whereas pixels are y-down
+            let to_pixels =
+                Affine::scale(scale, -scale).then(&Affine::new(1., 0., skew, 1., 0., 0.));
+            if let Some(glyph) = colr.render(glyph, &to_pixels, foreground, outlines) {
+                return Some(glyph);
+            }
+        }
+        if let Some(svg) = self.tables.svg.as_ref().filter(|s| s.has_glyph(glyph)) {
+            // The SVG coordinate system is already y-down
+            let to_pixels =
+                Affine::scale(scale, scale).then(&Affine::new(1., 0., -skew, 1., 0., 0.));
+            return svg.render(glyph, units_per_em, &to_pixels, foreground);
+        }
+        None
+    }
 }
 
 pub fn new_rasterizer(
@@ -95,5 +214,175 @@ pub fn new_rasterizer(
         FontRasterizerSelection::FreeType => Ok(Box::new(
             freetype::FreeTypeRasterizer::from_locator(handle, pixel_geometry)?,
         )),
+        FontRasterizerSelection::Swash => Ok(Box::new(swash::SwashRasterizer::from_locator(
+            handle,
+            pixel_geometry,
+        )?)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::locator::{FontDataHandle, FontDataSource, FontOrigin};
+    use config::DisplayPixelGeometry;
+
+    // These fonts are produced by test-data/make-color-fonts.py.
+    // In both of them, glyph 2 is a solid color square and glyph 3
+    // is a square painted in the text color.
+    const COLR_FONT: &[u8] = include_bytes!("../../../test-data/colrv1.ttf");
+    const SVG_FONT: &[u8] = include_bytes!("../../../test-data/svg-glyphs.ttf");
+    // A plain outline font, which exercises the rasterizers themselves
+    // rather than our shared vector color renderer.
+    const OUTLINE_FONT: &[u8] = include_bytes!("../../../assets/fonts/JetBrainsMono-Regular.ttf");
+
+    const BLUE: SrgbaTuple = SrgbaTuple(0., 0., 1., 1.);
+
+    fn parse(name: &'static str, data: &'static [u8]) -> ParsedFont {
+        let handle = FontDataHandle {
+            source: FontDataSource::BuiltIn { name, data },
+            index: 0,
+            variation: 0,
+            origin: FontOrigin::BuiltIn,
+            coverage: None,
+        };
+        ParsedFont::from_locator(&handle).unwrap()
+    }
+
+    /// Renders `glyph` at 10 pixels per em
+    fn rasterize(
+        selection: FontRasterizerSelection,
+        parsed: &ParsedFont,
+        glyph: u32,
+    ) -> RasterizedGlyph {
+        let raster = new_rasterizer(selection, parsed, DisplayPixelGeometry::RGB).unwrap();
+        raster.rasterize_glyph(glyph, 10., 72, BLUE).unwrap()
+    }
+
+    fn pixel(glyph: &RasterizedGlyph, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * glyph.width + x) * 4;
+        [
+            glyph.data[i],
+            glyph.data[i + 1],
+            glyph.data[i + 2],
+            glyph.data[i + 3],
+        ]
+    }
+
+    fn assert_square(glyph: &RasterizedGlyph, color: [u8; 4]) {
+        assert_eq!(glyph.width, 10);
+        assert_eq!(glyph.height, 10);
+        assert_eq!(glyph.bearing_x.get(), 0.);
+        assert_eq!(glyph.bearing_y.get(), 10.);
+        assert!(glyph.has_color);
+        for (x, y) in [(0, 0), (5, 5), (9, 9)] {
+            assert_eq!(pixel(glyph, x, y), color, "pixel {},{}", x, y);
+        }
+    }
+
+    #[test]
+    fn colr_font() {
+        let parsed = parse("colrv1.ttf", COLR_FONT);
+        let red = rasterize(FontRasterizerSelection::FreeType, &parsed, 2);
+        assert_square(&red, [0xff, 0, 0, 0xff]);
+        let fg = rasterize(FontRasterizerSelection::FreeType, &parsed, 3);
+        assert_square(&fg, [0, 0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn foreground_is_per_glyph() {
+        let parsed = parse("colrv1.ttf", COLR_FONT);
+        let raster = new_rasterizer(
+            FontRasterizerSelection::FreeType,
+            &parsed,
+            DisplayPixelGeometry::RGB,
+        )
+        .unwrap();
+        assert!(raster.uses_foreground(3));
+        let blue = raster.rasterize_glyph(3, 10., 72, BLUE).unwrap();
+        assert_square(&blue, [0, 0, 0xff, 0xff]);
+        let green = raster
+            .rasterize_glyph(3, 10., 72, SrgbaTuple(0., 1., 0., 1.))
+            .unwrap();
+        assert_square(&green, [0, 0xff, 0, 0xff]);
+    }
+
+    #[test]
+    fn svg_font() {
+        let parsed = parse("svg-glyphs.ttf", SVG_FONT);
+        let green = rasterize(FontRasterizerSelection::FreeType, &parsed, 2);
+        assert_square(&green, [0, 0xff, 0, 0xff]);
+        let fg = rasterize(FontRasterizerSelection::FreeType, &parsed, 3);
+        assert_square(&fg, [0, 0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn swash_matches_freetype() {
+        for (name, data) in [("colrv1.ttf", COLR_FONT), ("svg-glyphs.ttf", SVG_FONT)] {
+            let parsed = parse(name, data);
+            for glyph in 1..=3 {
+                let freetype = rasterize(FontRasterizerSelection::FreeType, &parsed, glyph);
+                let swash = rasterize(FontRasterizerSelection::Swash, &parsed, glyph);
+                assert_eq!(swash.width, freetype.width, "{} glyph {}", name, glyph);
+                assert_eq!(swash.height, freetype.height, "{} glyph {}", name, glyph);
+                assert_eq!(swash.bearing_x.get(), freetype.bearing_x.get());
+                assert_eq!(swash.bearing_y.get(), freetype.bearing_y.get());
+                assert_eq!(swash.has_color, freetype.has_color);
+                assert!(swash.data == freetype.data, "{} glyph {}", name, glyph);
+            }
+        }
+
+        // The two rasterizers antialias outlines differently, so
+        // the results are only expected to be approximately equal
+        let parsed = parse("JetBrainsMono-Regular.ttf", OUTLINE_FONT);
+        let font = ::swash::FontRef::from_index(OUTLINE_FONT, 0).unwrap();
+        let coverage =
+            |glyph: &RasterizedGlyph| glyph.data.chunks(4).map(|p| p[3] as f64).sum::<f64>();
+        for c in ['A', 'g', 'W', '@', '%'] {
+            let glyph = font.charmap().map(c) as u32;
+            assert_ne!(glyph, 0, "{} is missing from the font", c);
+            let render = |selection| {
+                new_rasterizer(selection, &parsed, DisplayPixelGeometry::RGB)
+                    .unwrap()
+                    .rasterize_glyph(glyph, 24., 72, BLUE)
+                    .unwrap()
+            };
+            let freetype = render(FontRasterizerSelection::FreeType);
+            let swash = render(FontRasterizerSelection::Swash);
+            assert!(!swash.has_color && !freetype.has_color, "{}", c);
+            assert!(freetype.width > 0 && freetype.height > 0, "{}", c);
+            assert!(swash.width.abs_diff(freetype.width) <= 2, "{} width", c);
+            assert!(swash.height.abs_diff(freetype.height) <= 2, "{} height", c);
+            assert!(
+                (swash.bearing_x.get() - freetype.bearing_x.get()).abs() <= 2.,
+                "{} bearing_x",
+                c
+            );
+            assert!(
+                (swash.bearing_y.get() - freetype.bearing_y.get()).abs() <= 2.,
+                "{} bearing_y",
+                c
+            );
+            let (swash, freetype) = (coverage(&swash), coverage(&freetype));
+            assert!(
+                (swash - freetype).abs() <= freetype * 0.1,
+                "{}: coverage {} vs {}",
+                c,
+                swash,
+                freetype
+            );
+        }
+    }
+
+    #[test]
+    fn tables_are_shared() {
+        let parsed = parse("svg-glyphs.ttf", SVG_FONT);
+        let lib = crate::ftwrap::Library::new().unwrap();
+        let face = lib.face_from_locator(&parsed.handle).unwrap();
+        let first = VectorColorTables::new(&parsed, |tag| face.load_sfnt_table(tag));
+        assert!(first.has_glyph(2));
+        let second =
+            VectorColorTables::new(&parsed, |_| panic!("tables should not be loaded again"));
+        assert!(Arc::ptr_eq(&first.tables, &second.tables));
     }
 }
@@ -0,0 +1,1331 @@
+//! Renders glyphs from the OpenType SVG table.
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/svg>
+//!
+//! This is not a general purpose SVG implementation; it handles the
+//! static subset of SVG that is used by color fonts: basic shapes and
+//! paths, transforms, `<use>`, group opacity, clip paths, solid fills,
+//! strokes and linear/radial gradients.
+//! Text, filters, masks, patterns, images, CSS stylesheets and animation
+//! are not supported.
+use crate::rasterizer::canvas::*;
+use crate::rasterizer::colr::Reader;
+use crate::rasterizer::RasterizedGlyph;
+use crate::units::PixelLength;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::io::Read;
+use tiny_skia::{FillRule, LineCap, LineJoin, Path, PathBuilder, Rect, Stroke};
+use wezterm_color_types::SrgbaTuple;
+
+/// Guards against cycles introduced via `<use>` and `href`
+const MAX_DEPTH: usize = 32;
+
+pub struct SvgTable {
+    data: Vec<u8>,
+    document_list: usize,
+}
+
+impl SvgTable {
+    pub fn new(data: Vec<u8>) -> Option<Self> {
+        let r = Reader::new(&data);
+        if r.u16(0)? != 0 {
+            return None;
+        }
+        let document_list = r.u32(2)? as usize;
+        if document_list == 0 {
+            return None;
+        }
+        Some(Self {
+            data,
+            document_list,
+        })
+    }
+
+    /// Returns the raw, possibly compressed, document containing `glyph`
+    fn document(&self, glyph: u16) -> Option<&[u8]> {
+        let r = Reader::new(&self.data);
+        let list = self.document_list;
+        let count = r.u16(list)? as usize;
+        // SVGDocumentRecords are sorted by glyph id and do not overlap
+        let (mut lo, mut hi) = (0, count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let rec = list + 2 + mid * 12;
+            let start = r.u16(rec)?;
+            let end = r.u16(rec + 2)?;
+            if glyph < start {
+                hi = mid;
+            } else if glyph > end {
+                lo = mid + 1;
+            } else {
+                let offset = list + r.u32(rec + 4)? as usize;
+                let len = r.u32(rec + 8)? as usize;
+                return self.data.get(offset..offset + len);
+            }
+        }
+        None
+    }
+
+    pub fn has_glyph(&self, glyph: u16) -> bool {
+        self.document(glyph).is_some()
+    }
+
+    /// Render `glyph` into a bitmap.
+    /// `to_pixels` maps the SVG coordinate system (font units, y-down,
+    /// origin at the glyph origin) to pixels (y-down).
+    pub fn render(
+        &self,
+        glyph: u16,
+        units_per_em: f32,
+        to_pixels: &Affine,
+        foreground: Rgba,
+    ) -> Option<RasterizedGlyph> {
+        let doc = self.document(glyph)?;
+        let text = if doc.starts_with(&[0x1f, 0x8b]) {
+            let mut text = String::new();
+            if let Err(err) = flate2::read::GzDecoder::new(doc).read_to_string(&mut text) {
+                log::warn!(
+                    "SVG glyph {}: failed to decompress document: {:#}",
+                    glyph,
+                    err
+                );
+                return None;
+            }
+            text
+        } else {
+            String::from_utf8_lossy(doc).into_owned()
+        };
+        render_document(&text, glyph, units_per_em, to_pixels, foreground)
+    }
+}
+
+/// Render the element for `glyph` from an SVG document
+pub fn render_document(
+    text: &str,
+    glyph: u16,
+    units_per_em: f32,
+    to_pixels: &Affine,
+    foreground: Rgba,
+) -> Option<RasterizedGlyph> {
+    let root = match parse_document(text) {
+        Ok(root) => root,
+        Err(err) => {
+            log::warn!("SVG glyph {}: {:#}", glyph, err);
+            return None;
+        }
+    };
+
+    let mut ids = HashMap::new();
+    root.collect_ids(&mut ids);
+
+    // The viewBox scales the document, but does not translate it;
+    // the origin always corresponds to the glyph origin
+    let mut ctm = *to_pixels;
+    if let Some(view_box) = root.attr("viewBox") {
+        let v = parse_numbers(view_box);
+        if v.len() == 4 && v[2] > 0. && v[3] > 0. {
+            ctm = ctm.then(&Affine::scale(units_per_em / v[2], units_per_em / v[3]));
+        }
+    }
+
+    let glyph_id = format!("glyph{}", glyph);
+    let mut ancestors = vec![];
+    let target = match root.find_path(&glyph_id, &mut ancestors) {
+        Some(target) => target,
+        // A document for a single glyph may omit the id
+        None if ids.is_empty() || !ids.keys().any(|id| id.starts_with("glyph")) => &root,
+        None => return None,
+    };
+
+    let mut renderer = Renderer {
+        ids,
+        foreground,
+        units_per_em,
+        ops: vec![],
+    };
+
+    // Inherit styles and transforms from the ancestors of the glyph element
+    let mut style = Style::new(foreground);
+    for ancestor in &ancestors {
+        style.apply(ancestor, foreground);
+        if let Some(t) = ancestor.attr("transform") {
+            ctm = ctm.then(&parse_transform(t));
+        }
+    }
+    renderer.element(target, &ctm, &style, 0);
+    renderer.finish()
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attrs: HashMap<String, String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> anyhow::Result<Self> {
+        let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+        let mut attrs = HashMap::new();
+        let mut style = None;
+        for attr in start.attributes() {
+            let attr = attr?;
+            let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+            let value = attr.unescape_value()?.into_owned();
+            if key == "style" {
+                style.replace(value);
+            } else {
+                attrs.insert(key, value);
+            }
+        }
+        // Declarations in the style attribute take precedence
+        // over presentation attributes
+        if let Some(style) = style {
+            for decl in style.split(';') {
+                if let Some((key, value)) = decl.split_once(':') {
+                    attrs.insert(key.trim().to_string(), value.trim().to_string());
+                }
+            }
+        }
+        Ok(Self {
+            name,
+            attrs,
+            children: vec![],
+        })
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(|s| s.as_str())
+    }
+
+    fn href(&self) -> Option<&str> {
+        self.attr("xlink:href")
+            .or_else(|| self.attr("href"))
+            .and_then(|h| h.strip_prefix('#'))
+    }
+
+    fn collect_ids<'a>(&'a self, ids: &mut HashMap<&'a str, &'a Element>) {
+        if let Some(id) = self.attr("id") {
+            ids.insert(id, self);
+        }
+        for child in &self.children {
+            child.collect_ids(ids);
+        }
+    }
+
+    /// Find the element with the specified id, recording the chain
+    /// of ancestor elements that lead to it
+    fn find_path<'a>(&'a self, id: &str, ancestors: &mut Vec<&'a Element>) -> Option<&'a Element> {
+        if self.attr("id") == Some(id) {
+            return Some(self);
+        }
+        ancestors.push(self);
+        for child in &self.children {
+            if let Some(found) = child.find_path(id, ancestors) {
+                return Some(found);
+            }
+        }
+        ancestors.pop();
+        None
+    }
+}
+
+fn parse_document(text: &str) -> anyhow::Result<Element> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    reader.trim_text(true);
+    let mut stack: Vec<Element> = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(Element::from_start(&start)?),
+            Event::Empty(start) => {
+                let element = Element::from_start(&start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::End(_) => {
+                let element = stack
+                    .pop()
+                    .ok_or_else(|| anyhow::anyhow!("unbalanced end tag"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Eof => anyhow::bail!("unexpected end of document"),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PaintSpec {
+    Color(Rgba),
+    Url(String),
+}
+
+/// The inherited presentation properties
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Option<PaintSpec>,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: Option<PaintSpec>,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    color: Rgba,
+}
+
+impl Style {
+    fn new(foreground: Rgba) -> Self {
+        Self {
+            fill: Some(PaintSpec::Color([0., 0., 0., 1.])),
+            fill_opacity: 1.,
+            fill_rule: FillRule::Winding,
+            stroke: None,
+            stroke_opacity: 1.,
+            stroke_width: 1.,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.,
+            color: foreground,
+        }
+    }
+
+    fn apply(&mut self, el: &Element, foreground: Rgba) {
+        let get = |name| el.attr(name).filter(|v| *v != "inherit");
+        if let Some(color) = get("color") {
+            if let Some(color) = parse_color(color, foreground) {
+                self.color = color;
+            }
+        }
+        if let Some(fill) = get("fill") {
+            if let Some(fill) = parse_paint(fill, self.color) {
+                self.fill = fill;
+            }
+        }
+        if let Some(stroke) = get("stroke") {
+            if let Some(stroke) = parse_paint(stroke, self.color) {
+                self.stroke = stroke;
+            }
+        }
+        if let Some(v) = get("fill-opacity").and_then(parse_opacity) {
+            self.fill_opacity = v;
+        }
+        if let Some(v) = get("stroke-opacity").and_then(parse_opacity) {
+            self.stroke_opacity = v;
+        }
+        if let Some(v) = get("stroke-width").and_then(parse_number) {
+            self.stroke_width = v;
+        }
+        if let Some(v) = get("stroke-miterlimit").and_then(parse_number) {
+            self.miter_limit = v;
+        }
+        match get("fill-rule") {
+            Some("evenodd") => self.fill_rule = FillRule::EvenOdd,
+            Some("nonzero") => self.fill_rule = FillRule::Winding,
+            _ => {}
+        }
+        match get("stroke-linecap") {
+            Some("butt") => self.line_cap = LineCap::Butt,
+            Some("round") => self.line_cap = LineCap::Round,
+            Some("square") => self.line_cap = LineCap::Square,
+            _ => {}
+        }
+        match get("stroke-linejoin") {
+            Some("miter") => self.line_join = LineJoin::Miter,
+            Some("round") => self.line_join = LineJoin::Round,
+            Some("bevel") => self.line_join = LineJoin::Bevel,
+            _ => {}
+        }
+    }
+}
+
+fn parse_color(value: &str, current_color: Rgba) -> Option<Rgba> {
+    let value = value.trim();
+    if value == "currentColor" {
+        return Some(current_color);
+    }
+    let SrgbaTuple(r, g, b, a) = value.parse().ok()?;
+    Some(premultiply(r, g, b, a))
+}
+
+/// Returns Some(None) for `none`, and None if the value is invalid
+fn parse_paint(value: &str, current_color: Rgba) -> Option<Option<PaintSpec>> {
+    let value = value.trim();
+    if value == "none" {
+        return Some(None);
+    }
+    if let Some(url) = value.strip_prefix("url(") {
+        let id = url
+            .split(')')
+            .next()?
+            .trim()
+            .trim_matches(|c| c == '\'' || c == '"');
+        return Some(Some(PaintSpec::Url(id.trim_start_matches('#').to_string())));
+    }
+    parse_color(value, current_color).map(|c| Some(PaintSpec::Color(c)))
+}
+
+fn parse_opacity(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let v = match value.strip_suffix('%') {
+        Some(pct) => pct.trim().parse::<f32>().ok()? / 100.,
+        None => value.parse::<f32>().ok()?,
+    };
+    Some(v.clamp(0., 1.))
+}
+
+/// Parse a number, ignoring any trailing unit
+fn parse_number(value: &str) -> Option<f32> {
+    NumberParser::new(value).number()
+}
+
+/// Parse a length that may be expressed as a percentage of `reference`
+fn parse_length(value: &str, reference: f32) -> Option<f32> {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(pct) => Some(parse_number(pct)? / 100. * reference),
+        None => parse_number(value),
+    }
+}
+
+fn parse_numbers(value: &str) -> Vec<f32> {
+    let mut parser = NumberParser::new(value);
+    let mut result = vec![];
+    while let Some(n) = parser.number() {
+        result.push(n);
+    }
+    result
+}
+
+struct NumberParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NumberParser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(c) = self.s.get(self.pos) {
+            if c.is_ascii_whitespace() || *c == b',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.s.len()
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.s.get(self.pos).copied()
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |p: &mut Self| {
+            let begin = p.pos;
+            while p.s.get(p.pos).map(|c| c.is_ascii_digit()).unwrap_or(false) {
+                p.pos += 1;
+            }
+            p.pos > begin
+        };
+        if let Some(b'+') | Some(b'-') = self.s.get(self.pos) {
+            self.pos += 1;
+        }
+        let mut any = digits(self);
+        if self.s.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.pos = start;
+            return None;
+        }
+        if let Some(b'e') | Some(b'E') = self.s.get(self.pos) {
+            let mark = self.pos;
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.s.get(self.pos) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                // Something like `em` rather than an exponent
+                self.pos = mark;
+            }
+        }
+        let text = std::str::from_utf8(&self.s[start..self.pos]).ok()?;
+        text.parse().ok()
+    }
+
+    /// Arc flags may be written without any separator, eg: `a1 1 0 00 1 1`
+    fn flag(&mut self) -> Option<bool> {
+        match self.peek()? {
+            b'0' => {
+                self.pos += 1;
+                Some(false)
+            }
+            b'1' => {
+                self.pos += 1;
+                Some(true)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_transform(value: &str) -> Affine {
+    let mut result = Affine::identity();
+    for item in value.split(')') {
+        let (name, args) = match item.split_once('(') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let name = name.trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let a = parse_numbers(args);
+        let arg = |i: usize, default: f32| a.get(i).copied().unwrap_or(default);
+        let t = match name {
+            "matrix" if a.len() == 6 => Affine::new(a[0], a[1], a[2], a[3], a[4], a[5]),
+            "translate" => Affine::translate(arg(0, 0.), arg(1, 0.)),
+            "scale" => {
+                let sx = arg(0, 1.);
+                Affine::scale(sx, arg(1, sx))
+            }
+            "rotate" => Affine::rotate(arg(0, 0.)).around(arg(1, 0.), arg(2, 0.)),
+            "skewX" => Affine::skew(arg(0, 0.), 0.),
+            "skewY" => Affine::skew(0., arg(0, 0.)),
+            _ => {
+                log::debug!("SVG: ignoring invalid transform {}({})", name, args);
+                continue;
+            }
+        };
+        result = result.then(&t);
+    }
+    result
+}
+
+/// Convert an elliptical arc into cubic bezier segments
+/// following the SVG implementation notes, section F.6.
+fn arc_to(
+    pb: &mut PathBuilder,
+    from: (f32, f32),
+    radii: (f32, f32),
+    x_axis_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: (f32, f32),
+) {
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if rx == 0. || ry == 0. || from == to {
+        pb.line_to(to.0, to.1);
+        return;
+    }
+    let (sin_phi, cos_phi) = x_axis_rotation.to_radians().sin_cos();
+    let dx2 = (from.0 - to.0) / 2.;
+    let dy2 = (from.1 - to.1) / 2.;
+    let x1p = cos_phi * dx2 + sin_phi * dy2;
+    let y1p = -sin_phi * dx2 + cos_phi * dy2;
+
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+    let den = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+    let mut coef = if den == 0. {
+        0.
+    } else {
+        (num / den).max(0.).sqrt()
+    };
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cxp = coef * rx * y1p / ry;
+    let cyp = -coef * ry * x1p / rx;
+    let cx = cos_phi * cxp - sin_phi * cyp + (from.0 + to.0) / 2.;
+    let cy = sin_phi * cxp + cos_phi * cyp + (from.1 + to.1) / 2.;
+
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let ux = (x1p - cxp) / rx;
+    let uy = (y1p - cyp) / ry;
+    let vx = (-x1p - cxp) / rx;
+    let vy = (-y1p - cyp) / ry;
+    let theta1 = angle(1., 0., ux, uy);
+    let mut delta = angle(ux, uy, vx, vy);
+    let tau = std::f32::consts::PI * 2.;
+    if !sweep && delta > 0. {
+        delta -= tau;
+    } else if sweep && delta < 0. {
+        delta += tau;
+    }
+
+    let segments = (delta.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.);
+    let step = delta / segments;
+    let t = 4. / 3. * (step / 4.).tan();
+    let point = |a: f32| {
+        let (sin, cos) = a.sin_cos();
+        (
+            cx + rx * cos * cos_phi - ry * sin * sin_phi,
+            cy + rx * cos * sin_phi + ry * sin * cos_phi,
+        )
+    };
+    let deriv = |a: f32| {
+        let (sin, cos) = a.sin_cos();
+        (
+            -rx * sin * cos_phi - ry * cos * sin_phi,
+            -rx * sin * sin_phi + ry * cos * cos_phi,
+        )
+    };
+    for i in 0..segments as usize {
+        let a1 = theta1 + step * i as f32;
+        let a2 = a1 + step;
+        let p1 = point(a1);
+        let p2 = if i + 1 == segments as usize {
+            to
+        } else {
+            point(a2)
+        };
+        let d1 = deriv(a1);
+        let d2 = deriv(a2);
+        pb.cubic_to(
+            p1.0 + t * d1.0,
+            p1.1 + t * d1.1,
+            p2.0 - t * d2.0,
+            p2.1 - t * d2.1,
+            p2.0,
+            p2.1,
+        );
+    }
+}
+
+/// Parse SVG path data.
+/// Per the SVG error handling rules, the path is rendered up to
+/// the point where an error was encountered.
+fn parse_path_data(d: &str) -> Option<Path> {
+    let mut p = NumberParser::new(d);
+    let mut pb = PathBuilder::new();
+    let mut cur = (0f32, 0f32);
+    let mut start = (0f32, 0f32);
+    // The control point from the previous curve command,
+    // used to compute the reflected control point of S/T
+    let mut last_cubic: Option<(f32, f32)> = None;
+    let mut last_quad: Option<(f32, f32)> = None;
+    let mut cmd = b'M';
+
+    'outer: while !p.at_end() {
+        if let Some(c) = p.peek() {
+            if c.is_ascii_alphabetic() {
+                cmd = c;
+                p.pos += 1;
+            } else if cmd == b'M' {
+                cmd = b'L';
+            } else if cmd == b'm' {
+                cmd = b'l';
+            }
+        }
+        let rel = cmd.is_ascii_lowercase();
+        let (ox, oy) = if rel { cur } else { (0., 0.) };
+        macro_rules! num {
+            () => {
+                match p.number() {
+                    Some(n) => n,
+                    None => break 'outer,
+                }
+            };
+        }
+        let mut cubic_ctrl = None;
+        let mut quad_ctrl = None;
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                cur = (ox + num!(), oy + num!());
+                start = cur;
+                pb.move_to(cur.0, cur.1);
+            }
+            b'L' => {
+                cur = (ox + num!(), oy + num!());
+                pb.line_to(cur.0, cur.1);
+            }
+            b'H' => {
+                cur.0 = ox + num!();
+                pb.line_to(cur.0, cur.1);
+            }
+            b'V' => {
+                cur.1 = oy + num!();
+                pb.line_to(cur.0, cur.1);
+            }
+            b'C' => {
+                let c1 = (ox + num!(), oy + num!());
+                let c2 = (ox + num!(), oy + num!());
+                cur = (ox + num!(), oy + num!());
+                pb.cubic_to(c1.0, c1.1, c2.0, c2.1, cur.0, cur.1);
+                cubic_ctrl = Some(c2);
+            }
+            b'S' => {
+                let c1 = match last_cubic {
+                    Some(c) => (2. * cur.0 - c.0, 2. * cur.1 - c.1),
+                    None => cur,
+                };
+                let c2 = (ox + num!(), oy + num!());
+                cur = (ox + num!(), oy + num!());
+                pb.cubic_to(c1.0, c1.1, c2.0, c2.1, cur.0, cur.1);
+                cubic_ctrl = Some(c2);
+            }
+            b'Q' => {
+                let c = (ox + num!(), oy + num!());
+                cur = (ox + num!(), oy + num!());
+                pb.quad_to(c.0, c.1, cur.0, cur.1);
+                quad_ctrl = Some(c);
+            }
+            b'T' => {
+                let c = match last_quad {
+                    Some(c) => (2. * cur.0 - c.0, 2. * cur.1 - c.1),
+                    None => cur,
+                };
+                cur = (ox + num!(), oy + num!());
+                pb.quad_to(c.0, c.1, cur.0, cur.1);
+                quad_ctrl = Some(c);
+            }
+            b'A' => {
+                let radii = (num!(), num!());
+                let rotation = num!();
+                let large = match p.flag() {
+                    Some(f) => f,
+                    None => break,
+                };
+                let sweep = match p.flag() {
+                    Some(f) => f,
+                    None => break,
+                };
+                let to = (ox + num!(), oy + num!());
+                arc_to(&mut pb, cur, radii, rotation, large, sweep, to);
+                cur = to;
+            }
+            b'Z' => {
+                pb.close();
+                cur = start;
+            }
+            _ => break,
+        }
+        last_cubic = cubic_ctrl;
+        last_quad = quad_ctrl;
+    }
+    pb.finish()
+}
+
+fn parse_points(value: &str, close: bool) -> Option<Path> {
+    let points = parse_numbers(value);
+    let mut pb = PathBuilder::new();
+    for (i, pair) in points.chunks_exact(2).enumerate() {
+        if i == 0 {
+            pb.move_to(pair[0], pair[1]);
+        } else {
+            pb.line_to(pair[0], pair[1]);
+        }
+    }
+    if close {
+        pb.close();
+    }
+    pb.finish()
+}
+
+fn rect_path(x: f32, y: f32, w: f32, h: f32, rx: f32, ry: f32) -> Option<Path> {
+    if w <= 0. || h <= 0. {
+        return None;
+    }
+    if rx <= 0. || ry <= 0. {
+        return Some(PathBuilder::from_rect(Rect::from_xywh(x, y, w, h)?));
+    }
+    let rx = rx.min(w / 2.);
+    let ry = ry.min(h / 2.);
+    // Approximate the quarter ellipse corners with cubics
+    const KAPPA: f32 = 0.552_284_8;
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let (r, b) = (x + w, y + h);
+    let mut pb = PathBuilder::new();
+    pb.move_to(x + rx, y);
+    pb.line_to(r - rx, y);
+    pb.cubic_to(r - rx + kx, y, r, y + ry - ky, r, y + ry);
+    pb.line_to(r, b - ry);
+    pb.cubic_to(r, b - ry + ky, r - rx + kx, b, r - rx, b);
+    pb.line_to(x + rx, b);
+    pb.cubic_to(x + rx - kx, b, x, b - ry + ky, x, b - ry);
+    pb.line_to(x, y + ry);
+    pb.cubic_to(x, y + ry - ky, x + rx - kx, y, x + rx, y);
+    pb.close();
+    pb.finish()
+}
+
+/// A paint resolved into pixel space (relative to the glyph origin)
+enum OpPaint {
+    Solid(Rgba),
+    Gradient {
+        geometry: GradientGeometry,
+        stops: Vec<ColorStop>,
+        extend: Extend,
+        transform: Affine,
+    },
+}
+
+/// The document is flattened into a display list in pixel space
+/// so that the bounds can be computed before allocating the canvas
+enum Op {
+    Fill {
+        path: Path,
+        fill_rule: FillRule,
+        paint: OpPaint,
+    },
+    PushLayer,
+    PopLayer {
+        opacity: f32,
+        clip: Vec<(Path, FillRule)>,
+    },
+}
+
+struct Renderer<'a> {
+    ids: HashMap<&'a str, &'a Element>,
+    foreground: Rgba,
+    units_per_em: f32,
+    ops: Vec<Op>,
+}
+
+impl<'a> Renderer<'a> {
+    fn element(&mut self, el: &'a Element, ctm: &Affine, style: &Style, depth: usize) {
+        if depth > MAX_DEPTH {
+            log::warn!("SVG: document nesting is too deep; ignoring remainder");
+            return;
+        }
+        if el.attr("display") == Some("none") {
+            return;
+        }
+        let ctm = match el.attr("transform") {
+            Some(t) => ctm.then(&parse_transform(t)),
+            None => *ctm,
+        };
+        let mut style = style.clone();
+        style.apply(el, self.foreground);
+
+        let opacity = el.attr("opacity").and_then(parse_opacity).unwrap_or(1.);
+        let clip = el
+            .attr("clip-path")
+            .and_then(|v| parse_paint(v, self.foreground))
+            .and_then(|p| match p {
+                Some(PaintSpec::Url(id)) => Some(self.clip_path(&id, &ctm, depth)),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let layered = opacity < 1. || !clip.is_empty();
+        if layered {
+            self.ops.push(Op::PushLayer);
+        }
+
+        let number = |name: &str| el.attr(name).and_then(parse_number).unwrap_or(0.);
+        let path = match el.name.as_str() {
+            "svg" | "g" | "a" | "switch" => {
+                for child in &el.children {
+                    self.element(child, &ctm, &style, depth + 1);
+                }
+                None
+            }
+            "use" => {
+                if let Some(target) = el.href().and_then(|id| self.ids.get(id).copied()) {
+                    let ctm = ctm.then(&Affine::translate(number("x"), number("y")));
+                    self.element(target, &ctm, &style, depth + 1);
+                }
+                None
+            }
+            "path" => el.attr("d").and_then(parse_path_data),
+            "rect" => {
+                let rx = el.attr("rx").and_then(parse_number);
+                let ry = el.attr("ry").and_then(parse_number);
+                rect_path(
+                    number("x"),
+                    number("y"),
+                    number("width"),
+                    number("height"),
+                    rx.or(ry).unwrap_or(0.),
+                    ry.or(rx).unwrap_or(0.),
+                )
+            }
+            "circle" => {
+                let r = number("r");
+                PathBuilder::from_circle(number("cx"), number("cy"), r)
+            }
+            "ellipse" => {
+                let (cx, cy) = (number("cx"), number("cy"));
+                let (rx, ry) = (number("rx"), number("ry"));
+                Rect::from_ltrb(cx - rx, cy - ry, cx + rx, cy + ry).and_then(PathBuilder::from_oval)
+            }
+            "line" => {
+                let mut pb = PathBuilder::new();
+                pb.move_to(number("x1"), number("y1"));
+                pb.line_to(number("x2"), number("y2"));
+                pb.finish()
+            }
+            "polyline" => el.attr("points").and_then(|p| parse_points(p, false)),
+            "polygon" => el.attr("points").and_then(|p| parse_points(p, true)),
+            // defs, gradients, clipPath, metadata and anything unsupported
+            _ => None,
+        };
+        if let Some(path) = path {
+            self.shape(&path, &ctm, &style);
+        }
+
+        if layered {
+            self.ops.push(Op::PopLayer { opacity, clip });
+        }
+    }
+
+    /// Collects the shapes of a clipPath element in pixel space
+    fn clip_path(&self, id: &str, ctm: &Affine, depth: usize) -> Vec<(Path, FillRule)> {
+        let mut result = vec![];
+        let clip = match self.ids.get(id) {
+            Some(el) if el.name == "clipPath" => *el,
+            _ => return result,
+        };
+        let ctm = match clip.attr("transform") {
+            Some(t) => ctm.then(&parse_transform(t)),
+            None => *ctm,
+        };
+        // Render the clip shapes into a scratch renderer and
+        // take only their geometry
+        let mut scratch = Renderer {
+            ids: self.ids.clone(),
+            foreground: self.foreground,
+            units_per_em: self.units_per_em,
+            ops: vec![],
+        };
+        let mut style = Style::new(self.foreground);
+        style.stroke = None;
+        for child in &clip.children {
+            scratch.element(child, &ctm, &style, depth + 1);
+        }
+        for op in scratch.ops {
+            if let Op::Fill {
+                path, fill_rule, ..
+            } = op
+            {
+                result.push((path, fill_rule));
+            }
+        }
+        result
+    }
+
+    fn shape(&mut self, path: &Path, ctm: &Affine, style: &Style) {
+        let bbox = BBox::of_path(path);
+        if let Some(spec) = &style.fill {
+            if let Some(paint) = self.resolve_paint(spec, style.fill_opacity, &bbox, ctm) {
+                if let Some(path) = path.clone().transform(ctm.to_skia()) {
+                    self.ops.push(Op::Fill {
+                        path,
+                        fill_rule: style.fill_rule,
+                        paint,
+                    });
+                }
+            }
+        }
+        if let Some(spec) = &style.stroke {
+            if style.stroke_width <= 0. {
+                return;
+            }
+            let stroke = Stroke {
+                width: style.stroke_width,
+                miter_limit: style.miter_limit,
+                line_cap: style.line_cap,
+                line_join: style.line_join,
+                dash: None,
+            };
+            let res_scale = (ctm.xx * ctm.yy - ctm.xy * ctm.yx).abs().sqrt().max(0.001);
+            let outline = match path.stroke(&stroke, res_scale) {
+                Some(outline) => outline,
+                None => return,
+            };
+            if let Some(paint) = self.resolve_paint(spec, style.stroke_opacity, &bbox, ctm) {
+                if let Some(path) = outline.transform(ctm.to_skia()) {
+                    self.ops.push(Op::Fill {
+                        path,
+                        fill_rule: FillRule::Winding,
+                        paint,
+                    });
+                }
+            }
+        }
+    }
+
+    fn resolve_paint(
+        &self,
+        spec: &PaintSpec,
+        opacity: f32,
+        bbox: &BBox,
+        ctm: &Affine,
+    ) -> Option<OpPaint> {
+        let scale = |c: Rgba| {
+            [
+                c[0] * opacity,
+                c[1] * opacity,
+                c[2] * opacity,
+                c[3] * opacity,
+            ]
+        };
+        let id = match spec {
+            PaintSpec::Color(c) => return Some(OpPaint::Solid(scale(*c))),
+            PaintSpec::Url(id) => id,
+        };
+
+        // Gradients may inherit attributes and stops via href
+        let mut chain = vec![];
+        let mut next = self.ids.get(id.as_str()).copied();
+        while let Some(el) = next {
+            if chain.len() > MAX_DEPTH {
+                break;
+            }
+            chain.push(el);
+            next = el.href().and_then(|id| self.ids.get(id).copied());
+        }
+        let el = chain.first()?;
+        let attr = |name: &str| chain.iter().find_map(|e| e.attr(name));
+
+        let mut stops = vec![];
+        if let Some(with_stops) = chain
+            .iter()
+            .find(|e| e.children.iter().any(|c| c.name == "stop"))
+        {
+            let mut last_offset = 0f32;
+            for stop in with_stops.children.iter().filter(|c| c.name == "stop") {
+                let offset = stop
+                    .attr("offset")
+                    .and_then(|v| parse_length(v, 1.))
+                    .unwrap_or(0.)
+                    .clamp(0., 1.)
+                    .max(last_offset);
+                last_offset = offset;
+                let current = stop
+                    .attr("color")
+                    .and_then(|c| parse_color(c, self.foreground))
+                    .unwrap_or(self.foreground);
+                let color = stop
+                    .attr("stop-color")
+                    .and_then(|c| parse_color(c, current))
+                    .unwrap_or([0., 0., 0., 1.]);
+                let alpha = stop
+                    .attr("stop-opacity")
+                    .and_then(parse_opacity)
+                    .unwrap_or(1.)
+                    * opacity;
+                stops.push(ColorStop {
+                    offset,
+                    color: [
+                        color[0] * alpha,
+                        color[1] * alpha,
+                        color[2] * alpha,
+                        color[3] * alpha,
+                    ],
+                });
+            }
+        }
+        match stops.len() {
+            0 => return None,
+            1 => return Some(OpPaint::Solid(stops[0].color)),
+            _ => {}
+        }
+
+        let bbox_units = attr("gradientUnits") != Some("userSpaceOnUse");
+        let mut transform = *ctm;
+        let reference = if bbox_units {
+            if bbox.is_empty() {
+                return None;
+            }
+            transform = transform.then(&Affine::new(
+                bbox.max_x - bbox.min_x,
+                0.,
+                0.,
+                bbox.max_y - bbox.min_y,
+                bbox.min_x,
+                bbox.min_y,
+            ));
+            1.
+        } else {
+            self.units_per_em
+        };
+        if let Some(t) = attr("gradientTransform") {
+            transform = transform.then(&parse_transform(t));
+        }
+        let extend = match attr("spreadMethod") {
+            Some("reflect") => Extend::Reflect,
+            Some("repeat") => Extend::Repeat,
+            _ => Extend::Pad,
+        };
+        let len = |name: &str, default: &str| {
+            attr(name)
+                .and_then(|v| parse_length(v, reference))
+                .or_else(|| parse_length(default, reference))
+                .unwrap_or(0.)
+        };
+
+        let geometry = match el.name.as_str() {
+            "linearGradient" => GradientGeometry::Linear {
+                p0: (len("x1", "0%"), len("y1", "0%")),
+                p1: (len("x2", "100%"), len("y2", "0%")),
+            },
+            "radialGradient" => {
+                let (cx, cy) = (len("cx", "50%"), len("cy", "50%"));
+                GradientGeometry::Radial {
+                    c0: (
+                        attr("fx")
+                            .and_then(|v| parse_length(v, reference))
+                            .unwrap_or(cx),
+                        attr("fy")
+                            .and_then(|v| parse_length(v, reference))
+                            .unwrap_or(cy),
+                    ),
+                    r0: len("fr", "0%"),
+                    c1: (cx, cy),
+                    r1: len("r", "50%"),
+                }
+            }
+            _ => return None,
+        };
+
+        Some(OpPaint::Gradient {
+            geometry,
+            stops,
+            extend,
+            transform,
+        })
+    }
+
+    fn finish(self) -> Option<RasterizedGlyph> {
+        let mut bounds = BBox::empty();
+        for op in &self.ops {
+            if let Op::Fill { path, .. } = op {
+                bounds.union(&BBox::of_path(path));
+            }
+        }
+        let placement = Placement::new(&bounds, &Affine::identity())?;
+        let to_canvas = placement.transform;
+        let (width, height) = (placement.width, placement.height);
+
+        let mut stack = vec![Canvas::new(width, height)];
+        for op in self.ops {
+            match op {
+                Op::Fill {
+                    path,
+                    fill_rule,
+                    paint,
+                } => {
+                    let mask = match Mask::from_path(&path, fill_rule, &to_canvas, width, height) {
+                        Some(mask) => mask,
+                        None => continue,
+                    };
+                    let fill = match paint {
+                        OpPaint::Solid(color) => Fill::Solid(color),
+                        OpPaint::Gradient {
+                            geometry,
+                            stops,
+                            extend,
+                            transform,
+                        } => match Gradient::new(
+                            geometry,
+                            stops,
+                            extend,
+                            &to_canvas.then(&transform),
+                        ) {
+                            Some(g) => Fill::Gradient(g),
+                            None => continue,
+                        },
+                    };
+                    if let Some(canvas) = stack.last_mut() {
+                        canvas.fill(Some(&mask), &fill);
+                    }
+                }
+                Op::PushLayer => stack.push(Canvas::new(width, height)),
+                Op::PopLayer { opacity, clip } => {
+                    let layer = stack.pop()?;
+                    let mask = if clip.is_empty() {
+                        None
+                    } else {
+                        let paths: Vec<(&Path, FillRule)> =
+                            clip.iter().map(|(p, r)| (p, *r)).collect();
+                        Mask::from_paths(&paths, &to_canvas, width, height)
+                    };
+                    stack.last_mut()?.composite(
+                        &layer,
+                        CompositeMode::SrcOver,
+                        mask.as_ref(),
+                        opacity,
+                    );
+                }
+            }
+        }
+
+        let canvas = stack.pop()?;
+        Some(RasterizedGlyph {
+            data: canvas.into_rgba8(),
+            height,
+            width,
+            bearing_x: PixelLength::new(placement.left as f64),
+            bearing_y: PixelLength::new(placement.top as f64),
+            has_color: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 1000 units per em rendered at 100 pixels per em
+    fn render(doc: &str, glyph: u16) -> RasterizedGlyph {
+        render_document(
+            doc,
+            glyph,
+            1000.,
+            &Affine::scale(0.1, 0.1),
+            [1., 1., 1., 1.],
+        )
+        .unwrap()
+    }
+
+    fn pixel(glyph: &RasterizedGlyph, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * glyph.width + x) * 4;
+        [
+            glyph.data[i],
+            glyph.data[i + 1],
+            glyph.data[i + 2],
+            glyph.data[i + 3],
+        ]
+    }
+
+    #[test]
+    fn path_data() {
+        let path = parse_path_data("M10-20l30,0v40H10z").unwrap();
+        let b = BBox::of_path(&path);
+        assert_eq!(b, BBox::new(10., -20., 40., 20.));
+
+        // Arc flags without separators
+        let path = parse_path_data("M0 0a50 50 0 1050 50").unwrap();
+        let b = BBox::of_path(&path);
+        assert!(b.max_x > 50. && b.max_y > 50., "{:?}", b);
+    }
+
+    #[test]
+    fn transforms() {
+        let t = parse_transform("translate(10 20) scale(2)");
+        assert_eq!(t.apply(1., 1.), (12., 22.));
+        let t = parse_transform("matrix(1,0,0,1,5,6)");
+        assert_eq!(t.apply(0., 0.), (5., 6.));
+    }
+
+    #[test]
+    fn selects_glyph_element() {
+        let doc = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <g id="glyph3"><rect x="0" y="-500" width="500" height="500" fill="#ff0000"/></g>
+            <g id="glyph4"><rect x="0" y="-1000" width="1000" height="1000" fill="blue"/></g>
+        </svg>"##;
+        let glyph = render(doc, 3);
+        assert_eq!(glyph.width, 50);
+        assert_eq!(glyph.height, 50);
+        assert_eq!(glyph.bearing_x.get(), 0.);
+        assert_eq!(glyph.bearing_y.get(), 50.);
+        assert!(glyph.has_color);
+        assert_eq!(pixel(&glyph, 25, 25), [0xff, 0, 0, 0xff]);
+
+        let glyph = render(doc, 4);
+        assert_eq!(glyph.width, 100);
+        assert_eq!(pixel(&glyph, 50, 50), [0, 0, 0xff, 0xff]);
+
+        assert!(render_document(doc, 5, 1000., &Affine::identity(), [1.; 4]).is_none());
+    }
+
+    #[test]
+    fn gradient_and_opacity() {
+        let doc = r##"<svg xmlns="http://www.w3.org/2000/svg"
+                           xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs>
+              <linearGradient id="stops">
+                <stop offset="0" stop-color="#ff0000"/>
+                <stop offset="100%" style="stop-color:#0000ff"/>
+              </linearGradient>
+              <linearGradient id="g" xlink:href="#stops"/>
+            </defs>
+            <g id="glyph1" opacity="0.5">
+              <rect y="-1000" width="1000" height="1000" fill="url(#g)"/>
+            </g>
+        </svg>"##;
+        let glyph = render(doc, 1);
+        let left = pixel(&glyph, 0, 50);
+        let right = pixel(&glyph, 99, 50);
+        assert!(
+            left[0] > 0x70 && left[2] < 0x10 && left[3] == 0x80,
+            "{:?}",
+            left
+        );
+        assert!(
+            right[2] > 0x70 && right[0] < 0x10 && right[3] == 0x80,
+            "{:?}",
+            right
+        );
+    }
+
+    #[test]
+    fn current_color_and_clip() {
+        let doc = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <clipPath id="c"><rect y="-1000" width="500" height="1000"/></clipPath>
+            <g id="glyph1" clip-path="url(#c)">
+              <circle cx="500" cy="-500" r="500" fill="currentColor"/>
+            </g>
+        </svg>"##;
+        let glyph = render(doc, 1);
+        assert_eq!(pixel(&glyph, 25, 50), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(pixel(&glyph, 75, 50), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn compressed_table() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let doc = br##"<svg xmlns="http://www.w3.org/2000/svg"><path id="glyph7" d="M0 0h100v-100h-100z"/></svg>"##;
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(doc).unwrap();
+        let doc = encoder.finish().unwrap();
+
+        let mut table = vec![];
+        table.extend_from_slice(&0u16.to_be_bytes()); // version
+        table.extend_from_slice(&10u32.to_be_bytes()); // svgDocumentListOffset
+        table.extend_from_slice(&0u32.to_be_bytes()); // reserved
+        table.extend_from_slice(&1u16.to_be_bytes()); // numEntries
+        table.extend_from_slice(&5u16.to_be_bytes()); // startGlyphID
+        table.extend_from_slice(&9u16.to_be_bytes()); // endGlyphID
+        table.extend_from_slice(&14u32.to_be_bytes()); // svgDocOffset
+        table.extend_from_slice(&(doc.len() as u32).to_be_bytes());
+        table.extend_from_slice(&doc);
+
+        let svg = SvgTable::new(table).unwrap();
+        assert!(svg.has_glyph(7));
+        assert!(!svg.has_glyph(4));
+        assert!(!svg.has_glyph(10));
+        let glyph = svg
+            .render(7, 1000., &Affine::scale(0.1, 0.1), [1.; 4])
+            .unwrap();
+        assert_eq!(glyph.width, 10);
+        assert_eq!(glyph.height, 10);
+        assert_eq!(pixel(&glyph, 5, 5), [0, 0, 0, 0xff]);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::locator::FontDataSource;
+use crate::parser::ParsedFont;
+use crate::rasterizer::colr::GlyphOutlines;
+use crate::rasterizer::{FontRasterizer, VectorColorTables};
+use crate::units::*;
+use crate::RasterizedGlyph;
+use ::swash::scale::image::{Content, Image};
+use ::swash::scale::{Render, ScaleContext, Scaler, Source, StrikeWith};
+use ::swash::zeno::{Angle, Command, Format, PathData, Transform};
+use ::swash::{tag_from_bytes, tag_from_str_lossy, FontRef, Tag};
+use anyhow::Context;
+use config::{DisplayPixelGeometry, FreeTypeLoadFlags, FreeTypeLoadTarget};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use tiny_skia::{Path, PathBuilder};
+use wezterm_color_types::{linear_u8_to_srgb8, SrgbaTuple};
+
+lazy_static::lazy_static! {
+    /// The contents of font files that are currently in use by
+    /// a rasterizer.  A font is typically rasterized at several
+    /// sizes and in several styles, and we only want to hold one
+    /// copy of the file in memory.
+    static ref FILE_DATA: Mutex<HashMap<PathBuf, Weak<Box<[u8]>>>> = Mutex::new(HashMap::new());
+}
+
+/// The font data, which is shared rather than copied
+enum FontData {
+    Static(&'static [u8]),
+    Shared(Arc<Box<[u8]>>),
+}
+
+impl FontData {
+    fn load(source: &FontDataSource) -> anyhow::Result<Self> {
+        match source {
+            FontDataSource::BuiltIn { data, .. } => Ok(Self::Static(data)),
+            FontDataSource::Memory { data, .. } => Ok(Self::Shared(Arc::clone(data))),
+            FontDataSource::OnDisk(path) => {
+                let mut files = FILE_DATA.lock().unwrap();
+                if let Some(data) = files.get(path).and_then(Weak::upgrade) {
+                    return Ok(Self::Shared(data));
+                }
+                let data = Arc::new(std::fs::read(path)?.into_boxed_slice());
+                files.retain(|_, data| data.strong_count() > 0);
+                files.insert(path.clone(), Arc::downgrade(&data));
+                Ok(Self::Shared(data))
+            }
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Static(data) => data,
+            Self::Shared(data) => data,
+        }
+    }
+}
+
+/// A rasterizer that uses the pure-Rust swash crate to render glyphs,
+/// rather than FreeType.
+pub struct SwashRasterizer {
+    data: FontData,
+    index: usize,
+    context: RefCell<ScaleContext>,
+    variations: Vec<(Tag, f32)>,
+    hint: bool,
+    use_bitmaps: bool,
+    subpixel: bool,
+    synthesize_bold: bool,
+    synthesize_italic: bool,
+    display_pixel_geometry: DisplayPixelGeometry,
+    scale: f64,
+    vector_color: VectorColorTables,
+}
+
+impl FontRasterizer for SwashRasterizer {
+    fn rasterize_glyph(
+        &self,
+        glyph_pos: u32,
+        size: f64,
+        dpi: u32,
+        foreground: SrgbaTuple,
+    ) -> anyhow::Result<RasterizedGlyph> {
+        let font = self.font()?;
+        let ppem = (size * self.scale * dpi as f64 / 72.) as f32;
+        let mut context = self.context.borrow_mut();
+
+        if self.vector_color.has_glyph(glyph_pos) {
+            let units_per_em = font.metrics(&[]).units_per_em as f32;
+            let mut outlines = ScalerOutlines {
+                scaler: context
+                    .builder(font)
+                    .variations(self.variations.iter().copied())
+                    .build(),
+            };
+            if let Some(glyph) = self.vector_color.rasterize(
+                glyph_pos,
+                ppem / units_per_em,
+                units_per_em,
+                self.synthesize_italic,
+                foreground,
+                &mut outlines,
+            ) {
+                return Ok(glyph);
+            }
+        }
+
+        let glyph_id = u16::try_from(glyph_pos)
+            .with_context(|| format!("glyph index {} is out of range", glyph_pos))?;
+
+        let mut scaler = context
+            .builder(font)
+            .size(ppem)
+            .hint(self.hint)
+            .variations(self.variations.iter().copied())
+            .build();
+
+        let mut sources = vec![Source::ColorOutline(0)];
+        if self.use_bitmaps {
+            sources.push(Source::ColorBitmap(StrikeWith::BestFit));
+        }
+        sources.push(Source::Outline);
+
+        let mut render = Render::new(&sources);
+        render.format(if self.subpixel {
+            Format::Subpixel
+        } else {
+            Format::Alpha
+        });
+        if self.synthesize_italic {
+            // Matches the 0.2 skew factor used by the FreeType rasterizer
+            render.transform(Some(Transform::skew(
+                Angle::from_radians(0.2f32.atan()),
+                Angle::from_radians(0.),
+            )));
+        }
+        if self.synthesize_bold {
+            render.embolden(ppem / 48.);
+        }
+
+        let image = render
+            .render(&mut scaler, glyph_id)
+            .with_context(|| format!("swash failed to render glyph {}", glyph_pos))?;
+
+        let glyph = match image.content {
+            Content::Mask => self.rasterize_mask(&image),
+            Content::SubpixelMask => self.rasterize_subpixel(&image),
+            Content::Color => self.rasterize_color(&image),
+        };
+
+        if self.synthesize_italic && matches!(image.source, Source::ColorBitmap(_)) {
+            // Bitmap strikes are not affected by the render transform
+            Ok(glyph.skew())
+        } else {
+            Ok(glyph)
+        }
+    }
+
+    fn uses_foreground(&self, glyph_pos: u32) -> bool {
+        self.vector_color.has_glyph(glyph_pos)
+    }
+}
+
+impl SwashRasterizer {
+    pub fn from_locator(
+        parsed: &ParsedFont,
+        display_pixel_geometry: DisplayPixelGeometry,
+    ) -> anyhow::Result<Self> {
+        log::trace!("Rasterizier wants {:?}", parsed);
+        let data = FontData::load(&parsed.handle.source)?;
+        let index = parsed.handle.index as usize;
+        let font = FontRef::from_index(data.bytes(), index).with_context(|| {
+            format!(
+                "swash failed to parse {}",
+                parsed.handle.diagnostic_string()
+            )
+        })?;
+
+        let mut variations = vec![];
+        if parsed.handle.variation > 0 {
+            // Named instances are numbered from 1, with 0 meaning the default instance
+            if let Some(instance) = font.instances().nth(parsed.handle.variation as usize - 1) {
+                variations.extend(
+                    font.variations()
+                        .map(|axis| axis.tag())
+                        .zip(instance.values()),
+                );
+            }
+        }
+        for variation in &parsed.variations {
+            let tag = tag_from_str_lossy(&variation.tag);
+            let value = variation.value.into_inner() as f32;
+            match variations.iter_mut().find(|(t, _)| *t == tag) {
+                Some(existing) => existing.1 = value,
+                None => variations.push((tag, value)),
+            }
+        }
+
+        let config = config::configuration();
+        let load_flags = parsed
+            .freetype_load_flags
+            .unwrap_or(config.freetype_load_flags);
+        let render_target = parsed.freetype_render_target.unwrap_or(
+            config
+                .freetype_render_target
+                .unwrap_or(config.freetype_load_target),
+        );
+
+        let vector_color = VectorColorTables::new(parsed, |tag| {
+            font.table(tag_from_bytes(tag)).map(|data| data.to_vec())
+        });
+
+        Ok(Self {
+            data,
+            index,
+            context: RefCell::new(ScaleContext::new()),
+            variations,
+            hint: !load_flags.contains(FreeTypeLoadFlags::NO_HINTING),
+            use_bitmaps: !load_flags.contains(FreeTypeLoadFlags::NO_BITMAP),
+            subpixel: render_target == FreeTypeLoadTarget::HorizontalLcd,
+            synthesize_bold: parsed.synthesize_bold,
+            synthesize_italic: parsed.synthesize_italic,
+            display_pixel_geometry,
+            scale: parsed.scale.unwrap_or(1.),
+            vector_color,
+        })
+    }
+
+    fn font(&self) -> anyhow::Result<FontRef<'_>> {
+        FontRef::from_index(self.data.bytes(), self.index).context("swash failed to parse font")
+    }
+
+    fn make_glyph(image: &Image, data: Vec<u8>, has_color: bool) -> RasterizedGlyph {
+        RasterizedGlyph {
+            data,
+            height: image.placement.height as usize,
+            width: image.placement.width as usize,
+            bearing_x: PixelLength::new(image.placement.left as f64),
+            bearing_y: PixelLength::new(image.placement.top as f64),
+            has_color,
+        }
+    }
+
+    fn rasterize_mask(&self, image: &Image) -> RasterizedGlyph {
+        let mut rgba = Vec::with_capacity(image.data.len() * 4);
+        for &linear_gray in &image.data {
+            // Texture is SRGBA, which in OpenGL means
+            // that the RGB values are gamma adjusted
+            // non-linear values, but the A value is
+            // linear!
+            let gray = linear_u8_to_srgb8(linear_gray);
+            rgba.extend_from_slice(&[gray, gray, gray, linear_gray]);
+        }
+        Self::make_glyph(image, rgba, false)
+    }
+
+    fn rasterize_subpixel(&self, image: &Image) -> RasterizedGlyph {
+        let mut rgba = Vec::with_capacity(image.data.len());
+        for pixel in image.data.chunks_exact(4) {
+            let (red, green, blue) = (pixel[0], pixel[1], pixel[2]);
+            let linear_alpha = red.max(green).max(blue);
+
+            let red = linear_u8_to_srgb8(red);
+            let green = linear_u8_to_srgb8(green);
+            let blue = linear_u8_to_srgb8(blue);
+
+            let (red, blue) = match self.display_pixel_geometry {
+                DisplayPixelGeometry::RGB => (red, blue),
+                DisplayPixelGeometry::BGR => (blue, red),
+            };
+
+            rgba.extend_from_slice(&[red, green, blue, linear_alpha]);
+        }
+        Self::make_glyph(image, rgba, false)
+    }
+
+    fn rasterize_color(&self, image: &Image) -> RasterizedGlyph {
+        // swash produces straight alpha, but we want premultiplied
+        let mut rgba = image.data.clone();
+        for pixel in rgba.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for c in &mut pixel[0..3] {
+                *c = ((*c as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+        Self::make_glyph(image, rgba, true)
+    }
+}
+
+/// Provides unscaled glyph outlines for painting COLRv1 glyphs
+struct ScalerOutlines<'a> {
+    scaler: Scaler<'a>,
+}
+
+impl<'a> GlyphOutlines for ScalerOutlines<'a> {
+    fn glyph_outline(&mut self, glyph: u16) -> Option<Path> {
+        let outline = self.scaler.scale_outline(glyph)?;
+        let mut pb = PathBuilder::new();
+        for command in outline.path().commands() {
+            match command {
+                Command::MoveTo(p) => pb.move_to(p.x, p.y),
+                Command::LineTo(p) => pb.line_to(p.x, p.y),
+                Command::QuadTo(c, p) => pb.quad_to(c.x, c.y, p.x, p.y),
+                Command::CurveTo(c1, c2, p) => pb.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+                Command::Close => pb.close(),
+            }
+        }
+        pb.finish()
+    }
+}
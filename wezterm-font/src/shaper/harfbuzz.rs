@@ -152,7 +152,8 @@ impl HarfbuzzShaper {
                 if opt_pair.is_none() {
                     let handle = &self.handles[font_idx];
                     log::trace!("shaper wants {} {:?}", font_idx, handle);
-                    let face = self.lib.face_from_locator(&handle.handle)?;
+                    let mut face = self.lib.face_from_locator(&handle.handle)?;
+                    face.set_variation_coordinates(&handle.variations)?;
 
                     let font = if USE_OT_FACE {
                         harfbuzz::Font::from_locator(&handle.handle)?
@@ -847,6 +848,7 @@ mod test {
                     harfbuzz_features: None,
                     scale: None,
                     assume_emoji_presentation: None,
+                    variations: None,
                 },
                 14,
             )
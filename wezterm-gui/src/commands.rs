@@ -587,7 +587,8 @@ fn spawn_command_from_action(action: &KeyAssignment) -> Option<&SpawnCommand> {
         SplitHorizontal(command)
         | SplitVertical(command)
         | SpawnCommandInNewWindow(command)
-        | SpawnCommandInNewTab(command) => Some(command),
+        | SpawnCommandInNewTab(command)
+        | SpawnFloatingPane(command) => Some(command),
         _ => None,
     }
 }
@@ -641,6 +642,22 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: Some("mdi_content_copy"),
         },
+        CopyAs(copy) => {
+            let format = match copy.format {
+                ExportFormat::Text => "text",
+                ExportFormat::Ansi => "text with escape sequences",
+                ExportFormat::Html => "HTML",
+                ExportFormat::Svg => "SVG",
+            };
+            CommandDef {
+                brief: format!("Copy as {format}").into(),
+                doc: format!("Copies the selected text as {format}").into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &["Edit"],
+                icon: Some("mdi_content_copy"),
+            }
+        }
         PasteFrom(ClipboardPasteSource::Clipboard) => CommandDef {
             brief: "Paste from clipboard".into(),
             doc: "Pastes text from the clipboard".into(),
@@ -652,6 +669,14 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: Some("mdi_content_paste"),
         },
+        PasteFrom(ClipboardPasteSource::Register(name)) => CommandDef {
+            brief: format!("Paste from register {name}").into(),
+            doc: format!("Pastes text from the copy mode register {name}").into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("mdi_content_paste"),
+        },
         ToggleFullScreen => CommandDef {
             brief: "Toggle full screen mode".into(),
             doc: "Switch between normal and full screen mode".into(),
@@ -728,6 +753,16 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &[],
             icon: Some("oct_search"),
         },
+        GlobalSearch(_) => CommandDef {
+            brief: "Search all panes".into(),
+            doc: "Searches the scrollback of every pane in every tab, \
+                  window and workspace"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Edit"],
+            icon: Some("oct_search"),
+        },
         ShowDebugOverlay => CommandDef {
             brief: "Show debug overlay".into(),
             doc: "Activates the debug overlay and Lua REPL".into(),
@@ -760,6 +795,19 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: Some("mdi_sticker_emoji"),
         },
+        PaneSelect(PaneSelectArguments {
+            mode: PaneSelectMode::ToggleBroadcast,
+            ..
+        }) => CommandDef {
+            brief: "Select panes to broadcast input to".into(),
+            doc: "Activates the pane selection UI; the chosen pane is added to, \
+                  or removed from, the set of panes that receive broadcast input"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Window"],
+            icon: Some("cod_broadcast"),
+        },
         PaneSelect(_) => CommandDef {
             brief: "Enter Pane selection mode".into(),
             doc: "Activates the pane selection UI".into(),
@@ -1277,6 +1325,59 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
                 icon: Some("oct_terminal"),
             }
         }
+        ScrollToMark(n) => {
+            let (direction, amount) = if *n < 0 { ("up", -n) } else { ("down", *n) };
+            let ordinal = english_ordinal(amount);
+            CommandDef {
+                brief: format!("Scroll {direction} {amount} mark(s)").into(),
+                doc: format!(
+                    "Scrolls the viewport {direction} to the \
+                             {ordinal} mark in that direction"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &[],
+                icon: Some("mdi_bookmark"),
+            }
+        }
+        ScrollToFailedCommand(n) => {
+            let (direction, amount) = if *n < 0 { ("up", -n) } else { ("down", *n) };
+            let ordinal = english_ordinal(amount);
+            CommandDef {
+                brief: format!("Scroll {direction} {amount} failed command(s)").into(),
+                doc: format!(
+                    "Scrolls the viewport {direction} to the \
+                             {ordinal} command that exited with a non-zero \
+                             status in that direction"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &[],
+                icon: Some("oct_terminal"),
+            }
+        }
+        CopyLastCommandOutput(_) => CommandDef {
+            brief: "Copy output of last command".into(),
+            doc: "Copies the output of the most recently completed \
+                  command to the clipboard"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Edit"],
+            icon: Some("mdi_content_copy"),
+        },
+        RerunLastCommand => CommandDef {
+            brief: "Re-run last command".into(),
+            doc: "Sends the most recently run command line to the \
+                  active pane, as though you typed it again"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("oct_terminal"),
+        },
         ScrollByCurrentEventWheelDelta => CommandDef {
             brief: "Scrolls based on the mouse wheel position \
                 in the current mouse event"
@@ -1847,6 +1948,57 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
                 RotationDirection::CounterClockwise => "mdi_rotate_left",
             }),
         },
+        SelectLayout(layout) => {
+            let (brief, doc) = match layout {
+                PaneLayout::Rebalance => (
+                    "Rebalance panes",
+                    "Gives each of the panes in a row or column of the current tab \
+                     an equal share of its space",
+                ),
+                PaneLayout::EvenHorizontal => (
+                    "Arrange panes side by side",
+                    "Arranges the panes in the current tab side by side, with equal widths",
+                ),
+                PaneLayout::EvenVertical => (
+                    "Arrange panes top to bottom",
+                    "Arranges the panes in the current tab top to bottom, with equal heights",
+                ),
+                PaneLayout::MainHorizontal => (
+                    "Arrange panes with the main pane at the top",
+                    "Arranges the panes in the current tab so that the first pane \
+                     is across the top and the others are side by side beneath it",
+                ),
+                PaneLayout::MainVertical => (
+                    "Arrange panes with the main pane on the left",
+                    "Arranges the panes in the current tab so that the first pane \
+                     is on the left and the others are stacked on the right",
+                ),
+                PaneLayout::Tiled => (
+                    "Arrange panes in a grid",
+                    "Arranges the panes in the current tab in a grid of rows and columns",
+                ),
+            };
+            CommandDef {
+                brief: brief.into(),
+                doc: doc.into(),
+                keys: vec![],
+                args: &[ArgType::ActiveTab],
+                menubar: &["Window", "Layout"],
+                icon: Some(match layout {
+                    PaneLayout::Tiled => "mdi_view_grid",
+                    PaneLayout::EvenHorizontal => "mdi_view_column",
+                    _ => "mdi_view_dashboard",
+                }),
+            }
+        }
+        CycleLayouts => CommandDef {
+            brief: "Cycle pane layouts".into(),
+            doc: "Arranges the panes in the current tab using the next predefined layout".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveTab],
+            menubar: &["Window", "Layout"],
+            icon: Some("cod_layout"),
+        },
         SplitPane(split) => {
             let direction = split.direction;
             CommandDef {
@@ -1878,6 +2030,190 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: None,
         },
+        SetTabTitle(title) => CommandDef {
+            brief: format!("Set the tab title to `{title}`").into(),
+            doc: format!("Set the title of the current tab to `{title}`").into(),
+            keys: vec![],
+            args: &[ArgType::ActiveTab],
+            menubar: &[],
+            icon: Some("mdi_rename_box"),
+        },
+        PromptInputLine(prompt) => match &*prompt.action {
+            SetTabTitle(_) => CommandDef {
+                brief: "Rename Tab".into(),
+                doc: "Prompts for a new title for the current tab".into(),
+                keys: vec![],
+                args: &[ArgType::ActiveTab],
+                menubar: &["Window"],
+                icon: Some("mdi_rename_box"),
+            },
+            KeyAssignment::SwitchToWorkspace { name: None, .. } => CommandDef {
+                brief: "Switch to workspace by name".into(),
+                doc: "Prompts for the name of a workspace, then switches to it, \
+                      creating it if it doesn't already exist"
+                    .into(),
+                keys: vec![],
+                args: &[],
+                menubar: &["Window", "Workspace"],
+                icon: None,
+            },
+            KeyAssignment::SplitPane(split) => {
+                let direction = split.direction;
+                CommandDef {
+                    brief: format!("Split the current pane {direction:?} with a command").into(),
+                    doc: format!(
+                        "Prompts for a command, then splits the current pane \
+                         {direction:?} and runs it in the new pane"
+                    )
+                    .into(),
+                    keys: vec![],
+                    args: &[ArgType::ActivePane],
+                    menubar: &["Shell"],
+                    icon: Some("cod_terminal"),
+                }
+            }
+            _ => CommandDef {
+                brief: prompt.description.to_string().into(),
+                doc: prompt.description.to_string().into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &[],
+                icon: Some("cod_edit"),
+            },
+        },
+        ToggleBroadcastInput(scope) => {
+            let (brief, doc) = match scope {
+                BroadcastScope::CurrentTab => (
+                    "Toggle broadcast input to all panes in the tab",
+                    "Sends input that is typed or pasted into a pane to \
+                     all of the other panes in the current tab",
+                ),
+                BroadcastScope::CurrentWorkspace => (
+                    "Toggle broadcast input to all panes in the workspace",
+                    "Sends input that is typed or pasted into a pane to \
+                     all of the other panes in the current workspace",
+                ),
+                BroadcastScope::SelectedPanes => (
+                    "Toggle broadcast input to the selected panes",
+                    "Sends input that is typed or pasted into a pane to \
+                     the other panes that were chosen via PaneSelect",
+                ),
+            };
+            CommandDef {
+                brief: brief.into(),
+                doc: doc.into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &["Window"],
+                icon: Some("cod_broadcast"),
+            }
+        }
+        SpawnFloatingPane(cmd) => CommandDef {
+            brief: label_string(action, "Spawn a floating pane".to_string()).into(),
+            doc: format!("Spawn a floating pane with {cmd:?}").into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Shell"],
+            icon: Some("cod_window"),
+        },
+        ToggleFloatingPanes => CommandDef {
+            brief: "Toggle floating panes".into(),
+            doc: "Shows or hides the floating panes in the current tab".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Window"],
+            icon: Some("cod_layers"),
+        },
+        PipePane(spec) => CommandDef {
+            brief: match (&spec.file, &spec.command) {
+                (Some(file), _) => format!("Pipe pane output to {}", file).into(),
+                (None, Some(command)) => {
+                    format!("Pipe pane output to `{}`", command.join(" ")).into()
+                }
+                (None, None) => "Pipe pane output".into(),
+            },
+            doc: "Copies everything that the current pane outputs to a file or to \
+                  the input of a command"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("mdi_pipe"),
+        },
+        StopPipePane => CommandDef {
+            brief: "Stop piping pane output".into(),
+            doc: "Stops copying the output of the current pane".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("mdi_pipe_disconnected"),
+        },
+        MovePaneToNewTab => CommandDef {
+            brief: "Move pane to a new tab".into(),
+            doc: "Moves the current pane out of its tab and into a new tab in the same window"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Window"],
+            icon: None,
+        },
+        MovePaneToTab(args) => {
+            let ordinal = english_ordinal(args.tab + 1);
+            let direction = args.direction;
+            CommandDef {
+                brief: format!("Move pane to the {ordinal} tab").into(),
+                doc: format!(
+                    "Moves the current pane into a {direction:?} split of the \
+                     active pane of the {ordinal} tab"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &[],
+                icon: None,
+            }
+        }
+        SwapPaneWithTab(n) => {
+            let ordinal = english_ordinal(*n + 1);
+            CommandDef {
+                brief: format!("Swap pane with the {ordinal} tab").into(),
+                doc: format!(
+                    "Exchanges the current pane with the active pane of the {ordinal} tab"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &[],
+                icon: None,
+            }
+        }
+        MoveTabToNewWindow => CommandDef {
+            brief: "Move tab to a new window".into(),
+            doc: "Moves the current tab out of this window and into a new window".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveTab],
+            menubar: &["Window"],
+            icon: None,
+        },
+        MoveTabToWindow(n) => {
+            let ordinal = english_ordinal(*n as isize + 1);
+            CommandDef {
+                brief: format!("Move tab to the {ordinal} window").into(),
+                doc: format!("Moves the current tab to the end of the {ordinal} window").into(),
+                keys: vec![],
+                args: &[ArgType::ActiveTab],
+                menubar: &[],
+                icon: None,
+            }
+        }
+        InputSelector(selector) => CommandDef {
+            brief: selector.title.to_string().into(),
+            doc: selector.title.to_string().into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("cod_list_selection"),
+        },
     })
 }
 
@@ -1903,6 +2239,10 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
             domain: SpawnTabDomain::CurrentPaneDomain,
             ..Default::default()
         }),
+        SpawnFloatingPane(SpawnCommand {
+            domain: SpawnTabDomain::CurrentPaneDomain,
+            ..Default::default()
+        }),
         CloseCurrentTab { confirm: true },
         CloseCurrentPane { confirm: true },
         DetachDomain(SpawnTabDomain::CurrentPaneDomain),
@@ -1913,6 +2253,10 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         #[cfg(not(target_os = "macos"))]
         CopyTo(ClipboardCopyDestination::PrimarySelection),
         CopyTo(ClipboardCopyDestination::Clipboard),
+        CopyAs(config::keyassignment::CopyAs {
+            format: ExportFormat::Html,
+            destination: ClipboardCopyDestination::Clipboard,
+        }),
         PasteFrom(ClipboardPasteSource::Clipboard),
         ClearScrollback(ScrollbackEraseMode::ScrollbackOnly),
         ClearScrollback(ScrollbackEraseMode::ScrollbackAndViewport),
@@ -1934,9 +2278,25 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ToggleFullScreen,
         Hide,
         Search(Pattern::CurrentSelectionOrEmptyString),
+        GlobalSearch(GlobalSearchArguments::default()),
         PaneSelect(PaneSelectArguments::default()),
+        PaneSelect(PaneSelectArguments {
+            mode: PaneSelectMode::ToggleBroadcast,
+            ..Default::default()
+        }),
+        ToggleBroadcastInput(BroadcastScope::CurrentTab),
+        ToggleBroadcastInput(BroadcastScope::CurrentWorkspace),
+        ToggleBroadcastInput(BroadcastScope::SelectedPanes),
+        ToggleFloatingPanes,
         RotatePanes(RotationDirection::Clockwise),
         RotatePanes(RotationDirection::CounterClockwise),
+        CycleLayouts,
+        SelectLayout(PaneLayout::Rebalance),
+        SelectLayout(PaneLayout::EvenHorizontal),
+        SelectLayout(PaneLayout::EvenVertical),
+        SelectLayout(PaneLayout::MainHorizontal),
+        SelectLayout(PaneLayout::MainVertical),
+        SelectLayout(PaneLayout::Tiled),
         ActivateTab(0),
         ActivateTab(1),
         ActivateTab(2),
@@ -1962,6 +2322,8 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ActivateWindowRelative(1),
         MoveTabRelative(-1),
         MoveTabRelative(1),
+        MovePaneToNewTab,
+        MoveTabToNewWindow,
         AdjustPaneSize(PaneDirection::Left, 1),
         AdjustPaneSize(PaneDirection::Right, 1),
         AdjustPaneSize(PaneDirection::Up, 1),
@@ -1974,6 +2336,29 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ActivateLastTab,
         ShowLauncher,
         ShowTabNavigator,
+        PromptInputLine(config::keyassignment::PromptInputLine {
+            description: "Enter the new title for the tab".to_string(),
+            action: Box::new(SetTabTitle(String::new())),
+        }),
+        PromptInputLine(config::keyassignment::PromptInputLine {
+            description: "Enter the name of the workspace".to_string(),
+            action: Box::new(SwitchToWorkspace {
+                name: None,
+                spawn: None,
+            }),
+        }),
+        PromptInputLine(config::keyassignment::PromptInputLine {
+            description: "Enter the command to run in the new pane".to_string(),
+            action: Box::new(SplitPane(config::keyassignment::SplitPane {
+                direction: PaneDirection::Right,
+                size: SplitSize::default(),
+                command: SpawnCommand {
+                    domain: SpawnTabDomain::CurrentPaneDomain,
+                    ..Default::default()
+                },
+                top_level: false,
+            })),
+        }),
         // ----------------- Help
         OpenUri("https://wezfurlong.org/wezterm/".to_string()),
         OpenUri("https://github.com/wez/wezterm/discussions/".to_string()),
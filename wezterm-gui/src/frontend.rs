@@ -23,6 +23,10 @@ pub struct GuiFrontEnd {
     switching_workspaces: RefCell<bool>,
     spawned_mux_window: RefCell<HashSet<MuxWindowId>>,
     known_windows: RefCell<BTreeMap<Window, MuxWindowId>>,
+    /// Where each window was on screen when it last saw a mouse event.
+    /// There is no portable way to query the position of a window,
+    /// so this is inferred from the mouse coordinates.
+    window_screen_rects: RefCell<BTreeMap<Window, ScreenRect>>,
     client_id: Arc<ClientId>,
     config_subscription: RefCell<Option<ConfigSubscription>>,
 }
@@ -46,6 +50,7 @@ impl GuiFrontEnd {
             switching_workspaces: RefCell::new(false),
             spawned_mux_window: RefCell::new(HashSet::new()),
             known_windows: RefCell::new(BTreeMap::new()),
+            window_screen_rects: RefCell::new(BTreeMap::new()),
             client_id: client_id.clone(),
             config_subscription: RefCell::new(None),
         });
@@ -95,6 +100,10 @@ impl GuiFrontEnd {
                 MuxNotification::WindowInvalidated(_) => {}
                 MuxNotification::PaneOutput(_) => {}
                 MuxNotification::PaneAdded(_) => {}
+                MuxNotification::PanePipeStopped(_) => {}
+                MuxNotification::TriggerEvent { .. } => {
+                    // Handled by the TermWindow that contains the pane
+                }
                 MuxNotification::Alert {
                     pane_id: _,
                     alert:
@@ -126,8 +135,14 @@ impl GuiFrontEnd {
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
                         | Alert::IconTitleChanged(_)
-                        | Alert::SetUserVar { .. },
-                } => {}
+                        | Alert::SetUserVar { .. }
+                        | Alert::SetProfile(_)
+                        | Alert::BadgeFormatChanged(_)
+                        | Alert::CommandFinished { .. },
+                } => {
+                    // CommandFinished is handled by the TermWindow
+                    // that contains the pane
+                }
                 MuxNotification::Empty => {
                     if config::configuration().quit_when_all_windows_are_closed {
                         promise::spawn::spawn_into_main_thread(async move {
@@ -431,11 +446,51 @@ impl GuiFrontEnd {
 
     pub fn forget_known_window(&self, window: &Window) {
         self.known_windows.borrow_mut().remove(window);
+        self.window_screen_rects.borrow_mut().remove(window);
         if !self.is_switching_workspace() {
             self.reconcile_workspace();
         }
     }
 
+    pub fn record_window_screen_rect(&self, window: &Window, rect: ScreenRect) {
+        self.window_screen_rects
+            .borrow_mut()
+            .insert(window.clone(), rect);
+    }
+
+    /// Called when window is resized.  The window layer doesn't report
+    /// where a window is on screen, so the origin that was last derived
+    /// from a mouse event is kept and only the size is updated; the
+    /// origin is corrected by the next mouse event in the window.
+    pub fn update_window_screen_size(&self, window: &Window, width: usize, height: usize) {
+        if let Some(rect) = self.window_screen_rects.borrow_mut().get_mut(window) {
+            rect.size = euclid::size2(width as isize, height as isize);
+        }
+    }
+
+    pub fn forget_window_screen_rect(&self, window: &Window) {
+        self.window_screen_rects.borrow_mut().remove(window);
+    }
+
+    /// Returns the window that contains the specified point on
+    /// the screen, as far as we know
+    pub fn gui_window_at_screen_point(&self, point: ScreenPoint) -> Option<GuiWin> {
+        let rects = self.window_screen_rects.borrow();
+        for (window, &mux_window_id) in self.known_windows.borrow().iter() {
+            if rects
+                .get(window)
+                .map(|r| r.contains(point))
+                .unwrap_or(false)
+            {
+                return Some(GuiWin {
+                    mux_window_id,
+                    window: window.clone(),
+                });
+            }
+        }
+        None
+    }
+
     pub fn is_switching_workspace(&self) -> bool {
         *self.switching_workspaces.borrow()
     }
@@ -0,0 +1,253 @@
+//! Fuzzy matching and frecency ranking for the pickers that allow
+//! selecting an item from a list by typing part of its label.
+use frecency::Frecency;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use termwiz::cell::{unicode_column_width, AttributeChange, Intensity, Underline};
+use termwiz::surface::Change;
+
+/// How much a frecency score of 1.0, which is what an item that was
+/// accessed just now has, adds to the fuzzy match score.
+/// Typical fuzzy scores are in the range of tens to low hundreds,
+/// so this is enough to break ties and lift frequently used items
+/// without overriding a much better match.
+const FRECENCY_BOOST: f64 = 20.0;
+
+pub struct Match {
+    pub score: i64,
+    /// The char indices of the label that matched the pattern
+    pub positions: Vec<usize>,
+}
+
+pub struct Matcher {
+    matcher: SkimMatcherV2,
+}
+
+impl Matcher {
+    pub fn new() -> Self {
+        Self {
+            matcher: SkimMatcherV2::default(),
+        }
+    }
+
+    pub fn fuzzy_match(&self, label: &str, pattern: &str) -> Option<Match> {
+        let (score, positions) = self.matcher.fuzzy_indices(label, pattern)?;
+        Some(Match { score, positions })
+    }
+}
+
+/// Combine a fuzzy match score with a frecency score
+pub fn boosted_score(score: i64, frecency: f64) -> i64 {
+    score.saturating_add((frecency * FRECENCY_BOOST) as i64)
+}
+
+/// Render label, emphasizing the chars at the specified positions
+/// that matched the filter term
+pub fn render_highlighted(
+    label: &str,
+    positions: &[usize],
+    max_width: usize,
+    changes: &mut Vec<Change>,
+) {
+    let mut width = 0;
+    let mut highlighted = false;
+    let mut text = String::new();
+    for (idx, c) in label.chars().enumerate() {
+        let mut buf = [0u8; 4];
+        let s = c.encode_utf8(&mut buf);
+        width += unicode_column_width(s, None);
+        if width > max_width {
+            break;
+        }
+        let matched = positions.contains(&idx);
+        if matched != highlighted {
+            if !text.is_empty() {
+                changes.push(Change::Text(std::mem::take(&mut text)));
+            }
+            changes.push(
+                AttributeChange::Intensity(if matched {
+                    Intensity::Bold
+                } else {
+                    Intensity::Normal
+                })
+                .into(),
+            );
+            changes.push(
+                AttributeChange::Underline(if matched {
+                    Underline::Single
+                } else {
+                    Underline::None
+                })
+                .into(),
+            );
+            highlighted = matched;
+        }
+        text.push(c);
+    }
+    if !text.is_empty() {
+        changes.push(Change::Text(text));
+    }
+    if highlighted {
+        changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+        changes.push(AttributeChange::Underline(Underline::None).into());
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Recent {
+    /// The command palette used to call this `brief`
+    #[serde(alias = "brief")]
+    key: String,
+    frecency: Frecency,
+}
+
+/// A set of frecency scores that is persisted to a file in the
+/// runtime directory, keyed by an identifier of the item
+pub struct Recents {
+    scores: HashMap<String, f64>,
+}
+
+impl Recents {
+    fn file_name(name: &str) -> PathBuf {
+        config::RUNTIME_DIR.join(format!("recent-{name}.json"))
+    }
+
+    fn load_file(name: &str) -> anyhow::Result<Vec<Recent>> {
+        let f = std::fs::File::open(Self::file_name(name))?;
+        Ok(serde_json::from_reader(f)?)
+    }
+
+    /// Load the scores from the named file; if it doesn't exist,
+    /// all items have a zero score
+    pub fn load(name: &str) -> Self {
+        let scores = Self::load_file(name)
+            .unwrap_or_else(|_| vec![])
+            .into_iter()
+            .map(|recent| (recent.key, recent.frecency.score()))
+            .collect();
+        Self { scores }
+    }
+
+    pub fn score(&self, key: &str) -> f64 {
+        self.scores.get(key).copied().unwrap_or(0.)
+    }
+
+    /// Record an access to the item identified by key and save
+    /// the result to the named file
+    pub fn register_access(name: &str, key: &str) -> anyhow::Result<()> {
+        let mut recents = Self::load_file(name).unwrap_or_else(|_| vec![]);
+        match recents.iter_mut().find(|r| r.key == key) {
+            Some(recent) => recent.frecency.register_access(),
+            None => {
+                let mut frecency = Frecency::new();
+                frecency.register_access();
+                recents.push(Recent {
+                    key: key.to_string(),
+                    frecency,
+                });
+            }
+        }
+
+        let json = serde_json::to_string(&recents)?;
+        std::fs::write(Self::file_name(name), json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match() {
+        let matcher = Matcher::new();
+        let m = matcher.fuzzy_match("New Tab", "ntb").unwrap();
+        assert_eq!(m.positions, vec![0, 4, 6]);
+        assert!(m.score > 0);
+
+        assert!(matcher.fuzzy_match("New Tab", "xyz").is_none());
+
+        // A contiguous match beats a scattered one
+        let contiguous = matcher.fuzzy_match("tab", "tab").unwrap();
+        let scattered = matcher.fuzzy_match("the abyss below", "tab").unwrap();
+        assert!(contiguous.score > scattered.score);
+    }
+
+    #[test]
+    fn frecency_boost() {
+        assert_eq!(boosted_score(50, 0.), 50);
+        assert_eq!(boosted_score(50, 1.), 50 + FRECENCY_BOOST as i64);
+        assert_eq!(boosted_score(50, 0.5), 50 + (FRECENCY_BOOST / 2.) as i64);
+        assert_eq!(boosted_score(i64::MAX, 1.), i64::MAX);
+    }
+
+    fn bold(on: bool) -> Vec<Change> {
+        vec![
+            AttributeChange::Intensity(if on {
+                Intensity::Bold
+            } else {
+                Intensity::Normal
+            })
+            .into(),
+            AttributeChange::Underline(if on {
+                Underline::Single
+            } else {
+                Underline::None
+            })
+            .into(),
+        ]
+    }
+
+    fn text(s: &str) -> Vec<Change> {
+        vec![Change::Text(s.to_string())]
+    }
+
+    fn render(label: &str, positions: &[usize], max_width: usize) -> Vec<Change> {
+        let mut changes = vec![];
+        render_highlighted(label, positions, max_width, &mut changes);
+        changes
+    }
+
+    #[test]
+    fn highlight() {
+        assert_eq!(render("hello", &[], 80), text("hello"));
+
+        assert_eq!(
+            render("hello", &[1, 2, 4], 80),
+            [
+                text("h"),
+                bold(true),
+                text("el"),
+                bold(false),
+                text("l"),
+                bold(true),
+                text("o"),
+                bold(false),
+            ]
+            .concat()
+        );
+
+        assert_eq!(
+            render("hello", &[0], 80),
+            [bold(true), text("h"), bold(false), text("ello")].concat()
+        );
+    }
+
+    #[test]
+    fn highlight_truncates() {
+        assert_eq!(
+            render("hello", &[3, 4], 3),
+            text("hel"),
+            "highlighting beyond the width is not emitted"
+        );
+
+        // Wide chars count as two columns
+        assert_eq!(
+            render("日本語", &[1], 5),
+            [text("日"), bold(true), text("本"), bold(false)].concat()
+        );
+    }
+}
@@ -3,9 +3,9 @@ use crate::customglyph::*;
 use crate::renderstate::RenderContext;
 use ::window::bitmaps::atlas::{Atlas, OutOfTextureSpace, Sprite};
 use ::window::bitmaps::{BitmapImage, Image, ImageTexture, Texture2d};
-use ::window::color::SrgbaPixel;
+use ::window::color::{SrgbaPixel, SrgbaTuple};
 use ::window::{Point, Rect};
-use config::{AllowSquareGlyphOverflow, TextStyle};
+use config::{AllowSquareGlyphOverflow, ConfigHandle, TextStyle};
 use euclid::num::Zero;
 use image::io::Limits;
 use image::{AnimationDecoder, Frame, Frames, ImageDecoder, ImageFormat, ImageResult};
@@ -57,6 +57,8 @@ pub struct GlyphKey {
     pub followed_by_space: bool,
     pub metric: CellMetricKey,
     pub id: LoadedFontId,
+    /// The text color, for glyphs that are painted using it
+    pub foreground: Option<SrgbaTuple>,
 }
 
 /// We'd like to avoid allocating when resolving from the cache
@@ -73,6 +75,7 @@ pub struct BorrowedGlyphKey<'a> {
     pub followed_by_space: bool,
     pub metric: CellMetricKey,
     pub id: LoadedFontId,
+    pub foreground: Option<SrgbaTuple>,
 }
 
 impl<'a> BorrowedGlyphKey<'a> {
@@ -85,6 +88,7 @@ impl<'a> BorrowedGlyphKey<'a> {
             followed_by_space: self.followed_by_space,
             metric: self.metric,
             id: self.id,
+            foreground: self.foreground,
         }
     }
 }
@@ -103,6 +107,7 @@ impl GlyphKeyTrait for GlyphKey {
             followed_by_space: self.followed_by_space,
             metric: self.metric,
             id: self.id,
+            foreground: self.foreground,
         }
     }
 }
@@ -493,6 +498,16 @@ impl DecodedImage {
     }
 }
 
+/// Returns the text color to use for glyphs that aren't displayed
+/// in a terminal cell, and so don't have a color of their own
+pub fn default_glyph_foreground(config: &ConfigHandle) -> SrgbaTuple {
+    config
+        .resolved_palette
+        .foreground
+        .map(|color| *color)
+        .unwrap_or(SrgbaTuple(1., 1., 1., 1.))
+}
+
 /// A number of items here are HashMaps rather than LfuCaches;
 /// eviction is managed by recreating Self when the Atlas is filled
 pub struct GlyphCache {
@@ -565,6 +580,8 @@ impl GlyphCache {
 impl GlyphCache {
     /// Resolve a glyph from the cache, rendering the glyph on-demand if
     /// the cache doesn't already hold the desired glyph.
+    /// `foreground` is the resolved text color of the cell, which is
+    /// used to paint COLRv1 and OpenType-SVG glyphs that reference it
     pub fn cached_glyph(
         &mut self,
         info: &GlyphInfo,
@@ -573,7 +590,13 @@ impl GlyphCache {
         font: &Rc<LoadedFont>,
         metrics: &RenderMetrics,
         num_cells: u8,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<Rc<CachedGlyph>> {
+        // Only the glyphs that are painted in the text color are cached
+        // separately for each color; the others are tinted when drawn
+        let uses_foreground = font
+            .uses_foreground(info.glyph_pos, info.font_idx)
+            .unwrap_or(false);
         let key = BorrowedGlyphKey {
             font_idx: info.font_idx,
             glyph_pos: info.glyph_pos,
@@ -582,6 +605,11 @@ impl GlyphCache {
             followed_by_space,
             metric: metrics.into(),
             id: font.id(),
+            foreground: if uses_foreground {
+                Some(foreground)
+            } else {
+                None
+            },
         };
 
         if let Some(entry) = self.glyph_cache.get(&key as &dyn GlyphKeyTrait) {
@@ -590,7 +618,7 @@ impl GlyphCache {
         }
         metrics::histogram!("glyph_cache.glyph_cache.miss.rate", 1.);
 
-        let glyph = match self.load_glyph(info, font, followed_by_space, num_cells) {
+        let glyph = match self.load_glyph(info, font, followed_by_space, num_cells, foreground) {
             Ok(g) => g,
             Err(err) => {
                 if err
@@ -643,6 +671,7 @@ impl GlyphCache {
         font: &Rc<LoadedFont>,
         followed_by_space: bool,
         num_cells: u8,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<Rc<CachedGlyph>> {
         let base_metrics;
         let idx_metrics;
@@ -651,7 +680,7 @@ impl GlyphCache {
 
         {
             base_metrics = font.metrics();
-            glyph = font.rasterize_glyph(info.glyph_pos, info.font_idx)?;
+            glyph = font.rasterize_glyph(info.glyph_pos, info.font_idx, foreground)?;
 
             idx_metrics = font.metrics_for_idx(info.font_idx)?;
             brightness_adjust = font.brightness_adjust(info.font_idx);
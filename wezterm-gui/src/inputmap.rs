@@ -80,9 +80,10 @@ impl InputMap {
                 {
                     continue;
                 }
-                keys.default
-                    .entry((code, mods))
-                    .or_insert(KeyTableEntry { action });
+                keys.default.entry((code, mods)).or_insert(KeyTableEntry {
+                    action,
+                    description: None,
+                });
             }
         }
 
@@ -2,7 +2,7 @@
 #![cfg_attr(not(test), windows_subsystem = "windows")]
 
 use crate::customglyph::BlockKey;
-use crate::glyphcache::GlyphCache;
+use crate::glyphcache::{default_glyph_foreground, GlyphCache};
 use ::window::*;
 use anyhow::{anyhow, Context};
 use clap::builder::ValueParser;
@@ -37,6 +37,7 @@ mod commands;
 mod customglyph;
 mod download;
 mod frontend;
+mod fuzzy;
 mod glyphcache;
 mod inputmap;
 mod markdown;
@@ -1007,6 +1008,7 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
                     &font,
                     &render_metrics,
                     info.num_cells,
+                    default_glyph_foreground(&config),
                 )?;
 
                 let mut texture = cached_glyph.texture.clone();
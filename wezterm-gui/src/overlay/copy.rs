@@ -1,4 +1,5 @@
 use crate::selection::{SelectionCoordinate, SelectionRange, SelectionX};
+use crate::termwindow::clipboard::is_valid_register;
 use crate::termwindow::{TermWindow, TermWindowNotif};
 use config::keyassignment::{
     ClipboardCopyDestination, CopyModeAssignment, KeyAssignment, KeyTable, KeyTableEntry,
@@ -25,16 +26,27 @@ use unicode_segmentation::*;
 use url::Url;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    unicode_column_width, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, SemanticType,
-    StableRowIndex, TerminalSize,
+    unicode_column_width, Clipboard, CommandBlock, KeyCode, KeyModifiers, Line, MouseEvent,
+    SemanticType, StableRowIndex, TerminalSize,
 };
 use window::{KeyCode as WKeyCode, Modifiers, WindowOps};
 
 lazy_static::lazy_static! {
     static ref SAVED_PATTERN: Mutex<HashMap<TabId, Pattern>> = Mutex::new(HashMap::new());
+    /// Named marks set via SetNamedMark, keyed by the pane they were set in,
+    /// so that they persist across activations of copy mode
+    static ref NAMED_MARKS: Mutex<HashMap<PaneId, HashMap<char, (usize, StableRowIndex)>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Called when a pane is removed from the mux, to forget its marks
+pub fn pane_removed(pane_id: PaneId) {
+    NAMED_MARKS.lock().remove(&pane_id);
 }
 
 const SEARCH_CHUNK_SIZE: StableRowIndex = 1000;
+/// Upper bound on the numeric prefix that can be entered
+const MAX_COUNT: usize = 99_999;
 
 pub struct CopyOverlay {
     delegate: Arc<dyn Pane>,
@@ -45,6 +57,16 @@ pub struct CopyOverlay {
 struct PendingJump {
     forward: bool,
     prev_char: bool,
+    count: usize,
+}
+
+/// An assignment that needs the next key press to be resolved
+#[derive(Copy, Clone, Debug)]
+enum PendingKey {
+    TextObject { inner: bool },
+    SetMark,
+    MoveToMark { exact_position: bool },
+    Register,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -81,6 +103,15 @@ struct CopyRenderable {
     searching: Option<Searching>,
     pending_jump: Option<PendingJump>,
     last_jump: Option<Jump>,
+    pending_key: Option<PendingKey>,
+    /// The numeric prefix typed ahead of the next assignment
+    count: Option<usize>,
+    /// The register that the next copy will be stored in
+    register: Option<char>,
+    /// A result that was selected from outside of copy mode, eg: by
+    /// the global search overlay.  It is made the active match once
+    /// the search locates it, rather than the first match.
+    target_result: Option<SearchResult>,
 }
 
 struct Searching {
@@ -154,6 +185,10 @@ impl CopyOverlay {
             searching: None,
             pending_jump: None,
             last_jump: None,
+            pending_key: None,
+            count: None,
+            register: None,
+            target_result: None,
         };
 
         let search_row = render.compute_search_row();
@@ -166,6 +201,36 @@ impl CopyOverlay {
         }))
     }
 
+    /// Returns the register selected for the next copy, if any,
+    /// and resets it so that subsequent copies use the default
+    pub fn take_register(&self) -> Option<char> {
+        self.render.lock().register.take()
+    }
+
+    /// Move the cursor to result and select it.  If the search pattern
+    /// also matches it, it becomes the active match.
+    pub fn activate_search_result(&self, result: SearchResult) {
+        let mut render = self.render.lock();
+        render.select_result(&result);
+        match render
+            .results
+            .iter()
+            .position(|r| same_match_position(r, &result))
+        {
+            Some(idx) => render.activate_match_number(idx),
+            None => {
+                render.target_result.replace(result);
+            }
+        }
+    }
+
+    fn ring_bell(&self) {
+        self.delegate
+            .perform_actions(vec![termwiz::escape::Action::Control(
+                termwiz::escape::ControlCode::Bell,
+            )]);
+    }
+
     pub fn get_params(&self) -> CopyModeParams {
         let render = self.render.lock();
         CopyModeParams {
@@ -355,7 +420,16 @@ impl CopyRenderable {
         let is_first = self.results.is_empty();
         self.incrementally_recompute_results(results);
 
-        if is_first {
+        if let Some(target) = self.target_result {
+            if let Some(idx) = self
+                .results
+                .iter()
+                .position(|r| same_match_position(r, &target))
+            {
+                self.target_result.take();
+                self.activate_match_number(idx);
+            }
+        } else if is_first {
             if !self.results.is_empty() {
                 self.activate_match_number(0);
             } else {
@@ -367,6 +441,7 @@ impl CopyRenderable {
         let dims = self.delegate.get_dimensions();
         if range.start == dims.scrollback_top {
             self.searching.take();
+            self.target_result.take();
             return;
         }
 
@@ -417,6 +492,10 @@ impl CopyRenderable {
     fn activate_match_number(&mut self, n: usize) {
         self.result_pos.replace(n);
         let result = self.results[n].clone();
+        self.select_result(&result);
+    }
+
+    fn select_result(&mut self, result: &SearchResult) {
         self.cursor.y = result.end_y;
         self.cursor.x = result.end_x.saturating_sub(1);
 
@@ -667,33 +746,43 @@ impl CopyRenderable {
         self.select_to_cursor_pos();
     }
 
-    fn move_left_single_cell(&mut self) {
-        self.cursor.x = self.cursor.x.saturating_sub(1);
+    fn move_left(&mut self, count: usize) {
+        self.cursor.x = self.cursor.x.saturating_sub(count);
+        self.select_to_cursor_pos();
+    }
+
+    fn move_right(&mut self, count: usize) {
+        self.cursor.x = self.cursor.x.saturating_add(count);
         self.select_to_cursor_pos();
     }
 
-    fn move_right_single_cell(&mut self) {
-        self.cursor.x += 1;
+    fn move_up(&mut self, count: usize) {
+        self.cursor.y = self.cursor.y.saturating_sub(count as isize);
         self.select_to_cursor_pos();
     }
 
-    fn move_up_single_row(&mut self) {
-        self.cursor.y = self.cursor.y.saturating_sub(1);
+    fn move_down(&mut self, count: usize) {
+        self.cursor.y = self.cursor.y.saturating_add(count as isize);
         self.select_to_cursor_pos();
     }
 
-    fn move_down_single_row(&mut self) {
-        self.cursor.y += 1;
+    /// Move to the nth line of the scrollback, counting from 1
+    fn move_to_line(&mut self, n: usize) {
+        let dims = self.delegate.get_dimensions();
+        self.cursor.y = dims
+            .scrollback_top
+            .saturating_add(n.saturating_sub(1) as isize);
         self.select_to_cursor_pos();
     }
+
     fn move_to_start_of_line(&mut self) {
         self.cursor.x = 0;
         self.select_to_cursor_pos();
     }
 
-    fn move_to_start_of_next_line(&mut self) {
+    fn move_to_start_of_next_line(&mut self, count: usize) {
         self.cursor.x = 0;
-        self.cursor.y += 1;
+        self.cursor.y = self.cursor.y.saturating_add(count as isize);
         self.select_to_cursor_pos();
     }
 
@@ -709,7 +798,13 @@ impl CopyRenderable {
         self.select_to_cursor_pos();
     }
 
-    fn move_to_end_of_line_content(&mut self) {
+    fn move_to_end_of_line_content(&mut self, count: usize) {
+        // As in vim, a count moves down count-1 lines first
+        self.cursor.y = self
+            .cursor
+            .y
+            .saturating_add(count.saturating_sub(1) as isize);
+        self.clamp_cursor_to_scrollback();
         let y = self.cursor.y;
         let (top, lines) = self.delegate.get_lines(y..y + 1);
         if let Some(line) = lines.get(0) {
@@ -953,6 +1048,28 @@ impl CopyRenderable {
         self.select_to_cursor_pos();
     }
 
+    fn move_by_mark(&mut self, delta: isize) {
+        let marks = self.delegate.get_marks();
+        let y = self.cursor.y;
+        let target = if delta > 0 {
+            marks.iter().filter(|&&row| row > y).nth(delta as usize - 1)
+        } else if delta < 0 {
+            marks
+                .iter()
+                .rev()
+                .filter(|&&row| row < y)
+                .nth(delta.unsigned_abs() - 1)
+        } else {
+            None
+        };
+
+        if let Some(&row) = target {
+            self.cursor.x = 0;
+            self.cursor.y = row;
+            self.select_to_cursor_pos();
+        }
+    }
+
     fn perform_jump(&mut self, jump: Jump, repeat: bool) {
         let y = self.cursor.y;
         let (_top, lines) = self.delegate.get_lines(y..y + 1);
@@ -1009,17 +1126,184 @@ impl CopyRenderable {
         }
     }
 
-    fn jump(&mut self, forward: bool, prev_char: bool) {
-        self.pending_jump
-            .replace(PendingJump { forward, prev_char });
+    fn push_count_digit(&mut self, digit: char) {
+        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+        if digit == 0 && self.count.is_none() {
+            return;
+        }
+        let count = self.count.unwrap_or(0);
+        self.count.replace(
+            count
+                .saturating_mul(10)
+                .saturating_add(digit)
+                .min(MAX_COUNT),
+        );
+    }
+
+    fn jump(&mut self, forward: bool, prev_char: bool, count: usize) {
+        self.pending_jump.replace(PendingJump {
+            forward,
+            prev_char,
+            count,
+        });
     }
 
-    fn jump_again(&mut self, reverse: bool) {
+    fn jump_again(&mut self, reverse: bool, count: usize) {
         if let Some(mut jump) = self.last_jump {
             if reverse {
                 jump.forward = !jump.forward;
             }
-            self.perform_jump(jump, true);
+            for _ in 0..count {
+                self.perform_jump(jump, true);
+            }
+        }
+    }
+
+    /// Returns the visible cells of the line at the cursor as
+    /// (cell index, text) pairs, along with the position of the
+    /// cell under the cursor within that list
+    fn cells_at_cursor(&self) -> Option<(Vec<(usize, String)>, usize)> {
+        let y = self.cursor.y;
+        let (_top, lines) = self.delegate.get_lines(y..y + 1);
+        let line = lines.get(0)?;
+        let cells: Vec<(usize, String)> = line
+            .visible_cells()
+            .map(|cell| (cell.cell_index(), cell.str().to_string()))
+            .collect();
+        let pos = cells.iter().rposition(|(idx, _)| *idx <= self.cursor.x)?;
+        Some((cells, pos))
+    }
+
+    /// Select the text object identified by key around the cursor.
+    /// Returns false if there is no such object around the cursor.
+    fn select_text_object(&mut self, key: char, inner: bool) -> bool {
+        if key == 'p' {
+            return self.select_paragraph(inner);
+        }
+        let (cells, pos) = match self.cells_at_cursor() {
+            Some(cells) => cells,
+            None => return false,
+        };
+        let range = match key {
+            'w' => word_object(&cells, pos, inner, word_class),
+            'W' => word_object(&cells, pos, inner, bigword_class),
+            '"' | '\'' | '`' => quote_object(&cells, pos, inner, key),
+            '(' | ')' | 'b' => bracket_object(&cells, pos, inner, '(', ')'),
+            '[' | ']' => bracket_object(&cells, pos, inner, '[', ']'),
+            '{' | '}' | 'B' => bracket_object(&cells, pos, inner, '{', '}'),
+            '<' | '>' => bracket_object(&cells, pos, inner, '<', '>'),
+            _ => None,
+        };
+        match range {
+            Some((first, last)) => {
+                let y = self.cursor.y;
+                self.start
+                    .replace(SelectionCoordinate::x_y(cells[first].0, y));
+                self.cursor.x = cells[last].0;
+                self.selection_mode = SelectionMode::Cell;
+                self.select_to_cursor_pos();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn line_is_blank(&self, y: StableRowIndex) -> bool {
+        let (_top, lines) = self.delegate.get_lines(y..y + 1);
+        lines
+            .get(0)
+            .map(|line| line.is_whitespace())
+            .unwrap_or(true)
+    }
+
+    /// Select the run of lines around the cursor that are either all
+    /// blank or all non-blank.  The outer form includes the blank lines
+    /// that follow the paragraph, or those that precede it if there
+    /// are none following.
+    fn select_paragraph(&mut self, inner: bool) -> bool {
+        let dims = self.delegate.get_dimensions();
+        let min_row = dims.scrollback_top;
+        let max_row = dims.scrollback_top + dims.scrollback_rows as isize - 1;
+        let blank = self.line_is_blank(self.cursor.y);
+
+        let mut first = self.cursor.y;
+        while first > min_row && self.line_is_blank(first - 1) == blank {
+            first -= 1;
+        }
+        let mut last = self.cursor.y;
+        while last < max_row && self.line_is_blank(last + 1) == blank {
+            last += 1;
+        }
+
+        if !inner {
+            let para_end = last;
+            while last < max_row && self.line_is_blank(last + 1) != blank {
+                last += 1;
+            }
+            if last == para_end {
+                while first > min_row && self.line_is_blank(first - 1) != blank {
+                    first -= 1;
+                }
+            }
+        }
+
+        self.start.replace(SelectionCoordinate::x_y(0, first));
+        self.cursor.x = 0;
+        self.cursor.y = last;
+        self.selection_mode = SelectionMode::Line;
+        self.select_to_cursor_pos();
+        true
+    }
+
+    fn set_named_mark(&mut self, name: char) {
+        NAMED_MARKS
+            .lock()
+            .entry(self.delegate.pane_id())
+            .or_default()
+            .insert(name, (self.cursor.x, self.cursor.y));
+    }
+
+    /// Returns false if there is no mark with that name
+    fn move_to_named_mark(&mut self, name: char, exact_position: bool) -> bool {
+        let mark = NAMED_MARKS
+            .lock()
+            .get(&self.delegate.pane_id())
+            .and_then(|marks| marks.get(&name).copied());
+        match mark {
+            Some((x, y)) => {
+                self.cursor.y = y;
+                if exact_position {
+                    self.cursor.x = x;
+                    self.select_to_cursor_pos();
+                } else {
+                    self.move_to_start_of_line_content();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Resolve a pending key; returns false if the key isn't valid
+    /// in that context
+    fn resolve_pending_key(&mut self, pending: PendingKey, c: char) -> bool {
+        match pending {
+            PendingKey::TextObject { inner } => self.select_text_object(c, inner),
+            PendingKey::SetMark => {
+                if !c.is_ascii_alphabetic() {
+                    return false;
+                }
+                self.set_named_mark(c);
+                true
+            }
+            PendingKey::MoveToMark { exact_position } => self.move_to_named_mark(c, exact_position),
+            PendingKey::Register => {
+                if !is_valid_register(c) {
+                    return false;
+                }
+                self.register.replace(c);
+                true
+            }
         }
     }
 
@@ -1083,28 +1367,45 @@ impl Pane for CopyOverlay {
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         let mut render = self.render.lock();
-        if let Some(jump) = render.pending_jump.take() {
+        if let Some(pending) = render.pending_jump.take() {
             match (key, mods) {
                 (KeyCode::Char(c), KeyModifiers::NONE)
                 | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
                     let jump = Jump {
-                        forward: jump.forward,
-                        prev_char: jump.prev_char,
+                        forward: pending.forward,
+                        prev_char: pending.prev_char,
                         target: c,
                     };
                     render.last_jump.replace(jump);
                     render.perform_jump(jump, false);
+                    for _ in 1..pending.count {
+                        render.perform_jump(jump, true);
+                    }
                 }
-                _ => {
-                    self.delegate
-                        .perform_actions(vec![termwiz::escape::Action::Control(
-                            termwiz::escape::ControlCode::Bell,
-                        )]);
-                }
+                _ => self.ring_bell(),
+            }
+            return Ok(());
+        }
+
+        if let Some(pending) = render.pending_key.take() {
+            let resolved = match (key, mods) {
+                (KeyCode::Char(c), KeyModifiers::NONE)
+                | (KeyCode::Char(c), KeyModifiers::SHIFT) => render.resolve_pending_key(pending, c),
+                _ => false,
+            };
+            if !resolved {
+                self.ring_bell();
             }
             return Ok(());
         }
 
+        if !render.editing_search {
+            if let (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE) = (key, mods) {
+                render.push_count_digit(c);
+                return Ok(());
+            }
+        }
+
         if render.editing_search {
             match (key, mods) {
                 (KeyCode::Char(c), KeyModifiers::NONE)
@@ -1128,54 +1429,83 @@ impl Pane for CopyOverlay {
     fn perform_assignment(&self, assignment: &KeyAssignment) -> PerformAssignmentResult {
         use CopyModeAssignment::*;
         let mut render = self.render.lock();
-        if render.pending_jump.is_some() {
+        if render.pending_jump.is_some() || render.pending_key.is_some() {
             // Block key assignments until key_down is called
             // and resolves the next state
             return PerformAssignmentResult::BlockAssignmentAndRouteToKeyDown;
         }
+        if matches!(assignment, KeyAssignment::CopyMode(MoveToStartOfLine))
+            && render.count.is_some()
+        {
+            // As in vim, 0 is part of the count once one has been started
+            render.push_count_digit('0');
+            return PerformAssignmentResult::Handled;
+        }
+        let explicit_count = render.count.take();
+        let count = explicit_count.unwrap_or(1);
+        let delta = count as isize;
         match assignment {
             KeyAssignment::CopyMode(assignment) => {
                 match assignment {
                     MoveToViewportBottom => render.move_to_viewport_bottom(),
                     MoveToViewportTop => render.move_to_viewport_top(),
                     MoveToViewportMiddle => render.move_to_viewport_middle(),
+                    MoveToScrollbackTop | MoveToScrollbackBottom if explicit_count.is_some() => {
+                        render.move_to_line(count)
+                    }
                     MoveToScrollbackTop => render.move_to_top(),
                     MoveToScrollbackBottom => render.move_to_bottom(),
                     MoveToStartOfLineContent => render.move_to_start_of_line_content(),
-                    MoveToEndOfLineContent => render.move_to_end_of_line_content(),
+                    MoveToEndOfLineContent => render.move_to_end_of_line_content(count),
                     MoveToStartOfLine => render.move_to_start_of_line(),
-                    MoveToStartOfNextLine => render.move_to_start_of_next_line(),
+                    MoveToStartOfNextLine => render.move_to_start_of_next_line(count),
                     MoveToSelectionOtherEnd => render.move_to_selection_other_end(),
                     MoveToSelectionOtherEndHoriz => render.move_to_selection_other_end_horiz(),
-                    MoveBackwardWord => render.move_backward_one_word(),
-                    MoveForwardWord => render.move_forward_one_word(),
-                    MoveForwardWordEnd => render.move_to_end_of_word(),
-                    MoveRight => render.move_right_single_cell(),
-                    MoveLeft => render.move_left_single_cell(),
-                    MoveUp => render.move_up_single_row(),
-                    MoveDown => render.move_down_single_row(),
-                    MoveByPage(n) => render.move_by_page(**n),
-                    PageUp => render.move_by_page(-1.0),
-                    PageDown => render.move_by_page(1.0),
+                    MoveBackwardWord => (0..count).for_each(|_| render.move_backward_one_word()),
+                    MoveForwardWord => (0..count).for_each(|_| render.move_forward_one_word()),
+                    MoveForwardWordEnd => (0..count).for_each(|_| render.move_to_end_of_word()),
+                    MoveRight => render.move_right(count),
+                    MoveLeft => render.move_left(count),
+                    MoveUp => render.move_up(count),
+                    MoveDown => render.move_down(count),
+                    MoveByPage(n) => render.move_by_page(**n * count as f64),
+                    PageUp => render.move_by_page(-(count as f64)),
+                    PageDown => render.move_by_page(count as f64),
                     Close => render.close(),
-                    PriorMatch => render.prior_match(),
-                    NextMatch => render.next_match(),
-                    PriorMatchPage => render.prior_match_page(),
-                    NextMatchPage => render.next_match_page(),
+                    PriorMatch => (0..count).for_each(|_| render.prior_match()),
+                    NextMatch => (0..count).for_each(|_| render.next_match()),
+                    PriorMatchPage => (0..count).for_each(|_| render.prior_match_page()),
+                    NextMatchPage => (0..count).for_each(|_| render.next_match_page()),
                     CycleMatchType => render.cycle_match_type(),
                     ClearPattern => render.clear_pattern(),
                     EditPattern => render.edit_pattern(),
                     AcceptPattern => render.accept_pattern(),
                     SetSelectionMode(mode) => render.set_selection_mode(mode),
                     ClearSelectionMode => render.clear_selection_mode(),
-                    MoveBackwardSemanticZone => render.move_by_zone(-1, None),
-                    MoveForwardSemanticZone => render.move_by_zone(1, None),
-                    MoveBackwardZoneOfType(zone_type) => render.move_by_zone(-1, Some(*zone_type)),
-                    MoveForwardZoneOfType(zone_type) => render.move_by_zone(1, Some(*zone_type)),
-                    JumpForward { prev_char } => render.jump(true, *prev_char),
-                    JumpBackward { prev_char } => render.jump(false, *prev_char),
-                    JumpAgain => render.jump_again(false),
-                    JumpReverse => render.jump_again(true),
+                    MoveBackwardSemanticZone => render.move_by_zone(-delta, None),
+                    MoveForwardSemanticZone => render.move_by_zone(delta, None),
+                    MoveBackwardZoneOfType(zone_type) => {
+                        render.move_by_zone(-delta, Some(*zone_type))
+                    }
+                    MoveForwardZoneOfType(zone_type) => {
+                        render.move_by_zone(delta, Some(*zone_type))
+                    }
+                    MoveBackwardMark => render.move_by_mark(-delta),
+                    MoveForwardMark => render.move_by_mark(delta),
+                    JumpForward { prev_char } => render.jump(true, *prev_char, count),
+                    JumpBackward { prev_char } => render.jump(false, *prev_char, count),
+                    JumpAgain => render.jump_again(false, count),
+                    JumpReverse => render.jump_again(true, count),
+                    SelectTextObject { inner } => {
+                        render.pending_key = Some(PendingKey::TextObject { inner: *inner })
+                    }
+                    SetNamedMark => render.pending_key = Some(PendingKey::SetMark),
+                    MoveToNamedMark { exact_position } => {
+                        render.pending_key = Some(PendingKey::MoveToMark {
+                            exact_position: *exact_position,
+                        })
+                    }
+                    SelectRegister => render.pending_key = Some(PendingKey::Register),
                 }
                 PerformAssignmentResult::Handled
             }
@@ -1224,6 +1554,18 @@ impl Pane for CopyOverlay {
         self.delegate.get_current_working_dir()
     }
 
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        self.delegate.get_command_blocks()
+    }
+
+    fn get_marks(&self) -> Vec<StableRowIndex> {
+        self.delegate.get_marks()
+    }
+
+    fn get_badge(&self) -> Option<String> {
+        self.delegate.get_badge()
+    }
+
     fn get_cursor_position(&self) -> StableCursorPosition {
         let renderer = self.render.lock();
         if renderer.editing_search {
@@ -1476,6 +1818,12 @@ impl Pane for CopyOverlay {
     }
 }
 
+/// Search results have an identifier that is only meaningful within
+/// a single search, so compare results by their position
+fn same_match_position(a: &SearchResult, b: &SearchResult) -> bool {
+    a.start_x == b.start_x && a.start_y == b.start_y && a.end_x == b.end_x && a.end_y == b.end_y
+}
+
 fn is_whitespace_word(word: &str) -> bool {
     if let Some(c) = word.chars().next() {
         c.is_whitespace()
@@ -1484,6 +1832,160 @@ fn is_whitespace_word(word: &str) -> bool {
     }
 }
 
+/// Classifies cells for the `w` text object: whitespace, keyword
+/// characters and punctuation are distinct classes
+fn word_class(s: &str) -> u8 {
+    if s.chars().all(char::is_whitespace) {
+        0
+    } else if s.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        1
+    } else {
+        2
+    }
+}
+
+/// Classifies cells for the `W` text object, where any run of
+/// non-whitespace is considered to be a word
+fn bigword_class(s: &str) -> u8 {
+    if s.chars().all(char::is_whitespace) {
+        0
+    } else {
+        1
+    }
+}
+
+/// Returns the range of the run of cells with the same class as the
+/// cell at pos.  The outer form also includes the whitespace that
+/// follows the run, or that precedes it if there is none following.
+fn word_object(
+    cells: &[(usize, String)],
+    pos: usize,
+    inner: bool,
+    class: fn(&str) -> u8,
+) -> Option<(usize, usize)> {
+    let run = |pos: usize| {
+        let target = class(&cells[pos].1);
+        let mut first = pos;
+        while first > 0 && class(&cells[first - 1].1) == target {
+            first -= 1;
+        }
+        let mut last = pos;
+        while last + 1 < cells.len() && class(&cells[last + 1].1) == target {
+            last += 1;
+        }
+        (first, last)
+    };
+
+    let (first, last) = run(pos);
+    if inner {
+        return Some((first, last));
+    }
+    if last + 1 < cells.len() {
+        let (_, next_last) = run(last + 1);
+        return Some((first, next_last));
+    }
+    if first > 0 && class(&cells[first - 1].1) == 0 {
+        let (prev_first, _) = run(first - 1);
+        return Some((prev_first, last));
+    }
+    Some((first, last))
+}
+
+/// Returns the range of the quoted string that contains the cell at
+/// pos, or failing that, the first one that follows it on the line.
+/// Quotes that are escaped by a backslash are not considered to
+/// delimit a string.
+fn quote_object(
+    cells: &[(usize, String)],
+    pos: usize,
+    inner: bool,
+    quote: char,
+) -> Option<(usize, usize)> {
+    let quote = quote.to_string();
+    let is_escaped = |idx: usize| {
+        let backslashes = cells[..idx]
+            .iter()
+            .rev()
+            .take_while(|(_, s)| s == "\\")
+            .count();
+        backslashes % 2 == 1
+    };
+    let quotes: Vec<usize> = cells
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, (_, s))| {
+            if *s == quote && !is_escaped(idx) {
+                Some(idx)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(_, close)| pos <= close)?;
+    if inner {
+        if close == open + 1 {
+            return None;
+        }
+        Some((open + 1, close - 1))
+    } else {
+        Some((open, close))
+    }
+}
+
+/// Returns the range of the innermost bracketed text that contains
+/// the cell at pos
+fn bracket_object(
+    cells: &[(usize, String)],
+    pos: usize,
+    inner: bool,
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    let is = |idx: usize, c: char| cells[idx].1.chars().eq(std::iter::once(c));
+
+    let mut depth = 0;
+    let mut start = None;
+    for idx in (0..=pos).rev() {
+        if is(idx, close) && idx != pos {
+            depth += 1;
+        } else if is(idx, open) {
+            if depth == 0 {
+                start = Some(idx);
+                break;
+            }
+            depth -= 1;
+        }
+    }
+    let start = start?;
+
+    let mut depth = 0;
+    let mut end = None;
+    for idx in start + 1..cells.len() {
+        if is(idx, open) {
+            depth += 1;
+        } else if is(idx, close) {
+            if depth == 0 {
+                end = Some(idx);
+                break;
+            }
+            depth -= 1;
+        }
+    }
+    let end = end?;
+
+    if inner {
+        if end == start + 1 {
+            return None;
+        }
+        Some((start + 1, end - 1))
+    } else {
+        Some((start, end))
+    }
+}
+
 pub fn search_key_table() -> KeyTable {
     let mut table = KeyTable::default();
     for (key, mods, action) in [
@@ -1538,7 +2040,13 @@ pub fn search_key_table() -> KeyTable {
             KeyAssignment::CopyMode(CopyModeAssignment::ClearPattern),
         ),
     ] {
-        table.insert((key, mods), KeyTableEntry { action });
+        table.insert(
+            (key, mods),
+            KeyTableEntry {
+                action,
+                description: None,
+            },
+        );
     }
     table
 }
@@ -1859,6 +2367,45 @@ pub fn copy_key_table() -> KeyTable {
             Modifiers::NONE,
             KeyAssignment::CopyMode(CopyModeAssignment::JumpForward { prev_char: true }),
         ),
+        (
+            WKeyCode::Char('i'),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::SelectTextObject { inner: true }),
+        ),
+        (
+            WKeyCode::Char('a'),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::SelectTextObject { inner: false }),
+        ),
+        (
+            WKeyCode::Char('m'),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::SetNamedMark),
+        ),
+        (
+            WKeyCode::Char('\''),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::MoveToNamedMark {
+                exact_position: false,
+            }),
+        ),
+        (
+            WKeyCode::Char('`'),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::MoveToNamedMark {
+                exact_position: true,
+            }),
+        ),
+        (
+            WKeyCode::Char('"'),
+            Modifiers::NONE,
+            KeyAssignment::CopyMode(CopyModeAssignment::SelectRegister),
+        ),
+        (
+            WKeyCode::Char('"'),
+            Modifiers::SHIFT,
+            KeyAssignment::CopyMode(CopyModeAssignment::SelectRegister),
+        ),
         (
             WKeyCode::Home,
             Modifiers::NONE,
@@ -1870,7 +2417,140 @@ pub fn copy_key_table() -> KeyTable {
             KeyAssignment::CopyMode(CopyModeAssignment::MoveToEndOfLineContent),
         ),
     ] {
-        table.insert((key, mods), KeyTableEntry { action });
+        table.insert(
+            (key, mods),
+            KeyTableEntry {
+                action,
+                description: None,
+            },
+        );
     }
     table
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cells(text: &str) -> Vec<(usize, String)> {
+        text.chars()
+            .enumerate()
+            .map(|(idx, c)| (idx, c.to_string()))
+            .collect()
+    }
+
+    fn text_of(text: &str, range: Option<(usize, usize)>) -> Option<String> {
+        let (first, last) = range?;
+        Some(text.chars().skip(first).take(last + 1 - first).collect())
+    }
+
+    #[test]
+    fn word_objects() {
+        let text = "foo_bar  baz.qux";
+        let c = cells(text);
+        let word = |pos, inner| text_of(text, word_object(&c, pos, inner, word_class));
+        assert_eq!(word(1, true), Some("foo_bar".to_string()));
+        assert_eq!(word(1, false), Some("foo_bar  ".to_string()));
+        // On whitespace, the outer form includes the following word
+        assert_eq!(word(7, true), Some("  ".to_string()));
+        assert_eq!(word(7, false), Some("  baz".to_string()));
+        assert_eq!(word(12, true), Some(".".to_string()));
+
+        // At the end of the line there is no following whitespace, and
+        // the preceding cell is punctuation rather than whitespace
+        assert_eq!(word(15, false), Some("qux".to_string()));
+        let text = "foo bar";
+        let c = cells(text);
+        assert_eq!(
+            text_of(text, word_object(&c, 6, false, word_class)),
+            Some(" bar".to_string())
+        );
+        // At the start of the line, the following whitespace is used
+        assert_eq!(
+            text_of(text, word_object(&c, 0, false, bigword_class)),
+            Some("foo ".to_string())
+        );
+
+        let text = "a.b c";
+        let c = cells(text);
+        assert_eq!(
+            text_of(text, word_object(&c, 0, true, bigword_class)),
+            Some("a.b".to_string())
+        );
+    }
+
+    #[test]
+    fn quote_objects() {
+        let text = r#"say "hi" and "bye""#;
+        let c = cells(text);
+        let quote = |pos, inner| text_of(text, quote_object(&c, pos, inner, '"'));
+        assert_eq!(quote(5, true), Some("hi".to_string()));
+        assert_eq!(quote(5, false), Some("\"hi\"".to_string()));
+        // Before any quote, the following string is selected
+        assert_eq!(quote(0, true), Some("hi".to_string()));
+        // The closing quote is part of the string
+        assert_eq!(quote(17, true), Some("bye".to_string()));
+        // Between strings, the next one is selected
+        assert_eq!(quote(9, true), Some("bye".to_string()));
+
+        // Escaped quotes don't delimit the string
+        let text = r#"x "a\"b" "c\\" d"#;
+        let c = cells(text);
+        let quote = |pos, inner| text_of(text, quote_object(&c, pos, inner, '"'));
+        assert_eq!(quote(3, true), Some(r#"a\"b"#.to_string()));
+        assert_eq!(quote(11, true), Some(r#"c\\"#.to_string()));
+
+        // Empty and unterminated strings
+        let text = r#"'' 'open"#;
+        let c = cells(text);
+        assert_eq!(quote_object(&c, 0, true, '\''), None);
+        assert_eq!(
+            text_of(text, quote_object(&c, 0, false, '\'')),
+            Some("''".to_string())
+        );
+        assert_eq!(quote_object(&c, 4, true, '\''), None);
+    }
+
+    #[test]
+    fn bracket_objects() {
+        let text = "f(a, (b), [c])";
+        let c = cells(text);
+        let paren = |pos, inner| text_of(text, bracket_object(&c, pos, inner, '(', ')'));
+        assert_eq!(paren(2, true), Some("a, (b), [c]".to_string()));
+        // The innermost pair that contains the position is used
+        assert_eq!(paren(6, true), Some("b".to_string()));
+        assert_eq!(paren(6, false), Some("(b)".to_string()));
+        // On a bracket, that bracket's pair is selected
+        assert_eq!(paren(5, false), Some("(b)".to_string()));
+        assert_eq!(paren(7, false), Some("(b)".to_string()));
+        assert_eq!(paren(13, false), Some(text[1..].to_string()));
+        assert_eq!(
+            text_of(text, bracket_object(&c, 11, true, '[', ']')),
+            Some("c".to_string())
+        );
+
+        // Outside of any brackets, or unbalanced at the line edges
+        assert_eq!(paren(0, true), None);
+        let text = "(a";
+        let c = cells(text);
+        assert_eq!(bracket_object(&c, 1, true, '(', ')'), None);
+        let text = "a)";
+        let c = cells(text);
+        assert_eq!(bracket_object(&c, 0, true, '(', ')'), None);
+        let text = "()";
+        let c = cells(text);
+        assert_eq!(bracket_object(&c, 0, true, '(', ')'), None);
+        assert_eq!(bracket_object(&c, 1, false, '(', ')'), Some((0, 1)));
+    }
+
+    #[test]
+    fn named_marks_are_forgotten() {
+        NAMED_MARKS
+            .lock()
+            .entry(4242)
+            .or_default()
+            .insert('a', (1, 2));
+        pane_removed(4242);
+        assert!(NAMED_MARKS.lock().get(&4242).is_none());
+    }
+}
@@ -0,0 +1,805 @@
+//! The global search overlay searches the scrollback of every pane in
+//! the mux, across all windows, tabs and workspaces, and presents the
+//! matches grouped by pane.  Choosing a match activates its pane and
+//! opens copy mode with the match selected.
+//! The panes are searched on the main thread, as the overlay itself runs
+//! in a separate thread; remote panes are searched by their server.
+use crate::termwindow::TermWindowNotif;
+use mux::pane::{Pane, PaneId, Pattern, SearchResult};
+use mux::renderable::RenderableDimensions;
+use mux::termwiztermtab::TermWizTerminal;
+use mux::Mux;
+use std::ops::Range;
+use std::sync::Arc;
+use termwiz::cell::{AttributeChange, Intensity};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::{truncate_left, truncate_right};
+use wezterm_term::{unicode_column_width, Line, StableRowIndex};
+use window::WindowOps;
+
+/// Bounds the number of matches that are collected from any single pane
+const MAX_RESULTS_PER_PANE: u32 = 1000;
+/// The status line at the top of the overlay
+const ROW_OVERHEAD: usize = 1;
+
+pub struct GlobalSearchArgs {
+    pub pattern: Pattern,
+    pub context_lines: usize,
+}
+
+/// A line of text around a match.  For lines that are part of the
+/// match, `matched` holds the matching portion of the text.
+struct ContextLine {
+    row: StableRowIndex,
+    before: String,
+    matched: String,
+    after: String,
+}
+
+struct SearchMatch {
+    pane_id: PaneId,
+    result: SearchResult,
+    lines: Vec<ContextLine>,
+}
+
+/// The matches from a single pane
+struct PaneMatches {
+    label: String,
+    matches: Vec<SearchMatch>,
+}
+
+enum DisplayRow {
+    Header(String),
+    Separator,
+    Line { match_idx: usize, line_idx: usize },
+}
+
+/// Returns the range of rows to show for a match, including up to
+/// `context_lines` rows either side of it
+fn context_range(
+    dims: &RenderableDimensions,
+    result: &SearchResult,
+    context_lines: usize,
+) -> Range<StableRowIndex> {
+    let context_lines = context_lines as StableRowIndex;
+    let first = result
+        .start_y
+        .saturating_sub(context_lines)
+        .max(dims.scrollback_top);
+    let end = (result.end_y + 1 + context_lines)
+        .min(dims.scrollback_top + dims.scrollback_rows as StableRowIndex);
+    first..end.max(first)
+}
+
+/// Splits the lines around a match into the text before, within
+/// and after the matched portion
+fn context_lines(top: StableRowIndex, lines: &[Line], result: &SearchResult) -> Vec<ContextLine> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let row = top + idx as StableRowIndex;
+            let len = line.len();
+            let (start, end) = if row < result.start_y || row > result.end_y {
+                (len, len)
+            } else {
+                let start = if row == result.start_y {
+                    result.start_x.min(len)
+                } else {
+                    0
+                };
+                let end = if row == result.end_y {
+                    result.end_x.min(len).max(start)
+                } else {
+                    len
+                };
+                (start, end)
+            };
+            let before = line.columns_as_str(0..start);
+            ContextLine {
+                row,
+                before: if start == len {
+                    before.trim_end().to_string()
+                } else {
+                    before
+                },
+                matched: line.columns_as_str(start..end),
+                after: line.columns_as_str(end..len).trim_end().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Fetches the context for a match.  Remote panes only have a
+/// partial cache of their lines, so the lines are fetched through
+/// the pane rather than read from that cache.
+async fn context_for_result(
+    pane: &Arc<dyn Pane>,
+    result: &SearchResult,
+    context_lines: usize,
+) -> Vec<ContextLine> {
+    let range = context_range(&pane.get_dimensions(), result, context_lines);
+    let (top, lines) = match pane.fetch_lines(range.clone()).await {
+        Ok(lines) => lines,
+        Err(err) => {
+            log::error!(
+                "global search failed to fetch lines from pane {}: {err:#}",
+                pane.pane_id()
+            );
+            pane.get_lines(range)
+        }
+    };
+    self::context_lines(top, &lines, result)
+}
+
+/// Must be called on the Mux thread!
+async fn search_all_panes(pattern: Pattern, context_lines: usize) -> Vec<PaneMatches> {
+    let mux = Mux::get();
+    let mut groups = vec![];
+
+    for workspace in mux.iter_workspaces() {
+        for window_id in mux.iter_windows_in_workspace(&workspace) {
+            let tabs: Vec<_> = match mux.get_window(window_id) {
+                Some(window) => window.iter().cloned().collect(),
+                None => continue,
+            };
+            for (tab_idx, tab) in tabs.iter().enumerate() {
+                let tab_title = tab.get_title();
+                for pos in tab.iter_panes_ignoring_zoom() {
+                    let pane = pos.pane;
+                    let pane_id = pane.pane_id();
+                    let dims = pane.get_dimensions();
+                    let range = dims.scrollback_top
+                        ..dims.scrollback_top + dims.scrollback_rows as StableRowIndex;
+                    let mut results = match pane
+                        .search(pattern.clone(), range, Some(MAX_RESULTS_PER_PANE))
+                        .await
+                    {
+                        Ok(results) => results,
+                        Err(err) => {
+                            log::error!("global search failed in pane {pane_id}: {err:#}");
+                            continue;
+                        }
+                    };
+                    if results.is_empty() {
+                        continue;
+                    }
+                    results.sort();
+
+                    let title = if tab_title.is_empty() {
+                        pane.get_title()
+                    } else {
+                        format!("{tab_title} - {}", pane.get_title())
+                    };
+                    let mut matches = vec![];
+                    for result in results {
+                        matches.push(SearchMatch {
+                            pane_id,
+                            lines: context_for_result(&pane, &result, context_lines).await,
+                            result,
+                        });
+                    }
+                    groups.push(PaneMatches {
+                        label: format!("{workspace}: tab {} pane {pane_id}: {title}", tab_idx + 1),
+                        matches,
+                    });
+                }
+            }
+        }
+    }
+
+    groups
+}
+
+// The search futures are not Send, but spawn_into_main_thread wants
+// the overall future to be Send.  We spawn the search separately on
+// the main thread and use a channel to funnel the results back.
+fn search_trampoline(
+    pattern: Pattern,
+    context_lines: usize,
+) -> smol::channel::Receiver<Vec<PaneMatches>> {
+    let (tx, rx) = smol::channel::bounded(1);
+    promise::spawn::spawn(async move {
+        let _ = tx
+            .send(search_all_panes(pattern, context_lines).await)
+            .await;
+    })
+    .detach();
+    rx
+}
+
+/// The matches flattened into the rows that display them
+struct Layout {
+    matches: Vec<SearchMatch>,
+    rows: Vec<DisplayRow>,
+    /// The range of display rows occupied by each match
+    match_rows: Vec<(usize, usize)>,
+}
+
+impl Layout {
+    fn new(groups: Vec<PaneMatches>, context_lines: usize) -> Self {
+        let mut matches = vec![];
+        let mut rows = vec![];
+        let mut match_rows = vec![];
+        for group in groups {
+            rows.push(DisplayRow::Header(group.label));
+            for (idx, m) in group.matches.into_iter().enumerate() {
+                if idx > 0 && context_lines > 0 {
+                    rows.push(DisplayRow::Separator);
+                }
+                let match_idx = matches.len();
+                let first = rows.len();
+                for line_idx in 0..m.lines.len() {
+                    rows.push(DisplayRow::Line {
+                        match_idx,
+                        line_idx,
+                    });
+                }
+                match_rows.push((first, rows.len()));
+                matches.push(m);
+            }
+        }
+        Self {
+            matches,
+            rows,
+            match_rows,
+        }
+    }
+}
+
+struct GlobalSearchState {
+    pattern: Pattern,
+    context_lines: usize,
+    editing: bool,
+    searched: bool,
+    matches: Vec<SearchMatch>,
+    pane_count: usize,
+    rows: Vec<DisplayRow>,
+    /// The range of display rows occupied by each match
+    match_rows: Vec<(usize, usize)>,
+    active_idx: usize,
+    top_row: usize,
+    max_rows: usize,
+    window: ::window::Window,
+}
+
+impl GlobalSearchState {
+    fn search(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        term.render(&[
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::Text(format!("Searching all panes for {} ...", *self.pattern)),
+        ])?;
+
+        let pattern = self.pattern.clone();
+        let context_lines = self.context_lines;
+        let groups = smol::block_on(promise::spawn::spawn_into_main_thread(async move {
+            search_trampoline(pattern, context_lines)
+                .recv()
+                .await
+                .unwrap_or_default()
+        }));
+
+        self.pane_count = groups.len();
+        let layout = Layout::new(groups, self.context_lines);
+        self.matches = layout.matches;
+        self.rows = layout.rows;
+        self.match_rows = layout.match_rows;
+
+        self.searched = true;
+        self.editing = false;
+        self.active_idx = 0;
+        self.top_row = 0;
+        Ok(())
+    }
+
+    fn cycle_match_type(&mut self) {
+        self.pattern = match &self.pattern {
+            Pattern::CaseSensitiveString(s) => Pattern::CaseInSensitiveString(s.clone()),
+            Pattern::CaseInSensitiveString(s) => Pattern::Regex(s.clone()),
+            Pattern::Regex(s) => Pattern::CaseSensitiveString(s.clone()),
+        };
+    }
+
+    fn match_type(&self) -> &'static str {
+        match &self.pattern {
+            Pattern::CaseSensitiveString(_) => "case-sensitive",
+            Pattern::CaseInSensitiveString(_) => "ignore-case",
+            Pattern::Regex(_) => "regex",
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let max = self.matches.len() as isize - 1;
+        self.active_idx = (self.active_idx as isize + delta).max(0).min(max) as usize;
+
+        let (first, end) = self.match_rows[self.active_idx];
+        // Keep the pane header visible when moving up to the first
+        // match of a pane
+        let first = match first.checked_sub(1).map(|idx| &self.rows[idx]) {
+            Some(DisplayRow::Header(_)) => first - 1,
+            _ => first,
+        };
+        if first < self.top_row {
+            self.top_row = first;
+        } else if end > self.top_row + self.max_rows {
+            self.top_row = end.saturating_sub(self.max_rows);
+        }
+    }
+
+    fn page_size(&self) -> isize {
+        (self.max_rows / (self.context_lines * 2 + 2)).max(1) as isize
+    }
+
+    fn render(&self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(1);
+
+        let status = if self.editing {
+            format!(
+                "Search all panes ({}): {}",
+                self.match_type(),
+                *self.pattern
+            )
+        } else {
+            format!(
+                "{} matches in {} panes for {} ({}).  \
+                 Enter=activate  /=edit  Esc=cancel",
+                self.matches.len(),
+                self.pane_count,
+                *self.pattern,
+                self.match_type()
+            )
+        };
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(truncate_right(&status, max_width)),
+        ];
+
+        if self.editing && !self.searched {
+            changes.push(Change::Text(format!(
+                "\r\n{}",
+                truncate_right(
+                    "Enter=search  CTRL-R=cycle match type  Esc=cancel",
+                    max_width
+                )
+            )));
+        }
+
+        for (row_num, row) in self
+            .rows
+            .iter()
+            .skip(self.top_row)
+            .take(self.max_rows)
+            .enumerate()
+        {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(row_num + ROW_OVERHEAD),
+            });
+            match row {
+                DisplayRow::Header(label) => {
+                    changes.push(AttributeChange::Intensity(Intensity::Bold).into());
+                    changes.push(Change::Text(truncate_right(label, max_width)));
+                    changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+                }
+                DisplayRow::Separator => {
+                    changes.push(AttributeChange::Intensity(Intensity::Half).into());
+                    changes.push(Change::Text("  --".to_string()));
+                    changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+                }
+                DisplayRow::Line {
+                    match_idx,
+                    line_idx,
+                } => {
+                    let m = &self.matches[*match_idx];
+                    let line = &m.lines[*line_idx];
+                    self.render_line(
+                        &mut changes,
+                        m,
+                        line,
+                        *match_idx == self.active_idx,
+                        max_width,
+                    );
+                }
+            }
+        }
+
+        if self.editing {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(unicode_column_width(&status, None).min(max_width)),
+                y: Position::Absolute(0),
+            });
+        }
+
+        term.render(&changes)
+    }
+
+    fn render_line(
+        &self,
+        changes: &mut Vec<Change>,
+        m: &SearchMatch,
+        line: &ContextLine,
+        active: bool,
+        max_width: usize,
+    ) {
+        let is_match_line = line.row >= m.result.start_y && line.row <= m.result.end_y;
+        changes.push(Change::Text(
+            if active && is_match_line { "> " } else { "  " }.to_string(),
+        ));
+        let mut budget = max_width.saturating_sub(2);
+
+        if !is_match_line {
+            changes.push(AttributeChange::Intensity(Intensity::Half).into());
+            changes.push(Change::Text(truncate_right(&line.before, budget)));
+            changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+            return;
+        }
+
+        // Make sure that the matched text is visible, even if it is
+        // far over to the right of a long line
+        let matched_width = unicode_column_width(&line.matched, None);
+        let before = if unicode_column_width(&line.before, None) + matched_width > budget {
+            truncate_left(
+                &line.before,
+                budget.saturating_sub(matched_width).min(budget / 2),
+            )
+        } else {
+            line.before.clone()
+        };
+        budget = budget.saturating_sub(unicode_column_width(&before, None));
+        let matched = truncate_right(&line.matched, budget);
+        budget = budget.saturating_sub(unicode_column_width(&matched, None));
+        let after = truncate_right(&line.after, budget);
+
+        changes.push(Change::Text(before));
+        changes.push(AttributeChange::Reverse(true).into());
+        if active {
+            changes.push(AttributeChange::Intensity(Intensity::Bold).into());
+        }
+        changes.push(Change::Text(matched));
+        changes.push(AttributeChange::Reverse(false).into());
+        if active {
+            changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+        }
+        changes.push(Change::Text(after));
+    }
+
+    /// Activate the pane containing the active match and open copy
+    /// mode with the match selected
+    fn activate(&self) -> bool {
+        match self.matches.get(self.active_idx) {
+            Some(m) => {
+                let pane_id = m.pane_id;
+                let result = m.result;
+                let pattern = self.pattern.clone();
+                self.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        term_window.activate_search_result(pane_id, pattern, result);
+                    })));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        while let Ok(Some(event)) = term.poll_input(None) {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('G'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }) => {
+                    if self.editing && self.searched {
+                        self.editing = false;
+                    } else {
+                        break;
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('R'),
+                    modifiers: Modifiers::CTRL,
+                }) if self.editing => {
+                    self.cycle_match_type();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) if self.editing => {
+                    if !self.pattern.is_empty() {
+                        self.search(term)?;
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                }) if self.editing => {
+                    self.pattern.pop();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    modifiers: Modifiers::NONE | Modifiers::SHIFT,
+                }) if self.editing => {
+                    self.pattern.push(c);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    if self.activate() {
+                        break;
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('/'),
+                    ..
+                }) => {
+                    self.editing = true;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('q'),
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('j'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('N'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_by(1);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('k'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('P'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_by(-1);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageDown,
+                    ..
+                }) => {
+                    self.move_by(self.page_size());
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageUp,
+                    ..
+                }) => {
+                    self.move_by(-self.page_size());
+                }
+                InputEvent::Resized { rows, .. } => {
+                    self.max_rows = rows.saturating_sub(ROW_OVERHEAD);
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn global_search(
+    args: GlobalSearchArgs,
+    mut term: TermWizTerminal,
+    window: ::window::Window,
+) -> anyhow::Result<()> {
+    let size = term.get_screen_size()?;
+    let mut state = GlobalSearchState {
+        editing: args.pattern.is_empty(),
+        pattern: args.pattern,
+        context_lines: args.context_lines,
+        searched: false,
+        matches: vec![],
+        pane_count: 0,
+        rows: vec![],
+        match_rows: vec![],
+        active_idx: 0,
+        top_row: 0,
+        max_rows: size.rows.saturating_sub(ROW_OVERHEAD),
+        window,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("Search all panes".to_string())])?;
+    if !state.editing {
+        state.search(&mut term)?;
+    }
+    state.render(&mut term)?;
+    state.run_loop(&mut term)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::cell::CellAttributes;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn dims(scrollback_top: StableRowIndex, scrollback_rows: usize) -> RenderableDimensions {
+        RenderableDimensions {
+            scrollback_top,
+            scrollback_rows,
+            ..Default::default()
+        }
+    }
+
+    fn result(
+        start_y: StableRowIndex,
+        start_x: usize,
+        end_y: StableRowIndex,
+        end_x: usize,
+    ) -> SearchResult {
+        SearchResult {
+            start_y,
+            start_x,
+            end_y,
+            end_x,
+            match_id: 0,
+        }
+    }
+
+    fn lines(text: &[&str]) -> Vec<Line> {
+        text.iter()
+            .map(|t| Line::from_text(t, &CellAttributes::default(), SEQ_ZERO, None))
+            .collect()
+    }
+
+    fn split(lines: &[ContextLine]) -> Vec<(StableRowIndex, &str, &str, &str)> {
+        lines
+            .iter()
+            .map(|l| {
+                (
+                    l.row,
+                    l.before.as_str(),
+                    l.matched.as_str(),
+                    l.after.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn context_range_is_clamped_to_scrollback() {
+        assert_eq!(
+            context_range(&dims(10, 100), &result(50, 0, 50, 1), 2),
+            48..53
+        );
+        assert_eq!(
+            context_range(&dims(10, 100), &result(11, 0, 12, 1), 3),
+            10..16
+        );
+        assert_eq!(
+            context_range(&dims(10, 100), &result(108, 0, 109, 1), 3),
+            105..110
+        );
+        assert_eq!(context_range(&dims(0, 100), &result(0, 0, 0, 1), 0), 0..1);
+    }
+
+    #[test]
+    fn single_line_match() {
+        let lines = lines(&["before", "hello world  ", "after  "]);
+        assert_eq!(
+            split(&context_lines(4, &lines, &result(5, 6, 5, 11))),
+            vec![
+                (4, "before", "", ""),
+                (5, "hello ", "world", ""),
+                (6, "after", "", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_line_match() {
+        let lines = lines(&["one two", "three", "four five"]);
+        assert_eq!(
+            split(&context_lines(0, &lines, &result(0, 4, 2, 4))),
+            vec![
+                (0, "one ", "two", ""),
+                (1, "", "three", ""),
+                (2, "", "four", " five"),
+            ]
+        );
+    }
+
+    #[test]
+    fn match_beyond_end_of_line() {
+        // The line may have been changed since the search was run
+        let lines = lines(&["short"]);
+        assert_eq!(
+            split(&context_lines(0, &lines, &result(0, 10, 0, 20))),
+            vec![(0, "short", "", "")]
+        );
+    }
+
+    fn search_match(pane_id: PaneId, num_lines: usize) -> SearchMatch {
+        SearchMatch {
+            pane_id,
+            result: result(0, 0, 0, 1),
+            lines: (0..num_lines)
+                .map(|row| ContextLine {
+                    row: row as StableRowIndex,
+                    before: String::new(),
+                    matched: String::new(),
+                    after: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn describe(rows: &[DisplayRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                DisplayRow::Header(label) => label.clone(),
+                DisplayRow::Separator => "--".to_string(),
+                DisplayRow::Line {
+                    match_idx,
+                    line_idx,
+                } => format!("{match_idx}:{line_idx}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn layout_groups_matches_by_pane() {
+        let groups = || {
+            vec![
+                PaneMatches {
+                    label: "pane 1".to_string(),
+                    matches: vec![search_match(1, 3), search_match(1, 2)],
+                },
+                PaneMatches {
+                    label: "pane 2".to_string(),
+                    matches: vec![search_match(2, 1)],
+                },
+            ]
+        };
+
+        let layout = Layout::new(groups(), 1);
+        assert_eq!(
+            describe(&layout.rows),
+            vec!["pane 1", "0:0", "0:1", "0:2", "--", "1:0", "1:1", "pane 2", "2:0"]
+        );
+        assert_eq!(layout.match_rows, vec![(1, 4), (5, 7), (8, 9)]);
+        assert_eq!(
+            layout.matches.iter().map(|m| m.pane_id).collect::<Vec<_>>(),
+            vec![1, 1, 2]
+        );
+
+        // Without context there is nothing to separate
+        let layout = Layout::new(groups(), 0);
+        assert_eq!(
+            describe(&layout.rows),
+            vec!["pane 1", "0:0", "0:1", "0:2", "1:0", "1:1", "pane 2", "2:0"]
+        );
+        assert_eq!(layout.match_rows, vec![(1, 4), (4, 6), (7, 8)]);
+    }
+}
@@ -6,12 +6,11 @@
 //! time of writing our window layer doesn't provide an API for context
 //! menus.
 use crate::commands::derive_command_from_key_assignment;
+use crate::fuzzy::{boosted_score, render_highlighted, Matcher, Recents};
 use crate::inputmap::InputMap;
 use crate::termwindow::TermWindowNotif;
 use config::configuration;
-use config::keyassignment::{KeyAssignment, SpawnCommand, SpawnTabDomain};
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
+use config::keyassignment::{KeyAssignment, LauncherEntry, SpawnCommand, SpawnTabDomain};
 use mux::domain::{DomainId, DomainState};
 use mux::pane::PaneId;
 use mux::tab::TabId;
@@ -29,10 +28,29 @@ use window::WindowOps;
 
 pub use config::keyassignment::LauncherFlags;
 
+/// The name of the file in which we track how frequently and recently
+/// the launcher entries have been chosen
+const RECENTS_NAME: &str = "launcher";
+
 #[derive(Clone)]
 struct Entry {
     pub label: String,
     pub action: KeyAssignment,
+    /// Identifies the entry for the purposes of frecency ranking
+    pub recent_key: String,
+    /// The char positions in label that matched the filter term
+    pub positions: Vec<usize>,
+}
+
+impl Entry {
+    fn new(label: String, action: KeyAssignment, recent_key: String) -> Self {
+        Self {
+            label,
+            action,
+            recent_key,
+            positions: vec![],
+        }
+    }
 }
 
 pub struct LauncherTabEntry {
@@ -52,6 +70,7 @@ pub struct LauncherDomainEntry {
 
 pub struct LauncherArgs {
     flags: LauncherFlags,
+    entries: Vec<LauncherEntry>,
     domains: Vec<LauncherDomainEntry>,
     tabs: Vec<LauncherTabEntry>,
     pane_id: PaneId,
@@ -66,6 +85,7 @@ impl LauncherArgs {
     pub async fn new(
         title: &str,
         flags: LauncherFlags,
+        entries: Vec<LauncherEntry>,
         mux_window_id: WindowId,
         pane_id: PaneId,
         domain_id_of_current_tab: DomainId,
@@ -151,6 +171,7 @@ impl LauncherArgs {
 
         Self {
             flags,
+            entries,
             domains,
             tabs,
             pane_id,
@@ -175,22 +196,50 @@ struct LauncherState {
     window: ::window::Window,
     filtering: bool,
     flags: LauncherFlags,
+    recents: Recents,
+    /// The recent_key of the entry that should be selected
+    /// when the filter term is empty
+    preselected: Option<String>,
 }
 
 impl LauncherState {
     fn update_filter(&mut self) {
         if self.filter_term.is_empty() {
             self.filtered_entries = self.entries.clone();
+            // Float the most frequently and recently used entries to the
+            // top, preserving the natural order of the remainder.
+            // Tabs are left in tab order so that the numeric accelerators
+            // correspond to the tab numbers.
+            if !self.flags.contains(LauncherFlags::TABS) {
+                let recents = &self.recents;
+                self.filtered_entries.sort_by(|a, b| {
+                    recents
+                        .score(&b.recent_key)
+                        .partial_cmp(&recents.score(&a.recent_key))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            }
+            self.active_idx = self
+                .preselected
+                .as_ref()
+                .and_then(|key| {
+                    self.filtered_entries
+                        .iter()
+                        .position(|entry| entry.recent_key == *key)
+                })
+                .unwrap_or(0);
+            self.top_row = self.active_idx.saturating_sub(self.max_items);
             return;
         }
 
         self.filtered_entries.clear();
 
-        let matcher = SkimMatcherV2::default();
+        let matcher = Matcher::new();
 
         struct MatchResult {
             row_idx: usize,
             score: i64,
+            positions: Vec<usize>,
         }
 
         let mut scores: Vec<MatchResult> = self
@@ -198,16 +247,21 @@ impl LauncherState {
             .iter()
             .enumerate()
             .filter_map(|(row_idx, entry)| {
-                let score = matcher.fuzzy_match(&entry.label, &self.filter_term)?;
-                Some(MatchResult { row_idx, score })
+                let m = matcher.fuzzy_match(&entry.label, &self.filter_term)?;
+                Some(MatchResult {
+                    row_idx,
+                    score: boosted_score(m.score, self.recents.score(&entry.recent_key)),
+                    positions: m.positions,
+                })
             })
             .collect();
 
         scores.sort_by(|a, b| a.score.cmp(&b.score).reverse());
 
         for result in scores {
-            self.filtered_entries
-                .push(self.entries[result.row_idx].clone());
+            let mut entry = self.entries[result.row_idx].clone();
+            entry.positions = result.positions;
+            self.filtered_entries.push(entry);
         }
 
         self.active_idx = 0;
@@ -220,40 +274,56 @@ impl LauncherState {
         // section of the configuration.
         if args.flags.contains(LauncherFlags::LAUNCH_MENU_ITEMS) {
             for item in &config.launch_menu {
-                self.entries.push(Entry {
-                    label: match item.label.as_ref() {
-                        Some(label) => label.to_string(),
-                        None => match item.args.as_ref() {
-                            Some(args) => args.join(" "),
-                            None => "(default shell)".to_string(),
-                        },
+                let label = match item.label.as_ref() {
+                    Some(label) => label.to_string(),
+                    None => match item.args.as_ref() {
+                        Some(args) => args.join(" "),
+                        None => "(default shell)".to_string(),
                     },
-                    action: KeyAssignment::SpawnCommandInNewTab(item.clone()),
-                });
+                };
+                let recent_key = format!("launch_menu:{label}");
+                self.entries.push(Entry::new(
+                    label,
+                    KeyAssignment::SpawnCommandInNewTab(item.clone()),
+                    recent_key,
+                ));
             }
         }
 
+        // Entries passed in via ShowLauncherArgs
+        for item in &args.entries {
+            let recent_key = format!("lua:{}", item.id.as_ref().unwrap_or(&item.label));
+            self.entries.push(Entry::new(
+                item.label.clone(),
+                item.action.clone(),
+                recent_key,
+            ));
+        }
+
         for domain in &args.domains {
+            let recent_key = format!("domain:{}", domain.name);
             let entry = if domain.state == DomainState::Attached {
-                Entry {
-                    label: format!("New Tab ({})", domain.label),
-                    action: KeyAssignment::SpawnCommandInNewTab(SpawnCommand {
+                Entry::new(
+                    format!("New Tab ({})", domain.label),
+                    KeyAssignment::SpawnCommandInNewTab(SpawnCommand {
                         domain: SpawnTabDomain::DomainName(domain.name.to_string()),
                         ..SpawnCommand::default()
                     }),
-                }
+                    recent_key,
+                )
             } else {
-                Entry {
-                    label: format!("Attach {}", domain.label),
-                    action: KeyAssignment::AttachDomain(domain.name.to_string()),
-                }
+                Entry::new(
+                    format!("Attach {}", domain.label),
+                    KeyAssignment::AttachDomain(domain.name.to_string()),
+                    recent_key,
+                )
             };
 
             // Preselect the entry that corresponds to the active tab
             // at the time that the launcher was set up, so that pressing
             // Enter immediately afterwards spawns a tab in the same domain.
             if domain.domain_id == args.domain_id_of_current_tab {
-                self.active_idx = self.entries.len();
+                self.preselected = Some(entry.recent_key.clone());
             }
             self.entries.push(entry);
         }
@@ -261,35 +331,38 @@ impl LauncherState {
         if args.flags.contains(LauncherFlags::WORKSPACES) {
             for ws in &args.workspaces {
                 if *ws != args.active_workspace {
-                    self.entries.push(Entry {
-                        label: format!("Switch to workspace: `{}`", ws),
-                        action: KeyAssignment::SwitchToWorkspace {
+                    self.entries.push(Entry::new(
+                        format!("Switch to workspace: `{}`", ws),
+                        KeyAssignment::SwitchToWorkspace {
                             name: Some(ws.clone()),
                             spawn: None,
                         },
-                    });
+                        format!("workspace:{ws}"),
+                    ));
                 }
             }
-            self.entries.push(Entry {
-                label: format!(
+            self.entries.push(Entry::new(
+                format!(
                     "Create new Workspace (current is `{}`)",
                     args.active_workspace
                 ),
-                action: KeyAssignment::SwitchToWorkspace {
+                KeyAssignment::SwitchToWorkspace {
                     name: None,
                     spawn: None,
                 },
-            });
+                "workspace:".to_string(),
+            ));
         }
 
         for tab in &args.tabs {
-            self.entries.push(Entry {
-                label: match tab.pane_count {
+            self.entries.push(Entry::new(
+                match tab.pane_count {
                     Some(pane_count) => format!("{}. {pane_count} panes", tab.title),
                     None => format!("{}.", tab.title),
                 },
-                action: KeyAssignment::ActivateTab(tab.tab_idx as isize),
-            });
+                KeyAssignment::ActivateTab(tab.tab_idx as isize),
+                format!("tab:{}", tab.tab_id),
+            ));
         }
 
         if args.flags.contains(LauncherFlags::COMMANDS) {
@@ -302,10 +375,11 @@ impl LauncherState {
                     // Filter out some noisy, repetitive entries
                     continue;
                 }
-                self.entries.push(Entry {
-                    label: format!("{}. {}", cmd.brief, cmd.doc),
-                    action: cmd.action,
-                });
+                self.entries.push(Entry::new(
+                    format!("{}. {}", cmd.brief, cmd.doc),
+                    cmd.action,
+                    format!("command:{}", cmd.brief),
+                ));
             }
         }
 
@@ -342,10 +416,8 @@ impl LauncherState {
                     ),
                 };
 
-                key_entries.push(Entry {
-                    label,
-                    action: entry.action,
-                });
+                let recent_key = format!("key:{label}");
+                key_entries.push(Entry::new(label, entry.action, recent_key));
             }
             key_entries.sort_by(|a, b| a.label.cmp(&b.label));
             self.entries.append(&mut key_entries);
@@ -399,11 +471,15 @@ impl LauncherState {
                 changes.push(Change::Text("    ".to_string()));
             }
 
-            let mut line = crate::tabbar::parse_status_text(&entry.label, attr.clone());
-            if line.len() > max_width {
-                line.resize(max_width, termwiz::surface::SEQ_ZERO);
+            if entry.positions.is_empty() {
+                let mut line = crate::tabbar::parse_status_text(&entry.label, attr.clone());
+                if line.len() > max_width {
+                    line.resize(max_width, termwiz::surface::SEQ_ZERO);
+                }
+                changes.append(&mut line.changes(&attr));
+            } else {
+                render_highlighted(&entry.label, &entry.positions, max_width, &mut changes);
             }
-            changes.append(&mut line.changes(&attr));
             changes.push(Change::Text(" \r\n".to_string()));
 
             if entry_idx == self.active_idx {
@@ -430,6 +506,9 @@ impl LauncherState {
 
     fn launch(&self, active_idx: usize) -> bool {
         if let Some(entry) = self.filtered_entries.get(active_idx) {
+            if let Err(err) = Recents::register_access(RECENTS_NAME, &entry.recent_key) {
+                log::error!("Error recording launcher recents: {err:#}");
+            }
             let assignment = entry.action.clone();
             self.window.notify(TermWindowNotif::PerformAssignment {
                 pane_id: self.pane_id,
@@ -610,6 +689,8 @@ pub fn launcher(
         window,
         filtering: args.flags.contains(LauncherFlags::FUZZY),
         flags: args.flags,
+        recents: Recents::load(RECENTS_NAME),
+        preselected: None,
     };
 
     term.set_raw_mode()?;
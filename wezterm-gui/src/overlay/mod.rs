@@ -9,16 +9,22 @@ use wezterm_term::{TerminalConfiguration, TerminalSize};
 pub mod confirm_close_pane;
 pub mod copy;
 pub mod debug;
+pub mod global_search;
 pub mod launcher;
+pub mod prompt;
 pub mod quickselect;
+pub mod selector;
 
 pub use confirm_close_pane::{
     confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
 };
 pub use copy::{CopyModeParams, CopyOverlay};
 pub use debug::show_debug_overlay;
+pub use global_search::{global_search, GlobalSearchArgs};
 pub use launcher::{launcher, LauncherArgs, LauncherFlags};
+pub use prompt::{show_line_prompt_overlay, PromptInput};
 pub use quickselect::QuickSelectOverlay;
+pub use selector::selector;
 
 pub fn start_overlay<T, F>(
     term_window: &TermWindow,
@@ -0,0 +1,93 @@
+//! Prompts for a line of text on behalf of the `PromptInputLine` action
+use crate::termwindow::TermWindowNotif;
+use config::keyassignment::{KeyAssignment, PromptInputLine};
+use mux::pane::PaneId;
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent};
+use termwiz::lineedit::{Action, BasicHistory, History, LineEditor, LineEditorHost};
+use termwiz::surface::Change;
+use termwiz::terminal::Terminal;
+use window::WindowOps;
+
+/// What the user entered in response to a prompt
+#[derive(Debug, Clone)]
+pub enum PromptInput {
+    Line(String),
+    Choice { id: Option<String>, label: String },
+}
+
+impl PromptInput {
+    /// The text that is used to fill in an action
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Line(line) => line,
+            Self::Choice { id: Some(id), .. } => id,
+            Self::Choice { label, .. } => label,
+        }
+    }
+}
+
+/// Hands the outcome of a prompt back to the gui thread,
+/// where it is used to perform action.
+/// input is None if the prompt was cancelled.
+pub fn complete_prompt(
+    window: &::window::Window,
+    pane_id: PaneId,
+    action: KeyAssignment,
+    input: Option<PromptInput>,
+) {
+    window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+        term_window.complete_prompt(pane_id, &action, input);
+    })));
+}
+
+#[derive(Default)]
+struct PromptHost {
+    history: BasicHistory,
+}
+
+impl LineEditorHost for PromptHost {
+    fn history(&mut self) -> &mut dyn History {
+        &mut self.history
+    }
+
+    fn resolve_action(
+        &mut self,
+        event: &InputEvent,
+        _editor: &mut LineEditor<'_>,
+    ) -> Option<Action> {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) => Some(Action::Cancel),
+            _ => None,
+        }
+    }
+}
+
+pub fn show_line_prompt_overlay(
+    mut term: TermWizTerminal,
+    args: PromptInputLine,
+    window: ::window::Window,
+    pane_id: PaneId,
+) -> anyhow::Result<()> {
+    term.no_grab_mouse_in_raw_mode();
+
+    if !args.description.is_empty() {
+        term.render(&[Change::Text(format!(
+            "{}\r\n",
+            args.description.replace("\n", "\r\n")
+        ))])?;
+    }
+
+    let mut host = PromptHost::default();
+    let mut editor = LineEditor::new(&mut term);
+    editor.set_prompt("> ");
+    // CTRL-D produces an error rather than a cancellation,
+    // but we want to treat it as the latter
+    let line = editor.read_line(&mut host).unwrap_or(None);
+
+    complete_prompt(&window, pane_id, *args.action, line.map(PromptInput::Line));
+    Ok(())
+}
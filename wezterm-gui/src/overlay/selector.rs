@@ -0,0 +1,255 @@
+//! Prompts to choose one of a list of items on behalf of the
+//! `InputSelector` action.  Typing filters the list using fuzzy matching.
+use crate::fuzzy::{render_highlighted, Matcher};
+use crate::overlay::prompt::{complete_prompt, PromptInput};
+use config::keyassignment::InputSelector;
+use mux::pane::PaneId;
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::{AttributeChange, CellAttributes};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::truncate_right;
+
+const ROW_OVERHEAD: usize = 3;
+
+struct SelectorState {
+    args: InputSelector,
+    filter_term: String,
+    /// The index of each choice that matches the filter term,
+    /// along with the positions of the matching chars in its label
+    filtered: Vec<(usize, Vec<usize>)>,
+    active_idx: usize,
+    top_row: usize,
+    max_items: usize,
+}
+
+impl SelectorState {
+    fn update_filter(&mut self) {
+        self.active_idx = 0;
+        self.top_row = 0;
+
+        if self.filter_term.is_empty() {
+            self.filtered = (0..self.args.choices.len())
+                .map(|idx| (idx, vec![]))
+                .collect();
+            return;
+        }
+
+        let matcher = Matcher::new();
+        let mut scores: Vec<(i64, usize, Vec<usize>)> = self
+            .args
+            .choices
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, choice)| {
+                let m = matcher.fuzzy_match(&choice.label, &self.filter_term)?;
+                Some((m.score, idx, m.positions))
+            })
+            .collect();
+        scores.sort_by(|a, b| a.0.cmp(&b.0).reverse());
+
+        self.filtered = scores
+            .into_iter()
+            .map(|(_, idx, positions)| (idx, positions))
+            .collect();
+    }
+
+    fn render(&self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(6);
+
+        let title = if self.args.title.is_empty() {
+            "Select an item and press Enter=accept  Esc=cancel"
+        } else {
+            &self.args.title
+        };
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(format!("{}\r\n", truncate_right(title, max_width))),
+            Change::Text(format!(
+                "{}\r\n",
+                truncate_right(&format!("> {}", self.filter_term), max_width)
+            )),
+            Change::AllAttributes(CellAttributes::default()),
+        ];
+
+        for (row_num, (entry_idx, (choice_idx, positions))) in self
+            .filtered
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .enumerate()
+        {
+            if row_num >= self.max_items {
+                break;
+            }
+
+            let active = entry_idx == self.active_idx;
+            if active {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            changes.push(Change::Text("  ".to_string()));
+            render_highlighted(
+                &self.args.choices[*choice_idx].label,
+                positions,
+                max_width,
+                &mut changes,
+            );
+            changes.push(Change::Text(" \r\n".to_string()));
+            if active {
+                changes.push(AttributeChange::Reverse(false).into());
+            }
+        }
+
+        // Leave the cursor at the end of the filter term
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(2 + self.filter_term.chars().count()),
+            y: Position::Absolute(1),
+        });
+
+        term.render(&changes)
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.filtered.len().saturating_sub(1));
+        if self.active_idx >= self.top_row + self.max_items {
+            self.top_row = (self.active_idx + 1).saturating_sub(self.max_items);
+        }
+    }
+
+    /// Returns the index of the chosen item, or None if cancelled
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<Option<usize>> {
+        self.render(term)?;
+        while let Some(event) = term.poll_input(None)? {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('G'),
+                    modifiers: Modifiers::CTRL,
+                }) => return Ok(None),
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    if let Some((choice_idx, _)) = self.filtered.get(self.active_idx) {
+                        return Ok(Some(*choice_idx));
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('P'),
+                    modifiers: Modifiers::CTRL,
+                }) => self.move_up(),
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('N'),
+                    modifiers: Modifiers::CTRL,
+                }) => self.move_down(),
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                }) => {
+                    self.filter_term.pop();
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('U'),
+                    modifiers: Modifiers::CTRL,
+                }) => {
+                    self.filter_term.clear();
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    modifiers: Modifiers::NONE | Modifiers::SHIFT,
+                }) => {
+                    self.filter_term.push(c);
+                    self.update_filter();
+                }
+                InputEvent::Mouse(MouseEvent { mouse_buttons, .. })
+                    if mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+                {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.move_up();
+                    } else {
+                        self.move_down();
+                    }
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) => {
+                    let row = (y as usize).checked_sub(ROW_OVERHEAD - 1);
+                    if let Some(row) = row {
+                        let idx = self.top_row + row;
+                        if idx < self.filtered.len() {
+                            self.active_idx = idx;
+                            if mouse_buttons == MouseButtons::LEFT {
+                                return Ok(Some(self.filtered[idx].0));
+                            }
+                        }
+                    }
+                }
+                InputEvent::Resized { rows, .. } => {
+                    self.max_items = rows.saturating_sub(ROW_OVERHEAD);
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+        Ok(None)
+    }
+}
+
+pub fn selector(
+    mut term: TermWizTerminal,
+    args: InputSelector,
+    window: ::window::Window,
+    pane_id: PaneId,
+) -> anyhow::Result<()> {
+    let size = term.get_screen_size()?;
+    let mut state = SelectorState {
+        args,
+        filter_term: String::new(),
+        filtered: vec![],
+        active_idx: 0,
+        top_row: 0,
+        max_items: size.rows.saturating_sub(ROW_OVERHEAD),
+    };
+
+    term.set_raw_mode()?;
+    state.update_filter();
+    let chosen = state.run_loop(&mut term)?;
+
+    let input = chosen.map(|idx| {
+        let choice = &state.args.choices[idx];
+        PromptInput::Choice {
+            id: choice.id.clone(),
+            label: choice.label.clone(),
+        }
+    });
+    complete_prompt(&window, pane_id, *state.args.action, input);
+    Ok(())
+}
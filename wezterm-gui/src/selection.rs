@@ -196,6 +196,14 @@ impl SelectionRange {
         Self { start, end: start }
     }
 
+    /// Computes the selection range that covers the specified zone
+    pub fn from_zone(zone: &SemanticZone) -> Self {
+        Self {
+            start: SelectionCoordinate::x_y(zone.start_x, zone.start_y),
+            end: SelectionCoordinate::x_y(zone.end_x, zone.end_y),
+        }
+    }
+
     pub fn zone_around(start: SelectionCoordinate, pane: &dyn mux::pane::Pane) -> Self {
         let zones = match pane.get_semantic_zones() {
             Ok(z) => z,
@@ -226,11 +234,7 @@ impl SelectionRange {
         }
 
         if let Ok(idx) = zones.binary_search_by(|zone| find_zone(&start, zone)) {
-            let zone = &zones[idx];
-            Self {
-                start: SelectionCoordinate::x_y(zone.start_x, zone.start_y),
-                end: SelectionCoordinate::x_y(zone.end_x, zone.end_y),
-            }
+            Self::from_zone(&zones[idx])
         } else {
             Self { start, end: start }
         }
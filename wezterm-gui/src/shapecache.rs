@@ -1,5 +1,6 @@
 use crate::customglyph::BlockKey;
 use crate::glyphcache::CachedGlyph;
+use ::window::color::SrgbaTuple;
 use config::TextStyle;
 use std::rc::Rc;
 use wezterm_font::shaper::GlyphInfo;
@@ -9,6 +10,8 @@ use wezterm_font::units::*;
 pub struct ShapeCacheKey {
     pub style: TextStyle,
     pub text: String,
+    /// The text color, which is used to paint some color glyphs
+    pub foreground: SrgbaTuple,
 }
 
 #[derive(Debug, PartialEq)]
@@ -62,6 +65,7 @@ impl ShapedInfo {
 pub struct BorrowedShapeCacheKey<'a> {
     pub style: &'a TextStyle,
     pub text: &'a str,
+    pub foreground: SrgbaTuple,
 }
 
 impl<'a> BorrowedShapeCacheKey<'a> {
@@ -69,6 +73,7 @@ impl<'a> BorrowedShapeCacheKey<'a> {
         ShapeCacheKey {
             style: self.style.clone(),
             text: self.text.to_owned(),
+            foreground: self.foreground,
         }
     }
 }
@@ -82,6 +87,7 @@ impl ShapeCacheKeyTrait for ShapeCacheKey {
         BorrowedShapeCacheKey {
             style: &self.style,
             text: &self.text,
+            foreground: self.foreground,
         }
     }
 }
@@ -117,6 +123,7 @@ mod test {
     use crate::glyphcache::GlyphCache;
     use crate::shapecache::{GlyphPosition, ShapedInfo};
     use crate::utilsprites::RenderMetrics;
+    use ::window::color::SrgbaTuple;
     use config::{FontAttributes, TextStyle};
     use std::rc::Rc;
     use termwiz::cell::CellAttributes;
@@ -169,6 +176,7 @@ mod test {
                             font,
                             render_metrics,
                             num_cells,
+                            SrgbaTuple(1., 1., 1., 1.),
                         )
                         .unwrap()
                 })
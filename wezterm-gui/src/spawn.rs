@@ -15,6 +15,7 @@ pub enum SpawnWhere {
     NewWindow,
     NewTab,
     SplitPane(SplitRequest),
+    FloatingPane,
 }
 
 pub fn spawn_command_impl(
@@ -113,6 +114,20 @@ pub async fn spawn_command_internal(
                 bail!("there is no active tab while splitting pane!?");
             }
         }
+        SpawnWhere::FloatingPane => {
+            let src_window_id = match src_window_id {
+                Some(id) => id,
+                None => anyhow::bail!("no src window when spawning a floating pane?"),
+            };
+            let pane_id = current_pane_id
+                .ok_or_else(|| anyhow!("there is no active pane in window {src_window_id}"))?;
+
+            let pane = mux
+                .spawn_floating_pane(pane_id, None, cmd_builder, cwd, spawn.domain)
+                .await
+                .context("spawn_floating_pane")?;
+            pane.set_config(term_config);
+        }
         _ => {
             let (_tab, pane, window_id) = mux
                 .spawn_tab_or_window(
@@ -664,6 +664,7 @@ pub fn parse_status_text(text: &str, default_cell: CellAttributes) -> Line {
             | Action::Esc(_)
             | Action::KittyImage(_)
             | Action::XtGetTcap(_)
+            | Action::Vt52(_)
             | Action::Sixel(_) => {
                 flush_print(&mut print_buffer, &mut cells, &pen);
             }
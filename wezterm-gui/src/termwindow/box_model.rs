@@ -1,7 +1,7 @@
 #![allow(dead_code)]
-use crate::color::LinearRgba;
+use crate::color::{LinearRgba, SrgbaTuple};
 use crate::customglyph::{BlockKey, Poly};
-use crate::glyphcache::CachedGlyph;
+use crate::glyphcache::{default_glyph_foreground, CachedGlyph};
 use crate::quad::{QuadImpl, QuadTrait, TripleLayerQuadAllocator, TripleLayerQuadAllocatorTrait};
 use crate::termwindow::{
     ColorEase, MouseCapture, RenderState, TermWindowNotif, UIItem, UIItemType,
@@ -607,6 +607,7 @@ impl super::TermWindow {
                     None,
                 )?;
                 let mut computed_cells = vec![];
+                let foreground = self.glyph_foreground(&element.colors);
                 let mut glyph_cache = context.gl_state.glyph_cache.borrow_mut();
                 let mut pixel_width = 0.0;
                 let mut x_pos = context.bounds.min_x();
@@ -638,6 +639,7 @@ impl super::TermWindow {
                             &element.font,
                             context.metrics,
                             num_cells as u8,
+                            foreground,
                         )?;
 
                         if let Some(texture) = glyph.texture.as_ref() {
@@ -945,6 +947,19 @@ impl super::TermWindow {
         Ok(())
     }
 
+    /// Returns the color used to paint color glyphs that reference
+    /// the text color.  Inherited colors are only resolved when the
+    /// element is rendered, so the configured foreground is used
+    /// for those.
+    fn glyph_foreground(&self, colors: &ElementColors) -> SrgbaTuple {
+        match &colors.text {
+            InheritableColor::Color(color) | InheritableColor::Animated { color, .. } => {
+                color.to_srgb()
+            }
+            InheritableColor::Inherited => default_glyph_foreground(&self.config),
+        }
+    }
+
     fn resolve_text(
         &self,
         colors: &ElementColors,
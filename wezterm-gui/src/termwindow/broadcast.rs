@@ -0,0 +1,258 @@
+//! Routes keyboard input and pastes from the pane that received them
+//! to the other panes in the broadcast set, in the manner of tmux's
+//! `synchronize-panes` option.
+use crate::TermWindow;
+use config::keyassignment::BroadcastScope;
+use mux::pane::{Pane, PaneId};
+use mux::tab::Tab;
+use mux::Mux;
+use std::collections::HashSet;
+use std::sync::Arc;
+use window::WindowOps;
+
+#[derive(Default)]
+pub struct BroadcastState {
+    scope: Option<BroadcastScope>,
+    /// The panes that were explicitly chosen for
+    /// `BroadcastScope::SelectedPanes`
+    selected: HashSet<PaneId>,
+}
+
+impl BroadcastState {
+    /// Enables broadcasting to the panes in scope, or disables it if
+    /// that scope is already active.  Selecting panes starts out with
+    /// just the active pane.
+    fn toggle_scope(&mut self, scope: BroadcastScope, active_pane: Option<PaneId>) {
+        if self.scope == Some(scope) {
+            self.scope = None;
+        } else {
+            if scope == BroadcastScope::SelectedPanes && self.selected.is_empty() {
+                self.selected.extend(active_pane);
+            }
+            self.scope.replace(scope);
+        }
+    }
+
+    /// Adds the pane to the explicitly selected broadcast set, or
+    /// removes it if it is already a member
+    fn toggle_pane(&mut self, pane_id: PaneId) {
+        if !self.selected.remove(&pane_id) {
+            self.selected.insert(pane_id);
+        }
+        self.scope = if self.selected.is_empty() {
+            None
+        } else {
+            Some(BroadcastScope::SelectedPanes)
+        };
+    }
+
+    /// Forgets a pane that has been removed from the mux
+    pub fn pane_removed(&mut self, pane_id: PaneId) {
+        if self.selected.remove(&pane_id)
+            && self.selected.is_empty()
+            && self.scope == Some(BroadcastScope::SelectedPanes)
+        {
+            self.scope = None;
+        }
+    }
+
+    /// Returns the ids of the panes that make up the broadcast set.
+    /// `panes_in_scope` resolves the tab and workspace scopes to
+    /// their panes.
+    /// The set is empty when broadcasting is not enabled.
+    fn members<F>(&self, panes_in_scope: F) -> HashSet<PaneId>
+    where
+        F: FnOnce(BroadcastScope) -> Vec<PaneId>,
+    {
+        match self.scope {
+            None => HashSet::new(),
+            Some(BroadcastScope::SelectedPanes) => self.selected.clone(),
+            Some(scope) => panes_in_scope(scope).into_iter().collect(),
+        }
+    }
+}
+
+impl TermWindow {
+    /// Enables broadcasting to the panes in scope, or disables it if
+    /// that scope is already active
+    pub fn toggle_broadcast_input(&mut self, scope: BroadcastScope) {
+        let active_pane = self.get_active_pane_no_overlay().map(|pane| pane.pane_id());
+        self.broadcast.toggle_scope(scope, active_pane);
+        self.broadcast_changed();
+    }
+
+    /// Adds the pane to the explicitly selected broadcast set, or
+    /// removes it if it is already a member
+    pub fn toggle_broadcast_pane(&mut self, pane_id: PaneId) {
+        self.broadcast.toggle_pane(pane_id);
+        self.broadcast_changed();
+    }
+
+    fn broadcast_changed(&mut self) {
+        log::debug!(
+            "broadcast input scope is now {:?} {:?}",
+            self.broadcast.scope,
+            self.broadcast.selected
+        );
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Returns the panes in the current tab or workspace of this window
+    fn panes_in_broadcast_scope(&self, scope: BroadcastScope) -> Vec<PaneId> {
+        let mux = Mux::get();
+        let tab_panes = |tab: &Arc<Tab>| {
+            tab.iter_panes_ignoring_zoom()
+                .into_iter()
+                .map(|pos| pos.pane.pane_id())
+                .collect::<Vec<_>>()
+        };
+        match scope {
+            BroadcastScope::CurrentTab => match mux.get_active_tab_for_window(self.mux_window_id) {
+                Some(tab) => tab_panes(&tab),
+                None => vec![],
+            },
+            BroadcastScope::CurrentWorkspace => {
+                let workspace = match mux.get_window(self.mux_window_id) {
+                    Some(window) => window.get_workspace().to_string(),
+                    None => return vec![],
+                };
+                let mut panes = vec![];
+                for window_id in mux.iter_windows_in_workspace(&workspace) {
+                    if let Some(window) = mux.get_window(window_id) {
+                        for tab in window.iter() {
+                            panes.extend(tab_panes(tab));
+                        }
+                    }
+                }
+                panes
+            }
+            BroadcastScope::SelectedPanes => vec![],
+        }
+    }
+
+    /// Returns the ids of the panes that make up the broadcast set.
+    /// This walks the tab or workspace, so callers that check many
+    /// panes, such as the renderer, should compute it once.
+    pub fn broadcast_members(&self) -> HashSet<PaneId> {
+        self.broadcast
+            .members(|scope| self.panes_in_broadcast_scope(scope))
+    }
+
+    /// Applies `func` to each of the other panes in the broadcast set.
+    /// Nothing is sent unless `pane` is itself a member of the set,
+    /// so input to overlays and to panes outside the set stays local.
+    /// Remote panes forward the input to their mux server.
+    pub fn broadcast_input<F>(&self, pane: &Arc<dyn Pane>, func: F)
+    where
+        F: Fn(&Arc<dyn Pane>) -> anyhow::Result<()>,
+    {
+        if self.broadcast.scope.is_none() {
+            return;
+        }
+        let source_id = pane.pane_id();
+        let members = self.broadcast_members();
+        if !members.contains(&source_id) {
+            return;
+        }
+        let mux = Mux::get();
+        for target in members {
+            if target == source_id {
+                continue;
+            }
+            let target = match mux.get_pane(target) {
+                Some(target) => target,
+                None => continue,
+            };
+            if let Err(err) = func(&target) {
+                log::error!(
+                    "failed to broadcast input to pane {}: {:#}",
+                    target.pane_id(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ids(ids: &[PaneId]) -> HashSet<PaneId> {
+        ids.iter().copied().collect()
+    }
+
+    fn tab_and_workspace(scope: BroadcastScope) -> Vec<PaneId> {
+        match scope {
+            BroadcastScope::CurrentTab => vec![1, 2],
+            BroadcastScope::CurrentWorkspace => vec![1, 2, 3, 4],
+            BroadcastScope::SelectedPanes => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn scope_resolution() {
+        let mut state = BroadcastState::default();
+        assert_eq!(state.members(tab_and_workspace), ids(&[]));
+
+        state.toggle_scope(BroadcastScope::CurrentTab, Some(1));
+        assert_eq!(state.members(tab_and_workspace), ids(&[1, 2]));
+
+        state.toggle_scope(BroadcastScope::CurrentWorkspace, Some(1));
+        assert_eq!(state.members(tab_and_workspace), ids(&[1, 2, 3, 4]));
+
+        // Toggling the active scope turns broadcasting off
+        state.toggle_scope(BroadcastScope::CurrentWorkspace, Some(1));
+        assert_eq!(state.members(tab_and_workspace), ids(&[]));
+    }
+
+    #[test]
+    fn selected_panes() {
+        let mut state = BroadcastState::default();
+
+        // Selecting starts with the active pane
+        state.toggle_scope(BroadcastScope::SelectedPanes, Some(3));
+        assert_eq!(state.members(tab_and_workspace), ids(&[3]));
+
+        state.toggle_pane(5);
+        assert_eq!(state.members(tab_and_workspace), ids(&[3, 5]));
+
+        // The selection is remembered while another scope is active
+        state.toggle_scope(BroadcastScope::CurrentTab, Some(3));
+        assert_eq!(state.members(tab_and_workspace), ids(&[1, 2]));
+        state.toggle_scope(BroadcastScope::SelectedPanes, Some(1));
+        assert_eq!(state.members(tab_and_workspace), ids(&[3, 5]));
+
+        // Removing the last selected pane turns broadcasting off
+        state.toggle_pane(3);
+        assert_eq!(state.members(tab_and_workspace), ids(&[5]));
+        state.toggle_pane(5);
+        assert_eq!(state.scope, None);
+
+        // Toggling a pane enables the selected scope
+        state.toggle_pane(7);
+        assert_eq!(state.scope, Some(BroadcastScope::SelectedPanes));
+        assert_eq!(state.members(tab_and_workspace), ids(&[7]));
+    }
+
+    #[test]
+    fn removed_panes_are_forgotten() {
+        let mut state = BroadcastState::default();
+        state.toggle_pane(1);
+        state.toggle_pane(2);
+
+        state.pane_removed(1);
+        assert_eq!(state.members(tab_and_workspace), ids(&[2]));
+
+        state.pane_removed(2);
+        assert_eq!(state.scope, None);
+        assert!(state.selected.is_empty());
+
+        // Removal doesn't affect the other scopes
+        state.toggle_scope(BroadcastScope::CurrentTab, None);
+        state.pane_removed(1);
+        assert_eq!(state.scope, Some(BroadcastScope::CurrentTab));
+    }
+}
@@ -3,9 +3,46 @@ use crate::TermWindow;
 use config::keyassignment::{ClipboardCopyDestination, ClipboardPasteSource};
 use mux::pane::Pane;
 use mux::Mux;
+use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
 use window::{Clipboard, WindowOps};
 
+/// The register that receives the text of every copy,
+/// in addition to its explicit destination
+pub const UNNAMED_REGISTER: char = '"';
+
+lazy_static::lazy_static! {
+    /// Named registers, in the vim sense, that can be yanked
+    /// into from copy mode and pasted via PasteFrom
+    static ref REGISTERS: Mutex<HashMap<char, String>> = Mutex::new(HashMap::new());
+}
+
+/// Returns true if name is a valid register name
+pub fn is_valid_register(name: char) -> bool {
+    name.is_ascii_alphanumeric() || matches!(name, '"' | '+' | '*')
+}
+
+/// Store text into the named register.
+/// As in vim, an uppercase name appends to the lowercase register,
+/// and the unnamed register always holds the most recent text.
+pub fn set_register(name: char, text: String) {
+    let mut registers = REGISTERS.lock();
+    if name.is_ascii_uppercase() {
+        let entry = registers.entry(name.to_ascii_lowercase()).or_default();
+        entry.push_str(&text);
+    } else {
+        registers.insert(name, text.clone());
+    }
+    if name != UNNAMED_REGISTER {
+        registers.insert(UNNAMED_REGISTER, text);
+    }
+}
+
+pub fn get_register(name: char) -> Option<String> {
+    REGISTERS.lock().get(&name.to_ascii_lowercase()).cloned()
+}
+
 impl TermWindow {
     pub fn copy_to_clipboard(&self, clipboard: ClipboardCopyDestination, text: String) {
         let clipboard = match clipboard {
@@ -34,6 +71,20 @@ impl TermWindow {
         let clipboard = match clipboard {
             ClipboardPasteSource::Clipboard => Clipboard::Clipboard,
             ClipboardPasteSource::PrimarySelection => Clipboard::PrimarySelection,
+            ClipboardPasteSource::Register('+') => Clipboard::Clipboard,
+            ClipboardPasteSource::Register('*') => Clipboard::PrimarySelection,
+            ClipboardPasteSource::Register(register) if is_valid_register(register) => {
+                if let Some(text) = get_register(register) {
+                    self.broadcast_input(pane, |target| target.trickle_paste(text.clone()));
+                    pane.trickle_paste(text).ok();
+                    self.maybe_scroll_to_bottom_for_input(&pane);
+                }
+                return;
+            }
+            ClipboardPasteSource::Register(register) => {
+                log::error!("PasteFrom: invalid register name {register:?}");
+                return;
+            }
         };
         let future = window.get_clipboard(clipboard);
         promise::spawn::spawn(async move {
@@ -49,6 +100,7 @@ impl TermWindow {
                             mux.get_pane(pane_id)
                         })
                     {
+                        myself.broadcast_input(&pane, |target| target.trickle_paste(clip.clone()));
                         pane.trickle_paste(clip).ok();
                     }
                 })));
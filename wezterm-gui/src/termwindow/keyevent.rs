@@ -88,6 +88,15 @@ impl KeyTableState {
         }
     }
 
+    /// Returns the names of the active key tables, outermost first,
+    /// along with the time at which the innermost table expires
+    pub fn active_tables(&mut self) -> (Vec<String>, Option<Instant>) {
+        while self.process_expiration() {}
+        let names = self.stack.iter().map(|entry| entry.name.clone()).collect();
+        let expiration = self.stack.last().and_then(|entry| entry.expiration);
+        (names, expiration)
+    }
+
     pub fn current_table(&mut self) -> Option<&str> {
         while self.process_expiration() {}
         self.stack.last().map(|entry| entry.name.as_str())
@@ -129,6 +138,7 @@ impl KeyTableState {
                     result = Some((
                         KeyTableEntry {
                             action: KeyAssignment::Nop,
+                            description: None,
                         },
                         Some(name.to_string()),
                     ));
@@ -273,6 +283,11 @@ impl super::TermWindow {
                 let target = std::time::Instant::now() + duration;
                 self.leader_is_down.replace(target);
                 self.update_title();
+                if self.config.enable_which_key {
+                    // Paint so that the delay before showing the
+                    // available keys starts now
+                    context.invalidate();
+                }
                 // schedule an invalidation so that the cursor or status
                 // area will be repainted at the right time
                 if let Some(window) = self.window.clone() {
@@ -392,6 +407,13 @@ impl super::TermWindow {
                     };
 
                     if res.is_ok() {
+                        self.broadcast_input(&pane, |target| {
+                            if is_down {
+                                target.key_down(term_key, tw_raw_modifiers)
+                            } else {
+                                target.key_up(term_key, tw_raw_modifiers)
+                            }
+                        });
                         if is_down
                             && !keycode.is_modifier()
                             && *keycode != KeyCode::CapsLock
@@ -668,6 +690,18 @@ impl super::TermWindow {
                 };
 
                 if res.is_ok() {
+                    self.broadcast_input(&pane, |target| {
+                        if let Some(encoded) = self.encode_win32_input(target, &window_key) {
+                            target
+                                .writer()
+                                .write_all(encoded.as_bytes())
+                                .context("sending win32-input-mode encoded data")
+                        } else if window_key.key_is_down {
+                            target.key_down(key, modifiers)
+                        } else {
+                            target.key_up(key, modifiers)
+                        }
+                    });
                     if window_key.key_is_down
                         && !key.is_modifier()
                         && self.pane_state(pane.pane_id()).overlay.is_none()
@@ -698,6 +732,10 @@ impl super::TermWindow {
                     log::info!("send to pane string={:?}", s);
                 }
                 pane.writer().write_all(s.as_bytes()).ok();
+                self.broadcast_input(&pane, |target| {
+                    target.writer().write_all(s.as_bytes())?;
+                    Ok(())
+                });
                 self.maybe_scroll_to_bottom_for_input(&pane);
                 context.invalidate();
             }
@@ -5,13 +5,14 @@ use crate::colorease::ColorEase;
 use crate::frontend::{front_end, try_front_end};
 use crate::inputmap::InputMap;
 use crate::overlay::{
-    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program, launcher,
-    start_overlay, start_overlay_pane, CopyModeParams, CopyOverlay, LauncherArgs, LauncherFlags,
+    confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
+    global_search, launcher, selector, show_line_prompt_overlay, start_overlay, start_overlay_pane,
+    CopyModeParams, CopyOverlay, GlobalSearchArgs, LauncherArgs, LauncherFlags, PromptInput,
     QuickSelectOverlay,
 };
 use crate::scripting::guiwin::GuiWin;
 use crate::scrollbar::*;
-use crate::selection::Selection;
+use crate::selection::{Selection, SelectionRange};
 use crate::shapecache::*;
 use crate::tabbar::{TabBarItem, TabBarState};
 use crate::termwindow::background::{
@@ -20,16 +21,17 @@ use crate::termwindow::background::{
 use crate::termwindow::keyevent::{KeyTableArgs, KeyTableState};
 use crate::termwindow::modal::Modal;
 use crate::termwindow::render::{
-    CachedLineState, LineQuadCacheKey, LineQuadCacheValue, LineToEleShapeCacheKey,
-    LineToElementShapeItem,
+    CachedLineState, ContrastCacheKey, LineQuadCacheKey, LineQuadCacheValue,
+    LineToEleShapeCacheKey, LineToElementShapeItem,
 };
 use crate::termwindow::webgpu::WebGpuState;
 use ::wezterm_term::input::{ClickPosition, MouseButton as TMB};
 use ::window::*;
 use anyhow::{anyhow, ensure, Context};
 use config::keyassignment::{
-    KeyAssignment, PaneDirection, Pattern, QuickSelectArguments, RotationDirection, SpawnCommand,
-    SplitSize,
+    ClipboardCopyDestination, GlobalSearchArguments, InputSelector, KeyAssignment, LauncherEntry,
+    PaneDirection, Pattern, PipePaneSpec, PromptInputLine, QuickSelectArguments, RotationDirection,
+    SpawnCommand, SplitSize,
 };
 use config::{
     configuration, AudibleBell, ConfigHandle, Dimension, DimensionContext, FrontEndSelection,
@@ -37,11 +39,13 @@ use config::{
 };
 use lfucache::*;
 use mlua::{FromLua, UserData, UserDataFields};
-use mux::pane::{CloseReason, Pane, PaneId, Pattern as MuxPattern, PerformAssignmentResult};
+use mux::pane::{
+    CloseReason, Pane, PaneId, Pattern as MuxPattern, PerformAssignmentResult, SearchResult,
+};
 use mux::renderable::RenderableDimensions;
 use mux::tab::{
-    PositionedPane, PositionedSplit, SplitDirection, SplitRequest, SplitSize as MuxSplitSize, Tab,
-    TabId,
+    FloatingPaneRect, PositionedPane, PositionedSplit, SplitDirection, SplitRequest,
+    SplitSize as MuxSplitSize, Tab, TabId,
 };
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
@@ -65,11 +69,13 @@ use wezterm_term::{Alert, StableRowIndex, TerminalConfiguration, TerminalSize};
 
 pub mod background;
 pub mod box_model;
+mod broadcast;
 pub mod charselect;
 pub mod clipboard;
 mod keyevent;
 pub mod modal;
 mod mouseevent;
+mod movepane;
 pub mod palette;
 pub mod paneselect;
 mod prevcursor;
@@ -78,6 +84,7 @@ pub mod resize;
 mod selection;
 pub mod spawn;
 pub mod webgpu;
+mod which_key;
 use crate::spawn::SpawnWhere;
 use prevcursor::PrevCursorPos;
 
@@ -150,6 +157,12 @@ pub enum UIItemType {
     ScrollThumb,
     BelowScrollThumb,
     Split(PositionedSplit),
+    /// The frame of a floating pane, which can be dragged to move it.
+    /// The rect is the position of the pane when the drag started.
+    MoveFloatingPane(PaneId, FloatingPaneRect),
+    /// The bottom right corner of a floating pane, which can be
+    /// dragged to resize it
+    ResizeFloatingPane(PaneId, FloatingPaneRect),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -251,6 +264,7 @@ pub struct PaneInformation {
     pub pane_index: usize,
     pub is_active: bool,
     pub is_zoomed: bool,
+    pub is_piped: bool,
     pub has_unseen_output: bool,
     pub left: usize,
     pub top: usize,
@@ -268,6 +282,7 @@ impl UserData for PaneInformation {
         fields.add_field_method_get("pane_index", |_, this| Ok(this.pane_index));
         fields.add_field_method_get("is_active", |_, this| Ok(this.is_active));
         fields.add_field_method_get("is_zoomed", |_, this| Ok(this.is_zoomed));
+        fields.add_field_method_get("is_piped", |_, this| Ok(this.is_piped));
         fields.add_field_method_get("has_unseen_output", |_, this| Ok(this.has_unseen_output));
         fields.add_field_method_get("left", |_, this| Ok(this.left));
         fields.add_field_method_get("top", |_, this| Ok(this.top));
@@ -383,6 +398,8 @@ pub struct TermWindow {
     tab_state: RefCell<HashMap<TabId, TabState>>,
     pane_state: RefCell<HashMap<PaneId, PaneState>>,
     semantic_zones: HashMap<PaneId, SemanticZoneCache>,
+    /// The first row of each failed command, by pane
+    failed_commands: HashMap<PaneId, SemanticZoneCache>,
 
     window_background: Vec<LoadedBackgroundLayer>,
 
@@ -407,6 +424,9 @@ pub struct TermWindow {
 
     line_quad_cache: RefCell<LfuCache<LineQuadCacheKey, LineQuadCacheValue>>,
 
+    /// Maps (fg, bg) to the fg adjusted to satisfy minimum_contrast
+    contrast_cache: RefCell<HashMap<ContrastCacheKey, color::LinearRgba>>,
+
     last_status_call: Instant,
     cursor_blink_state: RefCell<ColorEase>,
     blink_state: RefCell<ColorEase>,
@@ -418,6 +438,8 @@ pub struct TermWindow {
     dragging: Option<(UIItem, MouseEvent)>,
 
     modal: RefCell<Option<Rc<dyn Modal>>>,
+    which_key: which_key::WhichKeyState,
+    broadcast: broadcast::BroadcastState,
 
     event_states: HashMap<String, EventState>,
     pub current_event: Option<Value>,
@@ -720,6 +742,7 @@ impl TermWindow {
                 |config| config.line_to_ele_shape_cache_size,
                 &config,
             )),
+            contrast_cache: RefCell::new(HashMap::new()),
             last_status_call: Instant::now(),
             cursor_blink_state: RefCell::new(ColorEase::new(
                 config.cursor_blink_rate,
@@ -748,12 +771,15 @@ impl TermWindow {
             scheduled_animation: RefCell::new(None),
             allow_images: true,
             semantic_zones: HashMap::new(),
+            failed_commands: HashMap::new(),
             ui_items: vec![],
             dragging: None,
             last_ui_item: None,
             is_click_to_focus_window: false,
             key_table_state: KeyTableState::default(),
             modal: RefCell::new(None),
+            which_key: which_key::WhichKeyState::default(),
+            broadcast: broadcast::BroadcastState::default(),
             opengl_info: None,
         };
 
@@ -860,7 +886,10 @@ impl TermWindow {
     ) -> anyhow::Result<bool> {
         log::debug!("{event:?}");
         match event {
-            WindowEvent::Destroyed => Ok(false),
+            WindowEvent::Destroyed => {
+                front_end().forget_window_screen_rect(window);
+                Ok(false)
+            }
             WindowEvent::CloseRequested => {
                 self.close_requested(window);
                 Ok(true)
@@ -1096,6 +1125,30 @@ impl TermWindow {
                     pane_id,
                 } => {
                     self.emit_user_var_event(pane_id, name, value);
+                    // The badge text may reference user vars
+                    window.invalidate();
+                }
+                MuxNotification::Alert {
+                    alert: Alert::SetProfile(name),
+                    pane_id,
+                } => {
+                    self.emit_set_profile_event(pane_id, name);
+                }
+                MuxNotification::Alert {
+                    alert:
+                        Alert::CommandFinished {
+                            duration,
+                            exit_status,
+                        },
+                    pane_id,
+                } => {
+                    self.command_finished(pane_id, duration, exit_status);
+                }
+                MuxNotification::Alert {
+                    alert: Alert::BadgeFormatChanged(_),
+                    ..
+                } => {
+                    window.invalidate();
                 }
                 MuxNotification::WindowTitleChanged { .. }
                 | MuxNotification::Alert {
@@ -1192,9 +1245,26 @@ impl TermWindow {
                 MuxNotification::TabTitleChanged { .. } => {
                     self.update_title_post_status();
                 }
+                MuxNotification::TriggerEvent {
+                    pane_id,
+                    name,
+                    captures,
+                } => {
+                    self.emit_trigger_event(pane_id, name, captures);
+                }
+                MuxNotification::PanePipeStopped(pane_id) => {
+                    // Refresh the tab bar and the pipe indicator
+                    self.update_title_post_status();
+                    self.mux_pane_output_event(pane_id);
+                }
+                MuxNotification::PaneRemoved(pane_id) => {
+                    self.semantic_zones.remove(&pane_id);
+                    self.failed_commands.remove(&pane_id);
+                    crate::overlay::copy::pane_removed(pane_id);
+                    self.broadcast.pane_removed(pane_id);
+                }
                 MuxNotification::PaneAdded(_)
                 | MuxNotification::WorkspaceRenamed { .. }
-                | MuxNotification::PaneRemoved(_)
                 | MuxNotification::WindowWorkspaceChanged(_)
                 | MuxNotification::ActiveWorkspaceChanged(_)
                 | MuxNotification::Empty
@@ -1337,8 +1407,13 @@ impl TermWindow {
                     | Alert::TabTitleChanged(_)
                     | Alert::IconTitleChanged(_)
                     | Alert::SetUserVar { .. }
+                    | Alert::SetProfile(_)
+                    | Alert::BadgeFormatChanged(_)
+                    | Alert::CommandFinished { .. }
                     | Alert::Bell,
             }
+            | MuxNotification::TriggerEvent { pane_id, .. }
+            | MuxNotification::PanePipeStopped(pane_id)
             | MuxNotification::PaneOutput(pane_id) => {
                 // Ideally we'd check to see if pane_id is part of this window,
                 // but overlays may not be 100% associated with the window
@@ -1639,6 +1714,7 @@ impl TermWindow {
         self.line_to_ele_shape_cache
             .borrow_mut()
             .update_config(&config);
+        self.contrast_cache.borrow_mut().clear();
         self.fancy_tab_bar.take();
         self.invalidate_fancy_tab_bar();
         self.invalidate_modal();
@@ -1787,6 +1863,176 @@ impl TermWindow {
         .detach();
     }
 
+    fn emit_set_profile_event(&mut self, pane_id: PaneId, name: String) {
+        let window = GuiWin::new(self);
+        let pane = match Mux::get().get_pane(pane_id) {
+            Some(pane) => mux_lua::MuxPane(pane.pane_id()),
+            None => return,
+        };
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            name: String,
+            window: GuiWin,
+            pane: MuxPane,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = lua.pack_multi((window, pane, name))?;
+                if let Err(err) =
+                    config::lua::emit_event(&lua, ("set-profile".to_string(), args)).await
+                {
+                    log::error!("while processing set-profile event: {:#}", err);
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, name, window, pane)
+        }))
+        .detach();
+    }
+
+    /// Returns true if pane_id is in one of the tabs of this window
+    fn window_contains_pane(&self, pane_id: PaneId) -> bool {
+        match Mux::get().resolve_pane_id(pane_id) {
+            Some((_domain, window_id, _tab_id)) => window_id == self.mux_window_id,
+            None => false,
+        }
+    }
+
+    /// Called when a trigger with an EmitEvent action matched
+    /// the output of pane_id
+    fn emit_trigger_event(&mut self, pane_id: PaneId, name: String, captures: Vec<Option<String>>) {
+        if !self.window_contains_pane(pane_id) {
+            return;
+        }
+        let window = GuiWin::new(self);
+        let pane = MuxPane(pane_id);
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            name: String,
+            captures: Vec<Option<String>>,
+            window: GuiWin,
+            pane: MuxPane,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = lua.pack_multi((window, pane, captures))?;
+                if let Err(err) = config::lua::emit_event(&lua, (name.clone(), args)).await {
+                    log::error!("while processing {} event: {:#}", name, err);
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, name, captures, window, pane)
+        }))
+        .detach();
+    }
+
+    /// Called when a command delimited by OSC 133 finishes in pane_id.
+    /// If it ran for at least command_finished_notification_threshold_ms
+    /// and the pane isn't the one that the user is looking at, emit the
+    /// command-finished event and, unless a handler returns false, show
+    /// a toast notification that activates the pane when clicked.
+    fn command_finished(&mut self, pane_id: PaneId, duration: Duration, exit_status: Option<i32>) {
+        let threshold = match self.config.command_finished_notification_threshold_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None => return,
+        };
+        if duration < threshold {
+            return;
+        }
+
+        // Every window sees the alert; only the window that
+        // contains the pane should act upon it
+        if !self.window_contains_pane(pane_id) {
+            return;
+        }
+        let pane = match Mux::get().get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+        let is_active_pane = self
+            .get_active_pane_no_overlay()
+            .map(|active| active.pane_id() == pane_id)
+            .unwrap_or(false);
+        if self.focused.is_some() && is_active_pane {
+            return;
+        }
+
+        let title = match exit_status {
+            Some(status) if status != 0 => format!("Command failed with exit status {status}"),
+            _ => "Command finished".to_string(),
+        };
+        let message = format!(
+            "{} completed after {}",
+            pane.get_title(),
+            format_command_duration(duration)
+        );
+        let on_click = {
+            let gui_window = self.window.clone();
+            wezterm_toast_notification::ClickHandler::new(move || {
+                let gui_window = gui_window.clone();
+                promise::spawn::spawn_into_main_thread(async move {
+                    let mux = Mux::get();
+                    if let Err(err) = mux.focus_pane_and_containing_tab(pane_id) {
+                        log::error!("Unable to activate pane {pane_id}: {err:#}");
+                        return;
+                    }
+                    if let Some(window) = gui_window {
+                        window.focus();
+                        window.invalidate();
+                    }
+                })
+                .detach();
+            })
+        };
+        let toast = wezterm_toast_notification::ToastNotification {
+            title,
+            message,
+            url: None,
+            timeout: None,
+            on_click: Some(on_click),
+        };
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            window: GuiWin,
+            pane: MuxPane,
+            duration: Duration,
+            exit_status: Option<i32>,
+            toast: wezterm_toast_notification::ToastNotification,
+        ) -> anyhow::Result<()> {
+            let default_action = match lua {
+                Some(lua) => {
+                    let args =
+                        lua.pack_multi((window, pane, duration.as_secs_f64(), exit_status))?;
+                    config::lua::emit_event(&lua, ("command-finished".to_string(), args))
+                        .await
+                        .map_err(|e| {
+                            log::error!("while processing command-finished event: {:#}", e);
+                            e
+                        })?
+                }
+                None => true,
+            };
+            if default_action {
+                toast.show();
+            }
+            Ok(())
+        }
+
+        let window = GuiWin::new(self);
+        let pane = MuxPane(pane_id);
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, window, pane, duration, exit_status, toast)
+        }))
+        .detach();
+    }
+
     /// Called by window:set_right_status after the status has
     /// been updated; let's update the bar
     pub fn update_title_post_status(&mut self) {
@@ -2129,7 +2375,7 @@ impl TermWindow {
     }
 
     fn show_tab_navigator(&mut self) {
-        self.show_launcher_impl("Tab Navigator", LauncherFlags::TABS);
+        self.show_launcher_impl("Tab Navigator", LauncherFlags::TABS, vec![]);
     }
 
     fn show_launcher(&mut self) {
@@ -2140,10 +2386,231 @@ impl TermWindow {
                 | LauncherFlags::DOMAINS
                 | LauncherFlags::KEY_ASSIGNMENTS
                 | LauncherFlags::COMMANDS,
+            vec![],
         );
     }
 
-    fn show_launcher_impl(&mut self, title: &str, flags: LauncherFlags) {
+    fn show_global_search(&mut self, args: &GlobalSearchArguments, pane: &Arc<dyn Pane>) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        let window = self.window.as_ref().unwrap().clone();
+        let args = GlobalSearchArgs {
+            pattern: self.resolve_search_pattern(args.pattern.clone(), pane),
+            context_lines: args.context_lines,
+        };
+
+        let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
+            global_search(args, term, window)
+        });
+        self.assign_overlay(tab.tab_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
+    fn show_prompt_input_line(&mut self, args: &PromptInputLine, pane: &Arc<dyn Pane>) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        let window = self.window.as_ref().unwrap().clone();
+        let args = args.clone();
+        let pane_id = pane.pane_id();
+
+        let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
+            show_line_prompt_overlay(term, args, window, pane_id)
+        });
+        self.assign_overlay(tab.tab_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
+    fn show_input_selector(&mut self, args: &InputSelector, pane: &Arc<dyn Pane>) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        let window = self.window.as_ref().unwrap().clone();
+        let args = args.clone();
+        let pane_id = pane.pane_id();
+
+        let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
+            selector(term, args, window, pane_id)
+        });
+        self.assign_overlay(tab.tab_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
+    /// Called when a `PromptInputLine` or `InputSelector` overlay
+    /// completes.  An `EmitEvent` action calls the lua handler with
+    /// the input, or nil if the prompt was cancelled; any other action
+    /// has the input filled in and is then performed.
+    pub fn complete_prompt(
+        &mut self,
+        pane_id: PaneId,
+        action: &KeyAssignment,
+        input: Option<PromptInput>,
+    ) {
+        if let KeyAssignment::EmitEvent(name) = action {
+            self.emit_prompt_event(name, pane_id, input);
+            return;
+        }
+
+        let input = match input {
+            Some(input) => input,
+            None => return,
+        };
+        let action = match action.with_prompt_input(input.text()) {
+            Ok(action) => action,
+            Err(err) => {
+                log::error!("Error while completing prompt: {err:#}");
+                return;
+            }
+        };
+        let pane = match Mux::get().get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+        if let Err(err) = self.perform_key_assignment(&pane, &action) {
+            log::error!("Error while performing {action:?}: {err:#}");
+        }
+    }
+
+    fn emit_prompt_event(&mut self, name: &str, pane_id: PaneId, input: Option<PromptInput>) {
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            name: String,
+            window: GuiWin,
+            pane: MuxPane,
+            input: Option<PromptInput>,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = match input {
+                    Some(PromptInput::Line(line)) => lua.pack_multi((window, pane, line))?,
+                    Some(PromptInput::Choice { id, label }) => {
+                        lua.pack_multi((window, pane, id, label))?
+                    }
+                    None => lua.pack_multi((window, pane, mlua::Value::Nil, mlua::Value::Nil))?,
+                };
+                if let Err(err) = config::lua::emit_event(&lua, (name.clone(), args)).await {
+                    log::error!("while processing {} event: {:#}", name, err);
+                }
+            }
+            Ok(())
+        }
+
+        let window = GuiWin::new(self);
+        let pane = MuxPane(pane_id);
+        let name = name.to_string();
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, name, window, pane, input)
+        }))
+        .detach();
+    }
+
+    /// Activate the pane that contains a result from the global search
+    /// overlay, switching workspace and window as needed, and open copy
+    /// mode there with the result selected
+    pub fn activate_search_result(
+        &mut self,
+        pane_id: PaneId,
+        pattern: MuxPattern,
+        result: SearchResult,
+    ) {
+        let mux = Mux::get();
+        let mux_window_id = match mux.resolve_pane_id(pane_id) {
+            Some((_domain, mux_window_id, _tab_id)) => mux_window_id,
+            None => return,
+        };
+
+        if mux_window_id == self.mux_window_id {
+            if let Err(err) = self.open_copy_mode_at_search_result(pane_id, pattern, result) {
+                log::error!("activate_search_result: {err:#}");
+            }
+            return;
+        }
+
+        promise::spawn::spawn(async move {
+            let mux = Mux::get();
+            let fe = front_end();
+            let workspace = mux
+                .get_window(mux_window_id)
+                .map(|window| window.get_workspace().to_string());
+            if let Some(workspace) = workspace {
+                if workspace != mux.active_workspace() {
+                    fe.switch_workspace(&workspace);
+                    let _ = fe.reconcile_workspace().await;
+                }
+            }
+            match fe.gui_window_for_mux_window(mux_window_id) {
+                Some(gui_win) => {
+                    gui_win
+                        .window
+                        .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                            if let Err(err) = term_window
+                                .open_copy_mode_at_search_result(pane_id, pattern, result)
+                            {
+                                log::error!("activate_search_result: {err:#}");
+                            }
+                        })));
+                    gui_win.window.focus();
+                }
+                None => {
+                    log::error!("activate_search_result: no gui window for {mux_window_id}");
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn open_copy_mode_at_search_result(
+        &mut self,
+        pane_id: PaneId,
+        pattern: MuxPattern,
+        result: SearchResult,
+    ) -> anyhow::Result<()> {
+        let mux = Mux::get();
+        mux.focus_pane_and_containing_tab(pane_id)?;
+        let pane = mux
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow::anyhow!("pane {pane_id} not found"))?;
+
+        let copy = CopyOverlay::with_pane(
+            self,
+            &pane,
+            CopyModeParams {
+                pattern,
+                editing_search: false,
+            },
+        )?;
+        if let Some(copy) = copy.downcast_ref::<CopyOverlay>() {
+            copy.activate_search_result(result);
+        }
+        self.assign_overlay_for_pane(pane_id, copy);
+        self.pane_state(pane_id).overlay.as_mut().map(|overlay| {
+            overlay.key_table_state.activate(KeyTableArgs {
+                name: "copy_mode",
+                timeout_milliseconds: None,
+                replace_current: false,
+                one_shot: false,
+                until_unknown: false,
+                prevent_fallback: false,
+            });
+        });
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+        Ok(())
+    }
+
+    fn show_launcher_impl(
+        &mut self,
+        title: &str,
+        flags: LauncherFlags,
+        entries: Vec<LauncherEntry>,
+    ) {
         let mux_window_id = self.mux_window_id;
         let window = self.window.as_ref().unwrap().clone();
 
@@ -2170,6 +2637,7 @@ impl TermWindow {
             let args = LauncherArgs::new(
                 &title,
                 flags,
+                entries,
                 mux_window_id,
                 pane_id,
                 domain_id_of_current_pane,
@@ -2225,7 +2693,13 @@ impl TermWindow {
         &cache.zones
     }
 
-    fn scroll_to_prompt(&mut self, amount: isize) -> anyhow::Result<()> {
+    /// Scrolls the viewport of the active pane to the row that is
+    /// `amount` entries away from the current position in the sorted
+    /// list of rows produced by `get_rows`.
+    fn scroll_to_row<F>(&mut self, amount: isize, get_rows: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut Self, &Arc<dyn Pane>) -> Vec<StableRowIndex>,
+    {
         let pane = match self.get_active_pane_or_overlay() {
             Some(pane) => pane,
             None => return Ok(()),
@@ -2234,16 +2708,16 @@ impl TermWindow {
         let position = self
             .get_viewport(pane.pane_id())
             .unwrap_or(dims.physical_top);
-        let zone = {
-            let zones = self.get_semantic_prompt_zones(&pane);
-            let idx = match zones.binary_search(&position) {
+        let row = {
+            let rows = get_rows(self, &pane);
+            let idx = match rows.binary_search(&position) {
                 Ok(idx) | Err(idx) => idx,
             };
             let idx = ((idx as isize) + amount).max(0) as usize;
-            zones.get(idx).cloned()
+            rows.get(idx).cloned()
         };
-        if let Some(zone) = zone {
-            self.set_viewport(pane.pane_id(), Some(zone), dims);
+        if let Some(row) = row {
+            self.set_viewport(pane.pane_id(), Some(row), dims);
         }
 
         if let Some(win) = self.window.as_ref() {
@@ -2252,6 +2726,80 @@ impl TermWindow {
         Ok(())
     }
 
+    /// Returns the first row of each command that failed
+    fn get_failed_command_rows(&mut self, pane: &Arc<dyn Pane>) -> &[StableRowIndex] {
+        let cache = self
+            .failed_commands
+            .entry(pane.pane_id())
+            .or_insert_with(SemanticZoneCache::default);
+
+        let seqno = pane.get_current_seqno();
+        if cache.seqno != seqno {
+            let mut rows: Vec<StableRowIndex> = pane
+                .get_command_blocks()
+                .into_iter()
+                .filter(|block| block.failed())
+                .filter_map(|block| block.prompt.or(block.input).or(block.output))
+                .map(|zone| zone.start_y)
+                .collect();
+            rows.dedup();
+            cache.zones = rows;
+            cache.seqno = seqno;
+        }
+        &cache.zones
+    }
+
+    fn scroll_to_prompt(&mut self, amount: isize) -> anyhow::Result<()> {
+        self.scroll_to_row(amount, |tw, pane| {
+            tw.get_semantic_prompt_zones(pane).to_vec()
+        })
+    }
+
+    fn scroll_to_mark(&mut self, amount: isize) -> anyhow::Result<()> {
+        self.scroll_to_row(amount, |_, pane| pane.get_marks())
+    }
+
+    fn scroll_to_failed_command(&mut self, amount: isize) -> anyhow::Result<()> {
+        self.scroll_to_row(amount, |tw, pane| tw.get_failed_command_rows(pane).to_vec())
+    }
+
+    /// Copies the output of the most recently completed command
+    fn copy_last_command_output(&mut self, pane: &Arc<dyn Pane>, dest: ClipboardCopyDestination) {
+        let output = pane
+            .get_command_blocks()
+            .into_iter()
+            .rev()
+            // Blocks derived from semantic zones have no timing
+            // information, so we go by the presence of output
+            .find(|block| {
+                block.end_time.is_some() || (block.start_time.is_none() && block.output.is_some())
+            })
+            .and_then(|block| block.output);
+        if let Some(zone) = output {
+            let text = Self::text_for_range(pane, &SelectionRange::from_zone(&zone), false);
+            self.copy_to_clipboard(dest, text);
+        }
+    }
+
+    /// Sends the command line of the most recently started command
+    /// to the pane, followed by a carriage return
+    fn rerun_last_command(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        let input = pane
+            .get_command_blocks()
+            .into_iter()
+            .rev()
+            .find(|block| block.start_time.is_some() || block.output.is_some())
+            .and_then(|block| block.input);
+        if let Some(zone) = input {
+            let text = Self::text_for_range(pane, &SelectionRange::from_zone(&zone), false);
+            let text = text.trim();
+            if !text.is_empty() {
+                pane.writer().write_all(format!("{text}\r").as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
     fn scroll_by_page(&mut self, amount: f64) -> anyhow::Result<()> {
         let pane = match self.get_active_pane_or_overlay() {
             Some(pane) => pane,
@@ -2320,6 +2868,17 @@ impl TermWindow {
         self.move_tab(tab)
     }
 
+    /// Starts or stops copying the output of pane
+    fn pipe_pane_output(&mut self, pane: &Arc<dyn Pane>, spec: Option<PipePaneSpec>) {
+        let pane_id = pane.pane_id();
+        promise::spawn::spawn(async move {
+            if let Err(err) = Mux::get().pipe_pane(pane_id, spec).await {
+                log::error!("Failed to pipe the output of pane {}: {:#}", pane_id, err);
+            }
+        })
+        .detach();
+    }
+
     pub fn perform_key_assignment(
         &mut self,
         pane: &Arc<dyn Pane>,
@@ -2418,7 +2977,25 @@ impl TermWindow {
             }
             CopyTo(dest) => {
                 let text = self.selection_text(pane);
-                self.copy_to_clipboard(*dest, text);
+                let register = pane
+                    .downcast_ref::<CopyOverlay>()
+                    .and_then(|copy| copy.take_register());
+                match register {
+                    Some('+') => self.copy_to_clipboard(ClipboardCopyDestination::Clipboard, text),
+                    Some('*') => {
+                        self.copy_to_clipboard(ClipboardCopyDestination::PrimarySelection, text)
+                    }
+                    Some(register) => clipboard::set_register(register, text),
+                    None => {
+                        clipboard::set_register(clipboard::UNNAMED_REGISTER, text.clone());
+                        self.copy_to_clipboard(*dest, text);
+                    }
+                }
+            }
+            CopyAs(copy) => {
+                let lines = self.selection_lines(pane);
+                let text = mux::export::export_lines(&lines, &pane.palette(), copy.format)?;
+                self.copy_to_clipboard(copy.destination, text);
             }
             PasteFrom(source) => {
                 self.paste_from_clipboard(pane, *source);
@@ -2462,7 +3039,13 @@ impl TermWindow {
             ActivateWindowRelativeNoWrap(n) => {
                 self.activate_window_relative(*n, false)?;
             }
-            SendString(s) => pane.writer().write_all(s.as_bytes())?,
+            SendString(s) => {
+                pane.writer().write_all(s.as_bytes())?;
+                self.broadcast_input(&pane, |target| {
+                    target.writer().write_all(s.as_bytes())?;
+                    Ok(())
+                });
+            }
             SendKey(key) => {
                 use keyevent::{window_mods_to_termwiz_mods, Key};
                 let mods = window_mods_to_termwiz_mods(key.mods);
@@ -2470,6 +3053,7 @@ impl TermWindow {
                     &key.key.resolve(self.config.key_map_preference),
                 ) {
                     pane.key_down(key, mods)?;
+                    self.broadcast_input(&pane, |target| target.key_down(key, mods));
                 }
             }
             Hide => {
@@ -2492,14 +3076,20 @@ impl TermWindow {
             ScrollByLine(n) => self.scroll_by_line(*n)?,
             ScrollByCurrentEventWheelDelta => self.scroll_by_current_event_wheel_delta()?,
             ScrollToPrompt(n) => self.scroll_to_prompt(*n)?,
+            ScrollToMark(n) => self.scroll_to_mark(*n)?,
+            ScrollToFailedCommand(n) => self.scroll_to_failed_command(*n)?,
+            CopyLastCommandOutput(dest) => self.copy_last_command_output(pane, *dest),
+            RerunLastCommand => self.rerun_last_command(pane)?,
             ScrollToTop => self.scroll_to_top(pane),
             ScrollToBottom => self.scroll_to_bottom(pane),
             ShowTabNavigator => self.show_tab_navigator(),
             ShowDebugOverlay => self.show_debug_overlay(),
             ShowLauncher => self.show_launcher(),
-            ShowLauncherArgs(args) => {
-                self.show_launcher_impl(args.title.as_deref().unwrap_or("Launcher"), args.flags)
-            }
+            ShowLauncherArgs(args) => self.show_launcher_impl(
+                args.title.as_deref().unwrap_or("Launcher"),
+                args.flags,
+                args.entries.clone(),
+            ),
             HideApplication => {
                 let con = Connection::get().expect("call on gui thread");
                 con.hide_application();
@@ -2605,6 +3195,7 @@ impl TermWindow {
                         });
                 }
             }
+            GlobalSearch(args) => self.show_global_search(args, pane),
             QuickSelect => {
                 if let Some(pane) = self.get_active_pane_no_overlay() {
                     let qa = QuickSelectOverlay::with_pane(
@@ -2811,6 +3402,18 @@ impl TermWindow {
                     RotationDirection::CounterClockwise => tab.rotate_counter_clockwise(),
                 }
             }
+            SelectLayout(layout) => {
+                let mux = Mux::get();
+                if let Some(tab) = mux.get_active_tab_for_window(self.mux_window_id) {
+                    tab.select_layout(*layout);
+                }
+            }
+            CycleLayouts => {
+                let mux = Mux::get();
+                if let Some(tab) = mux.get_active_tab_for_window(self.mux_window_id) {
+                    tab.cycle_layouts();
+                }
+            }
             SplitPane(split) => {
                 log::trace!("SplitPane {:?}", split);
                 self.spawn_command(
@@ -2862,6 +3465,33 @@ impl TermWindow {
                 let modal = crate::termwindow::palette::CommandPalette::new(self);
                 self.set_modal(Rc::new(modal));
             }
+            ToggleBroadcastInput(scope) => self.toggle_broadcast_input(*scope),
+            SpawnFloatingPane(spawn) => {
+                self.spawn_command(spawn, SpawnWhere::FloatingPane);
+            }
+            ToggleFloatingPanes => {
+                let mux = Mux::get();
+                let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+                    Some(tab) => tab,
+                    None => return Ok(PerformAssignmentResult::Handled),
+                };
+                tab.set_floating_panes_visible(!tab.floating_panes_visible());
+            }
+            PipePane(spec) => self.pipe_pane_output(pane, Some(spec.clone())),
+            StopPipePane => self.pipe_pane_output(pane, None),
+            MovePaneToNewTab => self.move_active_pane_to_new_tab(),
+            MovePaneToTab(args) => self.move_active_pane_to_tab(args),
+            SwapPaneWithTab(n) => self.swap_active_pane_with_tab(*n),
+            MoveTabToNewWindow => self.move_active_tab_to_window(None),
+            MoveTabToWindow(n) => self.move_active_tab_to_window(Some(*n)),
+            PromptInputLine(args) => self.show_prompt_input_line(args, pane),
+            InputSelector(args) => self.show_input_selector(args, pane),
+            SetTabTitle(title) => {
+                let mux = Mux::get();
+                if let Some(tab) = mux.get_active_tab_for_window(self.mux_window_id) {
+                    tab.set_title(title);
+                }
+            }
         };
         Ok(PerformAssignmentResult::Handled)
     }
@@ -3141,6 +3771,7 @@ impl TermWindow {
             pane_index: pos.index,
             is_active: pos.is_active,
             is_zoomed: pos.is_zoomed,
+            is_piped: mux::pipe::is_piped(pos.pane.pane_id()),
             has_unseen_output: pos.pane.has_unseen_output(),
             left: pos.left,
             top: pos.top,
@@ -3203,6 +3834,7 @@ impl TermWindow {
                 index: 0,
                 is_active: true,
                 is_zoomed: false,
+                is_floating: false,
                 left: 0,
                 top: 0,
                 width: size.cols as _,
@@ -3213,6 +3845,11 @@ impl TermWindow {
             }]
         } else {
             let mut panes = tab.iter_panes();
+            if tab.floating_panes_visible() {
+                // Floating panes are ordered from bottom to top,
+                // and are painted after the tiled panes
+                panes.append(&mut tab.iter_floating_panes());
+            }
             for p in &mut panes {
                 if let Some(overlay) = self.pane_state(p.pane.pane_id()).overlay.as_ref() {
                     p.pane = Arc::clone(&overlay.pane);
@@ -3322,3 +3959,17 @@ impl Drop for TermWindow {
         }
     }
 }
+
+/// Format a command duration for display in a notification,
+/// eg: `1h 2m 3s`, `4m 5s` or `6.7s`
+fn format_command_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {mins}m {secs}s")
+    } else if mins > 0 {
+        format!("{mins}m {secs}s")
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
@@ -1,16 +1,17 @@
+use crate::frontend::front_end;
 use crate::tabbar::TabBarItem;
 use crate::termwindow::keyevent::window_mods_to_termwiz_mods;
 use crate::termwindow::{
     GuiWin, MouseCapture, PositionedSplit, ScrollHit, TermWindowNotif, UIItem, UIItemType, TMB,
 };
 use ::window::{
-    MouseButtons as WMB, MouseCursor, MouseEvent, MouseEventKind as WMEK, MousePress, WindowOps,
-    WindowState,
+    MouseButtons as WMB, MouseCursor, MouseEvent, MouseEventKind as WMEK, MousePress, ScreenPoint,
+    ScreenRect, WindowOps, WindowState,
 };
 use config::keyassignment::{KeyAssignment, MouseEventTrigger, SpawnTabDomain};
 use config::MouseEventAltScreen;
-use mux::pane::{Pane, WithPaneLines};
-use mux::tab::SplitDirection;
+use mux::pane::{Pane, PaneId, WithPaneLines};
+use mux::tab::{FloatingPaneRect, SplitDirection};
 use mux::Mux;
 use mux_lua::MuxPane;
 use std::convert::TryInto;
@@ -44,7 +45,9 @@ impl super::TermWindow {
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
             | UIItemType::ScrollThumb
-            | UIItemType::Split(_) => {}
+            | UIItemType::Split(_)
+            | UIItemType::MoveFloatingPane(..)
+            | UIItemType::ResizeFloatingPane(..) => {}
         }
     }
 
@@ -55,7 +58,9 @@ impl super::TermWindow {
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
             | UIItemType::ScrollThumb
-            | UIItemType::Split(_) => {}
+            | UIItemType::Split(_)
+            | UIItemType::MoveFloatingPane(..)
+            | UIItemType::ResizeFloatingPane(..) => {}
         }
     }
 
@@ -68,6 +73,24 @@ impl super::TermWindow {
 
         self.current_mouse_event.replace(event.clone());
 
+        if let Some(window) = self.window.as_ref() {
+            // Remember where we are on screen so that tabs
+            // can be dragged and dropped between windows
+            front_end().record_window_screen_rect(
+                window,
+                ScreenRect::new(
+                    ScreenPoint::new(
+                        event.screen_coords.x - event.coords.x,
+                        event.screen_coords.y - event.coords.y,
+                    ),
+                    euclid::size2(
+                        self.dimensions.pixel_width as isize,
+                        self.dimensions.pixel_height as isize,
+                    ),
+                ),
+            );
+        }
+
         let border = self.get_os_border();
 
         let first_line_offset = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
@@ -130,9 +153,15 @@ impl super::TermWindow {
                     // Completed a window drag
                     return;
                 }
-                if press == &MousePress::Left && self.dragging.take().is_some() {
-                    // Completed a drag
-                    return;
+                if press == &MousePress::Left {
+                    if let Some((item, start_event)) = self.dragging.take() {
+                        // Completed a drag
+                        if let UIItemType::TabBar(TabBarItem::Tab { tab_idx, .. }) = item.item_type
+                        {
+                            self.drop_tab(tab_idx, &start_event, &event);
+                        }
+                        return;
+                    }
                 }
             }
 
@@ -281,6 +310,54 @@ impl super::TermWindow {
         self.dragging.replace((item, start_event));
     }
 
+    fn drag_floating_pane(
+        &mut self,
+        item: UIItem,
+        start_event: MouseEvent,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+
+        // Work in whole cells relative to where the drag started,
+        // so that the pane tracks the mouse without accumulating error
+        let delta_cols =
+            (event.coords.x - start_event.coords.x) / self.render_metrics.cell_size.width.max(1);
+        let delta_rows =
+            (event.coords.y - start_event.coords.y) / self.render_metrics.cell_size.height.max(1);
+        let adjust = |n: usize, delta: isize| (n as isize + delta).max(0) as usize;
+
+        let (pane_id, rect) = match item.item_type {
+            UIItemType::MoveFloatingPane(pane_id, rect) => (
+                pane_id,
+                FloatingPaneRect {
+                    left: adjust(rect.left, delta_cols),
+                    top: adjust(rect.top, delta_rows),
+                    ..rect
+                },
+            ),
+            UIItemType::ResizeFloatingPane(pane_id, rect) => (
+                pane_id,
+                FloatingPaneRect {
+                    cols: adjust(rect.cols, delta_cols).max(1),
+                    rows: adjust(rect.rows, delta_rows).max(1),
+                    ..rect
+                },
+            ),
+            _ => return,
+        };
+
+        if tab.get_floating_pane_rect(pane_id) != Some(rect) {
+            tab.set_floating_pane_rect(pane_id, rect);
+            context.invalidate();
+        }
+        self.dragging.replace((item, start_event));
+    }
+
     fn drag_scroll_thumb(
         &mut self,
         item: UIItem,
@@ -349,6 +426,14 @@ impl super::TermWindow {
             UIItemType::ScrollThumb => {
                 self.drag_scroll_thumb(item, start_event, event, context);
             }
+            UIItemType::MoveFloatingPane(..) | UIItemType::ResizeFloatingPane(..) => {
+                self.drag_floating_pane(item, start_event, event, context);
+            }
+            UIItemType::TabBar(TabBarItem::Tab { .. }) => {
+                // The tab is placed when the button is released
+                context.set_cursor(Some(MouseCursor::Hand));
+                self.dragging.replace((item, start_event));
+            }
             _ => {
                 log::error!("drag not implemented for {:?}", item);
             }
@@ -365,8 +450,14 @@ impl super::TermWindow {
     ) {
         self.last_ui_item.replace(item.clone());
         match item.item_type {
-            UIItemType::TabBar(item) => {
-                self.mouse_event_tab_bar(item, event, context);
+            UIItemType::TabBar(tab_item) => {
+                if let (TabBarItem::Tab { .. }, WMEK::Press(MousePress::Left)) =
+                    (tab_item, &event.kind)
+                {
+                    // Potentially starting to drag the tab
+                    self.dragging.replace((item.clone(), event.clone()));
+                }
+                self.mouse_event_tab_bar(tab_item, event, context);
             }
             UIItemType::AboveScrollThumb => {
                 self.mouse_event_above_scroll_thumb(item, pane, event, context);
@@ -383,6 +474,56 @@ impl super::TermWindow {
             UIItemType::CloseTab(idx) => {
                 self.mouse_event_close_tab(idx, event, context);
             }
+            UIItemType::MoveFloatingPane(pane_id, _)
+            | UIItemType::ResizeFloatingPane(pane_id, _) => {
+                self.mouse_event_floating_pane(item, pane_id, event, context);
+            }
+        }
+    }
+
+    /// Completes a drag of the tab at tab_idx.  Releasing it over the
+    /// tab bar of this window reorders the tabs, releasing it over
+    /// another window moves the tab into that window, and releasing it
+    /// elsewhere tears the tab off into a new window.
+    fn drop_tab(&mut self, tab_idx: usize, start_event: &MouseEvent, event: &MouseEvent) {
+        // Ignore small movements so that clicking on a tab
+        // doesn't accidentally move it
+        let threshold = self.render_metrics.cell_size.width;
+        if (event.coords.x - start_event.coords.x).abs() < threshold
+            && (event.coords.y - start_event.coords.y).abs() < threshold
+        {
+            return;
+        }
+
+        let mux = Mux::get();
+        let (tab_id, num_tabs) = match mux.get_window(self.mux_window_id) {
+            Some(window) => match window.get_by_idx(tab_idx) {
+                Some(tab) => (tab.tab_id(), window.len()),
+                None => return,
+            },
+            None => return,
+        };
+
+        match front_end().gui_window_at_screen_point(event.screen_coords) {
+            Some(win) if win.mux_window_id == self.mux_window_id => {
+                if let Some(UIItem {
+                    item_type:
+                        UIItemType::TabBar(TabBarItem::Tab {
+                            tab_idx: target, ..
+                        }),
+                    ..
+                }) = self.resolve_ui_item(event)
+                {
+                    if target != tab_idx {
+                        self.activate_tab(tab_idx as isize).ok();
+                        self.move_tab(target).ok();
+                    }
+                }
+            }
+            Some(win) => self.move_tab_to_gui_window(tab_id, Some(win)),
+            // Tearing off the only tab would just replace this window
+            None if num_tabs > 1 => self.move_tab_to_gui_window(tab_id, None),
+            None => {}
         }
     }
 
@@ -626,6 +767,32 @@ impl super::TermWindow {
         }
     }
 
+    pub fn mouse_event_floating_pane(
+        &mut self,
+        item: UIItem,
+        pane_id: PaneId,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        context.set_cursor(Some(match &item.item_type {
+            UIItemType::ResizeFloatingPane(..) => MouseCursor::SizeUpDown,
+            _ => MouseCursor::Hand,
+        }));
+
+        if event.kind == WMEK::Press(MousePress::Left) {
+            // Clicking on the frame raises and focuses the pane
+            let mux = Mux::get();
+            if let (Some(tab), Some(pane)) = (
+                mux.get_active_tab_for_window(self.mux_window_id),
+                mux.get_pane(pane_id),
+            ) {
+                tab.set_active_pane(&pane);
+            }
+            self.dragging.replace((item, event));
+            context.invalidate();
+        }
+    }
+
     fn mouse_event_terminal(
         &mut self,
         mut pane: Arc<dyn Pane>,
@@ -648,7 +815,9 @@ impl super::TermWindow {
             Some(MouseCapture::TerminalPane(_))
         );
 
-        for pos in self.get_panes_to_render() {
+        // Floating panes are last in the list and on top of the others,
+        // so they take precedence when hit testing
+        for pos in self.get_panes_to_render().into_iter().rev() {
             if !is_already_captured
                 && row >= pos.top as i64
                 && row <= (pos.top + pos.height) as i64
@@ -0,0 +1,204 @@
+//! Moves panes and tabs between the tabs and windows of the mux,
+//! in the manner of tmux's `break-pane`, `join-pane`, `swap-pane`
+//! and `move-window` commands.
+use crate::frontend::front_end;
+use crate::TermWindow;
+use config::keyassignment::{MovePaneToTab, PaneDirection, SplitSize};
+use mux::tab::{SplitDirection, SplitRequest, SplitSize as MuxSplitSize, Tab, TabId};
+use mux::window::WindowId as MuxWindowId;
+use mux::Mux;
+use std::sync::Arc;
+use window::WindowOps;
+
+/// Makes tab_id the active tab of window_id, and, if specified,
+/// makes pane_id the active pane of that tab
+fn activate_tab_and_pane(
+    window_id: MuxWindowId,
+    tab_id: TabId,
+    pane_id: Option<mux::pane::PaneId>,
+) {
+    let mux = Mux::get();
+    if let Some(pane_id) = pane_id {
+        if let (Some(tab), Some(pane)) = (mux.get_tab(tab_id), mux.get_pane(pane_id)) {
+            tab.set_active_pane(&pane);
+        }
+    }
+    if let Some(mut window) = mux.get_window_mut(window_id) {
+        if let Some(idx) = window.idx_by_id(tab_id) {
+            window.save_and_then_set_active(idx);
+        }
+    }
+}
+
+impl TermWindow {
+    /// Resolves a tab index in this window, where negative values
+    /// count back from the last tab
+    fn tab_by_index(&self, tab_idx: isize) -> Option<Arc<Tab>> {
+        let mux = Mux::get();
+        let window = mux.get_window(self.mux_window_id)?;
+        let max = window.len();
+        let tab_idx = if tab_idx < 0 {
+            max.saturating_sub(tab_idx.unsigned_abs())
+        } else {
+            tab_idx as usize
+        };
+        window.get_by_idx(tab_idx).map(Arc::clone)
+    }
+
+    /// Moves the active pane into a new tab in this window
+    pub fn move_active_pane_to_new_tab(&mut self) {
+        let pane_id = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane.pane_id(),
+            None => return,
+        };
+        let window_id = self.mux_window_id;
+        promise::spawn::spawn(async move {
+            match Mux::get()
+                .move_pane_to_new_tab(pane_id, Some(window_id), None)
+                .await
+            {
+                Ok((tab, window_id)) => activate_tab_and_pane(window_id, tab.tab_id(), None),
+                Err(err) => log::error!("Failed to move pane {} to a new tab: {:#}", pane_id, err),
+            }
+        })
+        .detach();
+    }
+
+    /// Moves the active pane into a split of the active pane
+    /// of another tab in this window
+    pub fn move_active_pane_to_tab(&mut self, args: &MovePaneToTab) {
+        let pane_id = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane.pane_id(),
+            None => return,
+        };
+        let tab = match self.tab_by_index(args.tab) {
+            Some(tab) => tab,
+            None => {
+                log::error!("MovePaneToTab: there is no tab with index {}", args.tab);
+                return;
+            }
+        };
+        let target_pane_id = match tab.get_active_pane() {
+            Some(pane) => pane.pane_id(),
+            None => return,
+        };
+        let request = SplitRequest {
+            direction: match args.direction {
+                PaneDirection::Down | PaneDirection::Up => SplitDirection::Vertical,
+                PaneDirection::Left | PaneDirection::Right => SplitDirection::Horizontal,
+                PaneDirection::Next | PaneDirection::Prev => {
+                    log::error!("Invalid direction {:?} for MovePaneToTab", args.direction);
+                    return;
+                }
+            },
+            target_is_second: match args.direction {
+                PaneDirection::Down | PaneDirection::Right => true,
+                PaneDirection::Up | PaneDirection::Left => false,
+                PaneDirection::Next | PaneDirection::Prev => unreachable!(),
+            },
+            size: match args.size {
+                SplitSize::Percent(n) => MuxSplitSize::Percent(n),
+                SplitSize::Cells(n) => MuxSplitSize::Cells(n),
+            },
+            top_level: args.top_level,
+        };
+        let window_id = self.mux_window_id;
+        let tab_id = tab.tab_id();
+        promise::spawn::spawn(async move {
+            match Mux::get()
+                .move_pane_to_tab(pane_id, target_pane_id, request)
+                .await
+            {
+                Ok(_) => activate_tab_and_pane(window_id, tab_id, Some(pane_id)),
+                Err(err) => log::error!(
+                    "Failed to move pane {} to tab {}: {:#}",
+                    pane_id,
+                    tab_id,
+                    err
+                ),
+            }
+        })
+        .detach();
+    }
+
+    /// Exchanges the active pane with the active pane of
+    /// another tab in this window
+    pub fn swap_active_pane_with_tab(&mut self, tab_idx: isize) {
+        let pane_id = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane.pane_id(),
+            None => return,
+        };
+        let other_pane_id = match self
+            .tab_by_index(tab_idx)
+            .and_then(|tab| tab.get_active_pane())
+        {
+            Some(pane) => pane.pane_id(),
+            None => {
+                log::error!("SwapPaneWithTab: there is no tab with index {}", tab_idx);
+                return;
+            }
+        };
+        promise::spawn::spawn(async move {
+            if let Err(err) = Mux::get().swap_panes(pane_id, other_pane_id).await {
+                log::error!(
+                    "Failed to swap pane {} with pane {}: {:#}",
+                    pane_id,
+                    other_pane_id,
+                    err
+                );
+            }
+        })
+        .detach();
+    }
+
+    /// Moves the active tab to the GUI window at window_idx, using the
+    /// same ordering as `ActivateWindow`, or to a new window when
+    /// window_idx is None
+    pub fn move_active_tab_to_window(&mut self, window_idx: Option<usize>) {
+        let mux = Mux::get();
+        let tab_id = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab.tab_id(),
+            None => return,
+        };
+        let target = match window_idx {
+            Some(idx) => match front_end().gui_windows().into_iter().nth(idx) {
+                Some(win) => Some(win),
+                None => {
+                    log::error!("MoveTabToWindow: there is no window with index {}", idx);
+                    return;
+                }
+            },
+            None => None,
+        };
+        self.move_tab_to_gui_window(tab_id, target);
+    }
+
+    /// Moves tab_id to the target window, or tears it off into
+    /// a new window if target is None
+    pub fn move_tab_to_gui_window(
+        &mut self,
+        tab_id: TabId,
+        target: Option<crate::scripting::guiwin::GuiWin>,
+    ) {
+        let target_window_id = target.as_ref().map(|win| win.mux_window_id);
+        if target_window_id == Some(self.mux_window_id) {
+            return;
+        }
+        promise::spawn::spawn(async move {
+            match Mux::get()
+                .move_tab_to_window(tab_id, target_window_id, None)
+                .await
+            {
+                Ok(_) => {
+                    if let Some(win) = target {
+                        win.window.focus();
+                    }
+                }
+                Err(err) => {
+                    log::error!("Failed to move tab {} to another window: {:#}", tab_id, err)
+                }
+            }
+        })
+        .detach();
+    }
+}
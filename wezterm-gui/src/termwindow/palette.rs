@@ -1,4 +1,6 @@
 use crate::commands::{CommandDef, ExpandedCommand};
+use crate::fuzzy::Recents;
+use crate::scripting::guiwin::GuiWin;
 use crate::termwindow::box_model::*;
 use crate::termwindow::modal::Modal;
 use crate::termwindow::render::corners::{
@@ -9,15 +11,13 @@ use crate::termwindow::{DimensionContext, TermWindow};
 use crate::utilsprites::RenderMetrics;
 use config::keyassignment::KeyAssignment;
 use config::Dimension;
-use frecency::Frecency;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use serde::{Deserialize, Serialize};
+use mux_lua::MuxPane;
 use std::cell::{Ref, RefCell};
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::path::PathBuf;
 use termwiz::nerdfonts::NERD_FONTS;
+use wezterm_dynamic::FromDynamic;
 use wezterm_term::{KeyCode, KeyModifiers, MouseEvent};
 use window::color::LinearRgba;
 use window::Modifiers;
@@ -37,46 +37,86 @@ pub struct CommandPalette {
     commands: Vec<ExpandedCommand>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Recent {
-    brief: String,
-    frecency: Frecency,
-}
+/// The name of the file in which we track how frequently and recently
+/// the commands have been chosen
+const RECENTS_NAME: &str = "commands";
 
-fn recent_file_name() -> PathBuf {
-    config::RUNTIME_DIR.join("recent-commands.json")
+/// An entry returned from the `augment-command-palette` event
+#[derive(Debug, FromDynamic)]
+struct LuaPaletteEntry {
+    brief: String,
+    #[dynamic(default)]
+    doc: String,
+    /// The name of an icon from `wezterm.nerdfonts`
+    #[dynamic(default)]
+    icon: Option<String>,
+    action: KeyAssignment,
 }
 
-fn load_recents() -> anyhow::Result<Vec<Recent>> {
-    let file_name = recent_file_name();
-    let f = std::fs::File::open(&file_name)?;
-    let mut recents: Vec<Recent> = serde_json::from_reader(f)?;
-    recents.sort_by(|a, b| b.frecency.score().partial_cmp(&a.frecency.score()).unwrap());
-    Ok(recents)
+impl LuaPaletteEntry {
+    fn into_command(self) -> ExpandedCommand {
+        // ExpandedCommand wants a static icon name, so we use the
+        // key from the NERD_FONTS map rather than the name we were given
+        let icon = self
+            .icon
+            .as_deref()
+            .and_then(|name| match NERD_FONTS.get_key_value(name) {
+                Some((name, _)) => Some(*name),
+                None => {
+                    log::error!("augment-command-palette: nerdfont {name} not found");
+                    None
+                }
+            });
+        ExpandedCommand {
+            brief: self.brief.into(),
+            doc: self.doc.into(),
+            action: self.action,
+            keys: vec![],
+            menubar: &[],
+            icon,
+        }
+    }
 }
 
-fn save_recent(command: &ExpandedCommand) -> anyhow::Result<()> {
-    let mut recents = load_recents().unwrap_or_else(|_| vec![]);
-    if let Some(recent_idx) = recents.iter().position(|r| r.brief == command.brief) {
-        let recent = recents.get_mut(recent_idx).unwrap();
-        recent.frecency.register_access();
-    } else {
-        let mut frecency = Frecency::new();
-        frecency.register_access();
-        recents.push(Recent {
-            brief: command.brief.to_string(),
-            frecency,
-        });
+/// Calls the `augment-command-palette` event to collect
+/// any additional entries from the configuration
+fn lua_commands(term_window: &mut TermWindow) -> Vec<ExpandedCommand> {
+    let pane = match term_window.get_active_pane_or_overlay() {
+        Some(pane) => MuxPane(pane.pane_id()),
+        None => return vec![],
+    };
+    let window = GuiWin::new(term_window);
+
+    match config::run_immediate_with_lua_config(|lua| {
+        if let Some(lua) = lua {
+            let v = config::lua::emit_sync_callback(
+                &*lua,
+                ("augment-command-palette".to_string(), (window, pane)),
+            )?;
+            match &v {
+                mlua::Value::Nil => Ok(vec![]),
+                _ => Ok(luahelper::from_lua_value_dynamic::<Vec<LuaPaletteEntry>>(
+                    v,
+                )?),
+            }
+        } else {
+            Ok(vec![])
+        }
+    }) {
+        Ok(entries) => entries
+            .into_iter()
+            .map(LuaPaletteEntry::into_command)
+            .collect(),
+        Err(err) => {
+            log::error!("augment-command-palette: {err:#}");
+            vec![]
+        }
     }
-
-    let json = serde_json::to_string(&recents)?;
-    let file_name = recent_file_name();
-    std::fs::write(&file_name, json)?;
-    Ok(())
 }
 
-fn build_commands(filter_copy_mode: bool) -> Vec<ExpandedCommand> {
+fn build_commands(filter_copy_mode: bool, extra: Vec<ExpandedCommand>) -> Vec<ExpandedCommand> {
     let mut commands = CommandDef::actions_for_palette_and_menubar(&config::configuration());
+    commands.extend(extra);
 
     commands.retain(|cmd| {
         if filter_copy_mode {
@@ -86,25 +126,16 @@ fn build_commands(filter_copy_mode: bool) -> Vec<ExpandedCommand> {
         }
     });
 
-    let mut scores: HashMap<&str, f64> = HashMap::new();
-    let recents = load_recents();
-    if let Ok(recents) = &recents {
-        for r in recents {
-            scores.insert(&r.brief, r.frecency.score());
-        }
-    }
-
+    let recents = Recents::load(RECENTS_NAME);
     commands.sort_by(|a, b| {
-        match (scores.get(&*a.brief), scores.get(&*b.brief)) {
-            // Want descending frecency score, so swap a<->b
-            // for the compare here
-            (Some(a), Some(b)) => match b.partial_cmp(&a) {
-                Some(Ordering::Equal) | None => {}
-                Some(ordering) => return ordering,
-            },
-            (Some(_), None) => return Ordering::Less,
-            (None, Some(_)) => return Ordering::Greater,
-            (None, None) => {}
+        // Want descending frecency score, so swap a<->b
+        // for the compare here
+        match recents
+            .score(&b.brief)
+            .partial_cmp(&recents.score(&a.brief))
+        {
+            Some(Ordering::Equal) | None => {}
+            Some(ordering) => return ordering,
         }
 
         match a.menubar.cmp(&b.menubar) {
@@ -175,7 +206,7 @@ impl CommandPalette {
                     .is_none()
             })
             .unwrap_or(true);
-        let commands = build_commands(filter_copy_mode);
+        let commands = build_commands(filter_copy_mode, lua_commands(term_window));
 
         Self {
             element: RefCell::new(None),
@@ -573,7 +604,7 @@ impl Modal for CommandPalette {
                     },
                 };
                 let item = &self.commands[alias_idx];
-                if let Err(err) = save_recent(item) {
+                if let Err(err) = Recents::register_access(RECENTS_NAME, &item.brief) {
                     log::error!("Error while saving recents: {err:#}");
                 }
                 term_window.cancel_modal();
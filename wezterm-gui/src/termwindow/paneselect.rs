@@ -164,6 +164,11 @@ impl PaneSelector {
                 PaneSelectMode::SwapWithActive => {
                     tab.swap_active_with_index(pane_index);
                 }
+                PaneSelectMode::ToggleBroadcast => {
+                    if let Some(pos) = panes.iter().find(|p| p.index == pane_index) {
+                        term_window.toggle_broadcast_pane(pos.pane.pane_id());
+                    }
+                }
             }
         }
 
@@ -0,0 +1,97 @@
+use crate::termwindow::box_model::*;
+use crate::termwindow::DimensionContext;
+use crate::utilsprites::RenderMetrics;
+use config::Dimension;
+
+impl crate::TermWindow {
+    /// Render the iTerm2 style badge text as a watermark in the
+    /// top right corner of each pane that has one
+    pub fn paint_badges(&mut self) -> anyhow::Result<()> {
+        let panes = self.get_panes_to_render();
+        if panes.iter().all(|pos| pos.pane.get_badge().is_none()) {
+            return Ok(());
+        }
+
+        let font = self.fonts.badge_font()?;
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+
+        let top_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        };
+        let (padding_left, padding_top) = self.padding_left_top();
+        let border = self.get_os_border();
+        let top_pixel_y = top_bar_height + padding_top + border.top.get() as f32;
+        let left_pixel_x = padding_left + border.left.get() as f32;
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+
+        for pos in panes {
+            let badge = match pos.pane.get_badge() {
+                Some(badge) if !badge.trim().is_empty() => badge,
+                _ => continue,
+            };
+
+            let lines = badge
+                .lines()
+                .map(|line| {
+                    Element::new(&font, ElementContent::Text(line.to_string()))
+                        .display(DisplayType::Block)
+                })
+                .collect();
+
+            let element = Element::new(&font, ElementContent::Children(lines))
+                .colors(ElementColors {
+                    border: BorderColor::default(),
+                    bg: InheritableColor::Inherited,
+                    text: self.config.badge_fg_color.to_linear().into(),
+                })
+                .padding(BoxDimension {
+                    left: Dimension::Cells(0.5),
+                    right: Dimension::Cells(0.5),
+                    top: Dimension::Cells(0.25),
+                    bottom: Dimension::Cells(0.),
+                });
+
+            let pane_width = pos.width as f32 * cell_width;
+            let pane_height = pos.height as f32 * cell_height;
+            let dimensions = self.dimensions;
+
+            let mut computed = self.compute_element(
+                &LayoutContext {
+                    height: DimensionContext {
+                        dpi: dimensions.dpi as f32,
+                        pixel_max: pane_height,
+                        pixel_cell: metrics.cell_size.height as f32,
+                    },
+                    width: DimensionContext {
+                        dpi: dimensions.dpi as f32,
+                        pixel_max: pane_width,
+                        pixel_cell: metrics.cell_size.width as f32,
+                    },
+                    bounds: euclid::rect(
+                        left_pixel_x + pos.left as f32 * cell_width,
+                        top_pixel_y + pos.top as f32 * cell_height,
+                        pane_width,
+                        pane_height,
+                    ),
+                    metrics: &metrics,
+                    gl_state: self.render_state.as_ref().unwrap(),
+                    zindex: 1,
+                },
+                &element,
+            )?;
+
+            computed.translate(euclid::vec2(
+                (pane_width - computed.bounds.width()).max(0.),
+                0.,
+            ));
+
+            let gl_state = self.render_state.as_ref().unwrap();
+            self.render_element(&computed, gl_state, None)?;
+        }
+
+        Ok(())
+    }
+}
@@ -28,8 +28,9 @@ use wezterm_font::units::{IntPixelLength, PixelLength};
 use wezterm_font::{ClearShapeCache, GlyphInfo, LoadedFont};
 use wezterm_term::color::{ColorAttribute, ColorPalette, RgbColor};
 use wezterm_term::{CellAttributes, Line, StableRowIndex};
-use window::color::LinearRgba;
+use window::color::{LinearRgba, SrgbaTuple};
 
+pub mod badge;
 pub mod borders;
 pub mod corners;
 pub mod draw;
@@ -92,6 +93,13 @@ pub struct LineToElementParams<'a> {
     pub shape_key: &'a Option<LineToEleShapeCacheKey>,
 }
 
+/// The bit patterns of the foreground and background colors
+pub type ContrastCacheKey = [u32; 8];
+
+/// Bounds the size of the contrast cache; it is simply cleared when
+/// it reaches this many entries
+const CONTRAST_CACHE_LIMIT: usize = 4096;
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct LineToEleShapeCacheKey {
     pub shape_hash: [u8; 16],
@@ -220,6 +228,9 @@ pub struct ClusterStyleCache<'a> {
     style: &'a TextStyle,
     underline_tex_rect: TextureRect,
     fg_color: LinearRgba,
+    /// The text color before blinking is applied, used to
+    /// paint color glyphs that reference it
+    glyph_foreground: SrgbaTuple,
     bg_color: LinearRgba,
     underline_color: LinearRgba,
 }
@@ -369,11 +380,13 @@ impl crate::TermWindow {
         font: Option<&Rc<LoadedFont>>,
         gl_state: &RenderState,
         metrics: &RenderMetrics,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<Rc<CachedGlyph>> {
         let fa_lock = "\u{f023}";
         let line = Line::from_text(fa_lock, attrs, 0, None);
         let cluster = line.cluster(None);
-        let shape_info = self.cached_cluster_shape(style, &cluster[0], gl_state, font, metrics)?;
+        let shape_info =
+            self.cached_cluster_shape(style, &cluster[0], gl_state, font, metrics, foreground)?;
         Ok(Rc::clone(&shape_info[0].glyph))
     }
 
@@ -683,6 +696,7 @@ impl crate::TermWindow {
         infos: &[GlyphInfo],
         font: &Rc<LoadedFont>,
         metrics: &RenderMetrics,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<Vec<Rc<CachedGlyph>>> {
         let mut glyphs = Vec::with_capacity(infos.len());
         let mut iter = infos.iter().peekable();
@@ -719,6 +733,7 @@ impl crate::TermWindow {
                 font,
                 metrics,
                 info.num_cells,
+                foreground,
             )?);
         }
         Ok(glyphs)
@@ -732,11 +747,13 @@ impl crate::TermWindow {
         gl_state: &RenderState,
         font: Option<&Rc<LoadedFont>>,
         metrics: &RenderMetrics,
+        foreground: SrgbaTuple,
     ) -> anyhow::Result<Rc<Vec<ShapedInfo>>> {
         let shape_resolve_start = Instant::now();
         let key = BorrowedShapeCacheKey {
             style,
             text: &cluster.text,
+            foreground,
         };
         let glyph_info = match self.lookup_cached_shape(&key) {
             Some(Ok(info)) => info,
@@ -766,6 +783,7 @@ impl crate::TermWindow {
                             &info,
                             &font,
                             metrics,
+                            foreground,
                         )?;
                         let shaped = Rc::new(ShapedInfo::process(&info, &glyphs));
 
@@ -860,6 +878,56 @@ pub fn rgbcolor_alpha_to_window_color(color: RgbColor, alpha: f32) -> LinearRgba
     LinearRgba::with_components(red, green, blue, alpha)
 }
 
+impl crate::TermWindow {
+    /// Adjusts fg so that its contrast ratio against bg satisfies
+    /// the minimum_contrast configuration.
+    pub fn apply_minimum_contrast(
+        &self,
+        fg: LinearRgba,
+        bg: LinearRgba,
+        min_ratio: f32,
+    ) -> LinearRgba {
+        let (r0, g0, b0, a0) = fg.tuple();
+        let (r1, g1, b1, a1) = bg.tuple();
+        let key = [
+            r0.to_bits(),
+            g0.to_bits(),
+            b0.to_bits(),
+            a0.to_bits(),
+            r1.to_bits(),
+            g1.to_bits(),
+            b1.to_bits(),
+            a1.to_bits(),
+        ];
+
+        let mut cache = self.contrast_cache.borrow_mut();
+        if let Some(adjusted) = cache.get(&key) {
+            return *adjusted;
+        }
+        if cache.len() >= CONTRAST_CACHE_LIMIT {
+            cache.clear();
+        }
+
+        let adjusted = minimum_contrast_fg(fg, bg, min_ratio);
+        cache.insert(key, adjusted);
+        adjusted
+    }
+}
+
+/// Returns fg adjusted to have at least min_ratio contrast against bg.
+/// Text that is the same color as its background has deliberately been
+/// hidden, so it is left that way.
+fn minimum_contrast_fg(fg: LinearRgba, bg: LinearRgba, min_ratio: f32) -> LinearRgba {
+    let (r0, g0, b0, _) = fg.tuple();
+    let (r1, g1, b1, _) = bg.tuple();
+    if (r0, g0, b0) == (r1, g1, b1) {
+        return fg;
+    }
+    fg.to_srgb()
+        .ensure_contrast_ratio(&bg.to_srgb(), min_ratio as f64)
+        .to_linear()
+}
+
 fn resolve_fg_color_attr(
     attrs: &CellAttributes,
     fg: ColorAttribute,
@@ -916,3 +984,23 @@ fn same_hyperlink(a: Option<&Arc<Hyperlink>>, b: Option<&Arc<Hyperlink>>) -> boo
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn minimum_contrast() {
+        let bg = LinearRgba::with_components(0.1, 0.1, 0.1, 1.0);
+
+        let fg = LinearRgba::with_components(0.12, 0.12, 0.15, 1.0);
+        let adjusted = minimum_contrast_fg(fg, bg, 4.5);
+        assert_ne!(adjusted, fg);
+        assert!(adjusted.to_srgb().contrast_ratio(&bg.to_srgb()) >= 4.5);
+
+        // Hidden text stays hidden
+        assert_eq!(minimum_contrast_fg(bg, bg, 4.5), bg);
+        let translucent = LinearRgba::with_components(0.1, 0.1, 0.1, 0.5);
+        assert_eq!(minimum_contrast_fg(translucent, bg, 4.5), translucent);
+    }
+}
@@ -216,15 +216,23 @@ impl crate::TermWindow {
             }
         }
 
-        for pos in panes {
+        let (floating_panes, panes): (Vec<_>, Vec<_>) =
+            panes.into_iter().partition(|pos| pos.is_floating);
+
+        for pos in panes.iter().chain(floating_panes.iter()) {
             if pos.is_active {
-                self.update_text_cursor(&pos);
+                self.update_text_cursor(pos);
                 if focused {
                     pos.pane.advise_focus();
                     mux::Mux::get().record_focus_for_current_identity(pos.pane.pane_id());
                 }
             }
-            self.paint_pane(&pos, num_panes, &mut layers)?;
+        }
+
+        // Resolve the broadcast set once, rather than for each pane
+        let broadcast_members = self.broadcast_members();
+        for pos in &panes {
+            self.paint_pane(pos, num_panes, &broadcast_members, &mut layers)?;
         }
 
         if let Some(pane) = self.get_active_pane_or_overlay() {
@@ -240,6 +248,19 @@ impl crate::TermWindow {
 
         self.paint_window_borders(&mut layers)?;
         drop(layers);
+
+        if !floating_panes.is_empty() {
+            // Floating panes are layered above the tiled panes
+            // and the splits between them
+            let gl_state = self.render_state.as_ref().unwrap();
+            let layer = gl_state.layer_for_zindex(1)?;
+            let mut layers = layer.quad_allocator();
+            for pos in &floating_panes {
+                self.paint_pane(pos, num_panes, &broadcast_members, &mut layers)?;
+            }
+        }
+        self.paint_badges()?;
+        self.paint_which_key()?;
         self.paint_modal()?;
 
         Ok(())
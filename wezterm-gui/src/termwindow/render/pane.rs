@@ -7,12 +7,13 @@ use crate::termwindow::render::{
 };
 use crate::termwindow::{ScrollHit, UIItem, UIItemType};
 use ::window::bitmaps::TextureRect;
-use ::window::DeadKeyStatus;
+use ::window::{DeadKeyStatus, RectF};
 use config::VisualBellTarget;
 use mux::pane::{PaneId, WithPaneLines};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::PositionedPane;
+use mux::tab::{FloatingPaneRect, PositionedPane};
 use ordered_float::NotNan;
+use std::collections::HashSet;
 use std::time::Instant;
 use wezterm_dynamic::Value;
 use wezterm_term::color::{ColorAttribute, ColorPalette};
@@ -36,6 +37,7 @@ impl crate::TermWindow {
         &mut self,
         pos: &PositionedPane,
         num_panes: usize,
+        broadcast_members: &HashSet<PaneId>,
         layers: &mut TripleLayerQuadAllocator,
     ) -> anyhow::Result<()> {
         if self.config.use_box_model_render {
@@ -154,7 +156,30 @@ impl crate::TermWindow {
             )
         };
 
-        if num_panes > 1 && self.window_background.is_empty() {
+        let background_rect = if pos.is_floating {
+            // Floating panes have a frame of half a cell around their
+            // content, and cover up whatever is beneath them
+            let rect = euclid::rect(
+                padding_left + border.left.get() as f32 - (cell_width / 2.0)
+                    + (pos.left as f32 * cell_width),
+                top_pixel_y + (pos.top as f32 * cell_height) - (cell_height / 2.0),
+                (pos.width as f32 + 1.) * cell_width,
+                (pos.height as f32 + 1.) * cell_height,
+            );
+            let mut quad =
+                self.filled_rectangle(layers, 0, rect, palette.background.to_linear())?;
+            quad.set_hsv(if pos.is_active {
+                None
+            } else {
+                Some(config.inactive_pane_hsb)
+            });
+            self.paint_floating_pane_frame(layers, pos, rect, &palette)?;
+            rect
+        } else {
+            background_rect
+        };
+
+        if num_panes > 1 && self.window_background.is_empty() && !pos.is_floating {
             // Per-pane, palette-specified background
 
             let mut quad = self.filled_rectangle(
@@ -220,6 +245,61 @@ impl crate::TermWindow {
             }
         }
 
+        if broadcast_members.contains(&pos.pane.pane_id()) {
+            // Outline panes that are receiving broadcast input, so that
+            // it is obvious where typing will end up
+            let color = config
+                .resolved_palette
+                .broadcast_indicator
+                .as_deref()
+                .unwrap_or(&palette.colors.0[1])
+                .to_linear();
+            let thickness = (self.dimensions.dpi as f32 / 96.).max(1.) * 2.;
+            let rect = background_rect;
+            for edge in [
+                euclid::rect(rect.min_x(), rect.min_y(), rect.width(), thickness),
+                euclid::rect(
+                    rect.min_x(),
+                    rect.max_y() - thickness,
+                    rect.width(),
+                    thickness,
+                ),
+                euclid::rect(rect.min_x(), rect.min_y(), thickness, rect.height()),
+                euclid::rect(
+                    rect.max_x() - thickness,
+                    rect.min_y(),
+                    thickness,
+                    rect.height(),
+                ),
+            ] {
+                self.filled_rectangle(layers, 2, edge, color)?;
+            }
+        }
+
+        if mux::pipe::is_piped(pos.pane.pane_id()) {
+            // Mark panes whose output is being copied elsewhere with
+            // a small square in their top right corner
+            let color = config
+                .resolved_palette
+                .pipe_indicator
+                .as_deref()
+                .unwrap_or(&palette.colors.0[1])
+                .to_linear();
+            let size = (cell_height / 2.).max(1.);
+            let rect = background_rect;
+            self.filled_rectangle(
+                layers,
+                2,
+                euclid::rect(
+                    rect.max_x() - size - (cell_width / 4.),
+                    rect.min_y() + (cell_height / 4.),
+                    size,
+                    size,
+                ),
+                color,
+            )?;
+        }
+
         // TODO: we only have a single scrollbar in a single position.
         // We only update it for the active pane, but we should probably
         // do a per-pane scrollbar.  That will require more extensive
@@ -285,6 +365,30 @@ impl crate::TermWindow {
                 ),
                 color,
             )?;
+
+            // Mark the location of any failed commands in the gutter
+            let max_thumb_height =
+                self.dimensions.pixel_height.saturating_sub(
+                    thumb_y_offset + border.bottom.get() + bottom_bar_height as usize,
+                ) as f32;
+            let total_rows = dims.scrollback_rows.max(1) as f32;
+            let marker_height = (max_thumb_height / total_rows).max(2.);
+            let failed_color = palette.colors.0[1].to_linear();
+            let failed_rows = self.get_failed_command_rows(&pos.pane).to_vec();
+            for row in failed_rows {
+                let offset = (row - dims.scrollback_top).max(0) as f32 / total_rows;
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(
+                        thumb_x as f32,
+                        thumb_y_offset as f32 + offset * max_thumb_height,
+                        padding,
+                        marker_height,
+                    ),
+                    failed_color,
+                )?;
+            }
         }
 
         let (selrange, rectangular) = {
@@ -574,6 +678,84 @@ impl crate::TermWindow {
         Ok(())
     }
 
+    /// Draws the outline of a floating pane and registers the ui items
+    /// that allow moving and resizing it with the mouse
+    fn paint_floating_pane_frame(
+        &mut self,
+        layers: &mut TripleLayerQuadAllocator,
+        pos: &PositionedPane,
+        rect: RectF,
+        palette: &ColorPalette,
+    ) -> anyhow::Result<()> {
+        let color = if pos.is_active {
+            palette.cursor_bg
+        } else {
+            palette.split
+        }
+        .to_linear();
+        let thickness = self.render_metrics.underline_height as f32;
+        for edge in [
+            euclid::rect(rect.min_x(), rect.min_y(), rect.width(), thickness),
+            euclid::rect(
+                rect.min_x(),
+                rect.max_y() - thickness,
+                rect.width(),
+                thickness,
+            ),
+            euclid::rect(rect.min_x(), rect.min_y(), thickness, rect.height()),
+            euclid::rect(
+                rect.max_x() - thickness,
+                rect.min_y(),
+                thickness,
+                rect.height(),
+            ),
+        ] {
+            self.filled_rectangle(layers, 2, edge, color)?;
+        }
+
+        let floating_rect = FloatingPaneRect {
+            left: pos.left,
+            top: pos.top,
+            cols: pos.width,
+            rows: pos.height,
+        };
+        let pane_id = pos.pane.pane_id();
+        let cell_width = self.render_metrics.cell_size.width as usize;
+        let cell_height = self.render_metrics.cell_size.height as usize;
+        let x = rect.min_x().max(0.) as usize;
+        let y = rect.min_y().max(0.) as usize;
+        let width = rect.width() as usize;
+        let height = rect.height() as usize;
+        let half_width = cell_width / 2;
+        let half_height = cell_height / 2;
+
+        // The half cell margin on each side of the pane is the drag handle
+        for (x, y, width, height) in [
+            (x, y, width, half_height),
+            (x, y + height - half_height, width, half_height),
+            (x, y, half_width, height),
+            (x + width - half_width, y, half_width, height),
+        ] {
+            self.ui_items.push(UIItem {
+                x,
+                y,
+                width,
+                height,
+                item_type: UIItemType::MoveFloatingPane(pane_id, floating_rect),
+            });
+        }
+        // and the bottom right corner is the resize handle
+        self.ui_items.push(UIItem {
+            x: x + width - cell_width,
+            y: y + height - cell_height,
+            width: cell_width,
+            height: cell_height,
+            item_type: UIItemType::ResizeFloatingPane(pane_id, floating_rect),
+        });
+
+        Ok(())
+    }
+
     pub fn build_pane(
         &mut self,
         pos: &PositionedPane,
@@ -361,6 +361,7 @@ impl crate::TermWindow {
                         params.font.as_ref(),
                         gl_state,
                         &params.render_metrics,
+                        cursor_border_color.to_srgb(),
                     )?;
 
                     if let Some(sprite) = &glyph.texture {
@@ -763,7 +764,7 @@ impl crate::TermWindow {
                     &params.config,
                     style,
                 );
-                let (fg_color, bg_color, bg_is_default) = {
+                let (fg_color, glyph_foreground, bg_color, bg_is_default) = {
                     let mut fg = fg_color;
                     let mut bg = bg_color;
                     let mut bg_default = bg_is_default;
@@ -774,6 +775,15 @@ impl crate::TermWindow {
                         bg_default = false;
                     }
 
+                    // Keep the text legible against its background.
+                    // Images are drawn using their own colors and are
+                    // left alone, as is text that is the same color as
+                    // its background.
+                    if params.config.minimum_contrast > 1.0 && attrs.images().is_none() {
+                        fg = self.apply_minimum_contrast(fg, bg, params.config.minimum_contrast);
+                    }
+                    let glyph_foreground = fg.to_srgb();
+
                     // Check for blink, and if this is the "not-visible"
                     // part of blinking then set fg = bg.  This is a cheap
                     // means of getting it done without impacting other
@@ -806,7 +816,7 @@ impl crate::TermWindow {
                         }
                     }
 
-                    (fg, bg, bg_default)
+                    (fg, glyph_foreground, bg, bg_default)
                 };
 
                 let glyph_color = fg_color;
@@ -833,6 +843,7 @@ impl crate::TermWindow {
                     underline_tex_rect: underline_tex_rect.clone(),
                     bg_color,
                     fg_color: glyph_color,
+                    glyph_foreground,
                     underline_color,
                 });
             }
@@ -845,6 +856,7 @@ impl crate::TermWindow {
                 &gl_state,
                 None,
                 &self.render_metrics,
+                style_params.glyph_foreground,
             )?;
             let pixel_width = glyph_info
                 .iter()
@@ -43,6 +43,12 @@ impl super::TermWindow {
             log::trace!("dimensions didn't change NOP!");
             return;
         }
+        // Keep the rect used when dropping tabs between windows current
+        crate::frontend::front_end().update_window_screen_size(
+            window,
+            dimensions.pixel_width,
+            dimensions.pixel_height,
+        );
         let last_state = self.window_state;
         self.window_state = window_state;
         self.quad_generation += 1;
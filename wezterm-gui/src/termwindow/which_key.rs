@@ -0,0 +1,357 @@
+//! Shows the key bindings that are available while the leader key
+//! or a key table is active, so that they don't need to be memorized.
+use crate::commands::derive_command_from_key_assignment;
+use crate::termwindow::box_model::*;
+use crate::termwindow::render::corners::{
+    BOTTOM_LEFT_ROUNDED_CORNER, BOTTOM_RIGHT_ROUNDED_CORNER, TOP_LEFT_ROUNDED_CORNER,
+    TOP_RIGHT_ROUNDED_CORNER,
+};
+use crate::termwindow::{DimensionContext, TermWindow};
+use crate::utilsprites::RenderMetrics;
+use config::keyassignment::{KeyAssignment, KeyTableEntry};
+use config::Dimension;
+use smol::Timer;
+use std::time::{Duration, Instant};
+use termwiz::cell::unicode_column_width;
+use window::color::LinearRgba;
+use window::{KeyCode, Modifiers, WindowOps};
+
+/// The widest that a column of bindings is allowed to be, in cells
+const MAX_COLUMN_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WhichKeyContext {
+    Leader,
+    /// The names of the active key tables, outermost first
+    Tables(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CacheKey {
+    generation: usize,
+    pixel_width: usize,
+    pixel_height: usize,
+}
+
+#[derive(Default)]
+pub struct WhichKeyState {
+    /// The context and the time at which it became active
+    context: Option<(WhichKeyContext, Instant)>,
+    element: Option<(CacheKey, Vec<ComputedElement>)>,
+}
+
+struct WhichKeyItem {
+    key: String,
+    description: String,
+}
+
+fn describe(entry: &KeyTableEntry) -> String {
+    if let Some(description) = &entry.description {
+        return description.to_string();
+    }
+    match derive_command_from_key_assignment(&entry.action) {
+        Some(cmd) => cmd.brief.to_string(),
+        None => format!("{:?}", entry.action),
+    }
+}
+
+impl TermWindow {
+    fn which_key_context(&mut self) -> Option<WhichKeyContext> {
+        if self.get_modal().is_some() {
+            return None;
+        }
+        if self.leader_is_active() {
+            return Some(WhichKeyContext::Leader);
+        }
+        let (tables, expiration) = self.key_table_state.active_tables();
+        if tables.is_empty() {
+            return None;
+        }
+        if expiration.is_some() {
+            self.update_next_frame_time(expiration);
+        }
+        Some(WhichKeyContext::Tables(tables))
+    }
+
+    fn key_label(&self, mods: Modifiers, keycode: &KeyCode) -> String {
+        let separator =
+            if self.config.ui_key_cap_rendering == ::window::UIKeyCapRendering::AppleSymbols {
+                ""
+            } else {
+                "-"
+            };
+        let mut label = mods.to_string_with_separator(::window::ModifierToStringArgs {
+            separator,
+            want_none: false,
+            ui_key_cap_rendering: Some(self.config.ui_key_cap_rendering),
+        });
+        if !label.is_empty() {
+            label.push_str(separator);
+        }
+        label.push_str(&crate::inputmap::ui_key(
+            keycode,
+            self.config.ui_key_cap_rendering,
+        ));
+        label
+    }
+
+    fn which_key_items(&self, context: &WhichKeyContext) -> Vec<WhichKeyItem> {
+        let mut items: Vec<(Modifiers, &KeyCode, &KeyTableEntry)> = match context {
+            WhichKeyContext::Leader => self
+                .input_map
+                .keys
+                .default
+                .iter()
+                .filter(|((_, mods), _)| mods.contains(Modifiers::LEADER))
+                .map(|((key, mods), entry)| (*mods - Modifiers::LEADER, key, entry))
+                .collect(),
+            WhichKeyContext::Tables(tables) => {
+                let table = tables
+                    .last()
+                    .and_then(|name| self.input_map.keys.by_name.get(name));
+                match table {
+                    Some(table) => table
+                        .iter()
+                        .map(|((key, mods), entry)| (*mods, key, entry))
+                        .collect(),
+                    None => vec![],
+                }
+            }
+        };
+        items.retain(|(_, _, entry)| {
+            !matches!(entry.action, KeyAssignment::DisableDefaultAssignment)
+        });
+
+        let mut items: Vec<WhichKeyItem> = items
+            .into_iter()
+            .map(|(mods, key, entry)| WhichKeyItem {
+                key: self.key_label(mods, key),
+                description: describe(entry),
+            })
+            .collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        items
+    }
+
+    fn compute_which_key(
+        &mut self,
+        context: &WhichKeyContext,
+    ) -> anyhow::Result<Vec<ComputedElement>> {
+        let font = self
+            .fonts
+            .command_palette_font()
+            .expect("to resolve command palette font");
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+
+        let items = self.which_key_items(context);
+
+        let title = match context {
+            WhichKeyContext::Leader => "LEADER".to_string(),
+            WhichKeyContext::Tables(tables) => tables.join(" > "),
+        };
+
+        let fg_color: InheritableColor = self.config.command_palette_fg_color.to_linear().into();
+        let bg_color: InheritableColor = self.config.command_palette_bg_color.to_linear().into();
+
+        let key_width = items
+            .iter()
+            .map(|item| unicode_column_width(&item.key, None))
+            .max()
+            .unwrap_or(0);
+        let column_width = items
+            .iter()
+            .map(|item| key_width + 3 + unicode_column_width(&item.description, None))
+            .max()
+            .unwrap_or(0)
+            .min(MAX_COLUMN_WIDTH)
+            .max(key_width + 4);
+
+        let size = self.terminal_size;
+        let num_columns = (size.cols.saturating_sub(4) / column_width).max(1);
+        let num_rows = (items.len() + num_columns - 1) / num_columns;
+
+        let mut elements = vec![Element::new(&font, ElementContent::Text(title))
+            .colors(ElementColors {
+                border: BorderColor::default(),
+                bg: LinearRgba::TRANSPARENT.into(),
+                text: fg_color.clone(),
+            })
+            .display(DisplayType::Block)];
+
+        // Lay the items out in columns, filling each column in turn
+        // so that they read top to bottom
+        for row in 0..num_rows {
+            let mut cells = vec![];
+            for col in 0..num_columns {
+                let item = match items.get(col * num_rows + row) {
+                    Some(item) => item,
+                    None => break,
+                };
+                let description_width = column_width.saturating_sub(key_width + 3);
+                let description =
+                    termwiz_funcs::truncate_right(&item.description, description_width);
+                cells.push(
+                    Element::new(
+                        &font,
+                        ElementContent::Children(vec![
+                            Element::new(&font, ElementContent::Text(item.key.to_string()))
+                                .colors(ElementColors {
+                                    border: BorderColor::default(),
+                                    bg: fg_color.clone(),
+                                    text: bg_color.clone(),
+                                })
+                                .padding(BoxDimension {
+                                    left: Dimension::Cells(0.25),
+                                    right: Dimension::Cells(0.25),
+                                    top: Dimension::Cells(0.),
+                                    bottom: Dimension::Cells(0.),
+                                })
+                                .min_width(Some(Dimension::Cells(key_width as f32))),
+                            Element::new(&font, ElementContent::Text(format!(" {description}"))),
+                        ]),
+                    )
+                    .min_width(Some(Dimension::Cells(column_width as f32))),
+                );
+            }
+            elements.push(
+                Element::new(&font, ElementContent::Children(cells)).display(DisplayType::Block),
+            );
+        }
+
+        let corner = Dimension::Cells(0.25);
+        let element = Element::new(&font, ElementContent::Children(elements))
+            .colors(ElementColors {
+                border: BorderColor::new(self.config.command_palette_bg_color.to_linear()),
+                bg: bg_color.clone(),
+                text: fg_color.clone(),
+            })
+            .margin(BoxDimension::new(Dimension::Cells(0.25)))
+            .padding(BoxDimension::new(Dimension::Cells(0.25)))
+            .border(BoxDimension::new(Dimension::Pixels(1.)))
+            .border_corners(Some(Corners {
+                top_left: SizedPoly {
+                    width: corner,
+                    height: corner,
+                    poly: TOP_LEFT_ROUNDED_CORNER,
+                },
+                top_right: SizedPoly {
+                    width: corner,
+                    height: corner,
+                    poly: TOP_RIGHT_ROUNDED_CORNER,
+                },
+                bottom_left: SizedPoly {
+                    width: corner,
+                    height: corner,
+                    poly: BOTTOM_LEFT_ROUNDED_CORNER,
+                },
+                bottom_right: SizedPoly {
+                    width: corner,
+                    height: corner,
+                    poly: BOTTOM_RIGHT_ROUNDED_CORNER,
+                },
+            }));
+
+        let dimensions = self.dimensions;
+        let (padding_left, _padding_top) = self.padding_left_top();
+        let border = self.get_os_border();
+        let bottom_bar_height = if self.show_tab_bar && self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height().unwrap_or(0.)
+        } else {
+            0.
+        };
+
+        // Anchor the element to the bottom of the terminal area.
+        // The title, the rows, and an allowance for the margin,
+        // padding and border.
+        let cell_height = metrics.cell_size.height as f32;
+        let height = (num_rows as f32 + 2.) * cell_height;
+        let bottom =
+            dimensions.pixel_height as f32 - bottom_bar_height - border.bottom.get() as f32;
+        let top = (bottom - height).max(0.);
+        let width = dimensions.pixel_width as f32
+            - (padding_left + border.left.get() as f32 + border.right.get() as f32);
+
+        let computed = self.compute_element(
+            &LayoutContext {
+                height: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_height as f32,
+                    pixel_cell: cell_height,
+                },
+                width: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_width as f32,
+                    pixel_cell: metrics.cell_size.width as f32,
+                },
+                bounds: euclid::rect(padding_left + border.left.get() as f32, top, width, height),
+                metrics: &metrics,
+                gl_state: self.render_state.as_ref().unwrap(),
+                zindex: 90,
+            },
+            &element,
+        )?;
+
+        Ok(vec![computed])
+    }
+
+    /// Paints the list of available key bindings, if enabled and the
+    /// leader or a key table has been active for long enough
+    pub fn paint_which_key(&mut self) -> anyhow::Result<()> {
+        if !self.config.enable_which_key {
+            self.which_key = WhichKeyState::default();
+            return Ok(());
+        }
+
+        let context = match self.which_key_context() {
+            Some(context) => context,
+            None => {
+                self.which_key = WhichKeyState::default();
+                return Ok(());
+            }
+        };
+
+        let delay = Duration::from_millis(self.config.which_key_delay_ms);
+        let since = match &self.which_key.context {
+            Some((prior, since)) if *prior == context => *since,
+            _ => {
+                let now = Instant::now();
+                self.which_key = WhichKeyState {
+                    context: Some((context.clone(), now)),
+                    element: None,
+                };
+                // Make sure that we paint again once the delay has passed
+                if let Some(window) = self.window.clone() {
+                    let target = now + delay;
+                    promise::spawn::spawn(async move {
+                        Timer::at(target).await;
+                        window.invalidate();
+                    })
+                    .detach();
+                }
+                now
+            }
+        };
+        if Instant::now() < since + delay {
+            return Ok(());
+        }
+
+        let cache_key = CacheKey {
+            generation: self.config.generation(),
+            pixel_width: self.dimensions.pixel_width,
+            pixel_height: self.dimensions.pixel_height,
+        };
+        let cached = matches!(&self.which_key.element, Some((key, _)) if *key == cache_key);
+        if !cached {
+            let element = self.compute_which_key(&context)?;
+            self.which_key.element.replace((cache_key, element));
+        }
+
+        let (_, computed) = self.which_key.element.take().unwrap();
+        let gl_state = self.render_state.as_ref().unwrap();
+        let result = computed
+            .iter()
+            .try_for_each(|element| self.render_element(element, gl_state, None));
+        self.which_key.element.replace((cache_key, computed));
+        result
+    }
+}
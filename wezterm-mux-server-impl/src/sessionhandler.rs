@@ -15,7 +15,7 @@ use std::time::Instant;
 use termwiz::surface::SequenceNo;
 use url::Url;
 use wezterm_term::terminal::Alert;
-use wezterm_term::StableRowIndex;
+use wezterm_term::{StableRowIndex, TerminalSize};
 
 #[derive(Clone)]
 pub struct PduSender {
@@ -334,12 +334,14 @@ impl SessionHandler {
                             let mut tabs = vec![];
                             let mut tab_titles = vec![];
                             let mut window_titles = HashMap::new();
+                            let mut floating_panes = vec![];
                             for window_id in mux.iter_windows().into_iter() {
                                 let window = mux.get_window(window_id).unwrap();
                                 window_titles.insert(window_id, window.get_title().to_string());
                                 for tab in window.iter() {
                                     tabs.push(tab.codec_pane_tree());
                                     tab_titles.push(tab.get_title());
+                                    floating_panes.push(tab.codec_floating_panes());
                                 }
                             }
                             log::trace!("ListPanes {tabs:#?} {tab_titles:?}");
@@ -347,6 +349,7 @@ impl SessionHandler {
                                 tabs,
                                 tab_titles,
                                 window_titles,
+                                floating_panes,
                             }))
                         },
                         send_response,
@@ -509,6 +512,37 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SetFloatingPaneState(SetFloatingPaneState {
+                containing_tab_id,
+                pane_id,
+                rect,
+                visible,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let tab = mux
+                                .get_tab(containing_tab_id)
+                                .ok_or_else(|| anyhow!("no such tab {}", containing_tab_id))?;
+                            if !tab.set_floating_pane_rect(pane_id, rect) {
+                                anyhow::bail!(
+                                    "pane {} is not floating in tab {}",
+                                    pane_id,
+                                    containing_tab_id
+                                );
+                            }
+                            if tab.floating_panes_visible() != visible {
+                                tab.set_floating_panes_visible(visible);
+                            }
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::ActivatePaneDirection(ActivatePaneDirection { pane_id, direction }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -624,6 +658,14 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SpawnFloatingPane(spawn) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_spawn_floating_pane(spawn, send_response, client_id);
+                })
+                .detach();
+            }
+
             Pdu::MovePaneToNewTab(request) => {
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
@@ -632,6 +674,30 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SwapPanes(request) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_swap_panes(request, send_response, client_id);
+                })
+                .detach();
+            }
+
+            Pdu::PipePane(request) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_pipe_pane(request, send_response, client_id);
+                })
+                .detach();
+            }
+
+            Pdu::MoveTabToWindow(request) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_move_tab(request, send_response, client_id);
+                })
+                .detach();
+            }
+
             Pdu::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -710,6 +776,27 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::ExportLines(ExportLines {
+                pane_id,
+                lines,
+                format,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let text = mux::export::export_pane_lines(&*pane, lines, format)?;
+                            Ok(Pdu::ExportLinesResponse(ExportLinesResponse { text }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::GetImageCell(GetImageCell {
                 pane_id,
                 line_idx,
@@ -870,10 +957,13 @@ impl SessionHandler {
             | Pdu::GetTlsCredsResponse { .. }
             | Pdu::GetClientListResponse { .. }
             | Pdu::PaneRemoved { .. }
+            | Pdu::PanePipeStopped { .. }
             | Pdu::PaneFocused { .. }
             | Pdu::TabResized { .. }
             | Pdu::GetImageCellResponse { .. }
             | Pdu::MovePaneToNewTabResponse { .. }
+            | Pdu::MoveTabToWindowResponse { .. }
+            | Pdu::ExportLinesResponse { .. }
             | Pdu::TabAddedToWindow { .. }
             | Pdu::GetPaneRenderableDimensionsResponse { .. }
             | Pdu::ErrorResponse { .. } => {
@@ -935,6 +1025,55 @@ async fn split_pane(split: SplitPane, client_id: Option<Arc<ClientId>>) -> anyho
     }))
 }
 
+fn schedule_spawn_floating_pane<SND>(
+    spawn: SpawnFloatingPane,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(
+        async move { send_response(spawn_floating_pane(spawn, client_id).await) },
+    )
+    .detach();
+}
+
+async fn spawn_floating_pane(
+    spawn: SpawnFloatingPane,
+    client_id: Option<Arc<ClientId>>,
+) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    let (_pane_domain_id, window_id, tab_id) = mux
+        .resolve_pane_id(spawn.pane_id)
+        .ok_or_else(|| anyhow!("pane_id {} invalid", spawn.pane_id))?;
+
+    let pane = mux
+        .spawn_floating_pane(
+            spawn.pane_id,
+            spawn.rect,
+            spawn.command,
+            spawn.command_dir,
+            spawn.domain,
+        )
+        .await?;
+
+    let dims = pane.get_dimensions();
+    Ok::<Pdu, anyhow::Error>(Pdu::SpawnResponse(SpawnResponse {
+        pane_id: pane.pane_id(),
+        tab_id,
+        window_id,
+        size: TerminalSize {
+            cols: dims.cols,
+            rows: dims.viewport_rows,
+            pixel_width: dims.pixel_width,
+            pixel_height: dims.pixel_height,
+            dpi: dims.dpi,
+        },
+    }))
+}
+
 async fn domain_spawn_v2(spawn: SpawnV2, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
     let mux = Mux::get();
     let _identity = mux.with_identity(client_id);
@@ -991,3 +1130,72 @@ async fn move_pane(
         window_id,
     }))
 }
+
+fn schedule_swap_panes<SND>(
+    request: SwapPanes,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(swap_panes(request, client_id).await) })
+        .detach();
+}
+
+async fn swap_panes(request: SwapPanes, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    mux.swap_panes(request.pane_id, request.other_pane_id)
+        .await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::UnitResponse(UnitResponse {}))
+}
+
+fn schedule_pipe_pane<SND>(request: PipePane, send_response: SND, client_id: Option<Arc<ClientId>>)
+where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(pipe_pane(request, client_id).await) })
+        .detach();
+}
+
+async fn pipe_pane(request: PipePane, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    mux.pipe_pane(request.pane_id, request.spec).await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::UnitResponse(UnitResponse {}))
+}
+
+fn schedule_move_tab<SND>(
+    request: MoveTabToWindow,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(move_tab(request, client_id).await) })
+        .detach();
+}
+
+async fn move_tab(
+    request: MoveTabToWindow,
+    client_id: Option<Arc<ClientId>>,
+) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    let window_id = mux
+        .move_tab_to_window(
+            request.tab_id,
+            request.window_id,
+            request.workspace_for_new_window,
+        )
+        .await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::MoveTabToWindowResponse(MoveTabToWindowResponse {
+        window_id,
+    }))
+}
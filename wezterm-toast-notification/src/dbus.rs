@@ -107,7 +107,7 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
             "org.wezfurlong.wezterm",
             &notif.title,
             &notif.message,
-            if notif.url.is_some() {
+            if notif.is_clickable() {
                 &["show", "Show"]
             } else {
                 &[]
@@ -124,12 +124,10 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
         async {
             while let Some(signal) = invoked_stream.next().await {
                 let args = signal.args()?;
-                if args.nid == notification {
-                    if let Some(url) = notif.url.as_ref() {
-                        wezterm_open_url::open_url(url);
-                        abort_closed.abort();
-                        break;
-                    }
+                if args.nid == notification && notif.is_clickable() {
+                    notif.clicked();
+                    abort_closed.abort();
+                    break;
                 }
             }
             Ok::<(), zbus::Error>(())
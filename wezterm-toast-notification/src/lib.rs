@@ -2,18 +2,58 @@ mod dbus;
 mod macos;
 mod windows;
 
+use std::sync::Arc;
+
+/// A callback that is invoked when the user clicks on a notification
+#[derive(Clone)]
+pub struct ClickHandler(Arc<dyn Fn() + Send + Sync>);
+
+impl ClickHandler {
+    pub fn new<F: Fn() + Send + Sync + 'static>(func: F) -> Self {
+        Self(Arc::new(func))
+    }
+
+    pub fn invoke(&self) {
+        (self.0)()
+    }
+}
+
+impl std::fmt::Debug for ClickHandler {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("ClickHandler").finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ToastNotification {
     pub title: String,
     pub message: String,
     pub url: Option<String>,
     pub timeout: Option<std::time::Duration>,
+    /// Called when the notification is clicked.
+    /// If `url` is also set, the url is opened first.
+    pub on_click: Option<ClickHandler>,
 }
 
 impl ToastNotification {
     pub fn show(self) {
         show(self)
     }
+
+    /// Returns true if clicking the notification does something
+    fn is_clickable(&self) -> bool {
+        self.url.is_some() || self.on_click.is_some()
+    }
+
+    /// Perform the action associated with clicking the notification
+    fn clicked(&self) {
+        if let Some(url) = self.url.as_ref() {
+            wezterm_open_url::open_url(url);
+        }
+        if let Some(on_click) = self.on_click.as_ref() {
+            on_click.invoke();
+        }
+    }
 }
 
 #[cfg(windows)]
@@ -44,6 +84,7 @@ pub fn persistent_toast_notification_with_click_to_open_url(title: &str, message
         message: message.to_string(),
         url: Some(url.to_string()),
         timeout: None,
+        on_click: None,
     });
 }
 
@@ -53,5 +94,6 @@ pub fn persistent_toast_notification(title: &str, message: &str) {
         message: message.to_string(),
         url: None,
         timeout: None,
+        on_click: None,
     });
 }
@@ -1,6 +1,6 @@
 #![cfg(target_os = "macos")]
 
-use crate::ToastNotification;
+use crate::{ClickHandler, ToastNotification};
 use cocoa::base::*;
 use cocoa::foundation::{NSDictionary, NSString};
 use core_foundation::dictionary::CFMutableDictionary;
@@ -9,9 +9,34 @@ use objc::declare::ClassDecl;
 use objc::rc::StrongPtr;
 use objc::runtime::{Class, Object, Protocol, Sel};
 use objc::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 const DELEGATE_CLS_NAME: &str = "WezTermNotifDelegate";
 
+lazy_static::lazy_static! {
+    /// Click handlers can't be stored in the userInfo dictionary,
+    /// so we keep them here, keyed by an id that is stored there instead
+    static ref CLICK_HANDLERS: Mutex<HashMap<usize, ClickHandler>> = Mutex::new(HashMap::new());
+}
+static NEXT_CLICK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Remove the click handler associated with notif, if any
+unsafe fn take_click_handler(notif: id) -> Option<ClickHandler> {
+    let info: *mut Object = msg_send![notif, userInfo];
+    if info.is_null() {
+        return None;
+    }
+    let click_id = info.valueForKey_(*nsstring("click_id"));
+    if click_id.is_null() {
+        return None;
+    }
+    let click_id = std::slice::from_raw_parts(click_id.UTF8String() as *const u8, click_id.len());
+    let click_id: usize = String::from_utf8_lossy(click_id).parse().ok()?;
+    CLICK_HANDLERS.lock().unwrap().remove(&click_id)
+}
+
 struct NotifDelegate {}
 
 impl NotifDelegate {
@@ -50,6 +75,7 @@ impl NotifDelegate {
 
     extern "C" fn did_dismiss_alert(_: &mut Object, _sel: Sel, center: id, notif: id) {
         unsafe {
+            take_click_handler(notif);
             let () = msg_send![center, removeDeliveredNotification: notif];
         }
     }
@@ -67,6 +93,9 @@ impl NotifDelegate {
                 let url = String::from_utf8_lossy(url);
                 wezterm_open_url::open_url(&*url);
             }
+            if let Some(on_click) = take_click_handler(notif) {
+                on_click.invoke();
+            }
             let () = msg_send![center, removeDeliveredNotification: notif];
         }
     }
@@ -115,8 +144,21 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
         let () = msg_send![*notif, setInformativeText: nsstring(&toast.message)];
 
         let mut info = CFMutableDictionary::new();
+        let mut has_info = false;
         if let Some(url) = toast.url {
             info.set(CFString::from_static_string("url"), CFString::new(&url));
+            has_info = true;
+        }
+        if let Some(on_click) = toast.on_click {
+            let click_id = NEXT_CLICK_ID.fetch_add(1, Ordering::Relaxed);
+            CLICK_HANDLERS.lock().unwrap().insert(click_id, on_click);
+            info.set(
+                CFString::from_static_string("click_id"),
+                CFString::new(&click_id.to_string()),
+            );
+            has_info = true;
+        }
+        if has_info {
             let () = msg_send![*notif, setUserInfo: info];
         }
 
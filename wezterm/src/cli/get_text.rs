@@ -1,5 +1,6 @@
 use crate::cli::resolve_pane_id;
 use clap::Parser;
+use config::keyassignment::ExportFormat;
 use mux::pane::PaneId;
 use termwiz_funcs::lines_to_escapes;
 use wezterm_client::client::Client;
@@ -33,6 +34,12 @@ pub struct GetText {
     /// If omitted, unattributed text will be returned.
     #[arg(long)]
     escapes: bool,
+
+    /// The format of the output; one of "text", "ansi", "html" or "svg".
+    /// "ansi" is equivalent to --escapes, while "html" and "svg"
+    /// preserve the colors of the text as they appear in the pane.
+    #[arg(long, conflicts_with = "escapes")]
+    format: Option<ExportFormat>,
 }
 
 impl GetText {
@@ -69,6 +76,20 @@ impl GetText {
             }
         };
 
+        if let Some(format @ (ExportFormat::Html | ExportFormat::Svg)) = self.format {
+            // These formats need the palette of the pane, so they
+            // are rendered by the mux server
+            let export = client
+                .export_lines(codec::ExportLines {
+                    pane_id,
+                    lines: start_line..end_line + 1,
+                    format,
+                })
+                .await?;
+            print!("{}", export.text);
+            return Ok(());
+        }
+
         let lines = client
             .get_lines(codec::GetLines {
                 pane_id: pane_id.into(),
@@ -84,7 +105,7 @@ impl GetText {
             .map(|(_idx, line)| line)
             .collect();
 
-        if self.escapes {
+        if self.escapes || self.format == Some(ExportFormat::Ansi) {
             println!("{}", lines_to_escapes(lines)?);
         } else {
             lines.iter().for_each(|line| println!("{}", line.as_str()));
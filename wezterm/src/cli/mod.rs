@@ -12,6 +12,8 @@ mod kill_pane;
 mod list;
 mod list_clients;
 mod move_pane_to_new_tab;
+mod move_tab_to_window;
+mod pipe_pane;
 mod proxy;
 mod rename_workspace;
 mod send_text;
@@ -19,6 +21,7 @@ mod set_tab_title;
 mod set_window_title;
 mod spawn_command;
 mod split_pane;
+mod swap_pane;
 mod tls_creds;
 
 #[derive(Debug, Parser, Clone, Copy)]
@@ -92,6 +95,23 @@ enum CliSubCommand {
     )]
     MovePaneToNewTab(move_pane_to_new_tab::MovePaneToNewTab),
 
+    /// Exchange the positions of two panes, which may be in
+    /// different tabs or windows
+    #[command(name = "swap-pane", rename_all = "kebab")]
+    SwapPane(swap_pane::SwapPane),
+
+    #[command(
+        name = "move-tab-to-window",
+        rename_all = "kebab",
+        about = "Move a tab into another window, or into a new window.
+Outputs the window-id for the window that now contains the tab"
+    )]
+    MoveTabToWindow(move_tab_to_window::MoveTabToWindow),
+
+    /// Copy the output of a pane to a file or to the input of a command
+    #[command(name = "pipe-pane", rename_all = "kebab")]
+    PipePane(pipe_pane::PipePane),
+
     #[command(
         name = "split-pane",
         rename_all = "kebab",
@@ -166,6 +186,9 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
         CliSubCommand::ListClients(cmd) => cmd.run(client).await,
         CliSubCommand::List(cmd) => cmd.run(client).await,
         CliSubCommand::MovePaneToNewTab(cmd) => cmd.run(client).await,
+        CliSubCommand::SwapPane(cmd) => cmd.run(client).await,
+        CliSubCommand::MoveTabToWindow(cmd) => cmd.run(client).await,
+        CliSubCommand::PipePane(cmd) => cmd.run(client).await,
         CliSubCommand::SplitPane(cmd) => cmd.run(client).await,
         CliSubCommand::SendText(cmd) => cmd.run(client).await,
         CliSubCommand::GetText(cmd) => cmd.run(client).await,
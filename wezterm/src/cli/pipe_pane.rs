@@ -0,0 +1,69 @@
+use crate::cli::resolve_pane_id;
+use clap::{Parser, ValueHint};
+use config::keyassignment::PipePaneSpec;
+use mux::pane::PaneId;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct PipePane {
+    /// Specify the pane whose output should be piped.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Append the output of the pane to the specified file
+    #[arg(long, value_parser, value_hint=ValueHint::FilePath, conflicts_with_all=&["prog", "stop"])]
+    file: Option<PathBuf>,
+
+    /// Remove escape sequences from the output, keeping only the text
+    #[arg(long)]
+    strip_escapes: bool,
+
+    /// Prefix each line of the output with the current time
+    #[arg(long)]
+    timestamps: bool,
+
+    /// Stop piping the output of the pane
+    #[arg(long, conflicts_with_all=&["prog", "strip_escapes", "timestamps"])]
+    stop: bool,
+
+    /// Spawn PROG and write the output of the pane to its stdin.
+    /// For example: `wezterm cli pipe-pane -- grep --line-buffered ERROR`
+    #[arg(value_hint=ValueHint::CommandWithArguments, num_args=1..)]
+    prog: Vec<String>,
+}
+
+impl PipePane {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = resolve_pane_id(&client, self.pane_id).await?;
+
+        let spec = if self.stop {
+            None
+        } else {
+            let file = match self.file {
+                // The file is opened by the mux server, which may have
+                // a different working directory than we do
+                Some(file) => Some(std::env::current_dir()?.join(file)),
+                None => None,
+            };
+            if file.is_none() && self.prog.is_empty() {
+                anyhow::bail!("one of --file, --stop or PROG must be specified");
+            }
+            Some(PipePaneSpec {
+                file: file.map(|f| f.to_string_lossy().to_string()),
+                command: if self.prog.is_empty() {
+                    None
+                } else {
+                    Some(self.prog)
+                },
+                strip_escapes: self.strip_escapes,
+                timestamps: self.timestamps,
+            })
+        };
+
+        client.pipe_pane(codec::PipePane { pane_id, spec }).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use crate::cli::resolve_pane_id;
+use clap::Parser;
+use mux::pane::PaneId;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct SwapPane {
+    /// Specify the pane that should be swapped.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Specify the pane that it should be swapped with.
+    /// The two panes may be in different tabs or windows.
+    #[arg(long)]
+    other_pane_id: PaneId,
+}
+
+impl SwapPane {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let pane_id = resolve_pane_id(&client, self.pane_id).await?;
+        client
+            .swap_panes(codec::SwapPanes {
+                pane_id,
+                other_pane_id: self.other_pane_id,
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::anyhow;
+use clap::{Parser, ValueHint};
+use color_funcs::schemes::SchemeFormat;
+use config::{ColorSchemeFile, ColorSchemeMetaData, ConfigHandle, RgbaColor, SrgbaTuple};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use termwiz::color::ColorSpec;
+use termwiz::escape::csi::{Sgr, CSI};
+
+#[derive(Debug, Parser, Clone)]
+pub struct ColorSchemeCommand {
+    #[command(subcommand)]
+    sub: ColorSchemeSubCommand,
+}
+
+#[derive(Debug, Parser, Clone)]
+enum ColorSchemeSubCommand {
+    #[command(name = "convert", about = "Convert a color scheme to another format")]
+    Convert(ConvertCommand),
+
+    #[command(name = "list", about = "List the names of the available color schemes")]
+    List(ListCommand),
+
+    #[command(name = "show", about = "Preview a color scheme in the terminal")]
+    Show(ShowCommand),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ConvertCommand {
+    /// The format of the input. If omitted, it is detected from
+    /// the name and content of the input file.
+    #[arg(long)]
+    from: Option<SchemeFormat>,
+
+    /// The format to produce. If omitted, it is detected from the
+    /// name of the output file, or wezterm's own format is used
+    /// when writing to stdout.
+    #[arg(long)]
+    to: Option<SchemeFormat>,
+
+    /// The color scheme file to convert, or the name of one of
+    /// the color schemes known to wezterm
+    input: String,
+
+    /// Where to write the converted scheme.
+    /// If omitted, the scheme is written to stdout.
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ListCommand {
+    /// Only list the schemes whose names contain this text.
+    /// The comparison is case insensitive.
+    filter: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ShowCommand {
+    /// The format of the input. If omitted, it is detected from
+    /// the name and content of the input file.
+    #[arg(long)]
+    from: Option<SchemeFormat>,
+
+    /// The color scheme file to show, or the name of one of
+    /// the color schemes known to wezterm
+    scheme: String,
+}
+
+impl ColorSchemeCommand {
+    pub fn run(&self, config: ConfigHandle) -> anyhow::Result<()> {
+        match &self.sub {
+            ColorSchemeSubCommand::Convert(cmd) => cmd.run(&config),
+            ColorSchemeSubCommand::List(cmd) => cmd.run(&config),
+            ColorSchemeSubCommand::Show(cmd) => cmd.run(&config),
+        }
+    }
+}
+
+/// Load a scheme from a file, or resolve it by name from the
+/// schemes defined in the config and those built in to wezterm
+fn load_scheme(
+    config: &ConfigHandle,
+    spec: &str,
+    format: Option<SchemeFormat>,
+) -> anyhow::Result<ColorSchemeFile> {
+    let path = Path::new(spec);
+    if path.exists() {
+        return SchemeFormat::load_file(path, format);
+    }
+
+    let colors = config
+        .color_schemes
+        .get(spec)
+        .or_else(|| config::COLOR_SCHEMES.get(spec))
+        .ok_or_else(|| anyhow!("{spec} is neither a file nor the name of a known color scheme"))?;
+
+    Ok(ColorSchemeFile {
+        colors: colors.clone(),
+        metadata: ColorSchemeMetaData {
+            name: Some(spec.to_string()),
+            ..Default::default()
+        },
+    })
+}
+
+impl ConvertCommand {
+    fn run(&self, config: &ConfigHandle) -> anyhow::Result<()> {
+        let scheme = load_scheme(config, &self.input, self.from)?;
+
+        let format = match (self.to, &self.output) {
+            (Some(format), _) => format,
+            (None, Some(output)) => SchemeFormat::detect(output, None).ok_or_else(|| {
+                anyhow!(
+                    "unable to determine the color scheme format from {}; use --to",
+                    output.display()
+                )
+            })?,
+            (None, None) => SchemeFormat::Wezterm,
+        };
+
+        match &self.output {
+            Some(output) => format.save_file(&scheme, output),
+            None => {
+                let data = format.export_str(&scheme)?;
+                std::io::stdout().write_all(data.as_bytes())?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ListCommand {
+    fn run(&self, config: &ConfigHandle) -> anyhow::Result<()> {
+        let filter = self.filter.as_ref().map(|f| f.to_lowercase());
+
+        let names: BTreeSet<&String> = config
+            .color_schemes
+            .keys()
+            .chain(config::COLOR_SCHEMES.keys())
+            .filter(|name| match &filter {
+                Some(filter) => name.to_lowercase().contains(filter),
+                None => true,
+            })
+            .collect();
+
+        let mut stdout = std::io::stdout().lock();
+        for name in names {
+            writeln!(stdout, "{name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the escape sequences to show text in the specified
+/// colors, followed by a reset
+fn colored(text: &str, fg: SrgbaTuple, bg: SrgbaTuple) -> String {
+    format!(
+        "{}{}{text}{}",
+        CSI::Sgr(Sgr::Foreground(ColorSpec::TrueColor(fg))),
+        CSI::Sgr(Sgr::Background(ColorSpec::TrueColor(bg))),
+        CSI::Sgr(Sgr::Reset)
+    )
+}
+
+/// Returns whichever of black or white is more legible on bg
+fn label_color(bg: SrgbaTuple) -> SrgbaTuple {
+    let white = SrgbaTuple(1., 1., 1., 1.);
+    let black = SrgbaTuple(0., 0., 0., 1.);
+    if white.contrast_ratio(&bg) >= black.contrast_ratio(&bg) {
+        white
+    } else {
+        black
+    }
+}
+
+/// A block of color labelled with its name
+fn swatch(label: &str, color: &RgbaColor) -> String {
+    let color: SrgbaTuple = **color;
+    colored(&format!(" {label:^5} "), label_color(color), color)
+}
+
+impl ShowCommand {
+    fn run(&self, config: &ConfigHandle) -> anyhow::Result<()> {
+        let scheme = load_scheme(config, &self.scheme, self.from)?;
+        let defaults: config::Palette = wezterm_term::color::ColorPalette::default().into();
+        let palette = defaults.overlay_with(&scheme.colors);
+
+        let fg: SrgbaTuple = *palette.foreground.unwrap_or_default();
+        let bg: SrgbaTuple = *palette.background.unwrap_or_default();
+        let ansi = palette.ansi.unwrap_or_default();
+        let brights = palette.brights.unwrap_or_default();
+
+        let mut out = String::new();
+
+        if let Some(name) = &scheme.metadata.name {
+            out.push_str(name);
+            if let Some(author) = &scheme.metadata.author {
+                out.push_str(&format!(" by {author}"));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+
+        out.push_str(&colored(
+            "  The quick brown fox jumps over the lazy dog  ",
+            fg,
+            bg,
+        ));
+        out.push('\n');
+        out.push('\n');
+
+        for (title, colors, base) in [("normal", &ansi, 0), ("bright", &brights, 8)] {
+            out.push_str(&format!("{title:<8}"));
+            for (idx, color) in colors.iter().enumerate() {
+                out.push_str(&swatch(&(base + idx).to_string(), color));
+            }
+            out.push('\n');
+
+            // Show each color as text against the background, which
+            // is how they are most often seen
+            out.push_str(&" ".repeat(8));
+            for color in colors.iter() {
+                out.push_str(&colored("  Aa   ", **color, bg));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+
+        let cursor_bg = palette.cursor_bg.unwrap_or_default();
+        let cursor_fg = palette.cursor_fg.unwrap_or_default();
+        out.push_str(&format!("{:<8}", "cursor"));
+        out.push_str(&colored(" text ", *cursor_fg, *cursor_bg));
+        out.push('\n');
+
+        let selection_bg = palette.selection_bg.unwrap_or_default();
+        let selection_fg = palette.selection_fg.unwrap_or_default();
+        out.push_str(&format!("{:<8}", "select"));
+        out.push_str(&colored(" selected text ", *selection_fg, *selection_bg));
+        out.push('\n');
+
+        if !scheme.colors.indexed.is_empty() {
+            let mut indexed: Vec<_> = scheme.colors.indexed.iter().collect();
+            indexed.sort_by_key(|(idx, _)| **idx);
+
+            out.push('\n');
+            out.push_str(&format!("{:<8}", "indexed"));
+            for (idx, color) in indexed {
+                out.push_str(&swatch(&idx.to_string(), color));
+            }
+            out.push('\n');
+        }
+
+        std::io::stdout().write_all(out.as_bytes())?;
+        Ok(())
+    }
+}
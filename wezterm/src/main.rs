@@ -11,6 +11,7 @@ use wezterm_gui_subcommands::*;
 
 mod asciicast;
 mod cli;
+mod color_scheme;
 
 //    let message = "; ❤ 😍🤢\n\x1b[91;mw00t\n\x1b[37;104;m bleet\x1b[0;m.";
 
@@ -122,6 +123,12 @@ enum SubCommand {
     #[command(name = "replay", about = "Replay an asciicast terminal session")]
     Replay(asciicast::PlayCommand),
 
+    #[command(
+        name = "color-scheme",
+        about = "Convert, list and preview color schemes"
+    )]
+    ColorScheme(color_scheme::ColorSchemeCommand),
+
     /// Generate shell completion information
     #[command(name = "shell-completion")]
     ShellCompletion {
@@ -278,6 +285,7 @@ fn run() -> anyhow::Result<()> {
         SubCommand::Cli(cli) => cli::run_cli(init_config(&opts)?, cli),
         SubCommand::Record(cmd) => cmd.run(init_config(&opts)?),
         SubCommand::Replay(cmd) => cmd.run(),
+        SubCommand::ColorScheme(cmd) => cmd.run(init_config(&opts)?),
         SubCommand::ShellCompletion { shell } => {
             use clap::CommandFactory;
             let mut cmd = Opt::command();